@@ -30,6 +30,8 @@ mod encode;
 pub mod matching;
 pub mod model;
 pub mod tasks;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod util;
 
 mod var;
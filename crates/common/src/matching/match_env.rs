@@ -31,6 +31,11 @@ pub trait MatchEnvironment {
 
     // Return the location of a given object.
     fn location_of(&self, player: &Obj) -> Result<Obj, WorldStateError>;
+
+    // Fold the case of a name (or the name being matched), for case-insensitive comparison.
+    fn fold_case(&self, name: &str) -> String {
+        name.to_lowercase()
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -91,8 +96,17 @@ pub fn match_contents<M: MatchEnvironment>(
             continue;
         }
 
-        let object_names = env.get_names(&oid)?;
-        let result = do_match_object_names(oid, &mut match_data, object_names, object_name)?;
+        let object_names = env
+            .get_names(&oid)?
+            .iter()
+            .map(|name| env.fold_case(name))
+            .collect();
+        let result = do_match_object_names(
+            oid,
+            &mut match_data,
+            object_names,
+            &env.fold_case(object_name),
+        )?;
         if result == AMBIGUOUS {
             return Ok(Some(AMBIGUOUS));
         }
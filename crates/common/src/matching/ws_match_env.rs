@@ -14,6 +14,7 @@
 use crate::model::ObjSet;
 use crate::model::WorldState;
 use crate::model::WorldStateError;
+use crate::util::casefold;
 use crate::Obj;
 
 use crate::matching::match_env::MatchEnvironment;
@@ -22,11 +23,22 @@ use crate::matching::match_env::MatchEnvironment;
 pub struct WsMatchEnv<'a> {
     pub(crate) ws: &'a dyn WorldState,
     pub(crate) perms: Obj,
+    /// Match names using full Unicode case folding rather than plain lowercasing.
+    pub(crate) unicode_folding: bool,
 }
 
 impl<'a> WsMatchEnv<'a> {
     pub fn new(ws: &'a dyn WorldState, perms: Obj) -> Self {
-        Self { ws, perms }
+        Self {
+            ws,
+            perms,
+            unicode_folding: false,
+        }
+    }
+
+    pub fn with_unicode_folding(mut self, unicode_folding: bool) -> Self {
+        self.unicode_folding = unicode_folding;
+        self
     }
 }
 impl MatchEnvironment for WsMatchEnv<'_> {
@@ -54,4 +66,12 @@ impl MatchEnvironment for WsMatchEnv<'_> {
     fn location_of(&self, player: &Obj) -> Result<Obj, WorldStateError> {
         self.ws.location_of(&self.perms, player)
     }
+
+    fn fold_case(&self, name: &str) -> String {
+        if self.unicode_folding {
+            casefold(name)
+        } else {
+            name.to_lowercase()
+        }
+    }
 }
@@ -14,7 +14,7 @@
 pub use crate::model::defset::{Defs, DefsIter, HasUuid, Named};
 pub use crate::model::objects::{ObjAttr, ObjAttrs, ObjFlag, ObjectRef};
 pub use crate::model::objset::{ObjSet, ObjSetIter};
-pub use crate::model::permissions::Perms;
+pub use crate::model::permissions::{PermissionAuditSink, PermissionCheck, Perms};
 pub use crate::model::propdef::{PropDef, PropDefs};
 pub use crate::model::props::{PropAttr, PropAttrs, PropFlag, PropPerms};
 pub use crate::model::r#match::{ArgSpec, PrepSpec, Preposition, VerbArgsSpec};
@@ -38,7 +38,7 @@ mod verbs;
 mod world_state;
 
 use crate::Symbol;
-pub use world_state::WorldStateError;
+pub use world_state::{CacheStats, WorldStateError, COMPILE_OPTIONS_KEY};
 
 /// The result code from a commit/complete operation on the world's state.
 #[derive(Debug, Eq, PartialEq)]
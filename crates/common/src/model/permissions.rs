@@ -18,6 +18,23 @@ use crate::model::{PropPerms, WorldStateError};
 use crate::util::BitEnum;
 use crate::Obj;
 
+/// One permission check made by a world state with auditing turned on.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PermissionCheck {
+    /// Whose permissions were checked.
+    pub programmer: Obj,
+    /// Whether the programmer is a wizard, and so passed the check whatever the object's flags.
+    pub wizard: bool,
+    /// The object being acted on (or #-1, for wizard-only operations on no object in particular).
+    pub object: Obj,
+    /// What was being done to it: "read", "write", "owner", "wizard", "read verb", "write verb",
+    /// "read property" or "write property".
+    pub action: &'static str,
+}
+
+/// Where an auditing world state sends each permission check as it's made.
+pub type PermissionAuditSink = Box<dyn Fn(PermissionCheck) + Send>;
+
 /// Combination of who a set of permissions is for, and what permissions they have.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Perms {
@@ -23,13 +23,25 @@ use crate::model::props::{PropAttrs, PropFlag};
 use crate::model::r#match::{PrepSpec, VerbArgsSpec};
 use crate::model::verbdef::{VerbDef, VerbDefs};
 use crate::model::verbs::{BinaryType, VerbAttrs, VerbFlag};
-use crate::model::{CommitResult, ObjectRef, PropPerms};
+use crate::model::{CommitResult, ObjectRef, PermissionAuditSink, PropPerms};
 use crate::model::{ObjAttr, Vid};
 use crate::util::BitEnum;
 use crate::Symbol;
 use crate::Var;
 use crate::{Error, Obj};
 
+/// How one of the database's caches is doing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub used_bytes: usize,
+    /// The size past which entries start being evicted.
+    pub threshold_bytes: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+}
+
 /// Errors related to the world state and operations on it.
 #[derive(Error, Debug, Eq, PartialEq, Clone, Decode, Encode)]
 pub enum WorldStateError {
@@ -41,6 +53,17 @@ pub enum WorldStateError {
     ObjectAttributeError(ObjAttr, Obj),
     #[error("Recursive move detected: {0} -> {1}")]
     RecursiveMove(Obj, Obj),
+    /// Reparenting the object would make it its own ancestor, through the given chain of parents
+    /// (from the would-be new parent up to the object).
+    #[error(
+        "Recursive chparent: {0} would become its own ancestor ({})",
+        .1.iter().map(|o| o.to_string()).collect::<Vec<_>>().join(" -> ")
+    )]
+    ParentCycle(Obj, Vec<Obj>),
+    /// Reparenting would have the object (or a descendant) define a property of the same name as
+    /// one it'd inherit from a new ancestor.
+    #[error("Property {2} on {0} conflicts with the one defined on new ancestor {1}")]
+    ChparentPropertyConflict(Obj, Obj, String),
 
     #[error("Object permission denied")]
     ObjectPermissionDenied,
@@ -89,6 +112,8 @@ impl WorldStateError {
             Self::ObjectNotFound(_) => Error::E_INVIND,
             Self::ObjectPermissionDenied => Error::E_PERM,
             Self::RecursiveMove(_, _) => Error::E_RECMOVE,
+            Self::ParentCycle(_, _) => Error::E_RECMOVE,
+            Self::ChparentPropertyConflict(_, _, _) => Error::E_INVARG,
             Self::VerbNotFound(_, _) => Error::E_VERBNF,
             Self::VerbPermissionDenied => Error::E_PERM,
             Self::InvalidVerb(_) => Error::E_VERBNF,
@@ -127,6 +152,10 @@ impl From<WorldStateError> for Error {
 /// Each world state is expected to have a consistent shapshotted view of the world, and to
 /// commit any changes to the world at the end of the transaction, or be capable of rolling back
 /// on failure.
+/// The server registry entry holding the database's own language feature set, which takes
+/// precedence over the server's configured one. See `WorldState::compile_options`.
+pub const COMPILE_OPTIONS_KEY: &str = "compile_options";
+
 pub trait WorldState: Send {
     // TODO: Combine worlstate owner & flags check into one call, to make perms check more efficient
 
@@ -160,6 +189,19 @@ pub trait WorldState: Send {
     /// Return the number of bytes used by the given object and all its attributes.
     fn object_bytes(&self, perms: &Obj, obj: &Obj) -> Result<usize, WorldStateError>;
 
+    /// Create a copy of `source` with the same parent, name and flags (except that it isn't a
+    /// player), the properties `source` defines, and the values it holds for those it inherits;
+    /// and, if `copy_verbs`, the verbs it defines, owned by `owner`. The copy is owned by `owner`
+    /// (or itself, if #-1), and is located nowhere.
+    /// Note it is the caller's responsibility to execute :initialize, if it wants to.
+    fn clone_object(
+        &mut self,
+        perms: &Obj,
+        source: &Obj,
+        owner: &Obj,
+        copy_verbs: bool,
+    ) -> Result<Obj, WorldStateError>;
+
     /// Create a new object, assigning it a new unique object id.
     /// If owner is #-1, the object's is set to itself.
     /// Note it is the caller's responsibility to execute :initialize).
@@ -173,18 +215,36 @@ pub trait WorldState: Send {
 
     /// Recycles (destroys) the given object, and re-parents all its children to the next parent up
     /// the chain, including removing property definitions inherited from the object.
-    /// If the object is a location, the contents of that location are moved to #-1.
-    /// (It is the caller's (bf_recycle) responsibility to execute :exitfunc for those objects).
+    /// The object is removed from its own location, and if it is a location, the contents of that
+    /// location are moved to #-1.
+    /// (It is the caller's (bf_recycle) responsibility to execute :exitfunc for those objects,
+    /// normally by calling `evict_contents` first).
     fn recycle_object(&mut self, perms: &Obj, obj: &Obj) -> Result<(), WorldStateError>;
 
     /// Return the highest used object # in the system.
     fn max_object(&self, perms: &Obj) -> Result<Obj, WorldStateError>;
 
+    /// Renumber the given object to the lowest-numbered unused object # lower than its current
+    /// one, fixing up all references to it held by the database. Returns the new object (which is
+    /// the same object if there was no lower free number).
+    /// (Wizard only.)
+    fn renumber_object(&mut self, perms: &Obj, obj: &Obj) -> Result<Obj, WorldStateError>;
+
+    /// Set the highest used object # to that of the highest valid object, so that the numbers of
+    /// recycled objects above it will be reused.
+    /// (Wizard only.)
+    fn reset_max_object(&mut self, perms: &Obj) -> Result<(), WorldStateError>;
+
     /// Move an object to a new location.
     /// (Note it is the caller's responsibility to execute :accept, :enterfunc, :exitfunc, etc.)
     fn move_object(&mut self, perms: &Obj, obj: &Obj, new_loc: &Obj)
         -> Result<(), WorldStateError>;
 
+    /// Move everything in the given object to #-1, returning what was moved, as the first step of
+    /// recycling it. Only the object being emptied has to be writable.
+    /// (It is the caller's (bf_recycle) responsibility to execute :exitfunc for the moved objects.)
+    fn evict_contents(&mut self, perms: &Obj, obj: &Obj) -> Result<ObjSet, WorldStateError>;
+
     /// Get the contents of a given object.
     fn contents_of(&self, perms: &Obj, obj: &Obj) -> Result<ObjSet, WorldStateError>;
 
@@ -203,6 +263,15 @@ pub trait WorldState: Send {
         pname: Symbol,
     ) -> Result<Var, WorldStateError>;
 
+    /// Retrieve several properties from the given object at once, as `retrieve_property` would,
+    /// failing with the first which can't be read.
+    fn retrieve_properties(
+        &self,
+        perms: &Obj,
+        obj: &Obj,
+        pnames: &[Symbol],
+    ) -> Result<Vec<Var>, WorldStateError>;
+
     /// Get information about a property, without walking the inheritance tree.
     /// Returns the PropDef as well as the owner of the property.
     fn get_property_info(
@@ -229,6 +298,15 @@ pub trait WorldState: Send {
         value: &Var,
     ) -> Result<(), WorldStateError>;
 
+    /// Update several properties on the given object at once, as `update_property` would. Every
+    /// ordinary (non-builtin) property is resolved and permission checked before any is written.
+    fn update_properties(
+        &mut self,
+        perms: &Obj,
+        obj: &Obj,
+        values: &[(Symbol, Var)],
+    ) -> Result<(), WorldStateError>;
+
     /// Check if a property is 'clear' (value is purely inherited)
     fn is_property_clear(
         &self,
@@ -354,11 +432,16 @@ pub trait WorldState: Send {
 
     /// Change the parent of the given object.
     /// This manages the movement of property definitions between the old and new parents.
+    /// If the object or any of its descendants defines a property with the same name as one
+    /// defined by the new parent or its ancestors, that's an error, unless `clear_conflicts` is
+    /// set, in which case those definitions (and their values) are dropped in favour of the
+    /// inherited ones.
     fn change_parent(
         &mut self,
         perms: &Obj,
         obj: &Obj,
         new_parent: &Obj,
+        clear_conflicts: bool,
     ) -> Result<(), WorldStateError>;
 
     /// Get the children of the given object.
@@ -370,9 +453,116 @@ pub trait WorldState: Send {
     /// Get the name & aliases of an object.
     fn names_of(&self, perms: &Obj, obj: &Obj) -> Result<(String, Vec<String>), WorldStateError>;
 
+    /// Get the objects whose names contain `pattern`, ignoring case unless `case_matters`.
+    fn find_objects_by_name(
+        &self,
+        perms: &Obj,
+        pattern: &str,
+        case_matters: bool,
+    ) -> Result<ObjSet, WorldStateError>;
+
+    /// Get `root` and those of its descendants which define a verb matching `name`, with the
+    /// matching verb, leaving out objects `perms` can't read. A `root` of #-1 searches everything.
+    fn find_verb_definers(
+        &self,
+        perms: &Obj,
+        root: &Obj,
+        name: Symbol,
+    ) -> Result<Vec<(Obj, VerbDef)>, WorldStateError>;
+
+    /// As `find_verb_definers`, but for properties defined (not just inherited) as `name`.
+    fn find_property_definers(
+        &self,
+        perms: &Obj,
+        root: &Obj,
+        name: Symbol,
+    ) -> Result<Vec<(Obj, PropDef)>, WorldStateError>;
+
+    /// Search the full-text index for up to `limit` `(object, property)` pairs matching `query`,
+    /// best first, leaving out properties `perms` can't read. `None` if there is no index.
+    fn search_text(
+        &self,
+        perms: &Obj,
+        query: &str,
+        limit: usize,
+    ) -> Result<Option<Vec<(Obj, Symbol)>>, WorldStateError>;
+
     /// Returns the (rough) total number of bytes used by database storage subsystem.
     fn db_usage(&self) -> Result<usize, WorldStateError>;
 
+    /// A number which changes whenever verbs, property definitions or inheritance (may) have
+    /// changed, as seen from this transaction, so that verb lookups can be cached for as long as
+    /// it stays the same.
+    fn dispatch_generation(&self) -> u64;
+
+    /// The generation `obj` was last changed at. It goes up with each committed change to
+    /// anything about the object (attributes, contents, verbs, properties), so that copies held
+    /// outside the server can be checked for staleness without comparing their contents.
+    fn object_generation(&self, obj: &Obj) -> Result<u64, WorldStateError>;
+
+    /// The database's own language feature set, as a map from option name to whether it's on,
+    /// if one has been set (as the server registry entry `COMPILE_OPTIONS_KEY`). Anyone may get
+    /// it, as it decides how their verbs compile.
+    fn compile_options(&self) -> Result<Option<Var>, WorldStateError>;
+
+    /// The language features the given verb was last compiled with, if they were recorded.
+    fn verb_compile_options(
+        &self,
+        perms: &Obj,
+        obj: &Obj,
+        uuid: Uuid,
+    ) -> Result<Option<Var>, WorldStateError>;
+
+    /// Record the language features the given verb was compiled with, as a map from option name
+    /// to whether it was on. Updating a verb's program drops what was recorded for it.
+    fn set_verb_compile_options(
+        &mut self,
+        perms: &Obj,
+        obj: &Obj,
+        uuid: Uuid,
+        options: Var,
+    ) -> Result<(), WorldStateError>;
+
+    /// Get the server registry entry for `key`, if there is one. Wizards only.
+    ///
+    /// The registry holds server-wide configuration (feature toggles, host settings) apart from
+    /// the objects, so it doesn't have to live in properties on #0 and isn't part of dumps.
+    fn registry_get(&self, perms: &Obj, key: &str) -> Result<Option<Var>, WorldStateError>;
+
+    /// Set the server registry entry for `key`. Wizards only.
+    fn registry_set(&mut self, perms: &Obj, key: &str, value: Var) -> Result<(), WorldStateError>;
+
+    /// Remove the server registry entry for `key`, returning whether there was one. Wizards only.
+    fn registry_delete(&mut self, perms: &Obj, key: &str) -> Result<bool, WorldStateError>;
+
+    /// All the server registry entries, in order of key. Wizards only.
+    fn registry_list(&self, perms: &Obj) -> Result<Vec<(String, Var)>, WorldStateError>;
+
+    /// Statistics for each of the database's caches, by name. Wizards only.
+    fn cache_stats(&self, perms: &Obj) -> Result<Vec<(String, CacheStats)>, WorldStateError>;
+
+    /// Empty the named cache, or all of them, returning how many entries were dropped; None if
+    /// there's no cache by that name. Wizards only.
+    fn flush_caches(
+        &self,
+        perms: &Obj,
+        name: Option<&str>,
+    ) -> Result<Option<usize>, WorldStateError>;
+
+    /// Set the size past which the named cache starts evicting entries, returning whether there's
+    /// a cache by that name. It's kept with the database, and used from then on in place of its
+    /// configured `cache_eviction_threshold`. Wizards only.
+    fn set_cache_capacity(
+        &self,
+        perms: &Obj,
+        name: &str,
+        threshold_bytes: usize,
+    ) -> Result<bool, WorldStateError>;
+
+    /// Report every permission check this world state makes from now on to `sink`, so that
+    /// wizards can see which programmers' permissions are being used on what.
+    fn audit_permissions(&mut self, sink: PermissionAuditSink);
+
     /// Commit all modifications made to the state of this world since the start of its transaction.
     fn commit(self: Box<Self>) -> Result<CommitResult, WorldStateError>;
 
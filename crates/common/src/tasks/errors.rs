@@ -67,6 +67,10 @@ pub enum SchedulerError {
     VerbRetrievalFailed(WorldStateError),
     #[error("Unable to resolve object reference {0}")]
     ObjectResolutionFailed(WorldStateError),
+    #[error("Server is shutting down")]
+    ShuttingDown,
+    #[error("Server is too busy to start the task")]
+    ServerTooBusy,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Decode, Encode)]
@@ -27,17 +27,48 @@ pub struct NarrativeEvent {
     pub event: Event,
 }
 
+/// Content type for notifications which are out-of-band lines (e.g. MCP messages), to be sent to
+/// the client verbatim rather than as in-band text.
+pub const CONTENT_TYPE_OUT_OF_BAND: &str = "application/x-moo-out-of-band";
+
+/// Content type for notifications which are prompts: text to be shown without a line break after
+/// it, e.g. from `notify(player, text, 0, 1)`. Telnet clients are told where a prompt ends, if
+/// they've agreed to it, so they can display it properly.
+pub const CONTENT_TYPE_PROMPT: &str = "application/x-moo-prompt";
+
+/// Content type for notifications which are GMCP messages, with content `{package, data}`.
+pub const CONTENT_TYPE_GMCP: &str = "application/x-gmcp";
+
 /// Types of events we can send to the session.
 #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
 pub enum Event {
     /// The typical "something happened" descriptive event.
     /// Value & Content-Type
     Notify(Var, Option<Symbol>),
+    /// Show (or replace, if one with the same id is already showing) a presentation: content
+    /// which stays on screen, e.g. in a side panel, until it's withdrawn.
+    Present(Presentation),
+    /// Withdraw the presentation with the given id.
+    Unpresent(String),
     // TODO: Other Event types on Session stream
     //   other events that might happen here would be things like (local) "object moved" or "object
     //   created."
 }
 
+/// Content for a client to show somewhere other than the narrative stream, until withdrawn.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Presentation {
+    /// Identifies the presentation, so it can be replaced or withdrawn.
+    pub id: String,
+    pub content_type: String,
+    pub content: String,
+    /// Where the client should show it, e.g. "window", "right-dock". Clients which don't know the
+    /// target should pick something sensible.
+    pub target: String,
+    /// Anything else the client might use, e.g. a title or size.
+    pub attributes: Vec<(String, String)>,
+}
+
 impl NarrativeEvent {
     #[must_use]
     pub fn notify(author: Var, value: Var, content_type: Option<Symbol>) -> Self {
@@ -48,6 +79,24 @@ impl NarrativeEvent {
         }
     }
 
+    #[must_use]
+    pub fn present(author: Var, presentation: Presentation) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            author,
+            event: Event::Present(presentation),
+        }
+    }
+
+    #[must_use]
+    pub fn unpresent(author: Var, id: String) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            author,
+            event: Event::Unpresent(id),
+        }
+    }
+
     #[must_use]
     pub fn timestamp(&self) -> SystemTime {
         self.timestamp
@@ -16,6 +16,9 @@ mod events;
 
 pub use errors::{AbortLimitReason, CommandError, Exception, SchedulerError, VerbProgramError};
 
-pub use events::{Event, NarrativeEvent};
+pub use events::{
+    Event, NarrativeEvent, Presentation, CONTENT_TYPE_GMCP, CONTENT_TYPE_OUT_OF_BAND,
+    CONTENT_TYPE_PROMPT,
+};
 
 pub type TaskId = usize;
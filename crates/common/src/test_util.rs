@@ -0,0 +1,143 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `proptest` strategies for values, for property-based tests here and in the crates which store
+//! or transmit them. Enabled with the `test-util` feature.
+//!
+//! Only values which can survive a round trip are generated: floats are finite, strings have no
+//! newlines (textdumps can't hold them), and flyweights are never sealed (sealed flyweights never
+//! compare equal, not even to themselves).
+
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Union};
+
+use crate::{v_err, v_float, v_flyweight, v_int, v_list, v_map, v_none, v_obj, v_str};
+use crate::{Error, List, Obj, Symbol, Var};
+
+/// Which kinds of compound value `Var`'s strategy generates. Scalars, strings and lists are always
+/// generated.
+#[derive(Clone, Copy, Debug)]
+pub struct VarStrategyOptions {
+    pub maps: bool,
+    pub flyweights: bool,
+    /// How deeply lists, maps and flyweights nest.
+    pub depth: u32,
+}
+
+impl Default for VarStrategyOptions {
+    fn default() -> Self {
+        Self {
+            maps: true,
+            flyweights: true,
+            depth: 3,
+        }
+    }
+}
+
+impl Arbitrary for Obj {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Obj>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<i32>().prop_map(Obj::mk_id).boxed()
+    }
+}
+
+impl Arbitrary for Error {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Error>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0..=Error::E_FLOAT as u8)
+            .prop_map(|e| Error::from_repr(e).unwrap())
+            .boxed()
+    }
+}
+
+impl Arbitrary for Var {
+    type Parameters = VarStrategyOptions;
+    type Strategy = BoxedStrategy<Var>;
+
+    fn arbitrary_with(options: VarStrategyOptions) -> Self::Strategy {
+        arb_scalar()
+            .prop_recursive(options.depth, 64, 8, move |inner| {
+                let mut kinds = vec![prop::collection::vec(inner.clone(), 0..8)
+                    .prop_map(|items| v_list(&items))
+                    .boxed()];
+                if options.maps {
+                    kinds.push(
+                        prop::collection::vec((arb_scalar(), inner.clone()), 0..8)
+                            .prop_map(|pairs| v_map(&pairs))
+                            .boxed(),
+                    );
+                }
+                if options.flyweights {
+                    kinds.push(
+                        (
+                            any::<Obj>(),
+                            prop::collection::vec((arb_symbol(), inner.clone()), 0..4),
+                            prop::collection::vec(inner, 0..4),
+                        )
+                            .prop_map(|(delegate, slots, contents)| {
+                                v_flyweight(delegate, &slots, List::from_iter(contents), None)
+                            })
+                            .boxed(),
+                    );
+                }
+                Union::new(kinds)
+            })
+            .boxed()
+    }
+}
+
+/// Values which contain no others.
+pub fn arb_scalar() -> BoxedStrategy<Var> {
+    let finite = prop::num::f64::POSITIVE
+        | prop::num::f64::NEGATIVE
+        | prop::num::f64::NORMAL
+        | prop::num::f64::SUBNORMAL
+        | prop::num::f64::ZERO;
+    prop_oneof![
+        Just(v_none()),
+        any::<i64>().prop_map(v_int),
+        finite.prop_map(v_float),
+        any::<Obj>().prop_map(v_obj),
+        any::<Error>().prop_map(v_err),
+        "[^\n]{0,16}".prop_map(|s| v_str(&s)),
+    ]
+    .boxed()
+}
+
+/// Names, as used for flyweight slots.
+pub fn arb_symbol() -> BoxedStrategy<Symbol> {
+    "[a-z_][a-z0-9_]{0,8}".prop_map(|s| Symbol::mk(&s)).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsByteBuffer;
+
+    proptest! {
+        #[test]
+        fn test_encode_decode_round_trip(v in any::<Var>()) {
+            let decoded = Var::from_bytes(v.as_bytes().unwrap()).unwrap();
+            prop_assert!(decoded.eq_case_sensitive(&v), "{:?} != {:?}", decoded, v);
+        }
+
+        #[test]
+        fn test_size_bytes_matches_encoding(v in any::<Var>()) {
+            prop_assert_eq!(v.size_bytes(), v.make_copy_as_vec().unwrap().len());
+        }
+    }
+}
@@ -14,10 +14,12 @@
 mod bitarray;
 mod bitenum;
 mod bitset;
+mod unicode;
 
 pub use bitarray::BitArray;
 pub use bitenum::BitEnum;
 pub use bitset::*;
+pub use unicode::{casefold, normalize, NormalizationForm};
 
 /// Check `names` for matches with wildcard prefixes.
 /// e.g. "dname*c" will match for any of 'dname', 'dnamec'
@@ -0,0 +1,82 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+
+/// The four Unicode normalization forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+impl NormalizationForm {
+    /// Look up a normalization form by its (case-insensitive) name, e.g. "NFC".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "NFC" => Some(Self::Nfc),
+            "NFD" => Some(Self::Nfd),
+            "NFKC" => Some(Self::Nfkc),
+            "NFKD" => Some(Self::Nfkd),
+            _ => None,
+        }
+    }
+}
+
+#[must_use]
+pub fn normalize(s: &str, form: NormalizationForm) -> String {
+    match form {
+        NormalizationForm::Nfc => s.nfc().collect(),
+        NormalizationForm::Nfd => s.nfd().collect(),
+        NormalizationForm::Nfkc => s.nfkc().collect(),
+        NormalizationForm::Nfkd => s.nfkd().collect(),
+    }
+}
+
+/// Full Unicode case folding, applied to the canonical decomposition and recomposed, so that two
+/// strings are caseless matches (per the Unicode standard, D145) exactly when their folds are
+/// equal. Unlike `to_lowercase`, this maps e.g. "ß" to "ss" and "ﬁ" to "fi".
+#[must_use]
+pub fn casefold(s: &str) -> String {
+    let decomposed: String = s.nfd().collect();
+    default_case_fold_str(&decomposed).nfc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let composed = "\u{e9}";
+        let decomposed = "e\u{301}";
+        assert_eq!(normalize(decomposed, NormalizationForm::Nfc), composed);
+        assert_eq!(normalize(composed, NormalizationForm::Nfd), decomposed);
+        assert_eq!(normalize("\u{fb01}", NormalizationForm::Nfkc), "fi");
+        assert_eq!(
+            NormalizationForm::from_name("nfkd"),
+            Some(NormalizationForm::Nfkd)
+        );
+        assert_eq!(NormalizationForm::from_name("nfx"), None);
+    }
+
+    #[test]
+    fn test_casefold() {
+        assert_eq!(casefold("Straße"), "strasse");
+        assert_eq!(casefold("STRASSE"), casefold("straße"));
+        assert_eq!(casefold("E\u{301}TE\u{301}"), casefold("\u{e9}t\u{e9}"));
+    }
+}
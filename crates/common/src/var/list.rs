@@ -11,7 +11,6 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::v_list_iter;
 use crate::var::var::Var;
 use crate::var::variant::Variant;
 use crate::var::Error;
@@ -27,6 +26,9 @@ use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
 use std::ops::Index;
 
+/// A MOO list, held as a persistent (RRB tree) vector. Lists produced by editing another list --
+/// appending, inserting, slicing, splicing -- share all but the changed parts of its structure
+/// with it, so those operations stay cheap (logarithmic) even for very large lists.
 #[derive(Clone)]
 pub struct List(Box<im::Vector<Var>>);
 
@@ -141,12 +143,9 @@ impl Sequence for List {
     }
 
     fn insert(&self, index: usize, value: &Var) -> Result<Var, Error> {
-        let inserted_iter = self
-            .iter()
-            .take(index)
-            .chain(std::iter::once(value.clone()))
-            .chain(self.iter().skip(index));
-        Ok(v_list_iter(inserted_iter))
+        let mut new = self.0.clone();
+        new.insert(index.min(self.len()), value.clone());
+        Ok(Var::from_variant(Variant::List(List(new))))
     }
 
     fn range(&self, from: isize, to: isize) -> Result<Var, Error> {
@@ -157,9 +156,10 @@ impl Sequence for List {
         if from > len + 1 || to > len {
             return Err(E_RANGE);
         }
-        let (from, to) = (max(from, 0) as usize, to as usize);
-        let range_iter = self.iter().skip(from).take(to - from + 1);
-        Ok(Var::mk_list_iter(range_iter))
+        let end = (to as usize + 1).min(self.len());
+        let from = (max(from, 0) as usize).min(end);
+        let range = self.0.skip(from).take(end - from);
+        Ok(Var::from_variant(Variant::List(List(Box::new(range)))))
     }
 
     fn range_set(&self, from: isize, to: isize, with: &Var) -> Result<Var, Error> {
@@ -192,14 +192,11 @@ impl Sequence for List {
             to
         };
 
-        // Iterator taking up to `from`
-        let base_iter = self.iter().take(from);
-        // Iterator for with_val...
-        let with_iter = with_val.iter();
-        // Iterator from after to, up to the end
-        let end_iter = self.iter().skip(to + 1);
-        let new_iter = base_iter.chain(with_iter).chain(end_iter);
-        Ok(v_list_iter(new_iter))
+        // Everything up to `from`, then `with`, then everything after `to`.
+        let mut new = self.0.take(from.min(base_len));
+        new.append(with_val.0.as_ref().clone());
+        new.append(self.0.skip((to + 1).min(base_len)));
+        Ok(Var::from_variant(Variant::List(List(Box::new(new)))))
     }
 
     fn append(&self, other: &Var) -> Result<Var, Error> {
@@ -208,8 +205,9 @@ impl Sequence for List {
             _ => return Err(Error::E_TYPE),
         };
 
-        let combined_iter = self.iter().chain(other.iter());
-        Ok(Var::mk_list_iter(combined_iter))
+        let mut new = self.0.clone();
+        new.append(other.0.as_ref().clone());
+        Ok(Var::from_variant(Variant::List(List(new))))
     }
 
     fn remove_at(&self, index: usize) -> Result<Var, Error> {
@@ -217,11 +215,9 @@ impl Sequence for List {
             return Err(E_RANGE);
         }
 
-        let new = self
-            .iter()
-            .enumerate()
-            .filter_map(|(i, v)| if i == index { None } else { Some(v) });
-        Ok(Var::mk_list_iter(new))
+        let mut new = self.0.clone();
+        new.remove(index);
+        Ok(Var::from_variant(Variant::List(List(new))))
     }
 }
 
@@ -243,17 +239,13 @@ impl PartialEq for List {
         if self.len() != other.len() {
             return false;
         }
-
-        // elements comparison
-        for i in 0..self.len() {
-            let a = Sequence::index(self, i).unwrap();
-            let b = Sequence::index(other, i).unwrap();
-            if a != b {
-                return false;
-            }
+        // Lists derived from one another share structure, and may share all of it.
+        if self.0.ptr_eq(&other.0) {
+            return true;
         }
 
-        true
+        // elements comparison
+        self.0.iter().zip(other.0.iter()).all(|(a, b)| a == b)
     }
 }
 
@@ -713,4 +705,51 @@ mod tests {
         );
         assert_eq!(r, Err(E_RANGE));
     }
+
+    #[test]
+    fn test_large_list_structural_ops() {
+        let n = 100_000;
+        let mut l = v_empty_list();
+        for i in 0..n {
+            l = l.push(&v_int(i)).unwrap();
+        }
+        assert_eq!(l.len().unwrap(), n as usize);
+
+        // Slices and splices of a large list agree with the same operations on a plain Vec.
+        let model: Vec<Var> = (0..n).map(v_int).collect();
+        let r = l
+            .range(&v_int(1000), &v_int(50_000), IndexMode::OneBased)
+            .unwrap();
+        assert_eq!(r, v_list(&model[999..50_000]));
+
+        let r = l
+            .range_set(
+                &v_int(2),
+                &v_int(n - 1),
+                &v_list(&[v_str("x")]),
+                IndexMode::OneBased,
+            )
+            .unwrap();
+        assert_eq!(r, v_list(&[v_int(0), v_str("x"), v_int(n - 1)]));
+
+        let r = l
+            .insert(&v_int(n / 2), &v_str("mid"), IndexMode::OneBased)
+            .unwrap();
+        assert_eq!(r.len().unwrap(), n as usize + 1);
+        assert_eq!(
+            r.index(&v_int(n / 2), IndexMode::OneBased).unwrap(),
+            v_str("mid")
+        );
+
+        let r = r.append(&l).unwrap();
+        assert_eq!(r.len().unwrap(), 2 * n as usize + 1);
+        let r = r.remove_at(&v_int(n / 2), IndexMode::OneBased).unwrap();
+        assert_eq!(
+            r.append(&v_empty_list()).unwrap().len().unwrap(),
+            2 * n as usize
+        );
+
+        // The original is untouched by all of the above.
+        assert_eq!(l, v_list(&model));
+    }
 }
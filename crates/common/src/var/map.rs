@@ -23,6 +23,10 @@ use bincode::{BorrowDecode, Decode, Encode};
 use std::cmp::Ordering;
 use std::hash::Hash;
 
+/// An immutable map, kept sorted by key. Keys are ordered as `Var`s are: objects, then integers,
+/// then floats, then strings (without regard to case), then errors, each by value. This is the
+/// order iteration, `mapkeys()`/`mapvalues()` and literal output all see, regardless of the order
+/// entries were added in.
 #[derive(Clone)]
 pub struct Map(Box<im::Vector<(Var, Var)>>);
 
@@ -307,7 +311,8 @@ mod tests {
     use crate::var::var::Var;
     use crate::var::variant::Variant;
     use crate::var::{Associative, IndexMode};
-    use crate::{v_bool, v_int, v_str};
+    use crate::Error::E_RANGE;
+    use crate::{v_bool, v_err, v_float, v_int, v_objid, v_str};
 
     #[test]
     fn test_map_pack_unpack_index() {
@@ -646,6 +651,26 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Keys of different types come out grouped by type, in a fixed order.
+    fn test_map_mixed_key_ordering() {
+        let m = Var::mk_map(&[
+            (v_err(E_RANGE), v_int(5)),
+            (v_str("a"), v_int(4)),
+            (v_float(1.5), v_int(3)),
+            (v_int(10), v_int(2)),
+            (v_objid(1), v_int(1)),
+        ]);
+        let Variant::Map(m) = m.variant() else {
+            panic!("Expected map");
+        };
+        assert_eq!(
+            m.values(),
+            vec![v_int(1), v_int(2), v_int(3), v_int(4), v_int(5)]
+        );
+        assert_eq!(m.index(&v_float(1.5)).unwrap(), v_int(3));
+    }
+
     #[test]
     fn test_index_in() {
         // ["3" -> "3", "1" -> "1", "4" -> "4", "5" -> "5", "9" -> "9", "2" -> "2"];
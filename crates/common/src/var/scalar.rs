@@ -77,6 +77,49 @@ impl Var {
         }
     }
 
+    /// As `div`, but integer division rounds toward negative infinity rather than toward zero,
+    /// so that `-7 / 2` is -4. Float division is unchanged.
+    pub fn floor_div(&self, v: &Self) -> Result<Self, Error> {
+        match (self.variant(), v.variant()) {
+            (Variant::Int(l), Variant::Int(r)) => {
+                let q = l.checked_div(*r).ok_or(E_INVARG)?;
+                if l % r != 0 && (*l < 0) != (*r < 0) {
+                    Ok(v_int(q - 1))
+                } else {
+                    Ok(v_int(q))
+                }
+            }
+            _ => self.div(v),
+        }
+    }
+
+    /// As `modulus`, but the result takes the sign of the divisor rather than of the dividend,
+    /// so that `-7 % 2` is 1 and `-7.5 % 2.0` is 0.5.
+    pub fn floor_modulus(&self, v: &Self) -> Result<Self, Error> {
+        fn floored(l: f64, r: f64) -> f64 {
+            let m = l % r;
+            if m != 0.0 && (m < 0.0) != (r < 0.0) {
+                m + r
+            } else {
+                m
+            }
+        }
+        match (self.variant(), v.variant()) {
+            (Variant::Int(l), Variant::Int(r)) => {
+                let m = l.checked_rem(*r).ok_or(E_INVARG)?;
+                if m != 0 && (m < 0) != (*r < 0) {
+                    Ok(v_int(m + r))
+                } else {
+                    Ok(v_int(m))
+                }
+            }
+            (Variant::Float(l), Variant::Float(r)) => Ok(v_float(floored(*l, *r))),
+            (Variant::Float(l), Variant::Int(r)) => Ok(v_float(floored(*l, *r as f64))),
+            (Variant::Int(l), Variant::Float(r)) => Ok(v_float(floored(*l as f64, *r))),
+            (_, _) => Ok(v_err(E_TYPE)),
+        }
+    }
+
     pub fn pow(&self, v: &Self) -> Result<Self, Error> {
         match (self.variant(), v.variant()) {
             (Variant::Float(l), Variant::Float(r)) => Ok(v_float(l.powf(*r))),
@@ -146,6 +189,32 @@ mod tests {
         assert_eq!(v_str("moop").modulus(&v_int(2)), Ok(v_err(E_TYPE)));
     }
 
+    #[test]
+    fn test_floored_against_truncated() {
+        // (dividend, divisor, truncated quotient, remainder, floored quotient, remainder)
+        let cases = [
+            (7, 2, 3, 1, 3, 1),
+            (-7, 2, -3, -1, -4, 1),
+            (7, -2, -3, 1, -4, -1),
+            (-7, -2, 3, -1, 3, -1),
+            (-6, 2, -3, 0, -3, 0),
+        ];
+        for (l, r, tq, tm, fq, fm) in cases {
+            let (l, r) = (v_int(l), v_int(r));
+            assert_eq!(l.div(&r), Ok(v_int(tq)));
+            assert_eq!(l.modulus(&r), Ok(v_int(tm)));
+            assert_eq!(l.floor_div(&r), Ok(v_int(fq)));
+            assert_eq!(l.floor_modulus(&r), Ok(v_int(fm)));
+        }
+
+        assert_eq!(v_float(-7.5).modulus(&v_float(2.)), Ok(v_float(-1.5)));
+        assert_eq!(v_float(-7.5).floor_modulus(&v_float(2.)), Ok(v_float(0.5)));
+        assert_eq!(v_int(-7).floor_modulus(&v_float(2.)), Ok(v_float(1.)));
+        assert_eq!(v_float(-7.).floor_div(&v_int(2)), Ok(v_float(-3.5)));
+        assert_eq!(v_int(i64::MIN).floor_div(&v_int(-1)), Err(Error::E_INVARG));
+        assert_eq!(v_str("moop").floor_modulus(&v_int(2)), Ok(v_err(E_TYPE)));
+    }
+
     #[test]
     fn test_pow() {
         assert_eq!(v_int(1).pow(&v_int(2)), Ok(v_int(1)));
@@ -16,56 +16,144 @@ use crate::var::variant::Variant;
 use crate::var::Error;
 use crate::var::Error::{E_INVARG, E_RANGE, E_TYPE};
 use crate::var::Sequence;
-use bincode::{Decode, Encode};
+use bincode::de::{BorrowDecoder, Decoder};
+use bincode::enc::Encoder;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{BorrowDecode, Decode, Encode};
 use num_traits::ToPrimitive;
 use std::cmp::max;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
-use std::sync::Arc;
-
-#[derive(Clone, Encode, Decode)]
-pub struct Str(Arc<String>);
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Strings at least this long (in bytes) are built into a shared, growable buffer when appended
+/// to, rather than copied. Below it, copying is cheap enough that it isn't worth the bother.
+const APPEND_BUFFER_THRESHOLD: usize = 4096;
+
+#[derive(Clone)]
+pub struct Str(Arc<StrRepr>);
+
+enum StrRepr {
+    Flat(String),
+    /// The first `len` bytes of a buffer that appends are written to in place. Repeatedly
+    /// appending to the result of the last append (as MOO code building up a string in a loop
+    /// does) just extends the buffer, rather than copying the whole string each time; appending
+    /// to an older, shorter, view of the buffer copies it into a new one, leaving the newer views
+    /// alone. The string is only collapsed into a flat copy when something needs it whole.
+    Appended {
+        buffer: Arc<Mutex<String>>,
+        len: usize,
+        flat: OnceLock<String>,
+    },
+}
 
 impl Str {
     pub fn mk_str(s: &str) -> Self {
-        Str(Arc::new(s.to_string()))
+        Str(Arc::new(StrRepr::Flat(s.to_string())))
+    }
+
+    fn from_string(s: String) -> Self {
+        Str(Arc::new(StrRepr::Flat(s)))
     }
 
     pub fn as_string(&self) -> &String {
-        self.0.as_ref()
+        match self.0.as_ref() {
+            StrRepr::Flat(s) => s,
+            StrRepr::Appended { buffer, len, flat } => {
+                flat.get_or_init(|| buffer.lock().unwrap()[..*len].to_string())
+            }
+        }
+    }
+
+    /// The length in bytes, without collapsing an appended string.
+    fn byte_len(&self) -> usize {
+        match self.0.as_ref() {
+            StrRepr::Flat(s) => s.len(),
+            StrRepr::Appended { len, .. } => *len,
+        }
     }
 
     pub fn index_set(&self, index: usize, value: &Self) -> Result<Var, Error> {
         if value.len() != 1 {
             return Err(E_INVARG);
         }
-        let string = self.0.as_ref();
+        let string = self.as_string();
         if index >= string.len() {
             return Err(E_RANGE);
         }
         let mut s = string.clone();
         s.replace_range(index..=index, value.as_string());
-        let s = Str(Arc::new(s));
+        let s = Str::from_string(s);
         let v = Variant::Str(s);
         Ok(Var::from_variant(v))
     }
 
     pub fn append(&self, other: &Self) -> Var {
-        let mut s = self.0.as_ref().clone();
-        s.push_str(other.as_string());
-        let s = Str(Arc::new(s));
-        let v = Variant::Str(s);
-        Var::from_variant(v)
+        Var::from_variant(Variant::Str(self.appended(other)))
+    }
+
+    fn appended(&self, other: &Self) -> Self {
+        let len = self.byte_len() + other.byte_len();
+        // (Taken before locking anything, as `other` may be a view of the same buffer.)
+        let other = other.as_string();
+        if len < APPEND_BUFFER_THRESHOLD {
+            let mut s = String::with_capacity(len);
+            s.push_str(self.as_string());
+            s.push_str(other);
+            return Str::from_string(s);
+        }
+        if let StrRepr::Appended {
+            buffer,
+            len: our_len,
+            ..
+        } = self.0.as_ref()
+        {
+            let mut contents = buffer.lock().unwrap();
+            if contents.len() == *our_len {
+                contents.push_str(other);
+                return Str(Arc::new(StrRepr::Appended {
+                    buffer: buffer.clone(),
+                    len,
+                    flat: OnceLock::new(),
+                }));
+            }
+        }
+        let mut contents = String::with_capacity(len * 2);
+        contents.push_str(self.as_string());
+        contents.push_str(other);
+        Str(Arc::new(StrRepr::Appended {
+            buffer: Arc::new(Mutex::new(contents)),
+            len,
+            flat: OnceLock::new(),
+        }))
+    }
+}
+
+impl Encode for Str {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.as_string().encode(encoder)
+    }
+}
+
+impl Decode for Str {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Str::from_string(String::decode(decoder)?))
+    }
+}
+
+impl<'de> BorrowDecode<'de> for Str {
+    fn borrow_decode<D: BorrowDecoder<'de>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Str::from_string(String::borrow_decode(decoder)?))
     }
 }
 
 impl Sequence for Str {
     fn is_empty(&self) -> bool {
-        self.as_string().is_empty()
+        self.byte_len() == 0
     }
 
     fn len(&self) -> usize {
-        self.as_string().len()
+        self.byte_len()
     }
 
     fn index(&self, index: usize) -> Result<Var, Error> {
@@ -155,9 +243,7 @@ impl Sequence for Str {
             _ => return Err(E_TYPE),
         };
 
-        let mut new_copy = self.as_string().clone();
-        new_copy.push_str(value.as_string().as_str());
-        Ok(Var::mk_str(&new_copy))
+        Ok(Str::append(self, value))
     }
 
     fn append(&self, other: &Var) -> Result<Var, Error> {
@@ -166,9 +252,7 @@ impl Sequence for Str {
             _ => return Err(E_TYPE),
         };
 
-        let mut new_copy = self.as_string().clone();
-        new_copy.push_str(other.as_string().as_str());
-        Ok(Var::mk_str(&new_copy))
+        Ok(Str::append(self, other))
     }
 
     fn remove_at(&self, index: usize) -> Result<Var, Error> {
@@ -455,4 +539,27 @@ mod tests {
         assert_eq!(s, s2);
         assert!(!s.eq_case_sensitive(&s2));
     }
+
+    #[test]
+    fn test_string_repeated_append() {
+        let chunk = "x".repeat(1000);
+        let mut s = v_str("");
+        let mut views = vec![];
+        for _ in 0..10 {
+            s = s.add(&v_str(&chunk)).unwrap();
+            views.push(s.clone());
+        }
+        assert_eq!(s.len().unwrap(), 10000);
+        assert_eq!(s, v_str(&chunk.repeat(10)));
+
+        // Appending to an older, shorter, string leaves the ones built after it alone.
+        let branched = views[5].add(&v_str("y")).unwrap();
+        assert_eq!(branched, v_str(&format!("{}y", chunk.repeat(6))));
+        assert_eq!(views[6], v_str(&chunk.repeat(7)));
+        assert_eq!(views[9].add(&v_str("z")).unwrap().len().unwrap(), 10001);
+
+        // And a string appended to itself.
+        let doubled = s.add(&s).unwrap();
+        assert_eq!(doubled, v_str(&chunk.repeat(20)));
+    }
 }
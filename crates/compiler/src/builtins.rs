@@ -55,8 +55,8 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("disassemble"),
             min_args: Q(2),
-            max_args: Q(2),
-            types: vec![Typed(TYPE_OBJ), Any],
+            max_args: Q(3),
+            types: vec![Typed(TYPE_OBJ), Any, Any],
             implemented: true,
         },
         Builtin {
@@ -71,7 +71,7 @@ fn mk_builtin_table() -> Vec<Builtin> {
             min_args: Q(0),
             max_args: Q(0),
             types: vec![],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("call_function"),
@@ -356,22 +356,22 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("toint"),
             min_args: Q(1),
-            max_args: Q(1),
-            types: vec![Any],
+            max_args: Q(2),
+            types: vec![Any, Any],
             implemented: true,
         },
         Builtin {
             name: Symbol::mk("tonum"),
             min_args: Q(1),
-            max_args: Q(1),
-            types: vec![Any],
+            max_args: Q(2),
+            types: vec![Any, Any],
             implemented: true,
         },
         Builtin {
             name: Symbol::mk("tofloat"),
             min_args: Q(1),
-            max_args: Q(1),
-            types: vec![Any],
+            max_args: Q(2),
+            types: vec![Any, Any],
             implemented: true,
         },
         Builtin {
@@ -412,8 +412,8 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("ctime"),
             min_args: Q(0),
-            max_args: Q(1),
-            types: vec![Typed(TYPE_INT)],
+            max_args: Q(2),
+            types: vec![Typed(TYPE_INT), Typed(TYPE_STR)],
             implemented: true,
         },
         Builtin {
@@ -694,14 +694,14 @@ fn mk_builtin_table() -> Vec<Builtin> {
             min_args: Q(1),
             max_args: Q(1),
             types: vec![Typed(TYPE_OBJ)],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("reset_max_object"),
             min_args: Q(0),
             max_args: Q(0),
             types: vec![],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("memory_usage"),
@@ -769,8 +769,8 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("notify"),
             min_args: Q(2),
-            max_args: Q(3),
-            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR), Any],
+            max_args: Q(4),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR), Any, Any],
             implemented: true,
         },
         Builtin {
@@ -785,42 +785,42 @@ fn mk_builtin_table() -> Vec<Builtin> {
             min_args: Q(3),
             max_args: Q(3),
             types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR), Any],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("connection_option"),
             min_args: Q(2),
             max_args: Q(2),
             types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR)],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("connection_options"),
             min_args: Q(1),
             max_args: Q(1),
             types: vec![Typed(TYPE_OBJ)],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("listen"),
             min_args: Q(2),
-            max_args: Q(3),
-            types: vec![Typed(TYPE_OBJ), Any, Any],
-            implemented: false,
+            max_args: Q(4),
+            types: vec![Typed(TYPE_OBJ), Any, Any, Typed(TYPE_STR)],
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("unlisten"),
             min_args: Q(1),
-            max_args: Q(1),
-            types: vec![Any],
-            implemented: false,
+            max_args: Q(2),
+            types: vec![Any, Typed(TYPE_STR)],
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("listeners"),
             min_args: Q(0),
             max_args: Q(0),
             types: vec![],
-            implemented: false,
+            implemented: true,
         },
         Builtin {
             name: Symbol::mk("buffered_output_length"),
@@ -895,8 +895,8 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("verb_info"),
             min_args: Q(2),
-            max_args: Q(2),
-            types: vec![Typed(TYPE_OBJ), Any],
+            max_args: Q(3),
+            types: vec![Typed(TYPE_OBJ), Any, Any],
             implemented: true,
         },
         Builtin {
@@ -944,8 +944,8 @@ fn mk_builtin_table() -> Vec<Builtin> {
         Builtin {
             name: Symbol::mk("set_verb_code"),
             min_args: Q(3),
-            max_args: Q(3),
-            types: vec![Typed(TYPE_OBJ), Any, Typed(TYPE_LIST)],
+            max_args: Q(4),
+            types: vec![Typed(TYPE_OBJ), Any, Typed(TYPE_LIST), Typed(TYPE_MAP)],
             implemented: true,
         },
         Builtin {
@@ -997,6 +997,572 @@ fn mk_builtin_table() -> Vec<Builtin> {
             types: vec![Typed(TYPE_FLYWEIGHT), Typed(TYPE_MAP)],
             implemented: true,
         },
+        Builtin {
+            name: Symbol::mk("random_bytes"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("frandom"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_INT), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("argon2_hash"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("argon2_verify"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("bcrypt_hash"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("bcrypt_verify"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("hotp"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("totp"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("totp_verify"),
+            min_args: Q(2),
+            max_args: Q(3),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("debug_task"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_INT), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("set_breakpoint"),
+            min_args: Q(3),
+            max_args: Q(4),
+            types: vec![
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+                Typed(TYPE_INT),
+                Typed(TYPE_STR),
+            ],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("clear_breakpoint"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("breakpoints"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("sprintf"),
+            min_args: Q(1),
+            max_args: U,
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("normalize"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("casefold"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("caseless_cmp"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("notify_oob"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("notify_gmcp"),
+            min_args: Q(2),
+            max_args: Q(3),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("downgrade_markup"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_MAP)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("connection_attributes"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_OBJ)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("present"),
+            min_args: Q(5),
+            max_args: Q(6),
+            types: vec![
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+                Typed(TYPE_STR),
+                Typed(TYPE_STR),
+                Typed(TYPE_STR),
+                Any,
+            ],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("unpresent"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("ban_site"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("unban_site"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("banned_sites"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("revoke_tokens"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_OBJ)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("federation_send"),
+            min_args: Q(4),
+            max_args: Q(4),
+            types: vec![
+                Typed(TYPE_STR),
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+                Typed(TYPE_LIST),
+            ],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("connection_name_lookup"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("server_stats"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("locate_by_name"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("find_verb"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("find_property"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("search_text"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("shortest_path"),
+            min_args: Q(2),
+            max_args: Q(4),
+            types: vec![
+                Typed(TYPE_OBJ),
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+                Typed(TYPE_STR),
+            ],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("clone_object"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_MAP)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("get_properties"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_LIST)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("set_properties"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_MAP)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("task_local"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("set_task_local"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("add_webhook"),
+            min_args: Q(2),
+            max_args: Q(3),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_MAP), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("remove_webhook"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("webhooks"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("wrap_verb"),
+            min_args: Q(4),
+            max_args: Q(4),
+            types: vec![
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+                Typed(TYPE_OBJ),
+                Typed(TYPE_STR),
+            ],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("unwrap_verb"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("verb_wrappers"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("registry_get"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("registry_set"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("registry_delete"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("registry_list"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("cache_stats"),
+            min_args: Q(0),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("flush_caches"),
+            min_args: Q(0),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("set_cache_capacity"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("begin_atomic"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("end_atomic"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("flyweight_slots"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_FLYWEIGHT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("flyweight_info"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_FLYWEIGHT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("equal_deep"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Any, Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("copy"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("uuid_generate"),
+            min_args: Q(0),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("uuid_parse"),
+            min_args: Q(1),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("strftime"),
+            min_args: Q(1),
+            max_args: Q(3),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT), Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("monotonic_time_ns"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("bench"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("new_waif"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("audit_permissions"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("permission_checks"),
+            min_args: Q(0),
+            max_args: Q(1),
+            types: vec![Any],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("switch_player"),
+            min_args: Q(2),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_OBJ), Typed(TYPE_OBJ)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("server_load"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("perf_counters"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("task_limits"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("task_quota"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_OBJ)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("finished_tasks"),
+            min_args: Q(0),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_OBJ)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("parse_number"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_STR)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("read_lines"),
+            min_args: Q(0),
+            max_args: Q(2),
+            types: vec![Typed(TYPE_STR), Typed(TYPE_INT)],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("compile_options"),
+            min_args: Q(0),
+            max_args: Q(0),
+            types: vec![],
+            implemented: true,
+        },
+        Builtin {
+            name: Symbol::mk("set_compile_options"),
+            min_args: Q(1),
+            max_args: Q(1),
+            types: vec![Typed(TYPE_MAP)],
+            implemented: true,
+        },
     ]
 }
 
@@ -28,6 +28,7 @@ use crate::labels::{JumpLabel, Label, Offset};
 use crate::names::{Name, Names, UnboundName};
 use crate::opcode::Op::Jump;
 use crate::opcode::{Op, ScatterArgs, ScatterLabel};
+use crate::optimize::thread_jumps;
 use crate::parse::{parse_program, CompileOptions};
 use crate::program::Program;
 use moor_values::model::CompileError;
@@ -866,6 +867,7 @@ pub fn compile(program: &str, options: CompileOptions) -> Result<Program, Compil
     let compile_span = tracing::trace_span!("compile");
     let _compile_guard = compile_span.enter();
 
+    let optimize = options.optimize;
     let parse = parse_program(program, options)?;
 
     // Generate the code into 'cg_state'.
@@ -882,6 +884,13 @@ pub fn compile(program: &str, options: CompileOptions) -> Result<Program, Compil
         )
     }
 
+    if optimize {
+        thread_jumps(&mut cg_state.ops, &cg_state.jumps);
+        for fork_vector in cg_state.fork_vectors.iter_mut() {
+            thread_jumps(fork_vector, &cg_state.jumps);
+        }
+    }
+
     let binary = Program {
         literals: cg_state.literals,
         jump_labels: cg_state.jumps,
@@ -1534,4 +1534,87 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_optimize_folds_constants() {
+        let program = "return 1 + 2 * 3; return -(2 - 5); return \"a\" + \"b\" == \"ab\";";
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let binary = compile(program, options).unwrap();
+        assert_eq!(
+            *binary.main_vector.as_ref(),
+            vec![
+                ImmInt(7),
+                Return,
+                ImmInt(3),
+                Return,
+                ImmInt(1),
+                Return,
+                Done
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optimize_leaves_errors_to_runtime() {
+        let program = "return 1 / 0;";
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let binary = compile(program, options).unwrap();
+        assert_eq!(
+            *binary.main_vector.as_ref(),
+            vec![ImmInt(1), ImmInt(0), Div, Return, Done]
+        );
+    }
+
+    #[test]
+    fn test_optimize_leaves_negative_division_to_runtime() {
+        // The result depends on whether the server is doing floored or truncated division.
+        let program = "return -7 % 2;";
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let binary = compile(program, options).unwrap();
+        assert_eq!(
+            *binary.main_vector.as_ref(),
+            vec![ImmInt(-7), ImmInt(2), Mod, Return, Done]
+        );
+    }
+
+    #[test]
+    fn test_optimize_removes_dead_branches() {
+        let program = "if (0) return 1; endif while (0) return 2; endwhile if (1) return 3; else return 4; endif";
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let binary = compile(program, options).unwrap();
+        assert_eq!(*binary.main_vector.as_ref(), vec![ImmInt(3), Return, Done]);
+    }
+
+    #[test]
+    fn test_optimize_threads_jumps() {
+        // The arm's jump to the end of the `if` lands on the loop's jump back to its start, so it
+        // goes to the start directly.
+        let program = "while (x) if (y) a = 1; endif endwhile";
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let binary = compile(program, options).unwrap();
+        let jumps: Vec<_> = binary
+            .main_vector
+            .iter()
+            .filter_map(|op| match op {
+                Jump { label } => Some(*label),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(jumps, vec![Label(0), Label(0)]);
+    }
 }
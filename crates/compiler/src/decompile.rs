@@ -128,9 +128,19 @@ impl Decompile {
         offset: usize,
     ) -> Result<Vec<Stmt>, DecompileError> {
         let jump_label = self.find_jump(label)?; // check that the label exists
+        self.decompile_statements_to_position(
+            (jump_label.position.0 as usize).saturating_sub(offset),
+        )
+    }
+
+    /// Decompile statements up to (but not including) the opcode at `position`.
+    fn decompile_statements_to_position(
+        &mut self,
+        position: usize,
+    ) -> Result<Vec<Stmt>, DecompileError> {
         let old_len = self.statements.len();
 
-        while self.position + offset < jump_label.position.0 as usize {
+        while self.position < position {
             self.decompile()?;
         }
         if self.statements.len() > old_len {
@@ -214,7 +224,23 @@ impl Decompile {
 
                 // Decompile to the 'end_of_otherwise' label to get the statements for the
                 // otherwise branch.
-                let mut otherwise_stmts = self.decompile_statements_until(&end_of_otherwise)?;
+                // If jumps were threaded, an `if` at the end of a `while` body jumps straight back
+                // to the top of the loop instead of to the loop's own jump back, so the label is
+                // behind us. The statement then ends at that jump back: the last jump to the same
+                // place.
+                let end_position = self.find_jump(&end_of_otherwise)?.position.0 as usize;
+                let mut otherwise_stmts = if end_position < self.position {
+                    let Some(loop_jump) = self.opcode_vector().iter().rposition(
+                        |op| matches!(op, Op::Jump { label } if *label == end_of_otherwise),
+                    ) else {
+                        return Err(MalformedProgram(
+                            "expected jump back to loop start".to_string(),
+                        ));
+                    };
+                    self.decompile_statements_to_position(loop_jump)?
+                } else {
+                    self.decompile_statements_until(&end_of_otherwise)?
+                };
 
                 // Resulting thing should be a Scope, or empty.
                 let else_arm = if otherwise_stmts.is_empty() {
@@ -784,7 +810,10 @@ impl Decompile {
                     };
 
                     // Scan forward until the jump, decompiling as we go.
+                    // (If jumps were threaded and this is the end of a loop body, the jump goes
+                    // back to the top of the loop rather than to the end label.)
                     let end_label_position = self.find_jump(&end_label)?.position.0;
+                    let jump_labels = self.program.jump_labels.clone();
                     let (statements, _) =
                         self.decompile_statements_until_match(|position, o| {
                             if position == end_label_position as _ {
@@ -792,6 +821,8 @@ impl Decompile {
                             }
                             if let Op::Jump { label } = o {
                                 label == &end_label
+                                    || (jump_labels[label.0 as usize].position.0 as usize)
+                                        < position
                             } else {
                                 false
                             }
@@ -1028,8 +1059,12 @@ mod tests {
     use test_case::test_case;
 
     fn parse_decompile(program_text: &str) -> (Parse, Parse) {
-        let parse_1 = parse_program(program_text, CompileOptions::default()).unwrap();
-        let binary = compile(program_text, CompileOptions::default()).unwrap();
+        parse_decompile_with(program_text, CompileOptions::default())
+    }
+
+    fn parse_decompile_with(program_text: &str, options: CompileOptions) -> (Parse, Parse) {
+        let parse_1 = parse_program(program_text, options.clone()).unwrap();
+        let binary = compile(program_text, options).unwrap();
         let mut parse_2 = program_to_tree(&binary).unwrap();
         annotate_line_numbers(1, &mut parse_2.stmts);
         (parse_1, parse_2)
@@ -1105,6 +1140,32 @@ mod tests {
         assert_trees_match_recursive(&parse.stmts, &decompiled.stmts);
     }
 
+    #[test_case("return 1 + 2 * 3;"; "folded arithmetic")]
+    #[test_case("if (0) return 1; endif return 2;"; "dead if")]
+    #[test_case("if (0) return 1; elseif (x) return 2; else return 3; endif"; "dead first arm")]
+    #[test_case("if (x) return 1; elseif (1) return 2; else return 3; endif"; "live elseif")]
+    #[test_case("while (x) if (y) a = 1; endif endwhile"; "threaded if")]
+    #[test_case(
+        "while (x) if (y) a = 1; elseif (z) a = 2; else a = 3; endif endwhile";
+        "threaded if_elseif_else"
+    )]
+    #[test_case(
+        "while (x) if (y) a = 1; else while (z) if (a) b = 1; endif endwhile endif endwhile";
+        "threaded nested loops"
+    )]
+    #[test_case(
+        "while (x) try a = 1; except (E_INVARG) a = 2; except (E_RANGE) a = 3; endtry endwhile";
+        "threaded try_except"
+    )]
+    fn test_case_optimized_decompile_matches(prg: &str) {
+        let options = CompileOptions {
+            optimize: true,
+            ..Default::default()
+        };
+        let (parse, decompiled) = parse_decompile_with(prg, options);
+        assert_trees_match_recursive(&parse.stmts, &decompiled.stmts);
+    }
+
     #[test]
     fn test_decompile_lexical_scope_block() {
         let program = r#"begin
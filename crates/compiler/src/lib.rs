@@ -26,6 +26,7 @@ mod unparse;
 mod codegen_tests;
 mod names;
 mod opcode;
+mod optimize;
 mod program;
 
 pub use crate::builtins::{offset_for_builtin, ArgCount, ArgType, Builtin, BuiltinId, BUILTINS};
@@ -35,7 +36,7 @@ pub use crate::labels::{JumpLabel, Label, Offset};
 pub use crate::names::{Name, UnboundNames};
 pub use crate::opcode::{Op, ScatterLabel};
 pub use crate::parse::CompileOptions;
-pub use crate::program::{Program, EMPTY_PROGRAM};
+pub use crate::program::{Program, EMPTY_PROGRAM, PROGRAM_FORMAT_VERSION};
 pub use crate::unparse::{to_literal, unparse};
 
 #[macro_use]
@@ -0,0 +1,383 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Optimizations applied when `CompileOptions::optimize` is set.
+//!
+//! Constant folding and dead branch elimination work on the tree before code generation, so a
+//! decompiled listing shows the folded literals and the surviving branches, as if they'd been
+//! written that way. Folding uses the same operations the VM would, and leaves alone anything
+//! which would raise an error, so that the error is still raised when (and if) the code runs.
+//!
+//! Jump threading works on the opcodes afterwards: a jump to an unconditional jump goes straight
+//! to that jump's destination.
+
+use moor_values::{v_bool, Var, Variant};
+
+use crate::ast::{Arg, BinaryOp, CatchCodes, CondArm, ElseArm, Expr, Stmt, StmtNode, UnaryOp};
+use crate::labels::JumpLabel;
+use crate::opcode::Op;
+
+/// Fold constants and remove dead branches throughout a tree.
+pub fn optimize_tree(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut optimized = Vec::with_capacity(stmts.len());
+    for mut stmt in stmts {
+        optimize_stmt(&mut stmt);
+        match stmt.node {
+            StmtNode::Cond { arms, otherwise } => {
+                optimized.extend(prune_cond(arms, otherwise, stmt.parser_line_no));
+            }
+            // `while (0)` never runs its body. (A named loop assigns the condition to its
+            // variable, so it has to stay.)
+            StmtNode::While {
+                id: None,
+                condition: Expr::Value(ref v),
+                ..
+            } if !v.is_true() => {}
+            _ => optimized.push(stmt),
+        }
+    }
+    optimized
+}
+
+fn optimize_stmt(stmt: &mut Stmt) {
+    match &mut stmt.node {
+        StmtNode::Cond { arms, otherwise } => {
+            for arm in arms.iter_mut() {
+                fold_expr(&mut arm.condition);
+                arm.statements = optimize_tree(std::mem::take(&mut arm.statements));
+            }
+            if let Some(otherwise) = otherwise {
+                otherwise.statements = optimize_tree(std::mem::take(&mut otherwise.statements));
+            }
+        }
+        StmtNode::ForList { expr, body, .. } => {
+            fold_expr(expr);
+            *body = optimize_tree(std::mem::take(body));
+        }
+        StmtNode::ForRange { from, to, body, .. } => {
+            fold_expr(from);
+            fold_expr(to);
+            *body = optimize_tree(std::mem::take(body));
+        }
+        StmtNode::While {
+            condition, body, ..
+        } => {
+            fold_expr(condition);
+            *body = optimize_tree(std::mem::take(body));
+        }
+        StmtNode::Fork { time, body, .. } => {
+            fold_expr(time);
+            *body = optimize_tree(std::mem::take(body));
+        }
+        StmtNode::TryExcept { body, excepts, .. } => {
+            *body = optimize_tree(std::mem::take(body));
+            for except in excepts.iter_mut() {
+                fold_catch_codes(&mut except.codes);
+                except.statements = optimize_tree(std::mem::take(&mut except.statements));
+            }
+        }
+        StmtNode::TryFinally { body, handler, .. } => {
+            *body = optimize_tree(std::mem::take(body));
+            *handler = optimize_tree(std::mem::take(handler));
+        }
+        StmtNode::Scope { body, .. } => {
+            *body = optimize_tree(std::mem::take(body));
+        }
+        StmtNode::Return(Some(expr)) | StmtNode::Expr(expr) => fold_expr(expr),
+        StmtNode::Return(None) | StmtNode::Break { .. } | StmtNode::Continue { .. } => {}
+    }
+}
+
+/// Drop the arms of an `if` whose conditions are constantly false, and everything after the
+/// first arm whose condition is constantly true. What's left may be no statement at all, or just
+/// the body of one arm.
+fn prune_cond(arms: Vec<CondArm>, otherwise: Option<ElseArm>, line: usize) -> Vec<Stmt> {
+    let mut kept = vec![];
+    let mut otherwise = otherwise;
+    for arm in arms {
+        match &arm.condition {
+            Expr::Value(v) if !v.is_true() => continue,
+            Expr::Value(_) => {
+                // Always taken, so this is where the statement ends.
+                otherwise = Some(ElseArm {
+                    statements: arm.statements,
+                    environment_width: arm.environment_width,
+                });
+                break;
+            }
+            _ => kept.push(arm),
+        }
+    }
+    if !kept.is_empty() {
+        return vec![Stmt::new(
+            StmtNode::Cond {
+                arms: kept,
+                otherwise,
+            },
+            line,
+        )];
+    }
+    match otherwise {
+        None => vec![],
+        // Without any bindings of its own, the branch can stand in for the whole statement;
+        // otherwise it keeps its scope.
+        Some(ElseArm {
+            statements,
+            environment_width: 0,
+        }) => statements,
+        Some(ElseArm {
+            statements,
+            environment_width,
+        }) => vec![Stmt::new(
+            StmtNode::Scope {
+                num_bindings: environment_width,
+                body: statements,
+            },
+            line,
+        )],
+    }
+}
+
+fn fold_args(args: &mut [Arg]) {
+    for arg in args {
+        match arg {
+            Arg::Normal(e) | Arg::Splice(e) => fold_expr(e),
+        }
+    }
+}
+
+fn fold_catch_codes(codes: &mut CatchCodes) {
+    if let CatchCodes::Codes(args) = codes {
+        fold_args(args);
+    }
+}
+
+/// Fold the constant parts of an expression, in place.
+fn fold_expr(expr: &mut Expr) {
+    match expr {
+        Expr::Value(_) | Expr::Id(_) | Expr::Length => {}
+        Expr::Assign { left, right } => {
+            fold_expr(left);
+            fold_expr(right);
+        }
+        Expr::Pass { args } | Expr::Call { args, .. } | Expr::List(args) => fold_args(args),
+        Expr::Binary(op, l, r) => {
+            fold_expr(l);
+            fold_expr(r);
+            if let (Expr::Value(l), Expr::Value(r)) = (l.as_ref(), r.as_ref()) {
+                if let Some(v) = fold_binary(op, l, r) {
+                    *expr = Expr::Value(v);
+                }
+            }
+        }
+        Expr::And(l, r) => {
+            fold_expr(l);
+            fold_expr(r);
+            // `a && b` is `a` if `a` is false, and `b` otherwise.
+            if let Expr::Value(v) = l.as_ref() {
+                *expr = if v.is_true() {
+                    std::mem::replace(r.as_mut(), Expr::Length)
+                } else {
+                    Expr::Value(v.clone())
+                };
+            }
+        }
+        Expr::Or(l, r) => {
+            fold_expr(l);
+            fold_expr(r);
+            // `a || b` is `a` if `a` is true, and `b` otherwise.
+            if let Expr::Value(v) = l.as_ref() {
+                *expr = if v.is_true() {
+                    Expr::Value(v.clone())
+                } else {
+                    std::mem::replace(r.as_mut(), Expr::Length)
+                };
+            }
+        }
+        Expr::Unary(op, e) => {
+            fold_expr(e);
+            if let Expr::Value(v) = e.as_ref() {
+                if let Some(v) = fold_unary(op, v) {
+                    *expr = Expr::Value(v);
+                }
+            }
+        }
+        Expr::Prop { location, property } => {
+            fold_expr(location);
+            fold_expr(property);
+        }
+        Expr::Verb {
+            location,
+            verb,
+            args,
+        } => {
+            fold_expr(location);
+            fold_expr(verb);
+            fold_args(args);
+        }
+        Expr::Range { base, from, to } => {
+            fold_expr(base);
+            fold_expr(from);
+            fold_expr(to);
+        }
+        Expr::Cond {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            fold_expr(condition);
+            fold_expr(consequence);
+            fold_expr(alternative);
+            if let Expr::Value(v) = condition.as_ref() {
+                let taken = if v.is_true() {
+                    consequence
+                } else {
+                    alternative
+                };
+                *expr = std::mem::replace(taken.as_mut(), Expr::Length);
+            }
+        }
+        Expr::TryCatch {
+            trye,
+            codes,
+            except,
+        } => {
+            fold_expr(trye);
+            fold_catch_codes(codes);
+            if let Some(except) = except {
+                fold_expr(except);
+            }
+        }
+        Expr::Index(base, index) => {
+            fold_expr(base);
+            fold_expr(index);
+        }
+        Expr::Map(pairs) => {
+            for (k, v) in pairs {
+                fold_expr(k);
+                fold_expr(v);
+            }
+        }
+        Expr::Flyweight(delegate, slots, contents) => {
+            fold_expr(delegate);
+            for (_, v) in slots {
+                fold_expr(v);
+            }
+            fold_args(contents);
+        }
+        Expr::Scatter(items, e) => {
+            for item in items {
+                if let Some(default) = &mut item.expr {
+                    fold_expr(default);
+                }
+            }
+            fold_expr(e);
+        }
+    }
+}
+
+fn is_number(v: &Var) -> bool {
+    matches!(v.variant(), Variant::Int(_) | Variant::Float(_))
+}
+
+fn is_scalar(v: &Var) -> bool {
+    matches!(
+        v.variant(),
+        Variant::Int(_) | Variant::Float(_) | Variant::Str(_) | Variant::Obj(_) | Variant::Err(_)
+    )
+}
+
+fn is_zero(v: &Var) -> bool {
+    matches!(v.variant(), Variant::Int(0) | Variant::Float(0.0))
+}
+
+fn is_negative(v: &Var) -> bool {
+    match v.variant() {
+        Variant::Int(i) => *i < 0,
+        Variant::Float(f) => *f < 0.0,
+        _ => false,
+    }
+}
+
+/// The value of `l op r`, as the VM would compute it, if it can be computed without raising an
+/// error.
+fn fold_binary(op: &BinaryOp, l: &Var, r: &Var) -> Option<Var> {
+    let result = match op {
+        BinaryOp::Eq
+        | BinaryOp::NEq
+        | BinaryOp::Gt
+        | BinaryOp::GtE
+        | BinaryOp::Lt
+        | BinaryOp::LtE => {
+            if !is_scalar(l) || !is_scalar(r) {
+                return None;
+            }
+            let b = match op {
+                BinaryOp::Eq => l == r,
+                BinaryOp::NEq => l != r,
+                BinaryOp::Gt => l > r,
+                BinaryOp::GtE => l >= r,
+                BinaryOp::Lt => l < r,
+                _ => l <= r,
+            };
+            return Some(v_bool(b));
+        }
+        BinaryOp::Add => match (l.variant(), r.variant()) {
+            (Variant::Str(_), Variant::Str(_)) => l.add(r),
+            _ if is_number(l) && is_number(r) => l.add(r),
+            _ => return None,
+        },
+        _ if !is_number(l) || !is_number(r) => return None,
+        BinaryOp::Sub => l.sub(r),
+        BinaryOp::Mul => l.mul(r),
+        // How division and modulus treat negative numbers depends on the server's
+        // `floored_division` setting, which isn't known here, so those are left to the runtime.
+        BinaryOp::Div if !is_zero(r) && !is_negative(l) && !is_negative(r) => l.div(r),
+        BinaryOp::Mod if !is_zero(r) && !is_negative(l) && !is_negative(r) => l.modulus(r),
+        BinaryOp::Exp => l.pow(r),
+        BinaryOp::Div | BinaryOp::Mod | BinaryOp::In => return None,
+    };
+    result
+        .ok()
+        .filter(|v| !matches!(v.variant(), Variant::Err(_)))
+}
+
+fn fold_unary(op: &UnaryOp, v: &Var) -> Option<Var> {
+    match op {
+        UnaryOp::Not => Some(v_bool(!v.is_true())),
+        UnaryOp::Neg if is_number(v) => v.negative().ok(),
+        UnaryOp::Neg => None,
+    }
+}
+
+/// Send each unconditional jump whose destination is another unconditional jump straight to
+/// where that one goes.
+pub fn thread_jumps(ops: &mut [Op], jump_labels: &[JumpLabel]) {
+    for position in 0..ops.len() {
+        let Op::Jump { label } = ops[position] else {
+            continue;
+        };
+        let mut target = label;
+        // Bounded, in case of a cycle of jumps.
+        for _ in 0..ops.len() {
+            let destination = jump_labels[target.0 as usize].position.0 as usize;
+            match ops.get(destination) {
+                Some(Op::Jump { label: next }) if *next != target => target = *next,
+                _ => break,
+            }
+        }
+        if target != label {
+            ops[position] = Op::Jump { label: target };
+        }
+    }
+}
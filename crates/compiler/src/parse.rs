@@ -13,14 +13,14 @@
 
 /// Kicks off the Pest parser and converts it into our AST.
 /// This is the main entry point for parsing.
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::str::FromStr;
 
 use itertools::Itertools;
 use moor_values::SYSTEM_OBJECT;
-use moor_values::{v_none, Symbol};
+use moor_values::{v_none, Symbol, Variant};
 use pest::pratt_parser::{Assoc, Op, PrattParser};
 pub use pest::Parser as PestParser;
 use tracing::{instrument, warn};
@@ -39,6 +39,7 @@ use crate::ast::{
     StmtNode, UnaryOp,
 };
 use crate::names::{Names, UnboundName, UnboundNames};
+use crate::optimize::optimize_tree;
 use crate::parse::moo::{MooParser, Rule};
 use crate::unparse::annotate_line_numbers;
 use crate::Name;
@@ -57,9 +58,21 @@ pub struct CompileOptions {
     /// Whether to support a Map datatype ([ k -> v, .. ]) compatible with Stunt/ToastStunt
     pub map_type: bool,
     /// Whether to support the flyweight type (a delegate object with slots and contents)
-    pub flyweight_type: bool, // TODO: future options:
-                              //      - symbol types
-                              //      - disable "#" style object references (obscure_references)
+    pub flyweight_type: bool,
+    /// Whether to fold constants, remove dead branches, and thread jumps. Off by default, so that
+    /// programs decompile to what was written.
+    pub optimize: bool,
+    /// Whether `$` may be used inside an index or range to mean the length of the value being
+    /// indexed, as in `x[$]` or `x[2..$]`
+    pub range_end: bool,
+    /// Whether a negative integer literal used as an index or range bound counts back from the
+    /// end, so that `x[-1]` is `x[$]` and `x[-3..-1]` is `x[$ - 2..$]`. Off by default, in which
+    /// case such indices raise E_RANGE at runtime, as in LambdaMOO. Only literals are rewritten;
+    /// `x[-n]` still raises E_RANGE.
+    pub negative_indices: bool,
+    // TODO: future options:
+    //      - symbol types
+    //      - disable "#" style object references (obscure_references)
 }
 
 impl Default for CompileOptions {
@@ -68,6 +81,9 @@ impl Default for CompileOptions {
             lexical_scopes: true,
             map_type: true,
             flyweight_type: true,
+            optimize: false,
+            range_end: true,
+            negative_indices: false,
         }
     }
 }
@@ -77,6 +93,8 @@ pub struct TreeTransformer {
     //   borrowing issues, see: https://github.com/pest-parser/pest/discussions/1030
     names: RefCell<UnboundNames>,
     options: CompileOptions,
+    /// How many index or range expressions we're inside, as `$` means nothing outside of one.
+    index_depth: Cell<usize>,
 }
 
 impl TreeTransformer {
@@ -84,6 +102,45 @@ impl TreeTransformer {
         Rc::new(Self {
             names: RefCell::new(UnboundNames::new()),
             options,
+            index_depth: Cell::new(0),
+        })
+    }
+
+    /// Parse the expression used as an index or range bound, rewriting a negative literal to
+    /// count back from the end if `negative_indices` is on.
+    fn parse_index(
+        self: Rc<Self>,
+        pairs: pest::iterators::Pairs<Rule>,
+    ) -> Result<Expr, CompileError> {
+        self.index_depth.set(self.index_depth.get() + 1);
+        let index = self.clone().parse_expr(pairs);
+        self.index_depth.set(self.index_depth.get() - 1);
+        let index = index?;
+        if !self.options.negative_indices {
+            return Ok(index);
+        }
+        let from_end = match &index {
+            Expr::Value(v) => match v.variant() {
+                Variant::Int(i) if *i < 0 => Some(i.unsigned_abs()),
+                _ => None,
+            },
+            Expr::Unary(UnaryOp::Neg, operand) => match operand.as_ref() {
+                Expr::Value(v) => match v.variant() {
+                    Variant::Int(i) if *i > 0 => Some(i.unsigned_abs()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(match from_end {
+            None => index,
+            Some(1) => Expr::Length,
+            Some(n) => Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Length),
+                Box::new(Expr::Value(v_int((n - 1) as i64))),
+            ),
         })
     }
 
@@ -391,7 +448,17 @@ impl TreeTransformer {
                     };
                     Ok(Expr::Pass { args })
                 }
-                Rule::range_end => Ok(Expr::Length),
+                Rule::range_end => {
+                    if !self.options.range_end {
+                        return Err(CompileError::DisabledFeature("range_end".to_string()));
+                    }
+                    if self.index_depth.get() == 0 {
+                        return Err(CompileError::ParseError(
+                            "Illegal context for `$' expression.".to_string(),
+                        ));
+                    }
+                    Ok(Expr::Length)
+                }
                 Rule::try_expr => {
                     let mut inner = primary.into_inner();
                     let try_expr = primary_self
@@ -576,17 +643,17 @@ impl TreeTransformer {
                         let mut parts = op.into_inner();
                         let index = postfix_self
                             .clone()
-                            .parse_expr(parts.next().unwrap().into_inner())?;
+                            .parse_index(parts.next().unwrap().into_inner())?;
                         Ok(Expr::Index(Box::new(lhs?), Box::new(index)))
                     }
                     Rule::index_range => {
                         let mut parts = op.into_inner();
                         let start = postfix_self
                             .clone()
-                            .parse_expr(parts.next().unwrap().into_inner())?;
+                            .parse_index(parts.next().unwrap().into_inner())?;
                         let end = postfix_self
                             .clone()
-                            .parse_expr(parts.next().unwrap().into_inner())?;
+                            .parse_index(parts.next().unwrap().into_inner())?;
                         Ok(Expr::Range {
                             base: Box::new(lhs?),
                             from: Box::new(start),
@@ -1152,6 +1219,9 @@ impl TreeTransformer {
             }
         }
         let names = self.names.borrow_mut();
+        if self.options.optimize {
+            program = optimize_tree(program);
+        }
         // Annotate the "true" line numbers of the AST nodes.
         annotate_line_numbers(1, &mut program);
 
@@ -2650,6 +2720,65 @@ mod tests {
         assert!(matches!(parse, Err(CompileError::DisabledFeature(_))));
     }
 
+    #[test]
+    fn test_no_range_end() {
+        let parse = parse_program(
+            "return x[2..$];",
+            CompileOptions {
+                range_end: false,
+                ..CompileOptions::default()
+            },
+        );
+        assert!(matches!(parse, Err(CompileError::DisabledFeature(_))));
+    }
+
+    #[test]
+    fn test_range_end_outside_index() {
+        let parse = parse_program("return $ + 1;", CompileOptions::default());
+        assert!(matches!(parse, Err(CompileError::ParseError(_))));
+
+        // `$` inside a nested call is still inside the index.
+        let parse = parse_program("return x[min($, 2)];", CompileOptions::default());
+        assert!(parse.is_ok());
+    }
+
+    #[test]
+    fn test_negative_indices() {
+        let program = "return x[-1]; return x[-3..-1];";
+        let options = CompileOptions {
+            negative_indices: true,
+            ..CompileOptions::default()
+        };
+        let parse = parse_program(program, options).unwrap();
+        let x = parse.unbound_names.find_name("x").unwrap();
+        assert_eq!(
+            stripped_stmts(&parse.stmts),
+            vec![
+                StmtNode::Return(Some(Expr::Index(Box::new(Id(x)), Box::new(Expr::Length)))),
+                StmtNode::Return(Some(Expr::Range {
+                    base: Box::new(Id(x)),
+                    from: Box::new(Expr::Binary(
+                        BinaryOp::Sub,
+                        Box::new(Expr::Length),
+                        Box::new(Value(v_int(2))),
+                    )),
+                    to: Box::new(Expr::Length),
+                })),
+            ]
+        );
+
+        // Without the option, they're left for the runtime to reject.
+        let parse = parse_program("return x[-1];", CompileOptions::default()).unwrap();
+        let x = parse.unbound_names.find_name("x").unwrap();
+        assert_eq!(
+            stripped_stmts(&parse.stmts),
+            vec![StmtNode::Return(Some(Expr::Index(
+                Box::new(Id(x)),
+                Box::new(Value(v_int(-1)))
+            )))]
+        );
+    }
+
     #[test]
     fn test_map() {
         let program = r#"
@@ -27,6 +27,12 @@ lazy_static! {
     pub static ref EMPTY_PROGRAM: Program = Program::new();
 }
 
+/// The version of the program encoding and of the code the compiler generates for a given source.
+/// Bump it with any change to either (an opcode added or renumbered, different code generated for
+/// some construct, a new optimization) so that binaries compiled before aren't reused as if they
+/// were current.
+pub const PROGRAM_FORMAT_VERSION: u32 = 1;
+
 /// The result of compilation. The set of instructions, fork vectors, variable offsets, literals.
 #[derive(Clone, Debug, PartialEq, Encode, Decode)]
 pub struct Program {
@@ -64,6 +70,13 @@ impl Program {
             .unwrap_or_else(|| panic!("variable not found: {}", v))
     }
 
+    /// Does a statement on source line `line` begin at opcode `offset` in the main vector?
+    pub fn is_line_start(&self, offset: usize, line: usize) -> bool {
+        self.line_number_spans
+            .iter()
+            .any(|(span_offset, span_line)| *span_offset == offset && *span_line == line)
+    }
+
     pub fn find_literal(&self, l: Var) -> Label {
         Label(
             self.literals
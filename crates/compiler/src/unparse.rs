@@ -1039,6 +1039,18 @@ mod tests {
         assert_eq!(stripped.trim(), result.trim());
     }
 
+    #[test]
+    fn test_unparse_negative_indices() {
+        // Negative indices come back out in the `$` form, which any dialect with `$` accepts.
+        let options = CompileOptions {
+            negative_indices: true,
+            ..CompileOptions::default()
+        };
+        let tree = crate::parse::parse_program("return x[-1] + x[-3..-2];", options).unwrap();
+        let result = unparse(&tree).unwrap().join("\n");
+        assert_eq!(result.trim(), "return x[$] + x[$ - 2..$ - 1];");
+    }
+
     pub fn parse_and_unparse(original: &str) -> Result<String, DecompileError> {
         let tree = crate::parse::parse_program(original, CompileOptions::default()).unwrap();
         Ok(unparse(&tree)?.join("\n"))
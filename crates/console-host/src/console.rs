@@ -0,0 +1,409 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The one connection to the in-process server: the player at the terminal.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use moor_compiler::to_literal;
+use moor_kernel::tasks::sessions::{Session, SessionError, SessionFactory};
+use moor_values::tasks::{
+    Event, NarrativeEvent, CONTENT_TYPE_GMCP, CONTENT_TYPE_OUT_OF_BAND, CONTENT_TYPE_PROMPT,
+};
+use moor_values::{v_empty_map, Obj, Symbol, Var, Variant};
+use termimad::MadSkin;
+use uuid::Uuid;
+
+const CONTENT_TYPE_MARKDOWN: &str = "text/markdown";
+
+/// The state of the console, shared by every session attached to it.
+pub(crate) struct Console {
+    /// The connection object before login, then the player.
+    player: Mutex<Obj>,
+    /// The input a suspended `read()` is waiting for, if any.
+    input_request: Mutex<Option<Uuid>>,
+    connected_at: Instant,
+    last_input: Mutex<Instant>,
+    quit: AtomicBool,
+}
+
+impl Console {
+    pub(crate) fn new(connection: Obj) -> Arc<Self> {
+        Arc::new(Self {
+            player: Mutex::new(connection),
+            input_request: Mutex::new(None),
+            connected_at: Instant::now(),
+            last_input: Mutex::new(Instant::now()),
+            quit: AtomicBool::new(false),
+        })
+    }
+
+    pub(crate) fn session(self: &Arc<Self>) -> Arc<ConsoleSession> {
+        Arc::new(ConsoleSession {
+            console: self.clone(),
+            buffer: Mutex::new(vec![]),
+        })
+    }
+
+    pub(crate) fn player(&self) -> Obj {
+        self.player.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_player(&self, player: Obj) {
+        *self.player.lock().unwrap() = player;
+    }
+
+    /// Note a line of input, returning the `read()` it's for, if one is waiting.
+    pub(crate) fn input(&self) -> Option<Uuid> {
+        *self.last_input.lock().unwrap() = Instant::now();
+        self.input_request.lock().unwrap().take()
+    }
+
+    /// Send the next line of input to the given request, rather than as a command.
+    pub(crate) fn await_input(&self, input_request_id: Uuid) {
+        *self.input_request.lock().unwrap() = Some(input_request_id);
+    }
+
+    pub(crate) fn quitting(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    fn is_player(&self, player: &Obj) -> bool {
+        *self.player.lock().unwrap() == *player
+    }
+
+    fn render(&self, event: &NarrativeEvent) {
+        let mut stdout = std::io::stdout().lock();
+        render_event(&mut stdout, event);
+        let _ = stdout.flush();
+    }
+}
+
+const DISCONNECTED: &str = "** Disconnected **";
+
+fn shutdown_message(msg: Option<&str>) -> String {
+    match msg {
+        Some(msg) => format!("*** Shutting down: {msg} ***"),
+        None => "*** Shutting down ***".to_string(),
+    }
+}
+
+fn render_event(out: &mut impl Write, event: &NarrativeEvent) {
+    match event.event() {
+        Event::Notify(msg, content_type) => {
+            render_notify(out, &msg, content_type);
+        }
+        Event::Present(presentation) => {
+            let _ = writeln!(
+                out,
+                "--- {} ({}) ---\n{}\n---",
+                presentation.id, presentation.target, presentation.content
+            );
+        }
+        // Presentations aren't kept on screen, so there's nothing to take down.
+        Event::Unpresent(_) => {}
+    }
+}
+
+fn render_notify(out: &mut impl Write, msg: &Var, content_type: Option<Symbol>) {
+    let content_type = content_type.as_ref().map(|ct| ct.as_str());
+    if content_type == Some(CONTENT_TYPE_GMCP) {
+        return;
+    }
+    match msg.variant() {
+        Variant::Str(text) if content_type == Some(CONTENT_TYPE_PROMPT) => {
+            let _ = write!(out, "{}", text.as_string());
+        }
+        Variant::Str(text) if content_type == Some(CONTENT_TYPE_OUT_OF_BAND) => {
+            let _ = writeln!(out, "{}", text.as_string());
+        }
+        Variant::Str(text) => {
+            let _ = writeln!(out, "{}", format_line(text.as_string(), content_type));
+        }
+        Variant::List(lines) => {
+            for line in lines.iter() {
+                let Variant::Str(line) = line.variant() else {
+                    continue;
+                };
+                let _ = writeln!(out, "{}", format_line(line.as_string(), content_type));
+            }
+        }
+        _ => {
+            let _ = writeln!(out, "{}", to_literal(msg));
+        }
+    }
+}
+
+fn format_line(line: &str, content_type: Option<&str>) -> String {
+    match content_type {
+        Some(CONTENT_TYPE_MARKDOWN) => MadSkin::default_dark().inline(line).to_string(),
+        _ => line.to_string(),
+    }
+}
+
+/// A task's view of the console. Output is held until the task commits, as with the daemon's
+/// sessions.
+pub(crate) struct ConsoleSession {
+    console: Arc<Console>,
+    buffer: Mutex<Vec<NarrativeEvent>>,
+}
+
+impl Session for ConsoleSession {
+    fn commit(&self) -> Result<(), SessionError> {
+        for event in self.buffer.lock().unwrap().drain(..) {
+            self.console.render(&event);
+        }
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<(), SessionError> {
+        self.buffer.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn fork(self: Arc<Self>) -> Result<Arc<dyn Session>, SessionError> {
+        Ok(self.console.session())
+    }
+
+    fn request_input(&self, _player: Obj, input_request_id: Uuid) -> Result<(), SessionError> {
+        self.console.await_input(input_request_id);
+        Ok(())
+    }
+
+    fn send_event(&self, player: Obj, event: NarrativeEvent) -> Result<(), SessionError> {
+        // Output for anyone else has nowhere to go.
+        if self.console.is_player(&player) {
+            self.buffer.lock().unwrap().push(event);
+        }
+        Ok(())
+    }
+
+    fn send_system_msg(&self, player: Obj, msg: &str) -> Result<(), SessionError> {
+        if self.console.is_player(&player) {
+            println!("{msg}");
+        }
+        Ok(())
+    }
+
+    fn notify_shutdown(&self, msg: Option<String>) -> Result<(), SessionError> {
+        println!("{}", shutdown_message(msg.as_deref()));
+        self.console.quit.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn connection_name(&self, player: Obj) -> Result<String, SessionError> {
+        if !self.console.is_player(&player) {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        }
+        Ok("console".to_string())
+    }
+
+    fn connection_name_lookup(&self, player: Obj, _rewrite: bool) -> Result<String, SessionError> {
+        self.connection_name(player)
+    }
+
+    fn connection_attributes(&self, player: Obj) -> Result<Var, SessionError> {
+        if !self.console.is_player(&player) {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        }
+        Ok(v_empty_map())
+    }
+
+    fn set_connection_option(
+        &self,
+        _player: Obj,
+        option: Symbol,
+        _value: Var,
+    ) -> Result<(), SessionError> {
+        // Console output isn't buffered, so there's nothing to set.
+        Err(SessionError::InvalidConnectionOption(option.to_string()))
+    }
+
+    fn connection_options(&self, player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError> {
+        if !self.console.is_player(&player) {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        }
+        Ok(vec![])
+    }
+
+    fn disconnect(&self, player: Obj) -> Result<(), SessionError> {
+        if self.console.is_player(&player) {
+            println!("{DISCONNECTED}");
+            self.console.quit.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn connected_players(&self) -> Result<Vec<Obj>, SessionError> {
+        Ok(vec![self.console.player()])
+    }
+
+    fn connected_seconds(&self, player: Obj) -> Result<f64, SessionError> {
+        if !self.console.is_player(&player) {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        }
+        Ok(self.console.connected_at.elapsed().as_secs_f64())
+    }
+
+    fn idle_seconds(&self, player: Obj) -> Result<f64, SessionError> {
+        if !self.console.is_player(&player) {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        }
+        Ok(self
+            .console
+            .last_input
+            .lock()
+            .unwrap()
+            .elapsed()
+            .as_secs_f64())
+    }
+}
+
+/// Sessions for tasks resumed or started in the background, which all belong to the console.
+pub(crate) struct ConsoleSessionFactory {
+    pub(crate) console: Arc<Console>,
+}
+
+impl SessionFactory for ConsoleSessionFactory {
+    fn mk_background_session(
+        self: Arc<Self>,
+        _player: &Obj,
+    ) -> Result<Arc<dyn Session>, SessionError> {
+        Ok(self.console.session())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moor_values::tasks::Presentation;
+    use moor_values::{v_int, v_list, v_obj, v_str, SYSTEM_OBJECT};
+
+    fn rendered(value: Var, content_type: Option<&str>) -> String {
+        let event =
+            NarrativeEvent::notify(v_obj(SYSTEM_OBJECT), value, content_type.map(Symbol::mk));
+        let mut out = vec![];
+        render_event(&mut out, &event);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_render_line() {
+        assert_eq!(rendered(v_str("Hello."), None), "Hello.\n");
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        assert_eq!(rendered(v_str("> "), Some(CONTENT_TYPE_PROMPT)), "> ");
+    }
+
+    #[test]
+    fn test_render_out_of_band() {
+        assert_eq!(
+            rendered(
+                v_str("#$# edit name: #1.foo"),
+                Some(CONTENT_TYPE_OUT_OF_BAND)
+            ),
+            "#$# edit name: #1.foo\n"
+        );
+    }
+
+    #[test]
+    fn test_render_gmcp_suppressed() {
+        assert_eq!(
+            rendered(v_str("Core.Hello {}"), Some(CONTENT_TYPE_GMCP)),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_render_list() {
+        let lines = v_list(&[v_str("one"), v_int(2), v_str("three")]);
+        assert_eq!(rendered(lines, None), "one\nthree\n");
+    }
+
+    #[test]
+    fn test_render_literal() {
+        assert_eq!(rendered(v_int(42), None), "42\n");
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let out = rendered(v_str("some **bold** text"), Some(CONTENT_TYPE_MARKDOWN));
+        assert!(out.contains("bold"));
+        assert!(!out.contains("**"));
+        assert!(out.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_render_presentation() {
+        let event = NarrativeEvent::present(
+            v_obj(SYSTEM_OBJECT),
+            Presentation {
+                id: "help".to_string(),
+                content_type: "text/plain".to_string(),
+                content: "Some help.".to_string(),
+                target: "window".to_string(),
+                attributes: vec![],
+            },
+        );
+        let mut out = vec![];
+        render_event(&mut out, &event);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "--- help (window) ---\nSome help.\n---\n"
+        );
+
+        let mut out = vec![];
+        render_event(
+            &mut out,
+            &NarrativeEvent::unpresent(v_obj(SYSTEM_OBJECT), "help".to_string()),
+        );
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_message() {
+        assert_eq!(shutdown_message(None), "*** Shutting down ***");
+        assert_eq!(
+            shutdown_message(Some("for maintenance")),
+            "*** Shutting down: for maintenance ***"
+        );
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let console = Console::new(SYSTEM_OBJECT);
+        let session = console.session();
+        assert_eq!(DISCONNECTED, "** Disconnected **");
+        session.disconnect(Obj::mk_id(2)).unwrap();
+        assert!(!console.quitting());
+        session.disconnect(SYSTEM_OBJECT).unwrap();
+        assert!(console.quitting());
+    }
+
+    #[test]
+    fn test_output_held_until_commit() {
+        let console = Console::new(SYSTEM_OBJECT);
+        let session = console.session();
+        let event = NarrativeEvent::notify(v_obj(SYSTEM_OBJECT), v_str("Hello."), None);
+        session.send_event(SYSTEM_OBJECT, event.clone()).unwrap();
+        session.send_event(Obj::mk_id(2), event).unwrap();
+        assert_eq!(session.buffer.lock().unwrap().len(), 1);
+        session.rollback().unwrap();
+        assert!(session.buffer.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Single-user local play: the database and scheduler run in this process, and the terminal is
+//! the only connection, with no daemon, hosts or RPC in between. Handy for trying out a core
+//! offline.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use clap_derive::Parser;
+use eyre::{bail, Report};
+use moor_db::{Database, TxDB};
+use moor_kernel::config::Config;
+use moor_kernel::tasks::scheduler::Scheduler;
+use moor_kernel::tasks::sessions::NoopSystemControl;
+use moor_kernel::tasks::{NoopTasksDb, TaskHandle, TaskResult};
+use moor_kernel::textdump::textdump_load;
+use moor_kernel::SchedulerClient;
+use moor_values::model::ObjectRef;
+use moor_values::tasks::{AbortLimitReason, CommandError, SchedulerError};
+use moor_values::util::parse_into_words;
+use moor_values::{v_obj, v_str, List, Obj, Symbol, Var, Variant, SYSTEM_OBJECT};
+use tracing::{error, info};
+
+use crate::console::{Console, ConsoleSessionFactory};
+
+mod console;
+
+pub const MOOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The connection object used before login. Connection objects count down from here in the
+/// daemon, too, clear of #-1 and #-2.
+const CONNECTION: Obj = Obj::mk_id(-3);
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(
+        long,
+        value_name = "db",
+        help = "Path to the database to use or create",
+        default_value = "console.db"
+    )]
+    db: PathBuf,
+
+    #[arg(
+        long,
+        value_name = "textdump",
+        help = "Textdump to import, if the database is being created"
+    )]
+    textdump: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "player",
+        help = "Object number of the player to play as, skipping the core's login"
+    )]
+    player: Option<i32>,
+
+    #[arg(long, help = "Enable debug logging", default_value = "false")]
+    debug: bool,
+}
+
+fn main() -> Result<(), Report> {
+    color_eyre::install()?;
+
+    let args: Args = Args::parse();
+
+    // Logging goes to stderr, and only warnings by default, so as not to get in the way of play.
+    let main_subscriber = tracing_subscriber::fmt()
+        .compact()
+        .with_writer(std::io::stderr)
+        .with_max_level(if args.debug {
+            tracing::Level::DEBUG
+        } else {
+            tracing::Level::WARN
+        })
+        .finish();
+    tracing::subscriber::set_global_default(main_subscriber)
+        .expect("Unable to set configure logging");
+
+    let version = semver::Version::parse(MOOR_VERSION).expect("Invalid moor version");
+    let config = Arc::new(Config::default());
+
+    let (database, freshly_made) = TxDB::open(Some(&args.db), config.database_config.clone());
+    let database = Box::new(database);
+    if let Some(textdump) = &args.textdump {
+        if freshly_made {
+            info!("Loading textdump from {:?}", textdump);
+            let mut loader_interface = database
+                .loader_client()
+                .expect("Unable to get loader interface from database");
+            textdump_load(
+                loader_interface.as_mut(),
+                textdump.clone(),
+                version.clone(),
+                config.features_config.clone(),
+                config.textdump_config.import_threads,
+            )
+            .expect("Unable to load textdump");
+            loader_interface
+                .commit()
+                .expect("Failure to commit loaded database...");
+        } else {
+            info!("Database already exists, skipping textdump import");
+        }
+    }
+
+    let scheduler = Scheduler::new(
+        version,
+        database,
+        Box::new(NoopTasksDb {}),
+        config,
+        Arc::new(NoopSystemControl::default()),
+    );
+    let scheduler_client = scheduler.client().expect("Failed to get scheduler client");
+    let console = Console::new(CONNECTION);
+    let session_factory = Arc::new(ConsoleSessionFactory {
+        console: console.clone(),
+    });
+    let scheduler_loop_jh = std::thread::Builder::new()
+        .name("moor-scheduler".to_string())
+        .spawn(move || scheduler.run(session_factory))?;
+
+    // Lines are read on their own thread, so that a shutdown or disconnect from inside the world
+    // is noticed while we're waiting for input.
+    let (line_send, lines) = std::sync::mpsc::channel();
+    std::thread::Builder::new()
+        .name("moor-console-input".to_string())
+        .spawn(move || {
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line_send.send(line).is_err() {
+                    break;
+                }
+            }
+        })?;
+
+    let result = play(&args, &console, &scheduler_client, &lines);
+
+    // The scheduler will already be gone if the world shut itself down.
+    if let Err(e) = scheduler_client.submit_shutdown("Console closed") {
+        info!(error = ?e, "Scheduler already shut down");
+    }
+    scheduler_loop_jh
+        .join()
+        .expect("Failed to join() scheduler");
+    result
+}
+
+/// Log in, then pass each line to the world until input ends or the world lets us go.
+fn play(
+    args: &Args,
+    console: &Arc<Console>,
+    scheduler_client: &SchedulerClient,
+    lines: &Receiver<String>,
+) -> Result<(), Report> {
+    let player = match args.player {
+        Some(player) => Obj::mk_id(player),
+        None => {
+            // As with the network hosts, a login command with no words shows the welcome.
+            login(console, scheduler_client, vec![])?;
+            loop {
+                let Some(line) = next_line(console, lines) else {
+                    return Ok(());
+                };
+                console.input();
+                if let Some(player) = login(console, scheduler_client, parse_into_words(&line))? {
+                    break player;
+                }
+            }
+        }
+    };
+    console.set_player(player.clone());
+    println!("*** Connected ***");
+    let connected = scheduler_client.submit_verb_task(
+        &player,
+        &ObjectRef::Id(SYSTEM_OBJECT),
+        Symbol::mk("user_connected"),
+        List::mk_list(&[v_obj(player.clone())]),
+        "".to_string(),
+        &SYSTEM_OBJECT,
+        console.session(),
+    );
+    if let Err(e) = connected {
+        error!(error = ?e, "Error submitting user_connected task");
+    }
+
+    while let Some(line) = next_line(console, lines) {
+        if let Some(input_request_id) = console.input() {
+            match scheduler_client.submit_requested_input(&player, input_request_id, line) {
+                // A read_lines() wants the next line too.
+                Ok(true) => console.await_input(input_request_id),
+                Ok(false) => {}
+                Err(e) => error!(error = ?e, "Unable to send input to task"),
+            }
+            continue;
+        }
+        match scheduler_client.submit_command_task(
+            &SYSTEM_OBJECT,
+            &player,
+            &line,
+            console.session(),
+        ) {
+            // The command may well read() input, so its result is waited for separately.
+            Ok(task_handle) => {
+                std::thread::spawn(move || report_errors(task_handle));
+            }
+            Err(e) => report_error(e),
+        }
+    }
+    Ok(())
+}
+
+/// The next line of input, or None once input has ended or the world has shut down or
+/// disconnected us.
+fn next_line(console: &Console, lines: &Receiver<String>) -> Option<String> {
+    loop {
+        if console.quitting() {
+            return None;
+        }
+        match lines.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => return Some(line),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+/// Run the core's `do_login_command` with `words`, returning the player if it logged us in.
+fn login(
+    console: &Arc<Console>,
+    scheduler_client: &SchedulerClient,
+    words: Vec<String>,
+) -> Result<Option<Obj>, Report> {
+    let task_handle = scheduler_client.submit_verb_task(
+        &CONNECTION,
+        &ObjectRef::Id(SYSTEM_OBJECT),
+        Symbol::mk("do_login_command"),
+        words.iter().map(|s| v_str(s)).collect(),
+        words.join(" "),
+        &SYSTEM_OBJECT,
+        console.session(),
+    )?;
+    match wait_for(task_handle) {
+        Ok(v) => match v.variant() {
+            Variant::Obj(player) => Ok(Some(player.clone())),
+            _ => Ok(None),
+        },
+        Err(SchedulerError::TaskAbortedCancelled) => Ok(None),
+        Err(e) => bail!("Login failed: {e:?}"),
+    }
+}
+
+fn wait_for(mut task_handle: TaskHandle) -> Result<Var, SchedulerError> {
+    loop {
+        match task_handle.into_receiver().recv() {
+            Ok(Ok(TaskResult::Restarted(th))) => task_handle = th,
+            Ok(Ok(TaskResult::Result(v))) => return Ok(v),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(SchedulerError::SchedulerNotResponding),
+        }
+    }
+}
+
+fn report_errors(task_handle: TaskHandle) {
+    if let Err(e) = wait_for(task_handle) {
+        report_error(e);
+    }
+}
+
+/// Tell the player about a command which failed, as the telnet host would.
+fn report_error(e: SchedulerError) {
+    let msg = match e {
+        SchedulerError::CommandExecutionError(CommandError::CouldNotParseCommand)
+        | SchedulerError::CommandExecutionError(CommandError::NoCommandMatch) => {
+            "I couldn't understand that.".to_string()
+        }
+        SchedulerError::CommandExecutionError(CommandError::NoObjectMatch) => {
+            "I don't see that here.".to_string()
+        }
+        SchedulerError::CommandExecutionError(CommandError::PermissionDenied) => {
+            "You can't do that.".to_string()
+        }
+        SchedulerError::TaskAbortedLimit(AbortLimitReason::Ticks(_)) => {
+            "Task ran out of ticks".to_string()
+        }
+        SchedulerError::TaskAbortedLimit(AbortLimitReason::Time(_)) => {
+            "Task ran out of seconds".to_string()
+        }
+        SchedulerError::TaskAbortedError => "Task aborted".to_string(),
+        SchedulerError::TaskAbortedException(e) => format!("Task exception: {}", e),
+        SchedulerError::TaskAbortedCancelled => "Task cancelled".to_string(),
+        e => {
+            error!(error = ?e, "Unhandled task error");
+            return;
+        }
+    };
+    println!("{msg}");
+}
@@ -13,13 +13,13 @@
 
 use clap::builder::ValueHint;
 use clap_derive::Parser;
-use moor_db::DatabaseConfig;
-use moor_kernel::config::{Config, FeaturesConfig, TextdumpConfig};
+use moor_db::{DatabaseConfig, ObjectIdAllocation};
+use moor_kernel::config::{Config, FeaturesConfig, SchedulerConfig, TextdumpConfig};
 use moor_kernel::textdump::EncodingMode;
 use std::path::PathBuf;
 use std::time::Duration;
 
-#[derive(Parser, Debug)] // requires `derive` feature
+#[derive(Clone, Parser, Debug)] // requires `derive` feature
 pub struct Args {
     #[command(flatten)]
     pub db_args: DatabaseArgs,
@@ -30,6 +30,9 @@ pub struct Args {
     #[command(flatten)]
     feature_args: Option<FeatureArgs>,
 
+    #[command(flatten)]
+    scheduler_args: Option<SchedulerArgs>,
+
     #[arg(
         long,
         value_name = "write-merged-config",
@@ -41,8 +44,10 @@ pub struct Args {
     #[arg(
         long,
         value_name = "config",
-        help = "Path to configuration (json) file to use, if any. If not specified, defaults are used.\
-                Configuration file values can be overridden by command line arguments.",
+        help = "Path to configuration file to use, if any: TOML, or JSON if its name ends in .json. If not specified, \
+                defaults are used. Values in the file can be overridden by MOOR__ environment variables \
+                (e.g. MOOR__SCHEDULER_CONFIG__MAX_RUNNING_TASKS=32), and those by command line arguments. \
+                Sending the daemon SIGHUP re-reads it, and applies the settings which can change while running.",
         value_hint = ValueHint::FilePath
     )]
     pub config_file: Option<PathBuf>,
@@ -83,6 +88,21 @@ pub struct Args {
     )]
     pub events_listen: String,
 
+    #[arg(
+        long,
+        value_name = "metrics-listen",
+        help = "If set, serve Prometheus metrics over HTTP at this address (e.g. 127.0.0.1:9090)"
+    )]
+    pub metrics_listen: Option<String>,
+
+    #[cfg(feature = "otel")]
+    #[arg(
+        long,
+        value_name = "otlp-endpoint",
+        help = "If set, export tracing spans to this OTLP/HTTP collector (e.g. http://localhost:4318/v1/traces)"
+    )]
+    pub otlp_endpoint: Option<String>,
+
     #[arg(
         long,
         value_name = "public_key",
@@ -99,6 +119,35 @@ pub struct Args {
     )]
     pub private_key: PathBuf,
 
+    #[arg(
+        long,
+        value_name = "auth-token-ttl",
+        help = "If set, auth tokens can only be used to start new sessions for this many seconds after they're issued, \
+                unless refreshed. By default they don't expire"
+    )]
+    pub auth_token_ttl: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "world-name",
+        help = "The name this world goes by to the other worlds it federates with (experimental)"
+    )]
+    pub world_name: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "federation-peers",
+        help = "JSON file naming the worlds to federate with, each with its endpoint and the path to its public key"
+    )]
+    pub federation_peers: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "federation-listen",
+        help = "If set, receive messages from federated worlds on this ZMQ endpoint (e.g. tcp://0.0.0.0:7901)"
+    )]
+    pub federation_listen: Option<String>,
+
     #[arg(
         long,
         value_name = "num-io-threads",
@@ -107,11 +156,98 @@ pub struct Args {
     )]
     pub num_io_threads: i32,
 
+    #[arg(
+        long,
+        help = "Report connection names as addresses, without looking up hostnames in DNS",
+        default_value = "false"
+    )]
+    pub numeric_connection_names: bool,
+
+    #[arg(
+        long,
+        value_name = "max-connections",
+        help = "If set, refuse new connections while this many are open, showing them $server_options.server_full_msg"
+    )]
+    pub max_connections: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "max-listener-connections",
+        help = "If set, refuse new connections to a listener while this many are open through it"
+    )]
+    pub max_listener_connections: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "event-flush-window-ms",
+        help = "How long a connection's output may wait, in milliseconds, to be sent to its host along with more. 0 sends \
+                each task's output as soon as it commits",
+        default_value = "5"
+    )]
+    pub event_flush_window_ms: u64,
+
+    #[arg(
+        long,
+        value_name = "event-max-batch",
+        help = "The most lines of output sent to a connection's host in one message",
+        default_value = "64"
+    )]
+    pub event_max_batch: usize,
+
+    #[arg(
+        long,
+        value_name = "standby-ship-to",
+        help = "Copy each textdump checkpoint, once written, to this directory or http(s) URL (e.g. an S3-compatible \
+                bucket which accepts unsigned PUTs), with a manifest of checksums, for a warm standby to be restored from"
+    )]
+    pub standby_ship_to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "standby-retain",
+        help = "How many of the snapshots shipped to the standby location to keep",
+        default_value = "5"
+    )]
+    pub standby_retain: usize,
+
+    #[arg(
+        long,
+        value_name = "standby-interval-seconds",
+        help = "How often to look for a new checkpoint to ship to the standby location",
+        default_value = "10"
+    )]
+    pub standby_interval_seconds: u64,
+
+    #[arg(
+        long,
+        value_name = "restore-standby",
+        help = "Start a new database from the newest snapshot shipped to this directory or http(s) URL, \
+                once it's been checked against the manifest. The database must not already exist"
+    )]
+    pub restore_standby: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "dump-s3-url",
+        help = "Also write each textdump checkpoint to S3-compatible object storage, as a new object under this \
+                https://<endpoint>/<bucket>[/<prefix>] URL. Requests are signed with the credentials in \
+                AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY (and AWS_SESSION_TOKEN, if set)"
+    )]
+    pub dump_s3_url: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "dump-s3-region",
+        help = "The region to sign requests to --dump-s3-url for",
+        default_value = "us-east-1"
+    )]
+    pub dump_s3_region: String,
+
     #[arg(long, help = "Enable debug logging", default_value = "false")]
     pub debug: bool,
 }
 
-#[derive(Parser, Debug)]
+#[derive(Clone, Parser, Debug)]
 pub struct FeatureArgs {
     /// Whether to allow notify() to send arbitrary MOO common to players. The interpretation of
     /// the common varies depending on host/client.
@@ -157,6 +293,75 @@ pub struct FeatureArgs {
                 Note that this is the default behaviour in LambdaMOO."
     )]
     pub persistent_tasks: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Record the nondeterministic inputs (time, random numbers, input) of each task, \
+                so that failing tasks can be replayed deterministically."
+    )]
+    pub record_replay: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Match object names in commands using full Unicode case folding, rather than plain lowercasing."
+    )]
+    pub unicode_matching: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Offer each command to $do_command before the built-in parser, as LambdaMOO does. \
+                Enabled by default."
+    )]
+    pub do_command: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Allow recycle() to destroy objects which have children, reparenting them to the object's parent. \
+                Enabled by default; if disabled, recycling such an object raises E_PERM."
+    )]
+    pub recycle_parents: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Have chparent() drop properties on the object or its descendants whose names clash with ones inherited from the new parent. \
+                Disabled by default, raising E_INVARG as LambdaMOO does."
+    )]
+    pub chparent_clear_conflicts: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Optimize verb programs when compiling them: fold constant expressions, remove branches which can never run, and thread jumps. \
+                Disabled by default."
+    )]
+    pub optimize: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Run cores written for ToastStunt's waifs on flyweights: provide new_waif(), and dispatch w:foo() on a flyweight to its delegate's :foo verb. \
+                Disabled by default."
+    )]
+    pub waif_compat: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Allow `$` inside an index or range to mean the length of the value being indexed, as in LambdaMOO. \
+                Enabled by default."
+    )]
+    pub range_end: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Have a negative integer literal used as an index or range bound count back from the end, so that x[-1] is x[$]. \
+                Disabled by default, raising E_RANGE as LambdaMOO does."
+    )]
+    pub negative_indices: Option<bool>,
+
+    #[arg(
+        long,
+        help = "Round integer division toward negative infinity, and give the result of % the sign of the divisor, so that -7 / 2 is -4 and -7 % 2 is 1. \
+                Disabled by default, truncating toward zero as LambdaMOO does."
+    )]
+    pub floored_division: Option<bool>,
 }
 
 impl FeatureArgs {
@@ -179,9 +384,163 @@ impl FeatureArgs {
         if let Some(args) = self.persistent_tasks {
             config.persistent_tasks = args;
         }
+        if let Some(args) = self.record_replay {
+            config.record_replay = args;
+        }
+        if let Some(args) = self.unicode_matching {
+            config.unicode_matching = args;
+        }
+        if let Some(args) = self.do_command {
+            config.do_command = args;
+        }
+        if let Some(args) = self.recycle_parents {
+            config.recycle_parents = args;
+        }
+        if let Some(args) = self.chparent_clear_conflicts {
+            config.chparent_clear_conflicts = args;
+        }
+        if let Some(args) = self.optimize {
+            config.optimize = args;
+        }
+        if let Some(args) = self.waif_compat {
+            config.waif_compat = args;
+        }
+        if let Some(args) = self.range_end {
+            config.range_end = args;
+        }
+        if let Some(args) = self.negative_indices {
+            config.negative_indices = args;
+        }
+        if let Some(args) = self.floored_division {
+            config.floored_division = args;
+        }
     }
 }
-#[derive(Parser, Debug)]
+#[derive(Clone, Parser, Debug)]
+pub struct SchedulerArgs {
+    #[arg(
+        long,
+        value_name = "max-running-tasks",
+        help = "The most tasks to run at once. Commands and other player-submitted tasks beyond this wait to start, \
+                and are started round-robin across players, so one player flooding commands only delays their own. \
+                If not set, every task starts immediately, as in LambdaMOO."
+    )]
+    pub max_running_tasks: Option<usize>,
+
+    #[arg(
+        long,
+        help = "When tasks are waiting to start, start wizards' tasks ahead of everyone else's"
+    )]
+    pub wizard_priority: Option<bool>,
+
+    #[arg(
+        long,
+        value_name = "overload-ready-tasks",
+        help = "Turn away new commands from non-wizards while this many tasks are waiting to start \
+                (only possible with --max-running-tasks), sending them $server_options.server_too_busy_msg"
+    )]
+    pub overload_ready_tasks: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "overload-commit-latency-ms",
+        help = "Turn away new commands from non-wizards while task commits are taking this many milliseconds \
+                or more on average"
+    )]
+    pub overload_commit_latency_ms: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "finished-task-history",
+        help = "How many recently finished tasks to remember, with their results or errors, for finished_tasks(). \
+                0 turns the history off."
+    )]
+    pub finished_task_history: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "max-input-lines",
+        help = "The most lines read_lines() accepts; beyond this the rest of the input up to the terminator is discarded, \
+                and it returns E_QUOTA"
+    )]
+    pub max_input_lines: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "max-input-bytes",
+        help = "The most bytes of input read_lines() accepts, likewise"
+    )]
+    pub max_input_bytes: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "shutdown-hook-timeout-seconds",
+        help = "How long #0:server_shutdown gets to run when the server shuts down"
+    )]
+    pub shutdown_hook_timeout_seconds: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "shutdown-task-timeout-seconds",
+        help = "How long running tasks get to finish when the server shuts down, before they're aborted (and then again for aborted tasks to stop)"
+    )]
+    pub shutdown_task_timeout_seconds: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "shutdown-checkpoint-timeout-seconds",
+        help = "How long the final textdump gets to be written when the server shuts down"
+    )]
+    pub shutdown_checkpoint_timeout_seconds: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "replay-dir",
+        help = "Where to write the recorded inputs of tasks which fail while --record-replay is on, \
+                one file per task, for replaying them later"
+    )]
+    pub replay_dir: Option<PathBuf>,
+}
+
+impl SchedulerArgs {
+    pub fn merge_config(&self, config: &mut SchedulerConfig) {
+        if let Some(args) = self.max_running_tasks {
+            config.max_running_tasks = Some(args);
+        }
+        if let Some(args) = self.wizard_priority {
+            config.wizard_priority = args;
+        }
+        if let Some(args) = self.overload_ready_tasks {
+            config.overload_ready_tasks = Some(args);
+        }
+        if let Some(args) = self.overload_commit_latency_ms {
+            config.overload_commit_latency = Some(std::time::Duration::from_millis(args));
+        }
+        if let Some(args) = self.finished_task_history {
+            config.finished_task_history = args;
+        }
+        if let Some(args) = self.max_input_lines {
+            config.max_input_lines = args;
+        }
+        if let Some(args) = self.max_input_bytes {
+            config.max_input_bytes = args;
+        }
+        if let Some(args) = self.shutdown_hook_timeout_seconds {
+            config.shutdown_hook_timeout = std::time::Duration::from_secs(args);
+        }
+        if let Some(args) = self.shutdown_task_timeout_seconds {
+            config.shutdown_task_timeout = std::time::Duration::from_secs(args);
+        }
+        if let Some(args) = self.shutdown_checkpoint_timeout_seconds {
+            config.shutdown_checkpoint_timeout = std::time::Duration::from_secs(args);
+        }
+        if let Some(args) = &self.replay_dir {
+            config.replay_dir = Some(args.clone());
+        }
+    }
+}
+
+#[derive(Clone, Parser, Debug)]
 pub struct TextdumpArgs {
     #[arg(short, long, value_name = "textdump", help = "Path to textdump to import", value_hint = ValueHint::FilePath)]
     pub textdump: Option<PathBuf>,
@@ -219,6 +578,13 @@ pub struct TextdumpArgs {
           careful to not lie about the features (and encoding) you support."
     )]
     pub version_override: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "import-threads",
+        help = "Number of threads to use for compiling verbs during textdump import"
+    )]
+    pub import_threads: Option<usize>,
 }
 
 impl TextdumpArgs {
@@ -238,10 +604,13 @@ impl TextdumpArgs {
         if let Some(args) = self.version_override.as_ref() {
             config.version_override = Some(args.clone());
         }
+        if let Some(args) = self.import_threads {
+            config.import_threads = args;
+        }
     }
 }
 
-#[derive(Parser, Debug)]
+#[derive(Clone, Parser, Debug)]
 pub struct DatabaseArgs {
     #[arg(value_name = "db", help = "Path to database file to use or create", value_hint = ValueHint::FilePath)]
     pub db: PathBuf,
@@ -263,6 +632,73 @@ pub struct DatabaseArgs {
           If they are still there, untouched, by the next eviction cycle, they will be removed."
     )]
     pub default_eviction_threshold: Option<usize>,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["start", "end"],
+        help = "Only allocate new object ids within the (inclusive) range start..end. Giving each of several \
+          worlds which share a core a disjoint range means they can later be merged without id collisions.",
+        conflicts_with = "object_id_interleave"
+    )]
+    pub object_id_range: Option<Vec<i32>>,
+
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["offset", "stride"],
+        help = "Only allocate new object ids which are congruent to offset modulo stride. Giving each of up to \
+          `stride` worlds which share a core a distinct offset means they can later be merged without id collisions."
+    )]
+    pub object_id_interleave: Option<Vec<i32>>,
+
+    #[arg(
+        long,
+        help = "Before starting, renumber all objects so that object numbers are contiguous from #0, \
+          fixing up parent/child, location/contents, and ownership references, and reset the max object. \
+          References held in property values and verb code are NOT rewritten.",
+        default_value = "false"
+    )]
+    pub compact: bool,
+
+    #[arg(
+        long = "text-index-property",
+        value_name = "name",
+        help = "Keep values of properties with this name in a full-text index, for search_text(). May be given \
+          more than once. Needs the daemon to be built with the `fts` feature."
+    )]
+    pub text_indexed_properties: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        value_name = "bytes",
+        help = "Keep property values larger than this many bytes in a partition of their own, read only when \
+          they're wanted, so that they don't slow down access to everything else. 0 keeps all values inline."
+    )]
+    pub property_blob_threshold: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Keep a single copy of each distinct verb program, and of each distinct property value kept in \
+          a partition of its own (see --property-blob-threshold), however many verbs or properties have it."
+    )]
+    pub dedup_content: Option<bool>,
+
+    #[arg(
+        long,
+        value_name = "path",
+        help = "If the database was written by an older version of moor and has to be migrated, first copy it \
+          to this path, which must not already exist."
+    )]
+    pub migration_backup: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Report the migrations the database needs to reach the current schema version, without running \
+          them, and exit.",
+        default_value = "false"
+    )]
+    pub migrate_dry_run: bool,
     // TODO: per table options
 }
 
@@ -274,6 +710,30 @@ impl DatabaseArgs {
         if let Some(args) = self.default_eviction_threshold {
             config.default_eviction_threshold = args;
         }
+        if let Some(range) = &self.object_id_range {
+            config.object_id_allocation = ObjectIdAllocation::Range {
+                start: range[0],
+                end: range[1],
+            };
+        }
+        if let Some(interleave) = &self.object_id_interleave {
+            config.object_id_allocation = ObjectIdAllocation::Interleaved {
+                offset: interleave[0],
+                stride: interleave[1],
+            };
+        }
+        if let Some(properties) = &self.text_indexed_properties {
+            config.text_indexed_properties = properties.clone();
+        }
+        if let Some(path) = &self.migration_backup {
+            config.migration_backup_path = Some(path.clone());
+        }
+        if let Some(threshold) = self.property_blob_threshold {
+            config.property_blob_threshold = (threshold > 0).then_some(threshold);
+        }
+        if let Some(dedup_content) = self.dedup_content {
+            config.dedup_content = dedup_content;
+        }
     }
 }
 
@@ -285,6 +745,9 @@ impl Args {
         if let Some(args) = self.feature_args.as_ref() {
             args.merge_config(&mut config.features_config);
         }
+        if let Some(args) = self.scheduler_args.as_ref() {
+            args.merge_config(&mut config.scheduler_config);
+        }
         self.db_args.merge_config(&mut config.database_config);
 
         config
@@ -0,0 +1,269 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The daemon's configuration comes from, in increasing order of precedence: the defaults, the
+//! config file (TOML, or JSON if its name ends in `.json`), `MOOR__` environment variables, and
+//! the command line.
+//!
+//! The file holds the sections of the kernel's `Config`, plus the daemon's `log_level`:
+//!
+//! ```toml
+//! log_level = "info"
+//!
+//! [scheduler_config]
+//! max_running_tasks = 32
+//!
+//! [features_config]
+//! rich_notify = false
+//! ```
+//!
+//! Environment variables name a setting by its path, upper-cased, with `__` between the parts:
+//! `MOOR__SCHEDULER_CONFIG__MAX_RUNNING_TASKS=32`, or `MOOR__LOG_LEVEL=debug`. Values are read as
+//! JSON where they can be, and as strings where they can't.
+//!
+//! On SIGHUP everything is read again, and the settings which can change while the daemon runs
+//! are applied: the log level, the scheduler's limits, and the features which only affect how
+//! tasks run. Changes to anything else are reported, and wait for a restart.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use eyre::{bail, eyre, Report};
+use moor_kernel::config::Config;
+use serde_json::{Map, Value};
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+
+use crate::args::Args;
+
+/// The prefix of environment variables which override the config file.
+const ENV_PREFIX: &str = "MOOR__";
+
+#[derive(Clone, Debug)]
+pub struct DaemonConfig {
+    pub config: Config,
+    /// If set, the level to log at, rather than the one from the command line.
+    pub log_level: Option<LevelFilter>,
+}
+
+/// Read the config file named on the command line, if any, and apply the environment and the
+/// command line to it.
+pub fn load_config(args: &Args) -> Result<DaemonConfig, Report> {
+    let file = match &args.config_file {
+        Some(path) => read_file(path)?,
+        None => Map::new(),
+    };
+    make_config(file, std::env::vars(), args)
+}
+
+fn read_file(path: &Path) -> Result<Map<String, Value>, Report> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Unable to read config file {path:?}: {e}"))?;
+    let value: Value = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|e| eyre!("Unable to parse {path:?}: {e}"))?
+    } else {
+        toml::from_str(&text).map_err(|e| eyre!("Unable to parse {path:?}: {e}"))?
+    };
+    let Value::Object(file) = value else {
+        bail!("Expected {path:?} to hold a table of settings");
+    };
+    Ok(file)
+}
+
+fn make_config(
+    mut file: Map<String, Value>,
+    env: impl Iterator<Item = (String, String)>,
+    args: &Args,
+) -> Result<DaemonConfig, Report> {
+    for (name, value) in env {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        set_path(&mut file, &name, path, &value)?;
+    }
+    let log_level = match file.remove("log_level") {
+        None => None,
+        Some(Value::String(level)) => {
+            Some(LevelFilter::from_str(&level).map_err(|_| eyre!("Unknown log_level {level:?}"))?)
+        }
+        Some(level) => bail!("Expected log_level to be a string, not {level}"),
+    };
+    let config: Config = serde_json::from_value(Value::Object(file))
+        .map_err(|e| eyre!("Invalid configuration: {e}"))?;
+    let config = args.merge_config(config);
+    validate(&config)?;
+    Ok(DaemonConfig { config, log_level })
+}
+
+/// Set the setting at `path` (e.g. `SCHEDULER_CONFIG__MAX_RUNNING_TASKS`) from the environment
+/// variable `name`.
+fn set_path(
+    file: &mut Map<String, Value>,
+    name: &str,
+    path: &str,
+    value: &str,
+) -> Result<(), Report> {
+    let keys: Vec<_> = path.split("__").map(|k| k.to_lowercase()).collect();
+    let (last, sections) = keys.split_last().expect("split always yields one part");
+    let mut table = file;
+    for key in sections {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(section) = entry else {
+            bail!("{name} names a setting inside {key}, which isn't a section");
+        };
+        table = section;
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+/// Catch settings which would parse, but which make no sense.
+fn validate(config: &Config) -> Result<(), Report> {
+    if config.scheduler_config.max_running_tasks == Some(0) {
+        bail!("scheduler_config.max_running_tasks must be at least 1, if set");
+    }
+    if config.scheduler_config.overload_ready_tasks == Some(0) {
+        bail!("scheduler_config.overload_ready_tasks must be at least 1, if set");
+    }
+    if config.textdump_config.import_threads == 0 {
+        bail!("textdump_config.import_threads must be at least 1");
+    }
+    if config
+        .textdump_config
+        .checkpoint_interval
+        .is_some_and(|i| i.is_zero())
+    {
+        bail!("textdump_config.checkpoint_interval must be more than zero, if set");
+    }
+    Ok(())
+}
+
+/// `current`, with the settings from `reloaded` which can change while the daemon runs.
+pub fn reloadable(current: &Config, reloaded: &Config) -> Config {
+    let mut config = current.clone();
+    config.scheduler_config = reloaded.scheduler_config.clone();
+    let features = &mut config.features_config;
+    features.rich_notify = reloaded.features_config.rich_notify;
+    features.record_replay = reloaded.features_config.record_replay;
+    features.unicode_matching = reloaded.features_config.unicode_matching;
+    features.do_command = reloaded.features_config.do_command;
+    features.recycle_parents = reloaded.features_config.recycle_parents;
+    features.chparent_clear_conflicts = reloaded.features_config.chparent_clear_conflicts;
+    features.waif_compat = reloaded.features_config.waif_compat;
+
+    let applied = serde_json::to_value(&config).expect("Config is serializable");
+    let wanted = serde_json::to_value(reloaded).expect("Config is serializable");
+    if applied != wanted {
+        warn!("Some changed settings can't be applied while running, and will wait for a restart");
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use serde_json::{json, Map, Value};
+    use tracing::level_filters::LevelFilter;
+
+    use crate::args::Args;
+    use crate::config_file::{make_config, reloadable};
+
+    fn args(extra: &[&str]) -> Args {
+        let mut argv = vec!["moor-daemon", "test.db"];
+        argv.extend(extra);
+        Args::parse_from(argv)
+    }
+
+    fn file(value: Value) -> Map<String, Value> {
+        let Value::Object(file) = value else {
+            panic!("not an object");
+        };
+        file
+    }
+
+    #[test]
+    fn test_partial_toml() {
+        let file: Map<String, Value> = toml::from_str(
+            r#"
+            log_level = "debug"
+
+            [scheduler_config]
+            max_running_tasks = 8
+
+            [features_config]
+            rich_notify = false
+            "#,
+        )
+        .unwrap();
+        let config = make_config(file, std::iter::empty(), &args(&[])).unwrap();
+        assert_eq!(config.log_level, Some(LevelFilter::DEBUG));
+        assert_eq!(config.config.scheduler_config.max_running_tasks, Some(8));
+        assert!(!config.config.features_config.rich_notify);
+        // Everything else keeps its default.
+        assert!(config.config.features_config.map_type);
+    }
+
+    #[test]
+    fn test_precedence() {
+        let file = file(json!({"scheduler_config": {"max_running_tasks": 8}}));
+        let env = vec![
+            (
+                "MOOR__SCHEDULER_CONFIG__MAX_RUNNING_TASKS".to_string(),
+                "16".to_string(),
+            ),
+            (
+                "MOOR__FEATURES_CONFIG__DO_COMMAND".to_string(),
+                "false".to_string(),
+            ),
+            ("MOOR_UNRELATED".to_string(), "1".to_string()),
+        ];
+        let config = make_config(file.clone(), env.clone().into_iter(), &args(&[])).unwrap();
+        assert_eq!(config.config.scheduler_config.max_running_tasks, Some(16));
+        assert!(!config.config.features_config.do_command);
+
+        let config =
+            make_config(file, env.into_iter(), &args(&["--max-running-tasks", "32"])).unwrap();
+        assert_eq!(config.config.scheduler_config.max_running_tasks, Some(32));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let zero = file(json!({"scheduler_config": {"max_running_tasks": 0}}));
+        assert!(make_config(zero, std::iter::empty(), &args(&[])).is_err());
+
+        let mistyped = file(json!({"features_config": {"rich_notify": "yes"}}));
+        assert!(make_config(mistyped, std::iter::empty(), &args(&[])).is_err());
+
+        let level = file(json!({"log_level": "loud"}));
+        assert!(make_config(level, std::iter::empty(), &args(&[])).is_err());
+    }
+
+    #[test]
+    fn test_reloadable() {
+        let current = make_config(Map::new(), std::iter::empty(), &args(&[]))
+            .unwrap()
+            .config;
+        let mut reloaded = current.clone();
+        reloaded.scheduler_config.max_running_tasks = Some(4);
+        reloaded.features_config.rich_notify = false;
+        reloaded.features_config.map_type = false;
+        let applied = reloadable(&current, &reloaded);
+        assert_eq!(applied.scheduler_config.max_running_tasks, Some(4));
+        assert!(!applied.features_config.rich_notify);
+        // Whether maps can be compiled isn't something to change under running code.
+        assert!(applied.features_config.map_type);
+    }
+}
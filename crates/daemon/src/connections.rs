@@ -15,8 +15,9 @@ use std::time::{Duration, SystemTime};
 
 use uuid::Uuid;
 
-use moor_kernel::tasks::sessions::SessionError;
-use moor_values::Obj;
+use crate::webhooks::Webhook;
+use moor_kernel::tasks::sessions::{SessionError, WebhookFilter};
+use moor_values::{Obj, Symbol, Var};
 use rpc_common::RpcMessageError;
 
 pub const CONNECTION_TIMEOUT_DURATION: Duration = Duration::from_secs(30);
@@ -51,6 +52,29 @@ pub trait ConnectionsDB {
 
     fn connection_name_for(&self, player: Obj) -> Result<String, SessionError>;
 
+    /// Set (or with `None`, clear) an attribute the host reports for the given client, e.g. a
+    /// negotiated client capability.
+    fn set_client_attribute(
+        &self,
+        client_id: Uuid,
+        key: Symbol,
+        value: Option<Var>,
+    ) -> Result<(), RpcMessageError>;
+
+    /// The attributes of the player's first connection.
+    fn client_attributes_for(&self, player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError>;
+
+    /// Record a connection option (`set_connection_option()`) set for the given client.
+    fn set_client_option(
+        &self,
+        client_id: Uuid,
+        key: Symbol,
+        value: Var,
+    ) -> Result<(), SessionError>;
+
+    /// The connection options which have been set for the given client.
+    fn client_options(&self, client_id: Uuid) -> Vec<(Symbol, Var)>;
+
     fn connected_seconds_for(&self, player: Obj) -> Result<f64, SessionError>;
 
     fn client_ids_for(&self, player: Obj) -> Result<Vec<Uuid>, SessionError>;
@@ -63,4 +87,33 @@ pub trait ConnectionsDB {
 
     /// Remove the given client from the connection database.
     fn remove_client_connection(&self, client_id: Uuid) -> Result<(), eyre::Error>;
+
+    /// Add a site to the ban list, returning false if it was already there.
+    fn ban_site(&self, site: &str) -> Result<bool, eyre::Error>;
+
+    /// Remove a site from the ban list, returning false if it wasn't there.
+    fn unban_site(&self, site: &str) -> Result<bool, eyre::Error>;
+
+    /// The ban list, in order.
+    fn banned_sites(&self) -> Vec<String>;
+
+    /// Record that auth tokens issued to `player` at or before `before` are no longer valid.
+    fn revoke_auth_tokens(&self, player: &Obj, before: SystemTime) -> Result<(), eyre::Error>;
+
+    /// When `player`'s auth tokens were last revoked, if ever.
+    fn auth_tokens_revoked_before(&self, player: &Obj) -> Option<SystemTime>;
+
+    /// Add a webhook, returning its id. Ids aren't reused.
+    fn add_webhook(
+        &self,
+        url: &str,
+        secret: Option<String>,
+        filter: WebhookFilter,
+    ) -> Result<u64, eyre::Error>;
+
+    /// Remove a webhook, returning false if it wasn't there.
+    fn remove_webhook(&self, id: u64) -> Result<bool, eyre::Error>;
+
+    /// The webhooks, in order of id.
+    fn webhooks(&self) -> Vec<Webhook>;
 }
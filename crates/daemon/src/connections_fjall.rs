@@ -12,14 +12,15 @@
 //
 
 use crate::connections::{ConnectionsDB, CONNECTION_TIMEOUT_DURATION};
+use crate::webhooks::Webhook;
 use bincode::{Decode, Encode};
 use bytes::Bytes;
 use eyre::{bail, Error};
 use fjall::{Config, Keyspace, PartitionCreateOptions, PartitionHandle};
-use moor_kernel::tasks::sessions::SessionError;
-use moor_values::{AsByteBuffer, Obj, BINCODE_CONFIG};
+use moor_kernel::tasks::sessions::{SessionError, WebhookFilter};
+use moor_values::{AsByteBuffer, Obj, Symbol, Var, BINCODE_CONFIG};
 use rpc_common::RpcMessageError;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
@@ -40,6 +41,45 @@ struct ConnectionsRecords {
     connections: Vec<ConnectionRecord>,
 }
 
+#[derive(Debug, Clone, Encode, Decode)]
+struct WebhookRecord {
+    url: String,
+    secret: Option<String>,
+    object: Option<Obj>,
+    verb: Option<Symbol>,
+    property: Option<Symbol>,
+    events: Vec<String>,
+}
+
+impl From<&Webhook> for WebhookRecord {
+    fn from(webhook: &Webhook) -> Self {
+        Self {
+            url: webhook.url.clone(),
+            secret: webhook.secret.clone(),
+            object: webhook.filter.object.clone(),
+            verb: webhook.filter.verb,
+            property: webhook.filter.property,
+            events: webhook.filter.events.clone(),
+        }
+    }
+}
+
+impl WebhookRecord {
+    fn into_webhook(self, id: u64) -> Webhook {
+        Webhook {
+            id,
+            url: self.url,
+            secret: self.secret,
+            filter: WebhookFilter {
+                object: self.object,
+                verb: self.verb,
+                property: self.property,
+                events: self.events,
+            },
+        }
+    }
+}
+
 pub struct ConnectionsFjall {
     inner: Arc<Mutex<Inner>>,
 }
@@ -56,6 +96,25 @@ struct Inner {
 
     client_players: HashMap<Uuid, Obj>,
     player_clients: HashMap<Obj, ConnectionsRecords>,
+
+    /// Attributes reported by hosts, per client. These only mean anything for a live connection,
+    /// so unlike the rest they aren't persisted.
+    client_attributes: HashMap<Uuid, HashMap<Symbol, Var>>,
+    /// Connection options set per client, likewise.
+    client_options: HashMap<Uuid, HashMap<Symbol, Var>>,
+
+    /// Sites logins are refused from, keyed by the site itself.
+    banned_sites_table: PartitionHandle,
+    banned_sites: BTreeSet<String>,
+
+    /// For each player whose auth tokens have been revoked, when.
+    token_revocations_table: PartitionHandle,
+    token_revocations: HashMap<Obj, SystemTime>,
+
+    /// Webhooks, keyed by id (big-endian, so they iterate in order).
+    webhooks_table: PartitionHandle,
+    webhooks: BTreeMap<u64, Webhook>,
+    webhook_id_sequence: u64,
 }
 
 impl ConnectionsFjall {
@@ -83,11 +142,27 @@ impl ConnectionsFjall {
             .open_partition("player_clients", PartitionCreateOptions::default())
             .unwrap();
 
+        let banned_sites_table = keyspace
+            .open_partition("banned_sites", PartitionCreateOptions::default())
+            .unwrap();
+
+        let token_revocations_table = keyspace
+            .open_partition("token_revocations", PartitionCreateOptions::default())
+            .unwrap();
+
+        let webhooks_table = keyspace
+            .open_partition("webhooks", PartitionCreateOptions::default())
+            .unwrap();
+
         // Fill in the connection_id_sequence.
         let connection_id_sequence = match sequences_partition.get("connection_id_sequence") {
             Ok(Some(bytes)) => i32::from_le_bytes(bytes[0..size_of::<i32>()].try_into().unwrap()),
             _ => -3,
         };
+        let webhook_id_sequence = match sequences_partition.get("webhook_id_sequence") {
+            Ok(Some(bytes)) => u64::from_le_bytes(bytes[0..size_of::<u64>()].try_into().unwrap()),
+            _ => 1,
+        };
 
         // Fill in all the caches.
         let mut client_players = HashMap::new();
@@ -107,6 +182,25 @@ impl ConnectionsFjall {
                 bincode::decode_from_slice(&value, *BINCODE_CONFIG).unwrap();
             player_clients.insert(oid, connections_record);
         }
+        let banned_sites = banned_sites_table
+            .keys()
+            .map(|key| String::from_utf8(key.unwrap().to_vec()).unwrap())
+            .collect();
+        let mut token_revocations = HashMap::new();
+        for entry in token_revocations_table.iter() {
+            let (key, value) = entry.unwrap();
+            let oid = Obj::from_bytes(Bytes::from(key)).unwrap();
+            let (revoked_before, _) = bincode::decode_from_slice(&value, *BINCODE_CONFIG).unwrap();
+            token_revocations.insert(oid, revoked_before);
+        }
+        let mut webhooks = BTreeMap::new();
+        for entry in webhooks_table.iter() {
+            let (key, value) = entry.unwrap();
+            let id = u64::from_be_bytes(key[0..size_of::<u64>()].try_into().unwrap());
+            let (record, _): (WebhookRecord, _) =
+                bincode::decode_from_slice(&value, *BINCODE_CONFIG).unwrap();
+            webhooks.insert(id, record.into_webhook(id));
+        }
 
         Self {
             inner: Arc::new(Mutex::new(Inner {
@@ -118,6 +212,15 @@ impl ConnectionsFjall {
                 connection_id_sequence_table: sequences_partition,
                 client_players,
                 player_clients,
+                client_attributes: HashMap::new(),
+                client_options: HashMap::new(),
+                banned_sites_table,
+                banned_sites,
+                token_revocations_table,
+                token_revocations,
+                webhooks_table,
+                webhooks,
+                webhook_id_sequence,
             })),
         }
     }
@@ -321,18 +424,76 @@ impl ConnectionsDB for ConnectionsFjall {
 
     fn connection_name_for(&self, player: Obj) -> Result<String, SessionError> {
         let inner = self.inner.lock().unwrap();
-        let connections_records = inner
-            .player_clients
-            .get(&player)
-            .expect("no record")
-            .clone();
-        let name = connections_records
+        let Some(connections_record) = inner.player_clients.get(&player) else {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        };
+        connections_record
             .connections
             .iter()
             .map(|cr| cr.hostname.clone())
             .next()
-            .unwrap();
-        Ok(name)
+            .ok_or(SessionError::NoConnectionForPlayer(player))
+    }
+
+    fn set_client_attribute(
+        &self,
+        client_id: Uuid,
+        key: Symbol,
+        value: Option<Var>,
+    ) -> Result<(), RpcMessageError> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.client_players.contains_key(&client_id) {
+            return Err(RpcMessageError::NoConnection);
+        }
+        let attributes = inner.client_attributes.entry(client_id).or_default();
+        match value {
+            Some(value) => attributes.insert(key, value),
+            None => attributes.remove(&key),
+        };
+        Ok(())
+    }
+
+    fn client_attributes_for(&self, player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError> {
+        let inner = self.inner.lock().unwrap();
+        let Some(connections_record) = inner.player_clients.get(&player) else {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        };
+        let Some(client) = connections_record.connections.first() else {
+            return Err(SessionError::NoConnectionForPlayer(player));
+        };
+        let attributes = inner
+            .client_attributes
+            .get(&Uuid::from_u128(client.client_id))
+            .map(|attributes| attributes.iter().map(|(k, v)| (*k, v.clone())).collect())
+            .unwrap_or_default();
+        Ok(attributes)
+    }
+
+    fn set_client_option(
+        &self,
+        client_id: Uuid,
+        key: Symbol,
+        value: Var,
+    ) -> Result<(), SessionError> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.client_players.contains_key(&client_id) {
+            return Err(SessionError::DeliveryError);
+        }
+        inner
+            .client_options
+            .entry(client_id)
+            .or_default()
+            .insert(key, value);
+        Ok(())
+    }
+
+    fn client_options(&self, client_id: Uuid) -> Vec<(Symbol, Var)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .client_options
+            .get(&client_id)
+            .map(|options| options.iter().map(|(k, v)| (*k, v.clone())).collect())
+            .unwrap_or_default()
     }
 
     fn connected_seconds_for(&self, player: Obj) -> Result<f64, SessionError> {
@@ -382,6 +543,8 @@ impl ConnectionsDB for ConnectionsFjall {
         let Some(player_id) = inner.client_players.remove(&client_id) else {
             bail!("No connection to prune found for {:?}", client_id);
         };
+        inner.client_attributes.remove(&client_id);
+        inner.client_options.remove(&client_id);
         inner
             .client_player_table
             .remove(client_id.as_u128().to_le_bytes())
@@ -404,13 +567,92 @@ impl ConnectionsDB for ConnectionsFjall {
 
         Ok(())
     }
+
+    fn ban_site(&self, site: &str) -> Result<bool, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.banned_sites.insert(site.to_string()) {
+            return Ok(false);
+        }
+        inner.banned_sites_table.insert(site, "")?;
+        Ok(true)
+    }
+
+    fn unban_site(&self, site: &str) -> Result<bool, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.banned_sites.remove(site) {
+            return Ok(false);
+        }
+        inner.banned_sites_table.remove(site)?;
+        Ok(true)
+    }
+
+    fn banned_sites(&self) -> Vec<String> {
+        let inner = self.inner.lock().unwrap();
+        inner.banned_sites.iter().cloned().collect()
+    }
+
+    fn revoke_auth_tokens(&self, player: &Obj, before: SystemTime) -> Result<(), Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let encoded = bincode::encode_to_vec(before, *BINCODE_CONFIG)?;
+        inner
+            .token_revocations_table
+            .insert(player.as_bytes().unwrap(), encoded)?;
+        inner.token_revocations.insert(player.clone(), before);
+        Ok(())
+    }
+
+    fn auth_tokens_revoked_before(&self, player: &Obj) -> Option<SystemTime> {
+        let inner = self.inner.lock().unwrap();
+        inner.token_revocations.get(player).copied()
+    }
+
+    fn add_webhook(
+        &self,
+        url: &str,
+        secret: Option<String>,
+        filter: WebhookFilter,
+    ) -> Result<u64, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.webhook_id_sequence;
+        inner.webhook_id_sequence += 1;
+        inner.connection_id_sequence_table.insert(
+            "webhook_id_sequence",
+            inner.webhook_id_sequence.to_le_bytes(),
+        )?;
+        let webhook = Webhook {
+            id,
+            url: url.to_string(),
+            secret,
+            filter,
+        };
+        let encoded = bincode::encode_to_vec(WebhookRecord::from(&webhook), *BINCODE_CONFIG)?;
+        inner.webhooks_table.insert(id.to_be_bytes(), encoded)?;
+        inner.webhooks.insert(id, webhook);
+        Ok(id)
+    }
+
+    fn remove_webhook(&self, id: u64) -> Result<bool, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.webhooks.remove(&id).is_none() {
+            return Ok(false);
+        }
+        inner.webhooks_table.remove(id.to_be_bytes())?;
+        Ok(true)
+    }
+
+    fn webhooks(&self) -> Vec<Webhook> {
+        let inner = self.inner.lock().unwrap();
+        inner.webhooks.values().cloned().collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::SystemTime;
 
-    use moor_values::Obj;
+    use moor_kernel::tasks::sessions::WebhookFilter;
+    use moor_values::{Obj, Symbol};
 
     use crate::connections::ConnectionsDB;
     use crate::connections_fjall::ConnectionsFjall;
@@ -497,6 +739,81 @@ mod tests {
         assert_eq!(db.connection_object_for_client(client_id1), Some(ob));
     }
 
+    #[test]
+    fn test_banned_sites() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        assert!(db.ban_site("10.0.0.1").unwrap());
+        assert!(db.ban_site("192.168.*").unwrap());
+        assert!(!db.ban_site("10.0.0.1").unwrap());
+        assert!(db.unban_site("10.0.0.1").unwrap());
+        assert!(!db.unban_site("10.0.0.1").unwrap());
+        assert!(db.ban_site("10.0.0.2").unwrap());
+
+        drop(db);
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        assert_eq!(db.banned_sites(), vec!["10.0.0.2", "192.168.*"]);
+    }
+
+    #[test]
+    fn test_token_revocations() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        let player = Obj::mk_id(5);
+        assert_eq!(db.auth_tokens_revoked_before(&player), None);
+        let when = SystemTime::now();
+        db.revoke_auth_tokens(&player, when).unwrap();
+
+        drop(db);
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        assert_eq!(db.auth_tokens_revoked_before(&player), Some(when));
+        assert_eq!(db.auth_tokens_revoked_before(&Obj::mk_id(6)), None);
+    }
+
+    #[test]
+    fn test_webhooks() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        let filter = WebhookFilter {
+            object: Some(Obj::mk_id(5)),
+            verb: None,
+            property: Some(Symbol::mk("description")),
+            events: vec!["property_changed".to_string()],
+        };
+        let first = db
+            .add_webhook("http://localhost:9000/a", None, filter.clone())
+            .unwrap();
+        let second = db
+            .add_webhook(
+                "http://localhost:9000/b",
+                Some("sekrit".to_string()),
+                WebhookFilter::default(),
+            )
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(db.remove_webhook(first).unwrap());
+        assert!(!db.remove_webhook(first).unwrap());
+        let third = db
+            .add_webhook("http://localhost:9000/c", None, filter.clone())
+            .unwrap();
+
+        drop(db);
+        let db = Arc::new(ConnectionsFjall::open(Some(tmp_dir.path())));
+        let webhooks = db.webhooks();
+        assert_eq!(webhooks.len(), 2);
+        assert_eq!(webhooks[0].id, second);
+        assert_eq!(webhooks[0].secret, Some("sekrit".to_string()));
+        assert_eq!(webhooks[1].id, third);
+        assert_eq!(webhooks[1].url, "http://localhost:9000/c");
+        assert_eq!(webhooks[1].filter, filter);
+
+        // Ids aren't reused, even after a restart.
+        let fourth = db
+            .add_webhook("http://localhost:9000/d", None, WebhookFilter::default())
+            .unwrap();
+        assert!(fourth > third);
+    }
+
     // Validate that ping check works.
     #[test]
     fn ping_test() {
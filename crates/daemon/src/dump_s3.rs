@@ -0,0 +1,277 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Writing checkpoints straight to S3-compatible object storage.
+//!
+//! With `--dump-s3-url https://<endpoint>/<bucket>[/<prefix>]`, each checkpoint is PUT as a new
+//! object, `<prefix>/textdump-<UTC timestamp>.db`, signed with AWS Signature Version 4 using the
+//! credentials in `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and (optionally)
+//! `AWS_SESSION_TOKEN`. Old dumps aren't removed; a lifecycle rule on the bucket can expire them.
+//!
+//! The dump is held in memory while it's written, and then sent in one request.
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use eyre::{bail, eyre, Report};
+use hmac::{Hmac, Mac};
+use moor_kernel::textdump::DumpSink;
+use sha2::{Digest, Sha256};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    pub fn from_env() -> Result<Self, Report> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+        else {
+            bail!("Dumping to S3 needs AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY to be set");
+        };
+        Ok(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: var("AWS_SESSION_TOKEN"),
+        })
+    }
+}
+
+pub struct S3DumpSink {
+    scheme: String,
+    /// As sent in the `Host` header, with the port if there is one.
+    host: String,
+    /// The bucket, and the prefix if any, with no trailing `/`.
+    path: String,
+    region: String,
+    credentials: Credentials,
+}
+
+impl S3DumpSink {
+    pub fn new(url: &str, region: &str, credentials: Credentials) -> Result<Self, Report> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .filter(|(scheme, _)| *scheme == "http" || *scheme == "https")
+            .ok_or_else(|| eyre!("S3 dump URL must be http(s)://<endpoint>/<bucket>: {url}"))?;
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let path = path.trim_matches('/');
+        if host.is_empty() || path.is_empty() {
+            bail!("S3 dump URL must name an endpoint and a bucket: {url}");
+        }
+        Ok(Self {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            path: format!("/{path}"),
+            region: region.to_string(),
+            credentials,
+        })
+    }
+
+    fn object_path(&self, now: DateTime<Utc>) -> String {
+        format!(
+            "{}/textdump-{}.db",
+            self.path,
+            now.format("%Y%m%dT%H%M%S%.3fZ")
+        )
+    }
+
+    /// The headers to sign a PUT of `body` to `path` with, at `now`.
+    fn signed_headers(&self, path: &str, body: &[u8], now: DateTime<Utc>) -> Vec<(String, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let content_sha256 = hex(&Sha256::digest(body));
+
+        // Sorted by name, as the canonical request needs them.
+        let mut headers = vec![
+            ("host".to_string(), self.host.clone()),
+            ("x-amz-content-sha256".to_string(), content_sha256.clone()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        let signed_names = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+            .collect();
+        let canonical_request = format!(
+            "PUT\n{}\n\n{canonical_headers}\n{signed_names}\n{content_sha256}",
+            uri_encode_path(path)
+        );
+
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(
+            &self.credentials.secret_access_key,
+            &date,
+            &self.region,
+            "s3",
+        );
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+        headers.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_names}, Signature={signature}",
+                self.credentials.access_key_id
+            ),
+        ));
+        // ureq sets the Host header itself.
+        headers.retain(|(name, _)| name != "host");
+        headers
+    }
+}
+
+impl DumpSink for S3DumpSink {
+    fn describe(&self) -> String {
+        format!("{}://{}{}", self.scheme, self.host, self.path)
+    }
+
+    fn store(&self, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        let mut body = vec![];
+        write(&mut body)?;
+
+        let now = Utc::now();
+        let path = self.object_path(now);
+        let url = format!("{}://{}{}", self.scheme, self.host, uri_encode_path(&path));
+        let mut request = ureq::put(&url).timeout(REQUEST_TIMEOUT);
+        for (name, value) in self.signed_headers(&path, &body, now) {
+            request = request.set(&name, &value);
+        }
+        request
+            .send_bytes(&body)
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{secret}").as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+/// Percent-encode everything in `path` but unreserved characters and `/`, as SigV4 asks.
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use moor_kernel::textdump::DumpSink;
+
+    use super::{hex, signing_key, uri_encode_path, Credentials, S3DumpSink};
+
+    fn credentials() -> Credentials {
+        Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_signing_key() {
+        // From the AWS Signature Version 4 documentation.
+        assert_eq!(
+            hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20150830",
+                "us-east-1",
+                "iam"
+            )),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let sink = S3DumpSink::new(
+            "https://s3.example.com:9000/backups/moor/",
+            "us-east-1",
+            credentials(),
+        )
+        .unwrap();
+        assert_eq!(sink.describe(), "https://s3.example.com:9000/backups/moor");
+        let now = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            sink.object_path(now),
+            "/backups/moor/textdump-20250102T030405.000Z.db"
+        );
+
+        assert!(S3DumpSink::new("https://s3.example.com", "us-east-1", credentials()).is_err());
+        assert!(S3DumpSink::new("s3.example.com/bucket", "us-east-1", credentials()).is_err());
+    }
+
+    #[test]
+    fn test_signed_headers() {
+        let sink =
+            S3DumpSink::new("https://s3.example.com/bucket", "eu-west-1", credentials()).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        let headers = sink.signed_headers("/bucket/dump.db", b"", now);
+        let authorization = &headers
+            .iter()
+            .find(|(name, _)| name == "authorization")
+            .unwrap()
+            .1;
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20250102/eu-west-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="
+        ));
+        assert!(headers.iter().all(|(name, _)| name != "host"));
+        assert!(headers.contains(&(
+            "x-amz-content-sha256".to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_uri_encode_path() {
+        assert_eq!(uri_encode_path("/a b/c+d~e.db"), "/a%20b/c%2Bd~e.db");
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Publishing of events to hosts, with narrative events batched per client.
+//!
+//! A verb which `notify()`s hundreds of lines would otherwise cost a message (and its encoding,
+//! and a send) per line per connection. Instead each client's narrative events wait briefly, and
+//! go out together as one multipart message, `[client_id, event, event, ...]`, which the host
+//! splits back into the events in order. A batch goes out once it's been waiting for the flush
+//! window, or once it's as large as it's allowed to get, whichever comes first.
+//!
+//! Anything else published sends everything waiting first, so that each client sees events in
+//! the order they were published: a task's output always arrives before its result, say.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+use zmq::Socket;
+
+/// How narrative events are batched.
+#[derive(Debug, Clone, Copy)]
+pub struct EventBatching {
+    /// How long a client's narrative events may wait for more to go out with. Zero sends each
+    /// batch as soon as what's being published with it has been added.
+    pub flush_window: Duration,
+    /// The most events sent in one message.
+    pub max_batch: usize,
+}
+
+struct Batch {
+    started: Instant,
+    events: Vec<Vec<u8>>,
+}
+
+pub(crate) struct EventPublisher {
+    socket: Socket,
+    batching: EventBatching,
+    /// Narrative events waiting to go out, by client, in the order they were published.
+    pending: HashMap<Uuid, Batch>,
+}
+
+impl EventPublisher {
+    pub fn new(socket: Socket, batching: EventBatching) -> Self {
+        Self {
+            socket,
+            batching: EventBatching {
+                max_batch: batching.max_batch.max(1),
+                ..batching
+            },
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn flush_window(&self) -> Duration {
+        self.batching.flush_window
+    }
+
+    /// Send a message other than a narrative event, after whatever is waiting.
+    pub fn send_multipart(&mut self, payload: Vec<Vec<u8>>, flags: i32) -> Result<(), zmq::Error> {
+        self.flush_all()?;
+        self.socket.send_multipart(payload, flags)
+    }
+
+    /// Add the (encoded) narrative event to what's waiting to go to `client_id`, sending the
+    /// batch if that fills it.
+    pub fn queue_narrative(&mut self, client_id: Uuid, event: Vec<u8>) -> Result<(), zmq::Error> {
+        let batch = self.pending.entry(client_id).or_insert_with(|| Batch {
+            started: Instant::now(),
+            events: vec![],
+        });
+        batch.events.push(event);
+        if batch.events.len() >= self.batching.max_batch {
+            self.flush(client_id)?;
+        }
+        Ok(())
+    }
+
+    /// Send the batches which have waited out the flush window.
+    pub fn flush_due(&mut self) -> Result<(), zmq::Error> {
+        let window = self.batching.flush_window;
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, batch)| batch.started.elapsed() >= window)
+            .map(|(client_id, _)| *client_id)
+            .collect();
+        for client_id in due {
+            self.flush(client_id)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush_all(&mut self) -> Result<(), zmq::Error> {
+        let client_ids: Vec<_> = self.pending.keys().copied().collect();
+        for client_id in client_ids {
+            self.flush(client_id)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, client_id: Uuid) -> Result<(), zmq::Error> {
+        let Some(batch) = self.pending.remove(&client_id) else {
+            return Ok(());
+        };
+        let mut payload = Vec::with_capacity(batch.events.len() + 1);
+        payload.push(client_id.as_bytes().to_vec());
+        payload.extend(batch.events);
+        self.socket.send_multipart(payload, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher(batching: EventBatching) -> (EventPublisher, Socket) {
+        let context = zmq::Context::new();
+        let endpoint = format!("inproc://events-{}", Uuid::new_v4());
+        let publish = context.socket(zmq::PUB).unwrap();
+        publish.bind(&endpoint).unwrap();
+        let subscribe = context.socket(zmq::SUB).unwrap();
+        subscribe.connect(&endpoint).unwrap();
+        subscribe.set_subscribe(b"").unwrap();
+        // Give the subscription time to reach the publisher.
+        std::thread::sleep(Duration::from_millis(50));
+        (EventPublisher::new(publish, batching), subscribe)
+    }
+
+    #[test]
+    fn test_batches_until_full() {
+        let (mut publisher, subscribe) = publisher(EventBatching {
+            flush_window: Duration::from_secs(60),
+            max_batch: 3,
+        });
+        let client_id = Uuid::new_v4();
+        for i in 0..4u8 {
+            publisher.queue_narrative(client_id, vec![i]).unwrap();
+        }
+        let message = subscribe.recv_multipart(0).unwrap();
+        assert_eq!(
+            message,
+            vec![client_id.as_bytes().to_vec(), vec![0], vec![1], vec![2]]
+        );
+
+        // Not yet due, but anything else published sends it first.
+        publisher.flush_due().unwrap();
+        publisher
+            .send_multipart(vec![client_id.as_bytes().to_vec(), vec![9]], 0)
+            .unwrap();
+        let message = subscribe.recv_multipart(0).unwrap();
+        assert_eq!(message, vec![client_id.as_bytes().to_vec(), vec![3]]);
+        let message = subscribe.recv_multipart(0).unwrap();
+        assert_eq!(message, vec![client_id.as_bytes().to_vec(), vec![9]]);
+    }
+
+    #[test]
+    fn test_flushes_after_window() {
+        let (mut publisher, subscribe) = publisher(EventBatching {
+            flush_window: Duration::ZERO,
+            max_batch: 64,
+        });
+        let client_id = Uuid::new_v4();
+        publisher.queue_narrative(client_id, vec![1]).unwrap();
+        publisher.queue_narrative(client_id, vec![2]).unwrap();
+        publisher.flush_due().unwrap();
+        let message = subscribe.recv_multipart(0).unwrap();
+        assert_eq!(
+            message,
+            vec![client_id.as_bytes().to_vec(), vec![1], vec![2]]
+        );
+    }
+}
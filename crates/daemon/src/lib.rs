@@ -0,0 +1,433 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The moor daemon, as a library, so that it can be run in the same process as its hosts.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::args::Args;
+use crate::config_file::load_config;
+use crate::event_batch::EventBatching;
+use crate::federation::Federation;
+use crate::rpc_server::{ConnectionLimits, RpcServer};
+use eyre::{bail, Report};
+use moor_db::{Database, TxDB};
+use moor_kernel::tasks::scheduler::Scheduler;
+use moor_kernel::tasks::{NoopTasksDb, TasksDb};
+use moor_kernel::textdump::{textdump_load, DumpSink};
+use rpc_common::load_keypair;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, error, info, warn};
+
+pub mod args;
+mod config_file;
+mod connections;
+mod connections_fjall;
+mod dump_s3;
+mod event_batch;
+mod federation;
+mod login_throttle;
+mod metrics;
+mod name_lookup;
+#[cfg(feature = "otel")]
+pub mod otel;
+mod rpc_hosts;
+mod rpc_server;
+mod rpc_session;
+mod standby;
+mod sys_ctrl;
+mod tasks_fjall;
+mod webhooks;
+
+pub const MOOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Changes the level the process logs at.
+pub type SetLogLevel = Box<dyn Fn(LevelFilter) + Send>;
+
+/// Host for the moor runtime.
+///   * Brings up the database
+///   * Instantiates a scheduler
+///   * Exposes RPC interface for session/connection management.
+///
+/// Runs until `kill_switch` is thrown, by the caller or by the world shutting itself down, and
+/// then shuts down the scheduler. `set_log_level` is called with the configured log level, if
+/// any, at startup and whenever the configuration is reloaded.
+pub fn run_daemon(
+    args: Args,
+    kill_switch: Arc<AtomicBool>,
+    set_log_level: SetLogLevel,
+) -> Result<(), Report> {
+    let zmq_ctx = zmq::Context::new();
+    zmq_ctx
+        .set_io_threads(args.num_io_threads)
+        .expect("Failed to set number of IO threads");
+    run_daemon_with_context(zmq_ctx, args, kill_switch, set_log_level)
+}
+
+/// As `run_daemon`, but with its sockets in `zmq_ctx`, so that hosts in the same process can
+/// reach it over `inproc://` endpoints.
+pub fn run_daemon_with_context(
+    zmq_ctx: zmq::Context,
+    args: Args,
+    kill_switch: Arc<AtomicBool>,
+    set_log_level: SetLogLevel,
+) -> Result<(), Report> {
+    // Check the public/private keypair file to see if it exists. If it does, parse it and establish
+    // the keypair from it...
+    let (private_key, public_key) = if args.public_key.exists() && args.private_key.exists() {
+        load_keypair(&args.public_key, &args.private_key)
+            .expect("Unable to load keypair from public and private key files")
+    } else {
+        panic!(
+            "Public ({:?}) and/or private ({:?}) key files must exist",
+            args.public_key, args.private_key
+        );
+    };
+
+    let daemon_config = load_config(&args)?;
+    if let Some(level) = daemon_config.log_level {
+        set_log_level(level);
+    }
+    let mut config = daemon_config.config;
+
+    // Bringing up a standby: fetch and check the newest shipped snapshot, and load it into a new
+    // database as if it had been given with --textdump.
+    if let Some(source) = args.restore_standby.as_ref() {
+        if args.db_args.db.exists() {
+            bail!(
+                "--restore-standby needs a new database, but {:?} already exists",
+                args.db_args.db
+            );
+        }
+        let mut restored = args.db_args.db.clone().into_os_string();
+        restored.push(".standby.textdump");
+        let restored = PathBuf::from(restored);
+        let snapshot = standby::restore(&standby::Location::parse(source), &restored)?;
+        info!(
+            file = snapshot.file,
+            created = snapshot.created,
+            "Restored standby snapshot to {:?}",
+            restored
+        );
+        config.textdump_config.input_path = Some(restored);
+    }
+    let config = Arc::new(config);
+
+    if let Some(write_config) = args.write_merged_config.as_ref() {
+        let merged_config_json =
+            serde_json::to_string_pretty(config.as_ref()).expect("Unable to serialize config");
+        debug!("Merged config: {}", merged_config_json);
+        std::fs::write(write_config, merged_config_json).expect("Unable to write merged config");
+    }
+
+    if args.db_args.migrate_dry_run {
+        let pending = moor_db::migration::pending_migrations(&args.db_args.db)?;
+        if pending.is_empty() {
+            info!(path = ?args.db_args.db, "Database needs no migration");
+        }
+        for migration in pending {
+            info!(
+                from = migration.from,
+                to = migration.from + 1,
+                "Would migrate database: {}",
+                migration.description
+            );
+        }
+        return Ok(());
+    }
+
+    let version = semver::Version::parse(MOOR_VERSION).expect("Invalid moor version");
+    info!(
+        "moor {} daemon starting. Using database at {:?}",
+        version, args.db_args.db
+    );
+    let (database, freshly_made) =
+        TxDB::open(Some(&args.db_args.db), config.database_config.clone());
+    let database = Box::new(database);
+    info!(path = ?args.db_args.db, "Opened database");
+
+    // If the database already existed, do not try to import the textdump...
+    if let Some(textdump) = config.textdump_config.input_path.as_ref() {
+        if !freshly_made {
+            info!("Database already exists, skipping textdump import");
+        } else {
+            info!("Loading textdump from {:?}", textdump);
+            let start = std::time::Instant::now();
+            let mut loader_interface = database
+                .loader_client()
+                .expect("Unable to get loader interface from database");
+            textdump_load(
+                loader_interface.as_mut(),
+                textdump.clone(),
+                version.clone(),
+                config.features_config.clone(),
+                config.textdump_config.import_threads,
+            )
+            .unwrap();
+            let duration = start.elapsed();
+            info!("Loaded textdump in {:?}", duration);
+            loader_interface
+                .commit()
+                .expect("Failure to commit loaded database...");
+        }
+    }
+
+    if args.db_args.compact {
+        info!("Compacting object numbers");
+        let mut loader_interface = database
+            .loader_client()
+            .expect("Unable to get loader interface from database");
+        let renumbered = loader_interface
+            .compact()
+            .expect("Failure compacting database");
+        for (old, new) in &renumbered {
+            info!("Renumbered {} to {}", old, new);
+        }
+        loader_interface
+            .commit()
+            .expect("Failure to commit compacted database...");
+        info!(
+            "Compacted database; renumbered {} objects",
+            renumbered.len()
+        );
+    }
+
+    let tasks_db: Box<dyn TasksDb> = if config.features_config.persistent_tasks {
+        Box::new(tasks_fjall::FjallTasksDB::open(&args.tasks_db).0)
+    } else {
+        Box::new(NoopTasksDb {})
+    };
+
+    // We have to create the RpcServer before starting the scheduler because we need to pass it in
+    // as a parameter to the scheduler for background session construction.
+
+    let federation = match (&args.world_name, &args.federation_peers) {
+        (Some(world), Some(peers)) => {
+            // The RPC server gets the key we loaded above, so load another for signing messages.
+            let (private_key, _) = load_keypair(&args.public_key, &args.private_key)
+                .expect("Unable to load keypair from public and private key files");
+            let federation = Federation::open(world.clone(), private_key, zmq_ctx.clone(), peers)
+                .expect("Unable to configure federation");
+            Some(Arc::new(federation))
+        }
+        (None, None) => None,
+        _ => panic!("Federation needs both --world-name and --federation-peers"),
+    };
+    let rpc_server = Arc::new(RpcServer::new(
+        public_key,
+        private_key,
+        args.connections_file,
+        zmq_ctx.clone(),
+        args.events_listen.as_str(),
+        config.clone(),
+        args.auth_token_ttl.map(Duration::from_secs),
+        federation.clone(),
+        args.numeric_connection_names,
+        ConnectionLimits {
+            max_connections: args.max_connections,
+            max_listener_connections: args.max_listener_connections,
+        },
+        EventBatching {
+            flush_window: Duration::from_millis(args.event_flush_window_ms),
+            max_batch: args.event_max_batch,
+        },
+        kill_switch.clone(),
+    ));
+
+    // Committed changes, for webhooks. Subscribed to before the database goes to the scheduler.
+    let committed_changes = database.subscribe_changes();
+
+    // The pieces from core we're going to use:
+    //   Our DB.
+    //   Our scheduler.
+    let mut scheduler = Scheduler::new(
+        version,
+        database,
+        tasks_db,
+        config.clone(),
+        rpc_server.clone(),
+    );
+    let mut dump_destinations: Vec<String> = config
+        .textdump_config
+        .output_path
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect();
+    if let Some(url) = args.dump_s3_url.as_ref() {
+        let sink =
+            dump_s3::S3DumpSink::new(url, &args.dump_s3_region, dump_s3::Credentials::from_env()?)?;
+        dump_destinations.push(sink.describe());
+        scheduler.add_dump_sink(Arc::new(sink));
+    }
+    let scheduler_client = scheduler.client().expect("Failed to get scheduler client");
+
+    // The scheduler thread:
+    let scheduler_rpc_server = rpc_server.clone();
+    let scheduler_loop_jh = std::thread::Builder::new()
+        .name("moor-scheduler".to_string())
+        .spawn(move || scheduler.run(scheduler_rpc_server))?;
+
+    // Background DB checkpoint thread.
+    if let Some(checkpoint_interval) = config
+        .textdump_config
+        .checkpoint_interval
+        .filter(|_| !dump_destinations.is_empty())
+    {
+        let checkpoint_kill_switch = kill_switch.clone();
+        let checkpoint_scheduler_client = scheduler_client.clone();
+        info!(
+            "Checkpointing enabled to {}. Interval: {:?}",
+            dump_destinations.join(", "),
+            checkpoint_interval
+        );
+        std::thread::Builder::new()
+            .name("moor-checkpoint".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(checkpoint_interval);
+                if checkpoint_kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
+                    break;
+                }
+                checkpoint_scheduler_client
+                    .request_checkpoint()
+                    .expect("Failed to submit checkpoint");
+            })?;
+    } else {
+        info!("Checkpointing disabled.");
+    }
+
+    if let Some(ship_to) = args.standby_ship_to.as_ref() {
+        let Some(output_path) = config.textdump_config.output_path.clone() else {
+            bail!("--standby-ship-to needs textdump checkpoints to ship (--textdump-out)");
+        };
+        let location = standby::Location::parse(ship_to);
+        info!(?location, "Shipping checkpoints to standby");
+        let standby_kill_switch = kill_switch.clone();
+        let retain = args.standby_retain;
+        let interval = Duration::from_secs(args.standby_interval_seconds);
+        std::thread::Builder::new()
+            .name("moor-standby".to_string())
+            .spawn(move || {
+                standby::ship_loop(output_path, location, retain, interval, standby_kill_switch)
+            })?;
+    }
+
+    let webhooks_connections = rpc_server.connections.clone();
+    let webhooks_kill_switch = kill_switch.clone();
+    std::thread::Builder::new()
+        .name("moor-webhooks".to_string())
+        .spawn(move || {
+            webhooks::delivery_loop(
+                committed_changes,
+                webhooks_connections,
+                webhooks_kill_switch,
+            )
+        })?;
+
+    // Reload the configuration on SIGHUP.
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, reload_requested.clone())?;
+    let reload_args = args.clone();
+    let reload_kill_switch = kill_switch.clone();
+    let reload_scheduler_client = scheduler_client.clone();
+    let mut running_config = config.as_ref().clone();
+    std::thread::Builder::new()
+        .name("moor-config-reload".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(250));
+            if reload_kill_switch.load(Ordering::Relaxed) {
+                break;
+            }
+            if !reload_requested.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            info!("Reloading configuration");
+            let reloaded = match load_config(&reload_args) {
+                Ok(reloaded) => reloaded,
+                Err(e) => {
+                    error!("Not reloading configuration: {e}");
+                    continue;
+                }
+            };
+            if let Some(level) = reloaded.log_level {
+                set_log_level(level);
+            }
+            running_config = config_file::reloadable(&running_config, &reloaded.config);
+            if let Err(e) = reload_scheduler_client.reload_config(running_config.clone()) {
+                error!(?e, "Unable to pass reloaded configuration to the scheduler");
+            }
+        })?;
+
+    if let Some(metrics_listen) = args.metrics_listen.clone() {
+        let metrics_rpc_server = rpc_server.clone();
+        let metrics_scheduler_client = scheduler_client.clone();
+        std::thread::Builder::new()
+            .name("moor-metrics".to_string())
+            .spawn(move || {
+                if let Err(e) = metrics::serve_metrics(
+                    &metrics_listen,
+                    metrics_rpc_server,
+                    metrics_scheduler_client,
+                ) {
+                    error!(?e, "Metrics server failed");
+                }
+            })?;
+    }
+
+    if let Some(federation_listen) = args.federation_listen.clone() {
+        let Some(federation) = federation else {
+            panic!("--federation-listen needs --world-name and --federation-peers");
+        };
+        let federation_rpc_server = rpc_server.clone();
+        let federation_scheduler_client = scheduler_client.clone();
+        std::thread::Builder::new()
+            .name("moor-federation".to_string())
+            .spawn(move || {
+                if let Err(e) = federation::receive_loop(
+                    &federation_listen,
+                    federation,
+                    federation_rpc_server,
+                    federation_scheduler_client,
+                ) {
+                    error!(?e, "Federation listener failed");
+                }
+            })?;
+    }
+
+    let rpc_loop_scheduler_client = scheduler_client.clone();
+    let rpc_listen = args.rpc_listen.clone();
+    let rpc_loop_thread = std::thread::Builder::new()
+        .name("moor-rpc".to_string())
+        .spawn(move || {
+            rpc_server
+                .request_loop(rpc_listen, rpc_loop_scheduler_client)
+                .expect("RPC thread failed");
+        })?;
+
+    info!(
+        rpc_endpoint = args.rpc_listen,
+        events_endpoint = args.events_listen,
+        "Daemon started. Listening for RPC events."
+    );
+    rpc_loop_thread.join().expect("RPC thread panicked");
+    warn!("RPC thread exited. Departing...");
+
+    scheduler_client
+        .submit_shutdown("System shutting down")
+        .expect("Scheduler thread failed to stop");
+    scheduler_loop_jh.join().expect("Scheduler thread panicked");
+
+    Ok(())
+}
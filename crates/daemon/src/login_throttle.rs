@@ -0,0 +1,175 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Tracking of failed logins per site, so that password guessing from one address gets slower
+//! and slower, and matching of sites against the ban list.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Failed logins a site gets before it has to wait between attempts.
+const FREE_FAILURES: u32 = 3;
+/// The wait after the first failure past `FREE_FAILURES`; it doubles with each one after that.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// A site's failures are forgotten once it's gone this long without another.
+const FORGET_AFTER: Duration = Duration::from_secs(3600);
+
+/// The site a connection comes from: the address part of the "address:port" hosts report, or
+/// whatever they reported if it isn't one.
+pub fn site_of(hostname: &str) -> String {
+    match hostname.parse::<SocketAddr>() {
+        Ok(addr) => addr.ip().to_string(),
+        Err(_) => hostname.to_string(),
+    }
+}
+
+/// Whether `site` is covered by the ban list entry `ban`, which is either a site, or a prefix of
+/// sites ending in `*`.
+pub fn site_matches(ban: &str, site: &str) -> bool {
+    match ban.strip_suffix('*') {
+        Some(prefix) => site.starts_with(prefix),
+        None => ban == site,
+    }
+}
+
+/// Whether a login command (its words) is an attempt to log in to an existing player, as opposed
+/// to e.g. `who` or `help` at the login screen, which are free to fail.
+pub fn is_connect_attempt(args: &[String]) -> bool {
+    let Some(verb) = args.first() else {
+        return false;
+    };
+    verb.len() >= 2 && "connect".starts_with(verb.to_lowercase().as_str())
+}
+
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+#[derive(Default)]
+pub struct LoginThrottle {
+    failures: HashMap<String, Failures>,
+}
+
+impl LoginThrottle {
+    /// How much longer `site` has to wait before its next login attempt, if at all.
+    pub fn wait_for(&self, site: &str, now: Instant) -> Option<Duration> {
+        let failures = self.failures.get(site)?;
+        let backoff = backoff_for(failures.count)?;
+        (failures.last + backoff)
+            .checked_duration_since(now)
+            .filter(|wait| !wait.is_zero())
+    }
+
+    pub fn record_failure(&mut self, site: &str, now: Instant) {
+        self.failures
+            .retain(|_, failures| now.duration_since(failures.last) < FORGET_AFTER);
+        let failures = self.failures.entry(site.to_string()).or_insert(Failures {
+            count: 0,
+            last: now,
+        });
+        failures.count += 1;
+        failures.last = now;
+    }
+
+    pub fn record_success(&mut self, site: &str) {
+        self.failures.remove(site);
+    }
+}
+
+fn backoff_for(count: u32) -> Option<Duration> {
+    let doublings = count.checked_sub(FREE_FAILURES + 1)?;
+    let backoff = BASE_BACKOFF
+        .checked_mul(2u32.saturating_pow(doublings))
+        .unwrap_or(MAX_BACKOFF);
+    Some(backoff.min(MAX_BACKOFF))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::login_throttle::{is_connect_attempt, site_matches, site_of, LoginThrottle};
+
+    #[test]
+    fn test_sites() {
+        assert_eq!(site_of("10.0.0.1:4567"), "10.0.0.1");
+        assert_eq!(site_of("[::1]:4567"), "::1");
+        assert_eq!(site_of("localhost"), "localhost");
+
+        assert!(site_matches("10.0.0.1", "10.0.0.1"));
+        assert!(!site_matches("10.0.0.1", "10.0.0.10"));
+        assert!(site_matches("10.0.*", "10.0.3.4"));
+        assert!(!site_matches("10.0.*", "10.1.3.4"));
+        assert!(site_matches("*", "anywhere"));
+    }
+
+    #[test]
+    fn test_connect_attempts() {
+        let words = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert!(is_connect_attempt(&words("connect wizard secret")));
+        assert!(is_connect_attempt(&words("CO wizard secret")));
+        assert!(!is_connect_attempt(&words("c wizard")));
+        assert!(!is_connect_attempt(&words("create wizard secret")));
+        assert!(!is_connect_attempt(&words("who")));
+        assert!(!is_connect_attempt(&[]));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut throttle = LoginThrottle::default();
+        let start = Instant::now();
+
+        // The first few failures are free.
+        for _ in 0..3 {
+            throttle.record_failure("10.0.0.1", start);
+            assert_eq!(throttle.wait_for("10.0.0.1", start), None);
+        }
+
+        // Then the wait doubles with each one.
+        throttle.record_failure("10.0.0.1", start);
+        assert_eq!(
+            throttle.wait_for("10.0.0.1", start),
+            Some(Duration::from_secs(1))
+        );
+        throttle.record_failure("10.0.0.1", start);
+        assert_eq!(
+            throttle.wait_for("10.0.0.1", start),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            throttle.wait_for("10.0.0.1", start + Duration::from_millis(1500)),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            throttle.wait_for("10.0.0.1", start + Duration::from_secs(2)),
+            None
+        );
+
+        // Up to a point.
+        for _ in 0..20 {
+            throttle.record_failure("10.0.0.1", start);
+        }
+        assert_eq!(
+            throttle.wait_for("10.0.0.1", start),
+            Some(Duration::from_secs(300))
+        );
+
+        // Other sites aren't affected, and a successful login starts over.
+        assert_eq!(throttle.wait_for("10.0.0.2", start), None);
+        throttle.record_success("10.0.0.1");
+        assert_eq!(throttle.wait_for("10.0.0.1", start), None);
+    }
+}
@@ -11,213 +11,63 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
-use crate::args::Args;
-use crate::rpc_server::RpcServer;
 use clap::Parser;
 use eyre::Report;
-use moor_db::{Database, TxDB};
-use moor_kernel::tasks::scheduler::Scheduler;
-use moor_kernel::tasks::{NoopTasksDb, TasksDb};
-use moor_kernel::textdump::textdump_load;
-use rpc_common::load_keypair;
-use tracing::{debug, info, warn};
+use moor_daemon::args::Args;
+#[cfg(feature = "otel")]
+use moor_daemon::otel;
+use moor_daemon::run_daemon;
+use tracing::level_filters::LevelFilter;
+#[cfg(feature = "otel")]
+use tracing::warn;
+use tracing::{error, info};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
 
-mod connections;
-
-mod args;
-mod connections_fjall;
-mod rpc_hosts;
-mod rpc_server;
-mod rpc_session;
-mod sys_ctrl;
-mod tasks_fjall;
-
-pub const MOOR_VERSION: &str = env!("CARGO_PKG_VERSION");
-
-/// Host for the moor runtime.
-///   * Brings up the database
-///   * Instantiates a scheduler
-///   * Exposes RPC interface for session/connection management.
 fn main() -> Result<(), Report> {
     color_eyre::install()?;
 
-    let version = semver::Version::parse(MOOR_VERSION).expect("Invalid moor version");
-
     let args: Args = Args::parse();
 
-    let main_subscriber = tracing_subscriber::fmt()
-        .compact()
-        .with_ansi(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_thread_names(true)
-        .with_max_level(if args.debug {
-            tracing::Level::DEBUG
-        } else {
-            tracing::Level::INFO
-        })
-        .finish();
-    tracing::subscriber::set_global_default(main_subscriber)
-        .expect("Unable to set configure logging");
-
-    // Check the public/private keypair file to see if it exists. If it does, parse it and establish
-    // the keypair from it...
-    let (private_key, public_key) = if args.public_key.exists() && args.private_key.exists() {
-        load_keypair(&args.public_key, &args.private_key)
-            .expect("Unable to load keypair from public and private key files")
+    // The level is behind a reload layer, so that reloading the config can change it.
+    let (level_filter, level_handle) = reload::Layer::new(if args.debug {
+        LevelFilter::DEBUG
     } else {
-        panic!(
-            "Public ({:?}) and/or private ({:?}) key files must exist",
-            args.public_key, args.private_key
-        );
-    };
-
-    let config = args
-        .config_file
-        .as_ref()
-        .map(|path| {
-            let file = std::fs::File::open(path).expect("Unable to open config file");
-
-            serde_json::from_reader(file).expect("Unable to parse config file")
-        })
-        .unwrap_or_default();
-    let config = Arc::new(args.merge_config(config));
-
-    if let Some(write_config) = args.write_merged_config.as_ref() {
-        let merged_config_json =
-            serde_json::to_string_pretty(config.as_ref()).expect("Unable to serialize config");
-        debug!("Merged config: {}", merged_config_json);
-        std::fs::write(write_config, merged_config_json).expect("Unable to write merged config");
-    }
-
-    info!(
-        "moor {} daemon starting. Using database at {:?}",
-        version, args.db_args.db
+        LevelFilter::INFO
+    });
+    let main_subscriber = tracing_subscriber::registry().with(level_filter).with(
+        tracing_subscriber::fmt::layer()
+            .compact()
+            .with_ansi(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_names(true),
     );
-    let (database, freshly_made) =
-        TxDB::open(Some(&args.db_args.db), config.database_config.clone());
-    let database = Box::new(database);
-    info!(path = ?args.db_args.db, "Opened database");
 
-    // If the database already existed, do not try to import the textdump...
-    if let Some(textdump) = config.textdump_config.input_path.as_ref() {
-        if !freshly_made {
-            info!("Database already exists, skipping textdump import");
-        } else {
-            info!("Loading textdump from {:?}", textdump);
-            let start = std::time::Instant::now();
-            let mut loader_interface = database
-                .loader_client()
-                .expect("Unable to get loader interface from database");
-            textdump_load(
-                loader_interface.as_mut(),
-                textdump.clone(),
-                version.clone(),
-                config.features_config.clone(),
-            )
-            .unwrap();
-            let duration = start.elapsed();
-            info!("Loaded textdump in {:?}", duration);
-            loader_interface
-                .commit()
-                .expect("Failure to commit loaded database...");
+    #[cfg(feature = "otel")]
+    let otlp_export = otel::OtlpExport::new(args.otlp_endpoint.as_deref())?;
+    #[cfg(feature = "otel")]
+    let main_subscriber = main_subscriber.with(otlp_export.layer());
+    tracing::subscriber::set_global_default(main_subscriber)
+        .expect("Unable to set configure logging");
+    let set_log_level = Box::new(move |level: LevelFilter| {
+        if let Err(e) = level_handle.reload(level) {
+            error!(?e, "Unable to change log level");
         }
-    }
-
-    let tasks_db: Box<dyn TasksDb> = if config.features_config.persistent_tasks {
-        Box::new(tasks_fjall::FjallTasksDB::open(&args.tasks_db).0)
-    } else {
-        Box::new(NoopTasksDb {})
-    };
-
-    // We have to create the RpcServer before starting the scheduler because we need to pass it in
-    // as a parameter to the scheduler for background session construction.
-
-    let zmq_ctx = zmq::Context::new();
-    zmq_ctx
-        .set_io_threads(args.num_io_threads)
-        .expect("Failed to set number of IO threads");
-    let rpc_server = Arc::new(RpcServer::new(
-        public_key,
-        private_key,
-        args.connections_file,
-        zmq_ctx.clone(),
-        args.events_listen.as_str(),
-        config.clone(),
-    ));
-    let kill_switch = rpc_server.kill_switch();
-
-    // The pieces from core we're going to use:
-    //   Our DB.
-    //   Our scheduler.
-    let scheduler = Scheduler::new(
-        version,
-        database,
-        tasks_db,
-        config.clone(),
-        rpc_server.clone(),
-    );
-    let scheduler_client = scheduler.client().expect("Failed to get scheduler client");
-
-    // The scheduler thread:
-    let scheduler_rpc_server = rpc_server.clone();
-    let scheduler_loop_jh = std::thread::Builder::new()
-        .name("moor-scheduler".to_string())
-        .spawn(move || scheduler.run(scheduler_rpc_server))?;
-
-    // Background DB checkpoint thread.
-    if let (Some(checkpoint_interval), Some(output_path)) = (
-        config.textdump_config.checkpoint_interval,
-        config.textdump_config.output_path.clone(),
-    ) {
-        let checkpoint_kill_switch = kill_switch.clone();
-        let checkpoint_scheduler_client = scheduler_client.clone();
-        info!(
-            "Checkpointing enabled to {}. Interval: {:?}",
-            output_path.as_path().display(),
-            checkpoint_interval
-        );
-        std::thread::Builder::new()
-            .name("moor-checkpoint".to_string())
-            .spawn(move || loop {
-                std::thread::sleep(checkpoint_interval);
-                if checkpoint_kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
-                    break;
-                }
-                checkpoint_scheduler_client
-                    .request_checkpoint()
-                    .expect("Failed to submit checkpoint");
-            })?;
-    } else {
-        info!("Checkpointing disabled.");
-    }
-
-    let rpc_loop_scheduler_client = scheduler_client.clone();
-    let rpc_listen = args.rpc_listen.clone();
-    let rpc_loop_thread = std::thread::Builder::new()
-        .name("moor-rpc".to_string())
-        .spawn(move || {
-            rpc_server
-                .request_loop(rpc_listen, rpc_loop_scheduler_client)
-                .expect("RPC thread failed");
-        })?;
+    });
 
+    let kill_switch = Arc::new(AtomicBool::new(false));
     signal_hook::flag::register(signal_hook::consts::SIGTERM, kill_switch.clone())?;
     signal_hook::flag::register(signal_hook::consts::SIGINT, kill_switch.clone())?;
-    info!(
-        rpc_endpoint = args.rpc_listen,
-        events_endpoint = args.events_listen,
-        "Daemon started. Listening for RPC events."
-    );
-    rpc_loop_thread.join().expect("RPC thread panicked");
-    warn!("RPC thread exited. Departing...");
+    run_daemon(args, kill_switch, set_log_level)?;
 
-    scheduler_client
-        .submit_shutdown("System shutting down")
-        .expect("Scheduler thread failed to stop");
-    scheduler_loop_jh.join().expect("Scheduler thread panicked");
+    #[cfg(feature = "otel")]
+    if let Err(e) = otlp_export.shutdown() {
+        warn!(?e, "Unable to flush traces to OTLP collector");
+    }
     info!("Done.");
 
     Ok(())
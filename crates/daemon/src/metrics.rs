@@ -0,0 +1,389 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Performance counters for operators: gathered from the scheduler and the host registry, and
+//! served (optionally) over HTTP in the Prometheus text exposition format.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use moor_kernel::tasks::SchedulerCounters;
+use moor_kernel::SchedulerClient;
+use rpc_common::{metrics_to_prometheus, Metric, MetricKind};
+use tracing::{error, info, warn};
+
+use crate::rpc_hosts::Hosts;
+use crate::rpc_server::RpcServer;
+
+/// Turn the scheduler's counters into metrics.
+pub(crate) fn scheduler_metrics(counters: &SchedulerCounters) -> Vec<Metric> {
+    use MetricKind::{Counter, Gauge};
+    let finished = "moor_tasks_finished_total";
+    let finished_help = "Tasks which have finished, by outcome.";
+    vec![
+        Metric::new(
+            "moor_tasks_started_total",
+            "Tasks started, including restarts after commit conflicts.",
+            Counter,
+            counters.tasks_started,
+        ),
+        Metric::new(
+            "moor_tasks_resumed_total",
+            "Suspended tasks resumed.",
+            Counter,
+            counters.tasks_resumed,
+        ),
+        Metric::new(finished, finished_help, Counter, counters.tasks_succeeded)
+            .with_label("outcome", "success"),
+        Metric::new(finished, finished_help, Counter, counters.tasks_exceptions)
+            .with_label("outcome", "exception"),
+        Metric::new(
+            finished,
+            finished_help,
+            Counter,
+            counters.tasks_limits_exceeded,
+        )
+        .with_label("outcome", "limits_exceeded"),
+        Metric::new(finished, finished_help, Counter, counters.tasks_aborted)
+            .with_label("outcome", "aborted"),
+        Metric::new(
+            "moor_commit_conflicts_total",
+            "Task commits which conflicted with another transaction and were retried.",
+            Counter,
+            counters.commit_conflicts,
+        ),
+        Metric::new(
+            "moor_tasks_active",
+            "Tasks currently running.",
+            Gauge,
+            counters.active_tasks,
+        ),
+        Metric::new(
+            "moor_tasks_suspended",
+            "Tasks currently suspended, forked, or awaiting input.",
+            Gauge,
+            counters.suspended_tasks,
+        ),
+        Metric::new(
+            "moor_tasks_ready",
+            "Tasks submitted but waiting for a free slot to start in.",
+            Gauge,
+            counters.ready_tasks,
+        ),
+        Metric::new(
+            "moor_tasks_shed_total",
+            "Commands turned away because the server was overloaded.",
+            Counter,
+            counters.tasks_shed,
+        ),
+        Metric::new(
+            "moor_tasks_over_quota_total",
+            "Forks and suspensions refused because of a task quota.",
+            Counter,
+            counters.tasks_over_quota,
+        ),
+        Metric::new(
+            "moor_overloaded",
+            "1 if the server is past one of its overload thresholds and turning away commands.",
+            Gauge,
+            counters.overloaded as u64,
+        ),
+        Metric::new(
+            "moor_commit_latency_microseconds",
+            "The recent average time taken to commit a task's transaction.",
+            Gauge,
+            counters.commit_latency_us,
+        ),
+        Metric::new(
+            "moor_commits_total",
+            "Task transactions committed, successfully or not.",
+            Counter,
+            counters.commits,
+        ),
+        Metric::new(
+            "moor_commit_time_microseconds_total",
+            "Total time spent committing task transactions.",
+            Counter,
+            counters.commit_time_us,
+        ),
+        Metric::new(
+            "moor_program_cache_hits_total",
+            "Compiled program cache lookups which found an entry.",
+            Counter,
+            counters.program_cache_hits,
+        ),
+        Metric::new(
+            "moor_program_cache_misses_total",
+            "Compiled program cache lookups which did not find an entry.",
+            Counter,
+            counters.program_cache_misses,
+        ),
+    ]
+}
+
+/// Turn the host registry's view of the world into metrics.
+pub(crate) fn host_metrics(hosts: &Hosts, num_connections: usize) -> Vec<Metric> {
+    let counts = hosts.counts_by_type();
+    let mut metrics = vec![];
+    for (host_type, num_hosts, _) in &counts {
+        metrics.push(
+            Metric::new(
+                "moor_hosts",
+                "Hosts currently registered with the daemon.",
+                MetricKind::Gauge,
+                *num_hosts as u64,
+            )
+            .with_label("host_type", host_type.id_str()),
+        );
+    }
+    for (host_type, _, num_clients) in &counts {
+        metrics.push(
+            Metric::new(
+                "moor_host_connections",
+                "Client connections, by the type of host they are connected through.",
+                MetricKind::Gauge,
+                *num_clients as u64,
+            )
+            .with_label("host_type", host_type.id_str()),
+        );
+    }
+    for (port, num_clients) in hosts.counts_by_listener() {
+        metrics.push(
+            Metric::new(
+                "moor_listener_connections",
+                "Client connections, by the port of the listener they connected through.",
+                MetricKind::Gauge,
+                num_clients as u64,
+            )
+            .with_label("port", &port.to_string()),
+        );
+    }
+    metrics.push(Metric::new(
+        "moor_connections",
+        "Connection objects (logged in or not) known to the daemon.",
+        MetricKind::Gauge,
+        num_connections as u64,
+    ));
+    metrics
+}
+
+/// Connections turned away for being over the global or a listener's connection limit.
+pub(crate) fn connection_limit_metrics(refused_global: u64, refused_listener: u64) -> Vec<Metric> {
+    let refused = "moor_connections_refused_total";
+    let refused_help = "Connections refused because the server or listener was full.";
+    vec![
+        Metric::new(refused, refused_help, MetricKind::Counter, refused_global)
+            .with_label("limit", "global"),
+        Metric::new(refused, refused_help, MetricKind::Counter, refused_listener)
+            .with_label("limit", "listener"),
+    ]
+}
+
+/// Serve `GET /metrics` in the Prometheus text format until the daemon's kill switch is thrown.
+pub(crate) fn serve_metrics(
+    listen_addr: &str,
+    rpc_server: Arc<RpcServer>,
+    scheduler_client: SchedulerClient,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(listen_addr)?;
+    info!(
+        "Serving Prometheus metrics on http://{}/metrics",
+        listen_addr
+    );
+    for stream in listener.incoming() {
+        if rpc_server.kill_switch.load(Ordering::Relaxed) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(?e, "Failed to accept metrics connection");
+                continue;
+            }
+        };
+        if let Err(e) = respond(stream, &rpc_server, &scheduler_client) {
+            error!(?e, "Failed to respond to metrics request");
+        }
+    }
+    Ok(())
+}
+
+fn respond(
+    stream: TcpStream,
+    rpc_server: &RpcServer,
+    scheduler_client: &SchedulerClient,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; we don't care about any of them.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut stream = reader.into_inner();
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let hosts = rpc_server.hosts.lock().unwrap();
+            let metrics = rpc_server.collect_metrics(scheduler_client, &hosts);
+            ("200 OK", metrics_to_prometheus(&metrics))
+        }
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use moor_kernel::tasks::SchedulerCounters;
+    use moor_values::SYSTEM_OBJECT;
+    use rpc_common::{HostToken, HostType, Metric, MetricKind};
+    use uuid::Uuid;
+
+    use crate::metrics::{connection_limit_metrics, host_metrics, scheduler_metrics};
+    use crate::rpc_hosts::Hosts;
+
+    /// The value of the sample named `name` with the given labels, if there is one.
+    fn sample(metrics: &[Metric], name: &str, labels: &[(&str, &str)]) -> Option<u64> {
+        metrics
+            .iter()
+            .find(|m| {
+                m.name == name
+                    && m.labels.len() == labels.len()
+                    && labels
+                        .iter()
+                        .all(|(l, v)| m.labels.contains(&(l.to_string(), v.to_string())))
+            })
+            .map(|m| m.value)
+    }
+
+    #[test]
+    fn test_scheduler_metrics() {
+        let counters = SchedulerCounters {
+            tasks_started: 10,
+            tasks_succeeded: 6,
+            tasks_exceptions: 2,
+            tasks_limits_exceeded: 1,
+            tasks_aborted: 1,
+            active_tasks: 3,
+            overloaded: true,
+            program_cache_misses: 7,
+            ..Default::default()
+        };
+        let metrics = scheduler_metrics(&counters);
+        assert_eq!(sample(&metrics, "moor_tasks_started_total", &[]), Some(10));
+        let finished = "moor_tasks_finished_total";
+        assert_eq!(
+            sample(&metrics, finished, &[("outcome", "success")]),
+            Some(6)
+        );
+        assert_eq!(
+            sample(&metrics, finished, &[("outcome", "exception")]),
+            Some(2)
+        );
+        assert_eq!(
+            sample(&metrics, finished, &[("outcome", "limits_exceeded")]),
+            Some(1)
+        );
+        assert_eq!(
+            sample(&metrics, finished, &[("outcome", "aborted")]),
+            Some(1)
+        );
+        assert_eq!(sample(&metrics, "moor_tasks_active", &[]), Some(3));
+        assert_eq!(sample(&metrics, "moor_overloaded", &[]), Some(1));
+        assert_eq!(
+            sample(&metrics, "moor_program_cache_misses_total", &[]),
+            Some(7)
+        );
+        assert_eq!(sample(&metrics, "moor_commits_total", &[]), Some(0));
+
+        // Totals are counters, and samples are gauges.
+        let kind = |name: &str| metrics.iter().find(|m| m.name == name).unwrap().kind;
+        assert_eq!(kind("moor_tasks_started_total"), MetricKind::Counter);
+        assert_eq!(kind("moor_tasks_active"), MetricKind::Gauge);
+
+        // Samples of the same name are adjacent, as metrics_to_prometheus needs.
+        let mut names: Vec<_> = metrics.iter().map(|m| m.name.as_str()).collect();
+        names.dedup();
+        let mut unique = names.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(names.len(), unique.len());
+    }
+
+    #[test]
+    fn test_host_metrics() {
+        let mut hosts = Hosts::default();
+        let listener: SocketAddr = "0.0.0.0:7777".parse().unwrap();
+        hosts.receive_ping(
+            HostToken("telnet".to_string()),
+            HostType::TCP,
+            vec![(SYSTEM_OBJECT, listener)],
+        );
+        hosts.receive_ping(HostToken("web".to_string()), HostType::WebSocket, vec![]);
+        for _ in 0..2 {
+            let client = Uuid::new_v4();
+            hosts.receive_client_ping(client, HostType::TCP);
+            hosts.record_client_listener(client, 7777);
+        }
+        hosts.receive_client_ping(Uuid::new_v4(), HostType::WebSocket);
+
+        let metrics = host_metrics(&hosts, 5);
+        assert_eq!(
+            sample(&metrics, "moor_hosts", &[("host_type", "tcp")]),
+            Some(1)
+        );
+        assert_eq!(
+            sample(&metrics, "moor_hosts", &[("host_type", "websocket")]),
+            Some(1)
+        );
+        assert_eq!(
+            sample(&metrics, "moor_host_connections", &[("host_type", "tcp")]),
+            Some(2)
+        );
+        assert_eq!(
+            sample(
+                &metrics,
+                "moor_host_connections",
+                &[("host_type", "websocket")]
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            sample(&metrics, "moor_listener_connections", &[("port", "7777")]),
+            Some(2)
+        );
+        assert_eq!(sample(&metrics, "moor_connections", &[]), Some(5));
+    }
+
+    #[test]
+    fn test_connection_limit_metrics() {
+        let metrics = connection_limit_metrics(3, 4);
+        let refused = "moor_connections_refused_total";
+        assert_eq!(sample(&metrics, refused, &[("limit", "global")]), Some(3));
+        assert_eq!(sample(&metrics, refused, &[("limit", "listener")]), Some(4));
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Reverse DNS for connection names. Lookups can be slow, so they're done on a worker thread when
+//! a connection comes in, and `connection_name()` uses whatever name has been found by the time
+//! it's called, or the address until then.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+/// How long a name (or the lack of one) is believed before it's looked up again.
+const NAME_TTL: Duration = Duration::from_secs(3600);
+
+/// Big enough for any hostname (NI_MAXHOST).
+const MAX_HOSTNAME: usize = 1025;
+
+pub struct NameResolver {
+    /// Don't look names up unless asked to by `connection_name_lookup()`.
+    numeric_only: bool,
+    names: Mutex<HashMap<IpAddr, (Instant, Option<String>)>>,
+    requests: Mutex<Sender<IpAddr>>,
+}
+
+impl NameResolver {
+    /// Start the resolver, and the thread which does its lookups.
+    pub fn start(numeric_only: bool) -> Arc<Self> {
+        let (requests, receive) = channel::<IpAddr>();
+        let resolver = Arc::new(Self {
+            numeric_only,
+            names: Default::default(),
+            requests: Mutex::new(requests),
+        });
+        if !numeric_only {
+            let worker_resolver = resolver.clone();
+            std::thread::Builder::new()
+                .name("moor-name-lookup".to_string())
+                .spawn(move || {
+                    for ip in receive {
+                        if worker_resolver.fresh(&ip, Instant::now()) {
+                            continue;
+                        }
+                        worker_resolver.lookup_now(ip);
+                    }
+                })
+                .expect("Unable to start name lookup thread");
+        }
+        resolver
+    }
+
+    /// Queue a lookup of the address in `hostname` (as reported by hosts, "address:port"), unless
+    /// we've looked it up recently.
+    pub fn request(&self, hostname: &str) {
+        if self.numeric_only {
+            return;
+        }
+        let Ok(addr) = hostname.parse::<SocketAddr>() else {
+            return;
+        };
+        if self.fresh(&addr.ip(), Instant::now()) {
+            return;
+        }
+        if self.requests.lock().unwrap().send(addr.ip()).is_err() {
+            warn!("Name lookup thread has gone away");
+        }
+    }
+
+    /// Look `ip` up now, remembering the result for `connection_name()`.
+    pub fn lookup_now(&self, ip: IpAddr) -> Option<String> {
+        let name = reverse_lookup(ip);
+        debug!(?ip, ?name, "Looked up connection name");
+        self.names
+            .lock()
+            .unwrap()
+            .insert(ip, (Instant::now(), name.clone()));
+        name
+    }
+
+    /// `hostname` with its address replaced by the name it was found to have, if any.
+    pub fn display_name(&self, hostname: &str) -> String {
+        let names = self.names.lock().unwrap();
+        display_name(hostname, |ip| {
+            names.get(&ip).and_then(|(_, name)| name.clone())
+        })
+    }
+
+    fn fresh(&self, ip: &IpAddr, now: Instant) -> bool {
+        match self.names.lock().unwrap().get(ip) {
+            Some((looked_up, _)) => now.duration_since(*looked_up) < NAME_TTL,
+            None => false,
+        }
+    }
+}
+
+fn display_name<F: Fn(IpAddr) -> Option<String>>(hostname: &str, name_for: F) -> String {
+    let Ok(addr) = hostname.parse::<SocketAddr>() else {
+        return hostname.to_string();
+    };
+    match name_for(addr.ip()) {
+        Some(name) => format!("{}:{}", name, addr.port()),
+        None => hostname.to_string(),
+    }
+}
+
+/// The name `ip` resolves to, if it has one. Blocks for as long as the system resolver takes.
+pub fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    let mut host = [0 as libc::c_char; MAX_HOSTNAME];
+    let result = match ip {
+        IpAddr::V4(ip) => {
+            // SAFETY: sockaddr_in is plain old data, for which all zeroes is valid.
+            let mut sin: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr = libc::in_addr {
+                s_addr: u32::from_ne_bytes(ip.octets()),
+            };
+            #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+            {
+                sin.sin_len = std::mem::size_of::<libc::sockaddr_in>() as u8;
+            }
+            // SAFETY: the address and buffer are valid for the lengths given.
+            unsafe {
+                libc::getnameinfo(
+                    &sin as *const libc::sockaddr_in as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                    host.as_mut_ptr(),
+                    host.len() as libc::socklen_t,
+                    std::ptr::null_mut(),
+                    0,
+                    libc::NI_NAMEREQD,
+                )
+            }
+        }
+        IpAddr::V6(ip) => {
+            // SAFETY: sockaddr_in6 is plain old data, for which all zeroes is valid.
+            let mut sin6: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr = libc::in6_addr {
+                s6_addr: ip.octets(),
+            };
+            #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+            {
+                sin6.sin6_len = std::mem::size_of::<libc::sockaddr_in6>() as u8;
+            }
+            // SAFETY: the address and buffer are valid for the lengths given.
+            unsafe {
+                libc::getnameinfo(
+                    &sin6 as *const libc::sockaddr_in6 as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+                    host.as_mut_ptr(),
+                    host.len() as libc::socklen_t,
+                    std::ptr::null_mut(),
+                    0,
+                    libc::NI_NAMEREQD,
+                )
+            }
+        }
+    };
+    if result != 0 {
+        return None;
+    }
+    // SAFETY: getnameinfo succeeded, so `host` holds a NUL-terminated string.
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::name_lookup::{display_name, NameResolver};
+
+    #[test]
+    fn test_display_name() {
+        let name_for = |ip: IpAddr| {
+            (ip == "10.0.0.1".parse::<IpAddr>().unwrap()).then(|| "example.org".to_string())
+        };
+        assert_eq!(display_name("10.0.0.1:4567", name_for), "example.org:4567");
+        assert_eq!(display_name("10.0.0.2:4567", name_for), "10.0.0.2:4567");
+        assert_eq!(display_name("[::1]:4567", name_for), "[::1]:4567");
+        assert_eq!(display_name("localhost", name_for), "localhost");
+    }
+
+    #[test]
+    fn test_numeric_only() {
+        let resolver = NameResolver::start(true);
+        resolver.request("127.0.0.1:4567");
+        assert_eq!(resolver.display_name("127.0.0.1:4567"), "127.0.0.1:4567");
+    }
+}
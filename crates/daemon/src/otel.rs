@@ -0,0 +1,132 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Export of tracing spans to an OpenTelemetry (OTLP/HTTP) collector.
+//! Spans are carried from the RPC request through the scheduler and into the task thread, so a
+//! single player command shows up as one trace covering dispatch, VM execution and commit.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const SERVICE_NAME: &str = "moor-daemon";
+
+/// Build a provider which batches spans off to the collector at `endpoint`
+/// (e.g. `http://localhost:4318/v1/traces`). It must be kept alive, and shut down on exit to
+/// flush any spans still buffered.
+pub fn tracer_provider(endpoint: &str) -> eyre::Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// A tracing layer which forwards spans to the given provider.
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+/// Span export as configured by `--otlp-endpoint`: to the collector there, or, with no endpoint,
+/// nowhere, with a layer which does nothing.
+pub struct OtlpExport {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl OtlpExport {
+    pub fn new(endpoint: Option<&str>) -> eyre::Result<Self> {
+        let provider = endpoint.map(tracer_provider).transpose()?;
+        Ok(Self { provider })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// The layer to add to the subscriber. With no endpoint, it's `None`, which as a layer is a
+    /// no-op.
+    pub fn layer<S>(&self) -> Option<OpenTelemetryLayer<S, SdkTracer>>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        self.provider.as_ref().map(layer)
+    }
+
+    /// Flush any spans still buffered, on exit.
+    pub fn shutdown(self) -> eyre::Result<()> {
+        if let Some(provider) = self.provider {
+            provider.shutdown()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::args::Args;
+    use crate::otel::OtlpExport;
+
+    #[test]
+    fn test_otlp_endpoint_argument() {
+        let args = Args::parse_from(["moor-daemon", "world.db"]);
+        assert_eq!(args.otlp_endpoint, None);
+
+        let args = Args::parse_from([
+            "moor-daemon",
+            "world.db",
+            "--otlp-endpoint",
+            "http://localhost:4318/v1/traces",
+        ]);
+        assert_eq!(
+            args.otlp_endpoint.as_deref(),
+            Some("http://localhost:4318/v1/traces")
+        );
+    }
+
+    #[test]
+    fn test_no_endpoint_is_a_no_op() {
+        let export = OtlpExport::new(None).unwrap();
+        assert!(!export.is_enabled());
+        let layer = export.layer();
+        assert!(layer.is_none());
+
+        // Spans still work with the (absent) layer in place.
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("test_span").in_scope(|| tracing::info!("in span"));
+        });
+        export.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let export = OtlpExport::new(Some("http://localhost:4318/v1/traces")).unwrap();
+        assert!(export.is_enabled());
+        assert!(export.layer::<tracing_subscriber::Registry>().is_some());
+
+        assert!(OtlpExport::new(Some("not a url")).is_err());
+    }
+}
@@ -17,6 +17,7 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::SystemTime;
 use tracing::warn;
+use uuid::Uuid;
 
 /// Manages the set of known hosts and the listeners they have registered.
 struct HostRecord {
@@ -26,7 +27,17 @@ struct HostRecord {
 }
 
 #[derive(Default)]
-pub struct Hosts(HashMap<HostToken, HostRecord>);
+pub struct Hosts {
+    hosts: HashMap<HostToken, HostRecord>,
+    /// The type of host each client last pinged us through, for per-host connection counts.
+    clients: HashMap<Uuid, HostType>,
+    /// The local port of the listener each client connected through, for per-listener limits.
+    client_listeners: HashMap<Uuid, u16>,
+    /// The listeners `listen()` has asked for, by host type and port, with whether they print
+    /// server messages. Hosts only report their listeners when pinged, so this is also what stops
+    /// a second `listen()` on a port before then.
+    requested_listeners: HashMap<(HostType, u16), bool>,
+}
 
 impl Hosts {
     pub(crate) fn receive_ping(
@@ -36,7 +47,7 @@ impl Hosts {
         listeners: Vec<(Obj, SocketAddr)>,
     ) -> bool {
         let now = SystemTime::now();
-        self.0
+        self.hosts
             .insert(
                 host_token,
                 HostRecord {
@@ -51,7 +62,7 @@ impl Hosts {
     pub(crate) fn ping_check(&mut self, timeout: std::time::Duration) {
         let now = SystemTime::now();
         let mut expired = vec![];
-        for (host_token, HostRecord { last_seen, .. }) in self.0.iter() {
+        for (host_token, HostRecord { last_seen, .. }) in self.hosts.iter() {
             if now.duration_since(*last_seen).unwrap() > timeout {
                 warn!(
                     "Host {} has not responded in time: {:?}, removing its listeners from the list",
@@ -67,7 +78,7 @@ impl Hosts {
     }
 
     pub(crate) fn listeners(&self) -> Vec<(Obj, HostType, SocketAddr)> {
-        self.0
+        self.hosts
             .values()
             .flat_map(
                 |HostRecord {
@@ -83,7 +94,96 @@ impl Hosts {
             .collect()
     }
 
+    /// Whether a host is listening on `port`, or has been asked to.
+    pub(crate) fn is_listening(&self, host_type: HostType, port: u16) -> bool {
+        self.requested_listeners.contains_key(&(host_type, port))
+            || self
+                .listeners()
+                .iter()
+                .any(|(_, t, addr)| *t == host_type && addr.port() == port)
+    }
+
+    pub(crate) fn request_listener(
+        &mut self,
+        host_type: HostType,
+        port: u16,
+        print_messages: bool,
+    ) {
+        self.requested_listeners
+            .insert((host_type, port), print_messages);
+    }
+
+    pub(crate) fn remove_listener(&mut self, host_type: HostType, port: u16) {
+        self.requested_listeners.remove(&(host_type, port));
+    }
+
+    /// Whether the listener on `port` prints server messages. Those hosts start on their own do.
+    pub(crate) fn prints_messages(&self, host_type: HostType, port: u16) -> bool {
+        self.requested_listeners
+            .get(&(host_type, port))
+            .copied()
+            .unwrap_or(true)
+    }
+
     pub(crate) fn unregister_host(&mut self, host_token: &HostToken) {
-        self.0.remove(host_token);
+        self.hosts.remove(host_token);
+    }
+
+    pub(crate) fn receive_client_ping(&mut self, client_id: Uuid, host_type: HostType) {
+        self.clients.insert(client_id, host_type);
+    }
+
+    pub(crate) fn remove_client(&mut self, client_id: &Uuid) {
+        self.clients.remove(client_id);
+        self.client_listeners.remove(client_id);
+    }
+
+    /// Forget any clients for which `is_live` no longer holds.
+    pub(crate) fn prune_clients<F: Fn(&Uuid) -> bool>(&mut self, is_live: F) {
+        self.clients.retain(|client_id, _| is_live(client_id));
+        self.client_listeners
+            .retain(|client_id, _| is_live(client_id));
+    }
+
+    /// Note the listener a new client connected through. Port 0 is for hosts which don't say.
+    pub(crate) fn record_client_listener(&mut self, client_id: Uuid, port: u16) {
+        if port != 0 {
+            self.client_listeners.insert(client_id, port);
+        }
+    }
+
+    /// The number of clients connected through the listener on `port`.
+    pub(crate) fn listener_connections(&self, port: u16) -> usize {
+        self.client_listeners
+            .values()
+            .filter(|p| **p == port)
+            .count()
+    }
+
+    /// The number of clients connected through each listener which has any.
+    pub(crate) fn counts_by_listener(&self) -> Vec<(u16, usize)> {
+        let mut counts: HashMap<u16, usize> = HashMap::new();
+        for port in self.client_listeners.values() {
+            *counts.entry(*port).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort();
+        counts
+    }
+
+    /// The number of registered hosts and of connected clients, per host type.
+    pub(crate) fn counts_by_type(&self) -> Vec<(HostType, usize, usize)> {
+        [HostType::TCP, HostType::WebSocket]
+            .into_iter()
+            .map(|host_type| {
+                let hosts = self
+                    .hosts
+                    .values()
+                    .filter(|h| h.host_type == host_type)
+                    .count();
+                let clients = self.clients.values().filter(|t| **t == host_type).count();
+                (host_type, hosts, clients)
+            })
+            .collect()
     }
 }
@@ -19,7 +19,7 @@ use uuid::Uuid;
 
 use moor_kernel::tasks::sessions::{Session, SessionError, SessionFactory};
 use moor_values::tasks::NarrativeEvent;
-use moor_values::Obj;
+use moor_values::{Obj, Symbol, Var};
 
 use crate::rpc_server::RpcServer;
 
@@ -113,6 +113,27 @@ impl Session for RpcSession {
         self.rpc_server.connection_name_for(player)
     }
 
+    fn connection_name_lookup(&self, player: Obj, rewrite: bool) -> Result<String, SessionError> {
+        self.rpc_server.connection_name_lookup(player, rewrite)
+    }
+
+    fn connection_attributes(&self, player: Obj) -> Result<Var, SessionError> {
+        self.rpc_server.connection_attributes_for(player)
+    }
+
+    fn set_connection_option(
+        &self,
+        player: Obj,
+        option: Symbol,
+        value: Var,
+    ) -> Result<(), SessionError> {
+        self.rpc_server.set_connection_option(player, option, value)
+    }
+
+    fn connection_options(&self, player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError> {
+        self.rpc_server.connection_options_for(player)
+    }
+
     fn disconnect(&self, player: Obj) -> Result<(), SessionError> {
         self.rpc_server.disconnect(player)
     }
@@ -0,0 +1,330 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Shipping checkpoints to a warm standby.
+//!
+//! With `--standby-ship-to`, each textdump checkpoint, once it's been written, is copied to a
+//! second location as a snapshot: a local directory (e.g. a volume mounted from another machine),
+//! or an HTTP(S) URL under which files can be PUT, fetched and deleted, such as an S3-compatible
+//! bucket. Requests aren't signed, so a bucket has to accept them as they come: through a
+//! pre-authorized path, or a signing proxy.
+//!
+//! Alongside the snapshots is `manifest.json`, listing them oldest first, each with its size and
+//! SHA-256, so a copy can be checked before it's used:
+//!
+//! ```json
+//! {"snapshots": [{"file": "snapshot-20250101T000000.000Z-9f86d081884c.textdump", "bytes": 1234,
+//!                 "sha256": "9f86d0...", "created": 1735689600}]}
+//! ```
+//!
+//! Only the newest `--standby-retain` snapshots are kept.
+//!
+//! `--restore-standby` brings a standby up: it fetches the newest snapshot listed, checks it
+//! against the manifest, and starts a new database from it, as if it had been given as
+//! `--textdump`.
+
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use eyre::{bail, eyre, Report};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+const MANIFEST: &str = "manifest.json";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where snapshots are shipped to, or restored from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    Directory(PathBuf),
+    /// A base URL, which file names are appended to.
+    Http(String),
+}
+
+impl Location {
+    pub fn parse(location: &str) -> Self {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Location::Http(location.trim_end_matches('/').to_string())
+        } else {
+            Location::Directory(PathBuf::from(location))
+        }
+    }
+
+    /// The contents of the file `name`, or None if there isn't one.
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>, Report> {
+        match self {
+            Location::Directory(dir) => match std::fs::read(dir.join(name)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+            Location::Http(base) => {
+                let request = ureq::get(&format!("{base}/{name}")).timeout(REQUEST_TIMEOUT);
+                match request.call() {
+                    Ok(response) => {
+                        let mut bytes = vec![];
+                        response.into_reader().read_to_end(&mut bytes)?;
+                        Ok(Some(bytes))
+                    }
+                    Err(ureq::Error::Status(404, _)) => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<(), Report> {
+        match self {
+            Location::Directory(dir) => {
+                std::fs::create_dir_all(dir)?;
+                // Moved into place, so that a standby never reads a file half-written.
+                let partial = dir.join(format!("{name}.partial"));
+                std::fs::write(&partial, bytes)?;
+                std::fs::rename(&partial, dir.join(name))?;
+            }
+            Location::Http(base) => {
+                ureq::put(&format!("{base}/{name}"))
+                    .timeout(REQUEST_TIMEOUT)
+                    .send_bytes(bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<(), Report> {
+        match self {
+            Location::Directory(dir) => match std::fs::remove_file(dir.join(name)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            },
+            Location::Http(base) => {
+                ureq::delete(&format!("{base}/{name}"))
+                    .timeout(REQUEST_TIMEOUT)
+                    .call()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A shipped snapshot, as listed in the manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub file: String,
+    pub bytes: u64,
+    pub sha256: String,
+    /// When it was shipped, in seconds since the epoch.
+    pub created: i64,
+}
+
+impl Snapshot {
+    fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "bytes": self.bytes,
+            "sha256": self.sha256,
+            "created": self.created,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Snapshot {
+            file: value.get("file")?.as_str()?.to_string(),
+            bytes: value.get("bytes")?.as_u64()?,
+            sha256: value.get("sha256")?.as_str()?.to_string(),
+            created: value.get("created")?.as_i64()?,
+        })
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The snapshots listed in the manifest at `location`, oldest first. None yet if there's no
+/// manifest.
+fn read_manifest(location: &Location) -> Result<Vec<Snapshot>, Report> {
+    let Some(manifest) = location.read(MANIFEST)? else {
+        return Ok(vec![]);
+    };
+    let manifest: Value = serde_json::from_slice(&manifest)?;
+    let Some(snapshots) = manifest.get("snapshots").and_then(Value::as_array) else {
+        bail!("Standby manifest has no list of snapshots");
+    };
+    snapshots
+        .iter()
+        .map(|s| Snapshot::from_json(s).ok_or_else(|| eyre!("Invalid standby manifest entry: {s}")))
+        .collect()
+}
+
+fn write_manifest(location: &Location, snapshots: &[Snapshot]) -> Result<(), Report> {
+    let manifest = json!({
+        "snapshots": snapshots.iter().map(Snapshot::to_json).collect::<Vec<_>>(),
+    });
+    location.write(MANIFEST, manifest.to_string().as_bytes())
+}
+
+/// Ship the textdump at `textdump` to `location` as a new snapshot, keeping only the newest
+/// `retain` there.
+pub fn ship(textdump: &Path, location: &Location, retain: usize) -> Result<Snapshot, Report> {
+    let bytes = std::fs::read(textdump)?;
+    let created = Utc::now();
+    let sha256 = sha256_hex(&bytes);
+    let snapshot = Snapshot {
+        file: format!(
+            "snapshot-{}-{}.textdump",
+            created.format("%Y%m%dT%H%M%S%.3fZ"),
+            &sha256[..12]
+        ),
+        bytes: bytes.len() as u64,
+        sha256,
+        created: created.timestamp(),
+    };
+    location.write(&snapshot.file, &bytes)?;
+
+    let mut snapshots = read_manifest(location)?;
+    snapshots.push(snapshot.clone());
+    let expired = snapshots.len().saturating_sub(retain.max(1));
+    let expired: Vec<_> = snapshots.drain(..expired).collect();
+    // The manifest goes first, so that it never lists a snapshot which has been removed.
+    write_manifest(location, &snapshots)?;
+    for old in expired {
+        if snapshots.iter().any(|s| s.file == old.file) {
+            continue;
+        }
+        if let Err(e) = location.remove(&old.file) {
+            warn!(file = old.file, error = ?e, "Unable to remove old standby snapshot");
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Fetch the newest snapshot listed at `location`, check it against the manifest, and write it
+/// to `to`.
+pub fn restore(location: &Location, to: &Path) -> Result<Snapshot, Report> {
+    let snapshots = read_manifest(location)?;
+    let Some(snapshot) = snapshots.last() else {
+        bail!("No standby snapshots at {location:?}");
+    };
+    let Some(bytes) = location.read(&snapshot.file)? else {
+        bail!("Standby snapshot {} is missing", snapshot.file);
+    };
+    if bytes.len() as u64 != snapshot.bytes || sha256_hex(&bytes) != snapshot.sha256 {
+        bail!(
+            "Standby snapshot {} doesn't match its checksum in the manifest",
+            snapshot.file
+        );
+    }
+    std::fs::write(to, bytes)?;
+    Ok(snapshot.clone())
+}
+
+/// Ship the textdump at `textdump` each time it's rewritten, looking every `interval`, until
+/// `kill_switch` is thrown.
+pub fn ship_loop(
+    textdump: PathBuf,
+    location: Location,
+    retain: usize,
+    interval: Duration,
+    kill_switch: Arc<AtomicBool>,
+) {
+    let mut last_shipped: Option<SystemTime> = None;
+    loop {
+        std::thread::sleep(interval);
+        if kill_switch.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(modified) = std::fs::metadata(&textdump).and_then(|m| m.modified()) else {
+            continue;
+        };
+        if last_shipped == Some(modified) {
+            continue;
+        }
+        match ship(&textdump, &location, retain) {
+            Ok(snapshot) => {
+                info!(
+                    file = snapshot.file,
+                    bytes = snapshot.bytes,
+                    "Shipped checkpoint to standby"
+                );
+                last_shipped = Some(modified);
+            }
+            Err(e) => warn!(error = ?e, "Unable to ship checkpoint to standby"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_manifest, restore, ship, Location, MANIFEST};
+
+    #[test]
+    fn test_ship_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let textdump = dir.path().join("checkpoint.db");
+        let location = Location::Directory(dir.path().join("standby"));
+
+        for i in 0..3 {
+            std::fs::write(&textdump, format!("textdump {i}")).unwrap();
+            ship(&textdump, &location, 2).unwrap();
+        }
+
+        // Only the newest two are kept, and the manifest lists just those.
+        let snapshots = read_manifest(&location).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        let shipped = std::fs::read_dir(dir.path().join("standby"))
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().file_name() != MANIFEST)
+            .count();
+        assert_eq!(shipped, 2);
+
+        let restored = dir.path().join("restored.db");
+        restore(&location, &restored).unwrap();
+        assert_eq!(std::fs::read_to_string(&restored).unwrap(), "textdump 2");
+    }
+
+    #[test]
+    fn test_restore_rejects_corrupt_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let textdump = dir.path().join("checkpoint.db");
+        let standby = dir.path().join("standby");
+        let location = Location::Directory(standby.clone());
+
+        std::fs::write(&textdump, "textdump").unwrap();
+        let snapshot = ship(&textdump, &location, 5).unwrap();
+        std::fs::write(standby.join(&snapshot.file), "tampered").unwrap();
+
+        assert!(restore(&location, &dir.path().join("restored.db")).is_err());
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            Location::parse("https://bucket.example.com/moor/"),
+            Location::Http("https://bucket.example.com/moor".to_string())
+        );
+        assert_eq!(
+            Location::parse("/mnt/standby"),
+            Location::Directory("/mnt/standby".into())
+        );
+    }
+}
@@ -11,13 +11,14 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::federation::FederationError;
 use crate::rpc_server::RpcServer;
 use moor_kernel::tasks::sessions::SessionError::DeliveryError;
-use moor_kernel::tasks::sessions::SystemControl;
-use moor_values::Obj;
+use moor_kernel::tasks::sessions::{SystemControl, WebhookFilter};
+use moor_values::{List, Obj, Symbol};
 use rpc_common::{HostBroadcastEvent, HostType, HOST_BROADCAST_TOPIC};
 use std::sync::atomic::Ordering;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 impl SystemControl for RpcServer {
     fn shutdown(&self, msg: Option<String>) -> Result<(), moor_values::Error> {
@@ -33,10 +34,12 @@ impl SystemControl for RpcServer {
         port: u16,
         print_messages: bool,
     ) -> Result<(), moor_values::Error> {
-        let host_type = match host_type {
-            "tcp" => HostType::TCP,
-            _ => return Err(moor_values::Error::E_INVARG),
+        let Some(host_type) = HostType::parse_id_str(host_type) else {
+            return Err(moor_values::Error::E_INVARG);
         };
+        if self.hosts.lock().unwrap().is_listening(host_type, port) {
+            return Err(moor_values::Error::E_INVARG);
+        }
 
         let event = HostBroadcastEvent::Listen {
             handler_object,
@@ -50,7 +53,7 @@ impl SystemControl for RpcServer {
         // We want responses from all clients, so send on this broadcast "topic"
         let payload = vec![HOST_BROADCAST_TOPIC.to_vec(), event_bytes];
         {
-            let publish = self.events_publish.lock().unwrap();
+            let mut publish = self.events_publish.lock().unwrap();
             publish
                 .send_multipart(payload, 0)
                 .map_err(|e| {
@@ -62,15 +65,21 @@ impl SystemControl for RpcServer {
                     moor_values::Error::E_INVARG
                 })?;
         }
+        self.hosts
+            .lock()
+            .unwrap()
+            .request_listener(host_type, port, print_messages);
 
         Ok(())
     }
 
     fn unlisten(&self, port: u16, host_type: &str) -> Result<(), moor_values::Error> {
-        let host_type = match host_type {
-            "tcp" => HostType::TCP,
-            _ => return Err(moor_values::Error::E_INVARG),
+        let Some(host_type) = HostType::parse_id_str(host_type) else {
+            return Err(moor_values::Error::E_INVARG);
         };
+        if !self.hosts.lock().unwrap().is_listening(host_type, port) {
+            return Err(moor_values::Error::E_INVARG);
+        }
 
         let event = HostBroadcastEvent::Unlisten { host_type, port };
 
@@ -79,7 +88,7 @@ impl SystemControl for RpcServer {
         // We want responses from all clients, so send on this broadcast "topic"
         let payload = vec![HOST_BROADCAST_TOPIC.to_vec(), event_bytes];
         {
-            let publish = self.events_publish.lock().unwrap();
+            let mut publish = self.events_publish.lock().unwrap();
             publish
                 .send_multipart(payload, 0)
                 .map_err(|e| {
@@ -91,6 +100,7 @@ impl SystemControl for RpcServer {
                     moor_values::Error::E_INVARG
                 })?;
         }
+        self.hosts.lock().unwrap().remove_listener(host_type, port);
         Ok(())
     }
 
@@ -99,8 +109,95 @@ impl SystemControl for RpcServer {
         let listeners = hosts
             .listeners()
             .iter()
-            .map(|(o, t, h)| (o.clone(), t.id_str().to_string(), h.port(), true))
+            .map(|(o, t, h)| {
+                let print_messages = hosts.prints_messages(*t, h.port());
+                (o.clone(), t.id_str().to_string(), h.port(), print_messages)
+            })
             .collect();
         Ok(listeners)
     }
+
+    fn ban_site(&self, site: &str) -> Result<bool, moor_values::Error> {
+        info!(?site, "Banning site");
+        self.connections.ban_site(site).map_err(|e| {
+            error!(error = ?e, "Could not ban site");
+            moor_values::Error::E_INVARG
+        })
+    }
+
+    fn unban_site(&self, site: &str) -> Result<bool, moor_values::Error> {
+        info!(?site, "Unbanning site");
+        self.connections.unban_site(site).map_err(|e| {
+            error!(error = ?e, "Could not unban site");
+            moor_values::Error::E_INVARG
+        })
+    }
+
+    fn banned_sites(&self) -> Result<Vec<String>, moor_values::Error> {
+        Ok(self.connections.banned_sites())
+    }
+
+    fn revoke_auth_tokens(&self, player: &Obj) -> Result<(), moor_values::Error> {
+        RpcServer::revoke_auth_tokens(self, player).map_err(|e| {
+            error!(error = ?e, "Could not revoke auth tokens");
+            moor_values::Error::E_INVARG
+        })
+    }
+
+    fn switch_player(&self, old_player: &Obj, new_player: &Obj) -> Result<(), moor_values::Error> {
+        RpcServer::switch_player(self, old_player, new_player).map_err(|e| {
+            error!(error = ?e, "Could not switch player");
+            moor_values::Error::E_INVARG
+        })
+    }
+
+    fn add_webhook(
+        &self,
+        url: &str,
+        secret: Option<String>,
+        filter: WebhookFilter,
+    ) -> Result<u64, moor_values::Error> {
+        info!(?url, ?filter, "Adding webhook");
+        self.connections
+            .add_webhook(url, secret, filter)
+            .map_err(|e| {
+                error!(error = ?e, "Could not add webhook");
+                moor_values::Error::E_INVARG
+            })
+    }
+
+    fn remove_webhook(&self, id: u64) -> Result<bool, moor_values::Error> {
+        info!(?id, "Removing webhook");
+        self.connections.remove_webhook(id).map_err(|e| {
+            error!(error = ?e, "Could not remove webhook");
+            moor_values::Error::E_INVARG
+        })
+    }
+
+    fn webhooks(&self) -> Result<Vec<(u64, String, WebhookFilter)>, moor_values::Error> {
+        Ok(self
+            .connections
+            .webhooks()
+            .into_iter()
+            .map(|webhook| (webhook.id, webhook.url, webhook.filter))
+            .collect())
+    }
+
+    fn federation_send(
+        &self,
+        world: &str,
+        target: &Obj,
+        verb: Symbol,
+        args: List,
+    ) -> Result<(), moor_values::Error> {
+        let Some(federation) = &self.federation else {
+            return Err(moor_values::Error::E_INVARG);
+        };
+        federation
+            .send(world, target, verb, args)
+            .map_err(|e| match e {
+                FederationError::UnknownWorld(_) => moor_values::Error::E_INVARG,
+                FederationError::DeliveryFailed(_) => moor_values::Error::E_QUOTA,
+            })
+    }
 }
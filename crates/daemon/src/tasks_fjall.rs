@@ -144,6 +144,14 @@ mod tests {
             fg_seconds: 0,
             fg_ticks: 0,
             max_stack_depth: 0,
+            argon2_memory_cost: 0,
+            argon2_time_cost: 0,
+            argon2_parallelism: 0,
+            bcrypt_cost: 0,
+            default_timezone: None,
+            server_too_busy_msg: None,
+            queued_task_limit: None,
+            connection_task_limit: None,
         };
 
         /*
@@ -202,6 +210,14 @@ mod tests {
                 fg_seconds: 0,
                 fg_ticks: 0,
                 max_stack_depth: 0,
+                argon2_memory_cost: 0,
+                argon2_time_cost: 0,
+                argon2_parallelism: 0,
+                bcrypt_cost: 0,
+                default_timezone: None,
+                server_too_busy_msg: None,
+                queued_task_limit: None,
+                connection_task_limit: None,
             };
 
             let task = Task::new(
@@ -259,6 +275,14 @@ mod tests {
                 fg_seconds: 0,
                 fg_ticks: 0,
                 max_stack_depth: 0,
+                argon2_memory_cost: 0,
+                argon2_time_cost: 0,
+                argon2_parallelism: 0,
+                bcrypt_cost: 0,
+                default_timezone: None,
+                server_too_busy_msg: None,
+                queued_task_limit: None,
+                connection_task_limit: None,
             };
 
             let task = Task::new(
@@ -0,0 +1,458 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Webhooks: POSTing what committed transactions changed to URLs wizards have registered with
+//! `add_webhook()`.
+//!
+//! Each webhook has a filter on the object, the verb or property name, and the kind of change.
+//! For each commit with any changes matching it, the webhook's URL is sent one JSON document:
+//!
+//! ```json
+//! {"webhook": 1, "timestamp": 1234,
+//!  "changes": [{"event": "property_changed", "object": "#5", "property": "description"}]}
+//! ```
+//!
+//! If the webhook has a secret, the request has an `X-Moor-Signature: sha256=<hex>` header, the
+//! HMAC-SHA256 of the body keyed with the secret. Failed deliveries are retried a few times, with
+//! backoff, and then dropped.
+//!
+//! Each webhook has a thread of its own making its deliveries, in commit order, so a slow endpoint
+//! only holds up its own. Up to `QUEUE_LENGTH` deliveries can wait for it; beyond that (e.g. while
+//! its endpoint is down) new ones are dropped, with a warning, rather than piling up.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
+use hmac::{Hmac, Mac};
+use moor_db::{Change, CommittedChanges};
+use moor_kernel::tasks::sessions::WebhookFilter;
+use serde_json::json;
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::connections::ConnectionsDB;
+
+/// How many times a delivery is attempted before it's dropped.
+const MAX_ATTEMPTS: u32 = 4;
+/// The wait before the first retry. It doubles with each one after that.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many deliveries can wait for each webhook before new ones are dropped.
+const QUEUE_LENGTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Webhook {
+    pub id: u64,
+    pub url: String,
+    pub secret: Option<String>,
+    pub filter: WebhookFilter,
+}
+
+impl Webhook {
+    fn matches(&self, change: &Change) -> bool {
+        let filter = &self.filter;
+        if !filter.events.is_empty() && !filter.events.iter().any(|e| e == event_name(change)) {
+            return false;
+        }
+        let (obj, verbs, property) = match change {
+            Change::ObjectCreated(obj) | Change::ObjectRecycled(obj) => (obj, None, None),
+            Change::PropertyChanged(obj, name) => (obj, None, Some(name)),
+            Change::VerbProgrammed(obj, names) => (obj, Some(names), None),
+        };
+        if filter.object.as_ref().is_some_and(|o| o != obj) {
+            return false;
+        }
+        if let Some(verb) = &filter.verb {
+            let Some(names) = verbs else {
+                return false;
+            };
+            if !names
+                .iter()
+                .any(|name| name.as_str().eq_ignore_ascii_case(verb.as_str()))
+            {
+                return false;
+            }
+        }
+        if let Some(wanted) = &filter.property {
+            if !property.is_some_and(|p| p.as_str().eq_ignore_ascii_case(wanted.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The request body for the changes in `committed` this webhook is interested in, if any.
+    fn body(&self, committed: &CommittedChanges) -> Option<String> {
+        let changes: Vec<_> = committed
+            .changes
+            .iter()
+            .filter(|change| self.matches(change))
+            .map(change_json)
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        let body = json!({
+            "webhook": self.id,
+            "timestamp": committed.timestamp,
+            "changes": changes,
+        });
+        Some(body.to_string())
+    }
+
+    fn deliver(&self, body: &str) {
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = ureq::post(&self.url)
+                .timeout(REQUEST_TIMEOUT)
+                .set("Content-Type", "application/json")
+                .set("X-Moor-Webhook", &self.id.to_string());
+            if let Some(secret) = &self.secret {
+                request = request.set("X-Moor-Signature", &signature(secret, body));
+            }
+            match request.send_string(body) {
+                Ok(_) => return,
+                Err(e) => {
+                    warn!(id = self.id, url = %self.url, attempt, error = ?e, "Webhook delivery failed");
+                }
+            }
+            if attempt < MAX_ATTEMPTS {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+        }
+        warn!(
+            id = self.id,
+            url = %self.url,
+            "Giving up on webhook delivery after {MAX_ATTEMPTS} attempts"
+        );
+    }
+}
+
+fn event_name(change: &Change) -> &'static str {
+    match change {
+        Change::ObjectCreated(_) => "object_created",
+        Change::ObjectRecycled(_) => "object_recycled",
+        Change::PropertyChanged(_, _) => "property_changed",
+        Change::VerbProgrammed(_, _) => "verb_programmed",
+    }
+}
+
+fn change_json(change: &Change) -> serde_json::Value {
+    match change {
+        Change::ObjectCreated(obj) | Change::ObjectRecycled(obj) => json!({
+            "event": event_name(change),
+            "object": obj.to_string(),
+        }),
+        Change::PropertyChanged(obj, name) => json!({
+            "event": event_name(change),
+            "object": obj.to_string(),
+            "property": name.as_str(),
+        }),
+        Change::VerbProgrammed(obj, names) => json!({
+            "event": event_name(change),
+            "object": obj.to_string(),
+            "verbs": names.iter().map(|n| n.as_str()).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `body`, keyed with `secret`.
+fn signature(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={hex}")
+}
+
+/// A webhook's delivery thread, and the queue of request bodies waiting for it. The thread stops
+/// once the worker is dropped and it's done with what's queued, or the kill switch is thrown.
+struct Worker {
+    webhook: Webhook,
+    queue: Sender<String>,
+    /// How many deliveries have been dropped because the queue was full.
+    dropped: u64,
+}
+
+impl Worker {
+    fn start(webhook: Webhook, kill_switch: Arc<AtomicBool>) -> Self {
+        let (queue, bodies) = crossbeam_channel::bounded::<String>(QUEUE_LENGTH);
+        let delivering = webhook.clone();
+        std::thread::Builder::new()
+            .name(format!("moor-webhook-{}", webhook.id))
+            .spawn(move || loop {
+                if kill_switch.load(Ordering::Relaxed) {
+                    break;
+                }
+                match bodies.recv_timeout(Duration::from_secs(1)) {
+                    Ok(body) => delivering.deliver(&body),
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            })
+            .expect("Unable to start webhook delivery thread");
+        Self {
+            webhook,
+            queue,
+            dropped: 0,
+        }
+    }
+
+    fn queue(&mut self, body: String) {
+        if let Err(TrySendError::Full(_)) = self.queue.try_send(body) {
+            self.dropped += 1;
+            warn!(
+                id = self.webhook.id,
+                url = %self.webhook.url,
+                dropped = self.dropped,
+                "Webhook delivery queue is full; dropping delivery"
+            );
+        }
+    }
+}
+
+/// The delivery workers of the webhooks with anything to deliver, by id.
+#[derive(Default)]
+struct Workers {
+    workers: HashMap<u64, Worker>,
+}
+
+impl Workers {
+    /// Queue the deliveries of `committed` to those of `webhooks` interested in it, starting
+    /// workers for new or changed webhooks, and stopping those of webhooks which have gone.
+    fn dispatch(
+        &mut self,
+        webhooks: Vec<Webhook>,
+        committed: &CommittedChanges,
+        kill_switch: &Arc<AtomicBool>,
+    ) {
+        self.workers
+            .retain(|_, worker| webhooks.contains(&worker.webhook));
+        for webhook in webhooks {
+            let Some(body) = webhook.body(committed) else {
+                continue;
+            };
+            self.workers
+                .entry(webhook.id)
+                .or_insert_with(|| Worker::start(webhook, kill_switch.clone()))
+                .queue(body);
+        }
+    }
+}
+
+/// Hand each batch of committed changes to the workers of the webhooks interested in it, until
+/// the kill switch is thrown or the database goes away.
+pub fn delivery_loop(
+    changes: Receiver<CommittedChanges>,
+    connections: Arc<dyn ConnectionsDB + Send + Sync>,
+    kill_switch: Arc<AtomicBool>,
+) {
+    info!("Webhook delivery started");
+    let mut workers = Workers::default();
+    loop {
+        if kill_switch.load(Ordering::Relaxed) {
+            break;
+        }
+        let committed = match changes.recv_timeout(Duration::from_secs(1)) {
+            Ok(committed) => committed,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        workers.dispatch(connections.webhooks(), &committed, &kill_switch);
+    }
+    info!("Webhook delivery stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use crossbeam_channel::Sender;
+    use moor_db::{Change, CommittedChanges};
+    use moor_kernel::tasks::sessions::WebhookFilter;
+    use moor_values::{Obj, Symbol};
+
+    use crate::webhooks::{signature, Webhook, Workers, QUEUE_LENGTH};
+
+    fn webhook(filter: WebhookFilter) -> Webhook {
+        Webhook {
+            id: 7,
+            url: "http://localhost:9000/".to_string(),
+            secret: None,
+            filter,
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let changes = CommittedChanges {
+            timestamp: 42,
+            changes: vec![
+                Change::ObjectCreated(Obj::mk_id(5)),
+                Change::PropertyChanged(Obj::mk_id(5), Symbol::mk("description")),
+                Change::VerbProgrammed(Obj::mk_id(6), vec![Symbol::mk("look"), Symbol::mk("l")]),
+            ],
+        };
+
+        let everything = webhook(WebhookFilter::default());
+        assert!(changes.changes.iter().all(|c| everything.matches(c)));
+
+        let on_five = webhook(WebhookFilter {
+            object: Some(Obj::mk_id(5)),
+            ..Default::default()
+        });
+        let matching: Vec<_> = changes
+            .changes
+            .iter()
+            .filter(|c| on_five.matches(c))
+            .collect();
+        assert_eq!(matching.len(), 2);
+
+        let look = webhook(WebhookFilter {
+            verb: Some(Symbol::mk("LOOK")),
+            ..Default::default()
+        });
+        let matching: Vec<_> = changes.changes.iter().filter(|c| look.matches(c)).collect();
+        assert_eq!(matching, vec![&changes.changes[2]]);
+
+        let created = webhook(WebhookFilter {
+            events: vec!["object_created".to_string()],
+            ..Default::default()
+        });
+        let body: serde_json::Value =
+            serde_json::from_str(&created.body(&changes).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "webhook": 7,
+                "timestamp": 42,
+                "changes": [{"event": "object_created", "object": "#5"}],
+            })
+        );
+
+        let recycled = webhook(WebhookFilter {
+            events: vec!["object_recycled".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(recycled.body(&changes), None);
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            signature("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    /// Accept one request on a local port, sending its body to `bodies`, and return the URL.
+    fn serve_once(bodies: Sender<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            bodies.send(String::from_utf8(body).unwrap()).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn test_unreachable_webhook() {
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        let (bodies_send, bodies) = crossbeam_channel::unbounded();
+        let dead = Webhook {
+            id: 1,
+            // Nothing listens on port 1.
+            url: "http://127.0.0.1:1/".to_string(),
+            ..webhook(WebhookFilter::default())
+        };
+        let live = Webhook {
+            id: 2,
+            url: serve_once(bodies_send),
+            ..webhook(WebhookFilter::default())
+        };
+
+        // Queuing deliveries doesn't wait for them, and the dead webhook's queue overflows rather
+        // than growing.
+        let mut workers = Workers::default();
+        let started = Instant::now();
+        let commits = QUEUE_LENGTH as u64 + 10;
+        for timestamp in 0..commits {
+            let committed = CommittedChanges {
+                timestamp,
+                changes: vec![Change::ObjectCreated(Obj::mk_id(5))],
+            };
+            workers.dispatch(vec![dead.clone(), live.clone()], &committed, &kill_switch);
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        // At most one is being delivered, and the queue is full behind it.
+        assert!(workers.workers[&1].dropped >= commits - QUEUE_LENGTH as u64 - 1);
+
+        // Nor does it hold up the other webhook.
+        let body: serde_json::Value =
+            serde_json::from_str(&bodies.recv_timeout(Duration::from_secs(5)).unwrap()).unwrap();
+        assert_eq!(body["webhook"], 2);
+        assert_eq!(body["timestamp"], 0);
+
+        kill_switch.store(true, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_removed_webhook_stops() {
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        let committed = CommittedChanges {
+            timestamp: 1,
+            changes: vec![Change::ObjectCreated(Obj::mk_id(5))],
+        };
+        let mut workers = Workers::default();
+        workers.dispatch(
+            vec![webhook(WebhookFilter::default())],
+            &committed,
+            &kill_switch,
+        );
+        assert_eq!(workers.workers.len(), 1);
+        workers.dispatch(vec![], &committed, &kill_switch);
+        assert!(workers.workers.is_empty());
+
+        kill_switch.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Where values too large to keep inline in their relation's partition go, so that a few huge
+//! property values don't bloat it and slow down scans and compaction of everything else, and
+//! where values which are stored many times over (verb programs) can be kept once.
+//!
+//! An offloaded value leaves behind only its timestamp, marked with `OFFLOADED`, followed by
+//! the hash of its content if it was deduplicated (see `content`); otherwise it's in the
+//! relation's blob partition, under the same key. It's read from there when the relation's entry
+//! for it is loaded into the cache, i.e. only when that value itself is wanted.
+//!
+//! Which values get offloaded, and how, only decides how they're written: whatever was written
+//! under an earlier configuration can still be read.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use fjall::{PartitionHandle, UserValue};
+use moor_values::model::CacheStats;
+
+use crate::content::{ContentHash, ContentStore};
+use crate::tx::Error;
+
+/// Set in the stored timestamp of an entry whose value is offloaded. Timestamps are transaction
+/// numbers, so never get this high.
+pub(crate) const OFFLOADED: u64 = 1 << 63;
+
+/// Where a stored entry's value is, going by what's stored for it in the relation's partition.
+enum Stub {
+    Inline,
+    Keyed,
+    Content(ContentHash),
+}
+
+impl Stub {
+    fn of(stored: &[u8]) -> Self {
+        let ts = u64::from_le_bytes(stored[0..8].try_into().unwrap());
+        if ts & OFFLOADED == 0 {
+            return Stub::Inline;
+        }
+        match <ContentHash>::try_from(&stored[8..]) {
+            Ok(hash) => Stub::Content(hash),
+            Err(_) => Stub::Keyed,
+        }
+    }
+}
+
+/// The relation's own blob partition.
+struct Keyed {
+    partition: PartitionHandle,
+    snapshot: Option<fjall::Snapshot>,
+}
+
+pub(crate) struct BlobStore {
+    keyed: Option<Keyed>,
+    content: Arc<ContentStore>,
+    /// Values whose encoding is larger than this are offloaded; None for none.
+    threshold: Option<usize>,
+    /// Whether offloaded values are deduplicated in `content`, rather than kept under their key.
+    dedup: bool,
+    /// Values offloaded since startup.
+    stored: AtomicUsize,
+    /// Reads of values which were offloaded, and the bytes read for them.
+    loads: AtomicUsize,
+    loaded_bytes: AtomicUsize,
+    /// Reads of values which were kept inline.
+    inline_reads: AtomicUsize,
+}
+
+impl BlobStore {
+    /// Offload values larger than `threshold` to `keyed` (the relation's blob partition, if it
+    /// has one), or to `content` if deduplicating, which must be over the same snapshot, if any.
+    pub fn new(
+        keyed: Option<PartitionHandle>,
+        content: Arc<ContentStore>,
+        snapshot_at: Option<fjall::Instant>,
+        threshold: Option<usize>,
+        dedup: bool,
+    ) -> Self {
+        let keyed = keyed.map(|partition| Keyed {
+            snapshot: snapshot_at.map(|instant| partition.snapshot_at(instant)),
+            partition,
+        });
+        Self {
+            // Without a blob partition of its own, all there is is the content store.
+            dedup: dedup || keyed.is_none(),
+            keyed,
+            content,
+            threshold,
+            stored: AtomicUsize::new(0),
+            loads: AtomicUsize::new(0),
+            loaded_bytes: AtomicUsize::new(0),
+            inline_reads: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn offloads(&self, value_len: usize) -> bool {
+        self.threshold
+            .is_some_and(|threshold| value_len > threshold)
+    }
+
+    /// Offload the value for `key`, returning what's to follow the timestamp in its stub.
+    pub fn store(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        let stub = match &self.keyed {
+            Some(keyed) if !self.dedup => {
+                keyed
+                    .partition
+                    .insert(key, value)
+                    .map_err(|e| Error::StorageFailure(e.to_string()))?;
+                vec![]
+            }
+            _ => self.content.add(value)?.to_vec(),
+        };
+        self.stored.fetch_add(1, Ordering::Relaxed);
+        Ok(stub)
+    }
+
+    /// The offloaded value for `key`, given what was stored for it in the relation's partition.
+    pub fn load(&self, key: &[u8], stored: &[u8]) -> Result<UserValue, Error> {
+        let value = match Stub::of(stored) {
+            Stub::Content(hash) => self.content.get(&hash)?,
+            Stub::Keyed | Stub::Inline => {
+                let keyed = self.keyed.as_ref().ok_or(Error::EncodingFailure)?;
+                let value = match &keyed.snapshot {
+                    Some(snapshot) => snapshot.get(key),
+                    None => keyed.partition.get(key),
+                };
+                value
+                    .map_err(|e| Error::RetrievalFailure(e.to_string()))?
+                    .ok_or_else(|| {
+                        Error::RetrievalFailure("offloaded value is missing".to_string())
+                    })?
+            }
+        };
+        self.loads.fetch_add(1, Ordering::Relaxed);
+        self.loaded_bytes.fetch_add(value.len(), Ordering::Relaxed);
+        Ok(value)
+    }
+
+    pub fn record_inline_read(&self) {
+        self.inline_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Let go of what was offloaded for the value `old` (as stored in the relation's partition)
+    /// under `key`, now that it's been deleted or replaced. `rekeyed` is whether the value it was
+    /// replaced by was itself stored under the key, in its place.
+    pub fn replaced(&self, key: &[u8], old: Option<&[u8]>, rekeyed: bool) -> Result<(), Error> {
+        let Some(old) = old else {
+            return Ok(());
+        };
+        match Stub::of(old) {
+            Stub::Inline => Ok(()),
+            Stub::Content(hash) => self.content.release(&hash),
+            Stub::Keyed if rekeyed => Ok(()),
+            Stub::Keyed => {
+                let Some(keyed) = &self.keyed else {
+                    return Ok(());
+                };
+                keyed
+                    .partition
+                    .remove(key)
+                    .map_err(|e| Error::StorageFailure(e.to_string()))
+            }
+        }
+    }
+
+    /// How offloading is going, in the shape of a cache's statistics: `entries` is the number of
+    /// values offloaded since startup, `bytes` what's been read back, and a hit a read which had
+    /// to come here (against a miss for one kept inline), so the hit rate is the fraction of
+    /// reads which were of offloaded values.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.stored.load(Ordering::Relaxed),
+            used_bytes: self.loaded_bytes.load(Ordering::Relaxed),
+            threshold_bytes: self.threshold.unwrap_or(0),
+            hits: self.loads.load(Ordering::Relaxed),
+            misses: self.inline_reads.load(Ordering::Relaxed),
+            evictions: 0,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Change-data capture: a stream, for anyone who subscribes, of what each committed transaction
+//! changed, for webhooks, indexers, replication and the like.
+//!
+//! Changes are worked out from each transaction's working sets as it commits, so they're only as
+//! fine-grained as the relations are: e.g. a property "changing" means its value was written,
+//! not necessarily that it now holds something different.
+
+use std::sync::Mutex;
+
+use crossbeam_channel::{Receiver, Sender};
+use moor_values::model::{HasUuid, Named};
+use moor_values::{Obj, Symbol};
+
+use crate::tx::OpType;
+use crate::worldstate_db::WorkingSets;
+use crate::worldstate_transaction::WorldStateTransaction;
+use crate::ObjAndUUIDHolder;
+
+/// Something a committed transaction did to the world.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The object was created.
+    ObjectCreated(Obj),
+    /// The object was recycled.
+    ObjectRecycled(Obj),
+    /// The named property's value on the object was set, or cleared (so that it's inherited).
+    PropertyChanged(Obj, Symbol),
+    /// The verb with these names on the object was (re)programmed.
+    VerbProgrammed(Obj, Vec<Symbol>),
+}
+
+/// Everything one transaction changed, in the order transactions committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommittedChanges {
+    /// The transaction's timestamp. Increases from one commit to the next.
+    pub timestamp: u64,
+    pub changes: Vec<Change>,
+}
+
+/// The keys touched by a commit, noted before its working sets are applied, to be turned into
+/// `Change`s once it has succeeded.
+pub(crate) struct PendingChanges {
+    timestamp: u64,
+    created: Vec<Obj>,
+    recycled: Vec<Obj>,
+    properties: Vec<ObjAndUUIDHolder>,
+    verbs: Vec<ObjAndUUIDHolder>,
+}
+
+impl PendingChanges {
+    pub(crate) fn from_working_sets(ws: &WorkingSets) -> Self {
+        let mut created = vec![];
+        let mut recycled = vec![];
+        for (obj, op) in &ws.object_flags {
+            match op.to_type {
+                OpType::Insert => created.push(obj.clone()),
+                OpType::Delete => recycled.push(obj.clone()),
+                _ => {}
+            }
+        }
+        Self {
+            timestamp: ws.tx.ts.0,
+            created,
+            recycled,
+            properties: ws
+                .object_propvalues
+                .iter()
+                .map(|(k, _)| k.clone())
+                .collect(),
+            verbs: ws
+                .object_verbs
+                .iter()
+                .filter(|(_, op)| op.to_type != OpType::Delete)
+                .map(|(k, _)| k.clone())
+                .collect(),
+        }
+    }
+
+    /// Work out the changes, looking up names in `tx`, which must see the commit. Anything which
+    /// has since gone (e.g. the properties of an object recycled in the same transaction) is
+    /// left out.
+    pub(crate) fn resolve<TX: WorldStateTransaction>(self, tx: &TX) -> CommittedChanges {
+        let mut changes: Vec<Change> = self
+            .created
+            .into_iter()
+            .map(Change::ObjectCreated)
+            .collect();
+        for holder in self.properties {
+            if let Some(name) = property_name(tx, &holder) {
+                changes.push(Change::PropertyChanged(holder.obj, name));
+            }
+        }
+        for holder in self.verbs {
+            let Ok(verbdefs) = tx.get_verbs(&holder.obj) else {
+                continue;
+            };
+            if let Some(verbdef) = verbdefs.find(&holder.uuid) {
+                let names = verbdef.names().into_iter().map(Symbol::mk).collect();
+                changes.push(Change::VerbProgrammed(holder.obj, names));
+            }
+        }
+        changes.extend(self.recycled.into_iter().map(Change::ObjectRecycled));
+        CommittedChanges {
+            timestamp: self.timestamp,
+            changes,
+        }
+    }
+}
+
+/// Property values are keyed by the uuid of the definition, which may be on any ancestor.
+fn property_name<TX: WorldStateTransaction>(tx: &TX, holder: &ObjAndUUIDHolder) -> Option<Symbol> {
+    let ancestors = tx.ancestors(&holder.obj).ok()?;
+    for ancestor in ancestors.iter() {
+        let propdefs = tx.get_properties(&ancestor).ok()?;
+        if let Some(propdef) = propdefs.iter().find(|p| p.uuid() == holder.uuid) {
+            return Some(Symbol::mk(propdef.name()));
+        }
+    }
+    None
+}
+
+/// The subscribers to the change stream.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    senders: Mutex<Vec<Sender<CommittedChanges>>>,
+}
+
+impl Subscribers {
+    /// A new subscription. It lasts until the receiver is dropped. Changes queue up without
+    /// bound for a subscriber which doesn't keep up.
+    pub(crate) fn subscribe(&self) -> Receiver<CommittedChanges> {
+        let (send, receive) = crossbeam_channel::unbounded();
+        self.senders.lock().unwrap().push(send);
+        receive
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.senders.lock().unwrap().is_empty()
+    }
+
+    pub(crate) fn publish(&self, changes: CommittedChanges) {
+        if changes.changes.is_empty() {
+            return;
+        }
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.send(changes.clone()).is_ok());
+    }
+}
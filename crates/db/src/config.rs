@@ -13,9 +13,11 @@
 
 use fjall::PartitionCreateOptions;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     /// The rate to run cache eviction cycles at.
     pub cache_eviction_interval: Duration,
@@ -26,6 +28,30 @@ pub struct DatabaseConfig {
     /// If they are still there, untouched, by the next eviction cycle, they will be removed.
     pub default_eviction_threshold: usize,
 
+    /// How ids are chosen for newly created objects.
+    #[serde(default)]
+    pub object_id_allocation: ObjectIdAllocation,
+
+    /// Properties whose values are kept in a full-text index, for `search_text()`. Needs the
+    /// `fts` feature. Empty (the default) means no index.
+    #[serde(default)]
+    pub text_indexed_properties: Vec<String>,
+
+    /// If set, and the database needs migrating to a newer schema version when it's opened, copy
+    /// it here first. The path must not already exist.
+    #[serde(default)]
+    pub migration_backup_path: Option<PathBuf>,
+
+    /// Property values whose encoding is larger than this many bytes are kept out of the
+    /// `object_propvalues` partition, in a partition of their own, and only read from there when
+    /// they're wanted. None keeps every value inline.
+    pub property_blob_threshold: Option<usize>,
+
+    /// Keep a single copy of each distinct verb program, and of each distinct property value
+    /// which is offloaded (see `property_blob_threshold`), however many entries have it, counting
+    /// references so that it's collected once none are left.
+    pub dedup_content: bool,
+
     /// Per-table configurations
     pub object_location: TableConfig,
     pub object_contents: TableConfig,
@@ -36,9 +62,11 @@ pub struct DatabaseConfig {
     pub object_name: TableConfig,
     pub object_verbdefs: TableConfig,
     pub object_verbs: TableConfig,
+    pub object_verb_options: TableConfig,
     pub object_propdefs: TableConfig,
     pub object_propvalues: TableConfig,
     pub object_propflags: TableConfig,
+    pub server_registry: TableConfig,
 }
 
 impl Default for DatabaseConfig {
@@ -47,6 +75,12 @@ impl Default for DatabaseConfig {
             cache_eviction_interval: Duration::from_secs(60),
             // 4MB
             default_eviction_threshold: 1 << 22,
+            object_id_allocation: ObjectIdAllocation::default(),
+            text_indexed_properties: vec![],
+            migration_backup_path: None,
+            // 64KB
+            property_blob_threshold: Some(1 << 16),
+            dedup_content: false,
             object_location: TableConfig::default(),
             object_contents: TableConfig::default(),
             object_flags: TableConfig::default(),
@@ -56,13 +90,57 @@ impl Default for DatabaseConfig {
             object_name: TableConfig::default(),
             object_verbdefs: TableConfig::default(),
             object_verbs: TableConfig::default(),
+            object_verb_options: TableConfig::default(),
             object_propdefs: TableConfig::default(),
             object_propvalues: TableConfig::default(),
             object_propflags: TableConfig::default(),
+            server_registry: TableConfig::default(),
         }
     }
 }
 
+impl DatabaseConfig {
+    /// The configuration for the table called `name`, if there is one.
+    pub fn table_mut(&mut self, name: &str) -> Option<&mut TableConfig> {
+        let table = match name {
+            "object_location" => &mut self.object_location,
+            "object_contents" => &mut self.object_contents,
+            "object_flags" => &mut self.object_flags,
+            "object_parent" => &mut self.object_parent,
+            "object_children" => &mut self.object_children,
+            "object_owner" => &mut self.object_owner,
+            "object_name" => &mut self.object_name,
+            "object_verbdefs" => &mut self.object_verbdefs,
+            "object_verbs" => &mut self.object_verbs,
+            "object_verb_options" => &mut self.object_verb_options,
+            "object_propdefs" => &mut self.object_propdefs,
+            "object_propvalues" => &mut self.object_propvalues,
+            "object_propflags" => &mut self.object_propflags,
+            "server_registry" => &mut self.server_registry,
+            _ => return None,
+        };
+        Some(table)
+    }
+}
+
+/// The policy for choosing the id of a newly created object.
+///
+/// The non-sequential policies exist for setups where several worlds share a core (via dump and
+/// load) and may later need to be merged: as long as each world is given a disjoint range, or a
+/// distinct offset, the objects they create will never collide.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ObjectIdAllocation {
+    /// One past the highest object id in use, as in LambdaMOO.
+    #[default]
+    Sequential,
+    /// Only allocate ids within `start..=end`, one past the highest id allocated within that
+    /// range. Creation fails once the range is exhausted.
+    Range { start: i32, end: i32 },
+    /// Allocate the lowest id above the highest one in use which is congruent to `offset` modulo
+    /// `stride`. Avoids having to size ranges up front.
+    Interleaved { offset: i32, stride: i32 },
+}
+
 /// Per-table configuration.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct TableConfig {
@@ -0,0 +1,174 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Content-addressed storage, for keeping one copy of values which are stored many times over:
+//! cores are full of verbs with identical programs (copied generics) and of identical large
+//! property values.
+//!
+//! Each distinct value is stored once under the hash of its bytes, with a count of the entries
+//! referring to it. The count is kept as entries are written and deleted, which only happens on
+//! the database's commit thread, and the value is collected as soon as nothing refers to it.
+//! Snapshots keep reading what they referred to, as they read the partitions as of their instant.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fjall::{PartitionHandle, UserValue};
+use moor_values::model::CacheStats;
+use sha2::{Digest, Sha256};
+
+use crate::tx::Error;
+
+pub(crate) type ContentHash = [u8; 32];
+
+pub(crate) struct ContentStore {
+    /// Values, by hash.
+    values: PartitionHandle,
+    /// How many entries refer to each value, by hash, as a little-endian u64.
+    refs: PartitionHandle,
+    snapshot: Option<fjall::Snapshot>,
+    /// Writes of values which were already stored, and the bytes they didn't have to write.
+    shared: AtomicUsize,
+    saved_bytes: AtomicUsize,
+    /// Writes of values which weren't.
+    added: AtomicUsize,
+    /// Values collected once nothing referred to them any more.
+    collected: AtomicUsize,
+}
+
+impl ContentStore {
+    pub fn new(
+        values: PartitionHandle,
+        refs: PartitionHandle,
+        snapshot_at: Option<fjall::Instant>,
+    ) -> Self {
+        let snapshot = snapshot_at.map(|instant| values.snapshot_at(instant));
+        Self {
+            values,
+            refs,
+            snapshot,
+            shared: AtomicUsize::new(0),
+            saved_bytes: AtomicUsize::new(0),
+            added: AtomicUsize::new(0),
+            collected: AtomicUsize::new(0),
+        }
+    }
+
+    /// Add a reference to `value`, storing it if it isn't already, and return its hash.
+    pub fn add(&self, value: &[u8]) -> Result<ContentHash, Error> {
+        let hash: ContentHash = Sha256::digest(value).into();
+        let refs = self.refs(&hash)?;
+        if refs == 0 {
+            self.values
+                .insert(hash.as_slice(), value)
+                .map_err(|e| Error::StorageFailure(e.to_string()))?;
+            self.added.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.shared.fetch_add(1, Ordering::Relaxed);
+            self.saved_bytes.fetch_add(value.len(), Ordering::Relaxed);
+        }
+        self.set_refs(&hash, refs + 1)?;
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &ContentHash) -> Result<UserValue, Error> {
+        let value = match &self.snapshot {
+            Some(snapshot) => snapshot.get(hash),
+            None => self.values.get(hash),
+        };
+        value
+            .map_err(|e| Error::RetrievalFailure(e.to_string()))?
+            .ok_or_else(|| Error::RetrievalFailure("stored content is missing".to_string()))
+    }
+
+    /// Drop a reference to the value with `hash`, collecting it if it was the last.
+    pub fn release(&self, hash: &ContentHash) -> Result<(), Error> {
+        let refs = self.refs(hash)?;
+        if refs > 1 {
+            return self.set_refs(hash, refs - 1);
+        }
+        self.refs
+            .remove(hash.as_slice())
+            .map_err(|e| Error::StorageFailure(e.to_string()))?;
+        self.values
+            .remove(hash.as_slice())
+            .map_err(|e| Error::StorageFailure(e.to_string()))?;
+        self.collected.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn refs(&self, hash: &ContentHash) -> Result<u64, Error> {
+        let refs = self
+            .refs
+            .get(hash)
+            .map_err(|e| Error::RetrievalFailure(e.to_string()))?;
+        Ok(refs
+            .and_then(|refs| <[u8; 8]>::try_from(&*refs).ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(0))
+    }
+
+    fn set_refs(&self, hash: &ContentHash, refs: u64) -> Result<(), Error> {
+        self.refs
+            .insert(hash.as_slice(), refs.to_le_bytes())
+            .map_err(|e| Error::StorageFailure(e.to_string()))
+    }
+
+    /// How deduplication is going since startup, in the shape of a cache's statistics: a hit is a
+    /// write of a value which was already stored, and `bytes` what those writes saved; a miss (and
+    /// an entry) a write of a new value; and an eviction a value collected once nothing referred
+    /// to it.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.added.load(Ordering::Relaxed),
+            used_bytes: self.saved_bytes.load(Ordering::Relaxed),
+            threshold_bytes: 0,
+            hits: self.shared.load(Ordering::Relaxed),
+            misses: self.added.load(Ordering::Relaxed),
+            evictions: self.collected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fjall::PartitionCreateOptions;
+
+    #[test]
+    fn test_refcounting() {
+        let dir = tempfile::tempdir().unwrap();
+        let keyspace = fjall::Config::new(dir.path()).open().unwrap();
+        let open = |name| {
+            keyspace
+                .open_partition(name, PartitionCreateOptions::default())
+                .unwrap()
+        };
+        let store = ContentStore::new(open("values"), open("refs"), None);
+
+        let a = store.add(b"return 1;").unwrap();
+        assert_eq!(store.add(b"return 1;").unwrap(), a);
+        let b = store.add(b"return 2;").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(store.values.len().unwrap(), 2);
+        let stats = store.stats();
+        assert_eq!((stats.hits, stats.misses, stats.used_bytes), (1, 2, 9));
+
+        // Kept until the last reference goes.
+        store.release(&a).unwrap();
+        assert_eq!(&*store.get(&a).unwrap(), b"return 1;");
+        store.release(&a).unwrap();
+        assert!(store.get(&a).is_err());
+        assert_eq!(&*store.get(&b).unwrap(), b"return 2;");
+        assert_eq!(store.stats().evictions, 1);
+    }
+}
@@ -30,6 +30,7 @@ use moor_values::Var;
 
 use crate::db_worldstate::DbTxWorldState;
 use crate::loader::LoaderInterface;
+use crate::program_cache::ProgramCache;
 use crate::worldstate_transaction::WorldStateTransaction;
 
 /// A loader client which uses a database transaction to load the world state.
@@ -89,8 +90,11 @@ impl<TX: WorldStateTransaction> LoaderInterface for DbTxWorldState<TX> {
             Symbol::mk_case_insensitive(propname),
             owner,
             flags,
-            value,
+            value.clone(),
         )?;
+        if let Some(value) = &value {
+            self.note_property_text(objid, Symbol::mk_case_insensitive(propname), Some(value));
+        }
         Ok(())
     }
     fn set_property(
@@ -108,6 +112,7 @@ impl<TX: WorldStateTransaction> LoaderInterface for DbTxWorldState<TX> {
 
         // Now set the value if provided.
         if let Some(value) = value {
+            self.note_property_text(objid, Symbol::mk_case_insensitive(propname), Some(&value));
             self.get_tx_mut()
                 .set_property(objid, propdef.uuid(), value)?;
         }
@@ -123,8 +128,36 @@ impl<TX: WorldStateTransaction> LoaderInterface for DbTxWorldState<TX> {
         Ok(())
     }
 
+    fn program_cache(&self) -> ProgramCache {
+        self.get_tx().program_cache()
+    }
+
     fn commit(self: Box<Self>) -> Result<CommitResult, WorldStateError> {
-        self.tx.commit()
+        self.commit_tx()
+    }
+
+    fn compact(&mut self) -> Result<Vec<(Obj, Obj)>, WorldStateError> {
+        let mut objects: Vec<i32> = self
+            .get_tx()
+            .get_objects()?
+            .iter()
+            .map(|o| o.id().0)
+            .filter(|id| *id >= 0)
+            .collect();
+        objects.sort_unstable();
+
+        let mut renumbered = vec![];
+        for (next_free, id) in objects.into_iter().enumerate() {
+            let next_free = next_free as i32;
+            if id == next_free {
+                continue;
+            }
+            let (obj, new_obj) = (Obj::mk_id(id), Obj::mk_id(next_free));
+            self.get_tx_mut().renumber_object(&obj, &new_obj)?;
+            renumbered.push((obj, new_obj));
+        }
+        self.get_tx_mut().reset_max_object()?;
+        Ok(renumbered)
     }
 
     fn get_objects(&self) -> Result<ObjSet, WorldStateError> {
@@ -11,22 +11,26 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::config::ObjectIdAllocation;
 use crate::fjall_provider::FjallProvider;
+use crate::object_generations::ObjectGenerations;
+use crate::program_cache::ProgramCache;
+use crate::prop_resolution_cache::PropResolutionCache;
 use crate::tx::{TransactionalCache, TransactionalTable, Tx};
-use crate::worldstate_db::WorkingSets;
+use crate::worldstate_db::{CacheDirectory, WorkingSets};
 use crate::worldstate_transaction::WorldStateTransaction;
 use crate::{BytesHolder, ObjAndUUIDHolder, StringHolder};
 use bytes::Bytes;
 use crossbeam_channel::Sender;
 use moor_values::model::{
-    BinaryType, CommitResult, HasUuid, Named, ObjAttrs, ObjFlag, ObjSet, ObjectRef, PropDef,
-    PropDefs, PropFlag, PropPerms, ValSet, VerbArgsSpec, VerbAttrs, VerbDef, VerbDefs, VerbFlag,
-    WorldStateError,
+    BinaryType, CacheStats, CommitResult, HasUuid, Named, ObjAttrs, ObjFlag, ObjSet, ObjectRef,
+    PropDef, PropDefs, PropFlag, PropPerms, ValSet, VerbArgsSpec, VerbAttrs, VerbDef, VerbDefs,
+    VerbFlag, WorldStateError,
 };
 use moor_values::util::BitEnum;
 use moor_values::{v_none, AsByteBuffer, Obj, Symbol, Var, NOTHING};
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::atomic::AtomicI64;
+use std::sync::atomic::{AtomicI64, AtomicU64};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -37,6 +41,9 @@ type LC<Domain, Codomain> = TransactionalTable<
 >;
 
 pub const SEQUENCE_MAX_OBJECT: usize = 0;
+/// The highest object id allocated so far within the configured range, under
+/// `ObjectIdAllocation::Range`.
+pub const SEQUENCE_RANGE_OBJECT: usize = 1;
 
 pub struct DbTransaction {
     #[allow(dead_code)]
@@ -60,14 +67,46 @@ pub struct DbTransaction {
 
     pub(crate) object_verbdefs: LC<Obj, VerbDefs>,
     pub(crate) object_verbs: LC<ObjAndUUIDHolder, BytesHolder>,
+    pub(crate) object_verb_options: LC<ObjAndUUIDHolder, Var>,
     pub(crate) object_propdefs: LC<Obj, PropDefs>,
     pub(crate) object_propvalues: LC<ObjAndUUIDHolder, Var>,
     pub(crate) object_propflags: LC<ObjAndUUIDHolder, PropPerms>,
 
+    pub(crate) server_registry: LC<StringHolder, Var>,
+
     pub(crate) sequences: [Arc<AtomicI64>; 16],
+
+    /// The policy for choosing ids for new objects.
+    pub(crate) object_id_allocation: ObjectIdAllocation,
+
+    /// The (non-transactional) cache of compiled programs, keyed by source hash.
+    pub(crate) program_cache: ProgramCache,
+
+    /// Whether this transaction is on a read-only replica, and so can't make changes.
+    pub(crate) read_only: bool,
+
+    /// The database's dispatch generation, and the number of changes this transaction has made
+    /// to verbs, property definitions or inheritance on top of it. See `dispatch_generation`.
+    pub(crate) dispatch_generation: Arc<AtomicU64>,
+    pub(crate) dispatch_changes: u64,
+    /// The dispatch generation as of when the transaction started.
+    pub(crate) started_generation: u64,
+
+    /// The database's (non-transactional) cache of where property names resolve to.
+    pub(crate) prop_resolution_cache: Arc<PropResolutionCache>,
+
+    /// The generation each object was last changed at, as of the last commit.
+    pub(crate) object_generations: ObjectGenerations,
+
+    /// The database's (non-transactional) global caches, for looking at and tuning them.
+    pub(crate) cache_directory: Arc<CacheDirectory>,
 }
 
 impl WorldStateTransaction for DbTransaction {
+    fn program_cache(&self) -> ProgramCache {
+        self.program_cache.clone()
+    }
+
     fn object_valid(&self, obj: &Obj) -> Result<bool, WorldStateError> {
         match self.object_flags.get(obj) {
             Ok(Some(_)) => Ok(true),
@@ -186,18 +225,7 @@ impl WorldStateTransaction for DbTransaction {
     fn create_object(&mut self, id: Option<Obj>, attrs: ObjAttrs) -> Result<Obj, WorldStateError> {
         let id = match id {
             Some(id) => id,
-            None => {
-                let max = self.increment_sequence(SEQUENCE_MAX_OBJECT);
-                let max = if max < i32::MIN as i64 || max > i32::MAX as i64 {
-                    return Err(WorldStateError::DatabaseError(format!(
-                        "Maximum object sequence number out of bounds: {}",
-                        max
-                    )));
-                } else {
-                    max as i32
-                };
-                Obj::mk_id(max)
-            }
+            None => self.allocate_object_id()?,
         };
 
         let owner = attrs.owner().unwrap_or(id.clone());
@@ -228,11 +256,17 @@ impl WorldStateTransaction for DbTransaction {
         // Update the maximum object number if ours is higher than the current one. This is for the
         // textdump case, where our numbers are coming in arbitrarily.
         self.update_sequence_max(SEQUENCE_MAX_OBJECT, id.id().0 as i64);
+        if let ObjectIdAllocation::Range { start, end } = self.object_id_allocation {
+            if (start..=end).contains(&id.id().0) {
+                self.update_sequence_max(SEQUENCE_RANGE_OBJECT, id.id().0 as i64);
+            }
+        }
 
         Ok(id)
     }
 
     fn recycle_object(&mut self, obj: &Obj) -> Result<(), WorldStateError> {
+        self.dispatch_changes += 1;
         // First go through and move all objects that are in this object's contents to the
         // to #-1.  It's up to the caller here to execute :exitfunc on all of them before invoking
         // this method.
@@ -250,6 +284,9 @@ impl WorldStateTransaction for DbTransaction {
             self.set_object_parent(&c, &parent)?;
         }
 
+        // Leave our own location. (As in LambdaMOO, its :exitfunc is not called for this.)
+        self.set_object_location(obj, &NOTHING)?;
+
         // Make sure we are removed from the parent's children list.
         let parent_children = self.get_object_children(&parent)?;
         let parent_children = parent_children.with_removed(obj.clone());
@@ -279,6 +316,9 @@ impl WorldStateTransaction for DbTransaction {
         self.object_location.delete(obj).map_err(|e| {
             WorldStateError::DatabaseError(format!("Error deleting object location: {:?}", e))
         })?;
+        self.object_contents.delete(obj).map_err(|e| {
+            WorldStateError::DatabaseError(format!("Error deleting object contents: {:?}", e))
+        })?;
         self.object_verbdefs.delete(obj).map_err(|e| {
             WorldStateError::DatabaseError(format!("Error deleting object verbdefs: {:?}", e))
         })?;
@@ -301,6 +341,217 @@ impl WorldStateTransaction for DbTransaction {
         Ok(())
     }
 
+    fn renumber_object(&mut self, obj: &Obj, new_obj: &Obj) -> Result<(), WorldStateError> {
+        if !self.object_valid(obj)? {
+            return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(obj.clone())));
+        }
+        if self.object_valid(new_obj)? {
+            return Err(WorldStateError::ObjectAlreadyExists(new_obj.clone()));
+        }
+        self.dispatch_changes += 1;
+        let renumbered = |o: Obj| if o.eq(obj) { new_obj.clone() } else { o };
+
+        // Move everything keyed directly on the object over to its new id.
+        macro_rules! rekey {
+            ($table:ident, $what:literal) => {
+                if let Some(value) = self.$table.delete(obj).map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error deleting {}: {:?}", $what, e))
+                })? {
+                    self.$table.upsert(new_obj.clone(), value).map_err(|e| {
+                        WorldStateError::DatabaseError(format!("Error setting {}: {:?}", $what, e))
+                    })?;
+                }
+            };
+        }
+        rekey!(object_flags, "object flags");
+        rekey!(object_name, "object name");
+        rekey!(object_owner, "object owner");
+        rekey!(object_parent, "object parent");
+        rekey!(object_location, "object location");
+        rekey!(object_children, "object children");
+        rekey!(object_contents, "object contents");
+        rekey!(object_verbdefs, "object verbdefs");
+        rekey!(object_propdefs, "object propdefs");
+
+        // And everything keyed on (object, uuid), which is stored by object first.
+        let prefix = obj.as_bytes().map_err(|e| {
+            WorldStateError::DatabaseError(format!("Error encoding object: {:?}", e))
+        })?;
+        macro_rules! rekey_uuid {
+            ($table:ident, $what:literal) => {
+                let entries = self.$table.scan_prefix(&prefix).map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error scanning {}: {:?}", $what, e))
+                })?;
+                for (k, value) in entries {
+                    self.$table.delete(&k).map_err(|e| {
+                        WorldStateError::DatabaseError(format!("Error deleting {}: {:?}", $what, e))
+                    })?;
+                    self.$table
+                        .upsert(ObjAndUUIDHolder::new(new_obj, k.uuid), value)
+                        .map_err(|e| {
+                            WorldStateError::DatabaseError(format!(
+                                "Error setting {}: {:?}",
+                                $what, e
+                            ))
+                        })?;
+                }
+            };
+        }
+        rekey_uuid!(object_verbs, "verb binary");
+        rekey_uuid!(object_verb_options, "verb compile options");
+        rekey_uuid!(object_propvalues, "property value");
+        rekey_uuid!(object_propflags, "property flags");
+
+        // Fix up the inheritance and containment relations on either side of us.
+        let parent = self.get_object_parent(new_obj)?;
+        if !parent.is_nothing() {
+            let siblings = self.get_object_children(&parent)?;
+            self.object_children
+                .upsert(
+                    parent.clone(),
+                    siblings
+                        .with_removed(obj.clone())
+                        .with_inserted(new_obj.clone()),
+                )
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error updating children: {:?}", e))
+                })?;
+        }
+        for child in self.get_object_children(new_obj)?.iter() {
+            self.object_parent
+                .upsert(child, new_obj.clone())
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error updating parent: {:?}", e))
+                })?;
+        }
+        let location = self.get_object_location(new_obj)?;
+        if !location.is_nothing() {
+            let neighbours = self.get_object_contents(&location)?;
+            self.object_contents
+                .upsert(
+                    location.clone(),
+                    neighbours
+                        .with_removed(obj.clone())
+                        .with_inserted(new_obj.clone()),
+                )
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error updating contents: {:?}", e))
+                })?;
+        }
+        for content in self.get_object_contents(new_obj)?.iter() {
+            self.object_location
+                .upsert(content, new_obj.clone())
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error updating location: {:?}", e))
+                })?;
+        }
+
+        // Ownership (and definer/location) of objects, verbs, and properties, across the whole
+        // database.
+        let owned = self
+            .object_owner
+            .scan(&|_, owner| owner.eq(obj))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning object owners: {:?}", e))
+            })?;
+        for (o, _) in owned {
+            self.object_owner.upsert(o, new_obj.clone()).map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error setting object owner: {:?}", e))
+            })?;
+        }
+
+        let verbdefs = self
+            .object_verbdefs
+            .scan(&|_, verbdefs| {
+                verbdefs
+                    .iter()
+                    .any(|v| v.owner().eq(obj) || v.location().eq(obj))
+            })
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning verbdefs: {:?}", e))
+            })?;
+        for (o, verbdefs) in verbdefs {
+            let verbdefs = VerbDefs::from_iter(verbdefs.iter().map(|v| {
+                VerbDef::new(
+                    v.uuid(),
+                    renumbered(v.location()),
+                    renumbered(v.owner()),
+                    &v.names(),
+                    v.flags(),
+                    v.binary_type(),
+                    v.args(),
+                )
+            }));
+            self.object_verbdefs.upsert(o, verbdefs).map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error setting verbdefs: {:?}", e))
+            })?;
+        }
+
+        let propdefs = self
+            .object_propdefs
+            .scan(&|_, propdefs| {
+                propdefs
+                    .iter()
+                    .any(|p| p.definer().eq(obj) || p.location().eq(obj))
+            })
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning propdefs: {:?}", e))
+            })?;
+        for (o, propdefs) in propdefs {
+            let propdefs = PropDefs::from_iter(propdefs.iter().map(|p| {
+                PropDef::new(
+                    p.uuid(),
+                    renumbered(p.definer()),
+                    renumbered(p.location()),
+                    p.name(),
+                )
+            }));
+            self.object_propdefs.upsert(o, propdefs).map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error setting propdefs: {:?}", e))
+            })?;
+        }
+
+        let propflags = self
+            .object_propflags
+            .scan(&|_, perms| perms.owner().eq(obj))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning property flags: {:?}", e))
+            })?;
+        for (k, perms) in propflags {
+            self.object_propflags
+                .upsert(k, perms.with_owner(new_obj.clone()))
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error setting property flags: {:?}", e))
+                })?;
+        }
+
+        self.update_sequence_max(SEQUENCE_MAX_OBJECT, new_obj.id().0 as i64);
+
+        Ok(())
+    }
+
+    fn reset_max_object(&mut self) -> Result<Obj, WorldStateError> {
+        let max = self
+            .get_objects()?
+            .iter()
+            .map(|o| o.id().0)
+            .max()
+            .unwrap_or(-1);
+        self.sequences[SEQUENCE_MAX_OBJECT].store(max as i64, std::sync::atomic::Ordering::SeqCst);
+        if let ObjectIdAllocation::Range { start, end } = self.object_id_allocation {
+            let range_max = self
+                .get_objects()?
+                .iter()
+                .map(|o| o.id().0)
+                .filter(|id| (start..=end).contains(id))
+                .max()
+                .unwrap_or(-1);
+            self.sequences[SEQUENCE_RANGE_OBJECT]
+                .store(range_max as i64, std::sync::atomic::Ordering::SeqCst);
+        }
+        Ok(Obj::mk_id(max))
+    }
+
     fn get_object_parent(&self, obj: &Obj) -> Result<Obj, WorldStateError> {
         let r = self.object_parent.get(obj).map_err(|e| {
             WorldStateError::DatabaseError(format!("Error getting object parent: {:?}", e))
@@ -309,6 +560,7 @@ impl WorldStateTransaction for DbTransaction {
     }
 
     fn set_object_parent(&mut self, o: &Obj, new_parent: &Obj) -> Result<(), WorldStateError> {
+        self.dispatch_changes += 1;
         // Steps for object re-parenting:
 
         // Get o's old-parents's children
@@ -672,6 +924,7 @@ impl WorldStateTransaction for DbTransaction {
         uuid: Uuid,
         verb_attrs: VerbAttrs,
     ) -> Result<(), WorldStateError> {
+        self.dispatch_changes += 1;
         let verbdefs = self.get_verbs(obj)?;
 
         let Some(verbdefs) = verbdefs.with_updated(uuid, |ov| {
@@ -710,6 +963,15 @@ impl WorldStateTransaction for DbTransaction {
                 .map_err(|e| {
                     WorldStateError::DatabaseError(format!("Error setting verb binary: {:?}", e))
                 })?;
+            // Whatever was recorded about how the old program was compiled no longer applies.
+            self.object_verb_options
+                .delete(&ObjAndUUIDHolder::new(obj, uuid))
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!(
+                        "Error deleting verb compile options: {:?}",
+                        e
+                    ))
+                })?;
         }
         Ok(())
     }
@@ -724,6 +986,7 @@ impl WorldStateTransaction for DbTransaction {
         flags: BitEnum<VerbFlag>,
         args: VerbArgsSpec,
     ) -> Result<(), WorldStateError> {
+        self.dispatch_changes += 1;
         let verbdefs = self.get_verbs(oid)?;
 
         let uuid = Uuid::new_v4();
@@ -755,6 +1018,7 @@ impl WorldStateTransaction for DbTransaction {
     }
 
     fn delete_verb(&mut self, location: &Obj, uuid: Uuid) -> Result<(), WorldStateError> {
+        self.dispatch_changes += 1;
         let verbdefs = self.get_verbs(location)?;
         let verbdefs = verbdefs
             .with_removed(uuid)
@@ -771,6 +1035,46 @@ impl WorldStateTransaction for DbTransaction {
             .map_err(|e| {
                 WorldStateError::DatabaseError(format!("Error deleting verb binary: {:?}", e))
             })?;
+        self.object_verb_options
+            .delete(&ObjAndUUIDHolder::new(location, uuid))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!(
+                    "Error deleting verb compile options: {:?}",
+                    e
+                ))
+            })?;
+        Ok(())
+    }
+
+    fn get_verb_compile_options(
+        &self,
+        obj: &Obj,
+        uuid: Uuid,
+    ) -> Result<Option<Var>, WorldStateError> {
+        self.object_verb_options
+            .get(&ObjAndUUIDHolder::new(obj, uuid))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!(
+                    "Error getting verb compile options: {:?}",
+                    e
+                ))
+            })
+    }
+
+    fn set_verb_compile_options(
+        &mut self,
+        obj: &Obj,
+        uuid: Uuid,
+        options: Var,
+    ) -> Result<(), WorldStateError> {
+        self.object_verb_options
+            .upsert(ObjAndUUIDHolder::new(obj, uuid), options)
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!(
+                    "Error setting verb compile options: {:?}",
+                    e
+                ))
+            })?;
         Ok(())
     }
 
@@ -855,6 +1159,7 @@ impl WorldStateTransaction for DbTransaction {
                     WorldStateError::DatabaseError(format!("Error setting property owner: {:?}", e))
                 })?;
         }
+        self.dispatch_changes += 1;
 
         Ok(u)
     }
@@ -889,6 +1194,7 @@ impl WorldStateTransaction for DbTransaction {
                 .map_err(|e| {
                     WorldStateError::DatabaseError(format!("Error updating property: {:?}", e))
                 })?;
+            self.dispatch_changes += 1;
         }
 
         // If flags or perms updated, do that.
@@ -928,10 +1234,22 @@ impl WorldStateTransaction for DbTransaction {
         let descendants = self.descendants(obj)?;
         let locations = ObjSet::from_items(&[obj.clone()]).with_concatenated(descendants);
         for location in locations.iter() {
+            // Values and permissions are kept on every descendant, the definition just where it
+            // was made.
+            self.object_propvalues
+                .delete(&ObjAndUUIDHolder::new(&location, uuid))
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error deleting property: {:?}", e))
+                })?;
+            self.object_propflags
+                .delete(&ObjAndUUIDHolder::new(&location, uuid))
+                .map_err(|e| {
+                    WorldStateError::DatabaseError(format!("Error deleting property: {:?}", e))
+                })?;
             let props: PropDefs = self.get_properties(&location)?;
-            let props = props
-                .with_removed(uuid)
-                .expect("Unable to remove property definition");
+            let Some(props) = props.with_removed(uuid) else {
+                continue;
+            };
 
             self.object_propdefs
                 .upsert(location.clone(), props)
@@ -939,6 +1257,7 @@ impl WorldStateTransaction for DbTransaction {
                     WorldStateError::DatabaseError(format!("Error deleting property: {:?}", e))
                 })?;
         }
+        self.dispatch_changes += 1;
         Ok(())
     }
 
@@ -984,21 +1303,7 @@ impl WorldStateTransaction for DbTransaction {
         obj: &Obj,
         name: Symbol,
     ) -> Result<(PropDef, Var, PropPerms, bool), WorldStateError> {
-        // Walk up the inheritance tree looking for the property definition.
-        let mut search_obj = obj.clone();
-        let propdef = loop {
-            let propdef = self.get_properties(&search_obj)?.find_first_named(name);
-
-            if let Some(propdef) = propdef {
-                break propdef;
-            }
-
-            let parent = self.get_object_parent(&search_obj)?;
-            if !parent.is_nothing() {
-                search_obj = parent;
-                continue;
-            };
-
+        let Some(propdef) = self.find_property_definition(obj, name)? else {
             return Err(WorldStateError::PropertyNotFound(
                 obj.clone(),
                 name.to_string(),
@@ -1056,9 +1361,11 @@ impl WorldStateTransaction for DbTransaction {
         let object_name = self.object_name.working_set();
         let object_verbdefs = self.object_verbdefs.working_set();
         let object_verbs = self.object_verbs.working_set();
+        let object_verb_options = self.object_verb_options.working_set();
         let object_propdefs = self.object_propdefs.working_set();
         let object_propvalues = self.object_propvalues.working_set();
         let object_propflags = self.object_propflags.working_set();
+        let server_registry = self.server_registry.working_set();
 
         let ws = WorkingSets {
             tx: self.tx,
@@ -1071,11 +1378,38 @@ impl WorldStateTransaction for DbTransaction {
             object_name,
             object_verbdefs,
             object_verbs,
+            object_verb_options,
             object_propdefs,
             object_propvalues,
             object_propflags,
+            server_registry,
+            dispatch_changes: self.dispatch_changes,
         };
 
+        // A replica has nothing to commit to. Transactions on one which only read are fine.
+        if self.read_only {
+            let has_writes = !ws.object_location.is_empty()
+                || !ws.object_contents.is_empty()
+                || !ws.object_flags.is_empty()
+                || !ws.object_parent.is_empty()
+                || !ws.object_children.is_empty()
+                || !ws.object_owner.is_empty()
+                || !ws.object_name.is_empty()
+                || !ws.object_verbdefs.is_empty()
+                || !ws.object_verbs.is_empty()
+                || !ws.object_verb_options.is_empty()
+                || !ws.object_propdefs.is_empty()
+                || !ws.object_propvalues.is_empty()
+                || !ws.object_propflags.is_empty()
+                || !ws.server_registry.is_empty();
+            if has_writes {
+                return Err(WorldStateError::DatabaseError(
+                    "Database replica is read-only".to_string(),
+                ));
+            }
+            return Ok(CommitResult::Success);
+        }
+
         // Send the working sets to the commit processing thread
         let (send, reply) = oneshot::channel();
         self.commit_channel.send((ws, send)).unwrap();
@@ -1084,11 +1418,121 @@ impl WorldStateTransaction for DbTransaction {
         Ok(reply.recv().expect("Error waiting for commit reply"))
     }
 
+    fn dispatch_generation(&self) -> u64 {
+        self.dispatch_generation
+            .load(std::sync::atomic::Ordering::SeqCst)
+            + self.dispatch_changes
+    }
+
+    fn object_generation(&self, obj: &Obj) -> Result<u64, WorldStateError> {
+        self.object_generations.get(obj).map_err(|e| {
+            WorldStateError::DatabaseError(format!("Error getting object generation: {:?}", e))
+        })
+    }
+
+    fn registry_get(&self, key: &str) -> Result<Option<Var>, WorldStateError> {
+        self.server_registry
+            .get(&StringHolder(key.to_string()))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error getting registry entry: {:?}", e))
+            })
+    }
+
+    fn registry_set(&mut self, key: &str, value: Var) -> Result<(), WorldStateError> {
+        self.server_registry
+            .upsert(StringHolder(key.to_string()), value)
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error setting registry entry: {:?}", e))
+            })?;
+        Ok(())
+    }
+
+    fn registry_delete(&mut self, key: &str) -> Result<bool, WorldStateError> {
+        let old = self
+            .server_registry
+            .delete(&StringHolder(key.to_string()))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error deleting registry entry: {:?}", e))
+            })?;
+        Ok(old.is_some())
+    }
+
+    fn registry_list(&self) -> Result<Vec<(String, Var)>, WorldStateError> {
+        let mut entries: Vec<_> = self
+            .server_registry
+            .scan(&|_, _| true)
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning registry: {:?}", e))
+            })?
+            .into_iter()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    fn cache_stats(&self) -> Result<Vec<(String, CacheStats)>, WorldStateError> {
+        Ok(self.cache_directory.stats())
+    }
+
+    fn flush_caches(&self, name: Option<&str>) -> Result<Option<usize>, WorldStateError> {
+        Ok(self.cache_directory.flush(name))
+    }
+
+    fn set_cache_capacity(
+        &self,
+        name: &str,
+        threshold_bytes: usize,
+    ) -> Result<bool, WorldStateError> {
+        self.cache_directory
+            .set_capacity(name, threshold_bytes)
+            .map_err(|e| WorldStateError::DatabaseError(e.to_string()))
+    }
+
     fn rollback(self) -> Result<(), WorldStateError> {
         // Just drop the transaction, it will be cleaned up by the drop impl.
         Ok(())
     }
 
+    fn find_objects_by_name(
+        &self,
+        predicate: &dyn Fn(&str) -> bool,
+    ) -> Result<ObjSet, WorldStateError> {
+        let named = self
+            .object_name
+            .scan(&|_, name| predicate(&name.0))
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning object names: {:?}", e))
+            })?;
+        Ok(ObjSet::from_iter(named.into_iter().map(|(o, _)| o)))
+    }
+
+    fn find_verb_definers(&self, name: Symbol) -> Result<Vec<(Obj, VerbDef)>, WorldStateError> {
+        let definers = self
+            .object_verbdefs
+            .scan(&|_, verbdefs| verbdefs.find_first_named(name).is_some())
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning verbdefs: {:?}", e))
+            })?;
+        Ok(definers
+            .into_iter()
+            .filter_map(|(o, verbdefs)| verbdefs.find_first_named(name).map(|v| (o, v)))
+            .collect())
+    }
+
+    fn find_property_definers(&self, name: Symbol) -> Result<Vec<(Obj, PropDef)>, WorldStateError> {
+        let definers = self
+            .object_propdefs
+            .scan(&|_, propdefs| propdefs.find_first_named(name).is_some())
+            .map_err(|e| {
+                WorldStateError::DatabaseError(format!("Error scanning propdefs: {:?}", e))
+            })?;
+        Ok(definers
+            .into_iter()
+            .filter_map(|(o, propdefs)| propdefs.find_first_named(name).map(|p| (o, p)))
+            .collect())
+    }
+
     fn descendants(&self, obj: &Obj) -> Result<ObjSet, WorldStateError> {
         let children = self
             .object_children
@@ -1120,12 +1564,114 @@ impl WorldStateTransaction for DbTransaction {
 }
 
 impl DbTransaction {
+    /// The definition of the property `name` as seen from `obj`: on `obj` itself, or on its
+    /// nearest ancestor which defines it. None if nothing up the chain does.
+    fn find_property_definition(
+        &self,
+        obj: &Obj,
+        name: Symbol,
+    ) -> Result<Option<PropDef>, WorldStateError> {
+        // The cache only holds what's committed as of the current generation, so it's no use once
+        // we've made changes which could move definitions around, or if others have since we
+        // started (and so we might not see them).
+        let generation = (self.dispatch_generation() == self.started_generation)
+            .then_some(self.started_generation);
+        if let Some(generation) = generation {
+            match self.prop_resolution_cache.lookup(generation, obj, name) {
+                Some(None) => return Ok(None),
+                Some(Some(definer)) => {
+                    if let Some(propdef) = self.get_properties(&definer)?.find_first_named(name) {
+                        return Ok(Some(propdef));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // Walk up the inheritance tree looking for the property definition.
+        let mut search_obj = obj.clone();
+        let found = loop {
+            if let Some(propdef) = self.get_properties(&search_obj)?.find_first_named(name) {
+                break Some((search_obj, propdef));
+            }
+            let parent = self.get_object_parent(&search_obj)?;
+            if parent.is_nothing() {
+                break None;
+            }
+            search_obj = parent;
+        };
+
+        if let Some(generation) = generation {
+            let definer = found.as_ref().map(|(definer, _)| definer.clone());
+            self.prop_resolution_cache
+                .remember(generation, obj, name, definer);
+        }
+        Ok(found.map(|(_, propdef)| propdef))
+    }
+
     /// Increment the given sequence, return the new value.
     fn increment_sequence(&self, seq: usize) -> i64 {
         self.sequences[seq].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         self.sequences[seq].load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Atomically advance the given sequence to `next(current)`, returning the new value.
+    fn advance_sequence<F: Fn(i64) -> i64>(&self, seq: usize, next: F) -> i64 {
+        loop {
+            let current = self.sequences[seq].load(std::sync::atomic::Ordering::SeqCst);
+            let value = next(current);
+            if self.sequences[seq]
+                .compare_exchange(
+                    current,
+                    value,
+                    std::sync::atomic::Ordering::SeqCst,
+                    std::sync::atomic::Ordering::SeqCst,
+                )
+                .is_ok()
+            {
+                return value;
+            }
+        }
+    }
+
+    /// Choose the id for a new object, according to the configured allocation policy.
+    fn allocate_object_id(&self) -> Result<Obj, WorldStateError> {
+        let id = match self.object_id_allocation {
+            ObjectIdAllocation::Sequential => self.increment_sequence(SEQUENCE_MAX_OBJECT),
+            ObjectIdAllocation::Range { start, end } => {
+                let id = self.advance_sequence(SEQUENCE_RANGE_OBJECT, |current| {
+                    std::cmp::max(current + 1, start as i64)
+                });
+                if id > end as i64 {
+                    return Err(WorldStateError::DatabaseError(format!(
+                        "Object id range #{}..#{} exhausted",
+                        start, end
+                    )));
+                }
+                id
+            }
+            ObjectIdAllocation::Interleaved { offset, stride } => {
+                if stride <= 0 || offset < 0 || offset >= stride {
+                    return Err(WorldStateError::DatabaseError(format!(
+                        "Invalid interleaved object id allocation: offset {} stride {}",
+                        offset, stride
+                    )));
+                }
+                self.advance_sequence(SEQUENCE_MAX_OBJECT, |current| {
+                    let candidate = current + 1;
+                    candidate + (offset as i64 - candidate).rem_euclid(stride as i64)
+                })
+            }
+        };
+        if id < i32::MIN as i64 || id > i32::MAX as i64 {
+            return Err(WorldStateError::DatabaseError(format!(
+                "Maximum object sequence number out of bounds: {}",
+                id
+            )));
+        }
+        Ok(Obj::mk_id(id as i32))
+    }
+
     fn get_sequence(&self, seq: usize) -> i64 {
         self.sequences[seq].load(std::sync::atomic::Ordering::Relaxed)
     }
@@ -13,20 +13,22 @@
 
 use bytes::Bytes;
 use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use moor_values::model::ObjSet;
-use moor_values::model::Perms;
 use moor_values::model::WorldState;
-use moor_values::model::WorldStateError;
 use moor_values::model::{ArgSpec, PrepSpec, VerbArgsSpec};
 use moor_values::model::{BinaryType, VerbAttrs, VerbFlag};
-use moor_values::model::{CommitResult, PropPerms, ValSet};
-use moor_values::model::{HasUuid, ObjectRef};
+use moor_values::model::{CacheStats, CommitResult, PropPerms, ValSet};
+use moor_values::model::{HasUuid, Named, ObjectRef};
 use moor_values::model::{ObjAttrs, ObjFlag};
+use moor_values::model::{PermissionAuditSink, PermissionCheck, Perms};
 use moor_values::model::{PropAttrs, PropFlag};
 use moor_values::model::{PropDef, PropDefs};
 use moor_values::model::{VerbDef, VerbDefs};
+use moor_values::model::{WorldStateError, COMPILE_OPTIONS_KEY};
 use moor_values::util::BitEnum;
 use moor_values::Variant;
 use moor_values::NOTHING;
@@ -34,6 +36,7 @@ use moor_values::{v_bool, Obj};
 use moor_values::{v_list, Symbol};
 use moor_values::{v_obj, Var};
 
+use crate::text_index::{indexable_text, TextIndex, TextIndexUpdate};
 use crate::worldstate_transaction::WorldStateTransaction;
 
 lazy_static! {
@@ -51,14 +54,47 @@ lazy_static! {
     static ref ALIASES_SYM: Symbol = Symbol::mk("aliases");
 }
 
+/// Whether `pname` names one of the properties every object has, which aren't stored as ordinary
+/// property values.
+fn is_builtin_property(pname: Symbol) -> bool {
+    [
+        *NAME_SYM,
+        *LOCATION_SYM,
+        *CONTENTS_SYM,
+        *OWNER_SYM,
+        *CHILDREN_SYM,
+        *PARENT_SYM,
+        *PROGRAMMER_SYM,
+        *WIZARD_SYM,
+        *R_SYM,
+        *W_SYM,
+        *F_SYM,
+    ]
+    .contains(&pname)
+}
+
 pub struct DbTxWorldState<TX: WorldStateTransaction> {
     pub tx: TX,
+    text_index: Option<Arc<TextIndex>>,
+    /// Changes to indexed properties, to hand to the text index if this transaction commits.
+    text_updates: Vec<TextIndexUpdate>,
+    /// Where to report permission checks, if they're being audited.
+    permission_audit: Option<PermissionAuditSink>,
 }
 
 impl<TX> DbTxWorldState<TX>
 where
     TX: WorldStateTransaction,
 {
+    pub(crate) fn new(tx: TX, text_index: Option<Arc<TextIndex>>) -> Self {
+        Self {
+            tx,
+            text_index,
+            text_updates: vec![],
+            permission_audit: None,
+        }
+    }
+
     pub(crate) fn get_tx(&self) -> &dyn WorldStateTransaction {
         &self.tx
     }
@@ -66,6 +102,78 @@ where
     pub(crate) fn get_tx_mut(&mut self) -> &mut dyn WorldStateTransaction {
         &mut self.tx
     }
+
+    /// Note a write of `value` to `obj.pname`, if that property is being indexed.
+    pub(crate) fn note_property_text(&mut self, obj: &Obj, pname: Symbol, value: Option<&Var>) {
+        let Some(text_index) = &self.text_index else {
+            return;
+        };
+        if !text_index.indexes(pname) {
+            return;
+        }
+        let update = match value.and_then(indexable_text) {
+            Some(text) => TextIndexUpdate::Set(obj.clone(), pname, text),
+            None => TextIndexUpdate::Clear(obj.clone(), pname),
+        };
+        self.text_updates.push(update);
+    }
+
+    /// Commit the transaction, then (if it went through) pass its changes to indexed properties on
+    /// to the text index.
+    pub(crate) fn commit_tx(self) -> Result<CommitResult, WorldStateError> {
+        let Self {
+            tx,
+            text_index,
+            text_updates,
+            ..
+        } = self;
+        let result = tx.commit()?;
+        if let (CommitResult::Success, Some(text_index)) = (&result, text_index) {
+            text_index.update(text_updates);
+        }
+        Ok(result)
+    }
+
+    /// Those of `found` which are `root` or its descendants (or anything, for a `root` of #-1) and
+    /// which `perms` can read, in object number order.
+    fn readable_within<T>(
+        &self,
+        perms: &Obj,
+        root: &Obj,
+        found: Vec<(Obj, T)>,
+    ) -> Result<Vec<(Obj, T)>, WorldStateError> {
+        let within: Option<HashSet<Obj>> = if root.eq(&NOTHING) {
+            None
+        } else {
+            if !self.valid(root)? {
+                return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(root.clone())));
+            }
+            let mut within: HashSet<_> = self.get_tx().descendants(root)?.iter().collect();
+            within.insert(root.clone());
+            Some(within)
+        };
+        let perms = self.perms(perms)?;
+        let mut readable = vec![];
+        for (obj, def) in found {
+            if let Some(within) = &within {
+                if !within.contains(&obj) {
+                    continue;
+                }
+            }
+            let (flags, owner) = (self.flags_of(&obj)?, self.owner_of(&obj)?);
+            self.audit(&perms, &obj, "read");
+            if perms
+                .check_object_allows(&owner, flags, ObjFlag::Read.into())
+                .is_err()
+            {
+                continue;
+            }
+            readable.push((obj, def));
+        }
+        readable.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(readable)
+    }
+
     fn perms(&self, who: &Obj) -> Result<Perms, WorldStateError> {
         let flags = self.flags_of(who)?;
         Ok(Perms {
@@ -74,6 +182,31 @@ where
         })
     }
 
+    /// The permissions of `who`, which are about to be checked for `action` on `object`.
+    fn perms_on(
+        &self,
+        who: &Obj,
+        object: &Obj,
+        action: &'static str,
+    ) -> Result<Perms, WorldStateError> {
+        let perms = self.perms(who)?;
+        self.audit(&perms, object, action);
+        Ok(perms)
+    }
+
+    /// Report a check of `perms` for `action` on `object`, if permissions are being audited.
+    fn audit(&self, perms: &Perms, object: &Obj, action: &'static str) {
+        let Some(sink) = &self.permission_audit else {
+            return;
+        };
+        sink(PermissionCheck {
+            programmer: perms.who.clone(),
+            wizard: perms.flags.contains(ObjFlag::Wizard),
+            object: object.clone(),
+            action,
+        });
+    }
+
     fn do_update_verb(
         &mut self,
         obj: &Obj,
@@ -81,7 +214,7 @@ where
         verbdef: &VerbDef,
         verb_attrs: VerbAttrs,
     ) -> Result<(), WorldStateError> {
-        let perms = self.perms(perms)?;
+        let perms = self.perms_on(perms, obj, "write verb")?;
         perms.check_verb_allows(&verbdef.owner(), verbdef.flags(), VerbFlag::Write)?;
 
         // If the verb code is being altered, a programmer or wizard bit is required.
@@ -104,7 +237,7 @@ where
     fn check_parent(&self, perms: &Obj, parent: &Obj) -> Result<(), WorldStateError> {
         if *parent != NOTHING {
             let (parentflags, parentowner) = (self.flags_of(parent)?, self.owner_of(parent)?);
-            self.perms(perms)?.check_object_allows(
+            self.perms_on(perms, parent, "write")?.check_object_allows(
                 &parentowner,
                 parentflags,
                 BitEnum::new_with(ObjFlag::Write) | ObjFlag::Fertile,
@@ -112,6 +245,19 @@ where
         }
         Ok(())
     }
+
+    /// Find the lowest-numbered object # below `obj` which is not in use, if there is one.
+    fn lowest_free_object(&self, obj: &Obj) -> Result<Option<Obj>, WorldStateError> {
+        let in_use: HashSet<i32> = self
+            .get_tx()
+            .get_objects()?
+            .iter()
+            .map(|o| o.id().0)
+            .collect();
+        Ok((0..obj.id().0)
+            .find(|id| !in_use.contains(id))
+            .map(Obj::mk_id))
+    }
 }
 
 impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
@@ -150,8 +296,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
     ) -> Result<(), WorldStateError> {
         // Owner or wizard only.
         let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, flags, ObjFlag::Write.into())?;
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Write.into(),
+        )?;
         self.get_tx_mut().set_object_flags(obj, new_flags)
     }
 
@@ -161,7 +310,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
     }
 
     fn object_bytes(&self, perms: &Obj, obj: &Obj) -> Result<usize, WorldStateError> {
-        self.perms(perms)?.check_wizard()?;
+        self.perms_on(perms, obj, "wizard")?.check_wizard()?;
         self.get_tx().get_object_size_bytes(obj)
     }
 
@@ -182,18 +331,151 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         self.get_tx_mut().create_object(None, attrs)
     }
 
+    fn clone_object(
+        &mut self,
+        perms: &Obj,
+        source: &Obj,
+        owner: &Obj,
+        copy_verbs: bool,
+    ) -> Result<Obj, WorldStateError> {
+        let (flags, source_owner) = (self.flags_of(source)?, self.owner_of(source)?);
+        let perms = self.perms_on(perms, source, "read")?;
+        perms.check_object_allows(&source_owner, flags, ObjFlag::Read.into())?;
+        self.audit(&perms, source, "owner");
+        perms.check_obj_owner_perms(owner)?;
+        let parent = self.get_tx().get_object_parent(source)?;
+        self.check_parent(&perms.who, &parent)?;
+
+        // Only wizards can hand out programmer and wizard bits.
+        let mut clone_flags = flags;
+        clone_flags.clear(ObjFlag::User);
+        if perms.check_wizard().is_err() {
+            clone_flags.clear(ObjFlag::Programmer);
+            clone_flags.clear(ObjFlag::Wizard);
+        }
+        let name = self.get_tx().get_object_name(source)?;
+        let attrs = ObjAttrs::new(owner.clone(), parent, NOTHING, clone_flags, &name);
+        let clone = self.get_tx_mut().create_object(None, attrs)?;
+        let owner = self.get_tx().get_object_owner(&clone)?;
+
+        // Properties the clone inherits alongside `source` already exist on it, but hold no
+        // values of their own yet. Values are shared rather than copied, as they're immutable.
+        for ancestor in self.get_tx().ancestors(source)?.iter() {
+            if ancestor == *source {
+                continue;
+            }
+            for pdef in self.get_tx().get_properties(&ancestor)?.iter() {
+                let (value, propperms) = self.get_tx().retrieve_property(source, pdef.uuid())?;
+                let propowner = if propperms.flags().contains(PropFlag::Chown) {
+                    owner.clone()
+                } else {
+                    propperms.owner()
+                };
+                self.get_tx_mut().update_property_info(
+                    &clone,
+                    pdef.uuid(),
+                    Some(propowner),
+                    Some(propperms.flags()),
+                    None,
+                )?;
+                if let Some(value) = value {
+                    let pname = Symbol::mk_case_insensitive(pdef.name());
+                    self.note_property_text(&clone, pname, Some(&value));
+                    self.get_tx_mut().set_property(&clone, pdef.uuid(), value)?;
+                }
+            }
+        }
+
+        // The properties `source` defines are defined afresh on the clone.
+        for pdef in self.get_tx().get_properties(source)?.iter() {
+            let (value, propperms) = self.get_tx().retrieve_property(source, pdef.uuid())?;
+            let pname = Symbol::mk_case_insensitive(pdef.name());
+            if let Some(value) = &value {
+                self.note_property_text(&clone, pname, Some(value));
+            }
+            self.get_tx_mut().define_property(
+                &clone,
+                &clone,
+                pname,
+                &propperms.owner(),
+                propperms.flags(),
+                value,
+            )?;
+        }
+
+        // Copied verbs belong to the clone's owner, so that cloning can't be used to get code
+        // running with someone else's permissions.
+        if copy_verbs {
+            for vdef in self.get_tx().get_verbs(source)?.iter() {
+                let binary = self.get_tx().get_verb_binary(source, vdef.uuid())?;
+                let names = vdef.names().into_iter().map(Symbol::mk).collect();
+                self.get_tx_mut().add_object_verb(
+                    &clone,
+                    &owner,
+                    names,
+                    binary.to_vec(),
+                    vdef.binary_type(),
+                    vdef.flags(),
+                    vdef.args(),
+                )?;
+                // The copy's program is the same, so was compiled with the same features.
+                if let Some(options) = self
+                    .get_tx()
+                    .get_verb_compile_options(source, vdef.uuid())?
+                {
+                    let copied = self.get_tx().get_verbs(&clone)?;
+                    if let Some(copied) = copied.iter().last() {
+                        self.get_tx_mut().set_verb_compile_options(
+                            &clone,
+                            copied.uuid(),
+                            options,
+                        )?;
+                    }
+                }
+            }
+        }
+
+        Ok(clone)
+    }
+
     fn recycle_object(&mut self, perms: &Obj, obj: &Obj) -> Result<(), WorldStateError> {
         let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, flags, ObjFlag::Write.into())?;
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Write.into(),
+        )?;
 
-        self.get_tx_mut().recycle_object(obj)
+        self.get_tx_mut().recycle_object(obj)?;
+        if self.text_index.is_some() {
+            self.text_updates
+                .push(TextIndexUpdate::Recycle(obj.clone()));
+        }
+        Ok(())
     }
 
     fn max_object(&self, _perms: &Obj) -> Result<Obj, WorldStateError> {
         self.get_tx().get_max_object()
     }
 
+    fn renumber_object(&mut self, perms: &Obj, obj: &Obj) -> Result<Obj, WorldStateError> {
+        self.perms_on(perms, obj, "wizard")?.check_wizard()?;
+        if !self.valid(obj)? {
+            return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(obj.clone())));
+        }
+        let Some(new_obj) = self.lowest_free_object(obj)? else {
+            return Ok(obj.clone());
+        };
+        self.get_tx_mut().renumber_object(obj, &new_obj)?;
+        Ok(new_obj)
+    }
+
+    fn reset_max_object(&mut self, perms: &Obj) -> Result<(), WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx_mut().reset_max_object()?;
+        Ok(())
+    }
+
     fn move_object(
         &mut self,
         perms: &Obj,
@@ -201,12 +483,30 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         new_loc: &Obj,
     ) -> Result<(), WorldStateError> {
         let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, flags, ObjFlag::Write.into())?;
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Write.into(),
+        )?;
 
         self.get_tx_mut().set_object_location(obj, new_loc)
     }
 
+    fn evict_contents(&mut self, perms: &Obj, obj: &Obj) -> Result<ObjSet, WorldStateError> {
+        let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Write.into(),
+        )?;
+
+        let contents = self.get_tx().get_object_contents(obj)?;
+        for c in contents.iter() {
+            self.get_tx_mut().set_object_location(&c, &NOTHING)?;
+        }
+        Ok(contents)
+    }
+
     fn contents_of(&self, _perms: &Obj, obj: &Obj) -> Result<ObjSet, WorldStateError> {
         // MOO does not do any perms checks on contents, pretty sure:
         // https://github.com/wrog/lambdamoo/blob/master/db_properties.c#L351
@@ -215,16 +515,22 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
 
     fn verbs(&self, perms: &Obj, obj: &Obj) -> Result<VerbDefs, WorldStateError> {
         let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, flags, ObjFlag::Read.into())?;
+        self.perms_on(perms, obj, "read")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Read.into(),
+        )?;
 
         self.get_tx().get_verbs(obj)
     }
 
     fn properties(&self, perms: &Obj, obj: &Obj) -> Result<PropDefs, WorldStateError> {
         let (flags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, flags, ObjFlag::Read.into())?;
+        self.perms_on(perms, obj, "read")?.check_object_allows(
+            &owner,
+            flags,
+            ObjFlag::Read.into(),
+        )?;
 
         let properties = self.get_tx().get_properties(obj)?;
         Ok(properties)
@@ -283,11 +589,35 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         }
 
         let (_, value, propperms, _) = self.get_tx().resolve_property(obj, pname)?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "read property")?
             .check_property_allows(&propperms, PropFlag::Read)?;
         Ok(value)
     }
 
+    fn retrieve_properties(
+        &self,
+        perms: &Obj,
+        obj: &Obj,
+        pnames: &[Symbol],
+    ) -> Result<Vec<Var>, WorldStateError> {
+        if *obj == NOTHING || !self.valid(obj)? {
+            return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(obj.clone())));
+        }
+        let task_perms = self.perms(perms)?;
+        let mut values = Vec::with_capacity(pnames.len());
+        for pname in pnames {
+            if is_builtin_property(*pname) {
+                values.push(self.retrieve_property(perms, obj, *pname)?);
+                continue;
+            }
+            let (_, value, propperms, _) = self.get_tx().resolve_property(obj, *pname)?;
+            self.audit(&task_perms, obj, "read property");
+            task_perms.check_property_allows(&propperms, PropFlag::Read)?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
     fn get_property_info(
         &self,
         perms: &Obj,
@@ -304,7 +634,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         let propperms = self
             .get_tx()
             .retrieve_property_permissions(obj, pdef.uuid())?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "read property")?
             .check_property_allows(&propperms, PropFlag::Read)?;
 
         Ok((pdef.clone(), propperms))
@@ -328,7 +658,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         let propperms = self
             .get_tx()
             .retrieve_property_permissions(obj, pdef.uuid())?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "write property")?
             .check_property_allows(&propperms, PropFlag::Write)?;
 
         // TODO Also keep a close eye on 'clear' & perms:
@@ -371,8 +701,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
             let (mut flags, objowner) = (self.flags_of(obj)?, self.owner_of(obj)?);
 
             // User is either wizard or owner
-            self.perms(perms)?
-                .check_object_allows(&objowner, flags, ObjFlag::Write.into())?;
+            self.perms_on(perms, obj, "write")?.check_object_allows(
+                &objowner,
+                flags,
+                ObjFlag::Write.into(),
+            )?;
             if pname == *NAME_SYM {
                 let Variant::Str(name) = value.variant() else {
                     return Err(WorldStateError::PropertyTypeMismatch);
@@ -432,7 +765,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
 
         if pname == *PROGRAMMER_SYM || pname == *WIZARD_SYM {
             // Caller *must* be a wizard for either of these.
-            self.perms(perms)?.check_wizard()?;
+            self.perms_on(perms, obj, "wizard")?.check_wizard()?;
 
             // Gott get and then set flags
             let mut flags = self.flags_of(obj)?;
@@ -455,11 +788,44 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         }
 
         let (pdef, _, propperms, _) = self.get_tx().resolve_property(obj, pname)?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "write property")?
             .check_property_allows(&propperms, PropFlag::Write)?;
 
         self.get_tx_mut()
             .set_property(obj, pdef.uuid(), value.clone())?;
+        self.note_property_text(obj, pname, Some(value));
+        Ok(())
+    }
+
+    fn update_properties(
+        &mut self,
+        perms: &Obj,
+        obj: &Obj,
+        values: &[(Symbol, Var)],
+    ) -> Result<(), WorldStateError> {
+        if *obj == NOTHING || !self.valid(obj)? {
+            return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(obj.clone())));
+        }
+        let task_perms = self.perms(perms)?;
+        let mut resolved = Vec::with_capacity(values.len());
+        for (pname, _) in values {
+            if is_builtin_property(*pname) {
+                resolved.push(None);
+                continue;
+            }
+            let (pdef, _, propperms, _) = self.get_tx().resolve_property(obj, *pname)?;
+            self.audit(&task_perms, obj, "write property");
+            task_perms.check_property_allows(&propperms, PropFlag::Write)?;
+            resolved.push(Some(pdef.uuid()));
+        }
+        for ((pname, value), uuid) in values.iter().zip(resolved) {
+            let Some(uuid) = uuid else {
+                self.update_property(perms, obj, *pname, value)?;
+                continue;
+            };
+            self.get_tx_mut().set_property(obj, uuid, value.clone())?;
+            self.note_property_text(obj, *pname, Some(value));
+        }
         Ok(())
     }
 
@@ -470,7 +836,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         pname: Symbol,
     ) -> Result<bool, WorldStateError> {
         let (_, _, propperms, clear) = self.get_tx().resolve_property(obj, pname)?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "read property")?
             .check_property_allows(&propperms, PropFlag::Read)?;
         Ok(clear)
     }
@@ -484,9 +850,10 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         // This is just deleting the local *value* portion of the property.
         // First seek the property handle.
         let (pdef, _, propperms, _) = self.get_tx().resolve_property(obj, pname)?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "write property")?
             .check_property_allows(&propperms, PropFlag::Write)?;
         self.get_tx_mut().clear_property(obj, pdef.uuid())?;
+        self.note_property_text(obj, pname, None);
         Ok(())
     }
 
@@ -503,9 +870,10 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         // Perms needs to be wizard, or have write permission on object *and* the owner in prop_flags
         // must be the perms
         let (flags, objowner) = (self.flags_of(location)?, self.owner_of(location)?);
-        self.perms(perms)?
+        self.perms_on(perms, location, "write")?
             .check_object_allows(&objowner, flags, ObjFlag::Write.into())?;
-        self.perms(perms)?.check_obj_owner_perms(propowner)?;
+        self.perms_on(perms, location, "owner")?
+            .check_obj_owner_perms(propowner)?;
 
         self.get_tx_mut().define_property(
             definer,
@@ -534,7 +902,7 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         let propperms = self
             .get_tx()
             .retrieve_property_permissions(obj, pdef.uuid())?;
-        self.perms(perms)?
+        self.perms_on(perms, obj, "write property")?
             .check_property_allows(&propperms, PropFlag::Write)?;
 
         self.get_tx_mut().delete_property(obj, pdef.uuid())
@@ -552,8 +920,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         binary_type: BinaryType,
     ) -> Result<(), WorldStateError> {
         let (objflags, obj_owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&obj_owner, objflags, ObjFlag::Write.into())?;
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &obj_owner,
+            objflags,
+            ObjFlag::Write.into(),
+        )?;
 
         self.get_tx_mut()
             .add_object_verb(obj, owner, names, binary, binary_type, flags, args)?;
@@ -565,8 +936,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         let vh = verbs
             .find(&uuid)
             .ok_or(WorldStateError::VerbNotFound(obj.clone(), uuid.to_string()))?;
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Write)?;
+        self.perms_on(perms, obj, "write verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Write,
+        )?;
 
         self.get_tx_mut().delete_verb(obj, vh.uuid())?;
         Ok(())
@@ -614,8 +988,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         }
 
         let vh = self.get_tx().get_verb_by_name(obj, vname)?;
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Read)?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
 
         Ok(vh)
     }
@@ -627,8 +1004,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         vidx: usize,
     ) -> Result<VerbDef, WorldStateError> {
         let vh = self.get_tx().get_verb_by_index(obj, vidx)?;
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Read)?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
         Ok(vh)
     }
 
@@ -642,8 +1022,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         let vh = verbs
             .find(&uuid)
             .ok_or(WorldStateError::VerbNotFound(obj.clone(), uuid.to_string()))?;
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Read)?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
         let binary = self.get_tx().get_verb_binary(&vh.location(), vh.uuid())?;
         Ok((binary, vh))
     }
@@ -655,8 +1038,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         vname: Symbol,
     ) -> Result<(Bytes, VerbDef), WorldStateError> {
         let vh = self.get_tx().resolve_verb(obj, vname, None)?;
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Read)?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
 
         let binary = self.get_tx().get_verb_binary(&vh.location(), vh.uuid())?;
         Ok((binary, vh))
@@ -676,8 +1062,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         }
 
         let (objflags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, objflags, ObjFlag::Read.into())?;
+        self.perms_on(perms, obj, "read")?.check_object_allows(
+            &owner,
+            objflags,
+            ObjFlag::Read.into(),
+        )?;
 
         let spec_for_fn = |oid, pco: &Obj| -> ArgSpec {
             if pco == oid {
@@ -704,8 +1093,11 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
             }
         };
 
-        self.perms(perms)?
-            .check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Read)?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
 
         let binary = self.get_tx().get_verb_binary(&vh.location(), vh.uuid())?;
         Ok(Some((binary, vh)))
@@ -720,27 +1112,68 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         perms: &Obj,
         obj: &Obj,
         new_parent: &Obj,
+        clear_conflicts: bool,
     ) -> Result<(), WorldStateError> {
-        if obj == new_parent {
-            return Err(WorldStateError::RecursiveMove(
-                obj.clone(),
-                new_parent.clone(),
-            ));
-        }
-
         let (objflags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
 
         self.check_parent(perms, new_parent)?;
-        self.perms(perms)?
-            .check_object_allows(&owner, objflags, ObjFlag::Write.into())?;
+        self.perms_on(perms, obj, "write")?.check_object_allows(
+            &owner,
+            objflags,
+            ObjFlag::Write.into(),
+        )?;
+
+        // The new parent mustn't be the object or one of its descendants. Walking up from it
+        // gives the chain of parents which would close the loop, to report if it does.
+        let mut new_ancestors = vec![];
+        let mut ancestor = new_parent.clone();
+        while !ancestor.is_nothing() {
+            new_ancestors.push(ancestor.clone());
+            if ancestor == *obj {
+                return Err(WorldStateError::ParentCycle(obj.clone(), new_ancestors));
+            }
+            ancestor = self.get_tx().get_object_parent(&ancestor)?;
+        }
+
+        // As in LambdaMOO, nothing in the family being moved may define a property with the same
+        // name as one it'd now inherit.
+        let mut inherited = HashMap::new();
+        for ancestor in &new_ancestors {
+            for p in self.get_tx().get_properties(ancestor)?.iter() {
+                inherited
+                    .entry(p.name().to_lowercase())
+                    .or_insert_with(|| ancestor.clone());
+            }
+        }
+        let family =
+            ObjSet::from_items(&[obj.clone()]).with_concatenated(self.get_tx().descendants(obj)?);
+        for o in family.iter() {
+            let props = self.get_tx().get_properties(&o)?;
+            for p in props.iter() {
+                let Some(definer) = inherited.get(&p.name().to_lowercase()) else {
+                    continue;
+                };
+                if !clear_conflicts {
+                    return Err(WorldStateError::ChparentPropertyConflict(
+                        o.clone(),
+                        definer.clone(),
+                        p.name().to_string(),
+                    ));
+                }
+                self.get_tx_mut().delete_property(&o, p.uuid())?;
+            }
+        }
 
         self.get_tx_mut().set_object_parent(obj, new_parent)
     }
 
     fn children_of(&self, perms: &Obj, obj: &Obj) -> Result<ObjSet, WorldStateError> {
         let (objflags, owner) = (self.flags_of(obj)?, self.owner_of(obj)?);
-        self.perms(perms)?
-            .check_object_allows(&owner, objflags, ObjFlag::Read.into())?;
+        self.perms_on(perms, obj, "read")?.check_object_allows(
+            &owner,
+            objflags,
+            ObjFlag::Read.into(),
+        )?;
 
         self.get_tx().get_object_children(obj)
     }
@@ -776,12 +1209,184 @@ impl<TX: WorldStateTransaction> WorldState for DbTxWorldState<TX> {
         Ok((name, aliases))
     }
 
+    fn find_objects_by_name(
+        &self,
+        _perms: &Obj,
+        pattern: &str,
+        case_matters: bool,
+    ) -> Result<ObjSet, WorldStateError> {
+        // Names, like in names_of, can be looked at without permissions.
+        if case_matters {
+            return self
+                .get_tx()
+                .find_objects_by_name(&|name| name.contains(pattern));
+        }
+        let pattern = pattern.to_lowercase();
+        self.get_tx()
+            .find_objects_by_name(&|name| name.to_lowercase().contains(&pattern))
+    }
+
+    fn find_verb_definers(
+        &self,
+        perms: &Obj,
+        root: &Obj,
+        name: Symbol,
+    ) -> Result<Vec<(Obj, VerbDef)>, WorldStateError> {
+        let definers = self.get_tx().find_verb_definers(name)?;
+        self.readable_within(perms, root, definers)
+    }
+
+    fn find_property_definers(
+        &self,
+        perms: &Obj,
+        root: &Obj,
+        name: Symbol,
+    ) -> Result<Vec<(Obj, PropDef)>, WorldStateError> {
+        let definers = self.get_tx().find_property_definers(name)?;
+        self.readable_within(perms, root, definers)
+    }
+
+    fn search_text(
+        &self,
+        perms: &Obj,
+        query: &str,
+        limit: usize,
+    ) -> Result<Option<Vec<(Obj, Symbol)>>, WorldStateError> {
+        let Some(text_index) = &self.text_index else {
+            return Ok(None);
+        };
+        let hits = text_index
+            .search(query, limit)
+            .map_err(WorldStateError::DatabaseError)?;
+
+        // The index lags behind commits, so may still hold objects and properties which are gone.
+        let perms = self.perms(perms)?;
+        let mut readable = Vec::with_capacity(hits.len());
+        for (obj, pname) in hits {
+            if !self.valid(&obj)? {
+                continue;
+            }
+            let Ok((_, _, propperms, _)) = self.get_tx().resolve_property(&obj, pname) else {
+                continue;
+            };
+            self.audit(&perms, &obj, "read property");
+            if perms
+                .check_property_allows(&propperms, PropFlag::Read)
+                .is_ok()
+            {
+                readable.push((obj, pname));
+            }
+        }
+        Ok(Some(readable))
+    }
+
     fn db_usage(&self) -> Result<usize, WorldStateError> {
         self.get_tx().db_usage()
     }
 
+    fn dispatch_generation(&self) -> u64 {
+        self.get_tx().dispatch_generation()
+    }
+
+    fn object_generation(&self, obj: &Obj) -> Result<u64, WorldStateError> {
+        if !self.valid(obj)? {
+            return Err(WorldStateError::ObjectNotFound(ObjectRef::Id(obj.clone())));
+        }
+        self.get_tx().object_generation(obj)
+    }
+
+    fn compile_options(&self) -> Result<Option<Var>, WorldStateError> {
+        self.get_tx().registry_get(COMPILE_OPTIONS_KEY)
+    }
+
+    fn verb_compile_options(
+        &self,
+        perms: &Obj,
+        obj: &Obj,
+        uuid: Uuid,
+    ) -> Result<Option<Var>, WorldStateError> {
+        let verbs = self.get_tx().get_verbs(obj)?;
+        let vh = verbs
+            .find(&uuid)
+            .ok_or(WorldStateError::VerbNotFound(obj.clone(), uuid.to_string()))?;
+        self.perms_on(perms, obj, "read verb")?.check_verb_allows(
+            &vh.owner(),
+            vh.flags(),
+            VerbFlag::Read,
+        )?;
+        self.get_tx().get_verb_compile_options(obj, uuid)
+    }
+
+    fn set_verb_compile_options(
+        &mut self,
+        perms: &Obj,
+        obj: &Obj,
+        uuid: Uuid,
+        options: Var,
+    ) -> Result<(), WorldStateError> {
+        let verbs = self.get_tx().get_verbs(obj)?;
+        let vh = verbs
+            .find(&uuid)
+            .ok_or(WorldStateError::VerbNotFound(obj.clone(), uuid.to_string()))?;
+        let perms = self.perms_on(perms, obj, "write verb")?;
+        perms.check_verb_allows(&vh.owner(), vh.flags(), VerbFlag::Write)?;
+        if !perms.check_is_wizard()? && !perms.flags.contains(ObjFlag::Programmer) {
+            return Err(WorldStateError::VerbPermissionDenied);
+        }
+        self.get_tx_mut()
+            .set_verb_compile_options(obj, uuid, options)
+    }
+
+    fn registry_get(&self, perms: &Obj, key: &str) -> Result<Option<Var>, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx().registry_get(key)
+    }
+
+    fn registry_set(&mut self, perms: &Obj, key: &str, value: Var) -> Result<(), WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx_mut().registry_set(key, value)
+    }
+
+    fn registry_delete(&mut self, perms: &Obj, key: &str) -> Result<bool, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx_mut().registry_delete(key)
+    }
+
+    fn registry_list(&self, perms: &Obj) -> Result<Vec<(String, Var)>, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx().registry_list()
+    }
+
+    fn cache_stats(&self, perms: &Obj) -> Result<Vec<(String, CacheStats)>, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx().cache_stats()
+    }
+
+    fn flush_caches(
+        &self,
+        perms: &Obj,
+        name: Option<&str>,
+    ) -> Result<Option<usize>, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx().flush_caches(name)
+    }
+
+    fn set_cache_capacity(
+        &self,
+        perms: &Obj,
+        name: &str,
+        threshold_bytes: usize,
+    ) -> Result<bool, WorldStateError> {
+        self.perms_on(perms, &NOTHING, "wizard")?.check_wizard()?;
+        self.get_tx().set_cache_capacity(name, threshold_bytes)
+    }
+
+    fn audit_permissions(&mut self, sink: PermissionAuditSink) {
+        self.permission_audit = Some(sink);
+    }
+
     fn commit(self: Box<Self>) -> Result<CommitResult, WorldStateError> {
-        self.tx.commit()
+        self.commit_tx()
     }
 
     fn rollback(self: Box<Self>) -> Result<(), WorldStateError> {
@@ -11,43 +11,28 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::blobs::{BlobStore, OFFLOADED};
 use crate::tx::{Error, Provider, Timestamp};
 use bytes::Bytes;
-use fjall::UserValue;
+use fjall::{UserKey, UserValue};
 use moor_values::AsByteBuffer;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
-/// A provider that fills the DB cache from a Fjall partition.
+/// A provider that fills the DB cache from a Fjall partition, or from a snapshot of one, for
+/// read-only replicas.
 pub(crate) struct FjallProvider<Domain, Codomain>
 where
     Domain: Clone + Eq + PartialEq + AsByteBuffer,
     Codomain: Clone + Eq + PartialEq + AsByteBuffer,
 {
     fjall_partition: fjall::PartitionHandle,
+    snapshot: Option<fjall::Snapshot>,
+    /// Where values too large to keep in the partition go, if anywhere.
+    blobs: Option<Arc<BlobStore>>,
     _phantom_data: PhantomData<(Domain, Codomain)>,
 }
 
-fn decode<Codomain>(user_value: UserValue) -> Result<(Timestamp, Codomain), Error>
-where
-    Codomain: AsByteBuffer,
-{
-    let result: Bytes = user_value.into();
-    let ts = Timestamp(u64::from_le_bytes(result[0..8].try_into().unwrap()));
-    let codomain = Codomain::from_bytes(result.slice(8..)).map_err(|_| Error::EncodingFailure)?;
-    Ok((ts, codomain))
-}
-
-fn encode<Codomain>(ts: Timestamp, codomain: Codomain) -> Result<UserValue, Error>
-where
-    Codomain: AsByteBuffer,
-{
-    let as_bytes = codomain.as_bytes().map_err(|_| Error::EncodingFailure)?;
-    let mut result = Vec::with_capacity(8 + as_bytes.len());
-    result.extend_from_slice(&ts.0.to_le_bytes());
-    result.extend_from_slice(&as_bytes);
-    Ok(UserValue::from(Bytes::from(result)))
-}
-
 impl<Domain, Codomain> FjallProvider<Domain, Codomain>
 where
     Domain: Clone + Eq + PartialEq + AsByteBuffer,
@@ -56,9 +41,85 @@ where
     pub fn new(fjall_partition: fjall::PartitionHandle) -> Self {
         Self {
             fjall_partition,
+            snapshot: None,
+            blobs: None,
             _phantom_data: PhantomData,
         }
     }
+
+    /// A provider which reads the partition as it was at `instant`, and can't be written to.
+    pub fn at_instant(fjall_partition: fjall::PartitionHandle, instant: fjall::Instant) -> Self {
+        let snapshot = fjall_partition.snapshot_at(instant);
+        Self {
+            fjall_partition,
+            snapshot: Some(snapshot),
+            blobs: None,
+            _phantom_data: PhantomData,
+        }
+    }
+
+    /// Read offloaded values from `blobs`, and offload the values it says to, which must be over
+    /// the same snapshot, if any.
+    pub fn with_blobs(mut self, blobs: Arc<BlobStore>) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    /// Decode a stored value, fetching it from the blob partition if it was offloaded, along with
+    /// its size.
+    fn decode(
+        &self,
+        key: &[u8],
+        user_value: UserValue,
+    ) -> Result<(Timestamp, Codomain, usize), Error> {
+        let result: Bytes = user_value.into();
+        let ts = u64::from_le_bytes(result[0..8].try_into().unwrap());
+        let bytes: Bytes = match &self.blobs {
+            Some(blobs) if ts & OFFLOADED != 0 => blobs.load(key, &result)?.into(),
+            Some(blobs) => {
+                blobs.record_inline_read();
+                result.slice(8..)
+            }
+            None => result.slice(8..),
+        };
+        let size = key.len() + 8 + bytes.len();
+        let codomain = Codomain::from_bytes(bytes).map_err(|_| Error::EncodingFailure)?;
+        Ok((Timestamp(ts & !OFFLOADED), codomain, size))
+    }
+
+    /// Encode a value for storing, offloading it if it's to be, along with whether it was
+    /// offloaded under its own key.
+    fn encode(
+        &self,
+        key: &[u8],
+        ts: Timestamp,
+        codomain: Codomain,
+    ) -> Result<(UserValue, bool), Error> {
+        let as_bytes = codomain.as_bytes().map_err(|_| Error::EncodingFailure)?;
+        if let Some(blobs) = &self.blobs {
+            if blobs.offloads(as_bytes.len()) {
+                let stub = blobs.store(key, &as_bytes)?;
+                let mut result = Vec::with_capacity(8 + stub.len());
+                result.extend_from_slice(&(ts.0 | OFFLOADED).to_le_bytes());
+                result.extend_from_slice(&stub);
+                return Ok((UserValue::from(Bytes::from(result)), stub.is_empty()));
+            }
+        }
+        let mut result = Vec::with_capacity(8 + as_bytes.len());
+        result.extend_from_slice(&ts.0.to_le_bytes());
+        result.extend_from_slice(&as_bytes);
+        Ok((UserValue::from(Bytes::from(result)), false))
+    }
+
+    /// What's stored for `key` now, if it might have to be let go of by the blob store.
+    fn stored(&self, key: &[u8]) -> Result<Option<UserValue>, Error> {
+        if self.blobs.is_none() {
+            return Ok(None);
+        }
+        self.fjall_partition
+            .get(key)
+            .map_err(|e| Error::RetrievalFailure(e.to_string()))
+    }
 }
 
 impl<Domain, Codomain> Provider<Domain, Codomain> for FjallProvider<Domain, Codomain>
@@ -68,33 +129,45 @@ where
 {
     fn get(&self, domain: &Domain) -> Result<Option<(Timestamp, Codomain, usize)>, Error> {
         let key = domain.as_bytes().map_err(|_| Error::EncodingFailure)?;
-        let key_len = key.len();
-        let Some(result) = self
-            .fjall_partition
-            .get(key)
-            .map_err(|e| Error::RetrievalFailure(e.to_string()))?
-        else {
+        let result = match &self.snapshot {
+            Some(snapshot) => snapshot.get(&key),
+            None => self.fjall_partition.get(&key),
+        };
+        let Some(result) = result.map_err(|e| Error::RetrievalFailure(e.to_string()))? else {
             return Ok(None);
         };
-        let size = key_len + result.len();
-        let (ts, codomain) = decode::<Codomain>(result)?;
+        let (ts, codomain, size) = self.decode(&key, result)?;
         Ok(Some((ts, codomain, size)))
     }
 
     fn put(&self, timestamp: Timestamp, domain: Domain, codomain: Codomain) -> Result<(), Error> {
+        if self.snapshot.is_some() {
+            return Err(Error::StorageFailure("snapshot is read-only".to_string()));
+        }
         let key = domain.as_bytes().map_err(|_| Error::EncodingFailure)?;
-        let value = encode::<Codomain>(timestamp, codomain)?;
+        let old = self.stored(&key)?;
+        let (value, rekeyed) = self.encode(&key, timestamp, codomain)?;
         self.fjall_partition
-            .insert(key, value)
+            .insert(key.clone(), value)
             .map_err(|e| Error::StorageFailure(e.to_string()))?;
+        if let Some(blobs) = &self.blobs {
+            blobs.replaced(&key, old.as_deref(), rekeyed)?;
+        }
         Ok(())
     }
 
     fn del(&self, _timestamp: Timestamp, domain: &Domain) -> Result<(), Error> {
+        if self.snapshot.is_some() {
+            return Err(Error::StorageFailure("snapshot is read-only".to_string()));
+        }
         let key = domain.as_bytes().map_err(|_| Error::EncodingFailure)?;
+        let old = self.stored(&key)?;
         self.fjall_partition
-            .remove(key)
+            .remove(key.clone())
             .map_err(|e| Error::StorageFailure(e.to_string()))?;
+        if let Some(blobs) = &self.blobs {
+            blobs.replaced(&key, old.as_deref(), false)?;
+        }
         Ok(())
     }
 
@@ -102,16 +175,39 @@ where
     where
         F: Fn(&Domain, &Codomain) -> bool,
     {
+        let entries: Box<dyn Iterator<Item = fjall::Result<(UserKey, UserValue)>>> =
+            match &self.snapshot {
+                Some(snapshot) => Box::new(snapshot.iter()),
+                None => Box::new(self.fjall_partition.iter()),
+            };
         let mut result = Vec::new();
-        for entry in self.fjall_partition.iter() {
+        for entry in entries {
             let (key, value) = entry.map_err(|e| Error::RetrievalFailure(e.to_string()))?;
-            let size = key.len() + value.len();
+            let (ts, codomain, size) = self.decode(&key, value)?;
             let domain = Domain::from_bytes(key.into()).map_err(|_| Error::EncodingFailure)?;
-            let (ts, codomain) = decode::<Codomain>(value)?;
             if predicate(&domain, &codomain) {
                 result.push((ts, domain, codomain, size));
             }
         }
         Ok(result)
     }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
+    where
+        Domain: AsByteBuffer,
+    {
+        let entries: Box<dyn Iterator<Item = fjall::Result<(UserKey, UserValue)>>> =
+            match &self.snapshot {
+                Some(snapshot) => Box::new(snapshot.prefix(prefix)),
+                None => Box::new(self.fjall_partition.prefix(prefix)),
+            };
+        let mut result = Vec::new();
+        for entry in entries {
+            let (key, value) = entry.map_err(|e| Error::RetrievalFailure(e.to_string()))?;
+            let (ts, codomain, size) = self.decode(&key, value)?;
+            let domain = Domain::from_bytes(key.into()).map_err(|_| Error::EncodingFailure)?;
+            result.push((ts, domain, codomain, size));
+        }
+        Ok(result)
+    }
 }
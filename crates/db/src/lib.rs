@@ -16,26 +16,36 @@ use moor_values::model::WorldStateSource;
 use moor_values::model::{WorldState, WorldStateError};
 use moor_values::{AsByteBuffer, DecodingError, EncodingError, Obj};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 use crate::loader::LoaderInterface;
 
+mod blobs;
+mod changes;
+mod content;
 mod db_loader_client;
 pub mod db_worldstate;
 pub mod loader;
+pub mod migration;
 pub mod worldstate_transaction;
 
 mod db_transaction;
 mod fjall_provider;
+mod object_generations;
 pub(crate) mod worldstate_db;
 mod worldstate_tests;
 
 use crate::db_worldstate::DbTxWorldState;
 use crate::worldstate_db::WorldStateDB;
-pub use config::{DatabaseConfig, TableConfig};
+pub use changes::{Change, CommittedChanges};
+pub use config::{DatabaseConfig, ObjectIdAllocation, TableConfig};
+pub use program_cache::ProgramCache;
 pub use worldstate_tests::*;
 mod config;
+mod program_cache;
+mod prop_resolution_cache;
+mod text_index;
 mod tx;
 
 pub use tx::Provider;
@@ -43,6 +53,14 @@ pub use tx::{Error, Timestamp, TransactionalCache, TransactionalTable, Tx, Worki
 
 pub trait Database: Send + WorldStateSource {
     fn loader_client(&self) -> Result<Box<dyn LoaderInterface>, WorldStateError>;
+
+    /// Get a handle on the persistent cache of compiled programs.
+    fn program_cache(&self) -> ProgramCache;
+
+    /// Subscribe to a stream of what each transaction committed from now on changed (objects
+    /// created & recycled, properties written, verbs programmed). The subscription ends when the
+    /// receiver is dropped.
+    fn subscribe_changes(&self) -> crossbeam_channel::Receiver<CommittedChanges>;
 }
 
 #[derive(Clone)]
@@ -56,10 +74,25 @@ impl TxDB {
         (Self { storage }, fresh)
     }
 }
+impl TxDB {
+    /// A read-only replica of the database, for serving snapshot-consistent reads (dashboards,
+    /// indexing, backups) alongside the running world without holding up its commits. It sees
+    /// the database as of the last commit before it was made, or last refreshed.
+    ///
+    /// The storage can only be opened by one process, so replicas live in the same process as the
+    /// database; a second daemon can't open the directory.
+    pub fn replica(&self) -> Replica {
+        Replica {
+            primary: self.storage.clone(),
+            snapshot: RwLock::new(self.storage.snapshot()),
+        }
+    }
+}
+
 impl WorldStateSource for TxDB {
     fn new_world_state(&self) -> Result<Box<dyn WorldState>, WorldStateError> {
         let tx = self.storage.start_transaction();
-        let tx = DbTxWorldState { tx };
+        let tx = DbTxWorldState::new(tx, self.storage.text_index());
         Ok(Box::new(tx))
     }
 
@@ -73,9 +106,48 @@ impl WorldStateSource for TxDB {
 impl Database for TxDB {
     fn loader_client(&self) -> Result<Box<dyn LoaderInterface>, WorldStateError> {
         let tx = self.storage.start_transaction();
-        let tx = DbTxWorldState { tx };
+        let tx = DbTxWorldState::new(tx, self.storage.text_index());
         Ok(Box::new(tx))
     }
+
+    fn program_cache(&self) -> ProgramCache {
+        self.storage.program_cache()
+    }
+
+    fn subscribe_changes(&self) -> crossbeam_channel::Receiver<CommittedChanges> {
+        self.storage.subscribe_changes()
+    }
+}
+
+/// A read-only view of a `TxDB` as of a particular commit. See `TxDB::replica`.
+///
+/// Every world state taken from a replica reads the same snapshot until `refresh` is called.
+/// Transactions on it which try to change anything fail to commit.
+pub struct Replica {
+    primary: Arc<WorldStateDB>,
+    snapshot: RwLock<Arc<WorldStateDB>>,
+}
+
+impl Replica {
+    /// Move the replica up to the primary's latest commit. World states already taken from it
+    /// keep reading the snapshot they started with.
+    pub fn refresh(&self) {
+        let snapshot = self.primary.snapshot();
+        *self.snapshot.write().unwrap() = snapshot;
+    }
+}
+
+impl WorldStateSource for Replica {
+    fn new_world_state(&self) -> Result<Box<dyn WorldState>, WorldStateError> {
+        let snapshot = self.snapshot.read().unwrap().clone();
+        let tx = snapshot.start_transaction();
+        let tx = DbTxWorldState::new(tx, snapshot.text_index());
+        Ok(Box::new(tx))
+    }
+
+    fn checkpoint(&self) -> Result<(), WorldStateError> {
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -206,6 +278,8 @@ impl AsByteBuffer for SystemTimeHolder {
     }
 }
 
+/// The key for the relations holding an object's verbs and properties. It's encoded object first,
+/// so that all of an object's entries are stored together, and can be found with a prefix scan.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ObjAndUUIDHolder {
     pub obj: Obj,
@@ -227,26 +301,27 @@ impl AsByteBuffer for ObjAndUUIDHolder {
 
     fn with_byte_buffer<R, F: FnMut(&[u8]) -> R>(&self, mut f: F) -> Result<R, EncodingError> {
         let mut bytes = Vec::with_capacity(self.size_bytes());
-        bytes.extend_from_slice(self.uuid.as_bytes());
         bytes.extend_from_slice(&self.obj.as_bytes()?);
+        bytes.extend_from_slice(self.uuid.as_bytes());
         Ok(f(&bytes))
     }
 
     fn make_copy_as_vec(&self) -> Result<Vec<u8>, EncodingError> {
         let mut bytes = Vec::with_capacity(self.size_bytes());
-        bytes.extend_from_slice(self.uuid.as_bytes());
         bytes.extend_from_slice(&self.obj.as_bytes()?);
+        bytes.extend_from_slice(self.uuid.as_bytes());
         Ok(bytes)
     }
 
     fn from_bytes(bytes: Bytes) -> Result<Self, DecodingError> {
         let bytes = bytes.as_ref();
-        let uuid_bytes = bytes.get(..16).ok_or(DecodingError::CouldNotDecode(
-            "Expected 16 bytes for UUID".to_string(),
-        ))?;
-        let obj_bytes = bytes.get(16..).ok_or(DecodingError::CouldNotDecode(
-            "Expected 16 bytes for UUID".to_string(),
-        ))?;
+        let split = bytes
+            .len()
+            .checked_sub(16)
+            .ok_or(DecodingError::CouldNotDecode(
+                "Expected 16 bytes for UUID".to_string(),
+            ))?;
+        let (obj_bytes, uuid_bytes) = bytes.split_at(split);
         let uuid = Uuid::from_bytes(uuid_bytes.try_into().map_err(|_| {
             DecodingError::CouldNotDecode("Expected 16 bytes for UUID".to_string())
         })?);
@@ -256,8 +331,8 @@ impl AsByteBuffer for ObjAndUUIDHolder {
 
     fn as_bytes(&self) -> Result<Bytes, EncodingError> {
         let mut bytes = Vec::with_capacity(self.size_bytes());
-        bytes.extend_from_slice(self.uuid.as_bytes());
         bytes.extend_from_slice(&self.obj.as_bytes()?);
+        bytes.extend_from_slice(self.uuid.as_bytes());
         Ok(Bytes::from(bytes))
     }
 }
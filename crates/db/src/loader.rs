@@ -26,6 +26,8 @@ use moor_values::util::BitEnum;
 use moor_values::Obj;
 use moor_values::Var;
 
+use crate::program_cache::ProgramCache;
+
 /// Interface exposed to be used by the textdump loader. Overlap of functionality with what
 /// WorldState could provide, but potentially different constraints/semantics (e.g. no perms checks)
 pub trait LoaderInterface: Send {
@@ -69,6 +71,16 @@ pub trait LoaderInterface: Send {
         value: Option<Var>,
     ) -> Result<(), WorldStateError>;
 
+    /// Get a handle on the compiled program cache, so that importers can skip recompiling verbs
+    /// whose source has not changed.
+    fn program_cache(&self) -> ProgramCache;
+
+    /// Renumber every object down into the lowest free object #, in ascending order, so that the
+    /// object numbers in use become contiguous from #0, and reset the max object accordingly.
+    /// All references held by the database are rewritten, but references in property values and
+    /// verb code are not. Returns the (old, new) numbers of the objects which were moved.
+    fn compact(&mut self) -> Result<Vec<(Obj, Obj)>, WorldStateError>;
+
     fn commit(self: Box<Self>) -> Result<CommitResult, WorldStateError>;
 
     // For writing textdumps...
@@ -0,0 +1,379 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Upgrading database directories written by older versions of moor.
+//!
+//! Each directory records the version of the layout of its partitions in a `schema_version` file
+//! next to them. Directories from before the file existed are version 0. When a database is
+//! opened, the migrations from its version up to `SCHEMA_VERSION` are run one at a time, and the
+//! version file is rewritten after each one, so that an interrupted upgrade picks up where it
+//! left off. Each migration's changes are committed in one batch, together with a record in the
+//! `schema_migrations` partition that it's been applied, so that one interrupted before its
+//! version was written isn't applied twice.
+//!
+//! Changing how any partition is laid out (keys, value encodings, partition names) means bumping
+//! `SCHEMA_VERSION` and adding a `Migration` from the previous version to `MIGRATIONS`.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use fjall::{Batch, Keyspace, PartitionCreateOptions, PersistMode};
+use tracing::info;
+
+/// The version of the on-disk layout written by this version of moor.
+pub const SCHEMA_VERSION: u32 = 4;
+
+const VERSION_FILE: &str = "schema_version";
+
+/// The partition recording which migrations have been applied, keyed by their `from` version.
+const APPLIED_PARTITION: &str = "schema_migrations";
+
+/// A step from one schema version to the next.
+pub struct Migration {
+    /// The version this migration upgrades from, to `from + 1`.
+    pub from: u32,
+    pub description: &'static str,
+    /// Adds the migration's changes to the batch, which is committed once they're all in.
+    apply: fn(&Keyspace, &mut Batch) -> Result<(), String>,
+}
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "Record the schema version of a database created before it was recorded",
+        apply: |_, _| Ok(()),
+    },
+    Migration {
+        from: 1,
+        description: "Key verbs and property values by object first, then uuid",
+        apply: swap_obj_uuid_keys,
+    },
+    Migration {
+        from: 2,
+        description: "Allow large property values to be kept in a partition of their own",
+        // Values written before are all inline; it's older versions which can't read newer ones.
+        apply: |_, _| Ok(()),
+    },
+    Migration {
+        from: 3,
+        description: "Allow verb programs and large property values to be deduplicated by content",
+        apply: |_, _| Ok(()),
+    },
+];
+
+/// The partitions keyed on (object, uuid).
+const OBJ_UUID_PARTITIONS: [&str; 3] = ["object_verbs", "object_propvalues", "object_propflags"];
+
+/// Rewrite the keys of the (object, uuid) partitions from uuid-then-object to object-then-uuid.
+fn swap_obj_uuid_keys(keyspace: &Keyspace, batch: &mut Batch) -> Result<(), String> {
+    for name in OBJ_UUID_PARTITIONS {
+        if !keyspace.partition_exists(name) {
+            continue;
+        }
+        let partition = keyspace
+            .open_partition(name, PartitionCreateOptions::default())
+            .map_err(|e| e.to_string())?;
+        let mut entries = vec![];
+        for entry in partition.iter() {
+            let (key, value) = entry.map_err(|e| e.to_string())?;
+            if key.len() < 16 {
+                return Err(format!("Malformed key in {name}"));
+            }
+            entries.push((key, value));
+        }
+        let swapped: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| {
+                let (uuid, obj) = key.split_at(16);
+                (key.clone(), [obj, uuid].concat(), value)
+            })
+            .collect();
+        // An old key which is also someone's new key is overwritten rather than removed, as the
+        // batch can't order a remove and an insert of the same key.
+        let new_keys: HashSet<&[u8]> = swapped.iter().map(|(_, new, _)| &new[..]).collect();
+        for (old_key, _, _) in &swapped {
+            if !new_keys.contains(&old_key[..]) {
+                batch.remove(&partition, old_key.clone());
+            }
+        }
+        for (_, new_key, value) in swapped {
+            batch.insert(&partition, new_key, value);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Database schema version {0} is newer than this version of moor supports")]
+    TooNew(u32),
+    #[error("No migration from schema version {0}")]
+    NoMigration(u32),
+    #[error("Unable to back up database to {0}: {1}")]
+    BackupFailed(String, String),
+    #[error("Migration from schema version {0} failed: {1}")]
+    MigrationFailed(u32, String),
+    #[error("Unable to read or write schema version: {0}")]
+    VersionFile(String),
+}
+
+/// The schema version of the database at `path`, or None if there's no database there yet.
+pub fn schema_version(path: &Path) -> Result<Option<u32>, MigrationError> {
+    let version_file = path.join(VERSION_FILE);
+    if version_file.exists() {
+        let version = std::fs::read_to_string(&version_file)
+            .map_err(|e| MigrationError::VersionFile(e.to_string()))?;
+        let version = version
+            .trim()
+            .parse()
+            .map_err(|e: std::num::ParseIntError| MigrationError::VersionFile(e.to_string()))?;
+        return Ok(Some(version));
+    }
+    let empty = match std::fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => return Err(MigrationError::VersionFile(e.to_string())),
+    };
+    Ok((!empty).then_some(0))
+}
+
+/// The migrations which opening the database at `path` would run, in order.
+pub fn pending_migrations(path: &Path) -> Result<Vec<&'static Migration>, MigrationError> {
+    let Some(mut version) = schema_version(path)? else {
+        return Ok(vec![]);
+    };
+    if version > SCHEMA_VERSION {
+        return Err(MigrationError::TooNew(version));
+    }
+    let mut pending = vec![];
+    while version < SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or(MigrationError::NoMigration(version))?;
+        pending.push(migration);
+        version += 1;
+    }
+    Ok(pending)
+}
+
+/// Copy the database at `path` to `backup`, which must not exist yet. Must be done before the
+/// keyspace is opened.
+pub(crate) fn backup(path: &Path, backup: &Path) -> Result<(), MigrationError> {
+    let failed = |e: std::io::Error| {
+        MigrationError::BackupFailed(backup.to_string_lossy().to_string(), e.to_string())
+    };
+    if backup.exists() {
+        return Err(failed(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "backup path already exists",
+        )));
+    }
+    copy_dir(path, backup).map_err(failed)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `pending` (from `pending_migrations`) against the freshly opened `keyspace`, recording the
+/// new version after each, or just record the current version for a new database.
+pub(crate) fn migrate(
+    path: &Path,
+    keyspace: &Keyspace,
+    pending: &[&Migration],
+) -> Result<(), MigrationError> {
+    for migration in pending {
+        let failed = |e: String| MigrationError::MigrationFailed(migration.from, e);
+        let applied = keyspace
+            .open_partition(APPLIED_PARTITION, PartitionCreateOptions::default())
+            .map_err(|e| failed(e.to_string()))?;
+        let marker = migration.from.to_be_bytes();
+        if applied
+            .contains_key(marker)
+            .map_err(|e| failed(e.to_string()))?
+        {
+            info!(
+                from = migration.from,
+                "Migration already applied; recording version"
+            );
+        } else {
+            info!(
+                from = migration.from,
+                to = migration.from + 1,
+                "Migrating database: {}",
+                migration.description
+            );
+            let mut batch = keyspace.batch();
+            (migration.apply)(keyspace, &mut batch).map_err(failed)?;
+            batch.insert(&applied, marker.to_vec(), migration.description);
+            batch.commit().map_err(|e| failed(e.to_string()))?;
+        }
+        keyspace
+            .persist(PersistMode::SyncAll)
+            .map_err(|e| MigrationError::MigrationFailed(migration.from, e.to_string()))?;
+        write_version(path, migration.from + 1)?;
+    }
+    if pending.is_empty() && schema_version(path)? != Some(SCHEMA_VERSION) {
+        write_version(path, SCHEMA_VERSION)?;
+    }
+    Ok(())
+}
+
+fn write_version(path: &Path, version: u32) -> Result<(), MigrationError> {
+    // Write-then-rename, so a crash never leaves a torn version file.
+    let temp = path.join(format!("{VERSION_FILE}.tmp"));
+    std::fs::write(&temp, version.to_string())
+        .and_then(|_| std::fs::rename(&temp, path.join(VERSION_FILE)))
+        .map_err(|e| MigrationError::VersionFile(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use fjall::{Config, PartitionCreateOptions};
+
+    use crate::migration::{
+        backup, migrate, pending_migrations, schema_version, write_version, MigrationError,
+        SCHEMA_VERSION,
+    };
+
+    #[test]
+    fn test_fresh_database_is_current() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(schema_version(dir.path()).unwrap(), None);
+        assert!(pending_migrations(dir.path()).unwrap().is_empty());
+
+        let keyspace = Config::new(dir.path()).open().unwrap();
+        migrate(dir.path(), &keyspace, &[]).unwrap();
+        assert_eq!(schema_version(dir.path()).unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_unversioned_database_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let keyspace = Config::new(dir.path()).open().unwrap();
+            keyspace
+                .open_partition("object_location", PartitionCreateOptions::default())
+                .unwrap();
+            keyspace.persist(fjall::PersistMode::SyncAll).unwrap();
+        }
+        assert_eq!(schema_version(dir.path()).unwrap(), Some(0));
+
+        let pending = pending_migrations(dir.path()).unwrap();
+        assert_eq!(pending.len(), SCHEMA_VERSION as usize);
+        assert_eq!(pending[0].from, 0);
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup");
+        backup(dir.path(), &backup_path).unwrap();
+        assert_eq!(schema_version(&backup_path).unwrap(), Some(0));
+        assert!(matches!(
+            backup(dir.path(), &backup_path),
+            Err(MigrationError::BackupFailed(_, _))
+        ));
+
+        let keyspace = Config::new(dir.path()).open().unwrap();
+        migrate(dir.path(), &keyspace, &pending).unwrap();
+        assert_eq!(schema_version(dir.path()).unwrap(), Some(SCHEMA_VERSION));
+        assert!(pending_migrations(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_obj_uuid_keys_are_swapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let uuid = [7u8; 16];
+        let obj = 5i32.to_le_bytes();
+        {
+            let keyspace = Config::new(dir.path()).open().unwrap();
+            let propvalues = keyspace
+                .open_partition("object_propvalues", PartitionCreateOptions::default())
+                .unwrap();
+            propvalues
+                .insert([&uuid[..], &obj[..]].concat(), "value")
+                .unwrap();
+            keyspace.persist(fjall::PersistMode::SyncAll).unwrap();
+        }
+        write_version(dir.path(), 1).unwrap();
+
+        let pending = pending_migrations(dir.path()).unwrap();
+        assert_eq!(pending[0].from, 1);
+        let keyspace = Config::new(dir.path()).open().unwrap();
+        migrate(dir.path(), &keyspace, &pending).unwrap();
+
+        let propvalues = keyspace
+            .open_partition("object_propvalues", PartitionCreateOptions::default())
+            .unwrap();
+        let entries: Vec<_> = propvalues.iter().map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(&*entries[0].0, &[&obj[..], &uuid[..]].concat()[..]);
+        assert_eq!(&*entries[0].1, b"value");
+    }
+
+    #[test]
+    fn test_interrupted_migration_is_not_reapplied() {
+        let dir = tempfile::tempdir().unwrap();
+        let uuid = [7u8; 16];
+        let obj = 5i32.to_le_bytes();
+        {
+            let keyspace = Config::new(dir.path()).open().unwrap();
+            let verbs = keyspace
+                .open_partition("object_verbs", PartitionCreateOptions::default())
+                .unwrap();
+            verbs
+                .insert([&uuid[..], &obj[..]].concat(), "verb")
+                .unwrap();
+            keyspace.persist(fjall::PersistMode::SyncAll).unwrap();
+        }
+        write_version(dir.path(), 1).unwrap();
+
+        let keyspace = Config::new(dir.path()).open().unwrap();
+        let pending = pending_migrations(dir.path()).unwrap();
+        migrate(dir.path(), &keyspace, &pending).unwrap();
+
+        // As if the process died after the keys were swapped, but before the version was written.
+        write_version(dir.path(), 1).unwrap();
+        let pending = pending_migrations(dir.path()).unwrap();
+        assert_eq!(pending[0].from, 1);
+        migrate(dir.path(), &keyspace, &pending).unwrap();
+        assert_eq!(schema_version(dir.path()).unwrap(), Some(SCHEMA_VERSION));
+
+        let verbs = keyspace
+            .open_partition("object_verbs", PartitionCreateOptions::default())
+            .unwrap();
+        let entries: Vec<_> = verbs.iter().map(|e| e.unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(&*entries[0].0, &[&obj[..], &uuid[..]].concat()[..]);
+        assert_eq!(&*entries[0].1, b"verb");
+    }
+
+    #[test]
+    fn test_newer_database_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        write_version(dir.path(), SCHEMA_VERSION + 1).unwrap();
+        assert_eq!(
+            pending_migrations(dir.path()).map(|p| p.len()),
+            Err(MigrationError::TooNew(SCHEMA_VERSION + 1))
+        );
+    }
+}
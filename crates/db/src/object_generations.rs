@@ -0,0 +1,88 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use std::collections::HashSet;
+
+use fjall::PartitionHandle;
+use moor_values::{AsByteBuffer, Obj};
+
+use crate::worldstate_db::WorkingSets;
+
+/// The generation of each object: the timestamp of the last committed transaction which changed
+/// anything about it (its attributes, contents, children, verbs or properties).
+///
+/// Generations only ever go up, so something outside the database holding a copy of an object
+/// (a web client, an editor, a dashboard) can tell whether it's stale by comparing the generation
+/// it was made at with the current one, rather than the contents. An object which hasn't changed
+/// since generations started being kept is at generation 0.
+///
+/// They're kept outside of the transactional relations, written by the commit thread, so that
+/// transactions changing different things about the same object don't conflict over them.
+#[derive(Clone)]
+pub(crate) struct ObjectGenerations {
+    partition: PartitionHandle,
+    /// For snapshots, the instant they're as of.
+    snapshot_at: Option<fjall::Instant>,
+}
+
+impl ObjectGenerations {
+    pub(crate) fn new(partition: PartitionHandle, snapshot_at: Option<fjall::Instant>) -> Self {
+        Self {
+            partition,
+            snapshot_at,
+        }
+    }
+
+    pub(crate) fn get(&self, obj: &Obj) -> Result<u64, fjall::Error> {
+        let key = obj.as_bytes().expect("Unable to encode object");
+        let value = match self.snapshot_at {
+            Some(instant) => self.partition.snapshot_at(instant).get(key)?,
+            None => self.partition.get(key)?,
+        };
+        Ok(value
+            .and_then(|v| <[u8; 8]>::try_from(&*v).ok())
+            .map(u64::from_le_bytes)
+            .unwrap_or(0))
+    }
+
+    /// Move each of `objects` to `generation`.
+    pub(crate) fn advance(
+        &self,
+        objects: &HashSet<Obj>,
+        generation: u64,
+    ) -> Result<(), fjall::Error> {
+        for obj in objects {
+            let key = obj.as_bytes().expect("Unable to encode object");
+            self.partition.insert(key, generation.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Every object a transaction changed anything about, from its working sets.
+pub(crate) fn touched_objects(ws: &WorkingSets) -> HashSet<Obj> {
+    let mut objects = HashSet::new();
+    objects.extend(ws.object_location.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_contents.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_flags.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_parent.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_children.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_owner.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_name.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_verbdefs.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_verbs.iter().map(|(h, _)| h.obj.clone()));
+    objects.extend(ws.object_propdefs.iter().map(|(obj, _)| obj.clone()));
+    objects.extend(ws.object_propvalues.iter().map(|(h, _)| h.obj.clone()));
+    objects.extend(ws.object_propflags.iter().map(|(h, _)| h.obj.clone()));
+    objects
+}
@@ -0,0 +1,124 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use bytes::Bytes;
+use fjall::PartitionHandle;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Where the generation of the compiler which filled the cache is recorded. Program keys are
+/// hashes, all of one length, which this isn't, so it can't be mistaken for one.
+const GENERATION_KEY: &[u8] = b"generation";
+
+/// A persistent, content-addressed cache of compiled verb binaries.
+///
+/// Keys are opaque hashes produced by the caller (typically over the source text and the compile
+/// options used), and values are the encoded program. Because entries are a pure function of their
+/// key, the cache lives outside the transactional tables: writes are not part of any transaction,
+/// never conflict, and are safe to keep around even if the transaction which produced them
+/// rolls back.
+#[derive(Clone)]
+pub struct ProgramCache {
+    partition: Option<PartitionHandle>,
+    hits: Arc<AtomicUsize>,
+    misses: Arc<AtomicUsize>,
+}
+
+impl ProgramCache {
+    pub(crate) fn new(partition: PartitionHandle) -> Self {
+        Self {
+            partition: Some(partition),
+            hits: Arc::new(AtomicUsize::new(0)),
+            misses: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// A cache which never stores or returns anything.
+    pub fn disabled() -> Self {
+        Self {
+            partition: None,
+            hits: Arc::new(AtomicUsize::new(0)),
+            misses: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Look up the program binary for the given source hash, if we have one.
+    pub fn lookup(&self, source_hash: &[u8]) -> Option<Bytes> {
+        let partition = self.partition.as_ref()?;
+        match partition.get(source_hash) {
+            Ok(Some(binary)) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(Bytes::from(binary.to_vec()))
+            }
+            Ok(None) => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(e) => {
+                warn!(?e, "Failed to read from program cache");
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Record the program binary for the given source hash.
+    pub fn store(&self, source_hash: &[u8], binary: &[u8]) {
+        let Some(partition) = self.partition.as_ref() else {
+            return;
+        };
+        if let Err(e) = partition.insert(source_hash, binary) {
+            warn!(?e, "Failed to write to program cache");
+        }
+    }
+
+    /// Empty the cache if what's in it was compiled by another generation of the compiler than
+    /// `generation` (however the caller identifies it), since those binaries mightn't run the same,
+    /// and record `generation` as the one filling it from now on.
+    pub fn retain_generation(&self, generation: &[u8]) {
+        let Some(partition) = self.partition.as_ref() else {
+            return;
+        };
+        match partition.get(GENERATION_KEY) {
+            Ok(Some(stored)) if stored.as_ref() == generation => return,
+            Ok(_) => {}
+            Err(e) => warn!(?e, "Failed to read program cache generation"),
+        }
+        let keys: Vec<_> = partition
+            .iter()
+            .filter_map(|entry| entry.ok().map(|(key, _)| key))
+            .collect();
+        for key in &keys {
+            if let Err(e) = partition.remove(key) {
+                warn!(?e, "Failed to clear program cache");
+                return;
+            }
+        }
+        info!(
+            entries = keys.len(),
+            "Cleared program cache filled by another compiler"
+        );
+        if let Err(e) = partition.insert(GENERATION_KEY, generation) {
+            warn!(?e, "Failed to write program cache generation");
+        }
+    }
+
+    /// Returns (hits, misses) since the database was opened.
+    pub fn stats(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
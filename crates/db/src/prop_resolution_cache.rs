@@ -0,0 +1,67 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use moor_values::{Obj, Symbol};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Past this many entries the cache is emptied and starts over, rather than tracking what's
+/// least recently used.
+const MAX_ENTRIES: usize = 1 << 16;
+
+/// Which object a property name resolves to the definition on, for an object, as of a dispatch
+/// generation: the object itself or its nearest ancestor defining it, or none at all.
+///
+/// Misses are cached as well as hits, so that looking up a property which isn't there (e.g. the
+/// optional properties core code probes for) doesn't walk the whole of a long inheritance chain
+/// each time. Property definitions and inheritance only change along with the dispatch
+/// generation, so everything cached under an earlier one is dropped once a later one is seen.
+///
+/// Like the program cache, this sits outside of transactions, and only holds what's been
+/// committed: it mustn't be consulted or filled by a transaction which has made dispatch changes
+/// of its own.
+#[derive(Default)]
+pub(crate) struct PropResolutionCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    generation: u64,
+    definers: HashMap<(Obj, Symbol), Option<Obj>>,
+}
+
+impl PropResolutionCache {
+    /// Where `name` is defined for `obj` as of `generation`, if that's known: `Some(None)` if it's
+    /// known not to be defined at all.
+    pub(crate) fn lookup(&self, generation: u64, obj: &Obj, name: Symbol) -> Option<Option<Obj>> {
+        let inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return None;
+        }
+        inner.definers.get(&(obj.clone(), name)).cloned()
+    }
+
+    /// Record where `name` is defined for `obj` (if anywhere) as of `generation`.
+    pub(crate) fn remember(&self, generation: u64, obj: &Obj, name: Symbol, definer: Option<Obj>) {
+        let mut inner = self.inner.lock().unwrap();
+        if generation < inner.generation {
+            return;
+        }
+        if generation > inner.generation || inner.definers.len() >= MAX_ENTRIES {
+            inner.generation = generation;
+            inner.definers.clear();
+        }
+        inner.definers.insert((obj.clone(), name), definer);
+    }
+}
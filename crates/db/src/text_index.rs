@@ -0,0 +1,318 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! An optional full-text index over the values of a configured set of properties, for
+//! `search_text()`. Writes to those properties are queued once the transaction which made them
+//! has committed, and applied in batches on a thread of their own, so searches may lag slightly
+//! behind the world.
+//!
+//! The index itself (tantivy) is only built with the `fts` feature; without it, configuring
+//! indexed properties is an error at startup.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use moor_values::{Obj, Symbol, Var, Variant};
+
+/// A change to an indexed property, made by a committed transaction.
+#[derive(Debug)]
+pub(crate) enum TextIndexUpdate {
+    /// `obj.prop` now holds this text.
+    Set(Obj, Symbol, String),
+    /// `obj.prop` no longer holds any text of its own.
+    Clear(Obj, Symbol),
+    /// `obj` is gone.
+    Recycle(Obj),
+}
+
+/// The text to index for `value`: strings as they are, and lists (of lists) of strings one per
+/// line, which is how most cores store mail, news and the like. Anything else isn't text.
+pub(crate) fn indexable_text(value: &Var) -> Option<String> {
+    fn collect(value: &Var, lines: &mut Vec<String>) {
+        match value.variant() {
+            Variant::Str(s) => lines.push(s.as_string().clone()),
+            Variant::List(l) => {
+                for v in l.iter() {
+                    collect(&v, lines);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut lines = vec![];
+    collect(value, &mut lines);
+    if lines.is_empty() {
+        return None;
+    }
+    Some(lines.join("\n"))
+}
+
+pub(crate) struct TextIndex {
+    /// Lowercased names of the properties to index.
+    properties: HashSet<String>,
+    #[cfg(feature = "fts")]
+    inner: fts::TantivyIndex,
+}
+
+impl TextIndex {
+    /// Open (or create) the index under `path`, or in memory if there's no path.
+    pub(crate) fn open(path: Option<&Path>, properties: &[String]) -> Result<Self, String> {
+        let properties: HashSet<String> = properties.iter().map(|p| p.to_lowercase()).collect();
+        #[cfg(feature = "fts")]
+        {
+            Ok(Self {
+                properties,
+                inner: fts::TantivyIndex::open(path)?,
+            })
+        }
+        #[cfg(not(feature = "fts"))]
+        {
+            let _ = (path, properties);
+            Err("full-text indexing requires moor to be built with the `fts` feature".to_string())
+        }
+    }
+
+    /// Whether writes to properties named `pname` are indexed.
+    pub(crate) fn indexes(&self, pname: Symbol) -> bool {
+        self.properties.contains(&pname.as_str().to_lowercase())
+    }
+
+    /// Queue the updates made by a transaction which has just committed.
+    pub(crate) fn update(&self, updates: Vec<TextIndexUpdate>) {
+        if updates.is_empty() {
+            return;
+        }
+        #[cfg(feature = "fts")]
+        self.inner.update(updates);
+    }
+
+    /// Up to `limit` `(object, property)` pairs whose text matches `query`, best first. Queries use
+    /// tantivy's syntax (`+must -mustnot "a phrase"`), though anything it can't parse is searched
+    /// for as plain words rather than rejected.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Result<Vec<(Obj, Symbol)>, String> {
+        #[cfg(feature = "fts")]
+        {
+            self.inner.search(query, limit)
+        }
+        #[cfg(not(feature = "fts"))]
+        {
+            let _ = (query, limit);
+            Ok(vec![])
+        }
+    }
+}
+
+#[cfg(feature = "fts")]
+mod fts {
+    use std::path::Path;
+
+    use crossbeam_channel::Sender;
+    use tantivy::collector::TopDocs;
+    use tantivy::directory::MmapDirectory;
+    use tantivy::query::QueryParser;
+    use tantivy::schema::{Field, Schema, Value, INDEXED, STORED, STRING, TEXT};
+    use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+    use tracing::{debug, error};
+
+    use moor_values::{Obj, Symbol};
+
+    use crate::text_index::TextIndexUpdate;
+
+    /// Memory the index writer may buffer before flushing a segment.
+    const WRITER_MEMORY_BYTES: usize = 50_000_000;
+
+    #[derive(Clone, Copy)]
+    struct Fields {
+        /// "obj.prop", so a property's document can be replaced.
+        key: Field,
+        obj: Field,
+        prop: Field,
+        text: Field,
+    }
+
+    pub(super) struct TantivyIndex {
+        index: Index,
+        reader: IndexReader,
+        fields: Fields,
+        updates: Sender<Vec<TextIndexUpdate>>,
+    }
+
+    fn key(obj: &Obj, prop: &str) -> String {
+        format!("{}.{}", obj.id().0, prop)
+    }
+
+    impl TantivyIndex {
+        pub(super) fn open(path: Option<&Path>) -> Result<Self, String> {
+            let mut schema = Schema::builder();
+            let fields = Fields {
+                key: schema.add_text_field("key", STRING),
+                obj: schema.add_i64_field("obj", INDEXED | STORED),
+                prop: schema.add_text_field("prop", STRING | STORED),
+                text: schema.add_text_field("text", TEXT),
+            };
+            let schema = schema.build();
+
+            let index = match path {
+                Some(path) => {
+                    std::fs::create_dir_all(path).map_err(|e| e.to_string())?;
+                    let directory = MmapDirectory::open(path).map_err(|e| e.to_string())?;
+                    Index::open_or_create(directory, schema).map_err(|e| e.to_string())?
+                }
+                None => Index::create_in_ram(schema),
+            };
+            let reader: IndexReader = index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::OnCommitWithDelay)
+                .try_into()
+                .map_err(|e: tantivy::TantivyError| e.to_string())?;
+            let mut writer: IndexWriter = index
+                .writer(WRITER_MEMORY_BYTES)
+                .map_err(|e| e.to_string())?;
+
+            let (updates, receive) = crossbeam_channel::unbounded::<Vec<TextIndexUpdate>>();
+            std::thread::Builder::new()
+                .name("moor-text-index".to_string())
+                .spawn(move || {
+                    // Everything queued since the last pass goes into one tantivy commit.
+                    while let Ok(batch) = receive.recv() {
+                        let mut count = 0;
+                        for update in std::iter::once(batch).chain(receive.try_iter()).flatten() {
+                            apply(&mut writer, fields, update);
+                            count += 1;
+                        }
+                        if let Err(e) = writer.commit() {
+                            error!(?e, "Unable to commit text index updates");
+                            continue;
+                        }
+                        debug!(count, "Applied text index updates");
+                    }
+                })
+                .map_err(|e| e.to_string())?;
+
+            Ok(Self {
+                index,
+                reader,
+                fields,
+                updates,
+            })
+        }
+
+        pub(super) fn update(&self, updates: Vec<TextIndexUpdate>) {
+            if self.updates.send(updates).is_err() {
+                error!("Text index thread has gone away");
+            }
+        }
+
+        pub(super) fn search(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<(Obj, Symbol)>, String> {
+            let searcher = self.reader.searcher();
+            let parser = QueryParser::for_index(&self.index, vec![self.fields.text]);
+            let (query, _) = parser.parse_query_lenient(query);
+            let top = searcher
+                .search(&query, &TopDocs::with_limit(limit))
+                .map_err(|e| e.to_string())?;
+            let mut hits = Vec::with_capacity(top.len());
+            for (_score, address) in top {
+                let doc: TantivyDocument = searcher.doc(address).map_err(|e| e.to_string())?;
+                let obj = doc.get_first(self.fields.obj).and_then(|v| v.as_i64());
+                let prop = doc.get_first(self.fields.prop).and_then(|v| v.as_str());
+                if let (Some(obj), Some(prop)) = (obj, prop) {
+                    hits.push((Obj::mk_id(obj as i32), Symbol::mk(prop)));
+                }
+            }
+            Ok(hits)
+        }
+    }
+
+    fn apply(writer: &mut IndexWriter, fields: Fields, update: TextIndexUpdate) {
+        match update {
+            TextIndexUpdate::Set(obj, prop, text) => {
+                let prop = prop.as_str().to_lowercase();
+                let key = key(&obj, &prop);
+                writer.delete_term(Term::from_field_text(fields.key, &key));
+                let result = writer.add_document(doc!(
+                    fields.key => key,
+                    fields.obj => obj.id().0 as i64,
+                    fields.prop => prop,
+                    fields.text => text,
+                ));
+                if let Err(e) = result {
+                    error!(?e, ?obj, "Unable to index property");
+                }
+            }
+            TextIndexUpdate::Clear(obj, prop) => {
+                let key = key(&obj, &prop.as_str().to_lowercase());
+                writer.delete_term(Term::from_field_text(fields.key, &key));
+            }
+            TextIndexUpdate::Recycle(obj) => {
+                writer.delete_term(Term::from_field_i64(fields.obj, obj.id().0 as i64));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use moor_values::{v_int, v_list, v_str};
+
+    use crate::text_index::indexable_text;
+
+    #[test]
+    fn test_indexable_text() {
+        assert_eq!(indexable_text(&v_str("hello")), Some("hello".to_string()));
+        assert_eq!(
+            indexable_text(&v_list(&[
+                v_str("one"),
+                v_int(2),
+                v_list(&[v_str("three")])
+            ])),
+            Some("one\nthree".to_string())
+        );
+        assert_eq!(indexable_text(&v_int(1)), None);
+        assert_eq!(indexable_text(&v_list(&[])), None);
+    }
+
+    #[cfg(feature = "fts")]
+    #[test]
+    fn test_search() {
+        use moor_values::{Obj, Symbol};
+
+        use crate::text_index::{TextIndex, TextIndexUpdate};
+
+        let index = TextIndex::open(None, &["description".to_string()]).unwrap();
+        assert!(index.indexes(Symbol::mk("Description")));
+        assert!(!index.indexes(Symbol::mk("name")));
+
+        let description = Symbol::mk("description");
+        index.update(vec![
+            TextIndexUpdate::Set(Obj::mk_id(1), description, "a rusty iron key".to_string()),
+            TextIndexUpdate::Set(Obj::mk_id(2), description, "a shiny brass key".to_string()),
+        ]);
+
+        // Updates are applied, and picked up by the reader, asynchronously.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let mut hits = vec![];
+        while hits.len() < 2 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            hits = index.search("key", 10).unwrap();
+        }
+        assert_eq!(hits.len(), 2);
+        assert_eq!(
+            index.search("brass", 10).unwrap(),
+            vec![(Obj::mk_id(2), description)]
+        );
+    }
+}
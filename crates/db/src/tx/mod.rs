@@ -15,8 +15,12 @@ mod transactional_cache;
 mod tx_table;
 
 pub use transactional_cache::TransactionalCache;
+pub(crate) use tx_table::OpType;
 pub use tx_table::{TransactionalTable, WorkingSet};
 
+use moor_values::model::CacheStats;
+use moor_values::AsByteBuffer;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
 pub struct Timestamp(pub u64);
 
@@ -50,6 +54,24 @@ pub trait Provider<Domain, Codomain> {
     fn scan<F>(&self, predicate: &F) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
     where
         F: Fn(&Domain, &Codomain) -> bool;
+
+    /// All the entries whose encoded keys start with `prefix`, in order of encoded key.
+    /// Providers over ordered storage should override this to avoid a scan of the whole table.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
+    where
+        Domain: AsByteBuffer,
+    {
+        let mut results = self.scan(&|domain, _| has_prefix(domain, prefix))?;
+        results.sort_by_cached_key(|(_, domain, _, _)| domain.as_bytes().ok());
+        Ok(results)
+    }
+}
+
+/// Whether the encoding of `domain` starts with `prefix`.
+pub(crate) fn has_prefix<Domain: AsByteBuffer>(domain: &Domain, prefix: &[u8]) -> bool {
+    domain
+        .with_byte_buffer(|bytes| bytes.starts_with(prefix))
+        .unwrap_or(false)
 }
 
 /// A `SizedCache` is a cache that has a maximum size in bytes, and will attempt to evict entries
@@ -57,6 +79,10 @@ pub trait Provider<Domain, Codomain> {
 pub trait SizedCache {
     fn process_cache_evictions(&self) -> (usize, usize);
     fn cache_usage_bytes(&self) -> usize;
+    fn cache_stats(&self) -> CacheStats;
+    /// Drop every entry, returning how many there were.
+    fn flush_cache(&self) -> usize;
+    fn set_eviction_threshold(&self, threshold_bytes: usize);
 }
 
 /// Represents a "canonical" source for some domain/codomain pair, to be supplied to a
@@ -66,4 +92,14 @@ pub trait Canonical<Domain, Codomain> {
     fn scan<F>(&self, f: &F) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
     where
         F: Fn(&Domain, &Codomain) -> bool;
+
+    /// All the entries whose encoded keys start with `prefix`, in order of encoded key.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
+    where
+        Domain: AsByteBuffer,
+    {
+        let mut results = self.scan(&|domain, _| has_prefix(domain, prefix))?;
+        results.sort_by_cached_key(|(_, domain, _, _)| domain.as_bytes().ok());
+        Ok(results)
+    }
 }
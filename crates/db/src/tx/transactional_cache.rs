@@ -16,6 +16,8 @@
 use crate::tx::tx_table::{OpType, TransactionalTable, WorkingSet};
 use crate::tx::{Canonical, Error, Provider, SizedCache, Timestamp, Tx};
 use indexmap::IndexMap;
+use moor_values::model::CacheStats;
+use moor_values::AsByteBuffer;
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::sync::{Arc, Mutex, MutexGuard};
@@ -62,6 +64,9 @@ where
                 evict_q: vec![],
                 used_bytes: 0,
                 threshold_bytes,
+                hits: 0,
+                misses: 0,
+                evictions: 0,
             }),
             source: provider,
         }
@@ -86,6 +91,12 @@ where
 
     /// Threshold for eviction.
     threshold_bytes: usize,
+
+    /// Lookups answered from the cache, and those which had to go to the backing store.
+    hits: usize,
+    misses: usize,
+    /// Entries evicted, over the life of the cache.
+    evictions: usize,
 }
 
 /// Holds a lock on the cache while a transaction commit is in progress.
@@ -209,6 +220,35 @@ where
         let mut inner = self.lock();
         inner.0.process_evictions()
     }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let inner = self.index.lock().unwrap();
+        CacheStats {
+            entries: inner.index.len(),
+            used_bytes: inner.used_bytes,
+            threshold_bytes: inner.threshold_bytes,
+            hits: inner.hits,
+            misses: inner.misses,
+            evictions: inner.evictions,
+        }
+    }
+
+    /// Drop everything held in the cache, returning how many entries there were. It's refilled
+    /// from the backing store as it's used.
+    pub fn flush_cache(&self) -> usize {
+        let mut inner = self.index.lock().unwrap();
+        let entries = inner.index.len();
+        inner.index.clear();
+        inner.evict_q.clear();
+        inner.used_bytes = 0;
+        entries
+    }
+
+    pub fn set_eviction_threshold(&self, threshold_bytes: usize) {
+        let mut inner = self.index.lock().unwrap();
+        inner.threshold_bytes = threshold_bytes;
+        inner.select_victims();
+    }
 }
 
 impl<Domain, Codomain> Inner<Domain, Codomain>
@@ -306,6 +346,7 @@ where
         for v in victims {
             self.index.swap_remove(&v);
         }
+        self.evictions += num_evicted;
         (num_evicted, before_eviction - self.used_bytes)
     }
 }
@@ -320,11 +361,14 @@ where
     fn get(&self, domain: &Domain) -> Result<Option<(Timestamp, Codomain)>, Error> {
         let mut inner = self.index.lock().unwrap();
         if let Some(entry) = inner.index_lookup(domain) {
-            match &entry.datum {
-                Datum::Value(codomain) => Ok(Some((entry.ts, codomain.clone()))),
+            let (ts, datum) = (entry.ts, entry.datum.clone());
+            inner.hits += 1;
+            match datum {
+                Datum::Value(codomain) => Ok(Some((ts, codomain))),
                 Datum::Tombstone => Ok(None),
             }
         } else {
+            inner.misses += 1;
             // Pull from backing store.
             if let Some((ts, codomain, bytes)) = self.source.get(domain)? {
                 inner.insert_entry(ts, domain.clone(), codomain.clone(), bytes);
@@ -347,6 +391,19 @@ where
 
         Ok(results)
     }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Timestamp, Domain, Codomain, usize)>, Error>
+    where
+        Domain: AsByteBuffer,
+    {
+        let results = self.source.scan_prefix(prefix)?;
+        let mut index = self.index.lock().unwrap();
+        for (ts, domain, codomain, size) in &results {
+            index.insert_entry(*ts, domain.clone(), codomain.clone(), *size);
+        }
+
+        Ok(results)
+    }
 }
 
 impl<Domain, Codomain, Source> SizedCache for TransactionalCache<Domain, Codomain, Source>
@@ -362,6 +419,18 @@ where
     fn cache_usage_bytes(&self) -> usize {
         self.cache_usage_bytes()
     }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.cache_stats()
+    }
+
+    fn flush_cache(&self) -> usize {
+        self.flush_cache()
+    }
+
+    fn set_eviction_threshold(&self, threshold_bytes: usize) {
+        self.set_eviction_threshold(threshold_bytes)
+    }
 }
 
 #[cfg(test)]
@@ -11,8 +11,9 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::tx::{Canonical, Error, Timestamp, Tx};
+use crate::tx::{has_prefix, Canonical, Error, Timestamp, Tx};
 use indexmap::IndexMap;
+use moor_values::AsByteBuffer;
 use std::cell::RefCell;
 use std::hash::Hash;
 use std::sync::Arc;
@@ -363,43 +364,65 @@ where
     {
         // Scan in the upstream first, and then merge the set with local changes.
         let upstream = self.backing_source.scan(predicate)?;
+        self.merge_upstream(upstream);
+        Ok(self.scan_local(predicate))
+    }
 
-        let mut index = self.index.borrow_mut();
+    /// All the entries whose encoded keys start with `prefix`, in order of encoded key, as seen
+    /// by this transaction: what was committed as of its start, with its own changes on top.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Domain, Codomain)>, Error>
+    where
+        Domain: AsByteBuffer,
+    {
+        let upstream = self.backing_source.scan_prefix(prefix)?;
+        self.merge_upstream(upstream);
+        let mut results = self.scan_local(&|domain, _| has_prefix(domain, prefix));
+        results.sort_by_cached_key(|(domain, _)| domain.as_bytes().ok());
+        Ok(results)
+    }
 
-        // This is basically like going a `get` on each entry, we're filling our cache with
-        // all the upstream common.
+    /// This is basically like doing a `get` on each entry, filling our cache with all the
+    /// upstream entries we don't already have our own version of.
+    fn merge_upstream(&self, upstream: Vec<(Timestamp, Domain, Codomain, usize)>) {
+        let mut index = self.index.borrow_mut();
         for (ts, d, c, _) in upstream {
-            let entry = index.get_mut(&d);
-            match entry {
-                Some(_) => continue,
-                None => {
-                    index.insert(
-                        d.clone(),
-                        Entry::Present(Op {
-                            read_ts: ts,
-                            write_ts: ts,
-                            source: DatumSource::Upstream,
-                            from_type: OpType::Cached,
-                            to_type: OpType::Cached,
-                            value: Some(c),
-                        }),
-                    );
-                }
+            if index.contains_key(&d) {
+                continue;
             }
+            index.insert(
+                d,
+                Entry::Present(Op {
+                    read_ts: ts,
+                    write_ts: ts,
+                    source: DatumSource::Upstream,
+                    from_type: OpType::Cached,
+                    to_type: OpType::Cached,
+                    value: Some(c),
+                }),
+            );
         }
+    }
 
+    fn scan_local<F>(&self, predicate: &F) -> Vec<(Domain, Codomain)>
+    where
+        F: Fn(&Domain, &Codomain) -> bool,
+    {
+        let index = self.index.borrow();
         let mut results = Vec::new();
-
-        // Now scan local
         for (domain, entry) in index.iter() {
-            if let Entry::Present(op) = entry {
-                if predicate(domain, op.value.as_ref().unwrap()) {
-                    results.push((domain.clone(), op.value.as_ref().unwrap().clone()));
+            // Entries deleted in this transaction have no value.
+            if let Entry::Present(Op {
+                value: Some(value), ..
+            }) = entry
+            {
+                if predicate(domain, value) {
+                    results.push((domain.clone(), value.clone()));
                 }
             }
         }
-        Ok(results)
+        results
     }
+
     pub fn working_set(self) -> WorkingSet<Domain, Codomain> {
         let index = self.index.take();
         index
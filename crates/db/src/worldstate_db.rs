@@ -11,29 +11,39 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::config::DatabaseConfig;
+use crate::blobs::BlobStore;
+use crate::changes::{CommittedChanges, PendingChanges, Subscribers};
+use crate::config::{DatabaseConfig, ObjectIdAllocation, TableConfig};
+use crate::content::ContentStore;
 use crate::db_transaction::DbTransaction;
 use crate::fjall_provider::FjallProvider;
+use crate::migration;
+use crate::object_generations::{touched_objects, ObjectGenerations};
+use crate::program_cache::ProgramCache;
+use crate::prop_resolution_cache::PropResolutionCache;
+use crate::text_index::TextIndex;
 use crate::tx::{SizedCache, Timestamp, TransactionalCache, Tx, WorkingSet};
 use crate::{BytesHolder, ObjAndUUIDHolder, StringHolder};
 use crossbeam_channel::Sender;
 use fjall::{Config, PartitionCreateOptions, PartitionHandle, PersistMode};
-use moor_values::model::{CommitResult, ObjFlag, ObjSet, PropDefs, PropPerms, VerbDefs};
+use moor_values::model::{
+    CacheStats, CommitResult, ObjFlag, ObjSet, PropDefs, PropPerms, VerbDefs,
+};
 use moor_values::util::BitEnum;
-use moor_values::{Obj, Var};
-use std::ops::Deref;
+use moor_values::{AsByteBuffer, Obj, Var};
+use std::hash::Hash;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64};
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
-use tracing::warn;
+use tracing::{info, warn};
 
 type GC<Domain, Codomain> =
     Arc<TransactionalCache<Domain, Codomain, FjallProvider<Domain, Codomain>>>;
+type DynCache = Arc<dyn SizedCache + Send + Sync>;
 
 pub(crate) struct WorkingSets {
-    #[allow(dead_code)]
     pub(crate) tx: Tx,
     pub(crate) object_location: WorkingSet<Obj, Obj>,
     pub(crate) object_contents: WorkingSet<Obj, ObjSet>,
@@ -44,9 +54,13 @@ pub(crate) struct WorkingSets {
     pub(crate) object_name: WorkingSet<Obj, StringHolder>,
     pub(crate) object_verbdefs: WorkingSet<Obj, VerbDefs>,
     pub(crate) object_verbs: WorkingSet<ObjAndUUIDHolder, BytesHolder>,
+    pub(crate) object_verb_options: WorkingSet<ObjAndUUIDHolder, Var>,
     pub(crate) object_propdefs: WorkingSet<Obj, PropDefs>,
     pub(crate) object_propvalues: WorkingSet<ObjAndUUIDHolder, Var>,
     pub(crate) object_propflags: WorkingSet<ObjAndUUIDHolder, PropPerms>,
+    pub(crate) server_registry: WorkingSet<StringHolder, Var>,
+    /// How many changes to verbs, property definitions or inheritance the transaction made.
+    pub(crate) dispatch_changes: u64,
 }
 
 pub struct WorldStateDB {
@@ -64,16 +78,211 @@ pub struct WorldStateDB {
 
     object_verbdefs: GC<Obj, VerbDefs>,
     object_verbs: GC<ObjAndUUIDHolder, BytesHolder>,
+    /// The language features each verb was last compiled with, where they were recorded.
+    object_verb_options: GC<ObjAndUUIDHolder, Var>,
     object_propdefs: GC<Obj, PropDefs>,
     object_propvalues: GC<ObjAndUUIDHolder, Var>,
     object_propflags: GC<ObjAndUUIDHolder, PropPerms>,
 
+    /// Server-wide configuration, by key, kept apart from the objects (and so out of dumps).
+    server_registry: GC<StringHolder, Var>,
+
+    partitions: Partitions,
+    /// Whether this is a snapshot of the database, which can't be committed to.
+    read_only: bool,
+    /// The fjall instant as of the last commit, for taking consistent snapshots.
+    committed_instant: AtomicU64,
+
     sequences: [Arc<AtomicI64>; 16],
     sequences_partition: PartitionHandle,
+    object_id_allocation: ObjectIdAllocation,
+
+    program_cache: ProgramCache,
+    text_index: Option<Arc<TextIndex>>,
 
     kill_switch: Arc<AtomicBool>,
     commit_channel: Sender<(WorkingSets, oneshot::Sender<CommitResult>)>,
     usage_send: crossbeam_channel::Sender<oneshot::Sender<usize>>,
+    config: DatabaseConfig,
+
+    /// Subscribers to the stream of committed changes.
+    subscribers: Subscribers,
+
+    /// Advanced by each commit which changes verbs, property definitions or inheritance, by the
+    /// number of changes it made, so that lookups cached under an earlier generation are known to
+    /// be stale.
+    dispatch_generation: Arc<AtomicU64>,
+
+    /// Where property names resolve to, as of the current dispatch generation.
+    prop_resolution_cache: Arc<PropResolutionCache>,
+
+    /// The generation each object was last changed at.
+    object_generations: ObjectGenerations,
+
+    cache_directory: Arc<CacheDirectory>,
+}
+
+/// The relations' global caches by name, for looking at and tuning them while running.
+pub(crate) struct CacheDirectory {
+    caches: Vec<(&'static str, DynCache)>,
+    /// Where capacities set while running are kept, to be used in place of the configured ones
+    /// when the database is next opened. None for snapshots.
+    capacities: Option<PartitionHandle>,
+    /// Where the relations which offload values offload them to, by relation.
+    blob_stores: Vec<(&'static str, Arc<BlobStore>)>,
+    /// Where deduplicated values are kept.
+    content: Arc<ContentStore>,
+}
+
+impl CacheDirectory {
+    pub(crate) fn stats(&self) -> Vec<(String, CacheStats)> {
+        self.caches
+            .iter()
+            .map(|(name, cache)| (name.to_string(), cache.cache_stats()))
+            .chain(
+                self.blob_stores
+                    .iter()
+                    .map(|(name, blobs)| (format!("{name}_blobs"), blobs.stats())),
+            )
+            .chain([("content".to_string(), self.content.stats())])
+            .collect()
+    }
+
+    /// Empty the cache called `name`, or all of them, returning how many entries were dropped.
+    pub(crate) fn flush(&self, name: Option<&str>) -> Option<usize> {
+        let mut flushed = None;
+        for (cache_name, cache) in &self.caches {
+            if name.is_none() || name == Some(*cache_name) {
+                *flushed.get_or_insert(0) += cache.flush_cache();
+            }
+        }
+        flushed
+    }
+
+    /// Change the eviction threshold of the cache called `name`, and remember it for next time,
+    /// returning whether there's a cache by that name.
+    pub(crate) fn set_capacity(
+        &self,
+        name: &str,
+        threshold_bytes: usize,
+    ) -> Result<bool, fjall::Error> {
+        let Some((name, cache)) = self.caches.iter().find(|(n, _)| *n == name) else {
+            return Ok(false);
+        };
+        if let Some(capacities) = &self.capacities {
+            capacities.insert(name, (threshold_bytes as u64).to_le_bytes())?;
+        }
+        cache.set_eviction_threshold(threshold_bytes);
+        Ok(true)
+    }
+}
+
+/// Handles on the fjall partitions backing each relation.
+#[derive(Clone)]
+struct Partitions {
+    object_location: PartitionHandle,
+    object_contents: PartitionHandle,
+    object_flags: PartitionHandle,
+    object_parent: PartitionHandle,
+    object_children: PartitionHandle,
+    object_owner: PartitionHandle,
+    object_name: PartitionHandle,
+    object_verbdefs: PartitionHandle,
+    object_verbs: PartitionHandle,
+    object_verb_options: PartitionHandle,
+    object_propdefs: PartitionHandle,
+    object_propvalues: PartitionHandle,
+    /// Property values too large to keep in `object_propvalues`.
+    object_propvalues_blobs: PartitionHandle,
+    /// Deduplicated values, by hash, and how many entries refer to each.
+    content: PartitionHandle,
+    content_refs: PartitionHandle,
+    object_propflags: PartitionHandle,
+    server_registry: PartitionHandle,
+    /// Cache capacities set while running, by relation.
+    cache_capacities: PartitionHandle,
+    object_generations: PartitionHandle,
+}
+
+impl Partitions {
+    fn open(keyspace: &fjall::Keyspace, config: &DatabaseConfig) -> Self {
+        let open = |name: &str, table: &TableConfig| {
+            keyspace
+                .open_partition(name, table.partition_options())
+                .unwrap()
+        };
+        Self {
+            object_location: open("object_location", &config.object_location),
+            object_contents: open("object_contents", &config.object_contents),
+            object_flags: open("object_flags", &config.object_flags),
+            object_parent: open("object_parent", &config.object_parent),
+            object_children: open("object_children", &config.object_children),
+            object_owner: open("object_owner", &config.object_owner),
+            object_name: open("object_name", &config.object_name),
+            object_verbdefs: open("object_verbdefs", &config.object_verbdefs),
+            object_verbs: open("object_verbs", &config.object_verbs),
+            object_verb_options: open("object_verb_options", &config.object_verb_options),
+            object_propdefs: open("object_propdefs", &config.object_propdefs),
+            object_propvalues: open("object_propvalues", &config.object_propvalues),
+            object_propvalues_blobs: keyspace
+                .open_partition("object_propvalues_blobs", PartitionCreateOptions::default())
+                .unwrap(),
+            content: keyspace
+                .open_partition("content", PartitionCreateOptions::default())
+                .unwrap(),
+            content_refs: keyspace
+                .open_partition("content_refs", PartitionCreateOptions::default())
+                .unwrap(),
+            object_propflags: open("object_propflags", &config.object_propflags),
+            server_registry: open("server_registry", &config.server_registry),
+            cache_capacities: keyspace
+                .open_partition("cache_capacities", PartitionCreateOptions::default())
+                .unwrap(),
+            object_generations: keyspace
+                .open_partition("object_generations", PartitionCreateOptions::default())
+                .unwrap(),
+        }
+    }
+}
+
+/// The global cache for a relation, over its partition, or a snapshot of it as of `snapshot_at`.
+fn relation<Domain, Codomain>(
+    partition: &PartitionHandle,
+    snapshot_at: Option<fjall::Instant>,
+    table: &TableConfig,
+    default_threshold: usize,
+) -> GC<Domain, Codomain>
+where
+    Domain: Clone + Eq + PartialEq + Hash + AsByteBuffer,
+    Codomain: Clone + Eq + PartialEq + AsByteBuffer,
+{
+    offloading_relation(partition, snapshot_at, table, default_threshold, None)
+}
+
+/// As `relation`, but with values too large for the partition offloaded to `blobs`.
+fn offloading_relation<Domain, Codomain>(
+    partition: &PartitionHandle,
+    snapshot_at: Option<fjall::Instant>,
+    table: &TableConfig,
+    default_threshold: usize,
+    blobs: Option<Arc<BlobStore>>,
+) -> GC<Domain, Codomain>
+where
+    Domain: Clone + Eq + PartialEq + Hash + AsByteBuffer,
+    Codomain: Clone + Eq + PartialEq + AsByteBuffer,
+{
+    let provider = match snapshot_at {
+        Some(instant) => FjallProvider::at_instant(partition.clone(), instant),
+        None => FjallProvider::new(partition.clone()),
+    };
+    let provider = match blobs {
+        Some(blobs) => provider.with_blobs(blobs),
+        None => provider,
+    };
+    Arc::new(TransactionalCache::new(
+        Arc::new(provider),
+        table.cache_eviction_threshold.unwrap_or(default_threshold),
+    ))
 }
 
 impl WorldStateDB {
@@ -83,15 +292,50 @@ impl WorldStateDB {
         } else {
             None
         };
+        let in_memory = path.is_none();
         // Open the fjall db and then get all the partition handles.
         let path = path.unwrap_or_else(|| tmpdir.as_ref().unwrap().path());
+        // Bring older database directories up to the current layout before touching them.
+        let pending = migration::pending_migrations(path)
+            .unwrap_or_else(|e| panic!("Unable to migrate database: {e}"));
+        if !pending.is_empty() {
+            if let Some(backup) = &config.migration_backup_path {
+                info!(?backup, "Backing up database before migrating it");
+                migration::backup(path, backup)
+                    .unwrap_or_else(|e| panic!("Unable to migrate database: {e}"));
+            }
+        }
         let keyspace = Config::new(path).open().unwrap();
+        migration::migrate(path, &keyspace, &pending)
+            .unwrap_or_else(|e| panic!("Unable to migrate database: {e}"));
 
         let sequences_partition = keyspace
             .open_partition("sequences", PartitionCreateOptions::default())
             .unwrap();
 
+        // Restore the sequences (max object, etc.) to their last committed values.
         let sequences = [(); 16].map(|_| Arc::new(AtomicI64::new(-1)));
+        for (i, seq) in sequences.iter().enumerate() {
+            if let Some(value) = sequences_partition.get(i.to_le_bytes()).unwrap() {
+                seq.store(
+                    i64::from_le_bytes(value[0..8].try_into().unwrap()),
+                    std::sync::atomic::Ordering::SeqCst,
+                );
+            }
+        }
+
+        let program_cache_partition = keyspace
+            .open_partition("program_cache", PartitionCreateOptions::default())
+            .unwrap();
+        let program_cache = ProgramCache::new(program_cache_partition);
+
+        let text_index = (!config.text_indexed_properties.is_empty()).then(|| {
+            let index_path = path.join("text_index");
+            let index_path = (!in_memory).then_some(index_path.as_path());
+            let index = TextIndex::open(index_path, &config.text_indexed_properties)
+                .unwrap_or_else(|e| panic!("Unable to open text index: {e}"));
+            Arc::new(index)
+        });
 
         let mut fresh = false;
         if !keyspace.partition_exists("object_location") {
@@ -105,166 +349,228 @@ impl WorldStateDB {
             .map(|b| u64::from_le_bytes(b[0..8].try_into().unwrap()))
             .unwrap_or(1);
 
-        let object_location = keyspace
-            .open_partition(
-                "object_location",
-                config.object_location.partition_options(),
-            )
-            .unwrap();
-        let object_contents = keyspace
-            .open_partition(
-                "object_contents",
-                config.object_contents.partition_options(),
-            )
-            .unwrap();
-        let object_flags = keyspace
-            .open_partition("object_flags", config.object_flags.partition_options())
-            .unwrap();
-        let object_parent = keyspace
-            .open_partition("object_parent", config.object_parent.partition_options())
-            .unwrap();
-        let object_children = keyspace
-            .open_partition(
-                "object_children",
-                config.object_children.partition_options(),
-            )
-            .unwrap();
-        let object_owner = keyspace
-            .open_partition("object_owner", config.object_owner.partition_options())
-            .unwrap();
-        let object_name = keyspace
-            .open_partition("object_name", config.object_name.partition_options())
-            .unwrap();
-        let object_verbdefs = keyspace
-            .open_partition(
-                "object_verbdefs",
-                config.object_verbdefs.partition_options(),
-            )
-            .unwrap();
-        let object_verbs = keyspace
-            .open_partition("object_verbs", config.object_verbs.partition_options())
-            .unwrap();
-        let object_propdefs = keyspace
-            .open_partition(
-                "object_propdefs",
-                config.object_propdefs.partition_options(),
-            )
-            .unwrap();
-        let object_propvalues = keyspace
-            .open_partition(
-                "object_propvalues",
-                config.object_propvalues.partition_options(),
-            )
-            .unwrap();
-        let object_propflags = keyspace
-            .open_partition(
-                "object_propflags",
-                config.object_propflags.partition_options(),
-            )
-            .unwrap();
+        let partitions = Partitions::open(&keyspace, &config);
+
+        // Capacities set while running take the place of the configured ones.
+        let mut config = config;
+        for entry in partitions.cache_capacities.iter() {
+            let (name, bytes) = entry.unwrap();
+            let (Ok(name), Ok(bytes)) = (std::str::from_utf8(&name), <[u8; 8]>::try_from(&*bytes))
+            else {
+                continue;
+            };
+            if let Some(table) = config.table_mut(name) {
+                table.cache_eviction_threshold = Some(u64::from_le_bytes(bytes) as usize);
+            }
+        }
+        let committed_instant = keyspace.instant();
+        let s = Self::with_partitions(
+            keyspace,
+            partitions,
+            None,
+            committed_instant,
+            sequences,
+            sequences_partition,
+            start_tx_num,
+            program_cache,
+            text_index,
+            config,
+        );
+        (s, fresh)
+    }
 
-        let object_location = FjallProvider::new(object_location);
-        let object_contents = FjallProvider::new(object_contents);
-        let object_flags = FjallProvider::new(object_flags);
-        let object_parent = FjallProvider::new(object_parent);
-        let object_children = FjallProvider::new(object_children);
-        let object_owner = FjallProvider::new(object_owner);
-        let object_name = FjallProvider::new(object_name);
-        let object_verbdefs = FjallProvider::new(object_verbdefs);
-        let object_verbs = FjallProvider::new(object_verbs);
-        let object_propdefs = FjallProvider::new(object_propdefs);
-        let object_propvalues = FjallProvider::new(object_propvalues);
-        let object_propflags = FjallProvider::new(object_propflags);
-
-        let default_cache_eviction_threshold = config.default_eviction_threshold;
-        let object_location = Arc::new(TransactionalCache::new(
-            Arc::new(object_location),
-            config
-                .object_location
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_contents = Arc::new(TransactionalCache::new(
-            Arc::new(object_contents),
-            config
-                .object_contents
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_flags = Arc::new(TransactionalCache::new(
-            Arc::new(object_flags),
-            config
-                .object_flags
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_parent = Arc::new(TransactionalCache::new(
-            Arc::new(object_parent),
-            config
-                .object_parent
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_children = Arc::new(TransactionalCache::new(
-            Arc::new(object_children),
-            config
-                .object_children
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_owner = Arc::new(TransactionalCache::new(
-            Arc::new(object_owner),
-            config
-                .object_owner
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_name = Arc::new(TransactionalCache::new(
-            Arc::new(object_name),
-            config
-                .object_name
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_verbdefs = Arc::new(TransactionalCache::new(
-            Arc::new(object_verbdefs),
-            config
-                .object_verbdefs
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_verbs = Arc::new(TransactionalCache::new(
-            Arc::new(object_verbs),
-            config
-                .object_verbs
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_propdefs = Arc::new(TransactionalCache::new(
-            Arc::new(object_propdefs),
-            config
-                .object_propdefs
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_propvalues = Arc::new(TransactionalCache::new(
-            Arc::new(object_propvalues),
-            config
-                .object_propvalues
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
-        let object_propflags = Arc::new(TransactionalCache::new(
-            Arc::new(object_propflags),
-            config
-                .object_propflags
-                .cache_eviction_threshold
-                .unwrap_or(default_cache_eviction_threshold),
-        ));
+    /// A read-only copy of the database as of its last commit, sharing its storage. Commits made
+    /// afterwards are not seen by it; take another to catch up.
+    pub(crate) fn snapshot(&self) -> Arc<Self> {
+        let instant = self
+            .committed_instant
+            .load(std::sync::atomic::Ordering::SeqCst);
 
+        // The sequences as they were at that commit (they're written out with every commit).
+        let sequences_snapshot = self.sequences_partition.snapshot_at(instant);
+        let sequences = [(); 16].map(|_| Arc::new(AtomicI64::new(-1)));
+        for (i, seq) in sequences.iter().enumerate() {
+            if let Some(value) = sequences_snapshot.get(i.to_le_bytes()).unwrap() {
+                seq.store(
+                    i64::from_le_bytes(value[0..8].try_into().unwrap()),
+                    std::sync::atomic::Ordering::SeqCst,
+                );
+            }
+        }
+        let start_tx_num = sequences[15]
+            .load(std::sync::atomic::Ordering::SeqCst)
+            .max(1) as u64;
+
+        Self::with_partitions(
+            self.keyspace.clone(),
+            self.partitions.clone(),
+            Some(instant),
+            instant,
+            sequences,
+            self.sequences_partition.clone(),
+            start_tx_num,
+            self.program_cache.clone(),
+            self.text_index.clone(),
+            self.config.clone(),
+        )
+    }
+
+    /// Set up the caches over `partitions` (or over a read-only snapshot of them, as of
+    /// `snapshot_at`), and start the processing thread.
+    #[allow(clippy::too_many_arguments)]
+    fn with_partitions(
+        keyspace: fjall::Keyspace,
+        partitions: Partitions,
+        snapshot_at: Option<fjall::Instant>,
+        committed_instant: fjall::Instant,
+        sequences: [Arc<AtomicI64>; 16],
+        sequences_partition: PartitionHandle,
+        start_tx_num: u64,
+        program_cache: ProgramCache,
+        text_index: Option<Arc<TextIndex>>,
+        config: DatabaseConfig,
+    ) -> Arc<Self> {
+        let threshold = config.default_eviction_threshold;
         let (commit_channel, commit_receiver) = crossbeam_channel::unbounded();
         let (usage_send, usage_recv) = crossbeam_channel::unbounded();
         let kill_switch = Arc::new(AtomicBool::new(false));
+        let content = Arc::new(ContentStore::new(
+            partitions.content.clone(),
+            partitions.content_refs.clone(),
+            snapshot_at,
+        ));
+        let property_blobs = Arc::new(BlobStore::new(
+            Some(partitions.object_propvalues_blobs.clone()),
+            content.clone(),
+            snapshot_at,
+            config.property_blob_threshold,
+            config.dedup_content,
+        ));
+        // Programs are only ever offloaded to be deduplicated, which is worth it for all of them.
+        let verb_blobs = Arc::new(BlobStore::new(
+            None,
+            content.clone(),
+            snapshot_at,
+            config.dedup_content.then_some(0),
+            true,
+        ));
+        let object_location: GC<Obj, Obj> = relation(
+            &partitions.object_location,
+            snapshot_at,
+            &config.object_location,
+            threshold,
+        );
+        let object_contents: GC<Obj, ObjSet> = relation(
+            &partitions.object_contents,
+            snapshot_at,
+            &config.object_contents,
+            threshold,
+        );
+        let object_flags: GC<Obj, BitEnum<ObjFlag>> = relation(
+            &partitions.object_flags,
+            snapshot_at,
+            &config.object_flags,
+            threshold,
+        );
+        let object_parent: GC<Obj, Obj> = relation(
+            &partitions.object_parent,
+            snapshot_at,
+            &config.object_parent,
+            threshold,
+        );
+        let object_children: GC<Obj, ObjSet> = relation(
+            &partitions.object_children,
+            snapshot_at,
+            &config.object_children,
+            threshold,
+        );
+        let object_owner: GC<Obj, Obj> = relation(
+            &partitions.object_owner,
+            snapshot_at,
+            &config.object_owner,
+            threshold,
+        );
+        let object_name: GC<Obj, StringHolder> = relation(
+            &partitions.object_name,
+            snapshot_at,
+            &config.object_name,
+            threshold,
+        );
+        let object_verbdefs: GC<Obj, VerbDefs> = relation(
+            &partitions.object_verbdefs,
+            snapshot_at,
+            &config.object_verbdefs,
+            threshold,
+        );
+        let object_verbs: GC<ObjAndUUIDHolder, BytesHolder> = offloading_relation(
+            &partitions.object_verbs,
+            snapshot_at,
+            &config.object_verbs,
+            threshold,
+            Some(verb_blobs.clone()),
+        );
+        let object_verb_options: GC<ObjAndUUIDHolder, Var> = relation(
+            &partitions.object_verb_options,
+            snapshot_at,
+            &config.object_verb_options,
+            threshold,
+        );
+        let object_propdefs: GC<Obj, PropDefs> = relation(
+            &partitions.object_propdefs,
+            snapshot_at,
+            &config.object_propdefs,
+            threshold,
+        );
+        let object_propvalues: GC<ObjAndUUIDHolder, Var> = offloading_relation(
+            &partitions.object_propvalues,
+            snapshot_at,
+            &config.object_propvalues,
+            threshold,
+            Some(property_blobs.clone()),
+        );
+        let object_propflags: GC<ObjAndUUIDHolder, PropPerms> = relation(
+            &partitions.object_propflags,
+            snapshot_at,
+            &config.object_propflags,
+            threshold,
+        );
+        let server_registry: GC<StringHolder, Var> = relation(
+            &partitions.server_registry,
+            snapshot_at,
+            &config.server_registry,
+            threshold,
+        );
+        let cache_directory = Arc::new(CacheDirectory {
+            caches: vec![
+                ("object_location", object_location.clone() as DynCache),
+                ("object_contents", object_contents.clone() as DynCache),
+                ("object_flags", object_flags.clone() as DynCache),
+                ("object_parent", object_parent.clone() as DynCache),
+                ("object_children", object_children.clone() as DynCache),
+                ("object_owner", object_owner.clone() as DynCache),
+                ("object_name", object_name.clone() as DynCache),
+                ("object_verbdefs", object_verbdefs.clone() as DynCache),
+                ("object_verbs", object_verbs.clone() as DynCache),
+                (
+                    "object_verb_options",
+                    object_verb_options.clone() as DynCache,
+                ),
+                ("object_propdefs", object_propdefs.clone() as DynCache),
+                ("object_propvalues", object_propvalues.clone() as DynCache),
+                ("object_propflags", object_propflags.clone() as DynCache),
+                ("server_registry", server_registry.clone() as DynCache),
+            ],
+            capacities: snapshot_at
+                .is_none()
+                .then(|| partitions.cache_capacities.clone()),
+            blob_stores: vec![
+                ("object_verbs", verb_blobs),
+                ("object_propvalues", property_blobs),
+            ],
+            content,
+        });
+        let object_generations =
+            ObjectGenerations::new(partitions.object_generations.clone(), snapshot_at);
         let s = Arc::new(Self {
             monotonic: AtomicU64::new(start_tx_num),
             object_location,
@@ -276,24 +582,43 @@ impl WorldStateDB {
             object_name,
             object_verbdefs,
             object_verbs,
+            object_verb_options,
             object_propdefs,
             object_propvalues,
             object_propflags,
+            server_registry,
+            partitions,
+            read_only: snapshot_at.is_some(),
+            committed_instant: AtomicU64::new(committed_instant),
             sequences,
             sequences_partition,
+            object_id_allocation: config.object_id_allocation.clone(),
+            program_cache,
+            text_index,
             commit_channel,
             usage_send,
             kill_switch: kill_switch.clone(),
             keyspace,
+            config: config.clone(),
+            subscribers: Subscribers::default(),
+            dispatch_generation: Arc::new(AtomicU64::new(0)),
+            prop_resolution_cache: Arc::new(PropResolutionCache::default()),
+            object_generations,
+            cache_directory,
         });
 
         s.clone()
             .start_processing_thread(commit_receiver, usage_recv, kill_switch, config);
 
-        (s, fresh)
+        s
     }
 
     pub(crate) fn start_transaction(&self) -> DbTransaction {
+        // Taken before the transaction's timestamp, so that anything it reads is at least as new
+        // as this generation.
+        let started_generation = self
+            .dispatch_generation
+            .load(std::sync::atomic::Ordering::SeqCst);
         let tx = Tx {
             ts: Timestamp(
                 self.monotonic
@@ -314,28 +639,47 @@ impl WorldStateDB {
             object_name: self.object_name.clone().start(&tx),
             object_verbdefs: self.object_verbdefs.clone().start(&tx),
             object_verbs: self.object_verbs.clone().start(&tx),
+            object_verb_options: self.object_verb_options.clone().start(&tx),
             object_propdefs: self.object_propdefs.clone().start(&tx),
             object_propvalues: self.object_propvalues.clone().start(&tx),
             object_propflags: self.object_propflags.clone().start(&tx),
+            server_registry: self.server_registry.clone().start(&tx),
             sequences: self.sequences.clone(),
+            object_id_allocation: self.object_id_allocation.clone(),
+            program_cache: self.program_cache.clone(),
+            read_only: self.read_only,
+            dispatch_generation: self.dispatch_generation.clone(),
+            dispatch_changes: 0,
+            started_generation,
+            prop_resolution_cache: self.prop_resolution_cache.clone(),
+            object_generations: self.object_generations.clone(),
+            cache_directory: self.cache_directory.clone(),
         }
     }
 
+    pub fn program_cache(&self) -> ProgramCache {
+        self.program_cache.clone()
+    }
+
+    pub(crate) fn text_index(&self) -> Option<Arc<TextIndex>> {
+        self.text_index.clone()
+    }
+
     fn caches(&self) -> Vec<&dyn SizedCache> {
-        vec![
-            self.object_location.deref(),
-            self.object_contents.deref(),
-            self.object_flags.deref(),
-            self.object_parent.deref(),
-            self.object_children.deref(),
-            self.object_owner.deref(),
-            self.object_name.deref(),
-            self.object_verbdefs.deref(),
-            self.object_verbs.deref(),
-            self.object_propdefs.deref(),
-            self.object_propvalues.deref(),
-            self.object_propflags.deref(),
-        ]
+        self.cache_directory
+            .caches
+            .iter()
+            .map(|(_, cache)| cache.as_ref() as &dyn SizedCache)
+            .collect()
+    }
+
+    pub(crate) fn cache_directory(&self) -> Arc<CacheDirectory> {
+        self.cache_directory.clone()
+    }
+
+    /// Subscribe to the changes made by each transaction which commits from now on.
+    pub(crate) fn subscribe_changes(&self) -> crossbeam_channel::Receiver<CommittedChanges> {
+        self.subscribers.subscribe()
     }
 
     pub fn usage_bytes(&self) -> usize {
@@ -369,11 +713,19 @@ impl WorldStateDB {
         thread_builder
             .spawn(move || {
                 let mut last_eviction_check = std::time::Instant::now();
+                let mut unpublished: Option<PendingChanges> = None;
                 loop {
                     if kill_switch.load(std::sync::atomic::Ordering::SeqCst) {
                         break;
                     }
 
+                    // Publish what the last commit changed, now that the relations are unlocked
+                    // and nothing else can have committed since.
+                    if let Some(pending) = unpublished.take() {
+                        let tx = this.start_transaction();
+                        this.subscribers.publish(pending.resolve(&tx));
+                    }
+
                     if let Ok(msg) = usage_recv.try_recv() {
                         msg.send(this.usage_bytes())
                             .map_err(|e| warn!("{}", e))
@@ -420,9 +772,11 @@ impl WorldStateDB {
                     let object_name = this.object_name.lock();
                     let object_verbdefs = this.object_verbdefs.lock();
                     let object_verbs = this.object_verbs.lock();
+                    let object_verb_options = this.object_verb_options.lock();
                     let object_propdefs = this.object_propdefs.lock();
                     let object_propvalues = this.object_propvalues.lock();
                     let object_propflags = this.object_propflags.lock();
+                    let server_registry = this.server_registry.lock();
 
                     let Ok(ol_lock) = this.object_flags.check(object_flags, &ws.object_flags)
                     else {
@@ -487,6 +841,14 @@ impl WorldStateDB {
                         continue;
                     };
 
+                    let Ok(ovo_lock) = this
+                        .object_verb_options
+                        .check(object_verb_options, &ws.object_verb_options)
+                    else {
+                        reply.send(CommitResult::ConflictRetry).unwrap();
+                        continue;
+                    };
+
                     let Ok(opd_lock) = this
                         .object_propdefs
                         .check(object_propdefs, &ws.object_propdefs)
@@ -510,7 +872,20 @@ impl WorldStateDB {
                         reply.send(CommitResult::ConflictRetry).unwrap();
                         continue;
                     };
-                    //
+
+                    let Ok(sr_lock) = this
+                        .server_registry
+                        .check(server_registry, &ws.server_registry)
+                    else {
+                        reply.send(CommitResult::ConflictRetry).unwrap();
+                        continue;
+                    };
+                    let dispatch_changes = ws.dispatch_changes;
+                    let generation = ws.tx.ts.0;
+                    let touched = touched_objects(&ws);
+                    let pending = (!this.subscribers.is_empty())
+                        .then(|| PendingChanges::from_working_sets(&ws));
+
                     let Ok(_unused) = this.object_flags.apply(ol_lock, ws.object_flags) else {
                         reply.send(CommitResult::ConflictRetry).unwrap();
                         continue;
@@ -560,6 +935,14 @@ impl WorldStateDB {
                         continue;
                     };
 
+                    let Ok(_unused) = this
+                        .object_verb_options
+                        .apply(ovo_lock, ws.object_verb_options)
+                    else {
+                        reply.send(CommitResult::ConflictRetry).unwrap();
+                        continue;
+                    };
+
                     let Ok(_unused) = this.object_propdefs.apply(opd_lock, ws.object_propdefs)
                     else {
                         reply.send(CommitResult::ConflictRetry).unwrap();
@@ -578,6 +961,12 @@ impl WorldStateDB {
                         continue;
                     };
 
+                    let Ok(_unused) = this.server_registry.apply(sr_lock, ws.server_registry)
+                    else {
+                        reply.send(CommitResult::ConflictRetry).unwrap();
+                        continue;
+                    };
+
                     // Now write out the current state of the sequences to the seq partition.
                     // Start by making sure that the monotonic sequence is written out.
                     self.sequences[15].store(
@@ -593,11 +982,22 @@ impl WorldStateDB {
                             .unwrap();
                     }
 
+                    this.object_generations
+                        .advance(&touched, generation)
+                        .expect("Unable to write object generations");
+
                     self.keyspace
                         .persist(PersistMode::SyncAll)
                         .expect("persist failed");
+                    this.committed_instant
+                        .store(this.keyspace.instant(), std::sync::atomic::Ordering::SeqCst);
+                    if dispatch_changes > 0 {
+                        this.dispatch_generation
+                            .fetch_add(dispatch_changes, std::sync::atomic::Ordering::SeqCst);
+                    }
 
                     reply.send(CommitResult::Success).unwrap();
+                    unpublished = pending;
                 }
             })
             .expect("failed to start DB processing thread");
@@ -617,16 +1017,25 @@ mod tests {
         perform_test_descendants, perform_test_location_contents, perform_test_max_object,
         perform_test_object_move_commits, perform_test_parent_children,
         perform_test_recycle_object, perform_test_regression_properties,
-        perform_test_rename_property, perform_test_simple_property,
+        perform_test_rename_property, perform_test_renumber_object, perform_test_simple_property,
         perform_test_transitive_property_resolution,
         perform_test_transitive_property_resolution_clear_property, perform_test_verb_add_update,
         perform_test_verb_resolve, perform_test_verb_resolve_inherited,
         perform_test_verb_resolve_wildcard,
     };
+    use moor_values::model::{
+        BinaryType, CommitResult, HasUuid, ObjAttrs, VerbArgsSpec, VerbAttrs, WorldStateError,
+    };
+    use moor_values::util::BitEnum;
+    use moor_values::{v_int, v_str, AsByteBuffer, Obj, Symbol, NOTHING};
     use std::sync::Arc;
+    use std::time::Duration;
 
-    use crate::config::DatabaseConfig;
+    use crate::changes::Change;
+    use crate::config::{DatabaseConfig, ObjectIdAllocation};
     use crate::db_transaction::DbTransaction;
+    use crate::worldstate_transaction::WorldStateTransaction;
+    use crate::{BytesHolder, ObjAndUUIDHolder};
 
     fn test_db() -> Arc<super::WorldStateDB> {
         super::WorldStateDB::open(None, DatabaseConfig::default()).0
@@ -636,6 +1045,14 @@ mod tests {
         db.start_transaction()
     }
 
+    fn test_db_allocating(object_id_allocation: ObjectIdAllocation) -> Arc<super::WorldStateDB> {
+        let config = DatabaseConfig {
+            object_id_allocation,
+            ..DatabaseConfig::default()
+        };
+        super::WorldStateDB::open(None, config).0
+    }
+
     #[test]
     fn test_create_object() {
         let db = test_db();
@@ -745,4 +1162,561 @@ mod tests {
         let db = test_db();
         perform_test_max_object(|| begin_tx(&db));
     }
+
+    #[test]
+    fn test_renumber_object() {
+        let db = test_db();
+        perform_test_renumber_object(|| begin_tx(&db));
+    }
+
+    #[test]
+    fn test_object_id_range_allocation() {
+        let db = test_db_allocating(ObjectIdAllocation::Range {
+            start: 100,
+            end: 101,
+        });
+        let mut tx = begin_tx(&db);
+
+        // Objects at fixed ids outside the range don't affect allocation within it.
+        tx.create_object(Some(Obj::mk_id(500)), ObjAttrs::default())
+            .unwrap();
+        assert_eq!(
+            tx.create_object(None, ObjAttrs::default()).unwrap(),
+            Obj::mk_id(100)
+        );
+        assert_eq!(
+            tx.create_object(None, ObjAttrs::default()).unwrap(),
+            Obj::mk_id(101)
+        );
+        assert!(tx.create_object(None, ObjAttrs::default()).is_err());
+        assert_eq!(tx.get_max_object().unwrap(), Obj::mk_id(500));
+    }
+
+    #[test]
+    fn test_object_id_interleaved_allocation() {
+        let db = test_db_allocating(ObjectIdAllocation::Interleaved {
+            offset: 1,
+            stride: 4,
+        });
+        let mut tx = begin_tx(&db);
+        assert_eq!(
+            tx.create_object(None, ObjAttrs::default()).unwrap(),
+            Obj::mk_id(1)
+        );
+        assert_eq!(
+            tx.create_object(None, ObjAttrs::default()).unwrap(),
+            Obj::mk_id(5)
+        );
+
+        // Another world's object landing above us pushes us past it, staying in our lane.
+        tx.create_object(Some(Obj::mk_id(6)), ObjAttrs::default())
+            .unwrap();
+        assert_eq!(
+            tx.create_object(None, ObjAttrs::default()).unwrap(),
+            Obj::mk_id(9)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_is_read_only_and_consistent() {
+        let db = test_db();
+        let mut tx = begin_tx(&db);
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        tx.set_object_name(&a, "before".to_string()).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let snapshot = db.snapshot();
+
+        // Later commits to the database aren't seen by the snapshot...
+        let mut tx = begin_tx(&db);
+        tx.set_object_name(&a, "after".to_string()).unwrap();
+        let b = tx.create_object(None, ObjAttrs::default()).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let tx = snapshot.start_transaction();
+        assert_eq!(tx.get_object_name(&a).unwrap(), "before");
+        assert!(!tx.object_valid(&b).unwrap());
+        assert_eq!(tx.get_max_object().unwrap(), a);
+        // ... which can be read from, but not written to.
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        let mut tx = snapshot.start_transaction();
+        tx.set_object_name(&a, "nope".to_string()).unwrap();
+        assert!(tx.commit().is_err());
+
+        // A new snapshot catches up.
+        let tx = db.snapshot().start_transaction();
+        assert_eq!(tx.get_object_name(&a).unwrap(), "after");
+        assert!(tx.object_valid(&b).unwrap());
+    }
+
+    #[test]
+    fn test_change_stream() {
+        let db = test_db();
+        let changes = db.subscribe_changes();
+
+        let mut tx = begin_tx(&db);
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        let uuid = tx
+            .define_property(
+                &a,
+                &a,
+                Symbol::mk("description"),
+                &a,
+                BitEnum::new(),
+                Some(v_int(1)),
+            )
+            .unwrap();
+        tx.add_object_verb(
+            &a,
+            &a,
+            vec![Symbol::mk("look"), Symbol::mk("l")],
+            vec![],
+            BinaryType::LambdaMoo18X,
+            BitEnum::new(),
+            VerbArgsSpec::this_none_this(),
+        )
+        .unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let committed = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            committed.changes,
+            vec![
+                Change::ObjectCreated(a.clone()),
+                Change::PropertyChanged(a.clone(), Symbol::mk("description")),
+                Change::VerbProgrammed(a.clone(), vec![Symbol::mk("look"), Symbol::mk("l")]),
+            ]
+        );
+
+        let mut tx = begin_tx(&db);
+        tx.set_property(&a, uuid, v_int(2)).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        let next = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(next.timestamp > committed.timestamp);
+        assert_eq!(
+            next.changes,
+            vec![Change::PropertyChanged(
+                a.clone(),
+                Symbol::mk("description")
+            )]
+        );
+
+        // Transactions which only read don't show up.
+        let tx = begin_tx(&db);
+        tx.get_object_name(&a).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let mut tx = begin_tx(&db);
+        tx.recycle_object(&a).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        let next = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(next.changes, vec![Change::ObjectRecycled(a)]);
+    }
+
+    #[test]
+    fn test_dispatch_generation() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        let start = tx.dispatch_generation();
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        tx.add_object_verb(
+            &a,
+            &a,
+            vec![Symbol::mk("look")],
+            vec![],
+            BinaryType::LambdaMoo18X,
+            BitEnum::new(),
+            VerbArgsSpec::this_none_this(),
+        )
+        .unwrap();
+        // The transaction sees its own change straight away...
+        assert!(tx.dispatch_generation() > start);
+
+        // ... and others only once it's committed.
+        let other = begin_tx(&db);
+        assert_eq!(other.dispatch_generation(), start);
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert!(other.dispatch_generation() > start);
+        other.rollback().unwrap();
+
+        // Changes to anything else leave it alone.
+        let mut tx = begin_tx(&db);
+        let committed = tx.dispatch_generation();
+        tx.set_object_name(&a, "thing".to_string()).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert_eq!(begin_tx(&db).dispatch_generation(), committed);
+    }
+
+    #[test]
+    fn test_resolve_property_cache() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        let attrs = ObjAttrs::new(NOTHING, a.clone(), NOTHING, BitEnum::new(), "child");
+        let b = tx.create_object(None, attrs.clone()).unwrap();
+        let c = tx.create_object(None, attrs).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        // A miss is remembered...
+        let tx = begin_tx(&db);
+        assert!(matches!(
+            tx.resolve_property(&b, Symbol::mk("colour")),
+            Err(WorldStateError::PropertyNotFound(_, _))
+        ));
+        let generation = tx.dispatch_generation();
+        assert_eq!(
+            db.prop_resolution_cache
+                .lookup(generation, &b, Symbol::mk("colour")),
+            Some(None)
+        );
+        tx.rollback().unwrap();
+
+        // ... but doesn't hide a definition the same transaction makes.
+        let mut tx = begin_tx(&db);
+        tx.define_property(
+            &a,
+            &a,
+            Symbol::mk("colour"),
+            &a,
+            BitEnum::new(),
+            Some(v_str("red")),
+        )
+        .unwrap();
+        let (_, value, _, _) = tx.resolve_property(&b, Symbol::mk("colour")).unwrap();
+        assert_eq!(value, v_str("red"));
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        // Nor, once it's committed, from anyone else.
+        let tx = begin_tx(&db);
+        let (propdef, value, _, _) = tx.resolve_property(&c, Symbol::mk("colour")).unwrap();
+        assert_eq!(propdef.definer(), a);
+        assert_eq!(value, v_str("red"));
+        assert_eq!(
+            db.prop_resolution_cache
+                .lookup(tx.dispatch_generation(), &c, Symbol::mk("colour")),
+            Some(Some(a.clone()))
+        );
+        // And it's answered the same way from the cache.
+        let (propdef, _, _, _) = tx.resolve_property(&c, Symbol::mk("colour")).unwrap();
+        assert_eq!(propdef.definer(), a);
+        tx.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_object_generations() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        let b = tx.create_object(None, ObjAttrs::default()).unwrap();
+        // Not counted until the change is committed.
+        assert_eq!(tx.object_generation(&a).unwrap(), 0);
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let tx = begin_tx(&db);
+        let a_created = tx.object_generation(&a).unwrap();
+        let b_created = tx.object_generation(&b).unwrap();
+        assert!(a_created > 0);
+        assert_eq!(a_created, b_created);
+        tx.rollback().unwrap();
+
+        // Changing one object moves it along, and leaves the other where it was.
+        let mut tx = begin_tx(&db);
+        tx.set_object_name(&a, "thing".to_string()).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        let tx = begin_tx(&db);
+        let a_renamed = tx.object_generation(&a).unwrap();
+        assert!(a_renamed > a_created);
+        assert_eq!(tx.object_generation(&b).unwrap(), b_created);
+        tx.rollback().unwrap();
+
+        // As does moving something into it, which changes its contents.
+        let mut tx = begin_tx(&db);
+        tx.set_object_location(&a, &b).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        let tx = begin_tx(&db);
+        assert!(tx.object_generation(&a).unwrap() > a_renamed);
+        assert!(tx.object_generation(&b).unwrap() > b_created);
+        tx.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_server_registry() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        assert_eq!(tx.registry_get("motd").unwrap(), None);
+        tx.registry_set("motd", v_str("hello")).unwrap();
+        tx.registry_set("flags", v_int(3)).unwrap();
+        assert_eq!(tx.registry_get("motd").unwrap(), Some(v_str("hello")));
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let mut tx = begin_tx(&db);
+        assert_eq!(
+            tx.registry_list().unwrap(),
+            vec![
+                ("flags".to_string(), v_int(3)),
+                ("motd".to_string(), v_str("hello")),
+            ]
+        );
+        assert!(tx.registry_delete("flags").unwrap());
+        assert!(!tx.registry_delete("flags").unwrap());
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let tx = begin_tx(&db);
+        assert_eq!(
+            tx.registry_list().unwrap(),
+            vec![("motd".to_string(), v_str("hello"))]
+        );
+    }
+    #[test]
+    fn test_verb_compile_options() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        let a = tx.create_object(None, ObjAttrs::default()).unwrap();
+        tx.add_object_verb(
+            &a,
+            &a,
+            vec![Symbol::mk("look")],
+            vec![],
+            BinaryType::LambdaMoo18X,
+            BitEnum::new(),
+            VerbArgsSpec::this_none_this(),
+        )
+        .unwrap();
+        let uuid = tx.get_verb_by_name(&a, Symbol::mk("look")).unwrap().uuid();
+        assert_eq!(tx.get_verb_compile_options(&a, uuid).unwrap(), None);
+        tx.set_verb_compile_options(&a, uuid, v_int(1)).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let mut tx = begin_tx(&db);
+        assert_eq!(
+            tx.get_verb_compile_options(&a, uuid).unwrap(),
+            Some(v_int(1))
+        );
+        // A new program drops what was recorded about the old one.
+        tx.update_verb(
+            &a,
+            uuid,
+            VerbAttrs {
+                definer: None,
+                owner: None,
+                names: None,
+                flags: None,
+                args_spec: None,
+                binary_type: None,
+                binary: Some(vec![]),
+            },
+        )
+        .unwrap();
+        assert_eq!(tx.get_verb_compile_options(&a, uuid).unwrap(), None);
+        tx.set_verb_compile_options(&a, uuid, v_int(2)).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let mut tx = begin_tx(&db);
+        tx.delete_verb(&a, uuid).unwrap();
+        assert_eq!(tx.get_verb_compile_options(&a, uuid).unwrap(), None);
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+    }
+
+    #[test]
+    fn test_cache_tuning() {
+        let db = test_db();
+
+        let mut tx = begin_tx(&db);
+        tx.registry_set("motd", v_str("hello")).unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let tx = begin_tx(&db);
+        let stats = tx.cache_stats().unwrap();
+        let (_, registry) = stats.iter().find(|(n, _)| n == "server_registry").unwrap();
+        assert_eq!(registry.entries, 1);
+
+        assert_eq!(tx.flush_caches(Some("no_such_cache")).unwrap(), None);
+        assert_eq!(tx.flush_caches(Some("server_registry")).unwrap(), Some(1));
+        // Refilled from storage.
+        assert_eq!(tx.registry_get("motd").unwrap(), Some(v_str("hello")));
+
+        assert!(!tx.set_cache_capacity("no_such_cache", 1024).unwrap());
+        assert!(tx.set_cache_capacity("server_registry", 1024).unwrap());
+        let stats = tx.cache_stats().unwrap();
+        let (_, registry) = stats.iter().find(|(n, _)| n == "server_registry").unwrap();
+        assert_eq!(registry.threshold_bytes, 1024);
+        // And kept for the next time the database is opened.
+        assert!(db
+            .partitions
+            .cache_capacities
+            .get("server_registry")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_scan_prefix() {
+        let db = test_db();
+        let (a, b) = (Obj::mk_id(1), Obj::mk_id(2));
+        let uuids: Vec<_> = (0..3).map(|_| uuid::Uuid::new_v4()).collect();
+
+        let mut tx = begin_tx(&db);
+        for (i, uuid) in uuids.iter().enumerate() {
+            tx.object_propvalues
+                .upsert(ObjAndUUIDHolder::new(&a, *uuid), v_int(i as i64))
+                .unwrap();
+            tx.object_propvalues
+                .upsert(ObjAndUUIDHolder::new(&b, *uuid), v_int(10 + i as i64))
+                .unwrap();
+        }
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+
+        let prefix = a.as_bytes().unwrap();
+        let mut tx = begin_tx(&db);
+        let entries = tx.object_propvalues.scan_prefix(&prefix).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|(k, _)| k.obj == a));
+        // In order of key.
+        let mut sorted = entries.clone();
+        sorted.sort_by_key(|(k, _)| k.uuid.as_bytes().to_vec());
+        assert_eq!(entries, sorted);
+
+        // The transaction's own changes are seen, on top of what was committed.
+        tx.object_propvalues
+            .delete(&ObjAndUUIDHolder::new(&a, uuids[0]))
+            .unwrap();
+        let added = uuid::Uuid::new_v4();
+        tx.object_propvalues
+            .upsert(ObjAndUUIDHolder::new(&a, added), v_int(99))
+            .unwrap();
+        let entries = tx.object_propvalues.scan_prefix(&prefix).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|(k, _)| k.uuid != uuids[0]));
+        assert!(entries.contains(&(ObjAndUUIDHolder::new(&a, added), v_int(99))));
+    }
+
+    #[test]
+    fn test_property_blobs() {
+        let config = DatabaseConfig {
+            property_blob_threshold: Some(64),
+            ..DatabaseConfig::default()
+        };
+        let db = super::WorldStateDB::open(None, config).0;
+        let a = Obj::mk_id(1);
+        let (small, large) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let big = v_str(&"x".repeat(1000));
+        let blobs = || db.partitions.object_propvalues_blobs.len().unwrap();
+
+        let mut tx = begin_tx(&db);
+        tx.object_propvalues
+            .upsert(ObjAndUUIDHolder::new(&a, small), v_int(1))
+            .unwrap();
+        tx.object_propvalues
+            .upsert(ObjAndUUIDHolder::new(&a, large), big.clone())
+            .unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        // Only the large value went to the blob partition...
+        assert_eq!(blobs(), 1);
+
+        // ... and it's read back from there once it's no longer cached.
+        let tx = begin_tx(&db);
+        tx.flush_caches(Some("object_propvalues")).unwrap();
+        let entries = tx
+            .object_propvalues
+            .scan_prefix(&a.as_bytes().unwrap())
+            .unwrap();
+        assert!(entries.contains(&(ObjAndUUIDHolder::new(&a, large), big.clone())));
+        assert!(entries.contains(&(ObjAndUUIDHolder::new(&a, small), v_int(1))));
+        let stats = tx.cache_stats().unwrap();
+        let (_, blob_stats) = stats
+            .iter()
+            .find(|(n, _)| n == "object_propvalues_blobs")
+            .unwrap();
+        assert_eq!(blob_stats.entries, 1);
+        assert!(blob_stats.hits >= 1 && blob_stats.misses >= 1);
+        tx.rollback().unwrap();
+
+        // Snapshots see the value as it was.
+        let snapshot = db.snapshot();
+
+        // Replacing it with a small value, or deleting it, drops the blob.
+        let mut tx = begin_tx(&db);
+        tx.object_propvalues
+            .upsert(ObjAndUUIDHolder::new(&a, large), v_int(2))
+            .unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert_eq!(blobs(), 0);
+
+        let tx = snapshot.start_transaction();
+        assert_eq!(
+            tx.object_propvalues
+                .get(&ObjAndUUIDHolder::new(&a, large))
+                .unwrap(),
+            Some(big)
+        );
+    }
+
+    #[test]
+    fn test_dedup_content() {
+        let config = DatabaseConfig {
+            property_blob_threshold: Some(64),
+            dedup_content: true,
+            ..DatabaseConfig::default()
+        };
+        let db = super::WorldStateDB::open(None, config).0;
+        let (a, b) = (Obj::mk_id(1), Obj::mk_id(2));
+        let uuid = uuid::Uuid::new_v4();
+        let program = BytesHolder(b"a compiled program".to_vec());
+        let big = v_str(&"x".repeat(1000));
+        let stored = || db.partitions.content.len().unwrap();
+
+        // The same program and the same large value on two objects are each stored once.
+        let mut tx = begin_tx(&db);
+        for obj in [&a, &b] {
+            tx.object_verbs
+                .upsert(ObjAndUUIDHolder::new(obj, uuid), program.clone())
+                .unwrap();
+            tx.object_propvalues
+                .upsert(ObjAndUUIDHolder::new(obj, uuid), big.clone())
+                .unwrap();
+        }
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert_eq!(stored(), 2);
+        assert_eq!(db.partitions.object_propvalues_blobs.len().unwrap(), 0);
+
+        let tx = begin_tx(&db);
+        tx.flush_caches(None).unwrap();
+        assert_eq!(
+            tx.object_verbs
+                .get(&ObjAndUUIDHolder::new(&b, uuid))
+                .unwrap(),
+            Some(program.clone())
+        );
+        let stats = tx.cache_stats().unwrap();
+        let (_, content) = stats.iter().find(|(n, _)| n == "content").unwrap();
+        assert_eq!((content.hits, content.misses), (2, 2));
+        tx.rollback().unwrap();
+
+        // Each is kept until nothing refers to it any more.
+        let mut tx = begin_tx(&db);
+        tx.object_verbs
+            .delete(&ObjAndUUIDHolder::new(&a, uuid))
+            .unwrap();
+        tx.object_propvalues
+            .upsert(ObjAndUUIDHolder::new(&a, uuid), v_int(1))
+            .unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert_eq!(stored(), 2);
+
+        let mut tx = begin_tx(&db);
+        tx.object_verbs
+            .delete(&ObjAndUUIDHolder::new(&b, uuid))
+            .unwrap();
+        tx.object_propvalues
+            .delete(&ObjAndUUIDHolder::new(&b, uuid))
+            .unwrap();
+        assert_eq!(tx.commit(), Ok(CommitResult::Success));
+        assert_eq!(stored(), 0);
+        assert_eq!(db.partitions.content_refs.len().unwrap(), 0);
+    }
 }
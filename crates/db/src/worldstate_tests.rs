@@ -1219,3 +1219,105 @@ where
         .unwrap();
     assert_eq!(tx.get_max_object().unwrap(), obj);
 }
+
+pub fn perform_test_renumber_object<F, TX>(begin_tx: F)
+where
+    F: Fn() -> TX,
+    TX: WorldStateTransaction,
+{
+    let mut tx = begin_tx();
+    let root = tx
+        .create_object(
+            None,
+            ObjAttrs::new(NOTHING, NOTHING, NOTHING, BitEnum::new(), "root"),
+        )
+        .unwrap();
+    let a = tx
+        .create_object(
+            None,
+            ObjAttrs::new(NOTHING, root.clone(), root.clone(), BitEnum::new(), "a"),
+        )
+        .unwrap();
+    let b = tx
+        .create_object(
+            None,
+            ObjAttrs::new(a.clone(), a.clone(), a.clone(), BitEnum::new(), "b"),
+        )
+        .unwrap();
+    let uuid = tx
+        .define_property(
+            &a,
+            &a,
+            Symbol::mk("test"),
+            &a,
+            BitEnum::new(),
+            Some(v_int(1)),
+        )
+        .unwrap();
+    tx.add_object_verb(
+        &a,
+        &a,
+        vec![Symbol::mk_case_insensitive("test")],
+        vec![1, 2, 3],
+        BinaryType::LambdaMoo18X,
+        BitEnum::new(),
+        VerbArgsSpec::this_none_this(),
+    )
+    .unwrap();
+
+    let new_a = Obj::mk_id(5);
+    tx.renumber_object(&a, &new_a).unwrap();
+    assert!(!tx.object_valid(&a).unwrap());
+    assert!(tx.object_valid(&new_a).unwrap());
+    assert_eq!(tx.get_object_name(&new_a).unwrap(), "a");
+
+    // Self-ownership, and the relations on either side of it, follow the object.
+    assert_eq!(tx.get_object_owner(&new_a).unwrap(), new_a);
+    assert_eq!(tx.get_object_owner(&b).unwrap(), new_a);
+    assert_eq!(tx.get_object_parent(&b).unwrap(), new_a);
+    assert_eq!(tx.get_object_location(&b).unwrap(), new_a);
+    assert_eq!(
+        tx.get_object_children(&root).unwrap(),
+        ObjSet::from_items(&[new_a.clone()])
+    );
+    assert_eq!(
+        tx.get_object_contents(&root).unwrap(),
+        ObjSet::from_items(&[new_a.clone()])
+    );
+    assert_eq!(
+        tx.get_object_contents(&new_a).unwrap(),
+        ObjSet::from_items(&[b.clone()])
+    );
+
+    // As do its verbs & properties, and their ownership.
+    let verb = tx
+        .get_verb_by_name(&new_a, Symbol::mk_case_insensitive("test"))
+        .unwrap();
+    assert_eq!(verb.location(), new_a);
+    assert_eq!(verb.owner(), new_a);
+    assert_eq!(
+        tx.get_verb_binary(&new_a, verb.uuid()).unwrap().as_ref(),
+        &[1, 2, 3]
+    );
+    let (propdef, value, perms, _) = tx.resolve_property(&b, Symbol::mk("test")).unwrap();
+    assert_eq!(propdef.uuid(), uuid);
+    assert_eq!(propdef.definer(), new_a);
+    assert_eq!(value, v_int(1));
+    assert_eq!(perms.owner(), new_a);
+
+    // Renumbering onto an existing object is refused.
+    assert_eq!(
+        tx.renumber_object(&b, &root),
+        Err(WorldStateError::ObjectAlreadyExists(root.clone()))
+    );
+
+    // Max object was bumped by the renumber, and can be brought back down to the highest valid
+    // object once that is recycled.
+    let c = tx.create_object(None, ObjAttrs::default()).unwrap();
+    assert_eq!(c, Obj::mk_id(6));
+    tx.recycle_object(&c).unwrap();
+    assert_eq!(tx.reset_max_object().unwrap(), new_a);
+    assert_eq!(tx.get_max_object().unwrap(), new_a);
+
+    assert_eq!(tx.commit(), Ok(CommitResult::Success));
+}
@@ -17,7 +17,7 @@ use uuid::Uuid;
 use moor_values::model::PropFlag;
 use moor_values::model::VerbArgsSpec;
 use moor_values::model::{BinaryType, VerbAttrs, VerbFlag};
-use moor_values::model::{CommitResult, WorldStateError};
+use moor_values::model::{CacheStats, CommitResult, WorldStateError};
 use moor_values::model::{ObjAttrs, ObjFlag};
 use moor_values::model::{ObjSet, PropPerms};
 use moor_values::model::{PropDef, PropDefs};
@@ -27,10 +27,15 @@ use moor_values::Obj;
 use moor_values::Symbol;
 use moor_values::Var;
 
+use crate::program_cache::ProgramCache;
+
 /// A trait defining a generic interface to a database for storing the per-attribute common
 /// of our objects and their properties and verbs.  Used by DbTxWorldState.
 /// One instance per transaction.
 pub trait WorldStateTransaction: Send {
+    /// Get a handle on the compiled program cache for this database.
+    fn program_cache(&self) -> ProgramCache;
+
     /// Check the validity of the given object.
     fn object_valid(&self, obj: &Obj) -> Result<bool, WorldStateError>;
 
@@ -75,6 +80,16 @@ pub trait WorldStateTransaction: Send {
     /// Destroy the given object, and restructure the property inheritance accordingly.
     fn recycle_object(&mut self, obj: &Obj) -> Result<(), WorldStateError>;
 
+    /// Move the given object to the (unused) object id `new_obj`, rewriting every reference to it
+    /// held by the database itself: its parent & children, location & contents, and the ownership
+    /// of objects, verbs and properties. References held in property values or in verb code are
+    /// not touched.
+    fn renumber_object(&mut self, obj: &Obj, new_obj: &Obj) -> Result<(), WorldStateError>;
+
+    /// Reset the object id sequence so that the next object created is numbered one past the
+    /// highest valid object. Returns the new maximum object.
+    fn reset_max_object(&mut self) -> Result<Obj, WorldStateError>;
+
     /// Get the parent of the given object.
     fn get_object_parent(&self, obj: &Obj) -> Result<Obj, WorldStateError>;
 
@@ -141,6 +156,21 @@ pub trait WorldStateTransaction: Send {
     /// Remove the given verb from the given object.
     fn delete_verb(&mut self, location: &Obj, uuid: Uuid) -> Result<(), WorldStateError>;
 
+    /// Get the language features the given verb was last compiled with, if they were recorded.
+    fn get_verb_compile_options(
+        &self,
+        obj: &Obj,
+        uuid: Uuid,
+    ) -> Result<Option<Var>, WorldStateError>;
+
+    /// Record the language features the given verb was compiled with.
+    fn set_verb_compile_options(
+        &mut self,
+        obj: &Obj,
+        uuid: Uuid,
+        options: Var,
+    ) -> Result<(), WorldStateError>;
+
     /// Get the properties defined on the given object.
     fn get_properties(&self, obj: &Obj) -> Result<PropDefs, WorldStateError>;
 
@@ -203,6 +233,44 @@ pub trait WorldStateTransaction: Send {
     /// Return the (rough) size of the database in bytes.
     fn db_usage(&self) -> Result<usize, WorldStateError>;
 
+    /// A number which changes whenever verbs, property definitions or inheritance (may) have
+    /// changed, as seen by this transaction: with each commit which changes them, and with each
+    /// such change this transaction makes itself. Lookups made under one generation hold for as
+    /// long as it does.
+    fn dispatch_generation(&self) -> u64;
+
+    /// The generation `obj` was last changed at: the timestamp of the last committed transaction
+    /// which changed anything about it, or 0 if none has since generations started being kept.
+    /// Changes this transaction has made itself aren't counted until it commits.
+    fn object_generation(&self, obj: &Obj) -> Result<u64, WorldStateError>;
+
+    /// Get the server registry entry for `key`, if there is one.
+    fn registry_get(&self, key: &str) -> Result<Option<Var>, WorldStateError>;
+
+    /// Set the server registry entry for `key`, replacing any existing one.
+    fn registry_set(&mut self, key: &str, value: Var) -> Result<(), WorldStateError>;
+
+    /// Remove the server registry entry for `key`, returning whether there was one.
+    fn registry_delete(&mut self, key: &str) -> Result<bool, WorldStateError>;
+
+    /// All the server registry entries, in order of key.
+    fn registry_list(&self) -> Result<Vec<(String, Var)>, WorldStateError>;
+
+    /// Statistics for each of the database's caches, by name.
+    fn cache_stats(&self) -> Result<Vec<(String, CacheStats)>, WorldStateError>;
+
+    /// Empty the named cache, or all of them, returning how many entries were dropped; None if
+    /// there's no cache by that name.
+    fn flush_caches(&self, name: Option<&str>) -> Result<Option<usize>, WorldStateError>;
+
+    /// Set the size past which the named cache starts evicting entries, and keep it for when the
+    /// database is next opened. Returns whether there's a cache by that name.
+    fn set_cache_capacity(
+        &self,
+        name: &str,
+        threshold_bytes: usize,
+    ) -> Result<bool, WorldStateError>;
+
     /// Attempt to commit the transaction, returning the result of the commit.
     fn commit(self) -> Result<CommitResult, WorldStateError>;
 
@@ -210,4 +278,16 @@ pub trait WorldStateTransaction: Send {
     fn rollback(self) -> Result<(), WorldStateError>;
 
     fn descendants(&self, obj: &Obj) -> Result<ObjSet, WorldStateError>;
+
+    /// Return the objects whose names satisfy `predicate`.
+    fn find_objects_by_name(
+        &self,
+        predicate: &dyn Fn(&str) -> bool,
+    ) -> Result<ObjSet, WorldStateError>;
+
+    /// Return the objects which define a verb matching `name`, each with the first such verb.
+    fn find_verb_definers(&self, name: Symbol) -> Result<Vec<(Obj, VerbDef)>, WorldStateError>;
+
+    /// Return the objects which define a property named `name`, each with its definition.
+    fn find_property_definers(&self, name: Symbol) -> Result<Vec<(Obj, PropDef)>, WorldStateError>;
 }
@@ -0,0 +1,319 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Fitting ANSI colour and MXP markup to what a client can actually display, using the
+//! capabilities its host negotiated (see `connection_attributes()`).
+
+use moor_compiler::offset_for_builtin;
+use moor_values::Error::{E_ARGS, E_TYPE};
+use moor_values::{v_string, Variant};
+
+use crate::bf_declare;
+use crate::builtins::BfRet::Ret;
+use crate::builtins::{BfCallState, BfErr, BfRet, BuiltinFunction};
+
+/// What the client can display. `None` means the host doesn't know, in which case markup is left
+/// alone rather than guessed at.
+#[derive(Debug, Default, Clone, Copy)]
+struct Capabilities {
+    ansi: Option<bool>,
+    xterm256: Option<bool>,
+    truecolor: Option<bool>,
+    mxp: Option<bool>,
+}
+
+/// The standard xterm values for the 16 basic colours.
+const BASIC_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+fn indexed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => BASIC_COLORS[n as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = n - 16;
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => {
+            let grey = 8 + (n - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn rgb_to_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40,
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+fn nearest_basic(r: u8, g: u8, b: u8) -> u8 {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    (0..16u8)
+        .min_by_key(|&c| distance(BASIC_COLORS[c as usize]))
+        .unwrap()
+}
+
+/// The SGR parameters for `color`, in the richest form the client supports.
+fn sgr_color(color: Color, background: bool, caps: &Capabilities) -> String {
+    let base = if background { 48 } else { 38 };
+    let basic = match color {
+        Color::Rgb(r, g, b) if caps.truecolor != Some(false) => {
+            return format!("{base};2;{r};{g};{b}");
+        }
+        Color::Rgb(r, g, b) if caps.xterm256 != Some(false) => {
+            return format!("{base};5;{}", rgb_to_indexed(r, g, b));
+        }
+        Color::Indexed(n) if caps.xterm256 != Some(false) => {
+            return format!("{base};5;{n}");
+        }
+        Color::Indexed(n) if n < 16 => n,
+        Color::Indexed(n) => {
+            let (r, g, b) = indexed_to_rgb(n);
+            nearest_basic(r, g, b)
+        }
+        Color::Rgb(r, g, b) => nearest_basic(r, g, b),
+    };
+    let offset = if background { 10 } else { 0 };
+    if basic < 8 {
+        (30 + offset + basic).to_string()
+    } else {
+        (90 + offset + basic - 8).to_string()
+    }
+}
+
+/// Rewrite the parameters of an SGR (`ESC [ ... m`) sequence, downgrading any extended colours.
+fn downgrade_sgr(params: &str, caps: &Capabilities) -> String {
+    let params: Vec<&str> = params.split(';').collect();
+    let number = |i: usize| params.get(i).and_then(|p| p.parse::<u8>().ok());
+    let mut out = vec![];
+    let mut i = 0;
+    while i < params.len() {
+        let param = params[i];
+        if param == "38" || param == "48" {
+            let color = match params.get(i + 1).copied() {
+                Some("5") => number(i + 2).map(|n| (Color::Indexed(n), 3)),
+                Some("2") => match (number(i + 2), number(i + 3), number(i + 4)) {
+                    (Some(r), Some(g), Some(b)) => Some((Color::Rgb(r, g, b), 5)),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some((color, len)) = color {
+                out.push(sgr_color(color, param == "48", caps));
+                i += len;
+                continue;
+            }
+        }
+        out.push(param.to_string());
+        i += 1;
+    }
+    out.join(";")
+}
+
+/// Remove MXP tags, and decode the entities MXP requires for literal `<`, `>` and `&`.
+fn strip_mxp_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn downgrade_markup(text: &str, caps: &Capabilities) -> String {
+    let keep_ansi = caps.ansi != Some(false);
+    let mut out = String::with_capacity(text.len());
+    let mut saw_mxp = false;
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        out.push_str(&rest[..start]);
+        let sequence = &rest[start..];
+        let Some(body) = sequence.strip_prefix("\x1b[") else {
+            if keep_ansi {
+                out.push('\x1b');
+            }
+            rest = &sequence[1..];
+            continue;
+        };
+        // A control sequence runs up to its final byte, which is in the range '@'..='~'.
+        let Some(end) = body.find(|c: char| ('@'..='~').contains(&c)) else {
+            if keep_ansi {
+                out.push_str(sequence);
+            }
+            rest = "";
+            break;
+        };
+        let params = &body[..end];
+        let final_byte = &body[end..end + 1];
+        rest = &body[end + 1..];
+        match final_byte {
+            // MXP line mode.
+            "z" => {
+                saw_mxp = true;
+                if caps.mxp != Some(false) {
+                    out.push_str(&format!("\x1b[{params}z"));
+                }
+            }
+            "m" if keep_ansi => {
+                out.push_str(&format!("\x1b[{}m", downgrade_sgr(params, caps)));
+            }
+            _ if keep_ansi => out.push_str(&format!("\x1b[{params}{final_byte}")),
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+    // Without MXP line mode escapes, `<` is just text, so only strip tags if we saw one.
+    if saw_mxp && caps.mxp == Some(false) {
+        return strip_mxp_tags(&out);
+    }
+    out
+}
+
+/*
+Syntax: downgrade_markup(str <text>, map <capabilities>)   => str
+
+Returns <text> with its ANSI colour and MXP markup fitted to <capabilities>, a map as returned by
+connection_attributes(): "ansi" false strips all escape sequences, "xterm256" and "truecolor" false
+map extended colours down to what is supported, and "mxp" false removes MXP tags. Capabilities
+missing from the map are assumed to be supported.
+ */
+fn bf_downgrade_markup(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(text) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Map(attributes) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    let mut caps = Capabilities::default();
+    for (key, value) in attributes.iter() {
+        let Variant::Str(key) = key.variant() else {
+            continue;
+        };
+        let supported = Some(value.is_true());
+        match key.as_string().as_str() {
+            "ansi" => caps.ansi = supported,
+            "xterm256" => caps.xterm256 = supported,
+            "truecolor" => caps.truecolor = supported,
+            "mxp" => caps.mxp = supported,
+            _ => {}
+        }
+    }
+
+    Ok(Ret(v_string(downgrade_markup(text.as_string(), &caps))))
+}
+bf_declare!(downgrade_markup, bf_downgrade_markup);
+
+pub(crate) fn register_bf_markup(builtins: &mut [Box<dyn BuiltinFunction>]) {
+    builtins[offset_for_builtin("downgrade_markup")] = Box::new(BfDowngradeMarkup {});
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::bf_markup::{downgrade_markup, Capabilities};
+
+    const ANSI_ONLY: Capabilities = Capabilities {
+        ansi: Some(true),
+        xterm256: Some(false),
+        truecolor: Some(false),
+        mxp: Some(false),
+    };
+
+    #[test]
+    fn test_unknown_capabilities_pass_through() {
+        let text = "\x1b[38;2;255;0;0mred\x1b[0m \x1b[1z<b>bold</b>";
+        assert_eq!(downgrade_markup(text, &Capabilities::default()), text);
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let caps = Capabilities {
+            ansi: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            downgrade_markup("\x1b[1;31mred\x1b[0m plain", &caps),
+            "red plain"
+        );
+    }
+
+    #[test]
+    fn test_downgrade_colors() {
+        // Truecolor red to 256 colours, and to the 16 basic colours.
+        let xterm256 = Capabilities {
+            truecolor: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            downgrade_markup("\x1b[38;2;255;0;0mred", &xterm256),
+            "\x1b[38;5;196mred"
+        );
+        assert_eq!(
+            downgrade_markup("\x1b[1;38;2;255;0;0mred", &ANSI_ONLY),
+            "\x1b[1;91mred"
+        );
+        // 256-colour backgrounds, both basic and from the colour cube.
+        assert_eq!(downgrade_markup("\x1b[48;5;4mx", &ANSI_ONLY), "\x1b[44mx");
+        assert_eq!(downgrade_markup("\x1b[48;5;46mx", &ANSI_ONLY), "\x1b[102mx");
+    }
+
+    #[test]
+    fn test_strip_mxp() {
+        assert_eq!(
+            downgrade_markup("\x1b[1z<send>look</send> &lt;here&gt;", &ANSI_ONLY),
+            "look <here>"
+        );
+        // Without MXP escapes, angle brackets are just text.
+        assert_eq!(downgrade_markup("a <b> c", &ANSI_ONLY), "a <b> c");
+    }
+}
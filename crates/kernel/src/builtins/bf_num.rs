@@ -11,16 +11,18 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use rand::Rng;
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use uuid::Uuid;
 
 use moor_compiler::offset_for_builtin;
 use moor_values::Error::{E_ARGS, E_INVARG, E_TYPE};
-use moor_values::{v_float, v_int, v_str};
+use moor_values::{v_float, v_int, v_list, v_string};
 use moor_values::{Sequence, Variant};
 
 use crate::bf_declare;
 use crate::builtins::BfRet::Ret;
-use crate::builtins::{BfCallState, BfErr, BfRet, BuiltinFunction};
+use crate::builtins::{encode_binary_string, BfCallState, BfErr, BfRet, BuiltinFunction};
 
 fn bf_abs(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if bf_args.args.len() != 1 {
@@ -72,45 +74,168 @@ fn bf_random(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         return Err(BfErr::Code(E_ARGS));
     }
 
+    let max = if bf_args.args.is_empty() {
+        2147483647
+    } else {
+        match bf_args.args[0].variant() {
+            Variant::Int(i) if *i > 0 => *i,
+            Variant::Int(_) => return Err(BfErr::Code(E_INVARG)),
+            _ => return Err(BfErr::Code(E_TYPE)),
+        }
+    };
+
     let mut rng = rand::thread_rng();
-    bf_args
-        .args
-        .is_empty()
-        .then_some(Ok(Ret(v_int(rng.gen_range(1..=2147483647)))))
-        .unwrap_or_else(|| match &bf_args.args[0].variant() {
-            Variant::Int(i) if *i > 0 => Ok(Ret(v_int(rng.gen_range(1..=*i)))),
-            Variant::Int(_) => Err(BfErr::Code(E_INVARG)),
-            _ => Err(BfErr::Code(E_TYPE)),
-        })
+    Ok(Ret(bf_args
+        .exec_state
+        .replay
+        .observe(|| v_int(rng.gen_range(1..=max)))))
 }
 bf_declare!(random, bf_random);
 
-fn bf_floatstr(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
+/// The largest number of bytes `random_bytes()` will produce in one call.
+const MAX_RANDOM_BYTES: i64 = 10000;
+
+/// random_bytes(count) => binary string of `count` bytes from the OS's CSPRNG.
+fn bf_random_bytes(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
         return Err(BfErr::Code(E_ARGS));
     }
+    let Variant::Int(count) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if !(0..=MAX_RANDOM_BYTES).contains(count) {
+        return Err(BfErr::Code(E_INVARG));
+    }
 
-    let x = match bf_args.args[0].variant() {
-        Variant::Float(f) => f,
-        _ => return Err(BfErr::Code(E_TYPE)),
+    let mut bytes = vec![0u8; *count as usize];
+    OsRng.fill_bytes(&mut bytes);
+    let bytes = encode_binary_string(&bytes);
+    Ok(Ret(bf_args.exec_state.replay.observe(|| v_string(bytes))))
+}
+bf_declare!(random_bytes, bf_random_bytes);
+
+/// uuid_generate([version]) => string
+/// A new random (version 4, the default) or time-ordered (version 7) UUID, in the usual
+/// hyphenated form. Version 7 UUIDs sort in the order they were generated in, which makes them
+/// better keys for anything that's going to be scanned in order.
+fn bf_uuid_generate(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let version = if bf_args.args.is_empty() {
+        4
+    } else {
+        match bf_args.args[0].variant() {
+            Variant::Int(version) => *version,
+            _ => return Err(BfErr::Code(E_TYPE)),
+        }
+    };
+    let uuid = match version {
+        4 => Uuid::new_v4(),
+        7 => Uuid::now_v7(),
+        _ => return Err(BfErr::Code(E_INVARG)),
     };
+    Ok(Ret(bf_args
+        .exec_state
+        .replay
+        .observe(|| v_string(uuid.hyphenated().to_string()))))
+}
+bf_declare!(uuid_generate, bf_uuid_generate);
 
-    let precision = match &bf_args.args[1].variant() {
-        Variant::Int(i) if *i > 0 => *i as usize,
-        _ => return Err(BfErr::Code(E_TYPE)),
+/// uuid_parse(string [, binary]) => string
+/// Checks that `string` is a UUID (hyphenated, simple, braced or `urn:uuid:` form) and returns it
+/// in the usual lowercase hyphenated form, or if `binary` is true, as a binary string of its 16
+/// bytes. E_INVARG if it isn't a UUID.
+fn bf_uuid_parse(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(text) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
     };
+    let uuid = Uuid::parse_str(text.as_string()).map_err(|_| BfErr::Code(E_INVARG))?;
+    let binary = bf_args.args.len() == 2 && bf_args.args[1].is_true();
+    if binary {
+        return Ok(Ret(v_string(encode_binary_string(uuid.as_bytes()))));
+    }
+    Ok(Ret(v_string(uuid.hyphenated().to_string())))
+}
+bf_declare!(uuid_parse, bf_uuid_parse);
+
+/// One step of the SplitMix64 generator. Chosen because it is tiny, fast, and -- unlike the
+/// generators in `rand` -- is guaranteed never to change its output between versions, which is
+/// the whole point of a seeded generator for reproducible simulations and tests.
+fn splitmix64(state: u64) -> (u64, u64) {
+    let next_state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = next_state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (z ^ (z >> 31), next_state)
+}
 
-    let scientific = match &bf_args.args[2].variant() {
-        Variant::Int(b) => *b == 1,
-        _ => return Err(BfErr::Code(E_TYPE)),
+/// frandom(seed [, max]) => {value, next_seed}
+/// Deterministic counterpart to `random()`: produces a number between 1 and `max` (default
+/// 2147483647) from `seed`, along with the seed to pass to the next call.
+fn bf_frandom(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Int(seed) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let max = if bf_args.args.len() == 2 {
+        match bf_args.args[1].variant() {
+            Variant::Int(i) if *i > 0 => *i,
+            Variant::Int(_) => return Err(BfErr::Code(E_INVARG)),
+            _ => return Err(BfErr::Code(E_TYPE)),
+        }
+    } else {
+        2147483647
+    };
+
+    let (value, next_seed) = splitmix64(*seed as u64);
+    let value = (value % max as u64) as i64 + 1;
+    Ok(Ret(v_list(&[v_int(value), v_int(next_seed as i64)])))
+}
+bf_declare!(frandom, bf_frandom);
+
+/// LambdaMOO caps floatstr() precision at DBL_DIG + 4.
+pub(crate) const FLOATSTR_MAX_PRECISION: usize = 19;
+
+/// Format `x` the way C's `printf("%.*f")` or `printf("%.*e")` would, as LambdaMOO's floatstr()
+/// does.
+fn floatstr(x: f64, precision: usize, scientific: bool) -> String {
+    if !scientific {
+        return format!("{:.*}", precision, x);
+    }
+    // Rust writes exponents as `e2` / `e-7`; C always gives a sign and at least two digits.
+    let s = format!("{:.*e}", precision, x);
+    let Some((mantissa, exponent)) = s.split_once('e') else {
+        return s;
     };
+    let exponent: i32 = exponent.parse().expect("float exponent is an integer");
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
 
-    let mut s = format!("{:.*}", precision, x);
-    if scientific {
-        s = format!("{:e}", x);
+fn bf_floatstr(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
+        return Err(BfErr::Code(E_ARGS));
     }
 
-    Ok(Ret(v_str(s.as_str())))
+    let Variant::Float(x) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    let precision = match bf_args.args[1].variant() {
+        Variant::Int(i) if *i < 0 => return Err(BfErr::Code(E_INVARG)),
+        Variant::Int(i) => (*i as usize).min(FLOATSTR_MAX_PRECISION),
+        _ => return Err(BfErr::Code(E_TYPE)),
+    };
+
+    let scientific = bf_args.args.len() == 3 && bf_args.args[2].is_true();
+
+    Ok(Ret(v_string(floatstr(*x, precision, scientific))))
 }
 bf_declare!(floatstr, bf_floatstr);
 
@@ -368,6 +493,10 @@ pub(crate) fn register_bf_num(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("min")] = Box::new(BfMin {});
     builtins[offset_for_builtin("max")] = Box::new(BfMax {});
     builtins[offset_for_builtin("random")] = Box::new(BfRandom {});
+    builtins[offset_for_builtin("random_bytes")] = Box::new(BfRandomBytes {});
+    builtins[offset_for_builtin("frandom")] = Box::new(BfFrandom {});
+    builtins[offset_for_builtin("uuid_generate")] = Box::new(BfUuidGenerate {});
+    builtins[offset_for_builtin("uuid_parse")] = Box::new(BfUuidParse {});
     builtins[offset_for_builtin("floatstr")] = Box::new(BfFloatstr {});
     builtins[offset_for_builtin("sqrt")] = Box::new(BfSqrt {});
     builtins[offset_for_builtin("sin")] = Box::new(BfSin {});
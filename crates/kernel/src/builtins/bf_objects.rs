@@ -11,6 +11,8 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::collections::{HashMap, HashSet, VecDeque};
+
 use lazy_static::lazy_static;
 use tracing::{debug, error, trace};
 
@@ -19,8 +21,8 @@ use moor_values::model::Named;
 use moor_values::model::WorldStateError;
 use moor_values::model::{ObjFlag, ValSet};
 use moor_values::util::BitEnum;
-use moor_values::Error::{E_ARGS, E_INVARG, E_NACC, E_PERM, E_TYPE};
-use moor_values::{v_bool, v_int, v_none, v_obj, v_str};
+use moor_values::Error::{E_ARGS, E_INVARG, E_NACC, E_PERM, E_RECMOVE, E_TYPE};
+use moor_values::{v_bool, v_empty_list, v_int, v_none, v_obj, v_str, Obj};
 use moor_values::{v_list, Sequence, Symbol};
 use moor_values::{v_list_iter, NOTHING};
 use moor_values::{List, Variant};
@@ -38,6 +40,8 @@ lazy_static! {
     static ref CREATE_SYM: Symbol = Symbol::mk("create");
     static ref RECYCLE_SYM: Symbol = Symbol::mk("recycle");
     static ref ACCEPT_SYM: Symbol = Symbol::mk("accept");
+    static ref EXITS_SYM: Symbol = Symbol::mk("exits");
+    static ref DEST_SYM: Symbol = Symbol::mk("dest");
 }
 /*
 Function: int valid (obj object)
@@ -83,10 +87,34 @@ fn bf_chparent(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     let Variant::Obj(new_parent) = bf_args.args[1].variant() else {
         return Err(BfErr::Code(E_TYPE));
     };
+    let valid = |o: &Obj| bf_args.world_state.valid(o).map_err(world_state_bf_err);
+    if !valid(obj)? || (!new_parent.is_nothing() && !valid(new_parent)?) {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let clear_conflicts = bf_args.config.chparent_clear_conflicts;
     bf_args
         .world_state
-        .change_parent(&bf_args.task_perms_who(), obj, new_parent)
-        .map_err(world_state_bf_err)?;
+        .change_parent(&bf_args.task_perms_who(), obj, new_parent, clear_conflicts)
+        .map_err(|e| match e {
+            // Say which way round the loop goes, or which property is in the way.
+            WorldStateError::ParentCycle(_, ref path) => BfErr::Raise(
+                E_RECMOVE,
+                Some(e.to_string()),
+                Some(v_list_iter(path.iter().cloned().map(v_obj))),
+            ),
+            WorldStateError::ChparentPropertyConflict(ref obj, ref definer, ref name) => {
+                BfErr::Raise(
+                    E_INVARG,
+                    Some(e.to_string()),
+                    Some(v_list(&[
+                        v_obj(obj.clone()),
+                        v_obj(definer.clone()),
+                        v_str(name),
+                    ])),
+                )
+            }
+            e => world_state_bf_err(e),
+        })?;
     Ok(Ret(v_none()))
 }
 bf_declare!(chparent, bf_chparent);
@@ -187,12 +215,71 @@ fn bf_create(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     }
 }
 bf_declare!(create, bf_create);
+
+/// clone_object(obj [, options])
+/// Creates a copy of `obj`, with its parent, name and flags, the properties it defines, and the
+/// values of the properties it inherits, all in one step. `options` is a map which may have:
+///   "owner": who owns the copy (default: the programmer; others need a wizard),
+///   "verbs": whether to copy the verbs `obj` defines too, owned by the copy's owner (default: no).
+/// Unlike create(), :initialize is not called, as the copy's state is already set up.
+fn bf_clone_object(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(source) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let mut owner = bf_args.task_perms_who();
+    let mut copy_verbs = false;
+    if bf_args.args.len() == 2 {
+        let Variant::Map(options) = bf_args.args[1].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        for (key, value) in options.iter() {
+            let Variant::Str(key) = key.variant() else {
+                return Err(BfErr::Code(E_INVARG));
+            };
+            match key.as_string().to_lowercase().as_str() {
+                "owner" => {
+                    let Variant::Obj(o) = value.variant() else {
+                        return Err(BfErr::Code(E_TYPE));
+                    };
+                    owner = o.clone();
+                }
+                "verbs" => copy_verbs = value.is_true(),
+                _ => return Err(BfErr::Code(E_INVARG)),
+            }
+        }
+    }
+    if !bf_args
+        .world_state
+        .valid(source)
+        .map_err(world_state_bf_err)?
+    {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let clone = bf_args
+        .world_state
+        .clone_object(&bf_args.task_perms_who(), source, &owner, copy_verbs)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_obj(clone)))
+}
+bf_declare!(clone_object, bf_clone_object);
 /*
 Function: none recycle (obj object)
 The given object is destroyed, irrevocably. The programmer must either own object or be a wizard; otherwise, E_PERM is raised. If object is not valid, then E_INVARG is raised. The children of object are reparented to the parent of object. Before object is recycled, each object in its contents is moved to #-1 (implying a call to object's exitfunc verb, if any) and then object's `recycle' verb, if any, is called with no arguments.
  */
-// This is invoked with a list of objects to move/call :exitfunc on. When the list is empty, the
-// next trampoline is called, to do the actual recycle.
+// In moor, the steps happen in this order, as in LambdaMOO's server (rather than its manual):
+//   1. `obj:recycle()` is called, if it's defined. If that recycles `obj` itself, we're done.
+//   2. Everything in `obj` is moved to #-1, and then `obj:exitfunc(thing)` is called for each, as
+//      `move()` would have.
+//   3. In one step, `obj`'s children are reparented to its parent, `obj` leaves its location
+//      (without calling its location's `exitfunc`, as in LambdaMOO), and `obj` is destroyed.
+// If the server is configured not to recycle objects with children, E_PERM is raised for them
+// before anything else happens.
+
+// After :recycle, move the contents out. The trampoline argument is then the list of contents
+// still to have :exitfunc called for them.
 const BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC: usize = 0;
 // Do the recycle.
 const BF_RECYCLE_TRAMPOLINE_DONE_MOVE: usize = 1;
@@ -204,70 +291,48 @@ fn bf_recycle(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         return Err(BfErr::Code(E_TYPE));
     };
 
-    let valid = bf_args.world_state.valid(&obj);
-    if valid == Ok(false)
-        || valid
-            .err()
-            .map(|e| e.database_error_msg() == Some("NotFound"))
-            .unwrap_or_default()
-    {
-        return Err(BfErr::Code(E_INVARG));
-    }
-
-    // Check if the given task perms can control the object before continuing.
-    if !bf_args
-        .world_state
-        .controls(&bf_args.task_perms_who(), &obj)
-        .map_err(world_state_bf_err)?
-    {
-        return Err(BfErr::Code(E_PERM));
-    }
-
-    // Before actually recycling the object, we need to move all its contents to #-1. While
-    // `recycle_object` will actually do this, we need to make sure :exitfunc is called on each
-    // object, so we'll do it manually here.
-
     'outer: loop {
         let tramp = bf_args.bf_frame_mut().bf_trampoline.take();
         match tramp {
             None => {
-                // Starting out, we need to call "recycle" on the object, if it exists.
-                // The next point in the trampoline is CALL_EXITFUNC and it will expect a list of
-                // objects to move/call :exitfunc on. So let's get the initial list of objects
-                // now
-                let object_contents = bf_args
+                let valid = bf_args.world_state.valid(&obj);
+                if valid == Ok(false)
+                    || valid
+                        .err()
+                        .map(|e| e.database_error_msg() == Some("NotFound"))
+                        .unwrap_or_default()
+                {
+                    return Err(BfErr::Code(E_INVARG));
+                }
+
+                // Check if the given task perms can control the object before continuing.
+                if !bf_args
                     .world_state
-                    .contents_of(&bf_args.task_perms_who(), &obj)
-                    .map_err(world_state_bf_err)?;
-                // Filter contents for objects that have an :exitfunc verb.
-                let mut contents = vec![];
-                for o in object_contents.iter() {
-                    match bf_args.world_state.find_method_verb_on(
-                        &bf_args.task_perms_who(),
-                        &o,
-                        *EXITFUNC_SYM,
-                    ) {
-                        Ok(_) => {
-                            contents.push(v_obj(o));
-                        }
-                        Err(WorldStateError::VerbNotFound(_, _)) => {}
-                        Err(e) => {
-                            error!("Error looking up exitfunc verb: {:?}", e);
-                            return Err(BfErr::Code(E_NACC));
-                        }
-                    }
+                    .controls(&bf_args.task_perms_who(), &obj)
+                    .map_err(world_state_bf_err)?
+                {
+                    return Err(BfErr::Code(E_PERM));
+                }
+
+                if !bf_args.config.recycle_parents
+                    && !bf_args
+                        .world_state
+                        .children_of(&bf_args.task_perms_who(), &obj)
+                        .map_err(world_state_bf_err)?
+                        .is_empty()
+                {
+                    return Err(BfErr::Code(E_PERM));
                 }
-                let contents = v_list(&contents);
+
+                let bf_frame = bf_args.bf_frame_mut();
+                bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC);
+                bf_frame.bf_trampoline_arg = None;
                 match bf_args.world_state.find_method_verb_on(
                     &bf_args.task_perms_who(),
                     &obj,
                     *RECYCLE_SYM,
                 ) {
                     Ok((binary, resolved_verb)) => {
-                        let bf_frame = bf_args.bf_frame_mut();
-                        bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC);
-                        bf_frame.bf_trampoline_arg = Some(contents);
-
                         return Ok(VmInstr(DispatchVerb {
                             permissions: bf_args.task_perms_who(),
                             resolved_verb,
@@ -285,11 +350,6 @@ fn bf_recycle(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
                         }));
                     }
                     Err(WorldStateError::VerbNotFound(_, _)) => {
-                        // Short-circuit fake-tramp state change.
-                        let bf_frame = bf_args.bf_frame_mut();
-
-                        bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC);
-                        bf_frame.bf_trampoline_arg = Some(contents);
                         // Fall through to the next case.
                     }
                     Err(e) => {
@@ -299,62 +359,94 @@ fn bf_recycle(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
                 }
             }
             Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC) => {
-                // Check the arguments, which must be a list of objects. IF it's empty, we can
-                // move onto DONE_MOVE, if not, take the head of the list, and call :exitfunc on it
-                // (if it exists), and then back to this state.
-
-                let contents = bf_args.bf_frame().bf_trampoline_arg.clone().unwrap();
+                let contents = match bf_args.bf_frame().bf_trampoline_arg.clone() {
+                    Some(contents) => contents,
+                    None => {
+                        // Straight after :recycle, which may have recycled the object itself.
+                        if !bf_args
+                            .world_state
+                            .valid(&obj)
+                            .map_err(world_state_bf_err)?
+                        {
+                            return Ok(Ret(v_int(0)));
+                        }
+                        let evicted = bf_args
+                            .world_state
+                            .evict_contents(&bf_args.task_perms_who(), &obj)
+                            .map_err(world_state_bf_err)?;
+                        let has_exitfunc = match bf_args.world_state.find_method_verb_on(
+                            &bf_args.task_perms_who(),
+                            &obj,
+                            *EXITFUNC_SYM,
+                        ) {
+                            Ok(_) => true,
+                            Err(WorldStateError::VerbNotFound(_, _)) => false,
+                            Err(e) => {
+                                error!("Error looking up exitfunc verb: {:?}", e);
+                                return Err(BfErr::Code(E_NACC));
+                            }
+                        };
+                        if !has_exitfunc {
+                            bf_args.bf_frame_mut().bf_trampoline =
+                                Some(BF_RECYCLE_TRAMPOLINE_DONE_MOVE);
+                            continue 'outer;
+                        }
+                        v_list_iter(evicted.iter().map(v_obj))
+                    }
+                };
                 let Variant::List(contents) = contents.variant() else {
                     panic!("Invalid trampoline argument for bf_recycle");
                 };
-                'inner: loop {
-                    if contents.is_empty() {
-                        let bf_frame = bf_args.bf_frame_mut();
-                        bf_frame.bf_trampoline_arg = None;
-                        bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_DONE_MOVE);
-                        continue 'outer;
-                    }
-                    let (head_obj, contents) =
-                        contents.pop_front().map_err(|_| BfErr::Code(E_INVARG))?;
-                    let Variant::Obj(head_obj) = head_obj.variant() else {
-                        panic!("Invalid trampoline argument for bf_recycle");
-                    };
-                    // :exitfunc *should* exist because we looked for it earlier, and we're supposed to
-                    // be transactionally isolated. But we need to do resolution anyways, so we will
-                    // look again anyways.
-                    let Ok((binary, resolved_verb)) = bf_args.world_state.find_method_verb_on(
-                        &bf_args.task_perms_who(),
-                        head_obj,
-                        *EXITFUNC_SYM,
-                    ) else {
-                        // If there's no :exitfunc, we can just move on to the next object.
-                        let bf_frame = bf_args.bf_frame_mut();
-                        bf_frame.bf_trampoline_arg = Some(contents);
-                        continue 'inner;
-                    };
+                if contents.is_empty() {
                     let bf_frame = bf_args.bf_frame_mut();
-                    bf_frame.bf_trampoline_arg = Some(contents);
-                    bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC);
-
-                    // Call :exitfunc on the head object.
-                    return Ok(VmInstr(DispatchVerb {
-                        permissions: bf_args.task_perms_who(),
-                        resolved_verb,
-                        binary,
-                        call: VerbCall {
-                            verb_name: *EXITFUNC_SYM,
-                            location: v_obj(head_obj.clone()),
-                            this: v_obj(head_obj.clone()),
-                            player: bf_args.exec_state.top().player.clone(),
-                            args: List::mk_list(&[v_obj(obj)]),
-                            argstr: "".to_string(),
-                            caller: bf_args.exec_state.top().this.clone(),
-                        },
-                        command: None,
-                    }));
+                    bf_frame.bf_trampoline_arg = None;
+                    bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_DONE_MOVE);
+                    continue 'outer;
                 }
+                let (head_obj, contents) =
+                    contents.pop_front().map_err(|_| BfErr::Code(E_INVARG))?;
+
+                // Resolve again, since the last :exitfunc may have changed things.
+                let Ok((binary, resolved_verb)) = bf_args.world_state.find_method_verb_on(
+                    &bf_args.task_perms_who(),
+                    &obj,
+                    *EXITFUNC_SYM,
+                ) else {
+                    let bf_frame = bf_args.bf_frame_mut();
+                    bf_frame.bf_trampoline_arg = None;
+                    bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_DONE_MOVE);
+                    continue 'outer;
+                };
+                let bf_frame = bf_args.bf_frame_mut();
+                bf_frame.bf_trampoline_arg = Some(contents);
+                bf_frame.bf_trampoline = Some(BF_RECYCLE_TRAMPOLINE_CALL_EXITFUNC);
+
+                // Call :exitfunc on the object being recycled, for the thing which just left it.
+                return Ok(VmInstr(DispatchVerb {
+                    permissions: bf_args.task_perms_who(),
+                    resolved_verb,
+                    binary,
+                    call: VerbCall {
+                        verb_name: *EXITFUNC_SYM,
+                        location: v_obj(obj.clone()),
+                        this: v_obj(obj),
+                        player: bf_args.exec_state.top().player.clone(),
+                        args: List::mk_list(&[head_obj]),
+                        argstr: "".to_string(),
+                        caller: bf_args.exec_state.top().this.clone(),
+                    },
+                    command: None,
+                }));
             }
             Some(BF_RECYCLE_TRAMPOLINE_DONE_MOVE) => {
+                // An :exitfunc may have recycled the object already.
+                if !bf_args
+                    .world_state
+                    .valid(&obj)
+                    .map_err(world_state_bf_err)?
+                {
+                    return Ok(Ret(v_int(0)));
+                }
                 debug!(obj = ?obj, "Recycling object");
                 bf_args
                     .world_state
@@ -382,6 +474,48 @@ fn bf_max_object(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(max_object, bf_max_object);
 
+/*
+Function: obj renumber (obj object)
+Renumbers `object` to the lowest-numbered unused object number lower than its current one, and
+returns the new object. References to it held by the database (parent/children,
+location/contents, ownership) are updated; references in property values and verb code are not.
+Wizard only.
+*/
+fn bf_renumber(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(obj) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if !bf_args.world_state.valid(obj).map_err(world_state_bf_err)? {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let new_obj = bf_args
+        .world_state
+        .renumber_object(&bf_args.task_perms_who(), obj)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_obj(new_obj)))
+}
+bf_declare!(renumber, bf_renumber);
+
+/*
+Function: none reset_max_object ()
+Sets the value returned by max_object() to the highest-numbered valid object, so that the numbers
+of recycled objects above it are reused by create(). Wizard only.
+*/
+fn bf_reset_max_object(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .world_state
+        .reset_max_object(&bf_args.task_perms_who())
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_none()))
+}
+bf_declare!(reset_max_object, bf_reset_max_object);
+
 const BF_MOVE_TRAMPOLINE_START_ACCEPT: usize = 0;
 const BF_MOVE_TRAMPOLINE_MOVE_CALL_EXITFUNC: usize = 1;
 const BF_MOVE_TRAMPOLINE_CALL_ENTERFUNC: usize = 2;
@@ -645,6 +779,216 @@ fn bf_properties(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(properties, bf_properties);
 
+/// locate_by_name(pattern [, case-matters])
+/// Returns the objects whose names contain `pattern`, ignoring case unless `case-matters` is
+/// true. The search is done by the database, rather than by looping over objects in MOO code.
+fn bf_locate_by_name(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(pattern) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let case_matters = bf_args.args.len() == 2 && bf_args.args[1].is_true();
+    let found = bf_args
+        .world_state
+        .find_objects_by_name(&bf_args.task_perms_who(), pattern.as_string(), case_matters)
+        .map_err(world_state_bf_err)?;
+    let mut found: Vec<_> = found.iter().collect();
+    found.sort();
+    Ok(Ret(v_list_iter(found.into_iter().map(v_obj))))
+}
+bf_declare!(locate_by_name, bf_locate_by_name);
+
+/// find_verb(root, name)
+/// Returns `{object, verb-names}` for `root` and each of its descendants which defines a verb
+/// matching `name`, as `verb_info()` would match it. Objects the programmer can't read are left
+/// out. A `root` of #-1 searches the whole database.
+fn bf_find_verb(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let (Variant::Obj(root), Variant::Str(name)) =
+        (bf_args.args[0].variant(), bf_args.args[1].variant())
+    else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let found = bf_args
+        .world_state
+        .find_verb_definers(
+            &bf_args.task_perms_who(),
+            root,
+            Symbol::mk_case_insensitive(name.as_string()),
+        )
+        .map_err(world_state_bf_err)?;
+    let found = found
+        .iter()
+        .map(|(obj, verb)| v_list(&[v_obj(obj.clone()), v_str(&verb.names().join(" "))]));
+    Ok(Ret(v_list_iter(found)))
+}
+bf_declare!(find_verb, bf_find_verb);
+
+/// find_property(root, name)
+/// Returns `root` and those of its descendants which define (rather than inherit) a property
+/// named `name`. Objects the programmer can't read are left out. A `root` of #-1 searches the
+/// whole database.
+fn bf_find_property(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let (Variant::Obj(root), Variant::Str(name)) =
+        (bf_args.args[0].variant(), bf_args.args[1].variant())
+    else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let found = bf_args
+        .world_state
+        .find_property_definers(
+            &bf_args.task_perms_who(),
+            root,
+            Symbol::mk_case_insensitive(name.as_string()),
+        )
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_list_iter(
+        found.into_iter().map(|(obj, _)| v_obj(obj)),
+    )))
+}
+bf_declare!(find_property, bf_find_property);
+
+/// How many hits `search_text()` returns when not told.
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+/// search_text(query [, limit])
+/// Returns up to `limit` (default 20) `{object, property}` pairs whose values match `query` in the
+/// full-text index, best match first. Only properties the server has been told to index, and the
+/// programmer can read, are searched. E_INVARG if nothing is being indexed.
+fn bf_search_text(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(query) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let limit = if bf_args.args.len() == 2 {
+        let Variant::Int(limit) = bf_args.args[1].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        *limit
+    } else {
+        DEFAULT_SEARCH_LIMIT
+    };
+    if limit <= 0 {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let Some(hits) = bf_args
+        .world_state
+        .search_text(&bf_args.task_perms_who(), query.as_string(), limit as usize)
+        .map_err(world_state_bf_err)?
+    else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    let hits = hits
+        .into_iter()
+        .map(|(obj, pname)| v_list(&[v_obj(obj), v_str(pname.as_str())]));
+    Ok(Ret(v_list_iter(hits)))
+}
+bf_declare!(search_text, bf_search_text);
+
+/// shortest_path(from, to [, exits-property [, destination-property]])
+/// Returns the list of exits to take to get from room `from` to room `to` in the fewest steps, or
+/// 0 if there's no way there. Rooms list their exits in `exits-property` (default "exits"), and
+/// each exit names the room it leads to in `destination-property` (default "dest"), as in
+/// LambdaCore. Exits the programmer can't read are treated as not being there.
+/// Each room visited costs a tick, and the search stops when the task runs out of them.
+fn bf_shortest_path(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() < 2 || bf_args.args.len() > 4 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let (Variant::Obj(from), Variant::Obj(to)) =
+        (bf_args.args[0].variant(), bf_args.args[1].variant())
+    else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let mut prop_names = [*EXITS_SYM, *DEST_SYM];
+    for (i, arg) in bf_args.args.iter().skip(2).enumerate() {
+        let Variant::Str(name) = arg.variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        prop_names[i] = Symbol::mk_case_insensitive(name.as_string());
+    }
+    let [exits_sym, dest_sym] = prop_names;
+    for obj in [from, to] {
+        if !bf_args.world_state.valid(obj).map_err(world_state_bf_err)? {
+            return Err(BfErr::Code(E_INVARG));
+        }
+    }
+    if from == to {
+        return Ok(Ret(v_empty_list()));
+    }
+
+    let perms = bf_args.task_perms_who();
+    let budget = bf_args.exec_state.ticks_left();
+
+    // Breadth first, remembering the room and exit each room was first reached by.
+    let mut reached_by: HashMap<Obj, (Obj, Obj)> = HashMap::new();
+    let mut seen = HashSet::from([from.clone()]);
+    let mut queue = VecDeque::from([from.clone()]);
+    let mut visited = 0;
+    let mut found = false;
+    'search: while let Some(room) = queue.pop_front() {
+        if visited >= budget {
+            break;
+        }
+        visited += 1;
+        let Ok(exits) = bf_args
+            .world_state
+            .retrieve_property(&perms, &room, exits_sym)
+        else {
+            continue;
+        };
+        let Variant::List(exits) = exits.variant() else {
+            continue;
+        };
+        for exit in exits.iter() {
+            let Variant::Obj(exit) = exit.variant() else {
+                continue;
+            };
+            let Ok(dest) = bf_args
+                .world_state
+                .retrieve_property(&perms, exit, dest_sym)
+            else {
+                continue;
+            };
+            let Variant::Obj(dest) = dest.variant() else {
+                continue;
+            };
+            if !seen.insert(dest.clone()) {
+                continue;
+            }
+            reached_by.insert(dest.clone(), (room.clone(), exit.clone()));
+            if dest == to {
+                found = true;
+                break 'search;
+            }
+            queue.push_back(dest.clone());
+        }
+    }
+    bf_args.exec_state.tick_count += visited;
+
+    if !found {
+        return Ok(Ret(v_int(0)));
+    }
+    let mut path = vec![];
+    let mut room = to.clone();
+    while let Some((previous, exit)) = reached_by.remove(&room) {
+        path.push(v_obj(exit));
+        room = previous;
+    }
+    path.reverse();
+    Ok(Ret(v_list(&path)))
+}
+bf_declare!(shortest_path, bf_shortest_path);
+
 fn bf_set_player_flag(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if bf_args.args.len() != 2 {
         return Err(BfErr::Code(E_ARGS));
@@ -706,6 +1050,12 @@ pub(crate) fn register_bf_objects(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("valid")] = Box::new(BfValid {});
     builtins[offset_for_builtin("verbs")] = Box::new(BfVerbs {});
     builtins[offset_for_builtin("properties")] = Box::new(BfProperties {});
+    builtins[offset_for_builtin("locate_by_name")] = Box::new(BfLocateByName {});
+    builtins[offset_for_builtin("find_verb")] = Box::new(BfFindVerb {});
+    builtins[offset_for_builtin("find_property")] = Box::new(BfFindProperty {});
+    builtins[offset_for_builtin("search_text")] = Box::new(BfSearchText {});
+    builtins[offset_for_builtin("shortest_path")] = Box::new(BfShortestPath {});
+    builtins[offset_for_builtin("clone_object")] = Box::new(BfCloneObject {});
     builtins[offset_for_builtin("parent")] = Box::new(BfParent {});
     builtins[offset_for_builtin("children")] = Box::new(BfChildren {});
     builtins[offset_for_builtin("move")] = Box::new(BfMove {});
@@ -713,5 +1063,7 @@ pub(crate) fn register_bf_objects(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("set_player_flag")] = Box::new(BfSetPlayerFlag {});
     builtins[offset_for_builtin("recycle")] = Box::new(BfRecycle {});
     builtins[offset_for_builtin("max_object")] = Box::new(BfMaxObject {});
+    builtins[offset_for_builtin("renumber")] = Box::new(BfRenumber {});
+    builtins[offset_for_builtin("reset_max_object")] = Box::new(BfResetMaxObject {});
     builtins[offset_for_builtin("players")] = Box::new(BfPlayers {});
 }
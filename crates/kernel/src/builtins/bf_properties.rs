@@ -249,6 +249,62 @@ fn bf_delete_property(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(delete_property, bf_delete_property);
 
+// get_properties (obj <object>, list <prop-names>)              => list
+// The values of the named properties, in order, as if each were read in turn, but with one
+// permission lookup for the lot.
+fn bf_get_properties(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(Code(E_ARGS));
+    }
+    let Variant::Obj(obj) = bf_args.args[0].variant() else {
+        return Err(Code(E_TYPE));
+    };
+    let Variant::List(prop_names) = bf_args.args[1].variant() else {
+        return Err(Code(E_TYPE));
+    };
+    let mut pnames = Vec::with_capacity(prop_names.len());
+    for prop_name in prop_names.iter() {
+        let Variant::Str(prop_name) = prop_name.variant() else {
+            return Err(Code(E_TYPE));
+        };
+        pnames.push(Symbol::mk_case_insensitive(prop_name.as_string()));
+    }
+    let values = bf_args
+        .world_state
+        .retrieve_properties(&bf_args.task_perms_who(), obj, &pnames)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_list(&values)))
+}
+bf_declare!(get_properties, bf_get_properties);
+
+// set_properties (obj <object>, map <prop-name -> value>)              => none
+// Sets each of the properties named in the map. Ordinary properties are all checked before any are
+// written, so a missing or unwritable one leaves the object as it was.
+fn bf_set_properties(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(Code(E_ARGS));
+    }
+    let Variant::Obj(obj) = bf_args.args[0].variant() else {
+        return Err(Code(E_TYPE));
+    };
+    let Variant::Map(values) = bf_args.args[1].variant() else {
+        return Err(Code(E_TYPE));
+    };
+    let mut pvalues = vec![];
+    for (prop_name, value) in values.iter() {
+        let Variant::Str(prop_name) = prop_name.variant() else {
+            return Err(Code(E_TYPE));
+        };
+        pvalues.push((Symbol::mk_case_insensitive(prop_name.as_string()), value));
+    }
+    bf_args
+        .world_state
+        .update_properties(&bf_args.task_perms_who(), obj, &pvalues)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_none()))
+}
+bf_declare!(set_properties, bf_set_properties);
+
 pub(crate) fn register_bf_properties(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("property_info")] = Box::new(BfPropertyInfo {});
     builtins[offset_for_builtin("set_property_info")] = Box::new(BfSetPropertyInfo {});
@@ -256,4 +312,6 @@ pub(crate) fn register_bf_properties(builtins: &mut [Box<dyn BuiltinFunction>])
     builtins[offset_for_builtin("clear_property")] = Box::new(BfSetClearProperty {});
     builtins[offset_for_builtin("add_property")] = Box::new(BfAddProperty {});
     builtins[offset_for_builtin("delete_property")] = Box::new(BfDeleteProperty {});
+    builtins[offset_for_builtin("get_properties")] = Box::new(BfGetProperties {});
+    builtins[offset_for_builtin("set_properties")] = Box::new(BfSetProperties {});
 }
@@ -12,30 +12,52 @@
 //
 
 use std::io::Read;
-use std::time::{Duration, SystemTime};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use chrono::{DateTime, Local, TimeZone};
-use chrono_tz::{OffsetName, Tz};
+use chrono::format::{Item, StrftimeItems};
+use chrono::TimeZone;
+use chrono_tz::Tz;
 use iana_time_zone::get_timezone;
+use lazy_static::lazy_static;
 use tracing::{error, info, warn};
 
 use moor_compiler::compile;
 use moor_compiler::{offset_for_builtin, ArgCount, ArgType, Builtin, BUILTINS};
-use moor_values::model::{ObjFlag, WorldStateError};
-use moor_values::tasks::NarrativeEvent;
+use moor_values::model::{CacheStats, ObjFlag, WorldStateError, COMPILE_OPTIONS_KEY};
+use moor_values::tasks::{
+    AbortLimitReason, NarrativeEvent, Presentation, SchedulerError, CONTENT_TYPE_GMCP,
+    CONTENT_TYPE_OUT_OF_BAND, CONTENT_TYPE_PROMPT,
+};
 use moor_values::Error::{E_ARGS, E_INVARG, E_INVIND, E_PERM, E_TYPE};
 use moor_values::Variant;
-use moor_values::{v_bool, v_int, v_list, v_none, v_obj, v_str, v_string, Var};
+use moor_values::{
+    v_bool, v_err, v_float, v_int, v_list, v_map, v_none, v_obj, v_str, v_string, Obj, Var,
+};
 use moor_values::{v_list_iter, Error};
 use moor_values::{Sequence, Symbol};
 
 use crate::bf_declare;
 use crate::builtins::BfRet::{Ret, VmInstr};
-use crate::builtins::{world_state_bf_err, BfCallState, BfErr, BfRet, BuiltinFunction};
-use crate::vm::ExecutionResult;
+use crate::builtins::{
+    world_state_bf_err, BfCallState, BfErr, BfRet, BuiltinFunction, BuiltinRegistry,
+};
+use crate::config::{compile_options_map, with_compile_options};
+use crate::tasks::breakpoints::Breakpoint;
+use crate::tasks::finished::TaskOrigin;
+use crate::tasks::owner_task_limit;
+use crate::tasks::sessions::{WebhookFilter, WEBHOOK_EVENTS};
+use crate::tasks::verb_wrappers::VerbWrapper;
+use crate::tasks::vm_host::VmHost;
+use crate::vm::{ExecutionResult, InputRequest, VMHostResponse};
 use moor_values::tasks::TaskId;
 use moor_values::VarType::TYPE_STR;
 
+lazy_static! {
+    /// The point monotonic_time_ns() counts from.
+    static ref MONOTONIC_EPOCH: Instant = Instant::now();
+}
+
 fn bf_noop(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     error!(
         "Builtin function {} is not implemented, called with arguments: ({:?})",
@@ -49,20 +71,20 @@ fn bf_noop(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(noop, bf_noop);
 
+/// notify(player, string [, no_flush [, no_newline]])
+/// notify(player, value [, content_type [, no_newline]]) with `rich_notify`
+/// Sends output to `player`. Output is never dropped, so `no_flush` is accepted for compatibility
+/// and ignored. With `no_newline`, the text is sent as a prompt, with no line break after it; a
+/// prompt can't also be given a `content_type`, which raises `E_INVARG`.
 fn bf_notify(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    // If in non rich-mode `notify` can only send text.
-    // Otherwise, it can send any value, and it's up to the host/client to interpret it.
-    if !bf_args.config.rich_notify {
-        if bf_args.args.len() != 2 {
-            return Err(BfErr::Code(E_ARGS));
-        }
-        if bf_args.args[1].type_code() != TYPE_STR {
-            return Err(BfErr::Code(E_TYPE));
-        }
+    if bf_args.args.len() < 2 || bf_args.args.len() > 4 {
+        return Err(BfErr::Code(E_ARGS));
     }
 
-    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
-        return Err(BfErr::Code(E_ARGS));
+    // If in non rich-mode `notify` can only send text.
+    // Otherwise, it can send any value, and it's up to the host/client to interpret it.
+    if !bf_args.config.rich_notify && bf_args.args[1].type_code() != TYPE_STR {
+        return Err(BfErr::Code(E_TYPE));
     }
 
     let player = bf_args.args[0].variant();
@@ -76,13 +98,24 @@ fn bf_notify(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         .check_obj_owner_perms(player)
         .map_err(world_state_bf_err)?;
 
-    let content_type = if bf_args.config.rich_notify && bf_args.args.len() == 3 {
-        let Variant::Str(content_type) = bf_args.args[2].variant() else {
+    let no_newline = bf_args.args.len() == 4 && bf_args.args[3].is_true();
+    let content_type = if no_newline {
+        if bf_args.args[1].type_code() != TYPE_STR {
             return Err(BfErr::Code(E_TYPE));
-        };
-        Some(Symbol::mk_case_insensitive(
-            content_type.as_string().as_str(),
-        ))
+        }
+        // A prompt has its own content type, so it can't have the caller's as well.
+        if bf_args.config.rich_notify && bf_args.args[2].type_code() == TYPE_STR {
+            return Err(BfErr::Code(E_INVARG));
+        }
+        Some(Symbol::mk(CONTENT_TYPE_PROMPT))
+    } else if bf_args.config.rich_notify && bf_args.args.len() >= 3 {
+        // A non-string is taken to be `no_flush`, as from a core written for LambdaMOO.
+        match bf_args.args[2].variant() {
+            Variant::Str(content_type) => Some(Symbol::mk_case_insensitive(
+                content_type.as_string().as_str(),
+            )),
+            _ => None,
+        }
     } else {
         None
     };
@@ -98,6 +131,166 @@ fn bf_notify(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(notify, bf_notify);
 
+/// notify_oob(player, line)
+/// Send `line` to `player` as an out-of-band line (e.g. an MCP message), which hosts pass to the
+/// client verbatim instead of quoting it as they do in-band lines that look out-of-band.
+fn bf_notify_oob(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if bf_args.args[1].type_code() != TYPE_STR {
+        return Err(BfErr::Code(E_TYPE));
+    }
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    task_perms
+        .check_obj_owner_perms(player)
+        .map_err(world_state_bf_err)?;
+
+    let event = NarrativeEvent::notify(
+        bf_args.exec_state.this(),
+        bf_args.args[1].clone(),
+        Some(Symbol::mk(CONTENT_TYPE_OUT_OF_BAND)),
+    );
+    bf_args.task_scheduler_client.notify(player.clone(), event);
+
+    Ok(Ret(v_int(1)))
+}
+bf_declare!(notify_oob, bf_notify_oob);
+
+/// notify_gmcp(player, package [, data])
+/// Send a GMCP message to `player`, if their client has negotiated GMCP. Hosts encode `data` as
+/// JSON: maps (with string keys) become JSON objects, and lists become arrays.
+fn bf_notify_gmcp(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if bf_args.args[1].type_code() != TYPE_STR {
+        return Err(BfErr::Code(E_TYPE));
+    }
+    let data = if bf_args.args.len() == 3 {
+        bf_args.args[2].clone()
+    } else {
+        v_none()
+    };
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    task_perms
+        .check_obj_owner_perms(player)
+        .map_err(world_state_bf_err)?;
+
+    let event = NarrativeEvent::notify(
+        bf_args.exec_state.this(),
+        v_list(&[bf_args.args[1].clone(), data]),
+        Some(Symbol::mk(CONTENT_TYPE_GMCP)),
+    );
+    bf_args.task_scheduler_client.notify(player.clone(), event);
+
+    Ok(Ret(v_int(1)))
+}
+bf_declare!(notify_gmcp, bf_notify_gmcp);
+
+/// present(player, id, content_type, target, content [, attributes])
+/// Show `content` to `player` somewhere other than the narrative stream (`target` names where,
+/// e.g. "window" or "right-dock"), until it's replaced by another presentation with the same `id`
+/// or withdrawn with `unpresent()`. `attributes` is a map, or a list of `{name, value}` pairs, of
+/// strings for the client, e.g. a title.
+fn bf_present(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() < 5 || bf_args.args.len() > 6 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let mut strings = vec![];
+    for arg in bf_args.args.iter().skip(1).take(4) {
+        let Variant::Str(s) = arg.variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        strings.push(s.as_string().clone());
+    }
+    let [id, content_type, target, content]: [String; 4] = strings.try_into().unwrap();
+
+    let mut attributes = vec![];
+    if bf_args.args.len() == 6 {
+        let pairs: Vec<(Var, Var)> = match bf_args.args[5].variant() {
+            Variant::Map(m) => m.iter().collect(),
+            Variant::List(l) => {
+                let mut pairs = vec![];
+                for pair in l.iter() {
+                    let Variant::List(pair) = pair.variant() else {
+                        return Err(BfErr::Code(E_TYPE));
+                    };
+                    if pair.len() != 2 {
+                        return Err(BfErr::Code(E_INVARG));
+                    }
+                    pairs.push((pair[0].clone(), pair[1].clone()));
+                }
+                pairs
+            }
+            _ => return Err(BfErr::Code(E_TYPE)),
+        };
+        for (name, value) in pairs {
+            let (Variant::Str(name), Variant::Str(value)) = (name.variant(), value.variant())
+            else {
+                return Err(BfErr::Code(E_TYPE));
+            };
+            attributes.push((name.as_string().clone(), value.as_string().clone()));
+        }
+    }
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    task_perms
+        .check_obj_owner_perms(player)
+        .map_err(world_state_bf_err)?;
+
+    let event = NarrativeEvent::present(
+        bf_args.exec_state.this(),
+        Presentation {
+            id,
+            content_type,
+            content,
+            target,
+            attributes,
+        },
+    );
+    bf_args.task_scheduler_client.notify(player.clone(), event);
+
+    Ok(Ret(v_none()))
+}
+bf_declare!(present, bf_present);
+
+/// unpresent(player, id)
+/// Withdraw the presentation `id` from `player`.
+fn bf_unpresent(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Str(id) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    task_perms
+        .check_obj_owner_perms(player)
+        .map_err(world_state_bf_err)?;
+
+    let event = NarrativeEvent::unpresent(bf_args.exec_state.this(), id.as_string().clone());
+    bf_args.task_scheduler_client.notify(player.clone(), event);
+
+    Ok(Ret(v_none()))
+}
+bf_declare!(unpresent, bf_unpresent);
+
 fn bf_connected_players(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if !bf_args.args.is_empty() {
         return Err(BfErr::Code(E_ARGS));
@@ -196,6 +389,84 @@ fn bf_task_id(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(task_id, bf_task_id);
 
+/// task_local()
+/// Returns the value set for the current task by set_task_local(), or an empty map if none has
+/// been. Wizard only, as in ToastStunt.
+fn bf_task_local(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    Ok(Ret(bf_args.exec_state.task_local.clone()))
+}
+bf_declare!(task_local, bf_task_local);
+
+/// set_task_local(value)
+/// Associates `value` with the current task, for the rest of its life (including across suspends
+/// and reads), for task_local() to return. Nothing is written to the database, so it can't cause
+/// transaction conflicts. Wizard only, as in ToastStunt.
+fn bf_set_task_local(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    bf_args.exec_state.task_local = bf_args.args[0].clone();
+    Ok(Ret(v_none()))
+}
+bf_declare!(set_task_local, bf_set_task_local);
+
+fn bf_begin_atomic(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  begin_atomic()   => int
+    //
+    // Opens an atomic section, returning how many are now open (they nest). Until the matching
+    // end_atomic(), suspend() and read() raise E_PERM, and an error which goes uncaught rolls
+    // back everything the task has done rather than committing it. Wizards only.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    bf_args.exec_state.atomic_depth += 1;
+    Ok(Ret(v_int(bf_args.exec_state.atomic_depth as i64)))
+}
+bf_declare!(begin_atomic, bf_begin_atomic);
+
+fn bf_end_atomic(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  end_atomic()   => int
+    //
+    // Closes the innermost atomic section, returning how many are still open. E_INVARG if there
+    // isn't one open. Wizards only.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.exec_state.atomic_depth == 0 {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    bf_args.exec_state.atomic_depth -= 1;
+    Ok(Ret(v_int(bf_args.exec_state.atomic_depth as i64)))
+}
+bf_declare!(end_atomic, bf_end_atomic);
+
 fn bf_idle_seconds(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if bf_args.args.len() != 1 {
         return Err(BfErr::Code(E_ARGS));
@@ -261,118 +532,463 @@ fn bf_connection_name(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(connection_name, bf_connection_name);
 
-fn bf_shutdown(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() > 1 {
-        return Err(BfErr::Code(E_ARGS));
-    }
-    let msg = if bf_args.args.is_empty() {
-        None
-    } else {
-        let Variant::Str(msg) = bf_args.args[0].variant() else {
-            return Err(BfErr::Code(E_TYPE));
-        };
-        Some(msg.as_string().clone())
-    };
-
+/// connection_name_lookup(player [, rewrite])
+/// Looks up the hostname of `player`'s connection now, waiting for the answer, rather than using
+/// whatever the background lookup has found. If `rewrite` is true, `connection_name()` reports
+/// the result from then on. Wizard only. Raises E_INVARG if `player` isn't connected or the
+/// address has no name.
+fn bf_connection_name_lookup(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     bf_args
         .task_perms()
         .map_err(world_state_bf_err)?
         .check_wizard()
         .map_err(world_state_bf_err)?;
 
-    bf_args.task_scheduler_client.shutdown(msg);
-
-    Ok(Ret(v_none()))
-}
-bf_declare!(shutdown, bf_shutdown);
-
-fn bf_time(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if !bf_args.args.is_empty() {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
         return Err(BfErr::Code(E_ARGS));
     }
-    Ok(Ret(v_int(
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64,
-    )))
-}
-bf_declare!(time, bf_time);
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let rewrite = bf_args.args.len() == 2 && bf_args.args[1].is_true();
 
-fn bf_ctime(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() > 1 {
-        return Err(BfErr::Code(E_ARGS));
-    }
-    let time = if bf_args.args.is_empty() {
-        SystemTime::now()
-    } else {
-        let Variant::Int(time) = bf_args.args[0].variant() else {
-            return Err(BfErr::Code(E_TYPE));
-        };
-        if *time < 0 {
-            SystemTime::UNIX_EPOCH - Duration::from_secs(time.unsigned_abs())
-        } else {
-            SystemTime::UNIX_EPOCH + Duration::from_secs(time.unsigned_abs())
-        }
+    let Ok(name) = bf_args
+        .session
+        .connection_name_lookup(player.clone(), rewrite)
+    else {
+        return Err(BfErr::Code(E_INVARG));
     };
+    Ok(Ret(v_string(name)))
+}
+bf_declare!(connection_name_lookup, bf_connection_name_lookup);
 
-    let date_time: DateTime<Local> = chrono::DateTime::from(time);
-    let tz_str = get_timezone().unwrap();
-    let tz: Tz = tz_str.parse().unwrap();
-    let offset = tz.offset_from_local_date(&date_time.date_naive()).unwrap();
-    let abbreviation = offset.abbreviation().unwrap_or("??");
-    let datetime_str = format!(
-        "{} {}",
-        date_time.format("%a %b %d %H:%M:%S %Y"),
-        abbreviation
-    );
+/*
+Syntax:  connection_attributes (obj <player>)   => map
 
-    Ok(Ret(v_string(datetime_str.to_string())))
-}
-bf_declare!(ctime, bf_ctime);
-fn bf_raise(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    // Syntax:  raise (<code> [, str <message> [, <value>]])   => none
-    //
-    // Raises <code> as an error in the same way as other MOO expressions, statements, and functions do.  <Message>, which defaults to the value of `tostr(<code>)',
-    // and <value>, which defaults to zero, are made available to any `try'-`except' statements that catch the error.  If the error is not caught, then <message> will
-    // appear on the first line of the traceback printed to the user.
-    if bf_args.args.is_empty() || bf_args.args.len() > 3 {
+Returns a map of the attributes the host has reported for the connection being used by the given
+player, such as the client capabilities it negotiated ("ansi", "xterm256", "truecolor", "mxp").
+Permissions are as for connection_name().
+ */
+fn bf_connection_attributes(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
         return Err(BfErr::Code(E_ARGS));
     }
 
-    let Variant::Err(err) = bf_args.args[0].variant() else {
-        return Err(BfErr::Code(E_ARGS));
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
     };
 
-    let msg = if bf_args.args.len() > 1 {
-        let Variant::Str(msg) = bf_args.args[1].variant() else {
-            return Err(BfErr::Code(E_TYPE));
-        };
-        Some(msg.as_string().clone())
-    } else {
-        None
-    };
+    let caller = bf_args.caller_perms();
+    if !bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_is_wizard()
+        .map_err(world_state_bf_err)?
+        && caller != *player
+    {
+        return Err(BfErr::Code(E_PERM));
+    }
 
-    let value = if bf_args.args.len() > 2 {
-        Some(bf_args.args[2].clone())
-    } else {
-        None
+    let Ok(attributes) = bf_args.session.connection_attributes(player.clone()) else {
+        return Err(BfErr::Code(E_INVARG));
     };
 
-    Err(BfErr::Raise(*err, msg, value))
+    Ok(Ret(attributes))
 }
+bf_declare!(connection_attributes, bf_connection_attributes);
 
-bf_declare!(raise, bf_raise);
+/// Check that `player`, the first argument, is a connection the task may see the options of: its
+/// own, unless it's a wizard's.
+fn connection_option_player(bf_args: &mut BfCallState<'_>) -> Result<Obj, BfErr> {
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let player = player.clone();
 
-fn bf_server_version(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if !bf_args.args.is_empty() {
-        return Err(BfErr::Code(E_ARGS));
-    }
-    // TODO: Support server version flag passed down the pipe, rather than hardcoded
-    //   This is a placeholder for now, should be set by the server on startup. But right now
-    //   there isn't a good place to stash this other than WorldState. I intend on refactoring the
-    //   signature for BF invocations, and when I do this, I'll get additional metadata on there.
-    Ok(Ret(v_string("0.0.1".to_string())))
+    let caller = bf_args.caller_perms();
+    if !bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_is_wizard()
+        .map_err(world_state_bf_err)?
+        && caller != player
+    {
+        return Err(BfErr::Code(E_PERM));
+    }
+    Ok(player)
+}
+
+/*
+Syntax:  set_connection_option (obj <conn>, str <option>, <value>)   => none
+
+Sets the option of the connection <conn>. The options are:
+
+  output-limit     the most lines of output the host holds for the connection while its client
+                   isn't reading them (a positive integer)
+  output-overflow  what happens to output beyond that: "drop-oldest" (as in LambdaMOO, and the
+                   default), "drop-newest", or "disconnect"
+
+Raises E_INVARG if <conn> isn't connected, or <option> isn't one of these, or <value> isn't valid
+for it. Permissions are as for connection_name().
+ */
+fn bf_set_connection_option(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 3 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let player = connection_option_player(bf_args)?;
+    let Variant::Str(option) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let option = Symbol::mk(option.as_string());
+    let value = bf_args.args[2].clone();
+
+    if bf_args
+        .session
+        .set_connection_option(player, option, value)
+        .is_err()
+    {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    Ok(Ret(v_none()))
+}
+bf_declare!(set_connection_option, bf_set_connection_option);
+
+/*
+Syntax:  connection_option (obj <conn>, str <name>)   => value
+
+Returns the current value of the option <name> of the connection <conn>. Raises E_INVARG if <conn>
+isn't connected or there's no such option. Permissions are as for connection_name().
+ */
+fn bf_connection_option(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let player = connection_option_player(bf_args)?;
+    let Variant::Str(name) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let name = Symbol::mk(name.as_string());
+
+    let Ok(options) = bf_args.session.connection_options(player) else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    let Some((_, value)) = options.into_iter().find(|(option, _)| *option == name) else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    Ok(Ret(value))
+}
+bf_declare!(connection_option, bf_connection_option);
+
+/*
+Syntax:  connection_options (obj <conn>)   => list
+
+Returns a list of {<name>, <value>} pairs for the options of the connection <conn>, as in LambdaMOO.
+Raises E_INVARG if <conn> isn't connected. Permissions are as for connection_name().
+ */
+fn bf_connection_options(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let player = connection_option_player(bf_args)?;
+
+    let Ok(options) = bf_args.session.connection_options(player) else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    let options = options
+        .into_iter()
+        .map(|(name, value)| v_list(&[v_str(name.as_str()), value]));
+    Ok(Ret(v_list_iter(options)))
+}
+bf_declare!(connection_options, bf_connection_options);
+
+fn bf_shutdown(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let msg = if bf_args.args.is_empty() {
+        None
+    } else {
+        let Variant::Str(msg) = bf_args.args[0].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        Some(msg.as_string().clone())
+    };
+
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    bf_args.task_scheduler_client.shutdown(msg);
+
+    Ok(Ret(v_none()))
+}
+bf_declare!(shutdown, bf_shutdown);
+
+fn bf_time(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    Ok(Ret(bf_args.exec_state.replay.observe(|| {
+        v_int(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+        )
+    })))
+}
+bf_declare!(time, bf_time);
+
+/// The time zone to format times in: the one named, if given (E_INVARG if it isn't a known IANA
+/// name), else `$server_options.default_timezone`, else the daemon's local time zone, else UTC.
+fn timezone_for(bf_args: &BfCallState<'_>, name: Option<&Var>) -> Result<Tz, BfErr> {
+    if let Some(name) = name {
+        let Variant::Str(name) = name.variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        return name.as_string().parse().map_err(|_| BfErr::Code(E_INVARG));
+    }
+    let default_timezone = bf_args
+        .task_scheduler_client
+        .server_options()
+        .default_timezone;
+    Ok(default_timezone
+        .or_else(|| get_timezone().ok())
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC))
+}
+
+/// The time given as argument `index` to ctime() or strftime(), or now if there isn't one.
+fn time_arg(bf_args: &mut BfCallState<'_>, index: usize) -> Result<i64, BfErr> {
+    let time = if bf_args.args.len() <= index {
+        bf_args.exec_state.replay.observe(|| {
+            v_int(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs() as i64,
+            )
+        })
+    } else {
+        bf_args.args[index].clone()
+    };
+    let Variant::Int(time) = time.variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    Ok(*time)
+}
+
+fn bf_ctime(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  ctime([int time [, str timezone]])   => str
+    //
+    // Formats `time` (default now) as e.g. "Mon Aug 13 19:13:20 1990 PDT", in `timezone` (an IANA
+    // name such as "America/Vancouver"), or the server's default time zone.
+    if bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let time = time_arg(bf_args, 0)?;
+    let tz_name = (bf_args.args.len() > 1).then(|| bf_args.args[1].clone());
+    let tz = timezone_for(bf_args, tz_name.as_ref())?;
+    let Some(date_time) = tz.timestamp_opt(time, 0).single() else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+
+    Ok(Ret(v_string(
+        date_time.format("%a %b %d %H:%M:%S %Y %Z").to_string(),
+    )))
+}
+bf_declare!(ctime, bf_ctime);
+fn bf_strftime(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  strftime(str format [, int time [, str timezone]])   => str
+    //
+    // Formats `time` (default now) with the strftime-style `format`, in `timezone` or the server's
+    // default time zone. Offsets and abbreviations (%z, %Z) follow daylight saving time as it
+    // applied at `time`. E_INVARG for a format with an unknown % directive.
+    if bf_args.args.is_empty() || bf_args.args.len() > 3 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(format) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let format = format.as_string().clone();
+    let items: Vec<_> = StrftimeItems::new(&format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let time = time_arg(bf_args, 1)?;
+    let tz_name = (bf_args.args.len() > 2).then(|| bf_args.args[2].clone());
+    let tz = timezone_for(bf_args, tz_name.as_ref())?;
+    let Some(date_time) = tz.timestamp_opt(time, 0).single() else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+
+    Ok(Ret(v_string(
+        date_time.format_with_items(items.into_iter()).to_string(),
+    )))
+}
+bf_declare!(strftime, bf_strftime);
+
+/// The most iterations bench() will run.
+const BENCH_MAX_ITERATIONS: i64 = 100_000;
+/// The ticks each iteration of bench() may take.
+const BENCH_MAX_TICKS: usize = 1_000_000;
+/// The longest a whole bench() run may take. Iterations still to go when it runs out are skipped.
+const BENCH_MAX_TIME: Duration = Duration::from_secs(10);
+
+fn bf_monotonic_time_ns(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  monotonic_time_ns()   => int
+    //
+    // Returns nanoseconds from a clock which only goes forward and isn't affected by changes to the
+    // system time. It counts from an arbitrary point, so only the difference between two calls
+    // means anything.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    Ok(Ret(bf_args.exec_state.replay.observe(|| {
+        v_int(MONOTONIC_EPOCH.elapsed().as_nanos() as i64)
+    })))
+}
+bf_declare!(monotonic_time_ns, bf_monotonic_time_ns);
+
+fn bf_bench(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  bench(str code, int iterations)   => map
+    //
+    // Compiles `code` as eval() would, then runs it `iterations` times (at most 100000), each run
+    // with a budget of its own of a million ticks rather than the task's, timing each with the
+    // monotonic clock. Returns ["iterations" -> n, "total_ns" -> .., "min_ns" -> .., "max_ns" -> ..,
+    // "mean_ns" -> .., "median_ns" -> ..]. The whole run stops after 10 seconds, so `iterations`
+    // may be fewer than asked for. Runs share the task's transaction, so changes they make are
+    // kept. Errors raised by `code` are raised from bench(). Wizards only.
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+    let (Variant::Str(code), Variant::Int(iterations)) =
+        (bf_args.args[0].variant(), bf_args.args[1].variant())
+    else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if !(1..=BENCH_MAX_ITERATIONS).contains(iterations) {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let program = compile(code.as_string(), bf_args.config.compile_options())
+        .map_err(|e| BfErr::Raise(E_INVARG, Some(e.to_string()), None))?;
+    let iterations = *iterations as usize;
+
+    let task_id = bf_args.exec_state.task_id;
+    let perms = bf_args.task_perms_who();
+    let max_stack_depth = bf_args
+        .task_scheduler_client
+        .server_options()
+        .max_stack_depth;
+    let builtin_registry = Arc::new(BuiltinRegistry::new());
+    let started = Instant::now();
+    let mut timings = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let Some(time_left) = BENCH_MAX_TIME.checked_sub(started.elapsed()) else {
+            break;
+        };
+        let mut host = VmHost::new(task_id, max_stack_depth, BENCH_MAX_TICKS, time_left);
+        host.start_eval(task_id, &perms, program.clone(), bf_args.world_state);
+        let run_started = Instant::now();
+        loop {
+            match host.exec_interpreter(
+                task_id,
+                bf_args.world_state,
+                bf_args.task_scheduler_client.clone(),
+                bf_args.session.clone(),
+                builtin_registry.clone(),
+                bf_args.config.clone(),
+            ) {
+                VMHostResponse::ContinueOk => continue,
+                VMHostResponse::CompleteSuccess(_) => break,
+                VMHostResponse::CompleteException(e) => {
+                    return Err(BfErr::Raise(e.code, Some(e.msg), Some(e.value)));
+                }
+                VMHostResponse::RollbackRetry => return Err(BfErr::Rollback),
+                _ => {
+                    return Err(BfErr::Raise(
+                        E_INVARG,
+                        Some("benchmarked code did not run to completion".to_string()),
+                        None,
+                    ));
+                }
+            }
+        }
+        timings.push(run_started.elapsed().as_nanos() as i64);
+    }
+
+    let total: i64 = timings.iter().sum();
+    let count = timings.len() as i64;
+    timings.sort_unstable();
+    let stats = [
+        ("iterations", count),
+        ("total_ns", total),
+        ("min_ns", timings.first().copied().unwrap_or(0)),
+        ("max_ns", timings.last().copied().unwrap_or(0)),
+        ("mean_ns", if count > 0 { total / count } else { 0 }),
+        (
+            "median_ns",
+            timings.get(timings.len() / 2).copied().unwrap_or(0),
+        ),
+    ];
+    let stats: Vec<_> = stats
+        .into_iter()
+        .map(|(name, value)| (v_str(name), v_int(value)))
+        .collect();
+    Ok(Ret(v_map(&stats)))
+}
+bf_declare!(bench, bf_bench);
+
+fn bf_raise(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  raise (<code> [, str <message> [, <value>]])   => none
+    //
+    // Raises <code> as an error in the same way as other MOO expressions, statements, and functions do.  <Message>, which defaults to the value of `tostr(<code>)',
+    // and <value>, which defaults to zero, are made available to any `try'-`except' statements that catch the error.  If the error is not caught, then <message> will
+    // appear on the first line of the traceback printed to the user.
+    if bf_args.args.is_empty() || bf_args.args.len() > 3 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let Variant::Err(err) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_ARGS));
+    };
+
+    let msg = if bf_args.args.len() > 1 {
+        let Variant::Str(msg) = bf_args.args[1].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        Some(msg.as_string().clone())
+    } else {
+        None
+    };
+
+    let value = if bf_args.args.len() > 2 {
+        Some(bf_args.args[2].clone())
+    } else {
+        None
+    };
+
+    Err(BfErr::Raise(*err, msg, value))
+}
+
+bf_declare!(raise, bf_raise);
+
+fn bf_server_version(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    // TODO: Support server version flag passed down the pipe, rather than hardcoded
+    //   This is a placeholder for now, should be set by the server on startup. But right now
+    //   there isn't a good place to stash this other than WorldState. I intend on refactoring the
+    //   signature for BF invocations, and when I do this, I'll get additional metadata on there.
+    Ok(Ret(v_string("0.0.1".to_string())))
 }
 bf_declare!(server_version, bf_server_version);
 
@@ -399,6 +1015,22 @@ fn bf_suspend(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         Some(Duration::from_secs_f64(seconds))
     };
 
+    if bf_args.exec_state.atomic_depth > 0 {
+        return Err(BfErr::Raise(
+            E_PERM,
+            Some("suspend() inside an atomic section".to_string()),
+            None,
+        ));
+    }
+
+    let owner = bf_args.task_perms_who();
+    let player = bf_args.exec_state.top().player.clone();
+    let owner_limit = owner_task_limit(bf_args.world_state, &owner);
+    bf_args
+        .task_scheduler_client
+        .check_task_quota(owner, player, owner_limit, false)
+        .map_err(BfErr::Code)?;
+
     Ok(VmInstr(ExecutionResult::TaskSuspend(seconds)))
 }
 bf_declare!(suspend, bf_suspend);
@@ -427,10 +1059,55 @@ fn bf_read(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         }
     }
 
-    Ok(VmInstr(ExecutionResult::TaskNeedInput))
+    if bf_args.exec_state.atomic_depth > 0 {
+        return Err(BfErr::Raise(
+            E_PERM,
+            Some("read() inside an atomic section".to_string()),
+            None,
+        ));
+    }
+
+    Ok(VmInstr(ExecutionResult::TaskNeedInput(InputRequest::Line)))
 }
 bf_declare!(read, bf_read);
 
+/// Read every line the player sends up to one equal to `terminator` (by default "."), as for
+/// `.program`, returning them as a list. The lines are gathered by the scheduler and handed over
+/// all at once, rather than waking the task for each. If there are more than `max_lines` of them
+/// (or more than the server allows), the rest are discarded, and E_QUOTA is returned instead.
+fn bf_read_lines(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let terminator = match bf_args.args.first().map(|a| a.variant()) {
+        None => ".".to_string(),
+        Some(Variant::Str(s)) => s.as_string().clone(),
+        Some(_) => return Err(BfErr::Code(E_TYPE)),
+    };
+    let max_lines = match bf_args.args.get(1).map(|a| a.variant()) {
+        None => None,
+        Some(Variant::Int(n)) if *n > 0 => Some(*n as usize),
+        Some(Variant::Int(_)) => return Err(BfErr::Code(E_INVARG)),
+        Some(_) => return Err(BfErr::Code(E_TYPE)),
+    };
+
+    if bf_args.exec_state.atomic_depth > 0 {
+        return Err(BfErr::Raise(
+            E_PERM,
+            Some("read_lines() inside an atomic section".to_string()),
+            None,
+        ));
+    }
+
+    Ok(VmInstr(ExecutionResult::TaskNeedInput(
+        InputRequest::Lines {
+            terminator,
+            max_lines,
+        },
+    )))
+}
+bf_declare!(read_lines, bf_read_lines);
+
 fn bf_queued_tasks(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if !bf_args.args.is_empty() {
         return Err(BfErr::Code(E_ARGS));
@@ -552,7 +1229,7 @@ fn bf_kill_task(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 bf_declare!(kill_task, bf_kill_task);
 
 fn bf_resume(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() < 2 {
+    if bf_args.args.is_empty() {
         return Err(BfErr::Code(E_ARGS));
     }
 
@@ -586,63 +1263,586 @@ fn bf_resume(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(resume, bf_resume);
 
-fn bf_ticks_left(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    // Syntax:  ticks_left()   => int
+fn bf_debug_task(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  debug_task(<task-id> [, <enable>])   => none
     //
-    // Returns the number of ticks left in the current time slice.
-    if !bf_args.args.is_empty() {
+    // Puts the suspended task <task-id> into single-step mode (or takes it out, if <enable> is
+    // false). Each time the task is resumed it executes a single opcode, notifies the calling
+    // player of its verb, line, next opcode and top of stack, and suspends again.
+    // Step with resume(<task-id>), abort with kill_task(<task-id>), and continue by disabling
+    // stepping and then resuming.
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
         return Err(BfErr::Code(E_ARGS));
     }
 
-    let ticks_left = bf_args
-        .exec_state
-        .max_ticks
-        .saturating_sub(bf_args.exec_state.tick_count);
-
-    Ok(Ret(v_int(ticks_left as i64)))
-}
-bf_declare!(ticks_left, bf_ticks_left);
+    let Variant::Int(debug_task_id) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let debug_task_id = *debug_task_id as TaskId;
 
-fn bf_seconds_left(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    // Syntax:  seconds_left()   => int
-    //
-    // Returns the number of seconds left in the current time slice.
-    if !bf_args.args.is_empty() {
-        return Err(BfErr::Code(E_ARGS));
+    // A running task can't step itself.
+    if debug_task_id == bf_args.exec_state.task_id {
+        return Err(BfErr::Code(E_INVARG));
     }
 
-    let seconds_left = match bf_args.exec_state.time_left() {
-        None => v_none(),
-        Some(d) => v_int(d.as_secs() as i64),
-    };
+    let enable = bf_args.args.len() == 1 || bf_args.args[1].is_true();
+    let debugger = enable.then(|| bf_args.exec_state.top().player.clone());
 
-    Ok(Ret(seconds_left))
+    let result = bf_args.task_scheduler_client.debug_task(
+        debug_task_id,
+        debugger,
+        bf_args.task_perms().map_err(world_state_bf_err)?,
+    );
+    if let Variant::Err(err) = result.variant() {
+        return Err(BfErr::Code(*err));
+    }
+    Ok(Ret(result))
 }
-bf_declare!(seconds_left, bf_seconds_left);
+bf_declare!(debug_task, bf_debug_task);
 
-fn bf_boot_player(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    // Syntax:  boot_player(<player>)   => none
+fn bf_set_breakpoint(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  set_breakpoint(<object>, <verb-name>, <line> [, <condition>])   => int
     //
-    // Disconnects the player with the given object number.
-    if bf_args.args.len() != 1 {
+    // Sets a breakpoint on <line> of the verb <verb-name> defined on <object>, returning its id.
+    // Any task about to execute that line suspends and notifies the calling player. If
+    // <condition> is given it is a MOO expression evaluated with the verb's variables, and the
+    // breakpoint only fires when it is true.
+    if bf_args.args.len() < 3 || bf_args.args.len() > 4 {
         return Err(BfErr::Code(E_ARGS));
     }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
 
-    let Variant::Obj(player) = bf_args.args[0].variant() else {
+    let Variant::Obj(location) = bf_args.args[0].variant() else {
         return Err(BfErr::Code(E_TYPE));
     };
-
-    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
-    if task_perms.who != *player && !task_perms.check_is_wizard().map_err(world_state_bf_err)? {
-        return Err(BfErr::Code(E_PERM));
+    let Variant::Str(verb) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Int(line) = bf_args.args[2].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if *line < 1 {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    if !bf_args
+        .world_state
+        .valid(location)
+        .map_err(world_state_bf_err)?
+    {
+        return Err(BfErr::Code(E_INVARG));
     }
 
-    bf_args.task_scheduler_client.boot_player(player.clone());
+    let condition = if bf_args.args.len() == 4 {
+        let Variant::Str(condition) = bf_args.args[3].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        let source = condition.as_string().clone();
+        let program = compile(
+            &format!("return {};", source),
+            bf_args.config.compile_options(),
+        )
+        .map_err(|_| BfErr::Code(E_INVARG))?;
+        Some((source, program))
+    } else {
+        None
+    };
+
+    let breakpoint = Breakpoint {
+        id: 0,
+        location: location.clone(),
+        verb: Symbol::mk_case_insensitive(verb.as_string().as_str()),
+        line: *line as usize,
+        condition,
+        debugger: bf_args.exec_state.top().player.clone(),
+    };
+    let id = bf_args.task_scheduler_client.set_breakpoint(breakpoint);
+    Ok(Ret(v_int(id as i64)))
+}
+bf_declare!(set_breakpoint, bf_set_breakpoint);
+
+fn bf_clear_breakpoint(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  clear_breakpoint(<id>)   => none
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let Variant::Int(id) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if *id < 1 || !bf_args.task_scheduler_client.clear_breakpoint(*id as usize) {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    Ok(Ret(v_none()))
+}
+bf_declare!(clear_breakpoint, bf_clear_breakpoint);
+
+fn bf_breakpoints(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  breakpoints()   => list
+    //
+    // Returns a list of {id, object, verb-name, line, condition, debugger} for every breakpoint,
+    // with an empty string for the condition of unconditional breakpoints.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let breakpoints = bf_args.task_scheduler_client.breakpoints();
+    let breakpoints = breakpoints.iter().map(|b| {
+        v_list(&[
+            v_int(b.id as i64),
+            v_obj(b.location.clone()),
+            v_str(b.verb.as_str()),
+            v_int(b.line as i64),
+            v_str(
+                b.condition
+                    .as_ref()
+                    .map_or("", |(source, _)| source.as_str()),
+            ),
+            v_obj(b.debugger.clone()),
+        ])
+    });
+    Ok(Ret(v_list_iter(breakpoints)))
+}
+bf_declare!(breakpoints, bf_breakpoints);
+
+fn bf_wrap_verb(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  wrap_verb(<object>, <verb-name>, <wrapper-object>, <wrapper-verb>)   => int
+    //
+    // Until unwrapped, calls from verb code to the verb <verb-name> defined on <object> run
+    // <wrapper-object>:<wrapper-verb> instead, with the same this, verb and args, and with the
+    // caller's permissions. The wrapper can call through with this:(verb)(@args); its own calls
+    // aren't wrapped. Returns the wrapper's id. A verb can only have one wrapper at a time.
+    if bf_args.args.len() != 4 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let perms = bf_args.task_perms_who();
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let Variant::Obj(location) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Str(verb) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Obj(wrapper_location) = bf_args.args[2].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let Variant::Str(wrapper_verb) = bf_args.args[3].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let verb = Symbol::mk_case_insensitive(verb.as_string().as_str());
+    let wrapper_verb = Symbol::mk_case_insensitive(wrapper_verb.as_string().as_str());
+
+    // Both verbs have to exist now; the wrapped one defined on <object> itself.
+    bf_args
+        .world_state
+        .get_verb(&perms, location, verb)
+        .map_err(world_state_bf_err)?;
+    bf_args
+        .world_state
+        .find_method_verb_on(&perms, wrapper_location, wrapper_verb)
+        .map_err(world_state_bf_err)?;
+
+    let wrapper = VerbWrapper {
+        id: 0,
+        location: location.clone(),
+        verb,
+        wrapper_location: wrapper_location.clone(),
+        wrapper_verb,
+        owner: perms,
+    };
+    let Some(id) = bf_args.task_scheduler_client.wrap_verb(wrapper) else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    Ok(Ret(v_int(id as i64)))
+}
+bf_declare!(wrap_verb, bf_wrap_verb);
+
+fn bf_unwrap_verb(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  unwrap_verb(<id>)   => none
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let Variant::Int(id) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if *id < 1 || !bf_args.task_scheduler_client.unwrap_verb(*id as usize) {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    Ok(Ret(v_none()))
+}
+bf_declare!(unwrap_verb, bf_unwrap_verb);
+
+fn bf_verb_wrappers(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  verb_wrappers()   => list
+    //
+    // Returns a list of {id, object, verb-name, wrapper-object, wrapper-verb, owner} for every
+    // verb wrapper.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let wrappers = bf_args.task_scheduler_client.verb_wrappers();
+    let wrappers = wrappers.iter().map(|w| {
+        v_list(&[
+            v_int(w.id as i64),
+            v_obj(w.location.clone()),
+            v_str(w.verb.as_str()),
+            v_obj(w.wrapper_location.clone()),
+            v_str(w.wrapper_verb.as_str()),
+            v_obj(w.owner.clone()),
+        ])
+    });
+    Ok(Ret(v_list_iter(wrappers)))
+}
+bf_declare!(verb_wrappers, bf_verb_wrappers);
+
+fn bf_audit_permissions(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  audit_permissions(<enabled>)   => int
+    //
+    // Turns the recording of permission checks on or off, returning whether it was on. Tasks
+    // start (or stop) recording at the start of their next transaction. While it's on, the most
+    // recent 10000 checks are kept, for `permission_checks()` to return.
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let enabled = bf_args.args[0].is_true();
+    let was_enabled = bf_args.task_scheduler_client.set_permission_audit(enabled);
+    Ok(Ret(v_bool(was_enabled)))
+}
+bf_declare!(audit_permissions, bf_audit_permissions);
+
+fn bf_permission_checks(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  permission_checks([<clear>])   => list
+    //
+    // Returns a list of {task-id, programmer, wizard, object, action} for each permission check
+    // recorded since auditing was turned on, oldest first, and empties the log if <clear> is
+    // true. <wizard> is whether the programmer was a wizard at the time, which is what lets a
+    // check pass regardless of the object's flags.
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let clear = bf_args.args.len() == 1 && bf_args.args[0].is_true();
+    let checks = bf_args.task_scheduler_client.permission_checks(clear);
+    let checks = checks.into_iter().map(|(task_id, check)| {
+        v_list(&[
+            v_int(task_id as i64),
+            v_obj(check.programmer),
+            v_bool(check.wizard),
+            v_obj(check.object),
+            v_str(check.action),
+        ])
+    });
+    Ok(Ret(v_list_iter(checks)))
+}
+bf_declare!(permission_checks, bf_permission_checks);
+
+fn bf_ticks_left(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  ticks_left()   => int
+    //
+    // Returns the number of ticks left in the current time slice.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    Ok(Ret(v_int(bf_args.exec_state.ticks_left() as i64)))
+}
+bf_declare!(ticks_left, bf_ticks_left);
+
+/// Whole seconds left of `time_left`, rounded up as LambdaMOO's one-second timers effectively do:
+/// a task which has just started with 5 seconds has 5 left, not 4.
+fn whole_seconds_left(time_left: Option<Duration>) -> Var {
+    match time_left {
+        None => v_none(),
+        Some(d) => v_int(d.as_secs() as i64 + i64::from(d.subsec_nanos() > 0)),
+    }
+}
+
+fn bf_seconds_left(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  seconds_left()   => int
+    //
+    // Returns the number of seconds left in the current time slice.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    Ok(Ret(whole_seconds_left(bf_args.exec_state.time_left())))
+}
+bf_declare!(seconds_left, bf_seconds_left);
+
+/// task_limits()
+/// Returns a map of the current task's resource limits: the ticks and seconds it was allotted for
+/// its current run ("ticks", "seconds"), what's left of them ("ticks_left", "seconds_left"), and
+/// the deepest its verb calls may nest ("max_stack_depth"). A task which has suspended runs on
+/// background limits from then on, as in LambdaMOO.
+fn bf_task_limits(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let exec_state = &bf_args.exec_state;
+    let seconds = exec_state
+        .maximum_time
+        .map(|d| v_int(d.as_secs() as i64))
+        .unwrap_or_else(v_none);
+    let max_stack_depth = bf_args
+        .task_scheduler_client
+        .server_options()
+        .max_stack_depth;
+    let limits = [
+        ("ticks", v_int(exec_state.max_ticks as i64)),
+        ("ticks_left", v_int(exec_state.ticks_left() as i64)),
+        ("seconds", seconds),
+        ("seconds_left", whole_seconds_left(exec_state.time_left())),
+        ("max_stack_depth", v_int(max_stack_depth as i64)),
+    ];
+    let limits: Vec<_> = limits
+        .into_iter()
+        .map(|(name, value)| (v_str(name), value))
+        .collect();
+    Ok(Ret(v_map(&limits)))
+}
+bf_declare!(task_limits, bf_task_limits);
+
+/// task_quota(player)
+/// Returns a map of how many tasks, running or queued, `player` owns ("tasks") and how many are
+/// running on their behalf ("connection_tasks"), against the limits beyond which `fork` and
+/// `suspend()` raise E_QUOTA ("limit", "connection_limit"; none if unlimited). Players may see
+/// their own; wizards anyone's.
+fn bf_task_quota(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(who) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    if task_perms.who != *who && !task_perms.check_is_wizard().map_err(world_state_bf_err)? {
+        return Err(BfErr::Code(E_PERM));
+    }
+
+    let server_options = bf_args.task_scheduler_client.server_options();
+    let limit = owner_task_limit(bf_args.world_state, who).or(server_options.queued_task_limit);
+    let (tasks, connection_tasks) = bf_args.task_scheduler_client.task_counts(who.clone());
+    let limit_var = |limit: Option<usize>| limit.map(|l| v_int(l as i64)).unwrap_or_else(v_none);
+    let quota = [
+        ("tasks", v_int(tasks as i64)),
+        ("limit", limit_var(limit)),
+        ("connection_tasks", v_int(connection_tasks as i64)),
+        (
+            "connection_limit",
+            limit_var(server_options.connection_task_limit),
+        ),
+    ];
+    let quota: Vec<_> = quota
+        .into_iter()
+        .map(|(name, value)| (v_str(name), value))
+        .collect();
+    Ok(Ret(v_map(&quota)))
+}
+bf_declare!(task_quota, bf_task_quota);
+
+/// finished_tasks([player])
+/// Returns a list of maps describing the most recently finished tasks, oldest first: "task_id",
+/// "player", "programmer", what it was started to do ("this" and "verb", or "command"), when it
+/// "finished" and how many seconds it ran for since it last started or resumed ("runtime"). A task
+/// which returned has its "result"; one which didn't has an "error": the error it raised (with
+/// "message" and "traceback"), or "ticks", "seconds" or "killed" if it was aborted.
+/// Players see only tasks they owned or which ran on their behalf; wizards see everyone's, or
+/// just `player`'s.
+fn bf_finished_tasks(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let who = match bf_args.args.first().map(|arg| arg.variant()) {
+        None => None,
+        Some(Variant::Obj(who)) => Some(who.clone()),
+        Some(_) => return Err(BfErr::Code(E_TYPE)),
+    };
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    let who = if task_perms.check_is_wizard().map_err(world_state_bf_err)? {
+        who
+    } else {
+        match who {
+            Some(who) if who != task_perms.who => return Err(BfErr::Code(E_PERM)),
+            _ => Some(task_perms.who.clone()),
+        }
+    };
+
+    let finished = bf_args.task_scheduler_client.finished_tasks(who);
+    let finished = finished.into_iter().map(|task| {
+        let finished_at = task
+            .finished_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut entry = vec![
+            ("task_id", v_int(task.task_id as i64)),
+            ("player", v_obj(task.player)),
+            ("programmer", v_obj(task.programmer)),
+        ];
+        match task.origin {
+            TaskOrigin::Command(command) => entry.push(("command", v_string(command))),
+            TaskOrigin::Verb(this, verb) => {
+                entry.push(("this", this));
+                entry.push(("verb", v_str(verb.as_str())));
+            }
+        }
+        entry.push(("finished", v_int(finished_at.as_secs() as i64)));
+        entry.push(("runtime", v_float(task.runtime.as_secs_f64())));
+        match task.result {
+            Ok(result) => entry.push(("result", result)),
+            Err(SchedulerError::TaskAbortedException(exception)) => {
+                entry.push(("error", v_err(exception.code)));
+                entry.push(("message", v_string(exception.msg)));
+                entry.push(("traceback", v_list(&exception.backtrace)));
+            }
+            Err(SchedulerError::TaskAbortedLimit(AbortLimitReason::Ticks(_))) => {
+                entry.push(("error", v_str("ticks")))
+            }
+            Err(SchedulerError::TaskAbortedLimit(AbortLimitReason::Time(_))) => {
+                entry.push(("error", v_str("seconds")))
+            }
+            Err(SchedulerError::TaskAbortedCancelled) => entry.push(("error", v_str("killed"))),
+            Err(e) => entry.push(("error", v_string(e.to_string()))),
+        }
+        let entry: Vec<_> = entry
+            .into_iter()
+            .map(|(name, value)| (v_str(name), value))
+            .collect();
+        v_map(&entry)
+    });
+    Ok(Ret(v_list_iter(finished)))
+}
+bf_declare!(finished_tasks, bf_finished_tasks);
+
+fn bf_boot_player(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  boot_player(<player>)   => none
+    //
+    // Disconnects the player with the given object number.
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    if task_perms.who != *player && !task_perms.check_is_wizard().map_err(world_state_bf_err)? {
+        return Err(BfErr::Code(E_PERM));
+    }
+
+    bf_args.task_scheduler_client.boot_player(player.clone());
 
     Ok(Ret(v_none()))
 }
 bf_declare!(boot_player, bf_boot_player);
 
+/// revoke_tokens(player)
+/// Invalidates the auth tokens `player`'s clients have been issued, so they have to log in again,
+/// e.g. after a password change. `boot_player()` does this too.
+fn bf_revoke_tokens(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let Variant::Obj(player) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    let task_perms = bf_args.task_perms().map_err(world_state_bf_err)?;
+    if task_perms.who != *player && !task_perms.check_is_wizard().map_err(world_state_bf_err)? {
+        return Err(BfErr::Code(E_PERM));
+    }
+
+    bf_args
+        .task_scheduler_client
+        .revoke_auth_tokens(player.clone())
+        .map_err(BfErr::Code)?;
+
+    Ok(Ret(v_none()))
+}
+bf_declare!(revoke_tokens, bf_revoke_tokens);
+
+/// switch_player(connection, new_player)
+/// Hands the connections of `connection` (a player, or a connection which hasn't logged in yet)
+/// over to `new_player`, issuing their clients credentials for it, e.g. for @su or for claiming a
+/// guest. No login verbs are called.
+fn bf_switch_player(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let (Variant::Obj(connection), Variant::Obj(new_player)) =
+        (bf_args.args[0].variant(), bf_args.args[1].variant())
+    else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    let is_player = match bf_args.world_state.flags_of(new_player) {
+        Ok(flags) => flags.contains(ObjFlag::User),
+        Err(WorldStateError::ObjectNotFound(_)) => false,
+        Err(e) => return Err(world_state_bf_err(e)),
+    };
+    if !is_player || connection == new_player {
+        return Err(BfErr::Code(E_INVARG));
+    }
+
+    bf_args
+        .task_scheduler_client
+        .switch_player(connection.clone(), new_player.clone())
+        .map_err(BfErr::Code)?;
+
+    Ok(Ret(v_none()))
+}
+bf_declare!(switch_player, bf_switch_player);
+
 fn bf_call_function(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Syntax:  call_function(<func>, <arg1>, <arg2>, ...)   => value
     //
@@ -783,6 +1983,7 @@ bf_declare!(function_info, bf_function_info);
 /// if `print-messages` is true, then the server will print messages like ** Connected ** etc to the connection when it establishes
 /// if `host-type` is provided, it should be a string, and it will be used to determine the type of host that will be expected to listen.
 ///   this defaults to "tcp", but other common can include "websocket"
+/// Raises E_INVARG if `object` isn't valid, or something is already listening on `point`.
 fn bf_listen(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Requires wizard permissions.
     bf_args
@@ -810,14 +2011,8 @@ fn bf_listen(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 
     let port = point as u16;
 
-    let print_messages = if bf_args.args.len() >= 3 {
-        let Variant::Int(print_messages) = bf_args.args[2].variant().clone() else {
-            return Err(BfErr::Code(E_TYPE));
-        };
-        print_messages == 1
-    } else {
-        false
-    };
+    // Any true value, as in LambdaMOO.
+    let print_messages = bf_args.args.len() >= 3 && bf_args.args[2].is_true();
 
     let host_type = if bf_args.args.len() == 4 {
         let Variant::Str(host_type) = bf_args.args[3].variant().clone() else {
@@ -828,11 +2023,19 @@ fn bf_listen(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         "tcp".to_string()
     };
 
-    // Ask the scheduler to broadcast a listen request out to all the hosts.
-    if let Some(error) =
-        bf_args
-            .task_scheduler_client
-            .listen(object, host_type, port, print_messages)
+    if !bf_args
+        .world_state
+        .valid(&object)
+        .map_err(world_state_bf_err)?
+    {
+        return Err(BfErr::Code(E_INVARG));
+    }
+
+    // Ask the scheduler to broadcast a listen request out to all the hosts.
+    if let Some(error) =
+        bf_args
+            .task_scheduler_client
+            .listen(object, host_type, port, print_messages)
     {
         return Err(BfErr::Code(error));
     }
@@ -844,6 +2047,8 @@ fn bf_listen(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 
 bf_declare!(listen, bf_listen);
 
+/// listeners()
+/// Returns `{object, canon, print-messages}` for each listener, as LambdaMOO does.
 fn bf_listeners(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Requires wizard permissions.
     bf_args
@@ -872,7 +2077,9 @@ fn bf_listeners(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(listeners, bf_listeners);
 
-// unlisten(port, [host-type])
+/// unlisten(canon, [host-type])
+/// Stops listening on `canon`, as returned by `listen()`. Raises E_INVARG if nothing is listening
+/// there.
 fn bf_unlisten(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Requires wizard permissions.
     bf_args
@@ -895,8 +2102,8 @@ fn bf_unlisten(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     }
 
     let port = point as u16;
-    let host_type = if bf_args.args.len() == 4 {
-        let Variant::Str(host_type) = bf_args.args[3].variant().clone() else {
+    let host_type = if bf_args.args.len() == 2 {
+        let Variant::Str(host_type) = bf_args.args[1].variant().clone() else {
             return Err(BfErr::Code(E_TYPE));
         };
         host_type.as_string().clone()
@@ -912,6 +2119,266 @@ fn bf_unlisten(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(unlisten, bf_unlisten);
 
+/// ban_site(site)
+/// Refuses logins from `site`, an address (e.g. "10.0.0.1"), or a prefix of one ending in `*`
+/// (e.g. "10.0.*"). Returns true if it wasn't already banned. The ban list survives restarts.
+fn bf_ban_site(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(site) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let site = site.as_string().trim().to_string();
+    if site.is_empty() {
+        return Err(BfErr::Code(E_INVARG));
+    }
+
+    let added = bf_args
+        .task_scheduler_client
+        .ban_site(site)
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_bool(added)))
+}
+bf_declare!(ban_site, bf_ban_site);
+
+/// unban_site(site)
+/// Removes `site`, exactly as it was given to `ban_site`, from the ban list. Returns true if it
+/// was there.
+fn bf_unban_site(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(site) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let site = site.as_string().trim().to_string();
+
+    let removed = bf_args
+        .task_scheduler_client
+        .unban_site(site)
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_bool(removed)))
+}
+bf_declare!(unban_site, bf_unban_site);
+
+/// banned_sites()
+/// The ban list, as given to `ban_site()`.
+fn bf_banned_sites(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let sites = bf_args
+        .task_scheduler_client
+        .banned_sites()
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_list_iter(sites.iter().map(|site| v_str(site)))))
+}
+bf_declare!(banned_sites, bf_banned_sites);
+
+/// Build a webhook filter from a map with any of the keys "object" (an object), "verb" and
+/// "property" (strings), and "events" (a list of strings from `WEBHOOK_EVENTS`).
+fn webhook_filter_from_map(filter: &Var) -> Result<WebhookFilter, BfErr> {
+    let Variant::Map(m) = filter.variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let mut webhook_filter = WebhookFilter::default();
+    for (key, value) in m.iter() {
+        let Variant::Str(key) = key.variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        match (key.as_string().to_lowercase().as_str(), value.variant()) {
+            ("object", Variant::Obj(o)) => webhook_filter.object = Some(o.clone()),
+            ("verb", Variant::Str(name)) => {
+                webhook_filter.verb = Some(Symbol::mk(name.as_string()))
+            }
+            ("property", Variant::Str(name)) => {
+                webhook_filter.property = Some(Symbol::mk(name.as_string()))
+            }
+            ("events", Variant::List(events)) => {
+                for event in events.iter() {
+                    let Variant::Str(event) = event.variant() else {
+                        return Err(BfErr::Code(E_TYPE));
+                    };
+                    let event = event.as_string().to_lowercase();
+                    if !WEBHOOK_EVENTS.contains(&event.as_str()) {
+                        return Err(BfErr::Code(E_INVARG));
+                    }
+                    webhook_filter.events.push(event);
+                }
+            }
+            ("object" | "verb" | "property" | "events", _) => return Err(BfErr::Code(E_TYPE)),
+            _ => return Err(BfErr::Code(E_INVARG)),
+        }
+    }
+    Ok(webhook_filter)
+}
+
+fn webhook_filter_to_map(filter: &WebhookFilter) -> Var {
+    let mut pairs = vec![];
+    if let Some(object) = &filter.object {
+        pairs.push((v_str("object"), v_obj(object.clone())));
+    }
+    if let Some(verb) = &filter.verb {
+        pairs.push((v_str("verb"), v_str(verb.as_str())));
+    }
+    if let Some(property) = &filter.property {
+        pairs.push((v_str("property"), v_str(property.as_str())));
+    }
+    if !filter.events.is_empty() {
+        pairs.push((
+            v_str("events"),
+            v_list_iter(filter.events.iter().map(|e| v_str(e))),
+        ));
+    }
+    v_map(&pairs)
+}
+
+/// add_webhook(url, filter [, secret])
+/// From now on, POSTs each committed transaction's changes matching `filter` (see
+/// `webhook_filter_from_map`; an empty map matches everything) to `url` as JSON. If `secret` is
+/// given, each request is signed with it, in an `X-Moor-Signature` header. Returns the webhook's
+/// id. Webhooks survive restarts.
+fn bf_add_webhook(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(url) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let url = url.as_string().trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let filter = webhook_filter_from_map(&bf_args.args[1])?;
+    let secret = if bf_args.args.len() == 3 {
+        let Variant::Str(secret) = bf_args.args[2].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        Some(secret.as_string().clone())
+    } else {
+        None
+    };
+
+    let id = bf_args
+        .task_scheduler_client
+        .add_webhook(url, secret, filter)
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_int(id as i64)))
+}
+bf_declare!(add_webhook, bf_add_webhook);
+
+/// remove_webhook(id)
+/// Removes the webhook `add_webhook` returned `id` for. Returns true if it was there.
+fn bf_remove_webhook(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Int(id) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if *id < 0 {
+        return Err(BfErr::Code(E_INVARG));
+    }
+
+    let removed = bf_args
+        .task_scheduler_client
+        .remove_webhook(*id as u64)
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_bool(removed)))
+}
+bf_declare!(remove_webhook, bf_remove_webhook);
+
+/// webhooks()
+/// The webhooks, as a list of {id, url, filter}. Secrets aren't shown.
+fn bf_webhooks(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let webhooks = bf_args
+        .task_scheduler_client
+        .webhooks()
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_list_iter(webhooks.iter().map(
+        |(id, url, filter)| v_list(&[v_int(*id as i64), v_str(url), webhook_filter_to_map(filter)]),
+    ))))
+}
+bf_declare!(webhooks, bf_webhooks);
+
+/// federation_send(world, target, verb, args)
+/// Sends `target:verb(@args)` to the federated world named `world`, where it's handed to that
+/// world's `#0:do_federated_message`. Delivery isn't confirmed. Raises E_INVARG if there's no such
+/// world, or E_QUOTA if the message couldn't be queued.
+fn bf_federation_send(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if bf_args.args.len() != 4 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let (Variant::Str(world), Variant::Obj(target), Variant::Str(verb), Variant::List(args)) = (
+        bf_args.args[0].variant(),
+        bf_args.args[1].variant(),
+        bf_args.args[2].variant(),
+        bf_args.args[3].variant(),
+    ) else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+
+    bf_args
+        .task_scheduler_client
+        .federation_send(
+            world.as_string().clone(),
+            target.clone(),
+            Symbol::mk_case_insensitive(verb.as_string().as_str()),
+            args.clone(),
+        )
+        .map_err(BfErr::Code)?;
+    Ok(Ret(v_none()))
+}
+bf_declare!(federation_send, bf_federation_send);
+
 pub const BF_SERVER_EVAL_TRAMPOLINE_START_INITIALIZE: usize = 0;
 pub const BF_SERVER_EVAL_TRAMPOLINE_RESUME: usize = 1;
 
@@ -937,7 +2404,11 @@ fn bf_eval(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     match tramp {
         BF_SERVER_EVAL_TRAMPOLINE_START_INITIALIZE => {
             let program_code = program_code.as_string().clone();
-            let program = match compile(&program_code, bf_args.config.compile_options()) {
+            let compile_options = bf_args
+                .config
+                .compile_options_for(bf_args.world_state)
+                .map_err(world_state_bf_err)?;
+            let program = match compile(&program_code, compile_options) {
                 Ok(program) => program,
                 Err(e) => return Ok(Ret(v_list(&[v_int(0), v_string(e.to_string())]))),
             };
@@ -988,31 +2459,7 @@ fn bf_memory_usage(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         .check_wizard()
         .map_err(world_state_bf_err)?;
 
-    // Get system page size
-    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
-    if page_size == -1 {
-        return Err(BfErr::Code(Error::E_QUOTA));
-    }
-
-    // Then read /proc/self/statm
-    let mut statm = String::new();
-    std::fs::File::open("/proc/self/statm")
-        .map_err(|_| BfErr::Code(Error::E_QUOTA))?
-        .read_to_string(&mut statm)
-        .map_err(|_| BfErr::Code(Error::E_QUOTA))?;
-
-    // Split on whitespace -- then we have VmSize and VmRSS in pages
-    let mut statm = statm.split_whitespace();
-    let vm_size = statm
-        .next()
-        .ok_or(BfErr::Code(Error::E_QUOTA))?
-        .parse::<i64>()
-        .map_err(|_| BfErr::Code(Error::E_QUOTA))?;
-    let vm_rss = statm
-        .next()
-        .ok_or(BfErr::Code(Error::E_QUOTA))?
-        .parse::<i64>()
-        .map_err(|_| BfErr::Code(Error::E_QUOTA))?;
+    let (page_size, vm_size, vm_rss) = process_memory().ok_or(BfErr::Code(Error::E_QUOTA))?;
 
     // Return format for memory_usage is:
     // {block-size, nused, nfree}
@@ -1030,6 +2477,179 @@ fn bf_memory_usage(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(memory_usage, bf_memory_usage);
 
+/// The system page size, and the process's virtual size and resident set size in pages.
+fn process_memory() -> Option<(i64, i64, i64)> {
+    // Get system page size
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size == -1 {
+        return None;
+    }
+
+    // Then read /proc/self/statm
+    let mut statm = String::new();
+    std::fs::File::open("/proc/self/statm")
+        .ok()?
+        .read_to_string(&mut statm)
+        .ok()?;
+
+    // Split on whitespace -- then we have VmSize and VmRSS in pages
+    let mut statm = statm.split_whitespace();
+    let vm_size = statm.next()?.parse::<i64>().ok()?;
+    let vm_rss = statm.next()?.parse::<i64>().ok()?;
+    Some((page_size as i64, vm_size, vm_rss))
+}
+
+/// The number of threads in the process.
+fn process_threads() -> Option<i64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// server_stats()
+/// Returns a map of figures for watching the server's resource use: the scheduler's task counts
+/// and totals, the process's memory and threads, and the size of the database on disk. Figures
+/// the platform can't provide (memory and threads come from /proc) are left out. Wizard only.
+fn bf_server_stats(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let counters = bf_args.task_scheduler_client.performance_counters();
+    let mut stats = vec![
+        ("active_tasks", counters.active_tasks as i64),
+        ("suspended_tasks", counters.suspended_tasks as i64),
+        ("ready_tasks", counters.ready_tasks as i64),
+        ("tasks_started", counters.tasks_started as i64),
+        ("tasks_succeeded", counters.tasks_succeeded as i64),
+        ("tasks_exceptions", counters.tasks_exceptions as i64),
+        ("tasks_aborted", counters.tasks_aborted as i64),
+        ("commit_conflicts", counters.commit_conflicts as i64),
+    ];
+    if let Some((page_size, vm_size, vm_rss)) = process_memory() {
+        stats.push(("virtual_bytes", vm_size * page_size));
+        stats.push(("resident_bytes", vm_rss * page_size));
+    }
+    if let Some(threads) = process_threads() {
+        stats.push(("threads", threads));
+    }
+    let disk_size = bf_args.world_state.db_usage().map_err(world_state_bf_err)?;
+    stats.push(("db_disk_bytes", disk_size as i64));
+
+    let stats: Vec<_> = stats
+        .into_iter()
+        .map(|(name, value)| (v_str(name), v_int(value)))
+        .collect();
+    Ok(Ret(v_map(&stats)))
+}
+bf_declare!(server_stats, bf_server_stats);
+
+/// server_load()
+/// Returns a map describing how loaded the server is: tasks running and waiting to start, the
+/// recent average commit latency in microseconds, whether the server is past one of its overload
+/// thresholds (and so turning away non-wizards' commands), and how many commands it has turned
+/// away. Open to everyone, so cores can show players why things are slow.
+fn bf_server_load(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let counters = bf_args.task_scheduler_client.performance_counters();
+    let load = [
+        ("active_tasks", v_int(counters.active_tasks as i64)),
+        ("ready_tasks", v_int(counters.ready_tasks as i64)),
+        (
+            "commit_latency_us",
+            v_int(counters.commit_latency_us as i64),
+        ),
+        ("overloaded", v_bool(counters.overloaded)),
+        ("tasks_shed", v_int(counters.tasks_shed as i64)),
+    ];
+    let load: Vec<_> = load
+        .into_iter()
+        .map(|(name, value)| (v_str(name), value))
+        .collect();
+    Ok(Ret(v_map(&load)))
+}
+bf_declare!(server_load, bf_server_load);
+
+/// perf_counters()
+/// Returns the scheduler's performance counters, the same ones the daemon exports to hosts as
+/// metrics, as a map from each counter's name to a map describing it. Every entry has a "kind":
+/// "counter" (a running total since startup) or "gauge" (a current figure) with a "value", or
+/// "timer" with the "count" of events timed, their "total_us" in microseconds, and the recent
+/// "average_us". Wizard only.
+fn bf_perf_counters(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    bf_args
+        .task_perms()
+        .map_err(world_state_bf_err)?
+        .check_wizard()
+        .map_err(world_state_bf_err)?;
+
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+
+    let c = bf_args.task_scheduler_client.performance_counters();
+    let counters = [
+        ("tasks_started", c.tasks_started),
+        ("tasks_resumed", c.tasks_resumed),
+        ("tasks_succeeded", c.tasks_succeeded),
+        ("tasks_exceptions", c.tasks_exceptions),
+        ("tasks_limits_exceeded", c.tasks_limits_exceeded),
+        ("tasks_aborted", c.tasks_aborted),
+        ("tasks_shed", c.tasks_shed),
+        ("tasks_over_quota", c.tasks_over_quota),
+        ("commit_conflicts", c.commit_conflicts),
+        ("program_cache_hits", c.program_cache_hits),
+        ("program_cache_misses", c.program_cache_misses),
+    ];
+    let gauges = [
+        ("active_tasks", c.active_tasks),
+        ("suspended_tasks", c.suspended_tasks),
+        ("ready_tasks", c.ready_tasks),
+        ("overloaded", c.overloaded as u64),
+    ];
+    let timers = [("commits", c.commits, c.commit_time_us, c.commit_latency_us)];
+
+    let stat = |kind: &str, figures: &[(&str, u64)]| {
+        let mut entry = vec![(v_str("kind"), v_str(kind))];
+        entry.extend(
+            figures
+                .iter()
+                .map(|(name, value)| (v_str(name), v_int(*value as i64))),
+        );
+        v_map(&entry)
+    };
+    let mut stats = vec![];
+    for (name, value) in counters {
+        stats.push((v_str(name), stat("counter", &[("value", value)])));
+    }
+    for (name, value) in gauges {
+        stats.push((v_str(name), stat("gauge", &[("value", value)])));
+    }
+    for (name, count, total_us, average_us) in timers {
+        let figures = [
+            ("count", count),
+            ("total_us", total_us),
+            ("average_us", average_us),
+        ];
+        stats.push((v_str(name), stat("timer", &figures)));
+    }
+    Ok(Ret(v_map(&stats)))
+}
+bf_declare!(perf_counters, bf_perf_counters);
+
 fn db_disk_size(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Syntax:  db_disk_size()   => int
     //
@@ -1051,6 +2671,273 @@ fn db_disk_size(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(db_disk_size, db_disk_size);
 
+fn registry_key(key: &Var) -> Result<String, BfErr> {
+    let Variant::Str(key) = key.variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if key.as_string().is_empty() {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    Ok(key.as_string().clone())
+}
+
+fn bf_registry_get(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  registry_get(str key [, default])   => value
+    //
+    // Returns the value of the server registry entry `key`, or `default` if there isn't one (and
+    // raises E_INVARG if no default is given). The registry holds server-wide configuration
+    // apart from the objects in the database, and is only available to wizards.
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let key = registry_key(&bf_args.args[0])?;
+    let value = bf_args
+        .world_state
+        .registry_get(&bf_args.task_perms_who(), &key)
+        .map_err(world_state_bf_err)?;
+    match (value, bf_args.args.len()) {
+        (Some(value), _) => Ok(Ret(value)),
+        (None, 2) => Ok(Ret(bf_args.args[1].clone())),
+        (None, _) => Err(BfErr::Code(E_INVARG)),
+    }
+}
+bf_declare!(registry_get, bf_registry_get);
+
+fn bf_registry_set(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  registry_set(str key, value)   => none
+    //
+    // Sets the server registry entry `key` to `value`. Wizards only.
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let key = registry_key(&bf_args.args[0])?;
+    let value = bf_args.args[1].clone();
+    bf_args
+        .world_state
+        .registry_set(&bf_args.task_perms_who(), &key, value)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_none()))
+}
+bf_declare!(registry_set, bf_registry_set);
+
+fn bf_registry_delete(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  registry_delete(str key)   => bool
+    //
+    // Removes the server registry entry `key`, returning whether there was one. Wizards only.
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let key = registry_key(&bf_args.args[0])?;
+    let removed = bf_args
+        .world_state
+        .registry_delete(&bf_args.task_perms_who(), &key)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(v_bool(removed)))
+}
+bf_declare!(registry_delete, bf_registry_delete);
+
+fn bf_registry_list(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  registry_list()   => map
+    //
+    // Returns all the server registry entries, as a map from key to value. Wizards only.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let entries = bf_args
+        .world_state
+        .registry_list(&bf_args.task_perms_who())
+        .map_err(world_state_bf_err)?;
+    let entries: Vec<_> = entries
+        .into_iter()
+        .map(|(key, value)| (v_string(key), value))
+        .collect();
+    Ok(Ret(v_map(&entries)))
+}
+bf_declare!(registry_list, bf_registry_list);
+
+fn bf_compile_options(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  compile_options()   => map
+    //
+    // Returns the language features verbs are compiled with, as a map from option name to whether
+    // it's on: those the server is configured with, overridden by any the database has been given
+    // with set_compile_options().
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let options = bf_args
+        .config
+        .compile_options_for(bf_args.world_state)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(compile_options_map(&options)))
+}
+bf_declare!(compile_options, bf_compile_options);
+
+fn bf_set_compile_options(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  set_compile_options(map options)   => map
+    //
+    // Turns the named language features on or off for the database, whatever the server is
+    // configured with, and returns them all as compile_options() now would. Options not named
+    // keep whatever the database had. Verbs are only affected when they're next compiled.
+    // Wizards only.
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    // Check the names before anything is kept.
+    with_compile_options(bf_args.config.compile_options(), &bf_args.args[0])
+        .map_err(BfErr::Code)?;
+    let Variant::Map(given) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let perms = bf_args.task_perms_who();
+    let mut entries: Vec<(Var, Var)> = match bf_args
+        .world_state
+        .registry_get(&perms, COMPILE_OPTIONS_KEY)
+        .map_err(world_state_bf_err)?
+    {
+        Some(stored) => match stored.variant() {
+            Variant::Map(stored) => stored.iter().collect(),
+            _ => vec![],
+        },
+        None => vec![],
+    };
+    for (name, on) in given.iter() {
+        entries.retain(|(n, _)| *n != name);
+        entries.push((name, v_bool(on.is_true())));
+    }
+    bf_args
+        .world_state
+        .registry_set(&perms, COMPILE_OPTIONS_KEY, v_map(&entries))
+        .map_err(world_state_bf_err)?;
+    let options = bf_args
+        .config
+        .compile_options_for(bf_args.world_state)
+        .map_err(world_state_bf_err)?;
+    Ok(Ret(compile_options_map(&options)))
+}
+bf_declare!(set_compile_options, bf_set_compile_options);
+
+fn cache_stats_map(stats: &CacheStats) -> Var {
+    let stats = [
+        ("entries", stats.entries),
+        ("bytes", stats.used_bytes),
+        ("capacity", stats.threshold_bytes),
+        ("hits", stats.hits),
+        ("misses", stats.misses),
+        ("evictions", stats.evictions),
+    ];
+    let stats: Vec<_> = stats
+        .into_iter()
+        .map(|(name, value)| (v_str(name), v_int(value as i64)))
+        .collect();
+    v_map(&stats)
+}
+
+fn cache_name(name: &Var) -> Result<String, BfErr> {
+    let Variant::Str(name) = name.variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    Ok(name.as_string().clone())
+}
+
+fn bf_cache_stats(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  cache_stats([str name])   => map
+    //
+    // Returns a map from the name of each of the database's caches to its statistics (entries,
+    // bytes, capacity, hits, misses, evictions), or with `name`, just the statistics for that
+    // cache. Wizards only.
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let name = bf_args.args.first().map(cache_name).transpose()?;
+    let stats = bf_args
+        .world_state
+        .cache_stats(&bf_args.task_perms_who())
+        .map_err(world_state_bf_err)?;
+    match name {
+        Some(name) => {
+            let Some((_, stats)) = stats.iter().find(|(n, _)| *n == name) else {
+                return Err(BfErr::Code(E_INVARG));
+            };
+            Ok(Ret(cache_stats_map(stats)))
+        }
+        None => {
+            let stats: Vec<_> = stats
+                .iter()
+                .map(|(name, stats)| (v_str(name), cache_stats_map(stats)))
+                .collect();
+            Ok(Ret(v_map(&stats)))
+        }
+    }
+}
+bf_declare!(cache_stats, bf_cache_stats);
+
+fn bf_verb_cache_stats(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  verb_cache_stats()   => map
+    //
+    // As `cache_stats()`, for just the caches verbs are looked up through. Wizards only.
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let stats = bf_args
+        .world_state
+        .cache_stats(&bf_args.task_perms_who())
+        .map_err(world_state_bf_err)?;
+    let stats: Vec<_> = stats
+        .iter()
+        .filter(|(name, _)| name == "object_verbdefs" || name == "object_verbs")
+        .map(|(name, stats)| (v_str(name), cache_stats_map(stats)))
+        .collect();
+    Ok(Ret(v_map(&stats)))
+}
+bf_declare!(verb_cache_stats, bf_verb_cache_stats);
+
+fn bf_flush_caches(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  flush_caches([str name])   => int
+    //
+    // Empties the named cache, or all of them, returning how many entries were dropped. They're
+    // refilled from storage as they're used. Wizards only.
+    if bf_args.args.len() > 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let name = bf_args.args.first().map(cache_name).transpose()?;
+    let flushed = bf_args
+        .world_state
+        .flush_caches(&bf_args.task_perms_who(), name.as_deref())
+        .map_err(world_state_bf_err)?;
+    let Some(flushed) = flushed else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    Ok(Ret(v_int(flushed as i64)))
+}
+bf_declare!(flush_caches, bf_flush_caches);
+
+fn bf_set_cache_capacity(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  set_cache_capacity(str name, int bytes)   => none
+    //
+    // Sets the size past which the named cache starts evicting entries. It's kept with the
+    // database, in place of the configured `cache_eviction_threshold` for that table. Wizards
+    // only.
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let name = cache_name(&bf_args.args[0])?;
+    let Variant::Int(bytes) = bf_args.args[1].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if *bytes < 0 {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    let found = bf_args
+        .world_state
+        .set_cache_capacity(&bf_args.task_perms_who(), &name, *bytes as usize)
+        .map_err(world_state_bf_err)?;
+    if !found {
+        return Err(BfErr::Code(E_INVARG));
+    }
+    Ok(Ret(v_none()))
+}
+bf_declare!(set_cache_capacity, bf_set_cache_capacity);
+
 /* Function: none load_server_options ()
 
    This causes the server to consult the current common of properties on $server_options, updating
@@ -1076,17 +2963,33 @@ bf_declare!(load_server_options, load_server_options);
 
 pub(crate) fn register_bf_server(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("notify")] = Box::new(BfNotify {});
+    builtins[offset_for_builtin("notify_oob")] = Box::new(BfNotifyOob {});
+    builtins[offset_for_builtin("notify_gmcp")] = Box::new(BfNotifyGmcp {});
+    builtins[offset_for_builtin("present")] = Box::new(BfPresent {});
+    builtins[offset_for_builtin("unpresent")] = Box::new(BfUnpresent {});
     builtins[offset_for_builtin("connected_players")] = Box::new(BfConnectedPlayers {});
     builtins[offset_for_builtin("is_player")] = Box::new(BfIsPlayer {});
     builtins[offset_for_builtin("caller_perms")] = Box::new(BfCallerPerms {});
     builtins[offset_for_builtin("set_task_perms")] = Box::new(BfSetTaskPerms {});
     builtins[offset_for_builtin("callers")] = Box::new(BfCallers {});
     builtins[offset_for_builtin("task_id")] = Box::new(BfTaskId {});
+    builtins[offset_for_builtin("task_local")] = Box::new(BfTaskLocal {});
+    builtins[offset_for_builtin("set_task_local")] = Box::new(BfSetTaskLocal {});
+    builtins[offset_for_builtin("begin_atomic")] = Box::new(BfBeginAtomic {});
+    builtins[offset_for_builtin("end_atomic")] = Box::new(BfEndAtomic {});
     builtins[offset_for_builtin("idle_seconds")] = Box::new(BfIdleSeconds {});
     builtins[offset_for_builtin("connected_seconds")] = Box::new(BfConnectedSeconds {});
     builtins[offset_for_builtin("connection_name")] = Box::new(BfConnectionName {});
+    builtins[offset_for_builtin("connection_name_lookup")] = Box::new(BfConnectionNameLookup {});
+    builtins[offset_for_builtin("connection_attributes")] = Box::new(BfConnectionAttributes {});
+    builtins[offset_for_builtin("set_connection_option")] = Box::new(BfSetConnectionOption {});
+    builtins[offset_for_builtin("connection_option")] = Box::new(BfConnectionOption {});
+    builtins[offset_for_builtin("connection_options")] = Box::new(BfConnectionOptions {});
     builtins[offset_for_builtin("time")] = Box::new(BfTime {});
     builtins[offset_for_builtin("ctime")] = Box::new(BfCtime {});
+    builtins[offset_for_builtin("strftime")] = Box::new(BfStrftime {});
+    builtins[offset_for_builtin("monotonic_time_ns")] = Box::new(BfMonotonicTimeNs {});
+    builtins[offset_for_builtin("bench")] = Box::new(BfBench {});
     builtins[offset_for_builtin("raise")] = Box::new(BfRaise {});
     builtins[offset_for_builtin("server_version")] = Box::new(BfServerVersion {});
     builtins[offset_for_builtin("shutdown")] = Box::new(BfShutdown {});
@@ -1095,19 +2998,54 @@ pub(crate) fn register_bf_server(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("queue_info")] = Box::new(BfQueueInfo {});
     builtins[offset_for_builtin("kill_task")] = Box::new(BfKillTask {});
     builtins[offset_for_builtin("resume")] = Box::new(BfResume {});
+    builtins[offset_for_builtin("debug_task")] = Box::new(BfDebugTask {});
+    builtins[offset_for_builtin("set_breakpoint")] = Box::new(BfSetBreakpoint {});
+    builtins[offset_for_builtin("clear_breakpoint")] = Box::new(BfClearBreakpoint {});
+    builtins[offset_for_builtin("breakpoints")] = Box::new(BfBreakpoints {});
+    builtins[offset_for_builtin("wrap_verb")] = Box::new(BfWrapVerb {});
+    builtins[offset_for_builtin("unwrap_verb")] = Box::new(BfUnwrapVerb {});
+    builtins[offset_for_builtin("verb_wrappers")] = Box::new(BfVerbWrappers {});
+    builtins[offset_for_builtin("audit_permissions")] = Box::new(BfAuditPermissions {});
+    builtins[offset_for_builtin("permission_checks")] = Box::new(BfPermissionChecks {});
     builtins[offset_for_builtin("ticks_left")] = Box::new(BfTicksLeft {});
     builtins[offset_for_builtin("seconds_left")] = Box::new(BfSecondsLeft {});
+    builtins[offset_for_builtin("task_limits")] = Box::new(BfTaskLimits {});
+    builtins[offset_for_builtin("task_quota")] = Box::new(BfTaskQuota {});
+    builtins[offset_for_builtin("finished_tasks")] = Box::new(BfFinishedTasks {});
     builtins[offset_for_builtin("boot_player")] = Box::new(BfBootPlayer {});
+    builtins[offset_for_builtin("revoke_tokens")] = Box::new(BfRevokeTokens {});
+    builtins[offset_for_builtin("switch_player")] = Box::new(BfSwitchPlayer {});
     builtins[offset_for_builtin("call_function")] = Box::new(BfCallFunction {});
     builtins[offset_for_builtin("server_log")] = Box::new(BfServerLog {});
     builtins[offset_for_builtin("function_info")] = Box::new(BfFunctionInfo {});
     builtins[offset_for_builtin("listeners")] = Box::new(BfListeners {});
     builtins[offset_for_builtin("listen")] = Box::new(BfListen {});
     builtins[offset_for_builtin("unlisten")] = Box::new(BfUnlisten {});
+    builtins[offset_for_builtin("ban_site")] = Box::new(BfBanSite {});
+    builtins[offset_for_builtin("unban_site")] = Box::new(BfUnbanSite {});
+    builtins[offset_for_builtin("banned_sites")] = Box::new(BfBannedSites {});
+    builtins[offset_for_builtin("add_webhook")] = Box::new(BfAddWebhook {});
+    builtins[offset_for_builtin("remove_webhook")] = Box::new(BfRemoveWebhook {});
+    builtins[offset_for_builtin("webhooks")] = Box::new(BfWebhooks {});
+    builtins[offset_for_builtin("federation_send")] = Box::new(BfFederationSend {});
     builtins[offset_for_builtin("eval")] = Box::new(BfEval {});
     builtins[offset_for_builtin("read")] = Box::new(BfRead {});
+    builtins[offset_for_builtin("read_lines")] = Box::new(BfReadLines {});
     builtins[offset_for_builtin("dump_database")] = Box::new(BfDumpDatabase {});
     builtins[offset_for_builtin("memory_usage")] = Box::new(BfMemoryUsage {});
+    builtins[offset_for_builtin("server_stats")] = Box::new(BfServerStats {});
+    builtins[offset_for_builtin("server_load")] = Box::new(BfServerLoad {});
+    builtins[offset_for_builtin("perf_counters")] = Box::new(BfPerfCounters {});
     builtins[offset_for_builtin("db_disk_size")] = Box::new(BfDbDiskSize {});
     builtins[offset_for_builtin("load_server_options")] = Box::new(BfLoadServerOptions {});
+    builtins[offset_for_builtin("registry_get")] = Box::new(BfRegistryGet {});
+    builtins[offset_for_builtin("registry_set")] = Box::new(BfRegistrySet {});
+    builtins[offset_for_builtin("registry_delete")] = Box::new(BfRegistryDelete {});
+    builtins[offset_for_builtin("registry_list")] = Box::new(BfRegistryList {});
+    builtins[offset_for_builtin("compile_options")] = Box::new(BfCompileOptions {});
+    builtins[offset_for_builtin("set_compile_options")] = Box::new(BfSetCompileOptions {});
+    builtins[offset_for_builtin("cache_stats")] = Box::new(BfCacheStats {});
+    builtins[offset_for_builtin("verb_cache_stats")] = Box::new(BfVerbCacheStats {});
+    builtins[offset_for_builtin("flush_caches")] = Box::new(BfFlushCaches {});
+    builtins[offset_for_builtin("set_cache_capacity")] = Box::new(BfSetCacheCapacity {});
 }
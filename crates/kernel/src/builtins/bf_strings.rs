@@ -11,16 +11,22 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use std::iter::Peekable;
+use std::str::Chars;
+
 use md5::Digest;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 
 use moor_compiler::offset_for_builtin;
-use moor_values::Error::{E_ARGS, E_INVARG, E_TYPE};
+use moor_values::util::{casefold, normalize, NormalizationForm};
+use moor_values::Error::{E_ARGS, E_INVARG, E_RANGE, E_TYPE};
 use moor_values::{v_int, v_str, v_string};
-use moor_values::{Sequence, Variant};
+use moor_values::{Associative, Error, Sequence, Var, Variant};
 
 use crate::bf_declare;
+use crate::builtins::bf_num::FLOATSTR_MAX_PRECISION;
+use crate::builtins::bf_values::push_tostr;
 use crate::builtins::BfRet::Ret;
 use crate::builtins::{BfCallState, BfErr, BfRet, BuiltinFunction};
 
@@ -166,6 +172,61 @@ fn bf_strcmp(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(strcmp, bf_strcmp);
 
+fn bf_normalize(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  normalize(str string [, str form])   => str
+    //
+    // Returns <string> in Unicode normalization form <form>: "NFC" (the default), "NFD", "NFKC"
+    // or "NFKD".
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(s) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let form = if bf_args.args.len() == 2 {
+        let Variant::Str(form) = bf_args.args[1].variant() else {
+            return Err(BfErr::Code(E_TYPE));
+        };
+        NormalizationForm::from_name(form.as_string()).ok_or(BfErr::Code(E_INVARG))?
+    } else {
+        NormalizationForm::Nfc
+    };
+    Ok(Ret(v_string(normalize(s.as_string(), form))))
+}
+bf_declare!(normalize, bf_normalize);
+
+fn bf_casefold(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  casefold(str string)   => str
+    //
+    // Returns the full Unicode case folding of <string>, for case-insensitive comparison.
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(s) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    Ok(Ret(v_string(casefold(s.as_string()))))
+}
+bf_declare!(casefold, bf_casefold);
+
+fn bf_caseless_cmp(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    // Syntax:  caseless_cmp(str str1, str str2)   => int
+    //
+    // Like strcmp(), but compares the case folded, normalized forms of the two strings, so that
+    // it is zero whenever they are caseless matches, whatever their case or composition.
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let (str1, str2) = (bf_args.args[0].variant(), bf_args.args[1].variant());
+    match (str1, str2) {
+        (Variant::Str(str1), Variant::Str(str2)) => Ok(Ret(v_int(
+            casefold(str1.as_string()).cmp(&casefold(str2.as_string())) as i64,
+        ))),
+        _ => Err(BfErr::Code(E_TYPE)),
+    }
+}
+bf_declare!(caseless_cmp, bf_caseless_cmp);
+
 /*
 str crypt (str text [, str salt])
 
@@ -219,6 +280,181 @@ fn bf_string_hash(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(string_hash, bf_string_hash);
 
+/// Which argument a `sprintf` directive takes its value from.
+enum FormatArg {
+    /// The next argument in sequence.
+    Next,
+    /// `%N$...`: the Nth (1-based) argument.
+    Position(usize),
+    /// `%(name)...`: the value under `name` in the map which is the first argument.
+    Named(String),
+}
+
+/// Pad `body` out to `width` characters, on the right if `left` is set, otherwise on the left
+/// with `fill`. A leading sign stays in front of any zero padding.
+fn pad(body: String, width: usize, left: bool, fill: char) -> String {
+    let len = body.chars().count();
+    if len >= width {
+        return body;
+    }
+    let padding = width - len;
+    if left {
+        return format!("{}{}", body, " ".repeat(padding));
+    }
+    if fill == '0' {
+        if let Some(digits) = body.strip_prefix('-') {
+            return format!("-{}{}", "0".repeat(padding), digits);
+        }
+    }
+    format!("{}{}", fill.to_string().repeat(padding), body)
+}
+
+/// The largest width, precision or position a sprintf() directive may give.
+const SPRINTF_MAX_COUNT: usize = 4096;
+
+/// Read the digits of a width, precision or position from a sprintf() directive; none is 0.
+fn sprintf_count(chars: &mut Peekable<Chars<'_>>) -> Result<usize, Error> {
+    let mut count: usize = 0;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        count = count
+            .checked_mul(10)
+            .and_then(|count| count.checked_add(digit as usize))
+            .filter(|count| *count <= SPRINTF_MAX_COUNT)
+            .ok_or(E_INVARG)?;
+        chars.next();
+    }
+    Ok(count)
+}
+
+/// Expand a printf-style `format` string against `args`.
+///
+/// Directives are `%[N$|(name)][-][0][width][.precision]conversion`, where conversion is one of
+/// `s` (as `tostr()`), `d` (integer), `f` (float, 6 places by default), `x` / `X` (hexadecimal)
+/// or `%` for a literal percent sign. Widths, precisions and positions past `SPRINTF_MAX_COUNT`,
+/// or `%f` precisions past floatstr()'s, are `E_INVARG`.
+fn sprintf(format: &str, args: &[Var]) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut chars = format.chars().peekable();
+    let mut next_arg = 0;
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            result.push('%');
+            continue;
+        }
+
+        // Which argument: named, positional, or just the next one.
+        let mut arg = FormatArg::Next;
+        if chars.peek() == Some(&'(') {
+            chars.next();
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some(')') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(E_INVARG),
+                }
+            }
+            arg = FormatArg::Named(name);
+        }
+
+        // Flags, then width. A run of digits followed by `$` was a position, not a width.
+        let mut left = false;
+        let mut fill = ' ';
+        loop {
+            match chars.peek() {
+                Some('-') => left = true,
+                Some('0') => fill = '0',
+                _ => break,
+            }
+            chars.next();
+        }
+        let mut width = sprintf_count(&mut chars)?;
+        if chars.peek() == Some(&'$') {
+            chars.next();
+            if width == 0 || !matches!(arg, FormatArg::Next) {
+                return Err(E_INVARG);
+            }
+            arg = FormatArg::Position(width);
+            loop {
+                match chars.peek() {
+                    Some('-') => left = true,
+                    Some('0') => fill = '0',
+                    _ => break,
+                }
+                chars.next();
+            }
+            width = sprintf_count(&mut chars)?;
+        }
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            precision = Some(sprintf_count(&mut chars)?);
+        }
+
+        let value = match arg {
+            FormatArg::Next => {
+                next_arg += 1;
+                args.get(next_arg - 1).cloned().ok_or(E_ARGS)?
+            }
+            FormatArg::Position(n) => args.get(n - 1).cloned().ok_or(E_ARGS)?,
+            FormatArg::Named(name) => {
+                let Some(Variant::Map(map)) = args.first().map(Var::variant) else {
+                    return Err(E_TYPE);
+                };
+                map.index(&v_string(name)).map_err(|_| E_RANGE)?
+            }
+        };
+
+        let body = match (chars.next(), value.variant()) {
+            (Some('s'), _) => {
+                let mut body = String::new();
+                push_tostr(&mut body, &value);
+                match precision {
+                    Some(p) => body.chars().take(p).collect(),
+                    None => body,
+                }
+            }
+            (Some('d'), Variant::Int(i)) => i.to_string(),
+            (Some('d'), Variant::Float(f)) => (f.trunc() as i64).to_string(),
+            (Some('f'), _) if precision.is_some_and(|p| p > FLOATSTR_MAX_PRECISION) => {
+                return Err(E_INVARG)
+            }
+            (Some('f'), Variant::Int(i)) => format!("{:.*}", precision.unwrap_or(6), *i as f64),
+            (Some('f'), Variant::Float(f)) => format!("{:.*}", precision.unwrap_or(6), f),
+            (Some('x'), Variant::Int(i)) => format!("{:x}", i),
+            (Some('X'), Variant::Int(i)) => format!("{:X}", i),
+            (Some('d' | 'f' | 'x' | 'X'), _) => return Err(E_TYPE),
+            _ => return Err(E_INVARG),
+        };
+        // Zero padding only makes sense for numbers.
+        let fill = if matches!(value.variant(), Variant::Int(_) | Variant::Float(_)) {
+            fill
+        } else {
+            ' '
+        };
+        result.push_str(&pad(body, width, left, fill));
+    }
+    Ok(result)
+}
+
+fn bf_sprintf(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(format) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    let args: Vec<Var> = bf_args.args.iter().skip(1).collect();
+    let result = sprintf(format.as_string(), &args).map_err(BfErr::Code)?;
+    Ok(Ret(v_string(result)))
+}
+bf_declare!(sprintf, bf_sprintf);
+
 fn bf_binary_hash(_bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     unimplemented!("binary_hash")
 }
@@ -232,11 +468,17 @@ pub(crate) fn register_bf_strings(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("crypt")] = Box::new(BfCrypt {});
     builtins[offset_for_builtin("string_hash")] = Box::new(BfStringHash {});
     builtins[offset_for_builtin("binary_hash")] = Box::new(BfBinaryHash {});
+    builtins[offset_for_builtin("sprintf")] = Box::new(BfSprintf {});
+    builtins[offset_for_builtin("normalize")] = Box::new(BfNormalize {});
+    builtins[offset_for_builtin("casefold")] = Box::new(BfCasefold {});
+    builtins[offset_for_builtin("caseless_cmp")] = Box::new(BfCaselessCmp {});
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::builtins::bf_strings::strsub;
+    use crate::builtins::bf_strings::{sprintf, strsub};
+    use moor_values::Error::{E_ARGS, E_INVARG, E_TYPE};
+    use moor_values::{v_float, v_int, v_map, v_str};
 
     #[test]
     fn test_strsub_remove_piece() {
@@ -288,4 +530,60 @@ mod tests {
         let expected = "foo bar baz";
         assert_eq!(strsub(subject, "fizz", "buzz", false), expected);
     }
+
+    #[test]
+    fn test_sprintf_conversions() {
+        let args = [v_str("abc"), v_int(42), v_float(1.23456), v_int(255)];
+        assert_eq!(
+            sprintf("%s %d %.2f %x %X %%", &args).unwrap(),
+            "abc 42 1.23 ff FF %"
+        );
+        assert_eq!(sprintf("%f", &[v_int(2)]).unwrap(), "2.000000");
+        assert_eq!(sprintf("%d", &[v_float(-2.7)]).unwrap(), "-2");
+        assert_eq!(sprintf("%.2s", &[v_str("abc")]).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_sprintf_padding() {
+        assert_eq!(sprintf("[%5s]", &[v_str("ab")]).unwrap(), "[   ab]");
+        assert_eq!(sprintf("[%-5s]", &[v_str("ab")]).unwrap(), "[ab   ]");
+        assert_eq!(sprintf("[%05d]", &[v_int(-42)]).unwrap(), "[-0042]");
+        assert_eq!(sprintf("[%05s]", &[v_str("ab")]).unwrap(), "[   ab]");
+        assert_eq!(sprintf("[%8.3f]", &[v_float(1.5)]).unwrap(), "[   1.500]");
+    }
+
+    #[test]
+    fn test_sprintf_positional_and_named() {
+        let args = [v_str("a"), v_str("b")];
+        assert_eq!(sprintf("%2$s%1$s%2$s", &args).unwrap(), "bab");
+        assert_eq!(sprintf("%2$-3s|", &args).unwrap(), "b  |");
+
+        let map = v_map(&[(v_str("name"), v_str("Fred")), (v_str("n"), v_int(3))]);
+        assert_eq!(
+            sprintf("%(name)s has %(n)03d", &[map]).unwrap(),
+            "Fred has 003"
+        );
+    }
+
+    #[test]
+    fn test_sprintf_errors() {
+        assert_eq!(sprintf("%s %s", &[v_str("a")]), Err(E_ARGS));
+        assert_eq!(sprintf("%d", &[v_str("a")]), Err(E_TYPE));
+        assert_eq!(sprintf("%q", &[v_str("a")]), Err(E_INVARG));
+        assert_eq!(sprintf("%4097s", &[v_str("a")]), Err(E_INVARG));
+        assert_eq!(
+            sprintf("%99999999999999999999999s", &[v_str("a")]),
+            Err(E_INVARG)
+        );
+        assert_eq!(
+            sprintf("%.99999999999999999999999s", &[v_str("a")]),
+            Err(E_INVARG)
+        );
+        assert_eq!(
+            sprintf("%99999999999999999999999$s", &[v_str("a")]),
+            Err(E_INVARG)
+        );
+        assert_eq!(sprintf("%.20f", &[v_float(1.0)]), Err(E_INVARG));
+        assert_eq!(sprintf("%(x)s", &[v_str("a")]), Err(E_TYPE));
+    }
 }
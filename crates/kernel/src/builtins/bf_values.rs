@@ -14,16 +14,18 @@
 use crate::bf_declare;
 use crate::builtins::BfRet::Ret;
 use crate::builtins::{world_state_bf_err, BfCallState, BfErr, BfRet, BuiltinFunction};
+use crate::vm::VMExecState;
 use md5::Digest;
 use moor_compiler::{offset_for_builtin, to_literal};
-use moor_values::model::WorldState;
-use moor_values::Error::{E_ARGS, E_INVARG, E_INVIND, E_PERM, E_RANGE, E_TYPE};
+use moor_values::model::{ValSet, WorldState};
+use moor_values::Error::{E_ARGS, E_FLOAT, E_INVARG, E_INVIND, E_PERM, E_QUOTA, E_RANGE, E_TYPE};
 use moor_values::{
-    v_bool, v_float, v_int, v_list, v_obj, v_objid, v_str, v_string, Flyweight, List, Map, Obj,
+    v_bool, v_float, v_int, v_list, v_map, v_obj, v_objid, v_str, v_string, Flyweight, List, Map,
+    Obj,
 };
 use moor_values::{v_flyweight, Associative};
 use moor_values::{AsByteBuffer, Sequence};
-use moor_values::{Symbol, Variant, SYSTEM_OBJECT};
+use moor_values::{Symbol, Var, Variant, NOTHING, SYSTEM_OBJECT};
 use std::io::{BufReader, BufWriter};
 use tracing::error;
 use xml::reader::XmlEvent;
@@ -35,26 +37,31 @@ fn bf_typeof(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(typeof, bf_typeof);
 
+/// Append the `tostr()` rendering of `arg` to `result`.
+pub(crate) fn push_tostr(result: &mut String, arg: &Var) {
+    match arg.variant() {
+        Variant::None => result.push_str("None"),
+        Variant::Int(i) => result.push_str(&i.to_string()),
+        Variant::Float(f) => result.push_str(format!("{:?}", f).as_str()),
+        Variant::Str(s) => result.push_str(s.as_string().as_str()),
+        Variant::Obj(o) => result.push_str(&o.to_string()),
+        Variant::List(_) => result.push_str("{list}"),
+        Variant::Map(_) => result.push_str("[map]"),
+        Variant::Err(e) => result.push_str(e.name()),
+        Variant::Flyweight(fl) => {
+            if fl.is_sealed() {
+                result.push_str("<sealed flyweight>")
+            } else {
+                result.push_str("<flyweight>")
+            }
+        }
+    }
+}
+
 fn bf_tostr(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     let mut result = String::new();
     for arg in bf_args.args.iter() {
-        match arg.variant() {
-            Variant::None => result.push_str("None"),
-            Variant::Int(i) => result.push_str(&i.to_string()),
-            Variant::Float(f) => result.push_str(format!("{:?}", f).as_str()),
-            Variant::Str(s) => result.push_str(s.as_string().as_str()),
-            Variant::Obj(o) => result.push_str(&o.to_string()),
-            Variant::List(_) => result.push_str("{list}"),
-            Variant::Map(_) => result.push_str("[map]"),
-            Variant::Err(e) => result.push_str(e.name()),
-            Variant::Flyweight(fl) => {
-                if fl.is_sealed() {
-                    result.push_str("<sealed flyweight>")
-                } else {
-                    result.push_str("<flyweight>")
-                }
-            }
-        }
+        push_tostr(&mut result, &arg);
     }
     Ok(Ret(v_str(result.as_str())))
 }
@@ -69,21 +76,88 @@ fn bf_toliteral(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(toliteral, bf_toliteral);
 
+/// The number in `s`, read the same way whatever the host's locale: an optional sign, digits with
+/// an optional `.` fraction, and an optional exponent, with surrounding whitespace ignored. Returns
+/// the trimmed text, and whether it has a fraction or exponent. Anything else, including trailing
+/// garbage and Rust's `inf` and `NaN`, isn't a number.
+fn scan_number(s: &str) -> Option<(&str, bool)> {
+    let text = s.trim();
+    let bytes = text.as_bytes();
+    let digits_from = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        i += 1;
+    }
+    let int_end = digits_from(i);
+    let mut mantissa_digits = int_end - i;
+    let mut is_float = false;
+    i = int_end;
+    if bytes.get(i) == Some(&b'.') {
+        let frac_end = digits_from(i + 1);
+        mantissa_digits += frac_end - (i + 1);
+        is_float = true;
+        i = frac_end;
+    }
+    if mantissa_digits == 0 {
+        return None;
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let mut exp = i + 1;
+        if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+            exp += 1;
+        }
+        let exp_end = digits_from(exp);
+        if exp_end == exp {
+            return None;
+        }
+        is_float = true;
+        i = exp_end;
+    }
+    (i == bytes.len()).then_some((text, is_float))
+}
+
+/// The value of a number found by `scan_number`, raising E_FLOAT if it's too large for a FLOAT.
+fn float_of(n: &str) -> Result<f64, BfErr> {
+    let f = n.parse::<f64>().map_err(|_| BfErr::Code(E_INVARG))?;
+    if f.is_finite() {
+        Ok(f)
+    } else {
+        Err(BfErr::Code(E_FLOAT))
+    }
+}
+
+/// A string which isn't a number is 0 to `toint()` and `tofloat()`, as in LambdaMOO, unless their
+/// optional `strict` argument is true, in which case it raises E_INVARG.
+fn not_a_number(bf_args: &BfCallState<'_>, zero: Var) -> Result<BfRet, BfErr> {
+    match bf_args.args.get(1) {
+        Some(strict) if strict.is_true() => Err(BfErr::Code(E_INVARG)),
+        _ => Ok(Ret(zero)),
+    }
+}
+
 fn bf_toint(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() != 1 {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
         return Err(BfErr::Code(E_ARGS));
     }
     match bf_args.args[0].variant() {
         Variant::Int(i) => Ok(Ret(v_int(*i))),
         Variant::Float(f) => Ok(Ret(v_int(*f as i64))),
         Variant::Obj(o) => Ok(Ret(v_int(o.id().0 as i64))),
-        Variant::Str(s) => {
-            let i = s.as_string().as_str().parse::<f64>();
-            match i {
-                Ok(i) => Ok(Ret(v_int(i as i64))),
-                Err(_) => Ok(Ret(v_int(0))),
-            }
-        }
+        Variant::Str(s) => match scan_number(s.as_string().as_str()) {
+            // Integers are parsed as such, so that large ones don't lose precision on the way
+            // through a float; those too large for an INT saturate, as floats do.
+            Some((n, false)) => match n.parse::<i64>() {
+                Ok(i) => Ok(Ret(v_int(i))),
+                Err(_) => Ok(Ret(v_int(n.parse::<f64>().unwrap() as i64))),
+            },
+            Some((n, true)) => Ok(Ret(v_int(n.parse::<f64>().unwrap() as i64))),
+            None => not_a_number(bf_args, v_int(0)),
+        },
         Variant::Err(e) => Ok(Ret(v_int(*e as i64))),
         _ => Err(BfErr::Code(E_INVARG)),
     }
@@ -131,25 +205,42 @@ fn bf_toobj(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 bf_declare!(toobj, bf_toobj);
 
 fn bf_tofloat(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() != 1 {
+    if bf_args.args.is_empty() || bf_args.args.len() > 2 {
         return Err(BfErr::Code(E_ARGS));
     }
     match bf_args.args[0].variant() {
         Variant::Int(i) => Ok(Ret(v_float(*i as f64))),
         Variant::Float(f) => Ok(Ret(v_float(*f))),
-        Variant::Str(s) => {
-            let f = s.as_string().as_str().parse::<f64>();
-            match f {
-                Ok(f) => Ok(Ret(v_float(f))),
-                Err(_) => Ok(Ret(v_float(0.0))),
-            }
-        }
+        Variant::Str(s) => match scan_number(s.as_string().as_str()) {
+            Some((n, _)) => Ok(Ret(v_float(float_of(n)?))),
+            None => not_a_number(bf_args, v_float(0.0)),
+        },
         Variant::Err(e) => Ok(Ret(v_float(*e as u8 as f64))),
         _ => Err(BfErr::Code(E_INVARG)),
     }
 }
 bf_declare!(tofloat, bf_tofloat);
 
+/// Parse a string as a number, strictly: an INT if it's written as one, a FLOAT if it has a
+/// fraction or exponent, and E_INVARG if it's anything else, or an integer too large for an INT.
+fn bf_parse_number(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Str(s) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    match scan_number(s.as_string().as_str()) {
+        Some((n, false)) => n
+            .parse::<i64>()
+            .map(|i| Ret(v_int(i)))
+            .map_err(|_| BfErr::Code(E_INVARG)),
+        Some((n, true)) => Ok(Ret(v_float(float_of(n)?))),
+        None => Err(BfErr::Code(E_INVARG)),
+    }
+}
+bf_declare!(parse_number, bf_parse_number);
+
 fn bf_equal(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if bf_args.args.len() != 2 {
         return Err(BfErr::Code(E_ARGS));
@@ -160,6 +251,153 @@ fn bf_equal(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(equal, bf_equal);
 
+/// Ticks left to spend on walking a value in `equal_deep()` or `copy()`, one per value visited.
+struct WalkBudget {
+    left: usize,
+    spent: usize,
+}
+
+impl WalkBudget {
+    fn new(exec_state: &VMExecState) -> Self {
+        Self {
+            left: exec_state.ticks_left(),
+            spent: 0,
+        }
+    }
+
+    fn charge(&mut self) -> Result<(), BfErr> {
+        if self.spent >= self.left {
+            return Err(BfErr::Code(E_QUOTA));
+        }
+        self.spent += 1;
+        Ok(())
+    }
+}
+
+/// Case-sensitive structural equality, looking into lists, maps and flyweights alike. Values are
+/// immutable, so there are no cycles to guard against; size is bounded by the tick budget instead.
+fn equal_deep(a: &Var, b: &Var, budget: &mut WalkBudget) -> Result<bool, BfErr> {
+    budget.charge()?;
+    match (a.variant(), b.variant()) {
+        (Variant::List(l1), Variant::List(l2)) => {
+            if l1.len() != l2.len() {
+                return Ok(false);
+            }
+            for (left, right) in l1.iter().zip(l2.iter()) {
+                if !equal_deep(&left, &right, budget)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Variant::Map(m1), Variant::Map(m2)) => {
+            if m1.len() != m2.len() {
+                return Ok(false);
+            }
+            for ((k1, v1), (k2, v2)) in m1.iter().zip(m2.iter()) {
+                if !equal_deep(&k1, &k2, budget)? || !equal_deep(&v1, &v2, budget)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Variant::Flyweight(f1), Variant::Flyweight(f2)) => {
+            if f1.delegate() != f2.delegate()
+                || f1.seal() != f2.seal()
+                || f1.slots().len() != f2.slots().len()
+                || f1.contents().len() != f2.contents().len()
+            {
+                return Ok(false);
+            }
+            for ((n1, v1), (n2, v2)) in f1.slots().iter().zip(f2.slots().iter()) {
+                if n1 != n2 || !equal_deep(v1, v2, budget)? {
+                    return Ok(false);
+                }
+            }
+            for (left, right) in f1.contents().iter().zip(f2.contents().iter()) {
+                if !equal_deep(&left, &right, budget)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        _ => Ok(a.eq_case_sensitive(b)),
+    }
+}
+
+/// Rebuild a value from scratch, so that it no longer shares storage with whatever it was sliced,
+/// spliced or appended from.
+fn copy_deep(v: &Var, budget: &mut WalkBudget) -> Result<Var, BfErr> {
+    budget.charge()?;
+    match v.variant() {
+        Variant::List(l) => {
+            let items = l
+                .iter()
+                .map(|item| copy_deep(&item, budget))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(v_list(&items))
+        }
+        Variant::Map(m) => {
+            let pairs = m
+                .iter()
+                .map(|(k, v)| Ok((copy_deep(&k, budget)?, copy_deep(&v, budget)?)))
+                .collect::<Result<Vec<_>, BfErr>>()?;
+            Ok(v_map(&pairs))
+        }
+        Variant::Str(s) => Ok(v_str(s.as_string())),
+        Variant::Flyweight(fl) => {
+            let slots = fl
+                .slots()
+                .iter()
+                .map(|(name, value)| Ok((*name, copy_deep(value, budget)?)))
+                .collect::<Result<Vec<_>, BfErr>>()?;
+            let contents = fl
+                .contents()
+                .iter()
+                .map(|item| copy_deep(&item, budget))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(v_flyweight(
+                fl.delegate().clone(),
+                &slots,
+                List::mk_list(&contents),
+                fl.seal().cloned(),
+            ))
+        }
+        _ => Ok(v.clone()),
+    }
+}
+
+/// equal_deep(a, b) -> bool
+///
+/// Like `equal()`, but also compares flyweights slot by slot. Costs a tick for every value looked
+/// at, raising E_QUOTA if the task runs out of them.
+fn bf_equal_deep(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 2 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let mut budget = WalkBudget::new(bf_args.exec_state);
+    let result = equal_deep(&bf_args.args[0], &bf_args.args[1], &mut budget);
+    bf_args.exec_state.tick_count += budget.spent;
+    Ok(Ret(v_bool(result?)))
+}
+bf_declare!(equal_deep, bf_equal_deep);
+
+/// copy(value) -> value
+///
+/// Returns a fresh copy of `value`. Values never change underneath you, so this is only worth
+/// doing to let go of a large list or string that a small one was taken from. Costs a tick for
+/// every value copied, raising E_QUOTA if the task runs out of them.
+fn bf_copy(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let mut budget = WalkBudget::new(bf_args.exec_state);
+    let result = copy_deep(&bf_args.args[0], &mut budget);
+    bf_args.exec_state.tick_count += budget.spent;
+    Ok(Ret(result?))
+}
+bf_declare!(copy, bf_copy);
+
 fn bf_value_bytes(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     if bf_args.args.len() != 1 {
         return Err(BfErr::Code(E_ARGS));
@@ -475,6 +713,124 @@ fn bf_to_xml(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(to_xml, bf_to_xml);
 
+/// Check that the flyweight argument to one of the introspection functions may be looked into:
+/// the contents of a sealed flyweight are only visible to wizards (or via `unseal()`).
+fn inspectable_flyweight<'a>(bf_args: &'a BfCallState<'_>) -> Result<&'a Flyweight, BfErr> {
+    if !bf_args.config.flyweight_type {
+        return Err(BfErr::Code(E_PERM));
+    }
+    if bf_args.args.len() != 1 {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Flyweight(fl) = bf_args.args[0].variant() else {
+        return Err(BfErr::Code(E_TYPE));
+    };
+    if fl.is_sealed() {
+        bf_args
+            .task_perms()
+            .map_err(world_state_bf_err)?
+            .check_wizard()
+            .map_err(world_state_bf_err)?;
+    }
+    Ok(fl)
+}
+
+fn flyweight_slots_map(fl: &Flyweight) -> Var {
+    let slots: Vec<_> = fl
+        .slots()
+        .iter()
+        .map(|(name, value)| (v_str(name.as_str()), value.clone()))
+        .collect();
+    v_map(&slots)
+}
+
+/// flyweight_slots(flyweight) -> map
+///
+/// Returns the slots of a flyweight as a map from slot name to value. E_PERM if the flyweight is
+/// sealed, unless the caller is a wizard.
+fn bf_flyweight_slots(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    let fl = inspectable_flyweight(bf_args)?;
+    Ok(Ret(flyweight_slots_map(fl)))
+}
+bf_declare!(flyweight_slots, bf_flyweight_slots);
+
+/// flyweight_info(flyweight) -> map
+///
+/// Returns everything there is to know about a flyweight, for generic serializers and debuggers:
+/// `["delegate" -> obj, "slots" -> map, "contents" -> list, "sealed" -> bool]`. The same
+/// permission rules as `flyweight_slots()` apply.
+fn bf_flyweight_info(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    let fl = inspectable_flyweight(bf_args)?;
+    let info = [
+        (v_str("delegate"), v_obj(fl.delegate().clone())),
+        (v_str("slots"), flyweight_slots_map(fl)),
+        (
+            v_str("contents"),
+            Var::from_variant(Variant::List(fl.contents().clone())),
+        ),
+        (v_str("sealed"), v_bool(fl.is_sealed())),
+    ];
+    Ok(Ret(v_map(&info)))
+}
+bf_declare!(flyweight_info, bf_flyweight_info);
+
+/// new_waif() -> flyweight
+///
+/// For cores written for ToastStunt's waifs, with the `waif_compat` feature on. Returns a
+/// flyweight delegating to the calling verb's `this`, with a slot for each property on it (or its
+/// ancestors) whose name begins with a colon, holding that property's value. The slots are named
+/// without the colon, so `:name` is read as `w.name`. Unlike a waif, the result is a value: there
+/// is no `w.name = value`, only making a new flyweight with the changed slots.
+fn bf_new_waif(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
+    if !bf_args.config.flyweight_type || !bf_args.config.waif_compat {
+        return Err(BfErr::Code(E_PERM));
+    }
+    if !bf_args.args.is_empty() {
+        return Err(BfErr::Code(E_ARGS));
+    }
+    let Variant::Obj(class) = bf_args.exec_state.caller().variant().clone() else {
+        return Err(BfErr::Code(E_INVARG));
+    };
+    let perms = bf_args.task_perms_who();
+    if !bf_args
+        .world_state
+        .valid(&class)
+        .map_err(world_state_bf_err)?
+    {
+        return Err(BfErr::Raise(
+            E_INVARG,
+            Some("new_waif() must be called from a verb on a valid object".to_string()),
+            None,
+        ));
+    }
+
+    let mut slots: Vec<(Symbol, Var)> = vec![];
+    let mut ancestor = class.clone();
+    while ancestor != NOTHING {
+        let propdefs = bf_args
+            .world_state
+            .properties(&perms, &ancestor)
+            .map_err(world_state_bf_err)?;
+        for propdef in propdefs.iter() {
+            let Some(slot_name) = propdef.name().strip_prefix(':') else {
+                continue;
+            };
+            let value = bf_args
+                .world_state
+                .retrieve_property(&perms, &class, Symbol::mk_case_insensitive(propdef.name()))
+                .map_err(world_state_bf_err)?;
+            slots.push((Symbol::mk_case_insensitive(slot_name), value));
+        }
+        ancestor = bf_args
+            .world_state
+            .parent_of(&perms, &ancestor)
+            .map_err(world_state_bf_err)?;
+    }
+
+    Ok(Ret(v_flyweight(class, &slots, List::mk_list(&[]), None)))
+}
+bf_declare!(new_waif, bf_new_waif);
+
 pub(crate) fn register_bf_values(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("typeof")] = Box::new(BfTypeof {});
     builtins[offset_for_builtin("tostr")] = Box::new(BfTostr {});
@@ -483,7 +839,10 @@ pub(crate) fn register_bf_values(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("tonum")] = Box::new(BfToint {});
     builtins[offset_for_builtin("toobj")] = Box::new(BfToobj {});
     builtins[offset_for_builtin("tofloat")] = Box::new(BfTofloat {});
+    builtins[offset_for_builtin("parse_number")] = Box::new(BfParseNumber {});
     builtins[offset_for_builtin("equal")] = Box::new(BfEqual {});
+    builtins[offset_for_builtin("equal_deep")] = Box::new(BfEqualDeep {});
+    builtins[offset_for_builtin("copy")] = Box::new(BfCopy {});
     builtins[offset_for_builtin("value_bytes")] = Box::new(BfValueBytes {});
     builtins[offset_for_builtin("object_bytes")] = Box::new(BfObjectBytes {});
     builtins[offset_for_builtin("value_hash")] = Box::new(BfValueHash {});
@@ -492,4 +851,7 @@ pub(crate) fn register_bf_values(builtins: &mut [Box<dyn BuiltinFunction>]) {
     // Extensions...
     builtins[offset_for_builtin("xml_parse")] = Box::new(BfXmlParse {});
     builtins[offset_for_builtin("to_xml")] = Box::new(BfToXml {});
+    builtins[offset_for_builtin("flyweight_slots")] = Box::new(BfFlyweightSlots {});
+    builtins[offset_for_builtin("flyweight_info")] = Box::new(BfFlyweightInfo {});
+    builtins[offset_for_builtin("new_waif")] = Box::new(BfNewWaif {});
 }
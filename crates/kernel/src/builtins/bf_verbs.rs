@@ -17,12 +17,14 @@ use tracing::{error, warn};
 use crate::bf_declare;
 use crate::builtins::BfRet::Ret;
 use crate::builtins::{world_state_bf_err, BfCallState, BfErr, BfRet, BuiltinFunction};
+use crate::config::{compile_options_map, with_compile_options};
 use moor_compiler::offset_for_builtin;
 use moor_compiler::program_to_tree;
 use moor_compiler::unparse;
 use moor_compiler::GlobalName;
 use moor_compiler::Program;
 use moor_compiler::{compile, to_literal};
+use moor_compiler::{Label, Name, Op, ScatterLabel, BUILTINS};
 use moor_values::matching::command_parse::{parse_preposition_spec, preposition_to_string};
 use moor_values::model::ObjFlag;
 use moor_values::model::VerbDef;
@@ -36,15 +38,21 @@ use moor_values::List;
 use moor_values::Obj;
 use moor_values::Symbol;
 use moor_values::Variant;
-use moor_values::{v_empty_list, v_list, v_none, v_obj, v_str, v_string, Var};
+use moor_values::{
+    v_empty_list, v_err, v_float, v_int, v_list, v_map, v_none, v_obj, v_str, v_string, Var,
+};
 use moor_values::{v_list_iter, Error};
 use moor_values::{AsByteBuffer, Sequence};
 
-// verb_info (obj <object>, str <verb-desc>) ->  {<owner>, <perms>, <names>}
+// verb_info (obj <object>, str <verb-desc> [, extended]) ->  {<owner>, <perms>, <names> [, <compile-options>]}
+//
+// If <extended> is true, the result also has the language features the verb was last compiled
+// with, as a map from option name to whether it was on, or an empty map if that wasn't recorded.
 fn bf_verb_info(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() != 2 {
+    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
         return Err(BfErr::Code(E_ARGS));
     }
+    let extended = bf_args.args.len() == 3 && bf_args.args[2].is_true();
     let Variant::Obj(obj) = bf_args.args[0].variant() else {
         return Err(BfErr::Code(E_TYPE));
     };
@@ -97,8 +105,15 @@ fn bf_verb_info(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
     // Join names into a single string, this is how MOO presents it.
     let verb_names = names.join(" ");
 
-    let result = v_list(&[v_obj(owner), v_string(perms_string), v_string(verb_names)]);
-    Ok(Ret(result))
+    let mut result = vec![v_obj(owner), v_string(perms_string), v_string(verb_names)];
+    if extended {
+        let options = bf_args
+            .world_state
+            .verb_compile_options(&bf_args.task_perms_who(), obj, verb_info.uuid())
+            .map_err(world_state_bf_err)?;
+        result.push(options.unwrap_or_else(|| v_map(&[])));
+    }
+    Ok(Ret(v_list(&result)))
 }
 bf_declare!(verb_info, bf_verb_info);
 
@@ -397,10 +412,14 @@ fn bf_verb_code(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(verb_code, bf_verb_code);
 
-// Function: list set_verb_code (obj object, str verb-desc, list code)
+// Function: list set_verb_code (obj object, str verb-desc, list code [, map compile-options])
+//
+// The code is compiled with the database's language features (see compile_options()), with any
+// given in <compile-options> turned on or off, and those it was compiled with are recorded for
+// verb_info() to report.
 fn bf_set_verb_code(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    //set_verb_code (obj object, str verb-desc, list code) => none
-    if bf_args.args.len() != 3 {
+    //set_verb_code (obj object, str verb-desc, list code [, map compile-options]) => none
+    if bf_args.args.len() < 3 || bf_args.args.len() > 4 {
         return Err(BfErr::Code(E_ARGS));
     }
     let Variant::Obj(obj) = bf_args.args[0].variant() else {
@@ -439,8 +458,16 @@ fn bf_set_verb_code(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         code_string.push_str(line.as_string());
         code_string.push('\n');
     }
+    let mut compile_options = bf_args
+        .config
+        .compile_options_for(bf_args.world_state)
+        .map_err(world_state_bf_err)?;
+    if bf_args.args.len() == 4 {
+        compile_options =
+            with_compile_options(compile_options, &bf_args.args[3]).map_err(BfErr::Code)?;
+    }
     // Now try to compile...
-    let program = match compile(code_string.as_str(), bf_args.config.compile_options()) {
+    let program = match compile(code_string.as_str(), compile_options.clone()) {
         Ok(program) => program,
         Err(e) => {
             // For set_verb_code(), the result is a list of strings, the error messages generated by the
@@ -468,6 +495,15 @@ fn bf_set_verb_code(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         .world_state
         .update_verb_with_id(&bf_args.task_perms_who(), obj, verbdef.uuid(), update_attrs)
         .map_err(world_state_bf_err)?;
+    bf_args
+        .world_state
+        .set_verb_compile_options(
+            &bf_args.task_perms_who(),
+            obj,
+            verbdef.uuid(),
+            compile_options_map(&compile_options),
+        )
+        .map_err(world_state_bf_err)?;
     Ok(Ret(v_none()))
 }
 bf_declare!(set_verb_code, bf_set_verb_code);
@@ -553,15 +589,20 @@ fn bf_delete_verb(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(delete_verb, bf_delete_verb);
 
-// Syntax:  disassemble (obj <object>, str <verb-desc>)   => list
+// Syntax:  disassemble (obj <object>, str <verb-desc> [, structured])   => list
 //
 // Returns a (longish) list of strings giving a listing of the server's internal ``compiled'' form of the verb as specified by <verb-desc>
 // on <object>.  This format is not documented and may indeed change from release to release, but some programmers may nonetheless find
 // the output of `disassemble()' interesting to peruse as a way to gain a deeper appreciation of how the server works.
+//
+// If <structured> is true, the verb's main vector is returned instead as a list of maps, one per opcode, with its `pc', the name of its
+// `opcode', its `operands' (jump labels resolved to the pc they jump to, variables to their names, literals to their values, builtins
+// to their names) and the source `line' it was compiled from.
 fn bf_disassemble(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
-    if bf_args.args.len() != 2 {
+    if bf_args.args.len() < 2 || bf_args.args.len() > 3 {
         return Err(BfErr::Code(E_ARGS));
     }
+    let structured = bf_args.args.len() == 3 && bf_args.args[2].is_true();
     let Variant::Obj(obj) = bf_args.args[0].variant() else {
         return Err(BfErr::Code(E_TYPE));
     };
@@ -591,6 +632,23 @@ fn bf_disassemble(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
         BfErr::Code(E_INVARG)
     })?;
 
+    if structured {
+        let listing: Vec<_> = program
+            .main_vector
+            .iter()
+            .enumerate()
+            .map(|(pc, op)| {
+                v_map(&[
+                    (v_str("pc"), v_int(pc as i64)),
+                    (v_str("opcode"), v_string(opcode_name(op))),
+                    (v_str("operands"), v_list(&op_operands(&program, op))),
+                    (v_str("line"), v_int(line_of(&program, pc) as i64)),
+                ])
+            })
+            .collect();
+        return Ok(Ret(v_list(&listing)));
+    }
+
     // The output of disassemble is a list of strings, one for each instruction in the verb's program.
     // But we also want some basic information on # of labels. Fork vectors. Jazz like that.
     // But I'll just keep it simple for now.
@@ -642,6 +700,132 @@ fn bf_disassemble(bf_args: &mut BfCallState<'_>) -> Result<BfRet, BfErr> {
 }
 bf_declare!(disassemble, bf_disassemble);
 
+/// The name of `op`'s variant, e.g. `ForList` for `ForList { id, .. }`.
+fn opcode_name(op: &Op) -> String {
+    let debug = format!("{:?}", op);
+    let end = debug.find([' ', '(']).unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+/// The source line the instruction at `pc` in the main vector was compiled from: that of the
+/// last statement which starts at or before it.
+fn line_of(program: &Program, pc: usize) -> usize {
+    program
+        .line_number_spans
+        .iter()
+        .take_while(|(start, _)| *start <= pc)
+        .last()
+        .map(|(_, line)| *line)
+        .unwrap_or(0)
+}
+
+/// `op`'s operands, resolved against `program` into something a programmer can read.
+fn op_operands(program: &Program, op: &Op) -> Vec<Var> {
+    let label = |l: &Label| {
+        program
+            .jump_labels
+            .get(l.0 as usize)
+            .map(|jl| v_int(jl.position.0 as i64))
+            .unwrap_or_else(|| v_int(l.0 as i64))
+    };
+    let name = |n: &Name| v_string(name_str(program, n));
+    match op {
+        Op::And(l)
+        | Op::EndCatch(l)
+        | Op::EndExcept(l)
+        | Op::ExitId(l)
+        | Op::IfQues(l)
+        | Op::Or(l)
+        | Op::PushCatchLabel(l)
+        | Op::Jump { label: l } => vec![label(l)],
+        Op::If(l, width) | Op::Eif(l, width) => vec![label(l), v_int(*width as i64)],
+        Op::Exit { stack, label: l } => vec![v_int(stack.0 as i64), label(l)],
+        Op::ForList {
+            id,
+            end_label,
+            environment_width,
+        }
+        | Op::ForRange {
+            id,
+            end_label,
+            environment_width,
+        }
+        | Op::WhileId {
+            id,
+            end_label,
+            environment_width,
+        } => vec![name(id), label(end_label), v_int(*environment_width as i64)],
+        Op::While {
+            jump_label,
+            environment_width,
+        } => vec![label(jump_label), v_int(*environment_width as i64)],
+        Op::Fork { fv_offset, id } => {
+            let mut operands = vec![v_int(fv_offset.0 as i64)];
+            operands.extend(id.as_ref().map(name));
+            operands
+        }
+        Op::FuncCall { id } => match BUILTINS.name_of(*id) {
+            Some(bf) => vec![v_str(bf.as_str())],
+            None => vec![v_int(id.0 as i64)],
+        },
+        Op::Imm(l) => match program.literals.get(l.0 as usize) {
+            Some(literal) => vec![literal.clone()],
+            None => vec![v_int(l.0 as i64)],
+        },
+        Op::ImmBigInt(i) => vec![v_int(*i)],
+        Op::ImmInt(i) => vec![v_int(*i as i64)],
+        Op::ImmFloat(f) => vec![v_float(*f)],
+        Op::ImmErr(e) => vec![v_err(*e)],
+        Op::ImmObjid(o) => vec![v_obj(*o)],
+        Op::Length(offset) => vec![v_int(offset.0 as i64)],
+        Op::MakeFlyweight(slots) => vec![v_int(*slots as i64)],
+        Op::Push(n) | Op::Put(n) => vec![name(n)],
+        Op::Scatter(sa) => {
+            let labels: Vec<_> = sa
+                .labels
+                .iter()
+                .map(|sl| match sl {
+                    ScatterLabel::Required(n) => name(n),
+                    ScatterLabel::Optional(n, _) => v_string(format!("?{}", name_str(program, n))),
+                    ScatterLabel::Rest(n) => v_string(format!("@{}", name_str(program, n))),
+                })
+                .collect();
+            vec![v_list(&labels), label(&sa.done)]
+        }
+        Op::TryCatch {
+            handler_label,
+            end_label,
+        } => vec![label(handler_label), label(end_label)],
+        Op::TryExcept {
+            num_excepts,
+            environment_width,
+            end_label,
+        } => vec![
+            v_int(*num_excepts as i64),
+            v_int(*environment_width as i64),
+            label(end_label),
+        ],
+        Op::TryFinally {
+            end_label,
+            environment_width,
+        } => vec![label(end_label), v_int(*environment_width as i64)],
+        Op::BeginScope {
+            num_bindings,
+            end_label,
+        } => vec![v_int(*num_bindings as i64), label(end_label)],
+        Op::EndScope { num_bindings } => vec![v_int(*num_bindings as i64)],
+        _ => vec![],
+    }
+}
+
+fn name_str(program: &Program, name: &Name) -> String {
+    program
+        .var_names
+        .name_of(name)
+        .map(|name| name.to_string())
+        .unwrap_or_default()
+}
+
 pub(crate) fn register_bf_verbs(builtins: &mut [Box<dyn BuiltinFunction>]) {
     builtins[offset_for_builtin("verb_info")] = Box::new(BfVerbInfo {});
     builtins[offset_for_builtin("set_verb_info")] = Box::new(BfSetVerbInfo {});
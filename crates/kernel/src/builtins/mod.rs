@@ -24,8 +24,10 @@ use moor_values::Symbol;
 use moor_values::Var;
 use moor_values::{Error, List};
 
+use crate::builtins::bf_crypto::register_bf_crypto;
 use crate::builtins::bf_list_sets::register_bf_list_sets;
 use crate::builtins::bf_maps::register_bf_maps;
+use crate::builtins::bf_markup::register_bf_markup;
 use crate::builtins::bf_num::register_bf_num;
 use crate::builtins::bf_objects::register_bf_objects;
 use crate::builtins::bf_properties::register_bf_properties;
@@ -39,8 +41,10 @@ use crate::tasks::task_scheduler_client::TaskSchedulerClient;
 use crate::vm::activation::{BfFrame, Frame};
 use crate::vm::{ExecutionResult, VMExecState};
 
+mod bf_crypto;
 mod bf_list_sets;
 mod bf_maps;
+mod bf_markup;
 mod bf_num;
 mod bf_objects;
 mod bf_properties;
@@ -76,6 +80,8 @@ impl BuiltinRegistry {
         register_bf_objects(&mut builtins);
         register_bf_verbs(&mut builtins);
         register_bf_properties(&mut builtins);
+        register_bf_crypto(&mut builtins);
+        register_bf_markup(&mut builtins);
 
         BuiltinRegistry {
             builtins: Arc::new(builtins),
@@ -191,3 +197,17 @@ pub(crate) fn world_state_bf_err(err: WorldStateError) -> BfErr {
         _ => BfErr::Code(err.into()),
     }
 }
+
+/// Encode raw bytes as a MOO "binary string", as in LambdaMOO: printable ASCII other than `~`
+/// passes through as-is, and every other byte is written as `~XX`.
+pub(crate) fn encode_binary_string(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len());
+    for b in bytes {
+        if (b' '..=b'~').contains(b) && *b != b'~' {
+            result.push(*b as char);
+        } else {
+            result.push_str(&format!("~{:02X}", b));
+        }
+    }
+    result
+}
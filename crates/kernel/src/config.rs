@@ -17,19 +17,27 @@
 use crate::textdump::EncodingMode;
 use moor_compiler::CompileOptions;
 use moor_db::DatabaseConfig;
+use moor_values::model::{WorldState, WorldStateError};
+use moor_values::{v_bool, v_map, v_str, Error, Var, Variant};
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::Duration;
+use tracing::warn;
 
+// Every section and setting has a default, so a config file need only give the ones it changes.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub database_config: DatabaseConfig,
     pub features_config: FeaturesConfig,
     pub textdump_config: TextdumpConfig,
+    pub scheduler_config: SchedulerConfig,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
 pub struct FeaturesConfig {
     /// Whether to host a tasks DB and persist the state of suspended/forked tasks between restarts.
     /// Note that this is the default behaviour in LambdaMOO.
@@ -48,6 +56,45 @@ pub struct FeaturesConfig {
     pub type_dispatch: bool,
     /// Whether to support flyweight types. Flyweights are a lightweight, non-persistent thingy
     pub flyweight_type: bool,
+    /// Whether to record the nondeterministic inputs (time, random numbers, input) of each task,
+    /// so that failing tasks can be replayed deterministically.
+    pub record_replay: bool,
+    /// Whether the command parser matches object names using full Unicode case folding (so that
+    /// e.g. "STRASSE" matches "straße"), rather than plain lowercasing.
+    pub unicode_matching: bool,
+    /// Whether every command line is first offered to `do_command` on the handler object (`#0`,
+    /// unless the connection came in on another listener), as in LambdaMOO, before the built-in
+    /// parser sees it. See the "Commands" section of ARCHITECTURE.md for where this falls relative
+    /// to out-of-band input and intrinsic commands.
+    pub do_command: bool,
+    /// Whether `recycle()` may destroy an object which has children, reparenting them to its
+    /// parent, as in LambdaMOO. If this is false, recycling such an object raises E_PERM, so that
+    /// a whole family of objects can't be reparented by accident.
+    pub recycle_parents: bool,
+    /// Whether `chparent()` drops properties defined on the object (or its descendants) which
+    /// clash by name with ones it would inherit from its new ancestors, rather than raising
+    /// E_INVARG as LambdaMOO does.
+    pub chparent_clear_conflicts: bool,
+    /// Whether to optimize verb programs when compiling them: folding constant expressions,
+    /// removing branches which can never run, and threading jumps. Programs still decompile to
+    /// equivalent source, but with the constants folded and the dead branches gone.
+    pub optimize: bool,
+    /// Whether to let cores written for ToastStunt's waifs run on flyweights: `new_waif()` makes a
+    /// flyweight of the calling object, with a slot for each of its `:`-prefixed properties, and
+    /// calling `w:foo()` on a flyweight runs its delegate's `:foo` verb, if it has one.
+    pub waif_compat: bool,
+    /// Whether `$` may be used inside an index or range to mean the length of the value being
+    /// indexed, as in LambdaMOO. Verbs using it fail to compile if this is off.
+    pub range_end: bool,
+    /// Whether a negative integer literal used as an index or range bound counts back from the
+    /// end, as in some other dialects, so that `x[-1]` is `x[$]`. Programs decompile with the
+    /// `$` form. If this is false, such indices raise E_RANGE, as in LambdaMOO.
+    pub negative_indices: bool,
+    /// Whether integer `/` rounds toward negative infinity, and `%` (on integers and floats) takes
+    /// the sign of the divisor, as in most modern languages, so that `-7 / 2` is -4 and `-7 % 2`
+    /// is 1. If this is false, `/` truncates toward zero and `%` takes the sign of the dividend,
+    /// exactly as in LambdaMOO (`-7 / 2` is -3 and `-7 % 2` is -1).
+    pub floored_division: bool,
 }
 
 impl Default for FeaturesConfig {
@@ -59,6 +106,16 @@ impl Default for FeaturesConfig {
             map_type: true,
             type_dispatch: true,
             flyweight_type: true,
+            record_replay: false,
+            unicode_matching: false,
+            do_command: true,
+            recycle_parents: true,
+            chparent_clear_conflicts: false,
+            optimize: false,
+            waif_compat: false,
+            range_end: true,
+            negative_indices: false,
+            floored_division: false,
         }
     }
 }
@@ -69,9 +126,31 @@ impl FeaturesConfig {
             lexical_scopes: self.lexical_scopes,
             map_type: self.map_type,
             flyweight_type: self.flyweight_type,
+            optimize: self.optimize,
+            range_end: self.range_end,
+            negative_indices: self.negative_indices,
         }
     }
 
+    /// The options to compile verbs with in `world_state`: the configured ones, overridden by
+    /// those the database has been given (see `WorldState::compile_options`).
+    pub fn compile_options_for(
+        &self,
+        world_state: &dyn WorldState,
+    ) -> Result<CompileOptions, WorldStateError> {
+        let options = self.compile_options();
+        let Some(overrides) = world_state.compile_options()? else {
+            return Ok(options);
+        };
+        // It's only checked when set with set_compile_options(), not with registry_set().
+        Ok(
+            with_compile_options(options.clone(), &overrides).unwrap_or_else(|e| {
+                warn!(?overrides, ?e, "ignoring invalid database compile options");
+                options
+            }),
+        )
+    }
+
     /// Returns true if the configuration is backwards compatible with LambdaMOO 1.8 features
     pub fn is_lambdammoo_compatible(&self) -> bool {
         !self.lexical_scopes
@@ -97,7 +176,133 @@ impl FeaturesConfig {
     }
 }
 
+/// The language features in `CompileOptions`, by the names they're given and reported under.
+const COMPILE_OPTION_NAMES: [&str; 6] = [
+    "lexical_scopes",
+    "map_type",
+    "flyweight_type",
+    "optimize",
+    "range_end",
+    "negative_indices",
+];
+
+fn compile_option_mut<'a>(options: &'a mut CompileOptions, name: &str) -> Option<&'a mut bool> {
+    let option = match name {
+        "lexical_scopes" => &mut options.lexical_scopes,
+        "map_type" => &mut options.map_type,
+        "flyweight_type" => &mut options.flyweight_type,
+        "optimize" => &mut options.optimize,
+        "range_end" => &mut options.range_end,
+        "negative_indices" => &mut options.negative_indices,
+        _ => return None,
+    };
+    Some(option)
+}
+
+/// `options` as a map from each option's name to whether it's on.
+pub fn compile_options_map(options: &CompileOptions) -> Var {
+    let mut options = options.clone();
+    let entries: Vec<_> = COMPILE_OPTION_NAMES
+        .iter()
+        .map(|name| {
+            let on = *compile_option_mut(&mut options, name).unwrap();
+            (v_str(name), v_bool(on))
+        })
+        .collect();
+    v_map(&entries)
+}
+
+/// `options`, with those named in `overrides` (a map from option name to whether it's on) turned
+/// on or off. E_INVARG if it names something which isn't an option.
+pub fn with_compile_options(
+    mut options: CompileOptions,
+    overrides: &Var,
+) -> Result<CompileOptions, Error> {
+    let Variant::Map(overrides) = overrides.variant() else {
+        return Err(Error::E_TYPE);
+    };
+    for (name, on) in overrides.iter() {
+        let Variant::Str(name) = name.variant() else {
+            return Err(Error::E_TYPE);
+        };
+        let option = compile_option_mut(&mut options, name.as_string()).ok_or(Error::E_INVARG)?;
+        *option = on.is_true();
+    }
+    Ok(options)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// The most tasks to run at once. Tasks submitted by players (commands, verb calls, evals and
+    /// out-of-band input) beyond this wait to start, and are started round-robin across players,
+    /// so that one player submitting a flood of commands only delays their own. Forked and resumed
+    /// tasks are never held back.
+    /// If None, every task starts as soon as it is submitted, as in LambdaMOO.
+    pub max_running_tasks: Option<usize>,
+    /// Whether tasks submitted by wizards skip ahead of everyone else's when tasks are waiting to
+    /// start.
+    pub wizard_priority: bool,
+    /// Turn away new commands from non-wizards while this many or more tasks are waiting to start
+    /// (which they only do when `max_running_tasks` is set). The player is sent
+    /// `$server_options.server_too_busy_msg` instead.
+    /// If None, commands are never turned away for queue depth.
+    pub overload_ready_tasks: Option<usize>,
+    /// Turn away new commands from non-wizards, likewise, while task commits are taking this long
+    /// or longer on average.
+    /// If None, commands are never turned away for commit latency.
+    pub overload_commit_latency: Option<Duration>,
+    /// How many of the most recently finished tasks to remember, with how they ended, for
+    /// `finished_tasks()` to return. 0 turns the history off.
+    pub finished_task_history: usize,
+    /// The most lines a `read_lines()` accepts, whatever it asks for. Beyond this, the rest of
+    /// the lines up to the terminator are discarded, and it returns E_QUOTA.
+    pub max_input_lines: usize,
+    /// The most bytes of input a `read_lines()` accepts, likewise, so that a client can't flood
+    /// the server with one enormous upload.
+    pub max_input_bytes: usize,
+    /// How long `#0:server_shutdown` gets to run when the server shuts down.
+    pub shutdown_hook_timeout: Duration,
+    /// How long running tasks get to finish when the server shuts down, before they're aborted.
+    /// Aborted tasks then get as long again to notice and stop, before they're abandoned.
+    pub shutdown_task_timeout: Duration,
+    /// How long the final textdump gets to be written when the server shuts down.
+    pub shutdown_checkpoint_timeout: Duration,
+    /// How many ticks executing an opcode counts for against a task's tick limit, by class of
+    /// opcode (`stack`, `arithmetic`, `comparison`, `branch`, `loop`, `collection`, `property`,
+    /// `verb_call`, `builtin_call`, `exception`, `fork`), for classes which don't count for one.
+    pub opcode_tick_costs: BTreeMap<String, usize>,
+    /// How many ticks calling a builtin counts for, by name, on top of the opcode which called
+    /// it, for builtins which should cost more than that, e.g. regular expression matching.
+    pub builtin_tick_costs: BTreeMap<String, usize>,
+    /// Where to write the recording of each task which fails while `record_replay` is on, as
+    /// `task-<id>.replay`, so that it can be replayed later (see `vm_test_utils::replay_task`).
+    /// If None, the recorded inputs are only logged.
+    pub replay_dir: Option<PathBuf>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_running_tasks: None,
+            wizard_priority: false,
+            overload_ready_tasks: None,
+            overload_commit_latency: None,
+            finished_task_history: 100,
+            max_input_lines: 10_000,
+            max_input_bytes: 1 << 20,
+            shutdown_hook_timeout: Duration::from_secs(10),
+            shutdown_task_timeout: Duration::from_secs(10),
+            shutdown_checkpoint_timeout: Duration::from_secs(120),
+            opcode_tick_costs: BTreeMap::new(),
+            builtin_tick_costs: BTreeMap::new(),
+            replay_dir: None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TextdumpConfig {
     /// Where to read the initial textdump from, if any.
     pub input_path: Option<PathBuf>,
@@ -114,6 +319,9 @@ pub struct TextdumpConfig {
     /// This is useful for producing textdumps that are compatible with other servers, but be
     /// careful to not lie about the features (and encoding) you support.
     pub version_override: Option<String>,
+    /// Number of threads to use for compiling verbs when importing a textdump.
+    /// 1 means the import is done entirely on the loading thread.
+    pub import_threads: usize,
 }
 
 impl Default for TextdumpConfig {
@@ -124,6 +332,7 @@ impl Default for TextdumpConfig {
             output_encoding: EncodingMode::UTF8,
             checkpoint_interval: Some(Duration::from_secs(60)),
             version_override: None,
+            import_threads: 1,
         }
     }
 }
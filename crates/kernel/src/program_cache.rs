@@ -0,0 +1,135 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Compilation through the database's program cache, so that verbs whose source (and compile
+//! options) haven't changed since the last time we saw them don't get re-parsed and re-generated.
+
+use lazy_static::lazy_static;
+use md5::{Digest, Md5};
+use moor_compiler::{compile, CompileOptions, BUILTINS, PROGRAM_FORMAT_VERSION};
+use moor_db::ProgramCache;
+use moor_values::model::CompileError;
+use moor_values::AsByteBuffer;
+
+lazy_static! {
+    /// Identifies what compiles programs here: the kernel version, the program format version,
+    /// and the builtin table, since programs call builtins by their offset in it.
+    static ref COMPILER_GENERATION: [u8; 16] = {
+        let mut hasher = Md5::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(PROGRAM_FORMAT_VERSION.to_le_bytes());
+        for builtin in BUILTINS.descriptions() {
+            hasher.update(builtin.name.as_str().as_bytes());
+            hasher.update([0]);
+        }
+        hasher.finalize().into()
+    };
+}
+
+/// Empty the cache of binaries compiled by another generation of the compiler, before it's used.
+pub fn prepare_program_cache(cache: &ProgramCache) {
+    cache.retain_generation(COMPILER_GENERATION.as_slice());
+}
+
+/// Produce the cache key for the given source under the given compile options.
+/// The compiler generation is mixed in so that a change to the compiler, the program encoding or
+/// the builtin table doesn't hand back stale binaries.
+pub fn source_hash(source: &str, options: &CompileOptions) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(COMPILER_GENERATION.as_slice());
+    hasher.update([
+        options.lexical_scopes as u8,
+        options.map_type as u8,
+        options.flyweight_type as u8,
+        options.optimize as u8,
+        options.range_end as u8,
+        options.negative_indices as u8,
+    ]);
+    hasher.update(source.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Compile the given source to an encoded program binary, consulting (and then populating) the
+/// program cache.
+pub fn compile_cached(
+    cache: &ProgramCache,
+    source: &str,
+    options: CompileOptions,
+) -> Result<Vec<u8>, CompileError> {
+    let key = source_hash(source, &options);
+    if let Some(binary) = cache.lookup(&key) {
+        return Ok(binary.to_vec());
+    }
+
+    let program = compile(source, options)?;
+    let binary = program
+        .with_byte_buffer(|d| Vec::from(d))
+        .expect("Failed to encode program byte stream");
+    cache.store(&key, &binary);
+    Ok(binary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moor_compiler::Program;
+    use moor_db::{Database, DatabaseConfig, TxDB};
+
+    #[test]
+    fn test_compile_cache_hit() {
+        let (db, _) = TxDB::open(None, DatabaseConfig::default());
+        let cache = db.program_cache();
+        let source = "return 1 + 2;";
+
+        let first = compile_cached(&cache, source, CompileOptions::default()).unwrap();
+        assert_eq!(cache.stats(), (0, 1));
+        let second = compile_cached(&cache, source, CompileOptions::default()).unwrap();
+        assert_eq!(cache.stats(), (1, 1));
+        assert_eq!(first, second);
+
+        let expected = compile(source, CompileOptions::default()).unwrap();
+        let decoded = Program::from_bytes(second.into()).unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_cache_cleared_for_another_compiler() {
+        let (db, _) = TxDB::open(None, DatabaseConfig::default());
+        let cache = db.program_cache();
+        let source = "return 1 + 2;";
+
+        prepare_program_cache(&cache);
+        compile_cached(&cache, source, CompileOptions::default()).unwrap();
+        prepare_program_cache(&cache);
+        compile_cached(&cache, source, CompileOptions::default()).unwrap();
+        assert_eq!(cache.stats(), (1, 1));
+
+        // Filled by something else, so what's there can't be trusted.
+        cache.retain_generation(b"another compiler");
+        compile_cached(&cache, source, CompileOptions::default()).unwrap();
+        assert_eq!(cache.stats(), (1, 2));
+    }
+
+    #[test]
+    fn test_compile_options_change_key() {
+        let options = CompileOptions::default();
+        let other_options = CompileOptions {
+            map_type: false,
+            ..CompileOptions::default()
+        };
+        assert_ne!(
+            source_hash("return 1;", &options),
+            source_hash("return 1;", &other_options)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Breakpoints keyed by (verb location, verb name, line), shared between the scheduler (which
+//! manages them) and running tasks (which check them as they execute).
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use moor_compiler::Program;
+use moor_values::model::{Named, VerbDef};
+use moor_values::{Obj, Symbol};
+
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    pub id: usize,
+    /// The object the verb is defined on.
+    pub location: Obj,
+    pub verb: Symbol,
+    pub line: usize,
+    /// Optional condition; the breakpoint only fires if this evaluates true in the verb's frame.
+    pub condition: Option<(String, Program)>,
+    /// The player to notify when the breakpoint is hit.
+    pub debugger: Obj,
+}
+
+impl Breakpoint {
+    fn applies_to(&self, location: &Obj, verbdef: &VerbDef) -> bool {
+        self.location.eq(location) && verbdef.matches_name(self.verb)
+    }
+}
+
+#[derive(Default)]
+pub struct Breakpoints {
+    /// Fast path so tasks don't touch the lock when no breakpoints are set at all.
+    armed: AtomicBool,
+    next_id: AtomicUsize,
+    breakpoints: RwLock<Vec<Breakpoint>>,
+}
+
+impl Breakpoints {
+    /// Add a breakpoint, returning its (newly assigned) id.
+    pub fn add(&self, mut breakpoint: Breakpoint) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        breakpoint.id = id;
+        let mut breakpoints = self.breakpoints.write().unwrap();
+        breakpoints.push(breakpoint);
+        self.armed.store(true, Ordering::SeqCst);
+        id
+    }
+
+    /// Remove the breakpoint with the given id, returning false if there was none.
+    pub fn remove(&self, id: usize) -> bool {
+        let mut breakpoints = self.breakpoints.write().unwrap();
+        let Some(position) = breakpoints.iter().position(|b| b.id == id) else {
+            return false;
+        };
+        breakpoints.remove(position);
+        self.armed.store(!breakpoints.is_empty(), Ordering::SeqCst);
+        true
+    }
+
+    pub fn list(&self) -> Vec<Breakpoint> {
+        self.breakpoints.read().unwrap().clone()
+    }
+
+    /// Does any breakpoint apply to the given verb? Used to decide whether a frame needs to be
+    /// executed one opcode at a time.
+    pub fn any_for_verb(&self, location: &Obj, verbdef: &VerbDef) -> bool {
+        if !self.armed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let breakpoints = self.breakpoints.read().unwrap();
+        breakpoints.iter().any(|b| b.applies_to(location, verbdef))
+    }
+
+    /// Find the breakpoints that fire when about to execute the opcode at `pc` in the given verb.
+    pub fn hits(
+        &self,
+        location: &Obj,
+        verbdef: &VerbDef,
+        program: &Program,
+        pc: usize,
+    ) -> Vec<Breakpoint> {
+        if !self.armed.load(Ordering::Relaxed) {
+            return vec![];
+        }
+        let breakpoints = self.breakpoints.read().unwrap();
+        breakpoints
+            .iter()
+            .filter(|b| b.applies_to(location, verbdef) && program.is_line_start(pc, b.line))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! A history of the most recently finished tasks and how they ended, so that a player whose
+//! background task quietly died can find out why without a wizard trawling the logs. Kept by the
+//! scheduler, which records each task as its result comes in.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use moor_values::tasks::{SchedulerError, TaskId};
+use moor_values::{v_obj, Obj, Symbol, Var};
+
+use crate::tasks::TaskStart;
+
+/// What a task was started to do.
+#[derive(Debug, Clone)]
+pub enum TaskOrigin {
+    /// A command typed by the player.
+    Command(String),
+    /// A verb called on `this` (or the forked part of one, or an `eval`, as `callers()` would
+    /// show it).
+    Verb(Var, Symbol),
+}
+
+impl TaskOrigin {
+    pub fn of(task_start: &TaskStart) -> Self {
+        match task_start {
+            TaskStart::StartCommandVerb { command, .. }
+            | TaskStart::StartDoCommand { command, .. } => TaskOrigin::Command(command.clone()),
+            TaskStart::StartVerb { vloc, verb, .. } => TaskOrigin::Verb(vloc.clone(), *verb),
+            TaskStart::StartFork { fork_request, .. } => TaskOrigin::Verb(
+                fork_request.activation.this.clone(),
+                fork_request.activation.verb_name,
+            ),
+            TaskStart::StartEval { player, .. } => {
+                TaskOrigin::Verb(v_obj(player.clone()), Symbol::mk("eval"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FinishedTask {
+    pub task_id: TaskId,
+    pub player: Obj,
+    pub programmer: Obj,
+    pub origin: TaskOrigin,
+    pub finished_at: SystemTime,
+    /// How long the task ran for since it last started or resumed; time spent suspended isn't
+    /// counted.
+    pub runtime: Duration,
+    pub result: Result<Var, SchedulerError>,
+}
+
+pub struct FinishedTasks {
+    capacity: usize,
+    tasks: VecDeque<FinishedTask>,
+}
+
+impl FinishedTasks {
+    /// A history of the last `capacity` tasks to finish. With a capacity of 0 nothing is kept.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tasks: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, task: FinishedTask) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.tasks.len() == self.capacity {
+            self.tasks.pop_front();
+        }
+        self.tasks.push_back(task);
+    }
+
+    /// The tasks remembered, oldest first; only those run by or on behalf of `who`, if given.
+    pub fn tasks(&self, who: Option<&Obj>) -> Vec<FinishedTask> {
+        self.tasks
+            .iter()
+            .filter(|task| who.map_or(true, |who| task.programmer == *who || task.player == *who))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moor_values::{v_int, SYSTEM_OBJECT};
+
+    fn finished(task_id: TaskId, player: Obj) -> FinishedTask {
+        FinishedTask {
+            task_id,
+            player: player.clone(),
+            programmer: player,
+            origin: TaskOrigin::Command("look".to_string()),
+            finished_at: SystemTime::now(),
+            runtime: Duration::ZERO,
+            result: Ok(v_int(1)),
+        }
+    }
+
+    #[test]
+    fn test_history_keeps_most_recent() {
+        let mut history = FinishedTasks::new(2);
+        let player = Obj::mk_id(2);
+        for task_id in 1..=3 {
+            history.record(finished(task_id, player.clone()));
+        }
+        history.record(finished(4, SYSTEM_OBJECT));
+
+        let ids = |tasks: Vec<FinishedTask>| tasks.iter().map(|t| t.task_id).collect::<Vec<_>>();
+        assert_eq!(ids(history.tasks(None)), vec![3, 4]);
+        assert_eq!(ids(history.tasks(Some(&player))), vec![3]);
+
+        let mut off = FinishedTasks::new(0);
+        off.record(finished(1, player));
+        assert!(off.tasks(None).is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The lines gathered for a task suspended in `read_lines()`. The scheduler keeps adding to them
+//! as the client sends them, without waking the task, until the terminator arrives, so that a
+//! long upload costs one resumption rather than one per line.
+
+use bincode::{Decode, Encode};
+use moor_values::Error::E_QUOTA;
+use moor_values::{v_err, v_list_iter, v_string, Var};
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct InputLines {
+    terminator: String,
+    max_lines: usize,
+    max_bytes: usize,
+    lines: Vec<String>,
+    bytes: usize,
+    /// Whether the input went over the limits, after which lines are discarded until the
+    /// terminator, so that the rest of an upload isn't taken as commands.
+    overflowed: bool,
+}
+
+impl InputLines {
+    pub fn new(terminator: String, max_lines: usize, max_bytes: usize) -> Self {
+        Self {
+            terminator,
+            max_lines,
+            max_bytes,
+            lines: vec![],
+            bytes: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Take the next line from the client. Once it's the terminator, returns what to resume the
+    /// task with: the lines before it, or E_QUOTA if there were too many of them.
+    pub fn push(&mut self, line: String) -> Option<Var> {
+        if line == self.terminator {
+            if self.overflowed {
+                return Some(v_err(E_QUOTA));
+            }
+            return Some(v_list_iter(self.lines.drain(..).map(v_string)));
+        }
+        if self.overflowed {
+            return None;
+        }
+        if self.lines.len() >= self.max_lines || self.bytes + line.len() > self.max_bytes {
+            self.overflowed = true;
+            self.lines = vec![];
+            self.bytes = 0;
+            return None;
+        }
+        self.bytes += line.len();
+        self.lines.push(line);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moor_values::v_str;
+
+    #[test]
+    fn test_gathers_until_terminator() {
+        let mut input = InputLines::new(".".to_string(), 10, 1000);
+        assert_eq!(input.push("return 1;".to_string()), None);
+        assert_eq!(input.push("".to_string()), None);
+        assert_eq!(
+            input.push(".".to_string()),
+            Some(v_list_iter([v_str("return 1;"), v_str("")]))
+        );
+    }
+
+    #[test]
+    fn test_overflow_discards_until_terminator() {
+        let mut input = InputLines::new("EOF".to_string(), 2, 1000);
+        for line in ["a", "b", "c", "d"] {
+            assert_eq!(input.push(line.to_string()), None);
+        }
+        assert_eq!(input.push("EOF".to_string()), Some(v_err(E_QUOTA)));
+
+        let mut input = InputLines::new(".".to_string(), 10, 5);
+        assert_eq!(input.push("abc".to_string()), None);
+        assert_eq!(input.push("def".to_string()), None);
+        assert_eq!(input.push(".".to_string()), Some(v_err(E_QUOTA)));
+    }
+}
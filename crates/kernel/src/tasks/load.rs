@@ -0,0 +1,93 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! A running measure of how long task commits are taking, for deciding whether the server is
+//! overloaded. Shared between the scheduler (which reads it) and running tasks (which report
+//! each commit to it).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How much weight the newest commit gets in the average, as a fraction 1/N. Large enough that
+/// one slow commit doesn't make the server look overloaded, small enough that a sustained slowdown
+/// shows within a few dozen commits.
+const LATENCY_SMOOTHING: u64 = 16;
+
+#[derive(Default)]
+pub struct LoadMonitor {
+    /// Exponentially weighted moving average of commit latency, in microseconds.
+    commit_latency_us: AtomicU64,
+    /// Commits made since startup, and the total time they took, in microseconds.
+    commits: AtomicU64,
+    commit_time_us: AtomicU64,
+}
+
+impl LoadMonitor {
+    pub fn record_commit(&self, latency: Duration) {
+        let latency_us = latency.as_micros().min(u64::MAX as u128) as u64;
+        self.commits.fetch_add(1, Ordering::Relaxed);
+        self.commit_time_us.fetch_add(latency_us, Ordering::Relaxed);
+        let update = |average| Some(smoothed(average, latency_us));
+        // Can't fail, as `update` never returns None.
+        let _ = self
+            .commit_latency_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, update);
+    }
+
+    /// The recent average time taken to commit a task's transaction.
+    pub fn commit_latency(&self) -> Duration {
+        Duration::from_micros(self.commit_latency_us.load(Ordering::Relaxed))
+    }
+
+    /// How many commits have been made since startup, and the total time they took.
+    pub fn commit_totals(&self) -> (u64, Duration) {
+        (
+            self.commits.load(Ordering::Relaxed),
+            Duration::from_micros(self.commit_time_us.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+fn smoothed(average: u64, latency: u64) -> u64 {
+    if average == 0 {
+        return latency;
+    }
+    let delta = (latency as i128 - average as i128) / LATENCY_SMOOTHING as i128;
+    (average as i128 + delta).max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_latency_average() {
+        let load = LoadMonitor::default();
+        assert_eq!(load.commit_latency(), Duration::ZERO);
+
+        load.record_commit(Duration::from_millis(10));
+        assert_eq!(load.commit_latency(), Duration::from_millis(10));
+
+        // One slow commit moves the average, but only a little.
+        load.record_commit(Duration::from_millis(170));
+        assert_eq!(load.commit_latency(), Duration::from_millis(20));
+
+        // A sustained slowdown moves it most of the way.
+        for _ in 0..100 {
+            load.record_commit(Duration::from_millis(170));
+        }
+        assert!(load.commit_latency() > Duration::from_millis(160));
+
+        assert_eq!(load.commit_totals(), (102, Duration::from_millis(17180)));
+    }
+}
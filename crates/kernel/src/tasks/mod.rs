@@ -12,18 +12,27 @@
 //
 
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
 use std::time::SystemTime;
 
 use bincode::{Decode, Encode};
 
 use moor_compiler::Program;
+use moor_values::model::WorldState;
 use moor_values::{List, Obj};
-use moor_values::{Symbol, Var};
+use moor_values::{Symbol, Var, Variant, SYSTEM_OBJECT};
 
 pub use crate::tasks::tasks_db::{NoopTasksDb, TasksDb, TasksDbError};
 use crate::vm::Fork;
 use moor_values::tasks::{SchedulerError, TaskId};
 
+pub(crate) mod breakpoints;
+pub(crate) mod finished;
+pub(crate) mod input_lines;
+pub(crate) mod load;
+pub(crate) mod permission_audit;
+pub(crate) mod ready_queue;
+pub mod recording;
 pub mod scheduler;
 pub mod sessions;
 
@@ -32,6 +41,7 @@ pub(crate) mod suspension;
 pub(crate) mod task;
 pub mod task_scheduler_client;
 mod tasks_db;
+pub(crate) mod verb_wrappers;
 pub mod vm_host;
 
 pub const DEFAULT_FG_TICKS: usize = 60_000;
@@ -39,6 +49,18 @@ pub const DEFAULT_BG_TICKS: usize = 30_000;
 pub const DEFAULT_FG_SECONDS: u64 = 5;
 pub const DEFAULT_BG_SECONDS: u64 = 3;
 pub const DEFAULT_MAX_STACK_DEPTH: usize = 50;
+/// Defaults for password hashing cost, per the OWASP recommendations for argon2id and bcrypt.
+pub const DEFAULT_ARGON2_MEMORY_COST: u32 = 19_456;
+pub const DEFAULT_ARGON2_TIME_COST: u32 = 2;
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+pub const DEFAULT_BCRYPT_COST: u32 = 10;
+/// The hashing costs $server_options may set. Beyond these, argon2_hash() or bcrypt_hash() would
+/// want more memory (up to 4 GiB, in KiB) or time than any server should give one call, and
+/// bcrypt takes nothing else.
+pub const ARGON2_MEMORY_COST_RANGE: RangeInclusive<u32> = 8..=4_194_304;
+pub const ARGON2_TIME_COST_RANGE: RangeInclusive<u32> = 1..=64;
+pub const ARGON2_PARALLELISM_RANGE: RangeInclusive<u32> = 1..=64;
+pub const BCRYPT_COST_RANGE: RangeInclusive<u32> = 4..=31;
 
 /// Just a handle to a task, with a receiver for the result.
 pub struct TaskHandle(
@@ -114,6 +136,101 @@ pub struct ServerOptions {
     pub fg_ticks: usize,
     /// The maximum number of levels of nested verb calls.
     pub max_stack_depth: usize,
+    /// The amount of memory (in KiB) used by argon2_hash.
+    pub argon2_memory_cost: u32,
+    /// The number of passes made by argon2_hash.
+    pub argon2_time_cost: u32,
+    /// The degree of parallelism used by argon2_hash.
+    pub argon2_parallelism: u32,
+    /// The (log2) work factor used by bcrypt_hash.
+    pub bcrypt_cost: u32,
+    /// The IANA time zone (e.g. "America/Toronto") that ctime() and strftime() use when not given
+    /// one. If unset, the daemon process's local time zone is used.
+    pub default_timezone: Option<String>,
+    /// What players are told when their command is turned away because the server is overloaded.
+    /// If unset, a generic message is used.
+    pub server_too_busy_msg: Option<String>,
+    /// The most tasks, running or queued, one programmer may own before `fork` and `suspend()`
+    /// raise E_QUOTA. A player's own `queued_task_limit` property overrides this for their tasks.
+    pub queued_task_limit: Option<usize>,
+    /// The most tasks, running or queued, which may run on behalf of one player (or connection)
+    /// before `fork` and `suspend()` raise E_QUOTA.
+    pub connection_task_limit: Option<usize>,
+}
+
+/// Running totals kept by the scheduler, for export to operators (e.g. as Prometheus metrics).
+/// Counters are cumulative since startup; the queue depths and cache figures are sampled when the
+/// counters are requested.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SchedulerCounters {
+    /// Tasks started, including restarts after a commit conflict.
+    pub tasks_started: u64,
+    /// Suspended tasks resumed (by time, input, or `resume()`).
+    pub tasks_resumed: u64,
+    /// Tasks which ran to completion and committed.
+    pub tasks_succeeded: u64,
+    /// Tasks which ended with an uncaught exception.
+    pub tasks_exceptions: u64,
+    /// Tasks aborted for exceeding their tick or time limits.
+    pub tasks_limits_exceeded: u64,
+    /// Tasks which ended any other way (killed, verb not found, command parse error, ...)
+    pub tasks_aborted: u64,
+    /// Commits which failed due to a conflict with another transaction, and were retried.
+    pub commit_conflicts: u64,
+    /// Tasks currently running.
+    pub active_tasks: u64,
+    /// Tasks currently suspended, forked, or waiting on input.
+    pub suspended_tasks: u64,
+    /// Tasks submitted but waiting for a free slot to start in, when running tasks are capped.
+    pub ready_tasks: u64,
+    /// Commands turned away because the server was overloaded.
+    pub tasks_shed: u64,
+    /// Forks and suspensions refused with E_QUOTA because of a task quota.
+    pub tasks_over_quota: u64,
+    /// Whether the server is currently past one of its configured overload thresholds.
+    pub overloaded: bool,
+    /// The recent average time taken to commit a task's transaction, in microseconds.
+    pub commit_latency_us: u64,
+    /// Task transactions committed (successfully or not), and the total time spent committing
+    /// them, in microseconds.
+    pub commits: u64,
+    pub commit_time_us: u64,
+    /// Lookups in the compiled program cache which found an entry.
+    pub program_cache_hits: u64,
+    /// Lookups in the compiled program cache which did not.
+    pub program_cache_misses: u64,
+}
+
+/// The `queued_task_limit` property on `owner`, if they have one, which takes the place of
+/// `$server_options.queued_task_limit` for the tasks they own, as in LambdaMOO.
+pub(crate) fn owner_task_limit(world_state: &dyn WorldState, owner: &Obj) -> Option<usize> {
+    let limit = world_state
+        .retrieve_property(&SYSTEM_OBJECT, owner, Symbol::mk("queued_task_limit"))
+        .ok()?;
+    match limit.variant() {
+        Variant::Int(i) if *i >= 0 => Some(*i as usize),
+        _ => None,
+    }
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            bg_seconds: DEFAULT_BG_SECONDS,
+            bg_ticks: DEFAULT_BG_TICKS,
+            fg_seconds: DEFAULT_FG_SECONDS,
+            fg_ticks: DEFAULT_FG_TICKS,
+            max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
+            argon2_memory_cost: DEFAULT_ARGON2_MEMORY_COST,
+            argon2_time_cost: DEFAULT_ARGON2_TIME_COST,
+            argon2_parallelism: DEFAULT_ARGON2_PARALLELISM,
+            bcrypt_cost: DEFAULT_BCRYPT_COST,
+            default_timezone: None,
+            server_too_busy_msg: None,
+            queued_task_limit: None,
+            connection_task_limit: None,
+        }
+    }
 }
 
 impl ServerOptions {
@@ -127,6 +244,7 @@ impl ServerOptions {
 }
 
 pub mod vm_test_utils {
+    use std::sync::atomic::AtomicBool;
     use std::sync::Arc;
     use std::time::Duration;
 
@@ -138,10 +256,13 @@ pub mod vm_test_utils {
 
     use crate::builtins::BuiltinRegistry;
     use crate::config::FeaturesConfig;
+    use crate::tasks::recording::TaskRecording;
     use crate::tasks::sessions::Session;
+    use crate::tasks::task::Task;
+    use crate::tasks::task_scheduler_client::TaskSchedulerClient;
     use crate::tasks::vm_host::VmHost;
-    use crate::tasks::VerbCall;
-    use crate::vm::VMHostResponse;
+    use crate::tasks::{ServerOptions, VerbCall};
+    use crate::vm::{ReplayLog, VMHostResponse};
     use moor_values::tasks::Exception;
 
     pub type ExecResult = Result<Var, Exception>;
@@ -150,18 +271,36 @@ pub mod vm_test_utils {
         world_state: &mut dyn WorldState,
         session: Arc<dyn Session>,
         builtins: Arc<BuiltinRegistry>,
+        replay_log: ReplayLog,
         fun: F,
-    ) -> ExecResult
+    ) -> (ExecResult, ReplayLog)
     where
         F: FnOnce(&mut dyn WorldState, &mut VmHost),
     {
         let (scs_tx, _scs_rx) = crossbeam_channel::unbounded();
-        let task_scheduler_client =
-            crate::tasks::task_scheduler_client::TaskSchedulerClient::new(0, scs_tx);
+        let task_scheduler_client = TaskSchedulerClient::new(0, scs_tx);
         let mut vm_host = VmHost::new(0, 20, 90_000, Duration::from_secs(5));
+        vm_host.set_replay_log(replay_log);
 
         fun(world_state, &mut vm_host);
 
+        let result = run(
+            world_state,
+            session,
+            builtins,
+            task_scheduler_client,
+            &mut vm_host,
+        );
+        (result, vm_host.replay_log().clone())
+    }
+
+    fn run(
+        world_state: &mut dyn WorldState,
+        session: Arc<dyn Session>,
+        builtins: Arc<BuiltinRegistry>,
+        task_scheduler_client: TaskSchedulerClient,
+        vm_host: &mut VmHost,
+    ) -> ExecResult {
         let config = FeaturesConfig::default();
 
         // Call repeatedly into exec until we ge either an error or Complete.
@@ -195,12 +334,15 @@ pub mod vm_test_utils {
                 VMHostResponse::Suspend(_) => {
                     panic!("Unexpected suspend");
                 }
-                VMHostResponse::SuspendNeedInput => {
+                VMHostResponse::SuspendNeedInput(_) => {
                     panic!("Unexpected suspend need input");
                 }
                 VMHostResponse::RollbackRetry => {
                     panic!("Unexpected rollback retry");
                 }
+                VMHostResponse::DebugStep { summary, .. } => {
+                    panic!("Unexpected debug step: {}", summary);
+                }
             }
         }
     }
@@ -212,26 +354,33 @@ pub mod vm_test_utils {
         verb_name: &str,
         args: List,
     ) -> ExecResult {
-        execute(world_state, session, builtins, |world_state, vm_host| {
-            let verb_name = Symbol::mk_case_insensitive(verb_name);
-            let vi = world_state
-                .find_method_verb_on(&SYSTEM_OBJECT, &SYSTEM_OBJECT, verb_name)
-                .unwrap();
-            vm_host.start_call_method_verb(
-                0,
-                &SYSTEM_OBJECT,
-                vi,
-                VerbCall {
-                    verb_name,
-                    location: v_obj(SYSTEM_OBJECT),
-                    this: v_obj(SYSTEM_OBJECT),
-                    player: SYSTEM_OBJECT,
-                    args,
-                    argstr: "".to_string(),
-                    caller: v_obj(SYSTEM_OBJECT),
-                },
-            );
-        })
+        let (result, _) = execute(
+            world_state,
+            session,
+            builtins,
+            ReplayLog::Off,
+            |world_state, vm_host| {
+                let verb_name = Symbol::mk_case_insensitive(verb_name);
+                let vi = world_state
+                    .find_method_verb_on(&SYSTEM_OBJECT, &SYSTEM_OBJECT, verb_name)
+                    .unwrap();
+                vm_host.start_call_method_verb(
+                    0,
+                    &SYSTEM_OBJECT,
+                    vi,
+                    VerbCall {
+                        verb_name,
+                        location: v_obj(SYSTEM_OBJECT),
+                        this: v_obj(SYSTEM_OBJECT),
+                        player: SYSTEM_OBJECT,
+                        args,
+                        argstr: "".to_string(),
+                        caller: v_obj(SYSTEM_OBJECT),
+                    },
+                );
+            },
+        );
+        result
     }
 
     pub fn call_eval_builtin(
@@ -242,9 +391,70 @@ pub mod vm_test_utils {
         player: Obj,
         program: Program,
     ) -> ExecResult {
-        execute(world_state, session, builtins, |world_state, vm_host| {
-            vm_host.start_eval(0, &player, program, world_state);
-        })
+        call_eval_builtin_replay(
+            world_state,
+            session,
+            builtins,
+            player,
+            program,
+            ReplayLog::Off,
+        )
+        .0
+    }
+
+    /// Evaluate `program`, recording or replaying its nondeterministic inputs according to
+    /// `replay_log`, and returning the log as it stood at the end of execution.
+    pub fn call_eval_builtin_replay(
+        world_state: &mut dyn WorldState,
+        session: Arc<dyn Session>,
+        builtins: Arc<BuiltinRegistry>,
+        player: Obj,
+        program: Program,
+        replay_log: ReplayLog,
+    ) -> (ExecResult, ReplayLog) {
+        execute(
+            world_state,
+            session,
+            builtins,
+            replay_log,
+            |world_state, vm_host| {
+                vm_host.start_eval(0, &player, program, world_state);
+            },
+        )
+    }
+
+    /// Run the task in `recording` again, from the start, against `world_state`, handing it the
+    /// nondeterministic inputs it saw the first time, e.g. to reproduce the failure it was
+    /// recorded for in a test.
+    pub fn replay_task(
+        world_state: &mut dyn WorldState,
+        session: Arc<dyn Session>,
+        builtins: Arc<BuiltinRegistry>,
+        recording: TaskRecording,
+    ) -> ExecResult {
+        let (control_sender, _control_receiver) = crossbeam_channel::unbounded();
+        let task_scheduler_client =
+            TaskSchedulerClient::new(recording.task_id, control_sender.clone());
+        let mut task = Task::new(
+            recording.task_id,
+            recording.player,
+            Arc::new(recording.task_start),
+            recording.perms,
+            &ServerOptions::default(),
+            Arc::new(AtomicBool::new(false)),
+        );
+        if !task.setup_task_start(&control_sender, world_state, &FeaturesConfig::default()) {
+            panic!("Could not set up the recorded task to start");
+        }
+        task.vm_host
+            .set_replay_log(ReplayLog::Replaying(recording.inputs.into()));
+        run(
+            world_state,
+            session,
+            builtins,
+            task_scheduler_client,
+            &mut task.vm_host,
+        )
     }
 }
 
@@ -0,0 +1,68 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! A log of the most recent permission checks made by tasks, kept while a wizard has auditing
+//! turned on, for tracking down verbs which do things with more (wizard) permissions than they
+//! should. Shared between the scheduler (which turns it on and off) and running tasks (whose
+//! transactions report to it).
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use moor_values::model::{PermissionAuditSink, PermissionCheck};
+use moor_values::tasks::TaskId;
+
+/// How many checks are kept; older ones are dropped to make room for new ones.
+const AUDIT_CAPACITY: usize = 10_000;
+
+#[derive(Default)]
+pub struct PermissionAudit {
+    enabled: AtomicBool,
+    checks: Mutex<VecDeque<(TaskId, PermissionCheck)>>,
+}
+
+impl PermissionAudit {
+    /// Turn auditing on or off, returning whether it was on. Tasks notice at the start of their
+    /// next transaction.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::SeqCst)
+    }
+
+    /// A sink for a transaction of `task_id` to report its checks to, if auditing is on.
+    pub fn sink_for(self: &Arc<Self>, task_id: TaskId) -> Option<PermissionAuditSink> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let audit = self.clone();
+        Some(Box::new(move |check| audit.record(task_id, check)))
+    }
+
+    fn record(&self, task_id: TaskId, check: PermissionCheck) {
+        let mut checks = self.checks.lock().unwrap();
+        if checks.len() == AUDIT_CAPACITY {
+            checks.pop_front();
+        }
+        checks.push_back((task_id, check));
+    }
+
+    /// The checks recorded, oldest first, optionally emptying the log.
+    pub fn checks(&self, clear: bool) -> Vec<(TaskId, PermissionCheck)> {
+        let mut checks = self.checks.lock().unwrap();
+        if clear {
+            checks.drain(..).collect()
+        } else {
+            checks.iter().cloned().collect()
+        }
+    }
+}
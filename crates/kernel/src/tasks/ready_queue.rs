@@ -0,0 +1,186 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Tasks which have been submitted but not yet started, because the scheduler is already running
+//! as many tasks as it's configured to (see `SchedulerConfig`). They're started round-robin across
+//! players, so that one player submitting a flood of commands only delays their own.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use moor_values::tasks::{SchedulerError, TaskId};
+use moor_values::Obj;
+
+use crate::tasks::sessions::Session;
+use crate::tasks::{TaskResult, TaskStart};
+
+/// Everything needed to start a task later, including the sender half of the handle which was
+/// already given to whoever submitted it.
+pub struct PendingTask {
+    pub task_id: TaskId,
+    pub task_start: Arc<TaskStart>,
+    pub player: Obj,
+    pub perms: Obj,
+    pub session: Arc<dyn Session>,
+    pub result_sender: oneshot::Sender<Result<TaskResult, SchedulerError>>,
+}
+
+#[derive(Default)]
+pub struct ReadyQ {
+    /// Tasks submitted by wizards, when wizard priority is on. These go ahead of everyone else's.
+    wizard_lane: VecDeque<PendingTask>,
+    /// Everyone else's, by player.
+    by_player: HashMap<Obj, VecDeque<PendingTask>>,
+    /// The players in `by_player`, in the order they'll next get a task started.
+    rotation: VecDeque<Obj>,
+}
+
+impl ReadyQ {
+    pub fn push(&mut self, task: PendingTask, priority: bool) {
+        if priority {
+            self.wizard_lane.push_back(task);
+            return;
+        }
+        let queue = self.by_player.entry(task.player.clone()).or_default();
+        if queue.is_empty() {
+            self.rotation.push_back(task.player.clone());
+        }
+        queue.push_back(task);
+    }
+
+    /// The next task to start: the oldest wizard task, if any, otherwise the oldest task of the
+    /// player whose turn it is.
+    pub fn pop(&mut self) -> Option<PendingTask> {
+        if let Some(task) = self.wizard_lane.pop_front() {
+            return Some(task);
+        }
+        let player = self.rotation.pop_front()?;
+        let queue = self
+            .by_player
+            .get_mut(&player)
+            .expect("player in rotation without pending tasks");
+        let task = queue.pop_front();
+        if queue.is_empty() {
+            self.by_player.remove(&player);
+        } else {
+            self.rotation.push_back(player);
+        }
+        task
+    }
+
+    pub fn remove(&mut self, task_id: TaskId) -> Option<PendingTask> {
+        if let Some(position) = self.wizard_lane.iter().position(|t| t.task_id == task_id) {
+            return self.wizard_lane.remove(position);
+        }
+        let (player, position) = self.by_player.iter().find_map(|(player, queue)| {
+            let position = queue.iter().position(|t| t.task_id == task_id)?;
+            Some((player.clone(), position))
+        })?;
+        let queue = self.by_player.get_mut(&player).unwrap();
+        let task = queue.remove(position);
+        if queue.is_empty() {
+            self.by_player.remove(&player);
+            self.rotation.retain(|p| p != &player);
+        }
+        task
+    }
+
+    pub fn get(&self, task_id: TaskId) -> Option<&PendingTask> {
+        self.wizard_lane
+            .iter()
+            .chain(self.by_player.values().flatten())
+            .find(|t| t.task_id == task_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.wizard_lane.len() + self.by_player.values().map(VecDeque::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wizard_lane.is_empty() && self.rotation.is_empty()
+    }
+
+    pub fn drain(&mut self) -> Vec<PendingTask> {
+        let mut tasks: Vec<_> = self.wizard_lane.drain(..).collect();
+        tasks.extend(self.by_player.drain().flat_map(|(_, queue)| queue));
+        self.rotation.clear();
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use moor_values::{v_none, List, Obj, Symbol};
+
+    use crate::tasks::ready_queue::{PendingTask, ReadyQ};
+    use crate::tasks::sessions::NoopClientSession;
+    use crate::tasks::TaskStart;
+
+    fn pending(task_id: usize, player: i32) -> PendingTask {
+        let player = Obj::mk_id(player);
+        PendingTask {
+            task_id,
+            task_start: Arc::new(TaskStart::StartVerb {
+                player: player.clone(),
+                vloc: v_none(),
+                verb: Symbol::mk("test"),
+                args: List::mk_list(&[]),
+                argstr: String::new(),
+            }),
+            player: player.clone(),
+            perms: player,
+            session: Arc::new(NoopClientSession::new()),
+            result_sender: oneshot::channel().0,
+        }
+    }
+
+    fn order(q: &mut ReadyQ) -> Vec<usize> {
+        std::iter::from_fn(|| q.pop()).map(|t| t.task_id).collect()
+    }
+
+    #[test]
+    fn test_round_robin_across_players() {
+        let mut q = ReadyQ::default();
+        // Player 1 floods the queue before player 2 gets a word in.
+        for task_id in 0..4 {
+            q.push(pending(task_id, 1), false);
+        }
+        q.push(pending(4, 2), false);
+        q.push(pending(5, 2), false);
+        assert_eq!(q.len(), 6);
+        assert_eq!(order(&mut q), vec![0, 4, 1, 5, 2, 3]);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_wizard_lane_first() {
+        let mut q = ReadyQ::default();
+        q.push(pending(0, 1), false);
+        q.push(pending(1, 2), true);
+        q.push(pending(2, 1), false);
+        assert_eq!(order(&mut q), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut q = ReadyQ::default();
+        q.push(pending(0, 1), false);
+        q.push(pending(1, 2), false);
+        q.push(pending(2, 1), false);
+        assert!(q.remove(1).is_some());
+        assert!(q.get(1).is_none());
+        assert_eq!(order(&mut q), vec![0, 2]);
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Recordings of tasks which failed while `record_replay` was on: what each was asked to do, and
+//! the nondeterministic inputs it saw doing it, written out so that the failure can be reproduced
+//! later by replaying the task (see `vm_test_utils::replay_task`).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bincode::{Decode, Encode};
+use moor_values::tasks::TaskId;
+use moor_values::{Obj, Var, BINCODE_CONFIG};
+
+use crate::tasks::TaskStart;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct TaskRecording {
+    pub task_id: TaskId,
+    pub player: Obj,
+    pub perms: Obj,
+    /// What the task was asked to do.
+    pub task_start: TaskStart,
+    /// The nondeterministic inputs it observed, in order.
+    pub inputs: Vec<Var>,
+}
+
+impl TaskRecording {
+    /// Write the recording into `dir`, as `task-<id>.replay`, returning where it went.
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("task-{}.replay", self.task_id));
+        let bytes = bincode::encode_to_vec(self, *BINCODE_CONFIG).map_err(io::Error::other)?;
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let (recording, _) =
+            bincode::decode_from_slice(&bytes, *BINCODE_CONFIG).map_err(io::Error::other)?;
+        Ok(recording)
+    }
+}
@@ -12,38 +12,48 @@
 //
 
 use std::collections::HashMap;
-use std::fs::File;
+use std::ops::RangeInclusive;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread::yield_now;
-use std::time::{Duration, Instant};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 
+use chrono_tz::Tz;
 use crossbeam_channel::Receiver;
 use crossbeam_channel::Sender;
 use lazy_static::lazy_static;
-use tracing::{debug, error, info, instrument, trace, warn};
+use tracing::{debug, error, info, info_span, instrument, trace, warn, Span};
 use uuid::Uuid;
 
-use moor_compiler::{compile, program_to_tree, unparse, Program};
+use moor_compiler::{program_to_tree, unparse, Program};
 use moor_db::Database;
-use moor_values::model::{BinaryType, HasUuid, ObjectRef, ValSet, VerbAttrs};
+use moor_values::model::{BinaryType, HasUuid, ObjFlag, ObjectRef, ValSet, VerbAttrs};
 use moor_values::model::{CommitResult, Perms};
 use moor_values::model::{WorldState, WorldStateError};
 
 use crate::builtins::BuiltinRegistry;
-use crate::config::Config;
+use crate::config::{compile_options_map, Config, SchedulerConfig};
+use crate::program_cache::{compile_cached, prepare_program_cache};
+use crate::tasks::breakpoints::Breakpoints;
+use crate::tasks::finished::{FinishedTask, FinishedTasks, TaskOrigin};
+use crate::tasks::input_lines::InputLines;
+use crate::tasks::load::LoadMonitor;
+use crate::tasks::permission_audit::PermissionAudit;
+use crate::tasks::ready_queue::{PendingTask, ReadyQ};
 use crate::tasks::scheduler_client::{SchedulerClient, SchedulerClientMsg};
-use crate::tasks::sessions::{Session, SessionFactory, SystemControl};
-use crate::tasks::suspension::{SuspensionQ, WakeCondition};
+use crate::tasks::sessions::{NoopClientSession, Session, SessionFactory, SystemControl};
+use crate::tasks::suspension::{InputTaken, SuspensionQ, WakeCondition};
 use crate::tasks::task::Task;
 use crate::tasks::task_scheduler_client::{TaskControlMsg, TaskSchedulerClient};
 use crate::tasks::tasks_db::TasksDb;
+use crate::tasks::verb_wrappers::VerbWrappers;
 use crate::tasks::{
-    ServerOptions, TaskHandle, TaskResult, TaskStart, DEFAULT_BG_SECONDS, DEFAULT_BG_TICKS,
-    DEFAULT_FG_SECONDS, DEFAULT_FG_TICKS, DEFAULT_MAX_STACK_DEPTH,
+    SchedulerCounters, ServerOptions, TaskHandle, TaskResult, TaskStart, ARGON2_MEMORY_COST_RANGE,
+    ARGON2_PARALLELISM_RANGE, ARGON2_TIME_COST_RANGE, BCRYPT_COST_RANGE,
 };
-use crate::textdump::{make_textdump, TextdumpWriter};
-use crate::vm::Fork;
+use crate::textdump::{make_textdump, DumpSink, FileDumpSink, TextdumpWriter};
+use crate::vm::tick_costs::TickCosts;
+use crate::vm::{Fork, InputRequest};
 use moor_values::matching::command_parse::ParseMatcher;
 use moor_values::matching::match_env::MatchEnvironmentParseMatcher;
 use moor_values::matching::ws_match_env::WsMatchEnv;
@@ -52,18 +62,22 @@ use moor_values::tasks::SchedulerError::{
     TaskAbortedException, TaskAbortedLimit, VerbProgramFailed,
 };
 use moor_values::tasks::{
-    AbortLimitReason, CommandError, SchedulerError, TaskId, VerbProgramError,
+    AbortLimitReason, CommandError, NarrativeEvent, SchedulerError, TaskId, VerbProgramError,
 };
-use moor_values::Error::{E_INVARG, E_INVIND, E_PERM};
-use moor_values::{v_err, v_int, v_none, v_obj, v_string, List, Symbol, Var};
+use moor_values::Error::{E_INVARG, E_INVIND, E_PERM, E_QUOTA};
+use moor_values::{v_err, v_int, v_none, v_obj, v_str, v_string, List, Symbol, Var};
 use moor_values::{AsByteBuffer, SYSTEM_OBJECT};
-use moor_values::{Obj, Variant};
+use moor_values::{Error, Obj, Variant};
 
 const SCHEDULER_TICK_TIME: Duration = Duration::from_millis(5);
 
 /// Number of times to retry a program compilation transaction in case of conflict, before giving up.
 const NUM_VERB_PROGRAM_ATTEMPTS: usize = 5;
 
+/// What players are told when their command is turned away under overload, if the core doesn't
+/// set `$server_options.server_too_busy_msg`.
+const DEFAULT_SERVER_TOO_BUSY_MSG: &str = "*** Server too busy; please try again in a moment. ***";
+
 lazy_static! {
     static ref SERVER_OPTIONS: Symbol = Symbol::mk("server_options");
     static ref BG_SECONDS: Symbol = Symbol::mk("bg_seconds");
@@ -71,7 +85,16 @@ lazy_static! {
     static ref FG_SECONDS: Symbol = Symbol::mk("fg_seconds");
     static ref FG_TICKS: Symbol = Symbol::mk("fg_ticks");
     static ref MAX_STACK_DEPTH: Symbol = Symbol::mk("max_stack_depth");
+    static ref ARGON2_MEMORY_COST: Symbol = Symbol::mk("argon2_memory_cost");
+    static ref ARGON2_TIME_COST: Symbol = Symbol::mk("argon2_time_cost");
+    static ref ARGON2_PARALLELISM: Symbol = Symbol::mk("argon2_parallelism");
+    static ref BCRYPT_COST: Symbol = Symbol::mk("bcrypt_cost");
+    static ref DEFAULT_TIMEZONE: Symbol = Symbol::mk("default_timezone");
+    static ref SERVER_TOO_BUSY_MSG: Symbol = Symbol::mk("server_too_busy_msg");
+    static ref QUEUED_TASK_LIMIT: Symbol = Symbol::mk("queued_task_limit");
+    static ref CONNECTION_TASK_LIMIT: Symbol = Symbol::mk("connection_task_limit");
     static ref DO_OUT_OF_BAND_COMMAND: Symbol = Symbol::mk("do_out_of_band_command");
+    static ref SERVER_SHUTDOWN: Symbol = Symbol::mk("server_shutdown");
 }
 /// Responsible for the dispatching, control, and accounting of tasks in the system.
 /// There should be only one scheduler per server.
@@ -81,8 +104,8 @@ pub struct Scheduler {
     task_control_sender: Sender<(TaskId, TaskControlMsg)>,
     task_control_receiver: Receiver<(TaskId, TaskControlMsg)>,
 
-    scheduler_sender: Sender<SchedulerClientMsg>,
-    scheduler_receiver: Receiver<SchedulerClientMsg>,
+    scheduler_sender: Sender<(Span, SchedulerClientMsg)>,
+    scheduler_receiver: Receiver<(Span, SchedulerClientMsg)>,
 
     config: Arc<Config>,
 
@@ -101,6 +124,36 @@ pub struct Scheduler {
     /// This is in a lock to allow interior mutability for the scheduler loop, but is only ever
     /// accessed by the scheduler thread.
     task_q: TaskQ,
+
+    /// The shutdown under way, if any.
+    shutdown: Option<Shutdown>,
+
+    /// Where checkpoints are written.
+    dump_sinks: Vec<Arc<dyn DumpSink>>,
+}
+
+/// An orderly shutdown in progress. It's worked through a stage at a time by the run loop (see
+/// `advance_shutdown`), so that the tasks it waits on keep being serviced meanwhile.
+struct Shutdown {
+    msg: Option<String>,
+    stage: ShutdownStage,
+    /// When the current stage stops waiting.
+    deadline: Instant,
+    /// Those waiting to hear that the shutdown is complete.
+    replies: Vec<oneshot::Sender<Result<(), SchedulerError>>>,
+}
+
+enum ShutdownStage {
+    /// Requested, but not yet started.
+    Requested,
+    /// Waiting for `#0:server_shutdown`, running as this task, to return.
+    Hook(TaskId),
+    /// Waiting for running tasks to finish.
+    Draining,
+    /// Waiting for the tasks which didn't finish in time to notice they've been aborted.
+    Aborting,
+    /// Waiting for the final textdump, if there's one being written.
+    Checkpointing(Option<JoinHandle<()>>),
 }
 
 /// Scheduler-side per-task record. Lives in the scheduler thread and owned by the scheduler and
@@ -110,6 +163,12 @@ pub struct Scheduler {
 struct RunningTaskControl {
     /// For which player this task is running on behalf of.
     player: Obj,
+    /// The permissions the task runs with, i.e. its owner.
+    perms: Obj,
+    /// What the task was started to do, and when it last started (or resumed) running, for the
+    /// finished task history.
+    origin: TaskOrigin,
+    started: Instant,
     /// A kill switch to signal the task to stop. True means the VM execution thread should stop
     /// as soon as it can.
     kill_switch: Arc<AtomicBool>,
@@ -129,6 +188,24 @@ struct TaskQ {
     ///     Suspended foreground tasks that are either indefinitely suspended or will execute someday
     ///     Suspended tasks waiting for input from the player
     suspended: SuspensionQ,
+    /// Tasks submitted while we were already running as many as we're allowed to, waiting to
+    /// start.
+    ready: ReadyQ,
+    /// Breakpoints set by wizards, shared with every task the queue starts or resumes.
+    breakpoints: Arc<Breakpoints>,
+    /// Verb wrappers set by wizards, shared the same way.
+    verb_wrappers: Arc<VerbWrappers>,
+    /// The log of permission checks, when a wizard has turned auditing on. Shared the same way.
+    permission_audit: Arc<PermissionAudit>,
+    /// The running average of commit latency, reported to by every task. Shared the same way.
+    load: Arc<LoadMonitor>,
+    /// What opcodes and builtins count for against tasks' tick limits, as configured. Shared the
+    /// same way.
+    tick_costs: Arc<TickCosts>,
+    /// Running totals of task outcomes, for performance monitoring.
+    counters: SchedulerCounters,
+    /// The most recently finished tasks, and how they ended.
+    finished: FinishedTasks,
 }
 
 fn load_int_sysprop(server_options_obj: &Obj, name: Symbol, tx: &dyn WorldState) -> Option<u64> {
@@ -138,7 +215,27 @@ fn load_int_sysprop(server_options_obj: &Obj, name: Symbol, tx: &dyn WorldState)
     match value.variant() {
         Variant::Int(i) if *i >= 0 => Some(*i as u64),
         _ => {
-            warn!("$bg_seconds is not a positive integer");
+            warn!(option = %name, "$server_options value is not a non-negative integer");
+            None
+        }
+    }
+}
+
+/// As `load_int_sysprop`, for a hashing cost, which is only taken if it's within `range`; one
+/// which isn't is ignored (leaving the default), rather than have argon2_hash() or bcrypt_hash()
+/// try to use it.
+fn load_cost_sysprop(
+    server_options_obj: &Obj,
+    name: Symbol,
+    range: RangeInclusive<u32>,
+    tx: &dyn WorldState,
+) -> Option<u32> {
+    let cost = load_int_sysprop(server_options_obj, name, tx)?;
+    match u32::try_from(cost) {
+        Ok(cost) if range.contains(&cost) => Some(cost),
+        _ => {
+            warn!(option = %name, cost, min = range.start(), max = range.end(),
+                "$server_options hashing cost out of range; using the default");
             None
         }
     }
@@ -152,21 +249,31 @@ impl Scheduler {
         config: Arc<Config>,
         system_control: Arc<dyn SystemControl>,
     ) -> Self {
+        prepare_program_cache(&database.program_cache());
         let (task_control_sender, task_control_receiver) = crossbeam_channel::unbounded();
         let (scheduler_sender, scheduler_receiver) = crossbeam_channel::unbounded();
         let suspension_q = SuspensionQ::new(tasks_database);
         let task_q = TaskQ {
             tasks: Default::default(),
             suspended: suspension_q,
+            ready: Default::default(),
+            breakpoints: Default::default(),
+            verb_wrappers: Default::default(),
+            permission_audit: Default::default(),
+            load: Default::default(),
+            tick_costs: Arc::new(TickCosts::new(
+                &config.scheduler_config.opcode_tick_costs,
+                &config.scheduler_config.builtin_tick_costs,
+            )),
+            counters: Default::default(),
+            finished: FinishedTasks::new(config.scheduler_config.finished_task_history),
         };
-        let default_server_options = ServerOptions {
-            bg_seconds: DEFAULT_BG_SECONDS,
-            bg_ticks: DEFAULT_BG_TICKS,
-            fg_seconds: DEFAULT_FG_SECONDS,
-            fg_ticks: DEFAULT_FG_TICKS,
-            max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
-        };
+        let default_server_options = ServerOptions::default();
         let builtin_registry = Arc::new(BuiltinRegistry::new());
+        let dump_sinks: Vec<Arc<dyn DumpSink>> = match &config.textdump_config.output_path {
+            Some(output_path) => vec![Arc::new(FileDumpSink::new(output_path.clone()))],
+            None => vec![],
+        };
         Self {
             version,
             running: false,
@@ -181,39 +288,54 @@ impl Scheduler {
             builtin_registry,
             server_options: default_server_options,
             system_control,
+            shutdown: None,
+            dump_sinks,
         }
     }
 
+    /// Also write checkpoints to `sink`, beside the textdump file configured, if any.
+    pub fn add_dump_sink(&mut self, sink: Arc<dyn DumpSink>) {
+        self.dump_sinks.push(sink);
+    }
+
     /// Execute the scheduler loop, run from the server process.
     #[instrument(skip(self, bg_session_factory))]
     pub fn run(mut self, bg_session_factory: Arc<dyn SessionFactory>) {
         // Rehydrate suspended tasks.
-        self.task_q.suspended.load_tasks(bg_session_factory);
+        self.task_q.suspended.load_tasks(bg_session_factory.clone());
 
         self.running = true;
         info!("Starting scheduler loop");
 
         self.reload_server_options();
         while self.running {
-            // Look for tasks that need to be woken (have hit their wakeup-time), and wake them.
-            let to_wake = self.task_q.suspended.collect_wake_tasks();
-            for sr in to_wake {
-                let task_id = sr.task.task_id;
-                if let Err(e) = self.task_q.resume_task_thread(
-                    sr.task,
-                    v_int(0),
-                    sr.session,
-                    sr.result_sender,
-                    &self.task_control_sender,
-                    self.database.as_ref(),
-                    self.builtin_registry.clone(),
-                    self.config.clone(),
-                ) {
-                    error!(?task_id, ?e, "Error resuming task");
+            // Once shutting down, suspended tasks stay suspended, and waiting ones don't start.
+            if self.shutdown.is_none() {
+                // Look for tasks that need to be woken (have hit their wakeup-time), and wake them.
+                let to_wake = self.task_q.suspended.collect_wake_tasks();
+                for sr in to_wake {
+                    let task_id = sr.task.task_id;
+                    if let Err(e) = self.task_q.resume_task_thread(
+                        sr.task,
+                        v_int(0),
+                        sr.session,
+                        sr.result_sender,
+                        &self.server_options,
+                        &self.task_control_sender,
+                        self.database.as_ref(),
+                        self.builtin_registry.clone(),
+                        self.config.clone(),
+                    ) {
+                        error!(?task_id, ?e, "Error resuming task");
+                    }
                 }
+                // Start whatever waiting tasks there's now room for.
+                self.start_ready_tasks();
             }
+
             // Handle any scheduler submissions...
-            if let Ok(msg) = self.scheduler_receiver.try_recv() {
+            if let Ok((span, msg)) = self.scheduler_receiver.try_recv() {
+                let _entered = span.enter();
                 self.handle_scheduler_msg(msg);
             }
 
@@ -221,6 +343,8 @@ impl Scheduler {
             {
                 self.handle_task_msg(task_id, msg);
             }
+
+            self.advance_shutdown(&bg_session_factory);
         }
 
         // Write out all the suspended tasks to the database.
@@ -268,6 +392,68 @@ impl Scheduler {
         {
             so.max_stack_depth = max_stack_depth as usize;
         }
+        if let Some(memory_cost) = load_cost_sysprop(
+            server_options_obj,
+            *ARGON2_MEMORY_COST,
+            ARGON2_MEMORY_COST_RANGE,
+            tx.as_ref(),
+        ) {
+            so.argon2_memory_cost = memory_cost;
+        }
+        if let Some(time_cost) = load_cost_sysprop(
+            server_options_obj,
+            *ARGON2_TIME_COST,
+            ARGON2_TIME_COST_RANGE,
+            tx.as_ref(),
+        ) {
+            so.argon2_time_cost = time_cost;
+        }
+        if let Some(parallelism) = load_cost_sysprop(
+            server_options_obj,
+            *ARGON2_PARALLELISM,
+            ARGON2_PARALLELISM_RANGE,
+            tx.as_ref(),
+        ) {
+            so.argon2_parallelism = parallelism;
+        }
+        if let Some(bcrypt_cost) = load_cost_sysprop(
+            server_options_obj,
+            *BCRYPT_COST,
+            BCRYPT_COST_RANGE,
+            tx.as_ref(),
+        ) {
+            so.bcrypt_cost = bcrypt_cost;
+        }
+        so.queued_task_limit =
+            load_int_sysprop(server_options_obj, *QUEUED_TASK_LIMIT, tx.as_ref())
+                .map(|limit| limit as usize);
+        so.connection_task_limit =
+            load_int_sysprop(server_options_obj, *CONNECTION_TASK_LIMIT, tx.as_ref())
+                .map(|limit| limit as usize);
+        so.default_timezone =
+            match tx.retrieve_property(&SYSTEM_OBJECT, server_options_obj, *DEFAULT_TIMEZONE) {
+                Ok(value) => match value.variant() {
+                    Variant::Str(tz) if tz.as_string().parse::<Tz>().is_ok() => {
+                        Some(tz.as_string().clone())
+                    }
+                    _ => {
+                        warn!("$server_options.default_timezone is not a known time zone name");
+                        None
+                    }
+                },
+                Err(_) => None,
+            };
+        so.server_too_busy_msg =
+            match tx.retrieve_property(&SYSTEM_OBJECT, server_options_obj, *SERVER_TOO_BUSY_MSG) {
+                Ok(value) => match value.variant() {
+                    Variant::Str(msg) => Some(msg.as_string().clone()),
+                    _ => {
+                        warn!("$server_options.server_too_busy_msg is not a string");
+                        None
+                    }
+                },
+                Err(_) => None,
+            };
         tx.rollback().unwrap();
 
         self.server_options = so;
@@ -309,19 +495,21 @@ impl Scheduler {
                 return Err(VerbProgramFailed(VerbProgramError::NoVerbToProgram));
             }
 
-            let program = compile(
+            let compile_options = self
+                .config
+                .features_config
+                .compile_options_for(tx.as_ref())
+                .map_err(|_| VerbProgramFailed(VerbProgramError::DatabaseError))?;
+            let binary = compile_cached(
+                &self.database.program_cache(),
                 code.join("\n").as_str(),
-                self.config.features_config.compile_options(),
+                compile_options.clone(),
             )
             .map_err(|e| {
                 // TODO: just dumping a string here sucks.
                 VerbProgramFailed(VerbProgramError::CompilationError(vec![format!("{:?}", e)]))
             })?;
 
-            // Now we have a program, we need to encode it.
-            let binary = program
-                .with_byte_buffer(|d| Vec::from(d))
-                .expect("Failed to encode program byte stream");
             // Now we can update the verb.
             let update_attrs = VerbAttrs {
                 definer: None,
@@ -334,6 +522,13 @@ impl Scheduler {
             };
             tx.update_verb_with_id(perms, &o, verbdef.uuid(), update_attrs)
                 .map_err(|_| VerbProgramFailed(VerbProgramError::NoVerbToProgram))?;
+            tx.set_verb_compile_options(
+                perms,
+                &o,
+                verbdef.uuid(),
+                compile_options_map(&compile_options),
+            )
+            .map_err(|_| VerbProgramFailed(VerbProgramError::DatabaseError))?;
 
             let commit_result = tx.commit().unwrap();
             if commit_result == CommitResult::Success {
@@ -357,27 +552,31 @@ impl Scheduler {
                 session,
                 reply,
             } => {
-                let task_start = Arc::new(TaskStart::StartCommandVerb {
+                // Under overload, turn the command away rather than let it queue up behind
+                // everything else. Wizards are let through, so they can deal with the problem.
+                if self.overloaded() && !self.is_wizard(&player) {
+                    self.task_q.counters.tasks_shed += 1;
+                    let msg = self
+                        .server_options
+                        .server_too_busy_msg
+                        .as_deref()
+                        .unwrap_or(DEFAULT_SERVER_TOO_BUSY_MSG);
+                    if let Err(e) = session.send_system_msg(player.clone(), msg) {
+                        warn!(?e, "Could not send server too busy message to player");
+                    }
+                    reply
+                        .send(Err(SchedulerError::ServerTooBusy))
+                        .expect("Could not send task handle reply");
+                    return;
+                }
+
+                let task_start = TaskStart::StartCommandVerb {
                     handler_object,
                     player: player.clone(),
                     command: command.to_string(),
-                });
+                };
 
-                let task_id = self.next_task_id;
-                self.next_task_id += 1;
-                let result = task_q.start_task_thread(
-                    task_id,
-                    task_start,
-                    &player,
-                    session,
-                    None,
-                    &player,
-                    &self.server_options,
-                    &self.task_control_sender,
-                    self.database.as_ref(),
-                    self.builtin_registry.clone(),
-                    self.config.clone(),
-                );
+                let result = self.submit_task(task_start, &player, &player, session);
                 reply
                     .send(result)
                     .expect("Could not send task handle reply");
@@ -413,28 +612,14 @@ impl Scheduler {
                     }
                 };
 
-                let task_start = Arc::new(TaskStart::StartVerb {
+                let task_start = TaskStart::StartVerb {
                     player: player.clone(),
                     vloc,
                     verb,
                     args,
                     argstr,
-                });
-                let task_id = self.next_task_id;
-                self.next_task_id += 1;
-                let result = task_q.start_task_thread(
-                    task_id,
-                    task_start,
-                    &player,
-                    session,
-                    None,
-                    &perms,
-                    &self.server_options,
-                    &self.task_control_sender,
-                    self.database.as_ref(),
-                    self.builtin_registry.clone(),
-                    self.config.clone(),
-                );
+                };
+                let result = self.submit_task(task_start, &player, &perms, session);
                 reply
                     .send(result)
                     .expect("Could not send task handle reply");
@@ -450,29 +635,41 @@ impl Scheduler {
                 trace!(?input_request_id, ?input, "Received input for task");
 
                 // Find the task that requested this input, if any
-                let Some(sr) = task_q
-                    .suspended
-                    .pull_task_for_input(input_request_id, &player)
-                else {
-                    warn!(?input_request_id, "Input request not found");
-                    reply
-                        .send(Err(InputRequestNotFound(input_request_id.as_u128())))
-                        .expect("Could not send input request not found reply");
-                    return;
-                };
+                let (sr, value) =
+                    match task_q
+                        .suspended
+                        .take_input(input_request_id, &player, input)
+                    {
+                        Some(InputTaken::Resume(sr, value)) => (sr, value),
+                        Some(InputTaken::MoreWanted) => {
+                            // A `read_lines()` still gathering; the client should keep sending.
+                            reply.send(Ok(true)).expect("Could not send input reply");
+                            return;
+                        }
+                        None => {
+                            warn!(?input_request_id, "Input request not found");
+                            reply
+                                .send(Err(InputRequestNotFound(input_request_id.as_u128())))
+                                .expect("Could not send input request not found reply");
+                            return;
+                        }
+                    };
 
                 // Wake and bake.
                 let response = task_q.resume_task_thread(
                     sr.task,
-                    v_string(input),
+                    value,
                     sr.session,
                     sr.result_sender,
+                    &self.server_options,
                     &self.task_control_sender,
                     self.database.as_ref(),
                     self.builtin_registry.clone(),
                     self.config.clone(),
                 );
-                reply.send(response).expect("Could not send input reply");
+                reply
+                    .send(response.map(|_| false))
+                    .expect("Could not send input reply");
             }
             SchedulerClientMsg::SubmitOobTask {
                 handler_object,
@@ -484,28 +681,14 @@ impl Scheduler {
             } => {
                 let args = command.into_iter().map(v_string);
                 let args = List::from_iter(args);
-                let task_start = Arc::new(TaskStart::StartVerb {
+                let task_start = TaskStart::StartVerb {
                     player: player.clone(),
                     vloc: v_obj(handler_object),
                     verb: *DO_OUT_OF_BAND_COMMAND,
                     args,
                     argstr,
-                });
-                let task_id = self.next_task_id;
-                self.next_task_id += 1;
-                let result = task_q.start_task_thread(
-                    task_id,
-                    task_start,
-                    &player,
-                    session,
-                    None,
-                    &player,
-                    &self.server_options,
-                    &self.task_control_sender,
-                    self.database.as_ref(),
-                    self.builtin_registry.clone(),
-                    self.config.clone(),
-                );
+                };
+                let result = self.submit_task(task_start, &player, &player, session);
                 reply
                     .send(result)
                     .expect("Could not send task handle reply");
@@ -517,34 +700,17 @@ impl Scheduler {
                 sessions,
                 reply,
             } => {
-                let task_start = Arc::new(TaskStart::StartEval {
+                let task_start = TaskStart::StartEval {
                     player: player.clone(),
                     program,
-                });
-                let task_id = self.next_task_id;
-                self.next_task_id += 1;
-                let result = task_q.start_task_thread(
-                    task_id,
-                    task_start,
-                    &player,
-                    sessions,
-                    None,
-                    &perms,
-                    &self.server_options,
-                    &self.task_control_sender,
-                    self.database.as_ref(),
-                    self.builtin_registry.clone(),
-                    self.config.clone(),
-                );
+                };
+                let result = self.submit_task(task_start, &player, &perms, sessions);
                 reply
                     .send(result)
                     .expect("Could not send task handle reply");
             }
             SchedulerClientMsg::Shutdown(msg, reply) => {
-                // Send shutdown notifications to all live tasks.
-
-                let result = self.stop(Some(msg));
-                reply.send(result).expect("Could not send shutdown reply");
+                self.begin_shutdown(Some(msg), Some(reply));
             }
             SchedulerClientMsg::SubmitProgramVerb {
                 player,
@@ -603,13 +769,30 @@ impl Scheduler {
                 let result = self.checkpoint();
                 reply.send(result).expect("Could not send checkpoint reply");
             }
+            SchedulerClientMsg::ReloadConfig(config, reply) => {
+                info!("Reloading configuration");
+                self.config = Arc::new(*config);
+                // There may be room for more tasks now.
+                self.start_ready_tasks();
+                if let Err(e) = reply.send(()) {
+                    error!(?e, "Could not send config reload reply");
+                }
+            }
+            SchedulerClientMsg::RequestPerformanceCounters(reply) => {
+                let counters = task_q.counters(
+                    self.database.program_cache().stats(),
+                    &self.config.scheduler_config,
+                );
+                if let Err(e) = reply.send(counters) {
+                    error!(?e, "Could not send performance counters to requester");
+                }
+            }
             SchedulerClientMsg::RequestProperties {
                 player,
                 perms,
                 obj,
                 reply,
             } => {
-                // TODO: check programmer perms here
                 let mut world_state = match self.database.new_world_state() {
                     Ok(ws) => ws,
                     Err(e) => {
@@ -620,6 +803,13 @@ impl Scheduler {
                     }
                 };
 
+                if !is_programmer(world_state.as_ref(), &perms) {
+                    reply
+                        .send(Err(CommandExecutionError(CommandError::PermissionDenied)))
+                        .expect("Could not send properties reply");
+                    return;
+                }
+
                 let Ok(object) = match_object_ref(&player, &perms, &obj, world_state.as_mut())
                 else {
                     reply
@@ -679,7 +869,12 @@ impl Scheduler {
                     }
                 };
 
-                // TODO: User must be a programmer...
+                if !is_programmer(world_state.as_ref(), &perms) {
+                    reply
+                        .send(Err(CommandExecutionError(CommandError::PermissionDenied)))
+                        .expect("Could not send property reply");
+                    return;
+                }
 
                 let Ok(object) = match_object_ref(&player, &perms, &obj, world_state.as_mut())
                 else {
@@ -722,8 +917,6 @@ impl Scheduler {
                 obj,
                 reply,
             } => {
-                // TODO: User must be a programmer...
-
                 let mut world_state = match self.database.new_world_state() {
                     Ok(ws) => ws,
                     Err(e) => {
@@ -734,6 +927,13 @@ impl Scheduler {
                     }
                 };
 
+                if !is_programmer(world_state.as_ref(), &perms) {
+                    reply
+                        .send(Err(CommandExecutionError(CommandError::PermissionDenied)))
+                        .expect("Could not send verbs reply");
+                    return;
+                }
+
                 let Ok(object) = match_object_ref(&perms, &perms, &obj, world_state.as_mut())
                 else {
                     reply
@@ -773,7 +973,13 @@ impl Scheduler {
                     }
                 };
 
-                // TODO: User must be a programmer...
+                if !is_programmer(world_state.as_ref(), &perms) {
+                    reply
+                        .send(Err(CommandExecutionError(CommandError::PermissionDenied)))
+                        .expect("Could not send verb code reply");
+                    return;
+                }
+
                 let Ok(object) = match_object_ref(&perms, &perms, &obj, world_state.as_mut())
                 else {
                     reply
@@ -855,6 +1061,38 @@ impl Scheduler {
                     .send(Ok((verbdef, unparsed)))
                     .expect("Could not send verb code reply");
             }
+            SchedulerClientMsg::RequestObjectGeneration {
+                player,
+                perms,
+                obj,
+                reply,
+            } => {
+                let mut world_state = match self.database.new_world_state() {
+                    Ok(ws) => ws,
+                    Err(e) => {
+                        reply
+                            .send(Err(CommandExecutionError(CommandError::DatabaseError(e))))
+                            .expect("Could not send object generation reply");
+                        return;
+                    }
+                };
+
+                let Ok(object) = match_object_ref(&player, &perms, &obj, world_state.as_mut())
+                else {
+                    reply
+                        .send(Err(CommandExecutionError(CommandError::NoObjectMatch)))
+                        .expect("Could not send object generation reply");
+                    return;
+                };
+
+                let generation = world_state
+                    .object_generation(&object)
+                    .map(|generation| (object, generation))
+                    .map_err(|e| CommandExecutionError(CommandError::DatabaseError(e)));
+                reply
+                    .send(generation)
+                    .expect("Could not send object generation reply");
+            }
             SchedulerClientMsg::ResolveObject { player, obj, reply } => {
                 let mut world_state = match self.database.new_world_state() {
                     Ok(ws) => ws,
@@ -906,6 +1144,7 @@ impl Scheduler {
             }
             TaskControlMsg::TaskConflictRetry(task) => {
                 trace!(?task_id, "Task retrying due to conflict");
+                task_q.counters.commit_conflicts += 1;
 
                 // Ask the task to restart itself, using its stashed original start info, but with
                 // a brand new transaction.
@@ -950,15 +1189,13 @@ impl Scheduler {
                 };
                 task_q.send_task_result(task_id, Err(TaskAbortedCancelled));
             }
-            TaskControlMsg::TaskAbortLimitsReached(limit_reason) => {
-                let abort_reason_text = match limit_reason {
+            TaskControlMsg::TaskAbortLimitsReached(limit_reason, backtrace) => {
+                match limit_reason {
                     AbortLimitReason::Ticks(t) => {
                         warn!(?task_id, ticks = t, "Task aborted, ticks exceeded");
-                        format!("Abort: Task exceeded ticks limit of {}", t)
                     }
                     AbortLimitReason::Time(t) => {
                         warn!(?task_id, time = ?t, "Task aborted, time exceeded");
-                        format!("Abort: Task exceeded time limit of {:?}", t)
                     }
                 };
 
@@ -968,9 +1205,19 @@ impl Scheduler {
                     return;
                 };
 
-                task.session
-                    .send_system_msg(task.player.clone(), &abort_reason_text)
-                    .expect("Could not send abort message to player");
+                // As in LambdaMOO, the player gets the traceback, ending "Task ran out of ticks"
+                // (or seconds).
+                for frame in backtrace.iter() {
+                    let Variant::Str(s) = frame.variant() else {
+                        continue;
+                    };
+                    if let Err(send_error) = task
+                        .session
+                        .send_system_msg(task.player.clone(), s.as_string().as_str())
+                    {
+                        warn!("Could not send abort traceback to player: {:?}", send_error);
+                    }
+                }
 
                 let _ = task.session.commit();
 
@@ -1006,6 +1253,42 @@ impl Scheduler {
 
                 task_q.send_task_result(task_id, Err(TaskAbortedException(exception)));
             }
+            TaskControlMsg::TaskExceptionHandled(exception) => {
+                debug!(?task_id, finally_reason = ?exception, "Task threw exception, handled by core");
+
+                let Some(task) = task_q.tasks.get_mut(&task_id) else {
+                    warn!(task_id, "Task not found for abort");
+                    return;
+                };
+                let _ = task.session.commit();
+
+                task_q.send_task_result(task_id, Err(TaskAbortedException(exception)));
+            }
+            TaskControlMsg::TaskRecorded(recording) => {
+                let Some(replay_dir) = &self.config.scheduler_config.replay_dir else {
+                    warn!(task_id, inputs = ?recording.inputs, "Recorded task inputs");
+                    return;
+                };
+                match recording.write_to(replay_dir) {
+                    Ok(path) => warn!(task_id, ?path, "Recorded task inputs"),
+                    Err(e) => error!(task_id, error = ?e, "Could not write task recording"),
+                }
+            }
+            TaskControlMsg::TaskAbortLimitsHandled(limit_reason) => {
+                warn!(
+                    ?task_id,
+                    ?limit_reason,
+                    "Task aborted, limits exceeded, handled by core"
+                );
+
+                let Some(task) = task_q.tasks.get_mut(&task_id) else {
+                    warn!(task_id, "Task not found for abort");
+                    return;
+                };
+                let _ = task.session.commit();
+
+                task_q.send_task_result(task_id, Err(TaskAbortedLimit(limit_reason)));
+            }
             TaskControlMsg::TaskRequestFork(fork_request, reply) => {
                 trace!(?task_id,  delay=?fork_request.delay, "Task requesting fork");
 
@@ -1050,7 +1333,7 @@ impl Scheduler {
 
                 debug!(task_id, "Task suspended");
             }
-            TaskControlMsg::TaskRequestInput(task) => {
+            TaskControlMsg::TaskRequestInput(request, task) => {
                 // Task has gone into suspension waiting for input from the client.
                 // Create a unique ID for this request, and we'll wake the task when the
                 // session receives input.
@@ -1071,12 +1354,23 @@ impl Scheduler {
                     warn!("Could not request input from session; aborting task");
                     return task_q.send_task_result(task_id, Err(TaskAbortedError));
                 };
-                task_q.suspended.add_task(
-                    WakeCondition::Input(input_request_id),
-                    task,
-                    tc.session,
-                    tc.result_sender,
-                );
+                let wake_condition = match request {
+                    InputRequest::Line => WakeCondition::Input(input_request_id),
+                    InputRequest::Lines {
+                        terminator,
+                        max_lines,
+                    } => {
+                        let limits = &self.config.scheduler_config;
+                        let max_lines = max_lines.map_or(limits.max_input_lines, |max_lines| {
+                            max_lines.min(limits.max_input_lines)
+                        });
+                        let lines = InputLines::new(terminator, max_lines, limits.max_input_bytes);
+                        WakeCondition::InputLines(input_request_id, lines)
+                    }
+                };
+                task_q
+                    .suspended
+                    .add_task(wake_condition, task, tc.session, tc.result_sender);
 
                 trace!(?task_id, "Task suspended waiting for input");
             }
@@ -1110,6 +1404,7 @@ impl Scheduler {
                     queued_task_id,
                     sender_permissions,
                     return_value,
+                    &self.server_options,
                     &self.task_control_sender,
                     self.database.as_ref(),
                     self.builtin_registry.clone(),
@@ -1119,10 +1414,103 @@ impl Scheduler {
                     error!(?e, "Could not send resume task result to requester");
                 }
             }
+            TaskControlMsg::DebugTask {
+                debug_task_id,
+                debugger,
+                sender_permissions,
+                result_sender,
+            } => {
+                let dr = task_q.debug_task(debug_task_id, debugger, sender_permissions);
+                if let Err(e) = result_sender.send(dr) {
+                    error!(?e, "Could not send debug task result to requester");
+                }
+            }
+            TaskControlMsg::SetBreakpoint(breakpoint, reply) => {
+                let id = task_q.breakpoints.add(breakpoint);
+                if let Err(e) = reply.send(id) {
+                    error!(?e, "Could not send breakpoint id to requester");
+                }
+            }
+            TaskControlMsg::ClearBreakpoint(id, reply) => {
+                let removed = task_q.breakpoints.remove(id);
+                if let Err(e) = reply.send(removed) {
+                    error!(?e, "Could not send clear breakpoint result to requester");
+                }
+            }
+            TaskControlMsg::RequestBreakpoints(reply) => {
+                if let Err(e) = reply.send(task_q.breakpoints.list()) {
+                    error!(?e, "Could not send breakpoints to requester");
+                }
+            }
+            TaskControlMsg::WrapVerb(wrapper, reply) => {
+                let id = task_q.verb_wrappers.add(wrapper);
+                if let Err(e) = reply.send(id) {
+                    error!(?e, "Could not send verb wrapper id to requester");
+                }
+            }
+            TaskControlMsg::UnwrapVerb(id, reply) => {
+                let removed = task_q.verb_wrappers.remove(id);
+                if let Err(e) = reply.send(removed) {
+                    error!(?e, "Could not send unwrap verb result to requester");
+                }
+            }
+            TaskControlMsg::RequestVerbWrappers(reply) => {
+                if let Err(e) = reply.send(task_q.verb_wrappers.list()) {
+                    error!(?e, "Could not send verb wrappers to requester");
+                }
+            }
+            TaskControlMsg::SetPermissionAudit(enabled, reply) => {
+                let was_enabled = task_q.permission_audit.set_enabled(enabled);
+                if let Err(e) = reply.send(was_enabled) {
+                    error!(?e, "Could not send permission audit state to requester");
+                }
+            }
+            TaskControlMsg::RequestPermissionChecks(clear, reply) => {
+                if let Err(e) = reply.send(task_q.permission_audit.checks(clear)) {
+                    error!(?e, "Could not send permission checks to requester");
+                }
+            }
             TaskControlMsg::BootPlayer { player } => {
-                // Task is asking to boot a player.
+                // Task is asking to boot a player. Their clients shouldn't be able to just
+                // reattach, so the tokens they hold go too.
+                if let Err(e) = self.system_control.revoke_auth_tokens(&player) {
+                    warn!(
+                        ?e,
+                        ?player,
+                        "Could not revoke auth tokens for booted player"
+                    );
+                }
                 task_q.disconnect_task(task_id, &player);
             }
+            TaskControlMsg::RevokeAuthTokens { player, reply } => {
+                if let Err(e) = reply.send(self.system_control.revoke_auth_tokens(&player)) {
+                    error!(?e, "Could not send revoke_auth_tokens reply to requester");
+                }
+            }
+            TaskControlMsg::SwitchPlayer {
+                old_player,
+                new_player,
+                reply,
+            } => {
+                let result = self.system_control.switch_player(&old_player, &new_player);
+                if let Err(e) = reply.send(result) {
+                    error!(?e, "Could not send switch_player reply to requester");
+                }
+            }
+            TaskControlMsg::FederationSend {
+                world,
+                target,
+                verb,
+                args,
+                reply,
+            } => {
+                let result = self
+                    .system_control
+                    .federation_send(&world, &target, verb, args);
+                if let Err(e) = reply.send(result) {
+                    error!(?e, "Could not send federation_send reply to requester");
+                }
+            }
             TaskControlMsg::Notify { player, event } => {
                 // Task is asking to notify a player of an event.
                 let Some(task) = task_q.tasks.get_mut(&task_id) else {
@@ -1180,10 +1568,85 @@ impl Scheduler {
                 };
                 reply.send(result).expect("Could not send unlisten reply");
             }
+            TaskControlMsg::BanSite { site, reply } => {
+                if let Err(e) = reply.send(self.system_control.ban_site(&site)) {
+                    error!(?e, "Could not send ban_site reply to requester");
+                }
+            }
+            TaskControlMsg::UnbanSite { site, reply } => {
+                if let Err(e) = reply.send(self.system_control.unban_site(&site)) {
+                    error!(?e, "Could not send unban_site reply to requester");
+                }
+            }
+            TaskControlMsg::CheckTaskQuota {
+                owner,
+                player,
+                owner_limit,
+                forking,
+                reply,
+            } => {
+                let owner_limit = owner_limit.or(self.server_options.queued_task_limit);
+                let result = task_q.check_task_quota(
+                    &owner,
+                    &player,
+                    owner_limit,
+                    self.server_options.connection_task_limit,
+                    forking,
+                );
+                if result.is_err() {
+                    task_q.counters.tasks_over_quota += 1;
+                }
+                if let Err(e) = reply.send(result) {
+                    error!(?e, "Could not send task quota reply to requester");
+                }
+            }
+            TaskControlMsg::RequestTaskCounts(who, reply) => {
+                if let Err(e) = reply.send(task_q.task_counts(&who)) {
+                    error!(?e, "Could not send task counts to requester");
+                }
+            }
+            TaskControlMsg::RequestFinishedTasks(who, reply) => {
+                if let Err(e) = reply.send(task_q.finished.tasks(who.as_ref())) {
+                    error!(?e, "Could not send finished tasks to requester");
+                }
+            }
+            TaskControlMsg::RequestPerformanceCounters(reply) => {
+                let counters = task_q.counters(
+                    self.database.program_cache().stats(),
+                    &self.config.scheduler_config,
+                );
+                if let Err(e) = reply.send(counters) {
+                    error!(?e, "Could not send performance counters to requester");
+                }
+            }
+            TaskControlMsg::GetBannedSites(reply) => {
+                if let Err(e) = reply.send(self.system_control.banned_sites()) {
+                    error!(?e, "Could not send banned sites to requester");
+                }
+            }
+            TaskControlMsg::AddWebhook {
+                url,
+                secret,
+                filter,
+                reply,
+            } => {
+                if let Err(e) = reply.send(self.system_control.add_webhook(&url, secret, filter)) {
+                    error!(?e, "Could not send add_webhook reply to requester");
+                }
+            }
+            TaskControlMsg::RemoveWebhook { id, reply } => {
+                if let Err(e) = reply.send(self.system_control.remove_webhook(id)) {
+                    error!(?e, "Could not send remove_webhook reply to requester");
+                }
+            }
+            TaskControlMsg::GetWebhooks(reply) => {
+                if let Err(e) = reply.send(self.system_control.webhooks()) {
+                    error!(?e, "Could not send webhooks to requester");
+                }
+            }
             TaskControlMsg::Shutdown(msg) => {
                 info!("Shutting down scheduler. Reason: {msg:?}");
-                self.stop(msg)
-                    .expect("Could not shutdown scheduler cleanly");
+                self.begin_shutdown(msg, None);
             }
             TaskControlMsg::Checkpoint => {
                 if let Err(e) = self.checkpoint() {
@@ -1193,14 +1656,25 @@ impl Scheduler {
             TaskControlMsg::RefreshServerOptions { .. } => {
                 self.reload_server_options();
             }
+            TaskControlMsg::RequestServerOptions(reply) => {
+                if let Err(e) = reply.send(self.server_options.clone()) {
+                    error!(?e, "Could not send server options to requester");
+                }
+            }
         }
     }
 
     fn checkpoint(&self) -> Result<(), SchedulerError> {
-        let Some(textdump_path) = self.config.textdump_config.output_path.clone() else {
+        self.start_checkpoint().map(|_| ())
+    }
+
+    /// Start writing a textdump to each of the dump sinks, returning the thread writing them.
+    fn start_checkpoint(&self) -> Result<JoinHandle<()>, SchedulerError> {
+        if self.dump_sinks.is_empty() {
             error!("Cannot textdump as textdump_file not configured");
             return Err(SchedulerError::CouldNotStartTask);
-        };
+        }
+        let dump_sinks = self.dump_sinks.clone();
 
         let encoding_mode = self.config.textdump_config.output_encoding;
 
@@ -1222,28 +1696,130 @@ impl Scheduler {
         let tr = std::thread::Builder::new()
             .name("textdump-thread".to_string())
             .spawn(move || {
-                let Ok(mut output) = File::create(&textdump_path) else {
-                    error!("Could not open textdump file for writing");
-                    return;
-                };
-
                 trace!("Creating textdump...");
                 let textdump = make_textdump(loader_client.as_ref(), version_string);
 
-                debug!(?textdump_path, "Writing textdump..");
-                let mut writer = TextdumpWriter::new(&mut output, encoding_mode);
-                if let Err(e) = writer.write_textdump(&textdump) {
-                    error!(?e, "Could not write textdump");
-                    return;
+                for sink in dump_sinks {
+                    let destination = sink.describe();
+                    debug!(?destination, "Writing textdump..");
+                    let result = sink.store(&mut |output| {
+                        TextdumpWriter::new(output, encoding_mode).write_textdump(&textdump)
+                    });
+                    match result {
+                        Ok(()) => trace!(?destination, "Textdump written."),
+                        Err(e) => error!(?destination, ?e, "Could not write textdump"),
+                    }
                 }
-                trace!(?textdump_path, "Textdump written.");
             });
-        if let Err(e) = tr {
+        tr.map_err(|e| {
             error!(?e, "Could not start textdump thread");
+            SchedulerError::CouldNotStartTask
+        })
+    }
+    /// Start a task submitted on behalf of a player, or if we're already running as many tasks as
+    /// we're allowed to, queue it up for `start_ready_tasks`. Either way the submitter gets a
+    /// handle for the task's result straight away.
+    #[instrument(skip(self, task_start, session))]
+    fn submit_task(
+        &mut self,
+        task_start: TaskStart,
+        player: &Obj,
+        perms: &Obj,
+        session: Arc<dyn Session>,
+    ) -> Result<TaskHandle, SchedulerError> {
+        if self.shutdown.is_some() {
+            return Err(SchedulerError::ShuttingDown);
         }
+        let task_id = self.next_task_id;
+        self.next_task_id += 1;
+        let task_start = Arc::new(task_start);
 
-        Ok(())
+        // Anything already waiting goes first, so as not to jump the queue.
+        if self.has_free_task_slot() && self.task_q.ready.is_empty() {
+            return self.task_q.start_task_thread(
+                task_id,
+                task_start,
+                player,
+                session,
+                None,
+                perms,
+                &self.server_options,
+                &self.task_control_sender,
+                self.database.as_ref(),
+                self.builtin_registry.clone(),
+                self.config.clone(),
+            );
+        }
+
+        let priority = self.config.scheduler_config.wizard_priority && self.is_wizard(perms);
+        let (result_sender, receiver) = oneshot::channel();
+        self.task_q.ready.push(
+            PendingTask {
+                task_id,
+                task_start,
+                player: player.clone(),
+                perms: perms.clone(),
+                session,
+                result_sender,
+            },
+            priority,
+        );
+        Ok(TaskHandle(task_id, receiver))
+    }
+
+    /// Whether the server is past one of its configured overload thresholds, and should turn
+    /// away new commands.
+    fn overloaded(&self) -> bool {
+        self.task_q.overloaded(&self.config.scheduler_config)
     }
+
+    fn has_free_task_slot(&self) -> bool {
+        match self.config.scheduler_config.max_running_tasks {
+            Some(max_running_tasks) => self.task_q.tasks.len() < max_running_tasks,
+            None => true,
+        }
+    }
+
+    fn is_wizard(&self, who: &Obj) -> bool {
+        let Ok(tx) = self.database.new_world_state() else {
+            return false;
+        };
+        let is_wizard = tx
+            .flags_of(who)
+            .map(|flags| flags.contains(ObjFlag::Wizard))
+            .unwrap_or(false);
+        let _ = tx.rollback();
+        is_wizard
+    }
+
+    /// Start waiting tasks, round-robin across players (wizards first, if so configured), until
+    /// there's no more room.
+    fn start_ready_tasks(&mut self) {
+        while self.has_free_task_slot() {
+            let Some(pending) = self.task_q.ready.pop() else {
+                return;
+            };
+            let task_id = pending.task_id;
+            // If this fails, the dropped result sender tells the submitter.
+            if let Err(e) = self.task_q.spawn_task(
+                task_id,
+                pending.task_start,
+                &pending.player,
+                pending.session,
+                None,
+                &pending.perms,
+                pending.result_sender,
+                &self.server_options,
+                &self.task_control_sender,
+                self.database.as_ref(),
+                self.builtin_registry.clone(),
+                self.config.clone(),
+            ) {
+                error!(?task_id, ?e, "Could not start waiting task");
+            }
+        }
+    }
+
     #[instrument(skip(self, session))]
     fn process_fork_request(
         &mut self,
@@ -1294,46 +1870,301 @@ impl Scheduler {
         }
     }
 
-    /// Stop the scheduler run loop.
-    fn stop(&mut self, msg: Option<String>) -> Result<(), SchedulerError> {
-        // Send shutdown notification to all live tasks.
+    /// Start an orderly shutdown, or if one is already under way, wait for that one. `reply`, if
+    /// given, hears when it's complete.
+    ///
+    /// The stages, each logged as it starts, are:
+    ///   1. Tell connected players, and stop starting new tasks or waking suspended ones.
+    ///   2. Call `#0:server_shutdown(msg)`, if it exists, giving it `shutdown_hook_timeout`.
+    ///   3. Give running tasks `shutdown_task_timeout` to finish, then abort the rest, and give
+    ///      them as long again to stop before abandoning them.
+    ///   4. Write a final textdump, if they're configured, giving it `shutdown_checkpoint_timeout`.
+    ///   5. Ask the hosts to shut down, and stop the run loop (which then saves suspended tasks).
+    fn begin_shutdown(
+        &mut self,
+        msg: Option<String>,
+        reply: Option<oneshot::Sender<Result<(), SchedulerError>>>,
+    ) {
+        if let Some(shutdown) = self.shutdown.as_mut() {
+            info!("Shutdown already in progress");
+            shutdown.replies.extend(reply);
+            return;
+        }
+        self.shutdown = Some(Shutdown {
+            msg,
+            stage: ShutdownStage::Requested,
+            deadline: Instant::now(),
+            replies: reply.into_iter().collect(),
+        });
+    }
+
+    /// Move the shutdown in progress, if any, on to its next stage once the current one is done
+    /// or out of time.
+    fn advance_shutdown(&mut self, bg_session_factory: &Arc<dyn SessionFactory>) {
+        let Some(mut shutdown) = self.shutdown.take() else {
+            return;
+        };
+        let now = Instant::now();
+        let timeouts = self.config.scheduler_config.clone();
+        let stage = std::mem::replace(&mut shutdown.stage, ShutdownStage::Requested);
+        shutdown.stage = match stage {
+            ShutdownStage::Requested => {
+                warn!("Shutting down: notifying players");
+                self.notify_shutdown(&shutdown.msg, bg_session_factory);
+                for pending in self.task_q.ready.drain() {
+                    let _ = pending.result_sender.send(Err(TaskAbortedCancelled));
+                }
+                match self.start_shutdown_hook(&shutdown.msg, bg_session_factory) {
+                    Some(task_id) => {
+                        info!("Shutting down: calling #0:server_shutdown");
+                        shutdown.deadline = now + timeouts.shutdown_hook_timeout;
+                        ShutdownStage::Hook(task_id)
+                    }
+                    None => self.start_draining(&mut shutdown, now),
+                }
+            }
+            ShutdownStage::Hook(task_id) => {
+                if !self.task_q.tasks.contains_key(&task_id) {
+                    self.start_draining(&mut shutdown, now)
+                } else if now >= shutdown.deadline {
+                    warn!("Shutting down: #0:server_shutdown did not return in time");
+                    self.start_draining(&mut shutdown, now)
+                } else {
+                    ShutdownStage::Hook(task_id)
+                }
+            }
+            ShutdownStage::Draining => {
+                if self.task_q.tasks.is_empty() {
+                    self.start_final_checkpoint(&mut shutdown, now)
+                } else if now >= shutdown.deadline {
+                    warn!(
+                        "Shutting down: aborting {} tasks still running",
+                        self.task_q.tasks.len()
+                    );
+                    for task in self.task_q.tasks.values() {
+                        task.kill_switch.store(true, Ordering::SeqCst);
+                    }
+                    shutdown.deadline = now + timeouts.shutdown_task_timeout;
+                    ShutdownStage::Aborting
+                } else {
+                    ShutdownStage::Draining
+                }
+            }
+            ShutdownStage::Aborting => {
+                if self.task_q.tasks.is_empty() {
+                    self.start_final_checkpoint(&mut shutdown, now)
+                } else if now >= shutdown.deadline {
+                    warn!(
+                        "Shutting down: {} tasks did not stop; abandoning them",
+                        self.task_q.tasks.len()
+                    );
+                    self.task_q.tasks.clear();
+                    self.start_final_checkpoint(&mut shutdown, now)
+                } else {
+                    ShutdownStage::Aborting
+                }
+            }
+            ShutdownStage::Checkpointing(textdump) => {
+                let done = match &textdump {
+                    Some(textdump) => textdump.is_finished(),
+                    None => true,
+                };
+                if done {
+                    return self.finish_shutdown(shutdown);
+                } else if now >= shutdown.deadline {
+                    warn!("Shutting down: final textdump did not finish in time");
+                    return self.finish_shutdown(shutdown);
+                } else {
+                    ShutdownStage::Checkpointing(textdump)
+                }
+            }
+        };
+        self.shutdown = Some(shutdown);
+    }
+
+    fn start_draining(&mut self, shutdown: &mut Shutdown, now: Instant) -> ShutdownStage {
+        info!(
+            "Shutting down: waiting for {} running tasks to finish",
+            self.task_q.tasks.len()
+        );
+        shutdown.deadline = now + self.config.scheduler_config.shutdown_task_timeout;
+        ShutdownStage::Draining
+    }
+
+    fn start_final_checkpoint(&mut self, shutdown: &mut Shutdown, now: Instant) -> ShutdownStage {
+        if self.dump_sinks.is_empty() {
+            return ShutdownStage::Checkpointing(None);
+        }
+        info!("Shutting down: writing final textdump");
+        shutdown.deadline = now + self.config.scheduler_config.shutdown_checkpoint_timeout;
+        ShutdownStage::Checkpointing(self.start_checkpoint().ok())
+    }
+
+    /// Let players know the server is going down: those with tasks running through their task's
+    /// session, and everyone else connected through a background one.
+    fn notify_shutdown(&self, msg: &Option<String>, bg_session_factory: &Arc<dyn SessionFactory>) {
+        let mut notified = vec![];
         for (_, task) in self.task_q.tasks.iter() {
             let _ = task.session.notify_shutdown(msg.clone());
+            notified.push(task.player.clone());
         }
-        warn!("Issuing clean shutdown...");
-        {
-            // Send shut down to all the tasks.
-            for (_, task) in self.task_q.tasks.drain() {
-                task.kill_switch.store(true, Ordering::SeqCst);
+        let Ok(session) = bg_session_factory
+            .clone()
+            .mk_background_session(&SYSTEM_OBJECT)
+        else {
+            return;
+        };
+        let Ok(players) = session.connected_players() else {
+            return;
+        };
+        let text = match msg {
+            Some(msg) => format!("** Server is shutting down: {msg} **"),
+            None => "** Server is shutting down **".to_string(),
+        };
+        for player in players {
+            if notified.contains(&player) {
+                continue;
             }
+            let event = NarrativeEvent::notify(v_obj(SYSTEM_OBJECT), v_str(&text), None);
+            let _ = session.send_event(player, event);
+        }
+        if let Err(e) = session.commit() {
+            warn!(?e, "Could not notify players of shutdown");
         }
-        warn!("Waiting for tasks to finish...");
+    }
 
-        // Then spin until they're all done.
-        loop {
-            {
-                if self.task_q.tasks.is_empty() {
-                    break;
-                }
+    /// Start `#0:server_shutdown(msg)` running, if there is such a verb.
+    fn start_shutdown_hook(
+        &mut self,
+        msg: &Option<String>,
+        bg_session_factory: &Arc<dyn SessionFactory>,
+    ) -> Option<TaskId> {
+        let tx = self.database.new_world_state().ok()?;
+        let has_hook = tx
+            .find_method_verb_on(&SYSTEM_OBJECT, &SYSTEM_OBJECT, *SERVER_SHUTDOWN)
+            .is_ok();
+        let _ = tx.rollback();
+        if !has_hook {
+            return None;
+        }
+        let session = bg_session_factory
+            .clone()
+            .mk_background_session(&SYSTEM_OBJECT)
+            .unwrap_or_else(|_| Arc::new(NoopClientSession::new()));
+        let task_id = self.next_task_id;
+        self.next_task_id += 1;
+        let task_start = Arc::new(TaskStart::StartVerb {
+            player: SYSTEM_OBJECT,
+            vloc: v_obj(SYSTEM_OBJECT),
+            verb: *SERVER_SHUTDOWN,
+            args: List::mk_list(&[v_str(msg.as_deref().unwrap_or(""))]),
+            argstr: String::new(),
+        });
+        match self.task_q.start_task_thread(
+            task_id,
+            task_start,
+            &SYSTEM_OBJECT,
+            session,
+            None,
+            &SYSTEM_OBJECT,
+            &self.server_options,
+            &self.task_control_sender,
+            self.database.as_ref(),
+            self.builtin_registry.clone(),
+            self.config.clone(),
+        ) {
+            Ok(_) => Some(task_id),
+            Err(e) => {
+                error!(?e, "Could not start #0:server_shutdown");
+                None
             }
-            yield_now();
         }
+    }
 
-        // Now ask the rpc server and hosts to shutdown
-        self.system_control
-            .shutdown(msg)
-            .expect("Could not cleanly shutdown system");
+    /// The last stage of shutdown: make sure the database is durable, ask the rpc server and
+    /// hosts to shut down, and stop the run loop.
+    fn finish_shutdown(&mut self, shutdown: Shutdown) {
+        if let Err(e) = self.database.checkpoint() {
+            error!(?e, "Could not checkpoint database");
+        }
+        if let Err(e) = self.system_control.shutdown(shutdown.msg) {
+            error!(?e, "Could not cleanly shut down system");
+        }
 
-        warn!("All tasks finished.  Stopping scheduler.");
+        warn!("Shutdown complete. Stopping scheduler.");
         self.running = false;
-
-        Ok(())
+        for reply in shutdown.replies {
+            let _ = reply.send(Ok(()));
+        }
     }
 }
 
 impl TaskQ {
+    /// The scheduler's counters, with the current task counts, load, and the given program cache
+    /// (hits, misses) filled in.
+    fn counters(
+        &self,
+        (hits, misses): (usize, usize),
+        config: &SchedulerConfig,
+    ) -> SchedulerCounters {
+        let (commits, commit_time) = self.load.commit_totals();
+        SchedulerCounters {
+            active_tasks: self.tasks.len() as u64,
+            suspended_tasks: self.suspended.num_tasks() as u64,
+            ready_tasks: self.ready.len() as u64,
+            overloaded: self.overloaded(config),
+            commit_latency_us: self.load.commit_latency().as_micros() as u64,
+            commits,
+            commit_time_us: commit_time.as_micros() as u64,
+            program_cache_hits: hits as u64,
+            program_cache_misses: misses as u64,
+            ..self.counters.clone()
+        }
+    }
+
+    /// How many tasks, running or queued, are owned by `who`, and how many are running on their
+    /// behalf.
+    fn task_counts(&self, who: &Obj) -> (usize, usize) {
+        let (mut owned, mut on_behalf) = self.suspended.task_counts(who);
+        for tc in self.tasks.values() {
+            owned += usize::from(tc.perms == *who);
+            on_behalf += usize::from(tc.player == *who);
+        }
+        (owned, on_behalf)
+    }
+
+    /// E_QUOTA if queueing the calling task -- or, if `forking`, another one -- would take its
+    /// owner or its player over their limit. The calling task is already counted, as running.
+    fn check_task_quota(
+        &self,
+        owner: &Obj,
+        player: &Obj,
+        owner_limit: Option<usize>,
+        player_limit: Option<usize>,
+        forking: bool,
+    ) -> Result<(), Error> {
+        let adding = usize::from(forking);
+        let (owned, _) = self.task_counts(owner);
+        let (_, on_behalf) = self.task_counts(player);
+        if owner_limit.is_some_and(|limit| owned + adding > limit)
+            || player_limit.is_some_and(|limit| on_behalf + adding > limit)
+        {
+            return Err(E_QUOTA);
+        }
+        Ok(())
+    }
+
+    fn overloaded(&self, config: &SchedulerConfig) -> bool {
+        let too_many_ready = config
+            .overload_ready_tasks
+            .is_some_and(|max| self.ready.len() >= max);
+        let commits_too_slow = config
+            .overload_commit_latency
+            .is_some_and(|max| self.load.commit_latency() >= max);
+        too_many_ready || commits_too_slow
+    }
+
     #[allow(clippy::too_many_arguments)]
-    #[instrument(skip(self, control_sender, database, session, builtin_registry))]
     fn start_task_thread(
         &mut self,
         task_id: TaskId,
@@ -1349,6 +2180,50 @@ impl TaskQ {
         config: Arc<Config>,
     ) -> Result<TaskHandle, SchedulerError> {
         let (sender, receiver) = oneshot::channel();
+        self.spawn_task(
+            task_id,
+            task_start,
+            player,
+            session,
+            delay_start,
+            perms,
+            sender,
+            server_options,
+            control_sender,
+            database,
+            builtin_registry,
+            config,
+        )?;
+        Ok(TaskHandle(task_id, receiver))
+    }
+
+    /// Set up a task and start its thread (or suspend it, if its start is delayed), with its
+    /// result going to `result_sender`.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(
+        self,
+        result_sender,
+        control_sender,
+        database,
+        session,
+        builtin_registry
+    ))]
+    fn spawn_task(
+        &mut self,
+        task_id: TaskId,
+        task_start: Arc<TaskStart>,
+        player: &Obj,
+        session: Arc<dyn Session>,
+        delay_start: Option<Duration>,
+        perms: &Obj,
+        result_sender: oneshot::Sender<Result<TaskResult, SchedulerError>>,
+        server_options: &ServerOptions,
+        control_sender: &Sender<(TaskId, TaskControlMsg)>,
+        database: &dyn Database,
+        builtin_registry: Arc<BuiltinRegistry>,
+        config: Arc<Config>,
+    ) -> Result<(), SchedulerError> {
+        self.counters.tasks_started += 1;
 
         let task_scheduler_client = TaskSchedulerClient::new(task_id, control_sender.clone());
 
@@ -1361,6 +2236,12 @@ impl TaskQ {
             server_options,
             kill_switch.clone(),
         );
+        task.vm_host.set_breakpoints(self.breakpoints.clone());
+        task.vm_host.set_verb_wrappers(self.verb_wrappers.clone());
+        task.vm_host
+            .set_permission_audit(self.permission_audit.clone());
+        task.vm_host.set_load_monitor(self.load.clone());
+        task.vm_host.set_tick_costs(self.tick_costs.clone());
 
         // If this task is delayed, stick it into suspension state immediately.
         if let Some(delay) = delay_start {
@@ -1374,7 +2255,11 @@ impl TaskQ {
                 }
             };
 
-            if !task.setup_task_start(control_sender, world_state.as_mut()) {
+            if !task.setup_task_start(
+                control_sender,
+                world_state.as_mut(),
+                &config.features_config,
+            ) {
                 error!(task_id, "Could not setup task start");
                 return Err(SchedulerError::CouldNotStartTask);
             }
@@ -1393,16 +2278,19 @@ impl TaskQ {
             }
             let wake_condition = WakeCondition::Time(Instant::now() + delay);
             self.suspended
-                .add_task(wake_condition, task, session, Some(sender));
-            return Ok(TaskHandle(task_id, receiver));
+                .add_task(wake_condition, task, session, Some(result_sender));
+            return Ok(());
         }
 
         // Otherwise, we create a task control record and fire up a thread.
         let task_control = RunningTaskControl {
             player: player.clone(),
+            perms: perms.clone(),
+            origin: TaskOrigin::of(task.task_start.as_ref()),
+            started: Instant::now(),
             kill_switch,
             session: session.clone(),
-            result_sender: Some(sender),
+            result_sender: Some(result_sender),
         };
 
         // Footgun warning: ALWAYS `self.tasks.insert` before spawning the task thread!
@@ -1418,13 +2306,20 @@ impl TaskQ {
                 return Err(SchedulerError::CouldNotStartTask);
             }
         };
+        // Parent the task's execution on whatever span it was started from, e.g. the RPC request.
+        let task_span = info_span!("task", task_id);
         std::thread::Builder::new()
             .name(thread_name)
             .spawn(move || {
+                let _entered = task_span.enter();
                 trace!(?task_id, "Starting up task");
                 // Start the db transaction, which will initially be used to resolve the verb before the task
                 // starts executing.
-                if !task.setup_task_start(&control_sender, world_state.as_mut()) {
+                if !task.setup_task_start(
+                    &control_sender,
+                    world_state.as_mut(),
+                    &config.features_config,
+                ) {
                     // Log level should be low here as this happens on every command if `do_command`
                     // is not found.
                     trace!(task_start = ?task.task_start, task_id, "Could not setup task start");
@@ -1443,13 +2338,14 @@ impl TaskQ {
             })
             .expect("Could not spawn task thread");
 
-        Ok(TaskHandle(task_id, receiver))
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
     #[instrument(skip(
         self,
         result_sender,
+        server_options,
         control_sender,
         database,
         session,
@@ -1461,6 +2357,7 @@ impl TaskQ {
         resume_val: Var,
         session: Arc<dyn Session>,
         result_sender: Option<oneshot::Sender<Result<TaskResult, SchedulerError>>>,
+        server_options: &ServerOptions,
         control_sender: &Sender<(TaskId, TaskControlMsg)>,
         database: &dyn Database,
         builtin_registry: Arc<BuiltinRegistry>,
@@ -1471,6 +2368,7 @@ impl TaskQ {
         //   Start a new transaction
         //   Create a new control record
         //   Push resume-value into the task
+        self.counters.tasks_resumed += 1;
 
         // Start its new transaction...
         let world_state = match database.new_world_state() {
@@ -1482,23 +2380,39 @@ impl TaskQ {
         };
 
         let task_id = task.task_id;
-        let player = task.perms.clone();
+        let player = task.player.clone();
         let kill_switch = task.kill_switch.clone();
         let task_control = RunningTaskControl {
             player: player.clone(),
+            perms: task.perms.clone(),
+            origin: TaskOrigin::of(task.task_start.as_ref()),
+            started: Instant::now(),
             kill_switch,
             session: session.clone(),
             result_sender,
         };
 
         self.tasks.insert(task_id, task_control);
+        // As in LambdaMOO, a task carries on from suspension as a background task, with a fresh
+        // background allotment of ticks and seconds.
+        let (max_seconds, max_ticks, _) = server_options.max_vm_values(true);
+        task.vm_host
+            .set_limits(max_ticks, Duration::from_secs(max_seconds));
         task.vm_host.resume_execution(resume_val);
+        task.vm_host.set_breakpoints(self.breakpoints.clone());
+        task.vm_host.set_verb_wrappers(self.verb_wrappers.clone());
+        task.vm_host
+            .set_permission_audit(self.permission_audit.clone());
+        task.vm_host.set_load_monitor(self.load.clone());
+        task.vm_host.set_tick_costs(self.tick_costs.clone());
         let thread_name = format!("moor-task-{}-player-{}", task_id, player);
         let control_sender = control_sender.clone();
         let task_scheduler_client = TaskSchedulerClient::new(task_id, control_sender.clone());
+        let task_span = info_span!("resume_task", task_id);
         std::thread::Builder::new()
             .name(thread_name)
             .spawn(move || {
+                let _entered = task_span.enter();
                 Task::run_task_loop(
                     task,
                     &task_scheduler_client,
@@ -1514,6 +2428,23 @@ impl TaskQ {
         Ok(())
     }
 
+    fn record_finished(
+        &mut self,
+        task_id: TaskId,
+        task_control: &RunningTaskControl,
+        result: Result<Var, SchedulerError>,
+    ) {
+        self.finished.record(FinishedTask {
+            task_id,
+            player: task_control.player.clone(),
+            programmer: task_control.perms.clone(),
+            origin: task_control.origin.clone(),
+            finished_at: SystemTime::now(),
+            runtime: task_control.started.elapsed(),
+            result,
+        });
+    }
+
     fn send_task_result(&mut self, task_id: TaskId, result: Result<Var, SchedulerError>) {
         let Some(mut task_control) = self.tasks.remove(&task_id) else {
             // Missing task, must have ended already or gone into suspension?
@@ -1521,6 +2452,13 @@ impl TaskQ {
             warn!(task_id, "Task not found for notification, ignoring");
             return;
         };
+        match &result {
+            Ok(_) => self.counters.tasks_succeeded += 1,
+            Err(TaskAbortedException(_)) => self.counters.tasks_exceptions += 1,
+            Err(TaskAbortedLimit(_)) => self.counters.tasks_limits_exceeded += 1,
+            Err(_) => self.counters.tasks_aborted += 1,
+        }
+        self.record_finished(task_id, &task_control, result.clone());
         let result_sender = task_control.result_sender.take();
         let Some(result_sender) = result_sender else {
             return;
@@ -1593,13 +2531,27 @@ impl TaskQ {
 
     #[instrument(skip(self))]
     fn kill_task(&mut self, victim_task_id: TaskId, sender_permissions: Perms) -> Var {
+        // Tasks which haven't started yet just get dropped from the ready queue.
+        if let Some(pending) = self.ready.get(victim_task_id) {
+            if !sender_permissions
+                .check_is_wizard()
+                .expect("Could not check wizard status for kill request")
+                && sender_permissions.who != pending.player
+            {
+                return v_err(E_PERM);
+            }
+            let pending = self.ready.remove(victim_task_id).unwrap();
+            let _ = pending.result_sender.send(Err(TaskAbortedCancelled));
+            return v_none();
+        }
+
         // We need to do perms check first, which means checking both running and suspended tasks,
         // and getting their permissions. And may as well remember whether it was in suspended or
         // active at the same time.
         let (perms, is_suspended) = match self.suspended.perms_check(victim_task_id, false) {
             Some(perms) => (perms, true),
             None => match self.tasks.get(&victim_task_id) {
-                Some(tc) => (tc.player.clone(), false),
+                Some(tc) => (tc.perms.clone(), false),
                 None => {
                     return v_err(E_INVARG);
                 }
@@ -1623,11 +2575,20 @@ impl TaskQ {
 
         // If suspended we can just remove completely and move on.
         if is_suspended {
-            if self.suspended.remove_task(victim_task_id).is_none() {
-                error!(
+            match self.suspended.remove_task(victim_task_id) {
+                Some(sr) => self.finished.record(FinishedTask {
+                    task_id: victim_task_id,
+                    player: sr.task.player.clone(),
+                    programmer: sr.task.perms.clone(),
+                    origin: TaskOrigin::of(sr.task.task_start.as_ref()),
+                    finished_at: SystemTime::now(),
+                    runtime: Duration::ZERO,
+                    result: Err(TaskAbortedCancelled),
+                }),
+                None => error!(
                     task = victim_task_id,
                     "Task not found in suspended list for kill request"
-                );
+                ),
             }
             return v_none();
         }
@@ -1641,17 +2602,44 @@ impl TaskQ {
             }
         };
         victim_task.kill_switch.store(true, Ordering::SeqCst);
+        self.record_finished(victim_task_id, &victim_task, Err(TaskAbortedCancelled));
+        v_none()
+    }
+
+    #[instrument(skip(self))]
+    fn debug_task(
+        &mut self,
+        debug_task_id: TaskId,
+        debugger: Option<Obj>,
+        sender_permissions: Perms,
+    ) -> Var {
+        // Only wizards get to poke around inside other tasks.
+        if !sender_permissions
+            .check_is_wizard()
+            .expect("Could not check wizard status for debug request")
+        {
+            return v_err(E_PERM);
+        }
+
+        // Only suspended tasks can be stepped; a running task would have to be caught mid-slice.
+        if self.suspended.perms_check(debug_task_id, true).is_none() {
+            return v_err(E_INVARG);
+        }
+        if !self.suspended.set_debugger(debug_task_id, debugger) {
+            return v_err(E_INVARG);
+        }
         v_none()
     }
 
     #[allow(clippy::too_many_arguments)]
-    #[instrument(skip(self, control_sender, database, builtin_registry))]
+    #[instrument(skip(self, server_options, control_sender, database, builtin_registry))]
     fn resume_task(
         &mut self,
         requesting_task_id: TaskId,
         queued_task_id: TaskId,
         sender_permissions: Perms,
         return_value: Var,
+        server_options: &ServerOptions,
         control_sender: &Sender<(TaskId, TaskControlMsg)>,
         database: &dyn Database,
         builtin_registry: Arc<BuiltinRegistry>,
@@ -1689,6 +2677,7 @@ impl TaskQ {
                 return_value,
                 sr.session,
                 sr.result_sender,
+                server_options,
                 control_sender,
                 database,
                 builtin_registry,
@@ -1735,6 +2724,14 @@ impl TaskQ {
     }
 }
 
+/// Browsing verbs and properties from outside the world, as an object explorer does, is for
+/// programmers (and wizards) only.
+fn is_programmer(tx: &dyn WorldState, who: &Obj) -> bool {
+    tx.flags_of(who)
+        .map(|flags| flags.contains(ObjFlag::Programmer) || flags.contains(ObjFlag::Wizard))
+        .unwrap_or(false)
+}
+
 fn match_object_ref(
     player: &Obj,
     perms: &Obj,
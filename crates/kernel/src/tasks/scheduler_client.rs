@@ -14,16 +14,16 @@
 use crossbeam_channel::Sender;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{instrument, trace};
+use tracing::{instrument, trace, Span};
 use uuid::Uuid;
 
 use moor_compiler::{compile, Program};
 use moor_values::model::{ObjectRef, PropDef, PropPerms, VerbDef, VerbDefs};
 use moor_values::{List, Obj, Symbol, Var};
 
-use crate::config::FeaturesConfig;
+use crate::config::{Config, FeaturesConfig};
 use crate::tasks::sessions::Session;
-use crate::tasks::TaskHandle;
+use crate::tasks::{SchedulerCounters, TaskHandle};
 use moor_values::tasks::SchedulerError;
 use moor_values::tasks::SchedulerError::CompilationError;
 
@@ -32,11 +32,13 @@ use moor_values::tasks::SchedulerError::CompilationError;
 /// Handles requests for task submission, shutdown, etc.
 #[derive(Clone)]
 pub struct SchedulerClient {
-    scheduler_sender: Sender<SchedulerClientMsg>,
+    scheduler_sender: Sender<(Span, SchedulerClientMsg)>,
 }
 
 impl SchedulerClient {
-    pub fn new(scheduler_sender: Sender<SchedulerClientMsg>) -> Self {
+    /// Messages are sent along with the caller's current span, so that work the scheduler does on
+    /// the caller's behalf is traced as part of the same request.
+    pub fn new(scheduler_sender: Sender<(Span, SchedulerClientMsg)>) -> Self {
         Self { scheduler_sender }
     }
 
@@ -52,13 +54,16 @@ impl SchedulerClient {
         trace!(?player, ?command, "Command submitting");
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitCommandTask {
-                handler_object: handler_object.clone(),
-                player: player.clone(),
-                command: command.to_string(),
-                session,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitCommandTask {
+                    handler_object: handler_object.clone(),
+                    player: player.clone(),
+                    command: command.to_string(),
+                    session,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -85,16 +90,19 @@ impl SchedulerClient {
         trace!(?player, ?verb, ?args, "Verb submitting");
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitVerbTask {
-                player: player.clone(),
-                vloc: vloc.clone(),
-                verb: Symbol::mk_case_insensitive(verb.as_str()),
-                args,
-                argstr,
-                perms: perms.clone(),
-                session,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitVerbTask {
+                    player: player.clone(),
+                    vloc: vloc.clone(),
+                    verb: Symbol::mk_case_insensitive(verb.as_str()),
+                    args,
+                    argstr,
+                    perms: perms.clone(),
+                    session,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -106,20 +114,25 @@ impl SchedulerClient {
     /// `input_request_id`.
     /// The request is identified by the `input_request_id`, and given the input and resumed under
     /// a new transaction.
+    /// Returns true if the task is in `read_lines()` and wants more lines, in which case the client
+    /// should send the next one to the same request.
     pub fn submit_requested_input(
         &self,
         player: &Obj,
         input_request_id: Uuid,
         input: String,
-    ) -> Result<(), SchedulerError> {
+    ) -> Result<bool, SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitTaskInput {
-                player: player.clone(),
-                input_request_id,
-                input,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitTaskInput {
+                    player: player.clone(),
+                    input_request_id,
+                    input,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -139,14 +152,17 @@ impl SchedulerClient {
         trace!(?player, ?command, "Out-of-band task submitting");
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitOobTask {
-                handler_object: handler_object.clone(),
-                player: player.clone(),
-                command,
-                argstr,
-                session,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitOobTask {
+                    handler_object: handler_object.clone(),
+                    player: player.clone(),
+                    command,
+                    argstr,
+                    session,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -172,13 +188,16 @@ impl SchedulerClient {
 
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitEvalTask {
-                player: player.clone(),
-                perms: perms.clone(),
-                program,
-                sessions,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitEvalTask {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    program,
+                    sessions,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -191,7 +210,10 @@ impl SchedulerClient {
         // If we can't deliver a shutdown message, that's really a cause for panic!
         let (send, reply) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::Shutdown(msg.to_string(), send))
+            .send((
+                Span::current(),
+                SchedulerClientMsg::Shutdown(msg.to_string(), send),
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
         reply
             .recv()
@@ -208,14 +230,17 @@ impl SchedulerClient {
     ) -> Result<(Obj, Symbol), SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::SubmitProgramVerb {
-                player: player.clone(),
-                perms: perms.clone(),
-                obj: obj.clone(),
-                verb_name,
-                code,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::SubmitProgramVerb {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    verb_name,
+                    code,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -231,12 +256,15 @@ impl SchedulerClient {
     ) -> Result<Var, SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::RequestSystemProperty {
-                player: player.clone(),
-                obj: obj.clone(),
-                property,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestSystemProperty {
+                    player: player.clone(),
+                    obj: obj.clone(),
+                    property,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -247,7 +275,7 @@ impl SchedulerClient {
     pub fn request_checkpoint(&self) -> Result<(), SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::Checkpoint(reply))
+            .send((Span::current(), SchedulerClientMsg::Checkpoint(reply)))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -255,6 +283,37 @@ impl SchedulerClient {
             .map_err(|_| SchedulerError::SchedulerNotResponding)?
     }
 
+    /// Replace the configuration used for tasks started from now on. Tasks already running keep
+    /// the configuration they started with.
+    pub fn reload_config(&self, config: Config) -> Result<(), SchedulerError> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                Span::current(),
+                SchedulerClientMsg::ReloadConfig(Box::new(config), reply),
+            ))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)?;
+
+        receive
+            .recv_timeout(Duration::from_secs(5))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)
+    }
+
+    /// Request the scheduler's running totals and current queue depths.
+    pub fn request_performance_counters(&self) -> Result<SchedulerCounters, SchedulerError> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestPerformanceCounters(reply),
+            ))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)?;
+
+        receive
+            .recv_timeout(Duration::from_secs(5))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)
+    }
+
     pub fn request_verbs(
         &self,
         player: &Obj,
@@ -263,12 +322,15 @@ impl SchedulerClient {
     ) -> Result<VerbDefs, SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::RequestVerbs {
-                player: player.clone(),
-                perms: perms.clone(),
-                obj: obj.clone(),
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestVerbs {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -285,13 +347,16 @@ impl SchedulerClient {
     ) -> Result<(VerbDef, Vec<String>), SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::RequestVerbCode {
-                player: player.clone(),
-                perms: perms.clone(),
-                obj: obj.clone(),
-                verb,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestVerbCode {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    verb,
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -307,12 +372,15 @@ impl SchedulerClient {
     ) -> Result<Vec<(PropDef, PropPerms)>, SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::RequestProperties {
-                player: player.clone(),
-                perms: perms.clone(),
-                obj: obj.clone(),
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestProperties {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -329,13 +397,41 @@ impl SchedulerClient {
     ) -> Result<(PropDef, PropPerms, Var), SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::RequestProperty {
-                player: player.clone(),
-                perms: perms.clone(),
-                obj: obj.clone(),
-                property,
-                reply,
-            })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestProperty {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    property,
+                    reply,
+                },
+            ))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)?;
+
+        receive
+            .recv_timeout(Duration::from_secs(5))
+            .map_err(|_| SchedulerError::SchedulerNotResponding)?
+    }
+
+    /// The object `obj` matches, and the generation it was last changed at.
+    pub fn request_object_generation(
+        &self,
+        player: &Obj,
+        perms: &Obj,
+        obj: &ObjectRef,
+    ) -> Result<(Obj, u64), SchedulerError> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                Span::current(),
+                SchedulerClientMsg::RequestObjectGeneration {
+                    player: player.clone(),
+                    perms: perms.clone(),
+                    obj: obj.clone(),
+                    reply,
+                },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -346,7 +442,10 @@ impl SchedulerClient {
     pub fn resolve_object(&self, player: Obj, obj: ObjectRef) -> Result<Var, SchedulerError> {
         let (reply, receive) = oneshot::channel();
         self.scheduler_sender
-            .send(SchedulerClientMsg::ResolveObject { player, obj, reply })
+            .send((
+                Span::current(),
+                SchedulerClientMsg::ResolveObject { player, obj, reply },
+            ))
             .map_err(|_| SchedulerError::SchedulerNotResponding)?;
 
         receive
@@ -380,7 +479,7 @@ pub enum SchedulerClientMsg {
         player: Obj,
         input_request_id: Uuid,
         input: String,
-        reply: oneshot::Sender<Result<(), SchedulerError>>,
+        reply: oneshot::Sender<Result<bool, SchedulerError>>,
     },
     /// Submit an out-of-band task to be executed
     SubmitOobTask {
@@ -446,12 +545,23 @@ pub enum SchedulerClientMsg {
         property: Symbol,
         reply: oneshot::Sender<Result<(PropDef, PropPerms, Var), SchedulerError>>,
     },
+    /// Request the generation an object was last changed at.
+    RequestObjectGeneration {
+        player: Obj,
+        perms: Obj,
+        obj: ObjectRef,
+        reply: oneshot::Sender<Result<(Obj, u64), SchedulerError>>,
+    },
     /// Resolve an ObjectRef into a Var
     ResolveObject {
         player: Obj,
         obj: ObjectRef,
         reply: oneshot::Sender<Result<Var, SchedulerError>>,
     },
+    /// Request the scheduler's performance counters.
+    RequestPerformanceCounters(oneshot::Sender<SchedulerCounters>),
+    /// Replace the scheduler's configuration.
+    ReloadConfig(Box<Config>, oneshot::Sender<()>),
     /// Submit a request to checkpoint the database.
     Checkpoint(oneshot::Sender<Result<(), SchedulerError>>),
     /// Submit a (non-task specific) request to shutdown the scheduler
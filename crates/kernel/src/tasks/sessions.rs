@@ -17,7 +17,7 @@ use thiserror::Error;
 use uuid::Uuid;
 
 use moor_values::tasks::NarrativeEvent;
-use moor_values::{Error, Obj, SYSTEM_OBJECT};
+use moor_values::{v_empty_map, Error, List, Obj, Symbol, Var, SYSTEM_OBJECT};
 
 /// The interface for managing the user I/O connection side of state, exposed by the scheduler to
 /// the VM during execution and by the host server to the scheduler.
@@ -87,6 +87,26 @@ pub trait Session: Send + Sync {
     /// LambdaMOO cores tend to expect this to be a resolved DNS hostname.
     fn connection_name(&self, player: Obj) -> Result<String, SessionError>;
 
+    /// Look up the hostname of the player's connection now, rather than using whatever name has
+    /// been found for it so far. With `rewrite`, `connection_name` reports the result from then on.
+    fn connection_name_lookup(&self, player: Obj, rewrite: bool) -> Result<String, SessionError>;
+
+    /// The attributes the host has reported for the player's connection, as a map, e.g. the
+    /// client capabilities (`"ansi"`, `"xterm256"`, `"truecolor"`, `"mxp"`) it negotiated.
+    fn connection_attributes(&self, player: Obj) -> Result<Var, SessionError>;
+
+    /// Set one of the options of the player's connection (`set_connection_option()`), e.g. how
+    /// much output is held for it while its client isn't reading.
+    fn set_connection_option(
+        &self,
+        player: Obj,
+        option: Symbol,
+        value: Var,
+    ) -> Result<(), SessionError>;
+
+    /// The options of the player's connection, and their values.
+    fn connection_options(&self, player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError>;
+
     /// Disconnect the given player's connection.
     fn disconnect(&self, player: Obj) -> Result<(), SessionError>;
 
@@ -100,6 +120,27 @@ pub trait Session: Send + Sync {
     fn idle_seconds(&self, player: Obj) -> Result<f64, SessionError>;
 }
 
+/// The kinds of committed change a webhook can be sent.
+pub const WEBHOOK_EVENTS: &[&str] = &[
+    "object_created",
+    "object_recycled",
+    "property_changed",
+    "verb_programmed",
+];
+
+/// Which committed changes are sent to a webhook. Each part left empty matches anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WebhookFilter {
+    /// Only changes to this object.
+    pub object: Option<Obj>,
+    /// Only the programming of a verb with this name.
+    pub verb: Option<Symbol>,
+    /// Only changes to the property with this name.
+    pub property: Option<Symbol>,
+    /// Only these kinds of change, from `WEBHOOK_EVENTS`.
+    pub events: Vec<String>,
+}
+
 /// A handle back to the controlling process (e.g. RpcServer) for handling system level events,
 /// such as shutdown, listen(), etc.
 ///
@@ -121,6 +162,49 @@ pub trait SystemControl: Send + Sync {
 
     /// Return the set of listeners, their type, and the port they are listening on.
     fn listeners(&self) -> Result<Vec<(Obj, String, u16, bool)>, Error>;
+
+    /// Refuse logins from `site` (an address, or a prefix of one ending in `*`) from now on.
+    /// Returns false if it was already banned.
+    fn ban_site(&self, site: &str) -> Result<bool, Error>;
+
+    /// Lift a ban added by `ban_site`. Returns false if there wasn't one.
+    fn unban_site(&self, site: &str) -> Result<bool, Error>;
+
+    /// Return the sites logins are refused from.
+    fn banned_sites(&self) -> Result<Vec<String>, Error>;
+
+    /// Invalidate the credentials `player`'s clients have been issued so far, so they have to log
+    /// in again.
+    fn revoke_auth_tokens(&self, player: &Obj) -> Result<(), Error>;
+
+    /// Move the connections `old_player` has now over to `new_player`, issuing them credentials
+    /// for it. E_INVARG if `old_player` has no connections.
+    fn switch_player(&self, old_player: &Obj, new_player: &Obj) -> Result<(), Error>;
+
+    /// POST committed changes matching `filter` to `url` from now on, signed with `secret` if
+    /// there is one. Returns the new webhook's id.
+    fn add_webhook(
+        &self,
+        url: &str,
+        secret: Option<String>,
+        filter: WebhookFilter,
+    ) -> Result<u64, Error>;
+
+    /// Remove the webhook added with this id. Returns false if there wasn't one.
+    fn remove_webhook(&self, id: u64) -> Result<bool, Error>;
+
+    /// Return the webhooks, as (id, url, filter).
+    fn webhooks(&self) -> Result<Vec<(u64, String, WebhookFilter)>, Error>;
+
+    /// Send a message for `target:verb(@args)` to the federated world named `world`. Delivery
+    /// isn't confirmed.
+    fn federation_send(
+        &self,
+        world: &str,
+        target: &Obj,
+        verb: Symbol,
+        args: List,
+    ) -> Result<(), Error>;
 }
 
 /// A factory for creating background sessions, usually on task resumption on server restart.
@@ -141,6 +225,10 @@ pub enum SessionError {
     CommitError(String),
     #[error("Invalid authorization token")]
     InvalidToken,
+    #[error("Could not look up the name of the connection for {0}")]
+    NameLookupFailed(Obj),
+    #[error("Invalid connection option: {0}")]
+    InvalidConnectionOption(String),
 }
 
 /// A simple no-op implementation of the Sessions trait, for use in unit tests.
@@ -192,6 +280,27 @@ impl Session for NoopClientSession {
     fn connection_name(&self, player: Obj) -> Result<String, SessionError> {
         Ok(format!("player-{}", player))
     }
+
+    fn connection_name_lookup(&self, player: Obj, _rewrite: bool) -> Result<String, SessionError> {
+        Ok(format!("player-{}", player))
+    }
+
+    fn connection_attributes(&self, _player: Obj) -> Result<Var, SessionError> {
+        Ok(v_empty_map())
+    }
+
+    fn set_connection_option(
+        &self,
+        _player: Obj,
+        option: Symbol,
+        _value: Var,
+    ) -> Result<(), SessionError> {
+        Err(SessionError::InvalidConnectionOption(option.to_string()))
+    }
+
+    fn connection_options(&self, _player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError> {
+        Ok(vec![])
+    }
     fn disconnect(&self, _player: Obj) -> Result<(), SessionError> {
         Ok(())
     }
@@ -233,6 +342,54 @@ impl SystemControl for NoopSystemControl {
     fn listeners(&self) -> Result<Vec<(Obj, String, u16, bool)>, Error> {
         Ok(vec![])
     }
+
+    fn ban_site(&self, _site: &str) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn unban_site(&self, _site: &str) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn banned_sites(&self) -> Result<Vec<String>, Error> {
+        Ok(vec![])
+    }
+
+    fn revoke_auth_tokens(&self, _player: &Obj) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn switch_player(&self, _old_player: &Obj, _new_player: &Obj) -> Result<(), Error> {
+        // No one is connected.
+        Err(Error::E_INVARG)
+    }
+
+    fn add_webhook(
+        &self,
+        _url: &str,
+        _secret: Option<String>,
+        _filter: WebhookFilter,
+    ) -> Result<u64, Error> {
+        Ok(1)
+    }
+
+    fn remove_webhook(&self, _id: u64) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn webhooks(&self) -> Result<Vec<(u64, String, WebhookFilter)>, Error> {
+        Ok(vec![])
+    }
+
+    fn federation_send(
+        &self,
+        _world: &str,
+        _target: &Obj,
+        _verb: Symbol,
+        _args: List,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 /// A 'mock' client connection which collects output in a vector of strings that tests can use to
 /// verify output.
@@ -331,6 +488,27 @@ impl Session for MockClientSession {
         Ok(format!("player-{}", player))
     }
 
+    fn connection_name_lookup(&self, player: Obj, _rewrite: bool) -> Result<String, SessionError> {
+        Ok(format!("player-{}", player))
+    }
+
+    fn connection_attributes(&self, _player: Obj) -> Result<Var, SessionError> {
+        Ok(v_empty_map())
+    }
+
+    fn set_connection_option(
+        &self,
+        _player: Obj,
+        option: Symbol,
+        _value: Var,
+    ) -> Result<(), SessionError> {
+        Err(SessionError::InvalidConnectionOption(option.to_string()))
+    }
+
+    fn connection_options(&self, _player: Obj) -> Result<Vec<(Symbol, Var)>, SessionError> {
+        Ok(vec![])
+    }
+
     fn disconnect(&self, _player: Obj) -> Result<(), SessionError> {
         let mut system = self.system.write().unwrap();
         system.push(String::from("disconnect"));
@@ -378,4 +556,65 @@ impl SystemControl for MockClientSession {
     fn listeners(&self) -> Result<Vec<(Obj, String, u16, bool)>, Error> {
         Ok(vec![(SYSTEM_OBJECT, String::from("tcp"), 8888, true)])
     }
+
+    fn ban_site(&self, site: &str) -> Result<bool, Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("ban_site: {}", site));
+        Ok(true)
+    }
+
+    fn unban_site(&self, site: &str) -> Result<bool, Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("unban_site: {}", site));
+        Ok(true)
+    }
+
+    fn banned_sites(&self) -> Result<Vec<String>, Error> {
+        Ok(vec![])
+    }
+
+    fn revoke_auth_tokens(&self, player: &Obj) -> Result<(), Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("revoke_auth_tokens: {}", player));
+        Ok(())
+    }
+
+    fn switch_player(&self, old_player: &Obj, new_player: &Obj) -> Result<(), Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("switch_player: {} {}", old_player, new_player));
+        Ok(())
+    }
+
+    fn add_webhook(
+        &self,
+        url: &str,
+        _secret: Option<String>,
+        _filter: WebhookFilter,
+    ) -> Result<u64, Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("add_webhook: {}", url));
+        Ok(1)
+    }
+
+    fn remove_webhook(&self, id: u64) -> Result<bool, Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("remove_webhook: {}", id));
+        Ok(true)
+    }
+
+    fn webhooks(&self) -> Result<Vec<(u64, String, WebhookFilter)>, Error> {
+        Ok(vec![])
+    }
+
+    fn federation_send(
+        &self,
+        world: &str,
+        target: &Obj,
+        verb: Symbol,
+        _args: List,
+    ) -> Result<(), Error> {
+        let mut system = self.system.write().unwrap();
+        system.push(format!("federation_send: {} {}:{}", world, target, verb));
+        Ok(())
+    }
 }
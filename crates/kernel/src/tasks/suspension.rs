@@ -22,8 +22,9 @@ use bincode::{BorrowDecode, Decode, Encode};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use moor_values::Obj;
+use moor_values::{v_string, Obj, Var};
 
+use crate::tasks::input_lines::InputLines;
 use crate::tasks::sessions::{NoopClientSession, Session, SessionFactory};
 use crate::tasks::task::Task;
 use crate::tasks::{TaskDescription, TaskResult, TasksDb};
@@ -38,6 +39,14 @@ pub struct SuspendedTask {
     pub result_sender: Option<oneshot::Sender<Result<TaskResult, SchedulerError>>>,
 }
 
+/// What became of a line of input given to a suspended task.
+pub enum InputTaken {
+    /// The task has what it was waiting for, and should be resumed with the given value.
+    Resume(SuspendedTask, Var),
+    /// The line was added to those being gathered for a `read_lines()`, which wants more.
+    MoreWanted,
+}
+
 /// Possible conditions in which a suspended task can wake from suspension.
 #[derive(Debug)]
 pub enum WakeCondition {
@@ -47,6 +56,8 @@ pub enum WakeCondition {
     Time(Instant),
     /// This task will wake up when the given input request is fulfilled.
     Input(Uuid),
+    /// This task will wake up when the given input request has sent all the lines it wants.
+    InputLines(Uuid, InputLines),
 }
 
 #[repr(u8)]
@@ -55,6 +66,7 @@ pub enum WakeConditionType {
     Never = 0,
     Time = 1,
     Input = 2,
+    InputLines = 3,
 }
 
 impl WakeCondition {
@@ -63,6 +75,7 @@ impl WakeCondition {
             WakeCondition::Never => WakeConditionType::Never,
             WakeCondition::Time(_) => WakeConditionType::Time,
             WakeCondition::Input(_) => WakeConditionType::Input,
+            WakeCondition::InputLines(..) => WakeConditionType::InputLines,
         }
     }
 }
@@ -171,23 +184,24 @@ impl SuspensionQ {
         tasks
     }
 
-    /// Pull a task from the suspended list that is waiting for input, for the given player.
-    pub(crate) fn pull_task_for_input(
+    /// Give a line of input to the task suspended waiting for it, for the given player.
+    pub(crate) fn take_input(
         &mut self,
         input_request_id: Uuid,
         player: &Obj,
-    ) -> Option<SuspendedTask> {
-        let (task_id, perms) = self.tasks.iter().find_map(|(task_id, sr)| {
-            if let WakeCondition::Input(request_id) = &sr.wake_condition {
-                if *request_id == input_request_id {
-                    Some((*task_id, sr.task.perms.clone()))
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })?;
+        input: String,
+    ) -> Option<InputTaken> {
+        let (task_id, perms) =
+            self.tasks
+                .iter()
+                .find_map(|(task_id, sr)| match &sr.wake_condition {
+                    WakeCondition::Input(request_id) | WakeCondition::InputLines(request_id, _)
+                        if *request_id == input_request_id =>
+                    {
+                        Some((*task_id, sr.task.perms.clone()))
+                    }
+                    _ => None,
+                })?;
 
         // If the player doesn't match, we'll pretend we didn't even see it.
         if perms.ne(player) {
@@ -200,8 +214,19 @@ impl SuspensionQ {
             return None;
         };
 
+        // Lines for a `read_lines()` are gathered here until the last of them. They aren't saved
+        // to the tasks DB as they come in: a task waiting on input can't get any more once its
+        // connection is gone, which a restart would see to.
+        let sr = self.tasks.get_mut(&task_id).expect("Corrupt task list");
+        let value = match &mut sr.wake_condition {
+            WakeCondition::InputLines(_, lines) => match lines.push(input) {
+                Some(value) => value,
+                None => return Some(InputTaken::MoreWanted),
+            },
+            _ => v_string(input),
+        };
         let sr = self.remove_task(task_id).expect("Corrupt task list");
-        Some(sr)
+        Some(InputTaken::Resume(sr, value))
     }
 
     /// Get a nice friendly list of all tasks in suspension state.
@@ -230,18 +255,45 @@ impl SuspensionQ {
         tasks
     }
 
+    /// The number of tasks in suspension, of any kind.
+    pub(crate) fn num_tasks(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// How many suspended tasks are owned by `who`, and how many are running on their behalf.
+    pub(crate) fn task_counts(&self, who: &Obj) -> (usize, usize) {
+        self.tasks.values().fold((0, 0), |(owned, on_behalf), sr| {
+            (
+                owned + usize::from(sr.task.perms == *who),
+                on_behalf + usize::from(sr.task.player == *who),
+            )
+        })
+    }
+
     /// Check if the task is suspended, and if so, return its permissions.
     /// If `filter_input` is true, filter out WaitingInput tasks.
     pub(crate) fn perms_check(&self, task_id: TaskId, filter_input: bool) -> Option<Obj> {
         let sr = self.tasks.get(&task_id)?;
         if filter_input {
-            if let WakeCondition::Input(_) = sr.wake_condition {
+            if let WakeCondition::Input(_) | WakeCondition::InputLines(..) = sr.wake_condition {
                 return None;
             }
         }
         Some(sr.task.perms.clone())
     }
 
+    /// Set or clear the debugger of a suspended task. Returns false if there's no such task.
+    pub(crate) fn set_debugger(&mut self, task_id: TaskId, debugger: Option<Obj>) -> bool {
+        let Some(sr) = self.tasks.get_mut(&task_id) else {
+            return false;
+        };
+        sr.task.vm_host.set_debugger(debugger);
+        if let Err(e) = self.tasks_database.save_task(sr) {
+            error!(?e, "Could not save suspended task");
+        }
+        true
+    }
+
     /// Remove all non-background tasks for the given player.
     pub(crate) fn prune_foreground_tasks(&mut self, player: &Obj) {
         let to_remove = self
@@ -318,6 +370,10 @@ impl Encode for WakeCondition {
                 time_to_wake.as_micros().encode(encoder)
             }
             WakeCondition::Input(uuid) => uuid.as_u128().encode(encoder),
+            WakeCondition::InputLines(uuid, lines) => {
+                uuid.as_u128().encode(encoder)?;
+                lines.encode(encoder)
+            }
         }
     }
 }
@@ -336,6 +392,11 @@ impl Decode for WakeCondition {
                 let uuid = Uuid::from_u128(Decode::decode(decoder)?);
                 Ok(WakeCondition::Input(uuid))
             }
+            WakeConditionType::InputLines => {
+                let uuid = Uuid::from_u128(Decode::decode(decoder)?);
+                let lines = InputLines::decode(decoder)?;
+                Ok(WakeCondition::InputLines(uuid, lines))
+            }
         }
     }
 }
@@ -354,6 +415,11 @@ impl<'de> BorrowDecode<'de> for WakeCondition {
                 let uuid = Uuid::from_u128(Decode::decode(decoder)?);
                 Ok(WakeCondition::Input(uuid))
             }
+            WakeConditionType::InputLines => {
+                let uuid = Uuid::from_u128(Decode::decode(decoder)?);
+                let lines = InputLines::decode(decoder)?;
+                Ok(WakeCondition::InputLines(uuid, lines))
+            }
         }
     }
 }
@@ -33,31 +33,45 @@ use bincode::{BorrowDecode, Decode, Encode};
 use bytes::Bytes;
 use crossbeam_channel::Sender;
 use lazy_static::lazy_static;
-use tracing::{error, trace, warn};
+use tracing::{debug_span, error, instrument, trace, warn};
 
 use moor_values::model::{CommitResult, VerbDef, WorldState, WorldStateError};
 use moor_values::tasks::CommandError;
 use moor_values::tasks::CommandError::PermissionDenied;
+use moor_values::tasks::NarrativeEvent;
 use moor_values::tasks::TaskId;
+use moor_values::tasks::{AbortLimitReason, Exception};
 use moor_values::util::parse_into_words;
-use moor_values::{v_int, v_str, List};
+use moor_values::{v_err, v_int, v_list, v_str, v_string, List, Var};
 use moor_values::{v_obj, Obj};
 use moor_values::{Symbol, Variant};
 use moor_values::{NOTHING, SYSTEM_OBJECT};
 
 use crate::builtins::BuiltinRegistry;
 use crate::config::{Config, FeaturesConfig};
+use crate::tasks::load::LoadMonitor;
+use crate::tasks::recording::TaskRecording;
 use crate::tasks::sessions::Session;
 use crate::tasks::task_scheduler_client::{TaskControlMsg, TaskSchedulerClient};
 use crate::tasks::vm_host::VmHost;
 use crate::tasks::{ServerOptions, TaskStart, VerbCall};
-use crate::vm::VMHostResponse;
+use crate::vm::{ReplayLog, VMHostResponse};
 use moor_values::matching::command_parse::{parse_command, ParseCommandError, ParsedCommand};
 use moor_values::matching::match_env::MatchEnvironmentParseMatcher;
 use moor_values::matching::ws_match_env::WsMatchEnv;
 
 lazy_static! {
     static ref HUH_SYM: Symbol = Symbol::mk("huh");
+    static ref HANDLE_UNCAUGHT_ERROR_SYM: Symbol = Symbol::mk("handle_uncaught_error");
+    static ref HANDLE_TASK_TIMEOUT_SYM: Symbol = Symbol::mk("handle_task_timeout");
+}
+
+/// How a task died, kept while the core's `$handle_uncaught_error` or `$handle_task_timeout` runs,
+/// to be reported to the scheduler once it's done.
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) enum TaskFailure {
+    Exception(Exception),
+    Limit(AbortLimitReason, Vec<Var>),
 }
 
 #[derive(Debug)]
@@ -74,6 +88,8 @@ pub struct Task {
     pub(crate) vm_host: VmHost,
     /// True if the task should die.
     pub(crate) kill_switch: Arc<AtomicBool>,
+    /// Set while the task is running the core's handler for how it died.
+    pub(crate) failure: Option<TaskFailure>,
 }
 
 impl Task {
@@ -107,6 +123,7 @@ impl Task {
             vm_host,
             perms,
             kill_switch,
+            failure: None,
         }
     }
 
@@ -118,6 +135,22 @@ impl Task {
         builtin_registry: Arc<BuiltinRegistry>,
         config: Arc<Config>,
     ) {
+        // Start recording nondeterministic inputs, unless we're already recording (we're being
+        // resumed) or have been handed a log to replay.
+        if config.features_config.record_replay
+            && matches!(task.vm_host.replay_log(), ReplayLog::Off)
+        {
+            task.vm_host.set_replay_log(ReplayLog::Recording(vec![]));
+        }
+
+        if let Some(sink) = task
+            .vm_host
+            .permission_audit()
+            .and_then(|audit| audit.sink_for(task.task_id))
+        {
+            world_state.audit_permissions(sink);
+        }
+
         while task.vm_host.is_running() {
             // Check kill switch.
             if task.kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
@@ -156,14 +189,24 @@ impl Task {
         config: FeaturesConfig,
     ) -> Option<(Self, Box<dyn WorldState>)> {
         // Call the VM
-        let vm_exec_result = self.vm_host.exec_interpreter(
-            self.task_id,
-            world_state.as_mut(),
-            task_scheduler_client.clone(),
-            session,
-            builtin_registry,
-            config,
-        );
+        let vm_exec_result = {
+            let _span = debug_span!(
+                "vm_exec",
+                task_id = self.task_id,
+                verb = %self.vm_host.verb_name(),
+                definer = %self.vm_host.verb_definer(),
+                line = self.vm_host.line_number(),
+            )
+            .entered();
+            self.vm_host.exec_interpreter(
+                self.task_id,
+                world_state.as_mut(),
+                task_scheduler_client.clone(),
+                session,
+                builtin_registry,
+                config.clone(),
+            )
+        };
 
         // Having done that, what should we now do?
         match vm_exec_result {
@@ -185,9 +228,9 @@ impl Task {
                 trace!(task_id = self.task_id, delay = ?delay, "Task suspend");
 
                 // VMHost is now suspended for execution, and we'll be waiting for a Resume
-                let commit_result = world_state
-                    .commit()
-                    .expect("Could not commit world state before suspend");
+                let commit_result =
+                    commit_world_state(self.task_id, self.vm_host.load_monitor(), world_state)
+                        .expect("Could not commit world state before suspend");
                 if let CommitResult::ConflictRetry = commit_result {
                     warn!("Conflict during commit before suspend");
                     task_scheduler_client.conflict_retry(self);
@@ -207,15 +250,15 @@ impl Task {
                 task_scheduler_client.suspend(resume_time, self);
                 None
             }
-            VMHostResponse::SuspendNeedInput => {
+            VMHostResponse::SuspendNeedInput(request) => {
                 trace!(task_id = self.task_id, "Task suspend need input");
 
                 // VMHost is now suspended for input, and we'll be waiting for a ResumeReceiveInput
 
                 // Attempt commit... See comments/notes on Suspend above.
-                let commit_result = world_state
-                    .commit()
-                    .expect("Could not commit world state before suspend");
+                let commit_result =
+                    commit_world_state(self.task_id, self.vm_host.load_monitor(), world_state)
+                        .expect("Could not commit world state before suspend");
                 if let CommitResult::ConflictRetry = commit_result {
                     warn!("Conflict during commit before suspend");
                     task_scheduler_client.conflict_retry(self);
@@ -226,7 +269,28 @@ impl Task {
                 self.vm_host.stop();
 
                 // Consume us, passing back to the scheduler that we're waiting for input.
-                task_scheduler_client.request_input(self);
+                task_scheduler_client.request_input(request, self);
+                None
+            }
+            VMHostResponse::DebugStep { debugger, summary } => {
+                trace!(task_id = self.task_id, ?debugger, "Task debug step");
+
+                // Report where we are to the debugging player, then park ourselves indefinitely
+                // exactly as `suspend()` would, until stepped with `resume()` or killed.
+                let event = NarrativeEvent::notify(self.vm_host.this(), v_string(summary), None);
+                task_scheduler_client.notify(debugger, event);
+
+                let commit_result =
+                    commit_world_state(self.task_id, self.vm_host.load_monitor(), world_state)
+                        .expect("Could not commit world state before suspend");
+                if let CommitResult::ConflictRetry = commit_result {
+                    warn!("Conflict during commit before suspend");
+                    task_scheduler_client.conflict_retry(self);
+                    return None;
+                }
+
+                self.vm_host.stop();
+                task_scheduler_client.suspend(None, self);
                 None
             }
             VMHostResponse::ContinueOk => Some((self, world_state)),
@@ -234,6 +298,17 @@ impl Task {
             VMHostResponse::CompleteSuccess(result) => {
                 trace!(task_id = self.task_id, result = ?result, "Task complete, success");
 
+                // If that was the core's handler for how the task died, it's the death we report,
+                // with nothing more printed if the handler returned true.
+                if let Some(failure) = self.failure.take() {
+                    return self.finish_failure(
+                        failure,
+                        result.is_true(),
+                        task_scheduler_client,
+                        world_state,
+                    );
+                }
+
                 // Special case: in case of return from $do_command @ top-level, we need to look at the results:
                 //      non-true value? => parse_command and restart (in same transaction)
                 //      true value? => commit and return success.
@@ -251,17 +326,26 @@ impl Task {
                             player: player.clone(),
                             command: command.clone(),
                         });
+                        // What's recorded from here on is what replaying that start will want.
+                        if self.vm_host.replay_log().recorded().is_some() {
+                            self.vm_host.set_replay_log(ReplayLog::Recording(vec![]));
+                        }
 
-                        if let Err(e) =
-                            self.setup_start_parse_command(player, &command, world_state.as_mut())
-                        {
+                        if let Err(e) = self.setup_start_parse_command(
+                            player,
+                            &command,
+                            world_state.as_mut(),
+                            &config,
+                        ) {
                             task_scheduler_client.command_error(e);
                         }
                         return Some((self, world_state));
                     }
                 }
 
-                let CommitResult::Success = world_state.commit().expect("Could not attempt commit")
+                let CommitResult::Success =
+                    commit_world_state(self.task_id, self.vm_host.load_monitor(), world_state)
+                        .expect("Could not attempt commit")
                 else {
                     warn!("Conflict during commit before complete, asking scheduler to retry task");
                     task_scheduler_client.conflict_retry(self);
@@ -286,49 +370,188 @@ impl Task {
                 None
             }
             VMHostResponse::CompleteException(exception) => {
-                // Commands that end in exceptions are still expected to be committed, to
-                // conform with MOO's expectations.
-                // However a conflict-retry here is maybe not the best idea here, I think.
-                // So we'll just panic the task (abort) if we can't commit for now.
-                // TODO: Should tasks that throw exception always commit?
-                //   Right now to preserve MOO semantics, we do.
-                //   We may revisit this later and add a user-selectable mode for this, and
-                //   evaluate this behaviour generally.
-
-                let CommitResult::Success = world_state.commit().expect("Could not attempt commit")
-                else {
-                    warn!("Conflict during commit before complete, asking scheduler to retry task ({})", self.task_id);
-                    task_scheduler_client.conflict_retry(self);
-                    return None;
-                };
-
-                warn!(task_id = self.task_id, "Task exception");
-                self.vm_host.stop();
+                if let Some(failure) = self.failure.take() {
+                    warn!(
+                        task_id = self.task_id,
+                        ?exception,
+                        "$handle_uncaught_error or $handle_task_timeout raised an error"
+                    );
+                    return self.finish_failure(failure, false, task_scheduler_client, world_state);
+                }
 
-                task_scheduler_client.exception(exception);
-                None
+                // Give the core a chance to deal with the error first, LambdaMOO-style:
+                //   $handle_uncaught_error(code, msg, value, traceback, formatted)
+                let args = [
+                    v_err(exception.code),
+                    v_string(exception.msg.clone()),
+                    exception.value.clone(),
+                    v_list(&exception.stack),
+                    v_list(&exception.backtrace),
+                ];
+                if self.start_failure_handler(
+                    *HANDLE_UNCAUGHT_ERROR_SYM,
+                    &args,
+                    world_state.as_ref(),
+                ) {
+                    self.failure = Some(TaskFailure::Exception(exception));
+                    return Some((self, world_state));
+                }
+                self.finish_failure(
+                    TaskFailure::Exception(exception),
+                    false,
+                    task_scheduler_client,
+                    world_state,
+                )
             }
             VMHostResponse::AbortLimit(reason) => {
                 warn!(task_id = self.task_id, "Task abort limit reached");
 
+                if let Some(failure) = self.failure.take() {
+                    warn!(
+                        task_id = self.task_id,
+                        ?reason,
+                        "$handle_uncaught_error or $handle_task_timeout ran out of time"
+                    );
+                    return self.finish_failure(failure, false, task_scheduler_client, world_state);
+                }
+
+                // Likewise for running out of ticks or seconds:
+                //   $handle_task_timeout(resource, traceback, formatted)
+                let resource = match reason {
+                    AbortLimitReason::Ticks(_) => "ticks",
+                    AbortLimitReason::Time(_) => "seconds",
+                };
+                let (stack, backtrace) = self
+                    .vm_host
+                    .unwind_for_abort(&format!("Task ran out of {resource}"));
+                let args = [v_str(resource), v_list(&stack), v_list(&backtrace)];
+                if self.start_failure_handler(*HANDLE_TASK_TIMEOUT_SYM, &args, world_state.as_ref())
+                {
+                    self.failure = Some(TaskFailure::Limit(reason, backtrace));
+                    return Some((self, world_state));
+                }
+                self.finish_failure(
+                    TaskFailure::Limit(reason, backtrace),
+                    false,
+                    task_scheduler_client,
+                    world_state,
+                )
+            }
+            VMHostResponse::RollbackRetry => {
+                warn!(task_id = self.task_id, "Task rollback requested, retrying");
+
                 self.vm_host.stop();
                 world_state
                     .rollback()
                     .expect("Could not rollback world state");
-                task_scheduler_client.abort_limits_reached(reason);
+                task_scheduler_client.conflict_retry(self);
                 None
             }
-            VMHostResponse::RollbackRetry => {
-                warn!(task_id = self.task_id, "Task rollback requested, retrying");
+        }
+    }
+
+    /// Start the core's handler `#0:<verb>` for how the task died, if there is one, in place of
+    /// the task's own (now empty) stack. It gets a fresh allotment of ticks and seconds.
+    fn start_failure_handler(
+        &mut self,
+        verb: Symbol,
+        args: &[Var],
+        world_state: &dyn WorldState,
+    ) -> bool {
+        let verb_info = match world_state.find_method_verb_on(&self.perms, &SYSTEM_OBJECT, verb) {
+            Ok(verb_info) => verb_info,
+            Err(WorldStateError::VerbNotFound(_, _)) => return false,
+            Err(e) => {
+                warn!(task_id = self.task_id, ?verb, error = ?e, "Could not look up failure handler");
+                return false;
+            }
+        };
+        let verb_call = VerbCall {
+            verb_name: verb,
+            location: v_obj(SYSTEM_OBJECT),
+            this: v_obj(SYSTEM_OBJECT),
+            player: self.player.clone(),
+            args: List::mk_list(args),
+            argstr: String::new(),
+            caller: v_obj(NOTHING),
+        };
+        self.vm_host
+            .start_call_method_verb(self.task_id, &self.perms, verb_info, verb_call);
+        true
+    }
+
+    /// Finish a task which died, with an uncaught error or by running out of ticks or seconds,
+    /// letting the scheduler know. `handled` is whether the core's handler dealt with it, in which
+    /// case the scheduler prints nothing.
+    fn finish_failure(
+        mut self,
+        failure: TaskFailure,
+        handled: bool,
+        task_scheduler_client: &TaskSchedulerClient,
+        world_state: Box<dyn WorldState>,
+    ) -> Option<(Self, Box<dyn WorldState>)> {
+        match failure {
+            TaskFailure::Exception(exception) => {
+                // Commands that end in exceptions are still expected to be committed, to
+                // conform with MOO's expectations.
+                // However a conflict-retry here is maybe not the best idea here, I think.
+                // So we'll just panic the task (abort) if we can't commit for now.
+                // TODO: Should tasks that throw exception always commit?
+                //   Right now to preserve MOO semantics, we do.
+                //   We may revisit this later and add a user-selectable mode for this, and
+                //   evaluate this behaviour generally.
+                // The exception is an atomic section which an error escaped from, though: the
+                // point of those is that their changes go in all together or not at all.
+                if self.vm_host.in_atomic_section() {
+                    warn!(
+                        task_id = self.task_id,
+                        "Task exception inside atomic section, rolling back"
+                    );
+                    world_state
+                        .rollback()
+                        .expect("Could not rollback world state transaction");
+                } else {
+                    let CommitResult::Success =
+                        commit_world_state(self.task_id, self.vm_host.load_monitor(), world_state)
+                            .expect("Could not attempt commit")
+                    else {
+                        warn!("Conflict during commit before complete, asking scheduler to retry task ({})", self.task_id);
+                        task_scheduler_client.conflict_retry(self);
+                        return None;
+                    };
+
+                    warn!(task_id = self.task_id, "Task exception");
+                }
+                if let Some(recorded) = self.vm_host.replay_log().recorded() {
+                    task_scheduler_client.recorded(TaskRecording {
+                        task_id: self.task_id,
+                        player: self.player.clone(),
+                        perms: self.perms.clone(),
+                        task_start: self.task_start.as_ref().clone(),
+                        inputs: recorded.to_vec(),
+                    });
+                }
+                self.vm_host.stop();
 
+                if handled {
+                    task_scheduler_client.exception_handled(exception);
+                } else {
+                    task_scheduler_client.exception(exception);
+                }
+            }
+            TaskFailure::Limit(reason, backtrace) => {
                 self.vm_host.stop();
                 world_state
                     .rollback()
                     .expect("Could not rollback world state");
-                task_scheduler_client.conflict_retry(self);
-                None
+                if handled {
+                    task_scheduler_client.abort_limits_handled(reason);
+                } else {
+                    task_scheduler_client.abort_limits_reached(reason, backtrace);
+                }
             }
         }
+        None
     }
 
     /// Set the task up to start executing, based on the task start configuration.
@@ -336,6 +559,7 @@ impl Task {
         &mut self,
         control_sender: &Sender<(TaskId, TaskControlMsg)>,
         world_state: &mut dyn WorldState,
+        config: &FeaturesConfig,
     ) -> bool {
         match self.task_start.clone().as_ref() {
             // We've been asked to start a command.
@@ -345,9 +569,13 @@ impl Task {
                 player,
                 command,
             } => {
-                if let Err(e) =
-                    self.start_command(handler_object, player, command.as_str(), world_state)
-                {
+                if let Err(e) = self.start_command(
+                    handler_object,
+                    player,
+                    command.as_str(),
+                    world_state,
+                    config,
+                ) {
                     control_sender
                         .send((self.task_id, TaskControlMsg::TaskCommandError(e)))
                         .expect("Could not send start response");
@@ -450,6 +678,7 @@ impl Task {
         player: &Obj,
         command: &str,
         world_state: &mut dyn WorldState,
+        config: &FeaturesConfig,
     ) -> Result<(), CommandError> {
         // Command execution is a multi-phase process:
         //   1. Lookup $do_command. If we have the verb, execute it.
@@ -463,15 +692,18 @@ impl Task {
         //  forms a multi-part process with continuation back from the VM along the whole
         //  chain, which complicates things significantly.
 
-        // First check to see if we have a $do_command at all, if yes, we're actually starting
-        // that verb with the command as an argument. If that then fails (non-true return code)
-        // we'll end up in the start_parse_command phase.
+        // First check to see if we have a do_command on the handler at all (and that it's enabled),
+        // if yes, we're actually starting that verb with the command as an argument. If that then
+        // fails (non-true return code) we'll end up in the start_parse_command phase.
+        if !config.do_command {
+            return self.setup_start_parse_command(player, command, world_state, config);
+        }
         let do_command =
-            world_state.find_method_verb_on(&self.perms, &SYSTEM_OBJECT, Symbol::mk("do_command"));
+            world_state.find_method_verb_on(&self.perms, handler_object, Symbol::mk("do_command"));
 
         match do_command {
             Err(WorldStateError::VerbNotFound(_, _)) => {
-                self.setup_start_parse_command(player, command, world_state)?;
+                self.setup_start_parse_command(player, command, world_state, config)?;
             }
             Ok(verb_info) => {
                 let arguments = parse_into_words(command);
@@ -509,6 +741,7 @@ impl Task {
         player: &Obj,
         command: &str,
         world_state: &mut dyn WorldState,
+        config: &FeaturesConfig,
     ) -> Result<(), CommandError> {
         // We need the player's location, and we'll just die if we can't get it.
         let player_location = match world_state.location_of(player, player) {
@@ -524,7 +757,8 @@ impl Task {
         };
 
         // Parse the command in the current environment.
-        let me = WsMatchEnv::new(world_state, player.clone());
+        let me = WsMatchEnv::new(world_state, player.clone())
+            .with_unicode_folding(config.unicode_matching);
         let matcher = MatchEnvironmentParseMatcher {
             env: me,
             player: player.clone(),
@@ -593,6 +827,22 @@ impl Task {
     }
 }
 
+/// Commit the task's transaction in a span of its own, so commit latency shows up in traces, and
+/// report how long it took to the scheduler's load monitor, if any.
+#[instrument(level = "debug", skip(load_monitor, world_state))]
+fn commit_world_state(
+    task_id: TaskId,
+    load_monitor: Option<&Arc<LoadMonitor>>,
+    world_state: Box<dyn WorldState>,
+) -> Result<CommitResult, WorldStateError> {
+    let started = Instant::now();
+    let result = world_state.commit();
+    if let Some(load_monitor) = load_monitor {
+        load_monitor.record_commit(started.elapsed());
+    }
+    result
+}
+
 #[allow(clippy::type_complexity)]
 fn find_verb_for_command(
     player: &Obj,
@@ -640,7 +890,8 @@ impl Encode for Task {
         self.player.encode(encoder)?;
         self.task_start.encode(encoder)?;
         self.vm_host.encode(encoder)?;
-        self.perms.encode(encoder)
+        self.perms.encode(encoder)?;
+        self.failure.encode(encoder)
     }
 }
 
@@ -651,6 +902,7 @@ impl Decode for Task {
         let task_start = Arc::decode(decoder)?;
         let vm_host = VmHost::decode(decoder)?;
         let perms = Obj::decode(decoder)?;
+        let failure = Option::decode(decoder)?;
         let kill_switch = Arc::new(AtomicBool::new(false));
         Ok(Task {
             task_id,
@@ -659,6 +911,7 @@ impl Decode for Task {
             vm_host,
             perms,
             kill_switch,
+            failure,
         })
     }
 }
@@ -670,6 +923,7 @@ impl<'de> BorrowDecode<'de> for Task {
         let task_start = Arc::borrow_decode(decoder)?;
         let vm_host = VmHost::borrow_decode(decoder)?;
         let perms = Obj::borrow_decode(decoder)?;
+        let failure = Option::borrow_decode(decoder)?;
         let kill_switch = Arc::new(AtomicBool::new(false));
         Ok(Task {
             task_id,
@@ -678,6 +932,7 @@ impl<'de> BorrowDecode<'de> for Task {
             vm_host,
             perms,
             kill_switch,
+            failure,
         })
     }
 }
@@ -696,20 +951,26 @@ mod tests {
     use moor_values::model::{
         ArgSpec, BinaryType, PrepSpec, VerbArgsSpec, VerbFlag, WorldState, WorldStateSource,
     };
-    use moor_values::tasks::{CommandError, Event, TaskId};
+    use moor_values::tasks::{CommandError, Event, TaskId, CONTENT_TYPE_PROMPT};
     use moor_values::util::BitEnum;
-    use moor_values::Error::E_DIV;
-    use moor_values::{v_int, v_str};
-    use moor_values::{v_obj, Symbol};
-    use moor_values::{AsByteBuffer, NOTHING, SYSTEM_OBJECT};
+    use moor_values::Error::{E_DIV, E_INVARG, E_PERM};
+    use moor_values::{v_float, v_int, v_list, v_none, v_str};
+    use moor_values::{v_obj, Symbol, Variant};
+    use moor_values::{AsByteBuffer, Obj, NOTHING, SYSTEM_OBJECT};
 
     use crate::builtins::BuiltinRegistry;
-    use crate::config::Config;
+    use crate::config::{Config, FeaturesConfig};
+    use crate::tasks::breakpoints::{Breakpoint, Breakpoints};
     use crate::tasks::sessions::NoopClientSession;
     use crate::tasks::task::Task;
     use crate::tasks::task_scheduler_client::{TaskControlMsg, TaskSchedulerClient};
-    use crate::tasks::{ServerOptions, TaskStart};
+    use crate::tasks::verb_wrappers::{VerbWrapper, VerbWrappers};
+    use crate::tasks::{
+        ServerOptions, TaskStart, DEFAULT_ARGON2_MEMORY_COST, DEFAULT_ARGON2_PARALLELISM,
+        DEFAULT_ARGON2_TIME_COST, DEFAULT_BCRYPT_COST,
+    };
     use crate::vm::activation::Frame;
+    use crate::vm::InputRequest;
 
     struct TestVerb {
         name: Symbol,
@@ -737,6 +998,14 @@ mod tests {
             fg_seconds: 5,
             fg_ticks: 50000,
             max_stack_depth: 5,
+            argon2_memory_cost: DEFAULT_ARGON2_MEMORY_COST,
+            argon2_time_cost: DEFAULT_ARGON2_TIME_COST,
+            argon2_parallelism: DEFAULT_ARGON2_PARALLELISM,
+            bcrypt_cost: DEFAULT_BCRYPT_COST,
+            default_timezone: None,
+            server_too_busy_msg: None,
+            queued_task_limit: None,
+            connection_task_limit: None,
         };
         let task_scheduler_client = TaskSchedulerClient::new(1, control_sender.clone());
         let mut task = Task::new(
@@ -784,7 +1053,7 @@ mod tests {
             )
             .unwrap();
         }
-        task.setup_task_start(&control_sender, tx.as_mut());
+        task.setup_task_start(&control_sender, tx.as_mut(), &FeaturesConfig::default());
 
         (
             kill_switch,
@@ -922,22 +1191,83 @@ mod tests {
         assert_eq!(result, v_int(123));
     }
 
-    /// Trigger a task-suspend-resume
+    // notify() with no_newline sends a prompt.
     #[test]
-    fn test_simple_run_suspend() {
-        let (_kill_switch, task, db, tx, task_scheduler_client, control_receiver) =
-            setup_test_env_eval("suspend(1); return 123;");
+    fn test_notify_prompt() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval(r#"notify(#0, "Name: ", 0, 1); return 123;"#);
 
         let session = Arc::new(NoopClientSession::new());
         Task::run_task_loop(
             task,
             &task_scheduler_client,
-            session.clone(),
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::Notify { event, .. } = msg else {
+            panic!("Expected Notify, got {:?}", msg);
+        };
+        assert_eq!(
+            event.event,
+            Event::Notify(v_str("Name: "), Some(Symbol::mk(CONTENT_TYPE_PROMPT)))
+        );
+    }
+
+    // notify() with no_newline can't be given a content type as well.
+    #[test]
+    fn test_notify_prompt_with_content_type() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval(r#"notify(#0, "Name: ", "text/markdown", 1); return 123;"#);
+
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
             tx,
             Arc::new(BuiltinRegistry::new()),
             Arc::new(Config::default()),
         );
 
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::TaskException(exception) = msg else {
+            panic!("Expected TaskException, got {:?}", msg);
+        };
+        assert_eq!(exception.code, E_INVARG);
+    }
+
+    /// Trigger a task-suspend-resume
+    #[test]
+    fn test_simple_run_suspend() {
+        let (_kill_switch, task, db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval("suspend(1); return 123;");
+
+        // This needs to run in a thread, because it blocks waiting on our fake scheduler to
+        // clear it against the task quotas.
+        let client = task_scheduler_client.clone();
+        let jh = std::thread::spawn(move || {
+            Task::run_task_loop(
+                task,
+                &client,
+                Arc::new(NoopClientSession::new()),
+                tx,
+                Arc::new(BuiltinRegistry::new()),
+                Arc::new(Config::default()),
+            );
+        });
+
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::CheckTaskQuota { forking, reply, .. } = msg else {
+            panic!("Expected CheckTaskQuota, got {:?}", msg);
+        };
+        assert!(!forking);
+        reply.send(Ok(())).unwrap();
+        jh.join().unwrap();
+
         // Scheduler should have received a TaskSuspend message.
         let (task_id, msg) = control_receiver.recv().unwrap();
         assert_eq!(task_id, 1);
@@ -951,6 +1281,7 @@ mod tests {
         resume_task.vm_host.resume_execution(v_int(0));
 
         let tx = db.new_world_state().unwrap();
+        let session = Arc::new(NoopClientSession::new());
         Task::run_task_loop(
             resume_task,
             &task_scheduler_client,
@@ -967,6 +1298,215 @@ mod tests {
         assert_eq!(result, v_int(123));
     }
 
+    /// Single-step a task under a debugger, and verify each step is reported and then suspends.
+    #[test]
+    fn test_debug_step() {
+        let (_kill_switch, mut task, db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval("return 1 + 2;");
+        task.vm_host.set_debugger(Some(SYSTEM_OBJECT));
+
+        let session = Arc::new(NoopClientSession::new());
+        let mut task = Some(task);
+        let mut tx = Some(tx);
+        let mut steps = 0;
+        let result = loop {
+            Task::run_task_loop(
+                task.take().unwrap(),
+                &task_scheduler_client,
+                session.clone(),
+                tx.take().unwrap_or_else(|| db.new_world_state().unwrap()),
+                Arc::new(BuiltinRegistry::new()),
+                Arc::new(Config::default()),
+            );
+
+            let (_, msg) = control_receiver.recv().unwrap();
+            let event = match msg {
+                TaskControlMsg::TaskSuccess(result) => break result,
+                TaskControlMsg::Notify { player, event } => {
+                    assert_eq!(player, SYSTEM_OBJECT);
+                    event
+                }
+                _ => panic!("Expected Notify, got {:?}", msg),
+            };
+            let Event::Notify(summary, _) = event.event else {
+                panic!("Expected notification, got {:?}", event.event);
+            };
+            let Variant::Str(summary) = summary.variant() else {
+                panic!("Expected string step summary, got {:?}", summary);
+            };
+            assert!(summary.as_string().starts_with("[debug] task 1 "));
+            steps += 1;
+
+            let (_, msg) = control_receiver.recv().unwrap();
+            let TaskControlMsg::TaskSuspend(None, mut suspended) = msg else {
+                panic!("Expected indefinite TaskSuspend, got {:?}", msg);
+            };
+            // Stepping resumes with no value to push on the stack.
+            suspended.vm_host.resume_execution(v_none());
+            task = Some(suspended);
+        };
+        assert_eq!(result, v_int(3));
+        assert!(steps >= 3);
+    }
+
+    /// Set breakpoints on a verb's lines, one with a condition that holds and one without, and
+    /// verify the task stops only at the first.
+    #[test]
+    fn test_breakpoint_condition() {
+        let test_verb = TestVerb {
+            name: Symbol::mk("test"),
+            program: compile("x = 1;\ny = 2;\nreturn x + y;", CompileOptions::default()).unwrap(),
+            argspec: VerbArgsSpec {
+                dobj: ArgSpec::None,
+                prep: PrepSpec::None,
+                iobj: ArgSpec::None,
+            },
+        };
+        let (_kill_switch, mut task, db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_command("test", &[test_verb]);
+
+        let breakpoints = Arc::new(Breakpoints::default());
+        for (line, condition) in [(2, "x == 1"), (3, "x == 5")] {
+            let program =
+                compile(&format!("return {};", condition), CompileOptions::default()).unwrap();
+            breakpoints.add(Breakpoint {
+                id: 0,
+                location: SYSTEM_OBJECT,
+                verb: Symbol::mk("test"),
+                line,
+                condition: Some((condition.to_string(), program)),
+                debugger: SYSTEM_OBJECT,
+            });
+        }
+        task.vm_host.set_breakpoints(breakpoints.clone());
+
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session.clone(),
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::Notify { event, .. } = msg else {
+            panic!("Expected Notify, got {:?}", msg);
+        };
+        let Event::Notify(summary, _) = event.event else {
+            panic!("Expected notification, got {:?}", event.event);
+        };
+        let Variant::Str(summary) = summary.variant() else {
+            panic!("Expected string breakpoint summary, got {:?}", summary);
+        };
+        assert!(summary.as_string().contains("#0:test line 2"));
+        assert!(summary.as_string().ends_with("(breakpoint 1)"));
+
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::TaskSuspend(None, mut suspended) = msg else {
+            panic!("Expected indefinite TaskSuspend, got {:?}", msg);
+        };
+        suspended.vm_host.resume_execution(v_none());
+        suspended.vm_host.set_breakpoints(breakpoints);
+
+        Task::run_task_loop(
+            suspended,
+            &task_scheduler_client,
+            session,
+            db.new_world_state().unwrap(),
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        assert_eq!(result, v_int(3));
+    }
+
+    /// Wrap a verb, and verify calls to it go through the wrapper, whose own call goes through to
+    /// the wrapped verb.
+    #[test]
+    fn test_verb_wrapper() {
+        let verbs = [
+            TestVerb {
+                name: Symbol::mk("test"),
+                program: compile("return #1:inner(1);", CompileOptions::default()).unwrap(),
+                argspec: VerbArgsSpec {
+                    dobj: ArgSpec::None,
+                    prep: PrepSpec::None,
+                    iobj: ArgSpec::None,
+                },
+            },
+            TestVerb {
+                name: Symbol::mk("inner"),
+                program: compile(
+                    "return {caller, length(callers()), args[1] * 10};",
+                    CompileOptions::default(),
+                )
+                .unwrap(),
+                argspec: VerbArgsSpec::this_none_this(),
+            },
+            TestVerb {
+                name: Symbol::mk("wrapper"),
+                program: compile(
+                    "return {verb, @this:(verb)(@args)};",
+                    CompileOptions::default(),
+                )
+                .unwrap(),
+                argspec: VerbArgsSpec::this_none_this(),
+            },
+        ];
+        let (_kill_switch, mut task, _db, mut tx, task_scheduler_client, control_receiver) =
+            setup_test_env_command("test", &verbs);
+        // #1 inherits #0's verbs, so that the wrapper runs with a different `this` from the
+        // caller of the wrapped verb.
+        let child = tx
+            .create_object(
+                &SYSTEM_OBJECT,
+                &SYSTEM_OBJECT,
+                &SYSTEM_OBJECT,
+                BitEnum::all(),
+            )
+            .unwrap();
+        assert_eq!(child, Obj::mk_id(1));
+
+        let verb_wrappers = Arc::new(VerbWrappers::default());
+        verb_wrappers
+            .add(VerbWrapper {
+                id: 0,
+                location: SYSTEM_OBJECT,
+                verb: Symbol::mk("inner"),
+                wrapper_location: SYSTEM_OBJECT,
+                wrapper_verb: Symbol::mk("wrapper"),
+                owner: SYSTEM_OBJECT,
+            })
+            .unwrap();
+        task.vm_host.set_verb_wrappers(verb_wrappers);
+
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        // The wrapped verb sees the caller it would have without the wrapper, which isn't among
+        // its callers().
+        assert_eq!(
+            result,
+            v_list(&[v_str("inner"), v_obj(SYSTEM_OBJECT), v_int(1), v_int(10)])
+        );
+    }
+
     /// Trigger a simulated read()
     #[test]
     fn test_simple_run_read() {
@@ -986,10 +1526,11 @@ mod tests {
         // Scheduler should have received a TaskRequestInput message, and it should contain the task.
         let (task_id, msg) = control_receiver.recv().unwrap();
         assert_eq!(task_id, 1);
-        let TaskControlMsg::TaskRequestInput(mut resume_task) = msg else {
+        let TaskControlMsg::TaskRequestInput(request, mut resume_task) = msg else {
             panic!("Expected TaskRequestInput, got {:?}", msg);
         };
         assert_eq!(resume_task.task_id, 1);
+        assert_eq!(request, InputRequest::Line);
 
         // Now we can simulate resumption...
         resume_task.vm_host.resume_execution(v_str("hello, world!"));
@@ -1014,6 +1555,55 @@ mod tests {
         assert_eq!(result, v_str("hello, world!"));
     }
 
+    /// `read_lines()` suspends once for all its lines, and is resumed with the list of them.
+    #[test]
+    fn test_run_read_lines() {
+        let (_kill_switch, task, db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval(r#"return read_lines("EOF", 5);"#);
+
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session.clone(),
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskRequestInput(request, mut resume_task) = msg else {
+            panic!("Expected TaskRequestInput, got {:?}", msg);
+        };
+        assert_eq!(
+            request,
+            InputRequest::Lines {
+                terminator: "EOF".to_string(),
+                max_lines: Some(5),
+            }
+        );
+
+        let lines = v_list(&[v_str("first"), v_str("second")]);
+        resume_task.vm_host.resume_execution(lines.clone());
+        let tx = db.new_world_state().unwrap();
+        Task::run_task_loop(
+            resume_task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(Config::default()),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        assert_eq!(result, lines);
+    }
+
     /// Trigger a task-fork
     #[test]
     fn test_simple_run_fork() {
@@ -1042,6 +1632,14 @@ mod tests {
             );
         });
 
+        // It checks the task quotas first...
+        let (_, msg) = control_receiver.recv().unwrap();
+        let TaskControlMsg::CheckTaskQuota { forking, reply, .. } = msg else {
+            panic!("Expected CheckTaskQuota, got {:?}", msg);
+        };
+        assert!(forking);
+        reply.send(Ok(())).unwrap();
+
         // Scheduler should have received a TaskRequestFork message.
         let (task_id, msg) = control_receiver.recv().unwrap();
         assert_eq!(task_id, 1);
@@ -1234,4 +1832,146 @@ mod tests {
         };
         assert_eq!(result, v_int(1));
     }
+
+    /// With `recycle_parents` off, recycling an object which has children fails, and leaves the
+    /// family intact.
+    #[test]
+    fn test_recycle_parents_disabled() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval("o = create(#-1); create(o); return recycle(o);");
+
+        let config = Config {
+            features_config: FeaturesConfig {
+                recycle_parents: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(config),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskException(exception) = msg else {
+            panic!("Expected TaskException, got {:?}", msg);
+        };
+        assert_eq!(exception.code, E_PERM);
+    }
+
+    /// With `chparent_clear_conflicts` on, a property which clashes with one inherited from the new
+    /// parent is dropped, rather than failing the chparent.
+    #[test]
+    fn test_chparent_clear_conflicts() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval(
+                r#"a = create(#-1); add_property(a, "x", 1, {#0, "r"});
+                   b = create(#-1); add_property(b, "x", 2, {#0, "r"});
+                   chparent(b, a); return b.x;"#,
+            );
+
+        let config = Config {
+            features_config: FeaturesConfig {
+                chparent_clear_conflicts: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(config),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        assert_eq!(result, v_int(1));
+    }
+
+    /// With `waif_compat` on, `new_waif()` makes a flyweight of the calling object with its
+    /// `:`-prefixed properties as slots, and verbs called on it find the `:`-prefixed verbs.
+    #[test]
+    fn test_waif_compat() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval(
+                r#"c = create(#-1); add_property(c, ":x", 21, {player, "r"});
+                   add_verb(c, {player, "xd", "make"}, {"this", "none", "this"});
+                   set_verb_code(c, "make", {"return new_waif();"});
+                   add_verb(c, {player, "xd", ":double"}, {"this", "none", "this"});
+                   set_verb_code(c, ":double", {"return this.x * 2;"});
+                   w = c:make(); return {w.x, w:double(), w.delegate == c};"#,
+            );
+
+        let config = Config {
+            features_config: FeaturesConfig {
+                waif_compat: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(config),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        assert_eq!(result, v_list(&[v_int(21), v_int(42), v_int(1)]));
+    }
+
+    /// With `floored_division` on, integer division rounds down and `%` takes the sign of the
+    /// divisor; math.moot pins the LambdaMOO behaviour for the same cases.
+    #[test]
+    fn test_floored_division() {
+        let (_kill_switch, task, _db, tx, task_scheduler_client, control_receiver) =
+            setup_test_env_eval("return {-7 / 2, -7 % 2, 7 % -2, -7.5 % 2.0, -6 / 2};");
+
+        let config = Config {
+            features_config: FeaturesConfig {
+                floored_division: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let session = Arc::new(NoopClientSession::new());
+        Task::run_task_loop(
+            task,
+            &task_scheduler_client,
+            session,
+            tx,
+            Arc::new(BuiltinRegistry::new()),
+            Arc::new(config),
+        );
+
+        let (task_id, msg) = control_receiver.recv().unwrap();
+        assert_eq!(task_id, 1);
+        let TaskControlMsg::TaskSuccess(result) = msg else {
+            panic!("Expected TaskSuccess, got {:?}", msg);
+        };
+        assert_eq!(
+            result,
+            v_list(&[v_int(-4), v_int(1), v_int(-1), v_float(0.5), v_int(-3)])
+        );
+    }
 }
@@ -15,14 +15,19 @@ use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 
+use crate::tasks::breakpoints::Breakpoint;
+use crate::tasks::finished::FinishedTask;
+use crate::tasks::recording::TaskRecording;
+use crate::tasks::sessions::WebhookFilter;
 use crate::tasks::task::Task;
-use crate::tasks::TaskDescription;
-use crate::vm::Fork;
-use moor_values::model::Perms;
+use crate::tasks::verb_wrappers::VerbWrapper;
+use crate::tasks::{SchedulerCounters, ServerOptions, TaskDescription};
+use crate::vm::{Fork, InputRequest};
+use moor_values::model::{PermissionCheck, Perms};
 use moor_values::tasks::{AbortLimitReason, CommandError, Exception, NarrativeEvent, TaskId};
 use moor_values::Symbol;
 use moor_values::Var;
-use moor_values::{Error, Obj};
+use moor_values::{Error, List, Obj};
 
 /// A handle for talking to the scheduler from within a task.
 #[derive(Clone)]
@@ -76,6 +81,24 @@ impl TaskSchedulerClient {
             .expect("Could not deliver client message -- scheduler shut down?");
     }
 
+    /// As `exception`, but the core's `$handle_uncaught_error` has already dealt with it, so
+    /// there's no traceback to print.
+    pub fn exception_handled(&self, exception: Exception) {
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::TaskExceptionHandled(exception),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+    }
+
+    /// Hand the scheduler the recording of the task's inputs, made as it failed, to keep.
+    pub fn recorded(&self, recording: TaskRecording) {
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::TaskRecorded(recording)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+    }
+
     /// Send a message to the scheduler that the task is requesting to fork itself.
     pub fn request_fork(&self, fork: Fork) -> TaskId {
         let (reply, receive) = oneshot::channel();
@@ -94,10 +117,22 @@ impl TaskSchedulerClient {
             .expect("Could not deliver client message -- scheduler shut down?");
     }
 
-    /// Send a message to the scheduler that the task has reached its abort limits.
-    pub fn abort_limits_reached(&self, reason: AbortLimitReason) {
+    /// Send a message to the scheduler that the task has reached its abort limits, with the
+    /// traceback of where it had got to.
+    pub fn abort_limits_reached(&self, reason: AbortLimitReason, backtrace: Vec<Var>) {
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::TaskAbortLimitsReached(reason, backtrace),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+    }
+
+    /// As `abort_limits_reached`, but the core's `$handle_task_timeout` has already dealt with
+    /// it, so there's no abort message to print.
+    pub fn abort_limits_handled(&self, reason: AbortLimitReason) {
         self.scheduler_sender
-            .send((self.task_id, TaskControlMsg::TaskAbortLimitsReached(reason)))
+            .send((self.task_id, TaskControlMsg::TaskAbortLimitsHandled(reason)))
             .expect("Could not deliver client message -- scheduler shut down?");
     }
 
@@ -110,9 +145,12 @@ impl TaskSchedulerClient {
 
     /// Send a message to the scheduler that the task is requesting input from the client.
     /// Moves this task into the suspension queue until the client provides input.
-    pub fn request_input(&self, task: Task) {
+    pub fn request_input(&self, request: InputRequest, task: Task) {
         self.scheduler_sender
-            .send((self.task_id, TaskControlMsg::TaskRequestInput(task)))
+            .send((
+                self.task_id,
+                TaskControlMsg::TaskRequestInput(request, task),
+            ))
             .expect("Could not deliver client message -- scheduler shut down?");
     }
 
@@ -169,6 +207,129 @@ impl TaskSchedulerClient {
             .expect("Could not receive result -- scheduler shut down?")
     }
 
+    /// Request that the scheduler put a suspended task into (or take it out of) single-step mode,
+    /// reporting each step to `debugger`.
+    pub fn debug_task(
+        &self,
+        debug_task_id: TaskId,
+        debugger: Option<Obj>,
+        sender_permissions: Perms,
+    ) -> Var {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::DebugTask {
+                    debug_task_id,
+                    debugger,
+                    sender_permissions,
+                    result_sender: reply,
+                },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive result -- scheduler shut down?")
+    }
+
+    /// Register a breakpoint with the scheduler, returning its id.
+    pub fn set_breakpoint(&self, breakpoint: Breakpoint) -> usize {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::SetBreakpoint(breakpoint, reply),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive breakpoint id -- scheduler shut down?")
+    }
+
+    /// Remove a breakpoint, returning false if there was no such breakpoint.
+    pub fn clear_breakpoint(&self, id: usize) -> bool {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::ClearBreakpoint(id, reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive result -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler for all registered breakpoints.
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::RequestBreakpoints(reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive breakpoints -- scheduler shut down?")
+    }
+
+    /// Register a verb wrapper with the scheduler, returning its id, or None if the verb is
+    /// already wrapped.
+    pub fn wrap_verb(&self, wrapper: VerbWrapper) -> Option<usize> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::WrapVerb(wrapper, reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive verb wrapper id -- scheduler shut down?")
+    }
+
+    /// Remove a verb wrapper, returning false if there was no such wrapper.
+    pub fn unwrap_verb(&self, id: usize) -> bool {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::UnwrapVerb(id, reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive result -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler for all registered verb wrappers.
+    pub fn verb_wrappers(&self) -> Vec<VerbWrapper> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::RequestVerbWrappers(reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive verb wrappers -- scheduler shut down?")
+    }
+
+    /// Turn permission auditing on or off, returning whether it was on.
+    pub fn set_permission_audit(&self, enabled: bool) -> bool {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::SetPermissionAudit(enabled, reply),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive permission audit state -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler for the audited permission checks, oldest first, optionally clearing them.
+    pub fn permission_checks(&self, clear: bool) -> Vec<(TaskId, PermissionCheck)> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::RequestPermissionChecks(clear, reply),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive permission checks -- scheduler shut down?")
+    }
+
     /// Request that the scheduler boot a player.
     pub fn boot_player(&self, player: Obj) {
         self.scheduler_sender
@@ -243,6 +404,141 @@ impl TaskSchedulerClient {
             .expect("Could not receive unlisten reply -- scheduler shut down?")
     }
 
+    /// Add `site` to the server's ban list, returning whether it was new.
+    pub fn ban_site(&self, site: String) -> Result<bool, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::BanSite { site, reply }))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive ban_site reply -- scheduler shut down?")
+    }
+
+    /// Remove `site` from the server's ban list, returning whether it was there.
+    pub fn unban_site(&self, site: String) -> Result<bool, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::UnbanSite { site, reply }))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive unban_site reply -- scheduler shut down?")
+    }
+
+    /// Invalidate the auth tokens `player`'s clients hold.
+    pub fn revoke_auth_tokens(&self, player: Obj) -> Result<(), Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::RevokeAuthTokens { player, reply },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive revoke_auth_tokens reply -- scheduler shut down?")
+    }
+
+    /// Hand the connections of `old_player` over to `new_player`.
+    pub fn switch_player(&self, old_player: Obj, new_player: Obj) -> Result<(), Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::SwitchPlayer {
+                    old_player,
+                    new_player,
+                    reply,
+                },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive switch_player reply -- scheduler shut down?")
+    }
+
+    /// Register a webhook with the daemon, returning its id.
+    pub fn add_webhook(
+        &self,
+        url: String,
+        secret: Option<String>,
+        filter: WebhookFilter,
+    ) -> Result<u64, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::AddWebhook {
+                    url,
+                    secret,
+                    filter,
+                    reply,
+                },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive add_webhook reply -- scheduler shut down?")
+    }
+
+    /// Remove a webhook, returning whether it was there.
+    pub fn remove_webhook(&self, id: u64) -> Result<bool, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::RemoveWebhook { id, reply }))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive remove_webhook reply -- scheduler shut down?")
+    }
+
+    pub fn webhooks(&self) -> Result<Vec<(u64, String, WebhookFilter)>, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::GetWebhooks(reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive webhooks -- scheduler shut down?")
+    }
+
+    /// Send a message to another world, via the daemon's federation.
+    pub fn federation_send(
+        &self,
+        world: String,
+        target: Obj,
+        verb: Symbol,
+        args: List,
+    ) -> Result<(), Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::FederationSend {
+                    world,
+                    target,
+                    verb,
+                    args,
+                    reply,
+                },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive federation_send reply -- scheduler shut down?")
+    }
+
+    pub fn banned_sites(&self) -> Result<Vec<String>, Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::GetBannedSites(reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive banned sites -- scheduler shut down?")
+    }
+
     /// Request that the server refresh its set of information off $server_options
     pub fn refresh_server_options(&self) {
         self.scheduler_sender
@@ -250,6 +546,87 @@ impl TaskSchedulerClient {
             .expect("Could not deliver client message -- scheduler shut down?");
     }
 
+    /// Ask the scheduler for its current set of server options.
+    pub fn server_options(&self) -> ServerOptions {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::RequestServerOptions(reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive server options -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler for its task counts and other counters.
+    pub fn performance_counters(&self) -> SchedulerCounters {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::RequestPerformanceCounters(reply),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive performance counters -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler whether a task owned by `owner` and running on behalf of `player` may
+    /// queue itself (with `suspend()`) or, if `forking`, another task, without going over their
+    /// task quotas. `owner_limit` is the owner's own limit, if they have one, in place of
+    /// `$server_options.queued_task_limit`.
+    pub fn check_task_quota(
+        &self,
+        owner: Obj,
+        player: Obj,
+        owner_limit: Option<usize>,
+        forking: bool,
+    ) -> Result<(), Error> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::CheckTaskQuota {
+                    owner,
+                    player,
+                    owner_limit,
+                    forking,
+                    reply,
+                },
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive task quota reply -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler how many tasks, running or queued, `who` owns, and how many are running
+    /// on their behalf.
+    pub fn task_counts(&self, who: Obj) -> (usize, usize) {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((self.task_id, TaskControlMsg::RequestTaskCounts(who, reply)))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive task counts -- scheduler shut down?")
+    }
+
+    /// Ask the scheduler for the recently finished tasks it remembers, oldest first; only those
+    /// run by or on behalf of `who`, if given.
+    pub fn finished_tasks(&self, who: Option<Obj>) -> Vec<FinishedTask> {
+        let (reply, receive) = oneshot::channel();
+        self.scheduler_sender
+            .send((
+                self.task_id,
+                TaskControlMsg::RequestFinishedTasks(who, reply),
+            ))
+            .expect("Could not deliver client message -- scheduler shut down?");
+        receive
+            .recv()
+            .expect("Could not receive finished tasks -- scheduler shut down?")
+    }
+
     /// Request that the system shut down.
     pub fn shutdown(&self, msg: Option<String>) {
         self.scheduler_sender
@@ -272,16 +649,22 @@ pub enum TaskControlMsg {
     TaskVerbNotFound(Var, Symbol),
     /// An exception was thrown while executing the verb.
     TaskException(Exception),
+    /// An exception was thrown while executing the verb, and `$handle_uncaught_error` handled it.
+    TaskExceptionHandled(Exception),
+    /// The task failed while recording its nondeterministic inputs, and this is the recording.
+    TaskRecorded(TaskRecording),
     /// The task is requesting that it be forked.
     TaskRequestFork(Fork, oneshot::Sender<TaskId>),
     /// The task is letting us know it was cancelled.
     TaskAbortCancelled,
-    /// The task is letting us know that it has reached its abort limits.
-    TaskAbortLimitsReached(AbortLimitReason),
+    /// The task is letting us know that it has reached its abort limits, and where it had got to.
+    TaskAbortLimitsReached(AbortLimitReason, Vec<Var>),
+    /// The task reached its abort limits, and `$handle_task_timeout` handled it.
+    TaskAbortLimitsHandled(AbortLimitReason),
     /// Tell the scheduler that the task in a suspended state, with a time to resume (if any)
     TaskSuspend(Option<Instant>, Task),
     /// Tell the scheduler we're suspending until we get input from the client.
-    TaskRequestInput(Task),
+    TaskRequestInput(InputRequest, Task),
     /// Task is requesting a list of all other tasks known to the scheduler.
     RequestQueuedTasks(oneshot::Sender<Vec<TaskDescription>>),
     /// Task is requesting that the scheduler abort another task.
@@ -297,6 +680,30 @@ pub enum TaskControlMsg {
         return_value: Var,
         result_sender: oneshot::Sender<Var>,
     },
+    /// Task is requesting that the scheduler single-step (or stop single-stepping) another,
+    /// suspended, task.
+    DebugTask {
+        debug_task_id: TaskId,
+        debugger: Option<Obj>,
+        sender_permissions: Perms,
+        result_sender: oneshot::Sender<Var>,
+    },
+    /// Task is registering a breakpoint.
+    SetBreakpoint(Breakpoint, oneshot::Sender<usize>),
+    /// Task is removing the breakpoint with the given id.
+    ClearBreakpoint(usize, oneshot::Sender<bool>),
+    /// Task is requesting a list of all breakpoints.
+    RequestBreakpoints(oneshot::Sender<Vec<Breakpoint>>),
+    /// Task is wrapping a verb.
+    WrapVerb(VerbWrapper, oneshot::Sender<Option<usize>>),
+    /// Task is removing the verb wrapper with the given id.
+    UnwrapVerb(usize, oneshot::Sender<bool>),
+    /// Task is requesting a list of all verb wrappers.
+    RequestVerbWrappers(oneshot::Sender<Vec<VerbWrapper>>),
+    /// Task is turning permission auditing on or off.
+    SetPermissionAudit(bool, oneshot::Sender<bool>),
+    /// Task is requesting the audited permission checks, and whether to clear them.
+    RequestPermissionChecks(bool, oneshot::Sender<Vec<(TaskId, PermissionCheck)>>),
     /// Task is requesting that the scheduler boot a player.
     BootPlayer {
         player: Obj,
@@ -324,8 +731,67 @@ pub enum TaskControlMsg {
         port: u16,
         reply: oneshot::Sender<Option<Error>>,
     },
+    /// Add a site to the daemon's login ban list.
+    BanSite {
+        site: String,
+        reply: oneshot::Sender<Result<bool, Error>>,
+    },
+    /// Remove a site from the daemon's login ban list.
+    UnbanSite {
+        site: String,
+        reply: oneshot::Sender<Result<bool, Error>>,
+    },
+    GetBannedSites(oneshot::Sender<Result<Vec<String>, Error>>),
+    /// Invalidate the auth tokens issued to a player so far.
+    RevokeAuthTokens {
+        player: Obj,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+    /// Move a player's connections over to another player, re-authenticating them as it.
+    SwitchPlayer {
+        old_player: Obj,
+        new_player: Obj,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+    /// Register a webhook for committed changes.
+    AddWebhook {
+        url: String,
+        secret: Option<String>,
+        filter: WebhookFilter,
+        reply: oneshot::Sender<Result<u64, Error>>,
+    },
+    /// Remove a webhook.
+    RemoveWebhook {
+        id: u64,
+        reply: oneshot::Sender<Result<bool, Error>>,
+    },
+    GetWebhooks(oneshot::Sender<Result<Vec<(u64, String, WebhookFilter)>, Error>>),
+    /// Send a message to a verb in another world.
+    FederationSend {
+        world: String,
+        target: Obj,
+        verb: Symbol,
+        args: List,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
     /// Request that the server refresh its set of information off $server_options
     RefreshServerOptions,
+    /// Task is requesting the current server options.
+    RequestServerOptions(oneshot::Sender<ServerOptions>),
+    /// Task is requesting the scheduler's task counts and other counters.
+    RequestPerformanceCounters(oneshot::Sender<SchedulerCounters>),
+    /// Task is asking whether it may queue itself, or fork another task, within the task quotas.
+    CheckTaskQuota {
+        owner: Obj,
+        player: Obj,
+        owner_limit: Option<usize>,
+        forking: bool,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+    /// Task is asking how many tasks a player owns, and how many are running on their behalf.
+    RequestTaskCounts(Obj, oneshot::Sender<(usize, usize)>),
+    /// Task is asking for the recently finished tasks, optionally only a given player's.
+    RequestFinishedTasks(Option<Obj>, oneshot::Sender<Vec<FinishedTask>>),
     /// Task requesting shutdown
     Shutdown(Option<String>),
 }
@@ -0,0 +1,101 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Verb wrappers: verbs which run in place of other verbs, for instrumenting a live core without
+//! editing its code. Shared between the scheduler (which manages them) and running tasks (which
+//! consult them when dispatching verb calls).
+//!
+//! The wrapper is called with the wrapped call's `this`, `verb` and `args`, so it can do its work
+//! before and after calling through with `this:(verb)(@args)`. Calls made by the wrapper itself go
+//! straight to the wrapped verb. The wrapper runs with the permissions of whoever made the call,
+//! and its call through passes on the original `caller`, with the wrapper left out of
+//! `callers()`, so the wrapped verb sees the same `caller`, `caller_perms()` and `callers()` it
+//! would have without it.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use moor_values::model::{Named, VerbDef};
+use moor_values::{Obj, Symbol};
+
+#[derive(Debug, Clone)]
+pub struct VerbWrapper {
+    pub id: usize,
+    /// The object the wrapped verb is defined on.
+    pub location: Obj,
+    pub verb: Symbol,
+    /// The object the wrapper verb is found on, and its name.
+    pub wrapper_location: Obj,
+    pub wrapper_verb: Symbol,
+    /// The wizard who set up the wrapper.
+    pub owner: Obj,
+}
+
+impl VerbWrapper {
+    fn applies_to(&self, location: &Obj, verbdef: &VerbDef) -> bool {
+        self.location.eq(location) && verbdef.matches_name(self.verb)
+    }
+}
+
+#[derive(Default)]
+pub struct VerbWrappers {
+    /// Fast path so verb dispatch doesn't touch the lock when nothing is wrapped.
+    armed: AtomicBool,
+    next_id: AtomicUsize,
+    wrappers: RwLock<Vec<VerbWrapper>>,
+}
+
+impl VerbWrappers {
+    /// Add a wrapper, returning its (newly assigned) id, or None if the verb is already wrapped.
+    pub fn add(&self, mut wrapper: VerbWrapper) -> Option<usize> {
+        let mut wrappers = self.wrappers.write().unwrap();
+        if wrappers
+            .iter()
+            .any(|w| w.location.eq(&wrapper.location) && w.verb == wrapper.verb)
+        {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        wrapper.id = id;
+        wrappers.push(wrapper);
+        self.armed.store(true, Ordering::SeqCst);
+        Some(id)
+    }
+
+    /// Remove the wrapper with the given id, returning false if there was none.
+    pub fn remove(&self, id: usize) -> bool {
+        let mut wrappers = self.wrappers.write().unwrap();
+        let Some(position) = wrappers.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        wrappers.remove(position);
+        self.armed.store(!wrappers.is_empty(), Ordering::SeqCst);
+        true
+    }
+
+    pub fn list(&self) -> Vec<VerbWrapper> {
+        self.wrappers.read().unwrap().clone()
+    }
+
+    /// The wrapper for the given verb, defined on `location`, if it has one.
+    pub fn find(&self, location: &Obj, verbdef: &VerbDef) -> Option<VerbWrapper> {
+        if !self.armed.load(Ordering::Relaxed) {
+            return None;
+        }
+        let wrappers = self.wrappers.read().unwrap();
+        wrappers
+            .iter()
+            .find(|w| w.applies_to(location, verbdef))
+            .cloned()
+    }
+}
@@ -24,7 +24,7 @@ use tracing::{debug, error, trace, warn};
 
 use moor_compiler::Name;
 use moor_compiler::Program;
-use moor_compiler::{compile, CompileOptions};
+use moor_compiler::{compile, to_literal, CompileOptions};
 use moor_values::model::{BinaryType, ObjFlag};
 use moor_values::model::{VerbDef, WorldState};
 use moor_values::tasks::{AbortLimitReason, TaskId};
@@ -36,18 +36,28 @@ use moor_values::{AsByteBuffer, List};
 
 use crate::builtins::BuiltinRegistry;
 use crate::config::FeaturesConfig;
+use crate::tasks::breakpoints::Breakpoints;
+use crate::tasks::load::LoadMonitor;
+use crate::tasks::permission_audit::PermissionAudit;
 use crate::tasks::sessions::Session;
 use crate::tasks::task_scheduler_client::TaskSchedulerClient;
-use crate::tasks::VerbCall;
+use crate::tasks::verb_wrappers::VerbWrappers;
+use crate::tasks::{owner_task_limit, VerbCall};
 use crate::vm::activation::Frame;
 use crate::vm::moo_execute::moo_frame_execute;
+use crate::vm::tick_costs::TickCosts;
 use crate::vm::vm_call::{VerbProgram, VmExecParams};
 use crate::vm::VMHostResponse::{AbortLimit, ContinueOk, DispatchFork, Suspend};
 use crate::vm::{ExecutionResult, Fork, VMHostResponse, VerbExecutionRequest};
-use crate::vm::{FinallyReason, VMExecState};
+use crate::vm::{FinallyReason, ReplayLog, VMExecState};
 use crate::PhantomUnsync;
 use moor_values::matching::command_parse::ParsedCommand;
 
+/// How many of the topmost value stack entries are shown in a debugger step summary.
+const DEBUG_STACK_SUMMARY_DEPTH: usize = 5;
+/// The tick budget for evaluating a breakpoint condition.
+const BREAKPOINT_CONDITION_TICKS: usize = 10_000;
+
 /// A 'host' for running some kind of interpreter / virtual machine inside a running moor task.
 pub struct VmHost {
     /// Where we store current execution state for this host. Includes all all activations and the
@@ -60,6 +70,18 @@ pub struct VmHost {
     /// The maximum amount of time allotted to this task
     max_time: Duration,
     running: bool,
+    /// The scheduler's breakpoints, if any are to be honoured. Transient; the scheduler hands this
+    /// over whenever it starts or resumes the task.
+    breakpoints: Option<Arc<Breakpoints>>,
+    /// The scheduler's verb wrappers. Transient, like `breakpoints`.
+    verb_wrappers: Option<Arc<VerbWrappers>>,
+    /// The scheduler's permission audit log. Transient, like `breakpoints`.
+    permission_audit: Option<Arc<PermissionAudit>>,
+    /// The scheduler's measure of commit latency, reported to on each commit. Transient, like
+    /// `breakpoints`.
+    load_monitor: Option<Arc<LoadMonitor>>,
+    /// What opcodes and builtins count for against `max_ticks`. Transient, like `breakpoints`.
+    tick_costs: Option<Arc<TickCosts>>,
 
     unsync: PhantomUnsync,
 }
@@ -91,6 +113,11 @@ impl VmHost {
             max_ticks,
             max_time,
             running: false,
+            breakpoints: None,
+            verb_wrappers: None,
+            permission_audit: None,
+            tick_costs: None,
+            load_monitor: None,
             unsync: Default::default(),
         }
     }
@@ -208,6 +235,8 @@ impl VmHost {
             builtin_registry,
             max_stack_depth: self.max_stack_depth,
             config,
+            verb_wrappers: self.verb_wrappers.clone(),
+            tick_costs: self.tick_costs.clone(),
         };
 
         // Check existing ticks and seconds, and abort the task if we've exceeded the limits.
@@ -221,14 +250,37 @@ impl VmHost {
             }
         };
 
-        // Grant the loop its next tick slice.
-        self.vm_exec_state.tick_slice = self.max_ticks - self.vm_exec_state.tick_count;
+        // Stop here if there's a breakpoint on the line we're about to execute.
+        if let Some(response) = self.check_breakpoints(&exec_params, world_state, session.clone()) {
+            return response;
+        }
+
+        // Grant the loop its next tick slice. A task being single-stepped, or executing a verb
+        // which has breakpoints in it, gets one opcode at a time.
+        self.vm_exec_state.tick_slice =
+            if self.vm_exec_state.debugger.is_some() || self.in_breakpointed_verb() {
+                1
+            } else {
+                self.max_ticks - self.vm_exec_state.tick_count
+            };
 
         // Actually invoke the VM, asking it to loop until it's ready to yield back to us.
         let mut result = self.run_interpreter(&exec_params, world_state, session.clone());
         while self.is_running() {
             match result {
-                ExecutionResult::More => return ContinueOk,
+                ExecutionResult::More => {
+                    if let (Some(debugger), false) = (
+                        self.vm_exec_state.debugger.clone(),
+                        self.in_atomic_section(),
+                    ) {
+                        self.vm_exec_state.debug_paused = true;
+                        return VMHostResponse::DebugStep {
+                            debugger,
+                            summary: self.debug_step_summary(),
+                        };
+                    }
+                    return ContinueOk;
+                }
                 ExecutionResult::PushError(e) => {
                     result = self.vm_exec_state.push_error(e);
                     continue;
@@ -313,6 +365,16 @@ impl VmHost {
                 }
                 ExecutionResult::TaskStartFork(delay, task_id, fv_offset) => {
                     let a = self.vm_exec_state.top().clone();
+                    let owner_limit = owner_task_limit(world_state, &a.permissions);
+                    if let Err(e) = task_scheduler_client.check_task_quota(
+                        a.permissions.clone(),
+                        a.player.clone(),
+                        owner_limit,
+                        true,
+                    ) {
+                        result = self.vm_exec_state.push_error(e);
+                        continue;
+                    }
                     let parent_task_id = self.vm_exec_state.task_id;
                     let new_activation = a.clone();
                     let fork_request = Fork {
@@ -329,8 +391,8 @@ impl VmHost {
                 ExecutionResult::TaskSuspend(delay) => {
                     return Suspend(delay);
                 }
-                ExecutionResult::TaskNeedInput => {
-                    return VMHostResponse::SuspendNeedInput;
+                ExecutionResult::TaskNeedInput(request) => {
+                    return VMHostResponse::SuspendNeedInput(request);
                 }
                 ExecutionResult::Complete(a) => {
                     trace!(task_id, "Task completed");
@@ -365,6 +427,124 @@ impl VmHost {
         VMHostResponse::CompleteAbort
     }
 
+    fn in_breakpointed_verb(&self) -> bool {
+        let (Some(breakpoints), Some(activation)) =
+            (&self.breakpoints, self.vm_exec_state.stack.last())
+        else {
+            return false;
+        };
+        breakpoints.any_for_verb(&activation.verb_definer(), &activation.verbdef)
+    }
+
+    /// If the top frame is about to execute the first opcode of a line with a breakpoint on it
+    /// (and the breakpoint's condition, if any, holds), pause the task for its debugger.
+    fn check_breakpoints(
+        &mut self,
+        exec_params: &VmExecParams,
+        world_state: &mut dyn WorldState,
+        session: Arc<dyn Session>,
+    ) -> Option<VMHostResponse> {
+        let breakpoints = self.breakpoints.clone()?;
+        if std::mem::take(&mut self.vm_exec_state.skip_breakpoint) {
+            return None;
+        }
+        // Pausing would commit the transaction part way through an atomic section.
+        if self.in_atomic_section() {
+            return None;
+        }
+        let activation = self.vm_exec_state.stack.last()?;
+        let Frame::Moo(fr) = &activation.frame else {
+            return None;
+        };
+        let hits = breakpoints.hits(
+            &activation.verb_definer(),
+            &activation.verbdef,
+            &fr.program,
+            fr.pc,
+        );
+        for breakpoint in hits {
+            if let Some((_, condition)) = &breakpoint.condition {
+                if !self.condition_holds(
+                    &breakpoint.debugger,
+                    condition,
+                    exec_params,
+                    world_state,
+                    session.clone(),
+                ) {
+                    continue;
+                }
+            }
+            self.vm_exec_state.debug_paused = true;
+            self.vm_exec_state.skip_breakpoint = true;
+            return Some(VMHostResponse::DebugStep {
+                debugger: breakpoint.debugger.clone(),
+                summary: format!(
+                    "{} (breakpoint {})",
+                    self.debug_step_summary(),
+                    breakpoint.id
+                ),
+            });
+        }
+        None
+    }
+
+    /// Evaluate a breakpoint condition in a scratch VM, with the variables of the current frame
+    /// copied in by name. A condition which fails to run to completion counts as true, so that
+    /// a broken condition is noticed rather than silently never firing.
+    fn condition_holds(
+        &self,
+        debugger: &Obj,
+        condition: &Program,
+        exec_params: &VmExecParams,
+        world_state: &mut dyn WorldState,
+        session: Arc<dyn Session>,
+    ) -> bool {
+        let task_id = self.vm_exec_state.task_id;
+        let Frame::Moo(fr) = &self.vm_exec_state.top().frame else {
+            return false;
+        };
+
+        let mut host = VmHost::new(
+            task_id,
+            self.max_stack_depth,
+            BREAKPOINT_CONDITION_TICKS,
+            self.max_time,
+        );
+        host.start_eval(task_id, debugger, condition.clone(), world_state);
+        for name in condition.var_names.names() {
+            let Some(symbol) = condition.var_names.name_of(&name) else {
+                continue;
+            };
+            let Some(value) = fr
+                .program
+                .var_names
+                .find_name(symbol.as_str())
+                .and_then(|frame_name| fr.get_env(&frame_name))
+            else {
+                continue;
+            };
+            host.set_variable(&name, value.clone());
+        }
+
+        loop {
+            match host.exec_interpreter(
+                task_id,
+                world_state,
+                exec_params.task_scheduler_client.clone(),
+                session.clone(),
+                exec_params.builtin_registry.clone(),
+                exec_params.config.clone(),
+            ) {
+                ContinueOk => continue,
+                VMHostResponse::CompleteSuccess(result) => return result.is_true(),
+                _ => {
+                    warn!(task_id, "Breakpoint condition did not complete");
+                    return true;
+                }
+            }
+        }
+    }
+
     pub fn run_interpreter(
         &mut self,
         vm_exec_params: &VmExecParams,
@@ -396,6 +576,8 @@ impl VmHost {
                     activation.permissions.clone(),
                     fr,
                     world_state,
+                    &vm_exec_params.config,
+                    vm_exec_params.tick_costs.as_deref(),
                 );
                 (result, tick_count)
             }
@@ -413,12 +595,30 @@ impl VmHost {
         result
     }
 
+    /// Replace the task's tick and time allotments, e.g. with the background ones when it resumes
+    /// from suspension. Takes effect from the next `start_*` or `resume_execution`.
+    pub fn set_limits(&mut self, max_ticks: usize, max_time: Duration) {
+        self.max_ticks = max_ticks;
+        self.max_time = max_time;
+        self.vm_exec_state.max_ticks = max_ticks;
+    }
+
     /// Resume what you were doing after suspension.
     pub fn resume_execution(&mut self, value: Var) {
         self.vm_exec_state.start_time = Some(SystemTime::now());
+        self.vm_exec_state.maximum_time = Some(self.max_time);
         self.vm_exec_state.tick_count = 0;
         self.running = true;
 
+        // Resuming out of a debugger step: there's no `suspend()` or `read()` waiting on a value.
+        if std::mem::take(&mut self.vm_exec_state.debug_paused) {
+            debug!(task_id = self.vm_exec_state.task_id, "Stepping VMHost");
+            return;
+        }
+
+        // The value we're resumed with (e.g. the line from `read()`) comes from outside the task.
+        let value = self.vm_exec_state.replay.observe(|| value);
+
         // If there's no activations at all, that means we're a Fork, not returning to something.
         if !self.vm_exec_state.stack.is_empty() {
             // coming back from any suspend, we need a return value to feed back to `bf_suspend` or
@@ -470,6 +670,80 @@ impl VmHost {
         self.vm_exec_state.top().frame.find_line_no().unwrap_or(0)
     }
 
+    pub fn debugger(&self) -> Option<Obj> {
+        self.vm_exec_state.debugger.clone()
+    }
+    pub fn set_debugger(&mut self, debugger: Option<Obj>) {
+        self.vm_exec_state.debugger = debugger;
+    }
+    pub fn set_breakpoints(&mut self, breakpoints: Arc<Breakpoints>) {
+        self.breakpoints = Some(breakpoints);
+    }
+    pub fn set_verb_wrappers(&mut self, verb_wrappers: Arc<VerbWrappers>) {
+        self.verb_wrappers = Some(verb_wrappers);
+    }
+    pub fn set_permission_audit(&mut self, permission_audit: Arc<PermissionAudit>) {
+        self.permission_audit = Some(permission_audit);
+    }
+    pub fn set_tick_costs(&mut self, tick_costs: Arc<TickCosts>) {
+        self.tick_costs = Some(tick_costs);
+    }
+    pub fn permission_audit(&self) -> Option<&Arc<PermissionAudit>> {
+        self.permission_audit.as_ref()
+    }
+    pub fn set_load_monitor(&mut self, load_monitor: Arc<LoadMonitor>) {
+        self.load_monitor = Some(load_monitor);
+    }
+    pub fn load_monitor(&self) -> Option<&Arc<LoadMonitor>> {
+        self.load_monitor.as_ref()
+    }
+
+    /// Describe where a single-stepped task is paused: verb and line, the next opcode to be
+    /// executed, and the top of the value stack.
+    pub fn debug_step_summary(&self) -> String {
+        let activation = self.vm_exec_state.top();
+        // `find_line_no` answers for the opcode just executed (at `pc - 1`); we want the line of
+        // the one about to be.
+        let line = match &activation.frame {
+            Frame::Moo(fr) => fr.find_line_no(fr.pc + 1),
+            Frame::Bf(_) => None,
+        };
+        let mut summary = format!(
+            "[debug] task {} {}:{} line {}",
+            self.vm_exec_state.task_id,
+            activation.verb_definer(),
+            activation.verb_name,
+            line.unwrap_or(0)
+        );
+        if let Frame::Moo(fr) = &activation.frame {
+            if let Some(op) = fr.program.main_vector.get(fr.pc) {
+                summary.push_str(&format!(": {:?}", op));
+            }
+            let skip = fr.valstack.len().saturating_sub(DEBUG_STACK_SUMMARY_DEPTH);
+            let stack: Vec<_> = fr.valstack.iter().skip(skip).map(to_literal).collect();
+            summary.push_str(&format!(" stack: {{{}}}", stack.join(", ")));
+        }
+        summary
+    }
+
+    /// True if the task is inside a `begin_atomic()`/`end_atomic()` section.
+    /// Throw away the stack of a task being aborted for running out of ticks or seconds, returning
+    /// its stack list and backtrace, as for an uncaught error with `msg`.
+    pub fn unwind_for_abort(&mut self, msg: &str) -> (Vec<Var>, Vec<Var>) {
+        self.vm_exec_state.unwind_for_abort(msg)
+    }
+
+    pub fn in_atomic_section(&self) -> bool {
+        self.vm_exec_state.atomic_depth > 0
+    }
+
+    pub fn replay_log(&self) -> &ReplayLog {
+        &self.vm_exec_state.replay
+    }
+    pub fn set_replay_log(&mut self, replay_log: ReplayLog) {
+        self.vm_exec_state.replay = replay_log;
+    }
+
     pub fn reset_ticks(&mut self) {
         self.vm_exec_state.tick_count = 0;
     }
@@ -508,6 +782,11 @@ impl Decode for VmHost {
             max_ticks,
             max_time,
             running: true,
+            breakpoints: None,
+            verb_wrappers: None,
+            permission_audit: None,
+            tick_costs: None,
+            load_monitor: None,
             unsync: Default::default(),
         })
     }
@@ -526,6 +805,11 @@ impl<'de> BorrowDecode<'de> for VmHost {
             max_ticks,
             max_time,
             running: true,
+            breakpoints: None,
+            verb_wrappers: None,
+            permission_audit: None,
+            tick_costs: None,
+            load_monitor: None,
             unsync: Default::default(),
         })
     }
@@ -20,14 +20,17 @@ use std::path::PathBuf;
 use tracing::{info, span, trace};
 
 use crate::config::{FeaturesConfig, TextdumpVersion};
+use crate::program_cache::{compile_cached, prepare_program_cache};
 use crate::textdump::read::TextdumpReaderError;
 use crate::textdump::{
-    Object, TextdumpReader, PREP_ANY, PREP_NONE, VF_ASPEC_ANY, VF_ASPEC_NONE, VF_ASPEC_THIS,
-    VF_DEBUG, VF_DOBJSHIFT, VF_EXEC, VF_IOBJSHIFT, VF_OBJMASK, VF_PERMMASK, VF_READ, VF_WRITE,
+    Object, Textdump, TextdumpReader, PREP_ANY, PREP_NONE, VF_ASPEC_ANY, VF_ASPEC_NONE,
+    VF_ASPEC_THIS, VF_DEBUG, VF_DOBJSHIFT, VF_EXEC, VF_IOBJSHIFT, VF_OBJMASK, VF_PERMMASK, VF_READ,
+    VF_WRITE,
 };
-use moor_compiler::compile;
-use moor_compiler::Program;
+use moor_compiler::{CompileOptions, Program};
 use moor_db::loader::LoaderInterface;
+use moor_db::ProgramCache;
+use moor_values::model::CompileError;
 use moor_values::model::Preposition;
 use moor_values::model::PropFlag;
 use moor_values::model::VerbFlag;
@@ -85,12 +88,63 @@ fn cv_aspec_flag(flags: u16) -> ArgSpec {
     }
 }
 
+type VerbKey = (Obj, usize);
+
+/// Compile every verb program in the textdump, splitting the work across `import_threads` threads.
+/// On failure, returns the first (in verb order) verb that failed to compile, along with its error.
+fn compile_verbs(
+    td: &Textdump,
+    program_cache: &ProgramCache,
+    compile_options: &CompileOptions,
+    import_threads: usize,
+) -> Result<BTreeMap<VerbKey, Vec<u8>>, (VerbKey, CompileError)> {
+    let sources: Vec<(&VerbKey, &String)> = td
+        .verbs
+        .iter()
+        .filter_map(|(key, verb)| verb.program.as_ref().map(|source| (key, source)))
+        .collect();
+    if sources.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let chunk_size = sources.len().div_ceil(import_threads.max(1));
+
+    let compiled: Vec<Result<Vec<(VerbKey, Vec<u8>)>, (VerbKey, CompileError)>> =
+        std::thread::scope(|s| {
+            let workers: Vec<_> = sources
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|&(key, source)| {
+                                compile_cached(program_cache, source, compile_options.clone())
+                                    .map(|binary| (key.clone(), binary))
+                                    .map_err(|e| (key.clone(), e))
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("Verb compilation thread panicked"))
+                .collect()
+        });
+
+    let mut binaries = BTreeMap::new();
+    for chunk in compiled {
+        binaries.extend(chunk?);
+    }
+    Ok(binaries)
+}
+
 #[tracing::instrument(skip(ldr))]
 pub fn textdump_load(
     ldr: &mut dyn LoaderInterface,
     path: PathBuf,
     moor_version: Version,
     features_config: FeaturesConfig,
+    import_threads: usize,
 ) -> Result<(), TextdumpReaderError> {
     let textdump_import_span = span!(tracing::Level::INFO, "textdump_import");
     let _enter = textdump_import_span.enter();
@@ -100,7 +154,7 @@ pub fn textdump_load(
 
     let br = BufReader::new(corefile);
 
-    read_textdump(ldr, br, moor_version, features_config)
+    read_textdump(ldr, br, moor_version, features_config, import_threads)
 }
 
 pub fn read_textdump<T: io::Read>(
@@ -108,6 +162,7 @@ pub fn read_textdump<T: io::Read>(
     reader: BufReader<T>,
     moo_version: Version,
     features_config: FeaturesConfig,
+    import_threads: usize,
 ) -> Result<(), TextdumpReaderError> {
     let mut tdr = TextdumpReader::new(reader);
     let (td, version) = tdr.read_textdump()?;
@@ -141,6 +196,8 @@ pub fn read_textdump<T: io::Read>(
     }
 
     let compile_options = features_config.compile_options();
+    let program_cache = loader.program_cache();
+    prepare_program_cache(&program_cache);
 
     info!("Instantiating objects");
     for (objid, o) in &td.objects {
@@ -209,6 +266,25 @@ pub fn read_textdump<T: io::Read>(
         }
     }
 
+    // Compilation is the expensive part of the import, and doesn't touch the database, so it's
+    // done up front (across threads, if so configured) before we apply the verb definitions.
+    info!(import_threads, "Compiling verbs...");
+    let mut binaries = compile_verbs(&td, &program_cache, &compile_options, import_threads)
+        .map_err(|((objid, vn), e)| {
+            let names = td
+                .objects
+                .get(&objid)
+                .and_then(|o| o.verbdefs.get(vn))
+                .map(|v| v.name.split(' ').collect::<Vec<_>>())
+                .unwrap_or_default();
+            TextdumpReaderError::VerbCompileError(
+                format!("compiling verb #{}/{} ({:?})", objid, vn, names),
+                e,
+            )
+        })?;
+    let (hits, misses) = program_cache.stats();
+    info!(hits, misses, "Verbs compiled.");
+
     info!("Defining verbs...");
     for (objid, o) in &td.objects {
         for (vn, v) in o.verbdefs.iter().enumerate() {
@@ -237,26 +313,15 @@ pub fn read_textdump<T: io::Read>(
 
             let names: Vec<&str> = v.name.split(' ').collect();
 
-            let program = match td.verbs.get(&(objid.clone(), vn)) {
-                Some(verb) if verb.program.is_some() => compile(
-                    verb.program.clone().unwrap().as_str(),
-                    compile_options.clone(),
-                )
-                .map_err(|e| {
-                    TextdumpReaderError::VerbCompileError(
-                        format!("compiling verb #{}/{} ({:?})", objid, vn, names),
-                        e.clone(),
-                    )
-                })?,
+            let binary = match binaries.remove(&(objid.clone(), vn)) {
+                Some(binary) => binary,
                 // If the verb program is missing, then it's an empty program, and we'll put in
                 // an empty binary.
-                _ => Program::new(),
+                None => Program::new()
+                    .with_byte_buffer(|d| Vec::from(d))
+                    .expect("Failed to encode program"),
             };
 
-            let binary =
-                // Encode the binary (for now using bincode)
-                program.with_byte_buffer(|d| Vec::from(d)).expect("Failed to encode program");
-
             loader
                 .add_verb(objid, names.clone(), &v.owner, flags, argspec, binary)
                 .map_err(|e| {
@@ -16,6 +16,7 @@ use moor_values::Obj;
 use moor_values::Var;
 pub use read::TextdumpReader;
 use serde::{Deserialize, Serialize};
+pub use sink::{DumpSink, FileDumpSink};
 /// Representation of the structure of objects verbs etc as read from a LambdaMOO textdump'd db
 /// file.
 use std::collections::BTreeMap;
@@ -25,6 +26,7 @@ pub use write_textdump::make_textdump;
 
 mod load_textdump;
 mod read;
+mod sink;
 mod write;
 mod write_textdump;
 
@@ -123,3 +125,25 @@ pub struct Textdump {
 
 const PREP_ANY: i16 = -2;
 const PREP_NONE: i16 = -1;
+
+#[cfg(test)]
+mod tests {
+    use super::{EncodingMode, TextdumpReader, TextdumpWriter};
+    use moor_values::Var;
+    use proptest::prelude::*;
+    use std::io::BufReader;
+
+    proptest! {
+        #[test]
+        fn test_var_round_trip(v in any::<Var>()) {
+            let mut buffer = vec![];
+            TextdumpWriter::new(&mut buffer, EncodingMode::UTF8)
+                .write_var(&v, false)
+                .unwrap();
+            let read = TextdumpReader::new(BufReader::new(&buffer[..]))
+                .read_var()
+                .unwrap();
+            prop_assert!(read.eq_case_sensitive(&v), "{:?} != {:?}", read, v);
+        }
+    }
+}
@@ -193,7 +193,7 @@ impl<R: Read> TextdumpReader<R> {
         Ok(v)
     }
 
-    fn read_var(&mut self) -> Result<Var, TextdumpReaderError> {
+    pub(super) fn read_var(&mut self) -> Result<Var, TextdumpReaderError> {
         let t_num = self.read_num()?;
         self.read_var_value(t_num)
     }
@@ -0,0 +1,56 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Somewhere a dump of the database can be written to: a local file, or somewhere offsite.
+pub trait DumpSink: Send + Sync {
+    /// Where dumps go, for logging.
+    fn describe(&self) -> String;
+
+    /// Store a new dump, the contents of which are written by `write`. The previous one stays
+    /// whole until the new one is complete.
+    fn store(&self, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()>;
+}
+
+/// Dumps to a file on the local filesystem, replacing the previous dump.
+pub struct FileDumpSink {
+    path: PathBuf,
+}
+
+impl FileDumpSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl DumpSink for FileDumpSink {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn store(&self, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        // Written alongside and then moved into place, so that anything reading the dump (e.g. to
+        // ship it to a standby) never sees one half-written.
+        let mut partial_path = self.path.clone().into_os_string();
+        partial_path.push(".partial");
+        let mut output = BufWriter::new(File::create(&partial_path)?);
+        write(&mut output)?;
+        output.flush()?;
+        drop(output);
+        std::fs::rename(&partial_path, &self.path)
+    }
+}
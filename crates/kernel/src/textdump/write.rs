@@ -46,7 +46,7 @@ impl<W: io::Write> TextdumpWriter<W> {
         )
     }
 
-    fn write_var(&mut self, var: &Var, is_clear: bool) -> Result<(), io::Error> {
+    pub(super) fn write_var(&mut self, var: &Var, is_clear: bool) -> Result<(), io::Error> {
         if is_clear {
             writeln!(self.writer, "{}", TYPE_CLEAR)?;
             return Ok(());
@@ -59,20 +59,19 @@ impl<W: io::Write> TextdumpWriter<W> {
                 writeln!(self.writer, "{}\n{}", VarType::TYPE_OBJ as u64, o.id().0)?;
             }
             Variant::Str(s) => {
+                writeln!(self.writer, "{}", VarType::TYPE_STR as i64)?;
                 match self.encoding_mode {
                     EncodingMode::ISO8859_1 => {
-                        //
                         let encoding = encoding_rs::WINDOWS_1252;
                         let s = s.as_string();
                         let s = encoding.encode(s);
-                        let written = self.writer.write(&s.0).unwrap();
-                        assert_eq!(written, s.0.len());
+                        self.writer.write_all(&s.0)?;
+                        writeln!(self.writer)?;
                     }
                     EncodingMode::UTF8 => {
-                        writeln!(self.writer, "{}\n{}", VarType::TYPE_STR as i64, s)?;
+                        writeln!(self.writer, "{}", s)?;
                     }
                 }
-                writeln!(self.writer, "{}\n{}", VarType::TYPE_STR as i64, s)?;
             }
             Variant::Err(e) => {
                 writeln!(self.writer, "{}\n{}", VarType::TYPE_ERR as i64, *e as u8)?;
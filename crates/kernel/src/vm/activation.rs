@@ -65,6 +65,18 @@ pub(crate) struct Activation {
     pub(crate) permissions: Obj,
     /// The command that triggered this verb call, if any.
     pub(crate) command: Option<ParsedCommand>,
+    /// Set if this is a verb wrapper standing in for a call to another verb.
+    pub(crate) wrapping: Option<WrappedCall>,
+}
+
+/// The call a verb wrapper's activation is standing in for, so that when the wrapper calls through
+/// to the wrapped verb, the wrapped verb sees the call as it was originally made.
+#[derive(Debug, Clone)]
+pub(crate) struct WrappedCall {
+    /// The wrapped verb.
+    pub(crate) verb: Uuid,
+    /// Who made the original call.
+    pub(crate) caller: Var,
 }
 
 impl Encode for Activation {
@@ -78,6 +90,10 @@ impl Encode for Activation {
         self.verb_name.encode(encoder)?;
         self.permissions.encode(encoder)?;
         self.command.encode(encoder)?;
+        self.wrapping
+            .as_ref()
+            .map(|w| (*w.verb.as_bytes(), w.caller.clone()))
+            .encode(encoder)?;
 
         // verbdef gets encoded as its raw bytes from the flatbuffer
         let verbdef_bytes = self.verbdef.as_bytes().unwrap();
@@ -94,6 +110,11 @@ impl Decode for Activation {
         let verb_name = Symbol::decode(decoder)?;
         let permissions = Obj::decode(decoder)?;
         let command = Option::<ParsedCommand>::decode(decoder)?;
+        let wrapping =
+            Option::<([u8; 16], Var)>::decode(decoder)?.map(|(verb, caller)| WrappedCall {
+                verb: Uuid::from_bytes(verb),
+                caller,
+            });
 
         let verbdef_bytes = Vec::<u8>::decode(decoder)?;
         let verbdef_bytes = Bytes::from(verbdef_bytes);
@@ -108,6 +129,7 @@ impl Decode for Activation {
             verbdef,
             permissions,
             command,
+            wrapping,
         })
     }
 }
@@ -121,6 +143,11 @@ impl<'de> BorrowDecode<'de> for Activation {
         let verb_name = Symbol::decode(decoder)?;
         let permissions = Obj::decode(decoder)?;
         let command = Option::<ParsedCommand>::decode(decoder)?;
+        let wrapping =
+            Option::<([u8; 16], Var)>::decode(decoder)?.map(|(verb, caller)| WrappedCall {
+                verb: Uuid::from_bytes(verb),
+                caller,
+            });
 
         let verbdef_bytes = Vec::<u8>::decode(decoder)?;
         let verbdef_bytes = Bytes::from(verbdef_bytes);
@@ -135,6 +162,7 @@ impl<'de> BorrowDecode<'de> for Activation {
             verbdef,
             permissions,
             command,
+            wrapping,
         })
     }
 }
@@ -297,6 +325,7 @@ impl Activation {
             verbdef: verb_call_request.resolved_verb,
             verb_name: verb_call_request.call.verb_name,
             command: verb_call_request.command.clone(),
+            wrapping: None,
             args: verb_call_request.call.args.clone(),
             permissions: verb_owner,
         }
@@ -336,6 +365,7 @@ impl Activation {
             verbdef,
             verb_name: *EVAL_SYMBOL,
             command: None,
+            wrapping: None,
             args: List::mk_list(&[]),
             permissions,
         }
@@ -372,6 +402,7 @@ impl Activation {
             verbdef,
             verb_name: bf_name,
             command: None,
+            wrapping: None,
             args,
             permissions: NOTHING,
         }
@@ -15,11 +15,13 @@ use std::time::{Duration, SystemTime};
 
 use bincode::{Decode, Encode};
 
+use moor_values::model::HasUuid;
 use moor_values::NOTHING;
-use moor_values::{v_obj, Var};
+use moor_values::{v_empty_map, v_obj, Var};
 use moor_values::{Obj, Symbol};
 
 use crate::vm::activation::{Activation, Frame};
+use crate::vm::ReplayLog;
 use crate::PhantomUnsync;
 use moor_values::tasks::TaskId;
 
@@ -53,6 +55,20 @@ pub struct VMExecState {
     pub(crate) start_time: Option<SystemTime>,
     /// The amount of time the task is allowed to run.
     pub(crate) maximum_time: Option<Duration>,
+    /// Record (or replay) of the nondeterministic inputs this task has observed.
+    pub(crate) replay: ReplayLog,
+    /// If set, the task is being single-stepped, and each step is reported to this player.
+    pub(crate) debugger: Option<Obj>,
+    /// True while the task is parked between steps, rather than inside `suspend()` or `read()`.
+    pub(crate) debug_paused: bool,
+    /// Set when paused at a breakpoint, so that resuming doesn't immediately stop there again.
+    pub(crate) skip_breakpoint: bool,
+    /// Scratch value for `task_local()`/`set_task_local()`. Lives as long as the task, and is
+    /// never written to the database.
+    pub(crate) task_local: Var,
+    /// How many `begin_atomic()` sections are open. While non-zero the task may not suspend, so
+    /// everything it does up to the matching `end_atomic()` lands in the one transaction.
+    pub(crate) atomic_depth: usize,
 
     unsync: PhantomUnsync,
 }
@@ -67,6 +83,12 @@ impl VMExecState {
             max_ticks,
             tick_slice: 0,
             maximum_time: None,
+            replay: ReplayLog::Off,
+            debugger: None,
+            debug_paused: false,
+            skip_breakpoint: false,
+            task_local: v_empty_map(),
+            atomic_depth: 0,
             unsync: Default::default(),
         }
     }
@@ -74,10 +96,18 @@ impl VMExecState {
     /// Return the callers stack, in the format expected by the `callers` built-in function.
     pub(crate) fn callers(&self) -> Vec<Caller> {
         let mut callers_iter = self.stack.iter().rev();
-        callers_iter.next(); // skip the top activation, that's our current frame
+        let mut above = callers_iter.next(); // skip the top activation, that's our current frame
 
         let mut callers = vec![];
         for activation in callers_iter {
+            // A verb wrapper is left out of the callers of the verb it wraps, which it stands in
+            // for the caller of.
+            let wrapped_by = activation.wrapping.as_ref().zip(above);
+            above = Some(activation);
+            if wrapped_by.is_some_and(|(wrapping, wrapped)| wrapping.verb == wrapped.verbdef.uuid())
+            {
+                continue;
+            }
             let verb_name = activation.verb_name;
             let definer = activation.verb_definer();
             let player = activation.player.clone();
@@ -176,13 +206,20 @@ impl VMExecState {
         self.top_mut().frame.set_return_value(v);
     }
 
+    /// How long the task has left in its current allotment, or None if it has no time limit.
     pub(crate) fn time_left(&self) -> Option<Duration> {
         let max_time = self.maximum_time?;
-        let now = SystemTime::now();
-        let elapsed = now
-            .duration_since(self.start_time.expect("No start time for task?"))
-            .unwrap();
+        // If the clock has stepped backwards, count nothing as having elapsed.
+        let elapsed = self
+            .start_time
+            .and_then(|start_time| start_time.elapsed().ok())
+            .unwrap_or_default();
+
+        Some(max_time.saturating_sub(elapsed))
+    }
 
-        max_time.checked_sub(elapsed)
+    /// How many ticks the task has left in its current allotment.
+    pub(crate) fn ticks_left(&self) -> usize {
+        self.max_ticks.saturating_sub(self.tick_count)
     }
 }
@@ -27,6 +27,7 @@ use moor_values::matching::command_parse::ParsedCommand;
 use moor_values::model::VerbDef;
 use moor_values::tasks::{AbortLimitReason, Exception};
 use moor_values::{Error, List, Obj, Symbol, Var};
+pub use replay::ReplayLog;
 pub use vm_call::VerbExecutionRequest;
 pub use vm_unwind::FinallyReason;
 
@@ -37,6 +38,8 @@ use crate::vm::activation::Activation;
 pub(crate) mod activation;
 pub(crate) mod exec_state;
 pub(crate) mod moo_execute;
+pub(crate) mod replay;
+pub(crate) mod tick_costs;
 pub(crate) mod vm_call;
 pub(crate) mod vm_unwind;
 
@@ -109,12 +112,26 @@ pub enum ExecutionResult {
     /// resumed using `resume()` or `kill_task()`.
     TaskSuspend(Option<Duration>),
     /// Request input from the client.
-    TaskNeedInput,
+    TaskNeedInput(InputRequest),
     /// Rollback the current transaction and restart the task in a new transaction.
     /// This can happen when a conflict occurs during execution, independent of a commit.
     TaskRollbackRestart,
 }
 
+/// What a task suspending for input wants from the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputRequest {
+    /// The next line, for `read()`.
+    Line,
+    /// Every line up to (but not including) one equal to `terminator`, for `read_lines()`. The
+    /// scheduler gathers them without waking the task, and hands them over all at once. If
+    /// `max_lines` is given, it's the most the task will accept.
+    Lines {
+        terminator: String,
+        max_lines: Option<usize>,
+    },
+}
+
 /// The set of parameters for a VM-requested fork.
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct Fork {
@@ -146,8 +163,8 @@ pub enum VMHostResponse {
     DispatchFork(Fork),
     /// Tell the task to suspend us.
     Suspend(Option<Duration>),
-    /// Tell the task Johnny 5 needs input from the client (`read` invocation).
-    SuspendNeedInput,
+    /// Tell the task Johnny 5 needs input from the client (`read` or `read_lines` invocation).
+    SuspendNeedInput(InputRequest),
     /// Task timed out or exceeded ticks.
     AbortLimit(AbortLimitReason),
     /// Tell the task that execution has completed, and the task is successful.
@@ -158,4 +175,6 @@ pub enum VMHostResponse {
     CompleteException(Exception),
     /// A rollback-retry was requested.
     RollbackRetry,
+    /// The task is being single-stepped; report the step to the debugger and suspend.
+    DebugStep { debugger: Obj, summary: String },
 }
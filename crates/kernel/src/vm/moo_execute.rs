@@ -11,7 +11,9 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::config::FeaturesConfig;
 use crate::vm::moo_frame::{CatchType, MooStackFrame, ScopeType};
+use crate::vm::tick_costs::TickCosts;
 use crate::vm::vm_unwind::FinallyReason;
 use crate::vm::ExecutionResult;
 use lazy_static::lazy_static;
@@ -63,6 +65,8 @@ pub fn moo_frame_execute(
     permissions: Obj,
     f: &mut MooStackFrame,
     world_state: &mut dyn WorldState,
+    config: &FeaturesConfig,
+    tick_costs: Option<&TickCosts>,
 ) -> ExecutionResult {
     // To avoid borrowing issues when mutating the frame elsewhere...
     let opcodes = f.program.main_vector.clone();
@@ -84,15 +88,17 @@ pub fn moo_frame_execute(
     //  `max_ticks` on the task is the total limit which is checked above us, outside this loop.
     let mut tick_slice_count = 0;
     while tick_slice_count < tick_slice {
-        tick_slice_count += 1;
-        *tick_count += 1;
-
         // Otherwise, start poppin' opcodes.
         // We panic here if we run out of opcodes, as that means there's a bug in either the
         // compiler or in opcode execution.
         let op = &opcodes[f.pc];
         f.pc += 1;
 
+        // Each opcode is a tick, unless configured otherwise.
+        let ticks = tick_costs.map_or(1, |costs| costs.opcode(op));
+        tick_slice_count += ticks;
+        *tick_count += ticks;
+
         match op {
             Op::If(label, environment_width)
             | Op::Eif(label, environment_width)
@@ -423,7 +429,11 @@ pub fn moo_frame_execute(
                 if matches!(divargs[1].variant(), Variant::Int(0) | Variant::Float(0.0)) {
                     return ExecutionResult::PushError(E_DIV);
                 };
-                binary_var_op!(self, f, state, div);
+                if config.floored_division {
+                    binary_var_op!(self, f, state, floor_div);
+                } else {
+                    binary_var_op!(self, f, state, div);
+                }
             }
             Op::Add => {
                 binary_var_op!(self, f, state, add);
@@ -436,7 +446,11 @@ pub fn moo_frame_execute(
                 if matches!(divargs[1].variant(), Variant::Int(0) | Variant::Float(0.0)) {
                     return ExecutionResult::PushError(E_DIV);
                 };
-                binary_var_op!(self, f, state, modulus);
+                if config.floored_division {
+                    binary_var_op!(self, f, state, floor_modulus);
+                } else {
+                    binary_var_op!(self, f, state, modulus);
+                }
             }
             Op::And(label) => {
                 let v = f.peek_top().is_true();
@@ -16,11 +16,14 @@ use bincode::de::{BorrowDecoder, Decoder};
 use bincode::enc::Encoder;
 use bincode::error::{DecodeError, EncodeError};
 use bincode::{BorrowDecode, Decode, Encode};
+use bytes::Bytes;
 use moor_compiler::Name;
 use moor_compiler::{GlobalName, Label, Op, Program};
+use moor_values::model::VerbDef;
 use moor_values::util::{BitArray, Bitset16};
 use moor_values::Error::E_VARNF;
-use moor_values::{v_none, Error, Var};
+use moor_values::{v_none, Error, Obj, Symbol, Var};
+use std::collections::HashMap;
 
 /// The MOO stack-frame specific portions of the activation:
 ///   the value stack, local variables, program, program counter, handler stack, etc.
@@ -46,6 +49,21 @@ pub(crate) struct MooStackFrame {
     /// Scratch space for holding finally-reasons to be popped off the stack when a finally block
     /// is ended.
     pub(crate) finally_stack: Vec<FinallyReason>,
+    /// Inline caches for the verb calls made from this frame, keyed by the position just after
+    /// each call. Not persisted; a restored frame starts without them.
+    pub(crate) call_caches: HashMap<usize, CallSiteCache>,
+}
+
+/// The verb a call site resolved to last time, reused for as long as the call is to the same
+/// place, with the same permissions, and the world state's dispatch generation hasn't moved.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CallSiteCache {
+    pub(crate) location: Obj,
+    pub(crate) verb: Symbol,
+    pub(crate) permissions: Obj,
+    pub(crate) generation: u64,
+    pub(crate) binary: Bytes,
+    pub(crate) verbdef: VerbDef,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
@@ -131,6 +149,7 @@ impl Decode for MooStackFrame {
             temp,
             catch_stack,
             finally_stack,
+            call_caches: HashMap::new(),
         })
     }
 }
@@ -163,6 +182,7 @@ impl<'de> BorrowDecode<'de> for MooStackFrame {
             temp,
             catch_stack,
             finally_stack,
+            call_caches: HashMap::new(),
         })
     }
 }
@@ -181,6 +201,7 @@ impl MooStackFrame {
             scope_stack: Default::default(),
             catch_stack: Default::default(),
             finally_stack: Default::default(),
+            call_caches: Default::default(),
         }
     }
 
@@ -0,0 +1,82 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use std::collections::VecDeque;
+
+use bincode::{Decode, Encode};
+use moor_values::Var;
+
+/// Record of the nondeterministic inputs (clock reads, random numbers, input lines, resumption
+/// values) observed by a task, so that a task's execution can be reproduced exactly.
+///
+/// When recording, every such input is appended to the log as it is produced. When replaying, the
+/// inputs are instead taken from the log, in order; if the log runs out (because the replayed
+/// execution diverged from the recorded one), the real source is consulted again.
+#[derive(Clone, Debug, Default, Encode, Decode)]
+pub enum ReplayLog {
+    #[default]
+    Off,
+    Recording(Vec<Var>),
+    Replaying(VecDeque<Var>),
+}
+
+impl ReplayLog {
+    /// Produce a nondeterministic value, recording or replaying it as appropriate.
+    pub fn observe<F: FnOnce() -> Var>(&mut self, produce: F) -> Var {
+        match self {
+            ReplayLog::Off => produce(),
+            ReplayLog::Recording(log) => {
+                let value = produce();
+                log.push(value.clone());
+                value
+            }
+            ReplayLog::Replaying(log) => log.pop_front().unwrap_or_else(produce),
+        }
+    }
+
+    /// Turn a finished recording into a log which will replay it.
+    pub fn into_replay(self) -> ReplayLog {
+        match self {
+            ReplayLog::Recording(log) => ReplayLog::Replaying(log.into()),
+            other => other,
+        }
+    }
+
+    /// The inputs recorded so far, if recording.
+    pub fn recorded(&self) -> Option<&[Var]> {
+        match self {
+            ReplayLog::Recording(log) => Some(log),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplayLog;
+    use moor_values::v_int;
+
+    #[test]
+    fn test_record_then_replay() {
+        let mut log = ReplayLog::Recording(vec![]);
+        assert_eq!(log.observe(|| v_int(1)), v_int(1));
+        assert_eq!(log.observe(|| v_int(2)), v_int(2));
+        assert_eq!(log.recorded(), Some(&[v_int(1), v_int(2)][..]));
+
+        let mut replay = log.into_replay();
+        assert_eq!(replay.observe(|| v_int(100)), v_int(1));
+        assert_eq!(replay.observe(|| v_int(200)), v_int(2));
+        // Once exhausted, we fall back to the real source.
+        assert_eq!(replay.observe(|| v_int(300)), v_int(300));
+    }
+}
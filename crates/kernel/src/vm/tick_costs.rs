@@ -0,0 +1,211 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! What executing each opcode, and calling each builtin, counts for against a task's tick limit.
+//!
+//! By default every opcode is a tick and builtins cost nothing beyond the opcode which calls them,
+//! as in LambdaMOO. Operators can make some classes of opcode, or some builtins (regular
+//! expression matching, say), count for more, so that tasks which lean on them run out sooner.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use moor_compiler::{BuiltinId, Op, BUILTINS};
+use moor_values::Symbol;
+use strum::{EnumCount, EnumString};
+use tracing::warn;
+
+/// The classes opcodes are costed by, named (in snake case) as in the configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumCount, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum OpClass {
+    /// Pushing literals and variables, and popping and storing them.
+    Stack,
+    Arithmetic,
+    /// Comparisons and logical operators.
+    Comparison,
+    /// Conditionals, jumps, returns and lexical scopes.
+    Branch,
+    /// The top of each loop iteration.
+    Loop,
+    /// Indexing, ranges, and building lists, maps and flyweights.
+    Collection,
+    Property,
+    /// Verb calls, including `pass()`.
+    VerbCall,
+    BuiltinCall,
+    /// Setting up and leaving `try` and `catch` handlers.
+    Exception,
+    Fork,
+}
+
+impl OpClass {
+    pub fn of(op: &Op) -> Self {
+        match op {
+            Op::Push(_)
+            | Op::Put(_)
+            | Op::Pop
+            | Op::PushTemp
+            | Op::PutTemp
+            | Op::Imm(_)
+            | Op::ImmBigInt(_)
+            | Op::ImmFloat(_)
+            | Op::ImmEmptyList
+            | Op::ImmErr(_)
+            | Op::ImmInt(_)
+            | Op::ImmNone
+            | Op::ImmObjid(_) => OpClass::Stack,
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod | Op::Exp | Op::UnaryMinus => {
+                OpClass::Arithmetic
+            }
+            Op::Eq
+            | Op::Ne
+            | Op::Lt
+            | Op::Le
+            | Op::Gt
+            | Op::Ge
+            | Op::In
+            | Op::Not
+            | Op::And(_)
+            | Op::Or(_) => OpClass::Comparison,
+            Op::If(..)
+            | Op::Eif(..)
+            | Op::IfQues(_)
+            | Op::Jump { .. }
+            | Op::Exit { .. }
+            | Op::ExitId(_)
+            | Op::Return
+            | Op::Return0
+            | Op::Done
+            | Op::BeginScope { .. }
+            | Op::EndScope { .. } => OpClass::Branch,
+            Op::While { .. } | Op::WhileId { .. } | Op::ForList { .. } | Op::ForRange { .. } => {
+                OpClass::Loop
+            }
+            Op::Ref
+            | Op::RangeRef
+            | Op::IndexSet
+            | Op::RangeSet
+            | Op::PushRef
+            | Op::Length(_)
+            | Op::ListAddTail
+            | Op::ListAppend
+            | Op::MakeSingletonList
+            | Op::MakeMap
+            | Op::MapInsert
+            | Op::CheckListForSplice
+            | Op::Scatter(_)
+            | Op::MakeFlyweight(_) => OpClass::Collection,
+            Op::GetProp | Op::PushGetProp | Op::PutProp => OpClass::Property,
+            Op::CallVerb | Op::Pass => OpClass::VerbCall,
+            Op::FuncCall { .. } => OpClass::BuiltinCall,
+            Op::PushCatchLabel(_)
+            | Op::TryCatch { .. }
+            | Op::TryExcept { .. }
+            | Op::TryFinally { .. }
+            | Op::EndCatch(_)
+            | Op::EndExcept(_)
+            | Op::EndFinally
+            | Op::FinallyContinue => OpClass::Exception,
+            Op::Fork { .. } => OpClass::Fork,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TickCosts {
+    /// By `OpClass`.
+    opcodes: [usize; OpClass::COUNT],
+    /// Extra, by builtin id.
+    builtins: Vec<usize>,
+}
+
+impl Default for TickCosts {
+    fn default() -> Self {
+        Self {
+            opcodes: [1; OpClass::COUNT],
+            builtins: vec![0; BUILTINS.len()],
+        }
+    }
+}
+
+impl TickCosts {
+    /// The costs configured for opcode classes and builtins, by name, over the defaults. Names
+    /// which aren't of either are ignored, with a warning, as are opcode costs of 0, which would
+    /// let a task loop forever without using up its ticks.
+    pub fn new(opcodes: &BTreeMap<String, usize>, builtins: &BTreeMap<String, usize>) -> Self {
+        let mut costs = Self::default();
+        for (name, ticks) in opcodes {
+            let Ok(class) = OpClass::from_str(name) else {
+                warn!(name, "ignoring tick cost for unknown opcode class");
+                continue;
+            };
+            if *ticks == 0 {
+                warn!(name, "ignoring tick cost of 0 for opcode class");
+                continue;
+            }
+            costs.opcodes[class as usize] = *ticks;
+        }
+        for (name, ticks) in builtins {
+            let Some(id) = BUILTINS.find_builtin(Symbol::mk(name)) else {
+                warn!(name, "ignoring tick cost for unknown builtin");
+                continue;
+            };
+            costs.builtins[id.0 as usize] = *ticks;
+        }
+        costs
+    }
+
+    pub fn opcode(&self, op: &Op) -> usize {
+        self.opcodes[OpClass::of(op) as usize]
+    }
+
+    /// What calling `builtin` counts for on top of the opcode which called it.
+    pub fn builtin(&self, builtin: BuiltinId) -> usize {
+        self.builtins.get(builtin.0 as usize).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moor_compiler::offset_for_builtin;
+
+    #[test]
+    fn test_configured_costs() {
+        let opcodes = BTreeMap::from([
+            ("loop".to_string(), 3),
+            ("stack".to_string(), 0),
+            ("bogus".to_string(), 5),
+        ]);
+        let builtins = BTreeMap::from([("match".to_string(), 10), ("bogus".to_string(), 5)]);
+        let costs = TickCosts::new(&opcodes, &builtins);
+
+        let while_op = Op::While {
+            jump_label: 0.into(),
+            environment_width: 0,
+        };
+        assert_eq!(costs.opcode(&while_op), 3);
+        // A cost of 0 is ignored.
+        assert_eq!(costs.opcode(&Op::Pop), 1);
+        assert_eq!(costs.opcode(&Op::Add), 1);
+        assert_eq!(
+            costs.builtin(BuiltinId(offset_for_builtin("match") as u16)),
+            10
+        );
+        assert_eq!(
+            costs.builtin(BuiltinId(offset_for_builtin("rmatch") as u16)),
+            0
+        );
+    }
+}
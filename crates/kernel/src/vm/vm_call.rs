@@ -11,11 +11,13 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use bytes::Bytes;
 use lazy_static::lazy_static;
 use std::sync::Arc;
-use tracing::trace;
+use tracing::{trace, warn};
 
 use moor_compiler::{to_literal, BuiltinId, Program, BUILTINS};
+use moor_values::model::HasUuid;
 use moor_values::model::VerbDef;
 use moor_values::model::WorldState;
 use moor_values::model::WorldStateError;
@@ -28,8 +30,12 @@ use crate::builtins::{BfCallState, BfErr, BfRet, BuiltinRegistry};
 use crate::config::FeaturesConfig;
 use crate::tasks::sessions::Session;
 use crate::tasks::task_scheduler_client::TaskSchedulerClient;
+use crate::tasks::verb_wrappers::{VerbWrapper, VerbWrappers};
+use crate::tasks::vm_host::VmHost;
 use crate::tasks::VerbCall;
-use crate::vm::activation::{Activation, Frame};
+use crate::vm::activation::{Activation, Frame, WrappedCall};
+use crate::vm::moo_frame::CallSiteCache;
+use crate::vm::tick_costs::TickCosts;
 use crate::vm::vm_unwind::FinallyReason;
 use crate::vm::VMExecState;
 use crate::vm::{ExecutionResult, Fork};
@@ -77,6 +83,11 @@ pub struct VmExecParams {
     pub builtin_registry: Arc<BuiltinRegistry>,
     pub max_stack_depth: usize,
     pub config: FeaturesConfig,
+    /// Verbs to run in place of others, if any are set.
+    pub verb_wrappers: Option<Arc<VerbWrappers>>,
+    /// What opcodes and builtins count for against the task's tick limit, if not one tick per
+    /// opcode.
+    pub tick_costs: Option<Arc<TickCosts>>,
 }
 
 impl VMExecState {
@@ -133,18 +144,38 @@ impl VMExecState {
                 (arguments.clone(), v_obj(prop_val.clone()), prop_val.clone())
             }
         };
-        Ok(self.prepare_call_verb(world_state, location, this, verb, args.clone()))
+        // Waif verbs are named with a leading colon; prefer those when calling on a flyweight.
+        let verb = match target.variant() {
+            Variant::Flyweight(_) if exec_params.config.waif_compat => {
+                let waif_verb = Symbol::mk_case_insensitive(&format!(":{}", verb.as_str()));
+                let perms = self.top().permissions.clone();
+                match world_state.find_method_verb_on(&perms, &location, waif_verb) {
+                    Ok(_) => waif_verb,
+                    Err(_) => verb,
+                }
+            }
+            _ => verb,
+        };
+        Ok(self.prepare_call_verb(
+            world_state,
+            exec_params.verb_wrappers.as_deref(),
+            location,
+            this,
+            verb,
+            args.clone(),
+        ))
     }
 
     fn prepare_call_verb(
         &mut self,
         world_state: &mut dyn WorldState,
+        verb_wrappers: Option<&VerbWrappers>,
         location: Obj,
         this: Var,
         verb_name: Symbol,
         args: List,
     ) -> ExecutionResult {
-        let call = VerbCall {
+        let mut call = VerbCall {
             verb_name,
             location: v_obj(location.clone()),
             this: this.clone(),
@@ -164,7 +195,7 @@ impl VMExecState {
         }
         // Find the callable verb ...
         let (binary, resolved_verb) =
-            match world_state.find_method_verb_on(&self.top().permissions, &location, verb_name) {
+            match self.find_method_verb_cached(world_state, &location, verb_name) {
                 Ok(vi) => vi,
                 Err(WorldStateError::ObjectPermissionDenied) => {
                     return self.push_error(E_PERM);
@@ -184,6 +215,22 @@ impl VMExecState {
                 }
             };
 
+        if let Some(wrapper) =
+            verb_wrappers.and_then(|w| w.find(&resolved_verb.location(), &resolved_verb))
+        {
+            if let Some(result) = self.call_wrapper(world_state, &wrapper, &resolved_verb, &call) {
+                return result;
+            }
+        }
+
+        // A wrapper calling through to the verb it wraps passes on the original caller, so that
+        // the wrapped verb's `caller != this` checks work as they would without it.
+        if let Some(wrapping) = &self.top().wrapping {
+            if wrapping.verb == resolved_verb.uuid() {
+                call.caller = wrapping.caller.clone();
+            }
+        }
+
         // Permissions for the activation are the verb's owner.
         let permissions = resolved_verb.owner();
 
@@ -196,6 +243,98 @@ impl VMExecState {
         }
     }
 
+    /// Look up a method verb for a call from the current activation, by way of the inline cache
+    /// for its call site, if it's a MOO frame. Entries hold only while the world state's dispatch
+    /// generation stays the same, which it doesn't across changes to verbs or inheritance.
+    fn find_method_verb_cached(
+        &mut self,
+        world_state: &dyn WorldState,
+        location: &Obj,
+        verb_name: Symbol,
+    ) -> Result<(Bytes, VerbDef), WorldStateError> {
+        let permissions = self.top().permissions.clone();
+        let Frame::Moo(frame) = &mut self.top_mut().frame else {
+            return world_state.find_method_verb_on(&permissions, location, verb_name);
+        };
+        let generation = world_state.dispatch_generation();
+        if let Some(cached) = frame.call_caches.get(&frame.pc) {
+            if cached.generation == generation
+                && cached.verb == verb_name
+                && cached.location.eq(location)
+                && cached.permissions.eq(&permissions)
+            {
+                return Ok((cached.binary.clone(), cached.verbdef.clone()));
+            }
+        }
+        let (binary, verbdef) =
+            world_state.find_method_verb_on(&permissions, location, verb_name)?;
+        frame.call_caches.insert(
+            frame.pc,
+            CallSiteCache {
+                location: location.clone(),
+                verb: verb_name,
+                permissions,
+                generation,
+                binary: binary.clone(),
+                verbdef: verbdef.clone(),
+            },
+        );
+        Ok((binary, verbdef))
+    }
+
+    /// Start the wrapper verb in place of the wrapped one, unless it's the wrapper itself making
+    /// the call, or the wrapper verb can no longer be found (in which case the call goes ahead
+    /// unwrapped).
+    fn call_wrapper(
+        &mut self,
+        world_state: &mut dyn WorldState,
+        wrapper: &VerbWrapper,
+        wrapped_verb: &VerbDef,
+        call: &VerbCall,
+    ) -> Option<ExecutionResult> {
+        let (binary, wrapper_verb) = match world_state.find_method_verb_on(
+            &wrapper.owner,
+            &wrapper.wrapper_location,
+            wrapper.wrapper_verb,
+        ) {
+            Ok(vi) => vi,
+            Err(WorldStateError::RollbackRetry) => {
+                return Some(ExecutionResult::TaskRollbackRestart);
+            }
+            Err(e) => {
+                warn!(
+                    ?e,
+                    wrapper = wrapper.id,
+                    "Unable to find wrapper verb; calling unwrapped"
+                );
+                return None;
+            }
+        };
+        if self.top().verbdef.uuid() == wrapper_verb.uuid() {
+            return None;
+        }
+
+        // The wrapper stands in for the caller, so it runs with the caller's permissions, and the
+        // wrapped verb sees the same caller_perms() it would have without it.
+        let caller_perms = self.top().permissions.clone();
+        let command = self.top().command.clone();
+        let program = VmHost::decode_program(wrapper_verb.binary_type(), binary);
+        self.exec_call_request(VerbExecutionRequest {
+            permissions: caller_perms.clone(),
+            resolved_verb: wrapper_verb,
+            call: call.clone(),
+            command,
+            program,
+        });
+        let top = self.top_mut();
+        top.permissions = caller_perms;
+        top.wrapping = Some(WrappedCall {
+            verb: wrapped_verb.uuid(),
+            caller: call.caller.clone(),
+        });
+        Some(ExecutionResult::More)
+    }
+
     /// Setup the VM to execute the verb of the same current name, but using the parent's
     /// version.
     /// TODO this should be done up in task.rs instead. let's add a new ExecutionResult for it.
@@ -302,6 +441,9 @@ impl VMExecState {
         session: Arc<dyn Session>,
     ) -> ExecutionResult {
         let bf = exec_args.builtin_registry.builtin_for(&bf_id);
+        if let Some(tick_costs) = &exec_args.tick_costs {
+            self.tick_count += tick_costs.builtin(bf_id);
+        }
         let bf_desc = BUILTINS.description_for(bf_id).expect("Builtin not found");
         let bf_name = bf_desc.name;
         trace!(
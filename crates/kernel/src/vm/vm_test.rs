@@ -30,8 +30,11 @@ mod tests {
     use moor_values::{AsByteBuffer, SYSTEM_OBJECT};
 
     use crate::builtins::BuiltinRegistry;
+    use crate::tasks::recording::TaskRecording;
     use crate::tasks::sessions::NoopClientSession;
-    use crate::tasks::vm_test_utils::call_verb;
+    use crate::tasks::vm_test_utils::{call_eval_builtin_replay, call_verb, replay_task};
+    use crate::tasks::TaskStart;
+    use crate::vm::ReplayLog;
     use moor_compiler::Op;
     use moor_compiler::Op::*;
     use moor_compiler::Program;
@@ -458,6 +461,33 @@ mod tests {
         db.new_world_state().unwrap()
     }
 
+    #[test]
+    fn test_call_site_cache_invalidated_by_verb_change() {
+        // The same call site, run twice, with the called verb reprogrammed in between. The second
+        // call must not be answered from the call site's cache.
+        let inner = compile("return 1;", CompileOptions::default()).unwrap();
+        let outer = compile(
+            r#"r = {};
+               for i in [1..2]
+                 r = {@r, this:inner()};
+                 set_verb_code(this, "inner", {"return 2;"});
+               endfor
+               return r;"#,
+            CompileOptions::default(),
+        )
+        .unwrap();
+        let mut state = world_with_test_programs(&[("inner", &inner), ("test", &outer)]);
+        let session = Arc::new(NoopClientSession::new());
+        let result = call_verb(
+            state.as_mut(),
+            session,
+            Arc::new(BuiltinRegistry::new()),
+            "test",
+            List::mk_list(&[]),
+        );
+        assert_eq!(result, Ok(v_list(&[v_int(1), v_int(2)])));
+    }
+
     #[test]
     fn test_assignment_from_range() {
         let program = "x = 1; y = {1,2,3}; x = x + y[2]; return x;";
@@ -1220,4 +1250,77 @@ mod tests {
         );
         assert_eq!(result.unwrap(), v_int(2));
     }
+
+    #[test]
+    fn test_replay_nondeterministic_inputs() {
+        let mut state = world_with_test_program("return 1;");
+        let session = Arc::new(NoopClientSession::new());
+        let builtins = Arc::new(BuiltinRegistry::new());
+        let program = compile(
+            "return {random(), random(1000), time()};",
+            CompileOptions::default(),
+        )
+        .unwrap();
+
+        let (recorded_result, replay_log) = call_eval_builtin_replay(
+            state.as_mut(),
+            session.clone(),
+            builtins.clone(),
+            SYSTEM_OBJECT,
+            program.clone(),
+            ReplayLog::Recording(vec![]),
+        );
+        let recorded_result = recorded_result.unwrap();
+        assert_eq!(replay_log.recorded().map(|r| r.len()), Some(3));
+
+        let (replayed_result, _) = call_eval_builtin_replay(
+            state.as_mut(),
+            session,
+            builtins,
+            SYSTEM_OBJECT,
+            program,
+            replay_log.into_replay(),
+        );
+        assert_eq!(replayed_result.unwrap(), recorded_result);
+    }
+
+    #[test]
+    fn test_replay_task_from_recording() {
+        let mut state = world_with_test_program("return 1;");
+        let session = Arc::new(NoopClientSession::new());
+        let builtins = Arc::new(BuiltinRegistry::new());
+        let program = compile(
+            "return {random(), random(1000), time()};",
+            CompileOptions::default(),
+        )
+        .unwrap();
+
+        let (recorded_result, replay_log) = call_eval_builtin_replay(
+            state.as_mut(),
+            session.clone(),
+            builtins.clone(),
+            SYSTEM_OBJECT,
+            program.clone(),
+            ReplayLog::Recording(vec![]),
+        );
+        let recording = TaskRecording {
+            task_id: 7,
+            player: SYSTEM_OBJECT,
+            perms: SYSTEM_OBJECT,
+            task_start: TaskStart::StartEval {
+                player: SYSTEM_OBJECT,
+                program,
+            },
+            inputs: replay_log.recorded().unwrap().to_vec(),
+        };
+
+        // As the scheduler writes it out, and as it'd be read back in for a test.
+        let dir = std::env::temp_dir().join(format!("moor-replay-{}", std::process::id()));
+        let path = recording.write_to(&dir).unwrap();
+        let recording = TaskRecording::read_from(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let replayed_result = replay_task(state.as_mut(), session, builtins, recording);
+        assert_eq!(replayed_result.unwrap(), recorded_result.unwrap());
+    }
 }
@@ -17,7 +17,7 @@ use moor_values::model::Named;
 use moor_values::model::VerbFlag;
 use moor_values::tasks::Exception;
 use moor_values::NOTHING;
-use moor_values::{v_err, v_int, v_list, v_none, v_obj, v_str, Var};
+use moor_values::{v_err, v_int, v_list, v_none, v_obj, v_str, v_string, Var};
 use moor_values::{Error, ErrorPack};
 use tracing::trace;
 
@@ -35,39 +35,33 @@ pub enum FinallyReason {
 }
 
 impl VMExecState {
-    /// Compose a list of the current stack frames, starting from `start_frame_num` and working
-    /// upwards.
+    /// Compose a list of the current stack frames, from the innermost outwards, in the form
+    /// LambdaMOO gives tracebacks (and `callers()`):
+    ///     {this, verb-name, programmer, verb-loc, player, line-number}
+    /// with builtin function frames as {#-1, name, #-1, #-1, player, 0}.
     fn make_stack_list(activations: &[Activation]) -> Vec<Var> {
         let mut stack_list = vec![];
         for a in activations.iter().rev() {
-            // Produce traceback line for each activation frame and append to stack_list
-            // Should include line numbers (if possible), the name of the currently running verb,
-            // its definer, its location, and the current player, and 'this'.
-            let line_no = match a.frame.find_line_no() {
-                None => v_none(),
-                Some(l) => v_int(l as i64),
-            };
-            // TODO: abstract this a bit further, putting its construction onto the activation/frame
             let traceback_entry = match &a.frame {
                 Frame::Moo(_) => {
                     vec![
                         a.this.clone(),
-                        v_str(a.verbdef.names().join(" ").as_str()),
+                        v_str(a.verb_name.as_str()),
+                        v_obj(a.permissions.clone()),
                         v_obj(a.verb_definer()),
-                        v_obj(a.verb_owner()),
                         v_obj(a.player.clone()),
-                        line_no,
+                        v_int(a.frame.find_line_no().unwrap_or(0) as i64),
                     ]
                 }
                 Frame::Bf(bf_frame) => {
                     let bf_name = BUILTINS.name_of(bf_frame.bf_id).unwrap();
                     vec![
-                        a.this.clone(),
+                        v_obj(NOTHING),
                         v_str(bf_name.as_str()),
                         v_obj(NOTHING),
                         v_obj(NOTHING),
                         v_obj(a.player.clone()),
-                        v_none(),
+                        v_int(0),
                     ]
                 }
             };
@@ -77,42 +71,56 @@ impl VMExecState {
         stack_list
     }
 
-    /// Compose a backtrace list of strings for an error, starting from the current stack frame.
+    /// Compose a backtrace list of strings for an error, starting from the current stack frame,
+    /// worded exactly as LambdaMOO's:
+    ///     #6:foo (this == #7), line 3:  Division by zero
+    ///     ... called from built-in function eval()
+    ///     ... called from #6:bar, line 5
+    ///     (End of traceback)
     fn make_backtrace(activations: &[Activation], raise_msg: &str) -> Vec<Var> {
-        // Walk live activation frames and produce a written representation of a traceback for each
-        // frame.
         let mut backtrace_list = vec![];
-        for (i, a) in activations.iter().rev().enumerate() {
-            let mut pieces = vec![];
-            if i != 0 {
-                pieces.push("... called from ".to_string());
-            }
-            // TODO: abstract this a bit further, putting it onto the frame itself
-            match &a.frame {
-                Frame::Moo(_) => {
-                    pieces.push(format!("{}:{}", a.verb_definer(), a.verb_name));
-                }
-                Frame::Bf(bf_frame) => {
+        let mut innermost = true;
+        for a in activations.iter().rev() {
+            if let Frame::Bf(bf_frame) = &a.frame {
+                // LambdaMOO has no frames of its own for builtins. One which raised the error
+                // goes unmentioned, the error being its caller's; one which called a verb gets
+                // a line between the verb and its own caller.
+                if !innermost {
                     let bf_name = BUILTINS.name_of(bf_frame.bf_id).unwrap();
-                    pieces.push(format!("builtin {bf_name}",));
+                    backtrace_list.push(v_string(format!(
+                        "... called from built-in function {bf_name}()"
+                    )));
                 }
+                continue;
             }
-            if v_obj(a.verb_definer()) != a.this {
-                pieces.push(format!(" (this == {})", to_literal(&a.this)));
+            let mut piece = String::new();
+            if !innermost {
+                piece.push_str("... called from ");
             }
-            if let Some(line_num) = a.frame.find_line_no() {
-                pieces.push(format!(" (line {})", line_num));
+            piece.push_str(&format!("{}:{}", a.verb_definer(), a.verb_name));
+            if v_obj(a.verb_definer()) != a.this {
+                piece.push_str(&format!(" (this == {})", to_literal(&a.this)));
             }
-            if i == 0 {
-                pieces.push(format!(": {}", raise_msg));
+            piece.push_str(&format!(", line {}", a.frame.find_line_no().unwrap_or(0)));
+            if innermost {
+                piece.push_str(&format!(":  {raise_msg}"));
+                innermost = false;
             }
-            let piece = pieces.join("");
-            backtrace_list.push(v_str(&piece))
+            backtrace_list.push(v_string(piece));
         }
         backtrace_list.push(v_str("(End of traceback)"));
         backtrace_list
     }
 
+    /// Throw away the stack of a task which is being aborted, returning the stack list and
+    /// backtrace it had, as an uncaught error would have, with `msg` as the reason.
+    pub(crate) fn unwind_for_abort(&mut self, msg: &str) -> (Vec<Var>, Vec<Var>) {
+        let stack = Self::make_stack_list(&self.stack);
+        let backtrace = Self::make_backtrace(&self.stack, msg);
+        self.stack.clear();
+        (stack, backtrace)
+    }
+
     /// Raise an error.
     /// Finds the catch handler for the given error if there is one, and unwinds the stack to it.
     /// If there is no handler, creates an 'Uncaught' reason with backtrace, and unwinds with that.
@@ -238,15 +246,21 @@ impl VMExecState {
                                 return ExecutionResult::More;
                             }
                             ScopeType::TryCatch(catches) => {
-                                if let FinallyReason::Raise(Exception { code, .. }) = &why {
+                                if let FinallyReason::Raise(exception) = &why {
                                     for catch in catches {
                                         let found = match catch.0 {
                                             CatchType::Any => true,
-                                            CatchType::Errors(e) => e.contains(code),
+                                            CatchType::Errors(e) => e.contains(&exception.code),
                                         };
                                         if found {
                                             frame.jump(&catch.1);
-                                            frame.push(v_list(&[v_err(*code)]));
+                                            // As in LambdaMOO: {code, message, value, traceback}
+                                            frame.push(v_list(&[
+                                                v_err(exception.code),
+                                                v_string(exception.msg.clone()),
+                                                exception.value.clone(),
+                                                v_list(&exception.stack),
+                                            ]));
                                             return ExecutionResult::More;
                                         }
                                     }
@@ -43,12 +43,13 @@ mod test {
         File::open(minimal_db.clone()).unwrap()
     }
 
-    fn load_textdump_file(mut tx: Box<dyn LoaderInterface>, path: &str) {
+    fn load_textdump_file(mut tx: Box<dyn LoaderInterface>, path: &str, import_threads: usize) {
         textdump_load(
             tx.as_mut(),
             PathBuf::from(path),
             Version::new(0, 1, 0),
             FeaturesConfig::default(),
+            import_threads,
         )
         .expect("Could not load textdump");
         assert_eq!(tx.commit().unwrap(), CommitResult::Success);
@@ -199,6 +200,7 @@ mod test {
             minimal_db,
             Version::new(0, 1, 0),
             FeaturesConfig::default(),
+            1,
         )
         .unwrap();
         assert_eq!(tx.commit().unwrap(), CommitResult::Success);
@@ -245,6 +247,7 @@ mod test {
         load_textdump_file(
             db.clone().loader_client().unwrap(),
             minimal_db.to_str().unwrap(),
+            1,
         );
 
         // Read input as string, and compare.
@@ -258,6 +261,31 @@ mod test {
         similar_asserts::assert_eq!(&input, &output, "");
     }
 
+    /// Same as above, but compiling verbs across multiple threads, which should make no
+    /// difference to the resulting database.
+    #[test]
+    fn load_minimal_parallel_then_compare() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let minimal_db = manifest_dir.join("tests/Minimal.db");
+
+        let (db, _) = TxDB::open(None, DatabaseConfig::default());
+        let db = Arc::new(db);
+        load_textdump_file(
+            db.clone().loader_client().unwrap(),
+            minimal_db.to_str().unwrap(),
+            4,
+        );
+
+        let corefile = File::open(minimal_db).unwrap();
+        let br = BufReader::new(corefile);
+        let input = String::from_utf8(br.bytes().map(|b| b.unwrap()).collect())
+            .expect("Failed to convert input to string");
+
+        let output = write_textdump(db, "** LambdaMOO Database, Format Version 1 **");
+
+        similar_asserts::assert_eq!(&input, &output, "");
+    }
+
     #[test]
     // This is an expensive test, so it's not run by default.
     fn load_big_core() {
@@ -269,6 +297,7 @@ mod test {
         load_textdump_file(
             db1.clone().loader_client().unwrap(),
             minimal_db.to_str().unwrap(),
+            1,
         );
     }
 
@@ -286,6 +315,7 @@ mod test {
         load_textdump_file(
             db1.clone().loader_client().unwrap(),
             minimal_db.to_str().unwrap(),
+            1,
         );
 
         let textdump = write_textdump(db1.clone(), "** LambdaMOO Database, Format Version 4 **");
@@ -300,6 +330,7 @@ mod test {
             buffered_string_reader,
             Version::new(0, 1, 0),
             FeaturesConfig::default(),
+            4,
         )
         .unwrap();
         assert_eq!(lc.commit().unwrap(), CommitResult::Success);
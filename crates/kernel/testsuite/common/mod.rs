@@ -53,6 +53,7 @@ pub fn load_textdump(db: &dyn Database) {
         test_db_path(),
         Version::new(0, 1, 0),
         FeaturesConfig::default(),
+        1,
     )
     .expect("Could not load textdump");
     assert_eq!(tx.commit().unwrap(), CommitResult::Success);
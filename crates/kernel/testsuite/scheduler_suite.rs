@@ -0,0 +1,329 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Tests of the scheduler as a whole, running on its own thread over the test database: how it
+//! queues tasks when it's running as many as it's allowed to, and how it shuts down.
+
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use common::create_db;
+use moor_kernel::config::{Config, FeaturesConfig, SchedulerConfig};
+use moor_kernel::tasks::scheduler::Scheduler;
+use moor_kernel::tasks::scheduler_test_utils;
+use moor_kernel::tasks::sessions::{MockClientSession, Session, SessionError, SessionFactory};
+use moor_kernel::tasks::{NoopTasksDb, TaskHandle, TaskResult};
+use moor_kernel::SchedulerClient;
+use moor_values::tasks::{Event, SchedulerError};
+use moor_values::{v_int, v_str, Obj, Var};
+
+mod common;
+
+const WIZARD: Obj = Obj::mk_id(3);
+
+/// Runs for a couple of seconds of wall clock time, holding on to its task slot throughout.
+const BUSY_LOOP: &str = "t = time(); while (time() < t + 2) endwhile";
+
+/// Hands out the same session for every background task, so that tests can see what they were
+/// sent.
+struct SharedSessionFactory(Arc<MockClientSession>);
+impl SessionFactory for SharedSessionFactory {
+    fn mk_background_session(
+        self: Arc<Self>,
+        _player: &Obj,
+    ) -> Result<Arc<dyn Session>, SessionError> {
+        Ok(self.0.clone())
+    }
+}
+
+struct TestScheduler {
+    client: SchedulerClient,
+    /// The session tasks are submitted with.
+    session: Arc<MockClientSession>,
+    /// The session background tasks get, and the system control, which records the shutdown.
+    background: Arc<MockClientSession>,
+    thread: JoinHandle<()>,
+}
+
+impl TestScheduler {
+    fn start(scheduler_config: SchedulerConfig) -> Self {
+        let config = Config {
+            scheduler_config,
+            ..Default::default()
+        };
+        let background = Arc::new(MockClientSession::new());
+        let scheduler = Scheduler::new(
+            semver::Version::new(0, 1, 0),
+            create_db(),
+            Box::new(NoopTasksDb {}),
+            Arc::new(config),
+            background.clone(),
+        );
+        let client = scheduler.client().unwrap();
+        let session_factory = Arc::new(SharedSessionFactory(background.clone()));
+        let thread = std::thread::Builder::new()
+            .name("moor-scheduler".to_string())
+            .spawn(move || scheduler.run(session_factory))
+            .expect("Failed to spawn scheduler");
+        let scheduler = Self {
+            client,
+            session: Arc::new(MockClientSession::new()),
+            background,
+            thread,
+        };
+
+        // Give tasks the time and ticks to run BUSY_LOOP. The options have to be committed before
+        // they're loaded, so that's two tasks.
+        scheduler.eval(
+            r#"add_property(#0, "server_options", create($nothing), {player, "r"});
+               for option in ({"fg_ticks", "bg_ticks"})
+                 add_property($server_options, option, 1000000000000, {player, "r"});
+               endfor
+               for option in ({"fg_seconds", "bg_seconds"})
+                 add_property($server_options, option, 60, {player, "r"});
+               endfor
+               return 1;"#,
+        );
+        scheduler.eval("load_server_options(); return 1;");
+        scheduler
+    }
+
+    /// Run `code` as the wizard, and wait for its result.
+    fn eval(&self, code: &str) -> Var {
+        scheduler_test_utils::call_eval(
+            self.client.clone(),
+            self.session.clone(),
+            &WIZARD,
+            code.to_string(),
+        )
+        .unwrap()
+    }
+
+    /// Submit `code` to run as the wizard, without waiting for it.
+    fn submit(&self, code: &str) -> TaskHandle {
+        self.client
+            .submit_eval_task(
+                &WIZARD,
+                &WIZARD,
+                code.to_string(),
+                self.session.clone(),
+                FeaturesConfig::default(),
+            )
+            .unwrap()
+    }
+
+    fn stop(self) {
+        self.client.submit_shutdown("Test is done").unwrap();
+        self.thread.join().expect("Failed to join() scheduler");
+    }
+}
+
+/// The task's result, if it has one within `timeout`.
+fn result_within(handle: &TaskHandle, timeout: Duration) -> Option<Result<Var, SchedulerError>> {
+    match handle.receiver().recv_timeout(timeout) {
+        Ok(Ok(TaskResult::Result(v))) => Some(Ok(v)),
+        Ok(Ok(TaskResult::Restarted(_))) => panic!("Unexpected task restart"),
+        Ok(Err(e)) => Some(Err(e)),
+        Err(_) => None,
+    }
+}
+
+#[test]
+fn test_tasks_wait_for_a_free_slot() {
+    let scheduler = TestScheduler::start(SchedulerConfig {
+        max_running_tasks: Some(1),
+        ..Default::default()
+    });
+
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} return 1;"));
+    let waiting = scheduler.submit("return 2;");
+
+    // The second task doesn't start until the first is done, and then it does.
+    assert_eq!(result_within(&waiting, Duration::from_millis(500)), None);
+    assert_eq!(
+        result_within(&busy, Duration::from_secs(5)),
+        Some(Ok(v_int(1)))
+    );
+    assert_eq!(
+        result_within(&waiting, Duration::from_secs(1)),
+        Some(Ok(v_int(2)))
+    );
+
+    scheduler.stop();
+}
+
+#[test]
+fn test_tasks_start_without_a_limit() {
+    let scheduler = TestScheduler::start(SchedulerConfig::default());
+
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} return 1;"));
+    let other = scheduler.submit("return 2;");
+    assert_eq!(
+        result_within(&other, Duration::from_secs(1)),
+        Some(Ok(v_int(2)))
+    );
+    assert_eq!(
+        result_within(&busy, Duration::from_secs(5)),
+        Some(Ok(v_int(1)))
+    );
+
+    scheduler.stop();
+}
+
+#[test]
+fn test_killing_a_waiting_task() {
+    let scheduler = TestScheduler::start(SchedulerConfig {
+        max_running_tasks: Some(1),
+        ..Default::default()
+    });
+
+    // The task waiting behind the busy one is the next one submitted, and it's killed before it
+    // gets to start.
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} kill_task(task_id() + 1); return 1;"));
+    let waiting = scheduler.submit("return 2;");
+    assert_eq!(waiting.task_id(), busy.task_id() + 1);
+
+    assert_eq!(
+        result_within(&busy, Duration::from_secs(5)),
+        Some(Ok(v_int(1)))
+    );
+    assert_eq!(
+        result_within(&waiting, Duration::from_secs(1)),
+        Some(Err(SchedulerError::TaskAbortedCancelled))
+    );
+
+    // And the slot it would have had is free.
+    assert_eq!(scheduler.eval("return 3;"), v_int(3));
+
+    scheduler.stop();
+}
+
+#[test]
+fn test_shutdown_cancels_waiting_tasks() {
+    let scheduler = TestScheduler::start(SchedulerConfig {
+        max_running_tasks: Some(1),
+        ..Default::default()
+    });
+
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} return 1;"));
+    let waiting = scheduler.submit("return 2;");
+    assert_eq!(result_within(&waiting, Duration::from_millis(100)), None);
+
+    // Shutting down drains the running task, but the waiting one never starts.
+    scheduler.client.submit_shutdown("Bye").unwrap();
+    assert_eq!(
+        result_within(&waiting, Duration::ZERO),
+        Some(Err(SchedulerError::TaskAbortedCancelled))
+    );
+    assert_eq!(result_within(&busy, Duration::ZERO), Some(Ok(v_int(1))));
+
+    // Nor does anything submitted afterwards.
+    assert!(scheduler
+        .client
+        .submit_eval_task(
+            &WIZARD,
+            &WIZARD,
+            "return 3;".to_string(),
+            scheduler.session.clone(),
+            FeaturesConfig::default(),
+        )
+        .is_err());
+
+    assert!(scheduler
+        .background
+        .system()
+        .contains(&"shutdown".to_string()));
+    scheduler.thread.join().expect("Failed to join() scheduler");
+}
+
+#[test]
+fn test_shutdown_runs_server_shutdown_hook() {
+    let scheduler = TestScheduler::start(SchedulerConfig::default());
+    scheduler.eval(
+        r#"add_verb(#0, {player, "xd", "server_shutdown"}, {"this", "none", "this"});
+           set_verb_code(#0, "server_shutdown", {"notify(#3, \"Going down: \" + args[1]);"});
+           return 1;"#,
+    );
+
+    scheduler.client.submit_shutdown("Bye").unwrap();
+
+    // The hook ran with the shutdown message, through a background session.
+    assert!(scheduler
+        .background
+        .committed()
+        .iter()
+        .any(|e| e.event == Event::Notify(v_str("Going down: Bye"), None)));
+    assert!(scheduler
+        .background
+        .system()
+        .contains(&"shutdown".to_string()));
+    scheduler.thread.join().expect("Failed to join() scheduler");
+}
+
+#[test]
+fn test_shutdown_drains_running_tasks() {
+    let scheduler = TestScheduler::start(SchedulerConfig::default());
+
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} return 1;"));
+    assert_eq!(result_within(&busy, Duration::from_millis(100)), None);
+
+    // The shutdown waits for the running task to finish, and its player is told it's coming.
+    scheduler.client.submit_shutdown("Bye").unwrap();
+    assert_eq!(result_within(&busy, Duration::ZERO), Some(Ok(v_int(1))));
+    assert!(scheduler
+        .session
+        .system()
+        .contains(&"shutdown: Bye".to_string()));
+    scheduler.thread.join().expect("Failed to join() scheduler");
+}
+
+#[test]
+fn test_shutdown_aborts_tasks_still_running() {
+    let scheduler = TestScheduler::start(SchedulerConfig {
+        shutdown_task_timeout: Duration::from_millis(500),
+        ..Default::default()
+    });
+
+    let busy = scheduler.submit("t = time(); while (time() < t + 30) endwhile return 1;");
+    assert_eq!(result_within(&busy, Duration::from_millis(100)), None);
+
+    // Rather than wait for the task, the shutdown aborts it once it's had its time.
+    let started = Instant::now();
+    scheduler.client.submit_shutdown("Bye").unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(matches!(
+        result_within(&busy, Duration::from_secs(1)),
+        Some(Err(_))
+    ));
+    scheduler.thread.join().expect("Failed to join() scheduler");
+}
+
+#[test]
+fn test_shutdown_replies_to_every_request() {
+    let scheduler = TestScheduler::start(SchedulerConfig::default());
+
+    let busy = scheduler.submit(&format!("{BUSY_LOOP} return 1;"));
+    assert_eq!(result_within(&busy, Duration::from_millis(100)), None);
+
+    // A second request while the first is under way waits for the same shutdown.
+    let client = scheduler.client.clone();
+    let first = std::thread::spawn(move || client.submit_shutdown("Bye"));
+    let client = scheduler.client.clone();
+    let second = std::thread::spawn(move || client.submit_shutdown("Bye again"));
+    assert_eq!(first.join().unwrap(), Ok(()));
+    assert_eq!(second.join().unwrap(), Ok(()));
+
+    assert_eq!(result_within(&busy, Duration::ZERO), Some(Ok(v_int(1))));
+    scheduler.thread.join().expect("Failed to join() scheduler");
+}
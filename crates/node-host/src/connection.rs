@@ -192,7 +192,7 @@ pub fn new_connection(mut cx: FunctionContext) -> JsResult<JsPromise> {
         // narrative subscription.
         let mut rpc_client = RpcSendClient::new(rpc_request_sock);
         let (client_token, connection_oid) = match rpc_client
-            .make_client_rpc_call(client_id, ConnectionEstablish(peer_addr.to_string()))
+            .make_client_rpc_call(client_id, ConnectionEstablish(peer_addr.to_string(), 0))
             .await
         {
             Ok(ReplyResult::ClientSuccess(DaemonToClientReply::NewConnection(token, objid))) => {
@@ -285,6 +285,8 @@ pub fn new_connection(mut cx: FunctionContext) -> JsResult<JsPromise> {
                 sender: conn_send.clone(),
             }))
         };
+        // Kept to re-authenticate the connection if its player is switched.
+        let connection = conn_handle.inner.clone();
 
         deferred.settle_with(&channel, move |mut cx| {
             let handle = cx.boxed(conn_handle);
@@ -293,7 +295,7 @@ pub fn new_connection(mut cx: FunctionContext) -> JsResult<JsPromise> {
         });
 
         debug!("Entering connection loop");
-        loop {
+        'connection: loop {
             if kill_switch.load(std::sync::atomic::Ordering::SeqCst) {
                 info!("Kill switch activated, stopping...");
                 break;
@@ -316,148 +318,164 @@ pub fn new_connection(mut cx: FunctionContext) -> JsResult<JsPromise> {
                         }
                     }
                 }
-                Ok(event) = events_recv(client_id.clone(), &mut events_sub) => {
-                    match event {
-                        ClientEvent::SystemMessage(_author, msg) => {
-                            debug!("System message: {}", msg);
-                            let continuation = channel.send(move |mut cx| {
-                                let callback = system_message_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let msg = cx.string(msg);
-                                let msg: Handle<JsValue> = msg.upcast();
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![msg]) else {
-                                    return cx.throw_error("Unable to call system message callback");
-                                };
-                                Ok(system_message_callback)
-                            }).join();
-                            system_message_callback = match continuation {
-                                Ok(continuation) => continuation,
-                                Err(e) => {
-                                    info!("Unable to schedule continuation: {}", e);
-                                    break;
-                                }
-                            };
-                        }
-                        ClientEvent::Narrative(_author, event) => {
-                            debug!("Narrative event: {:?}", event);
-                            let continuation = channel.send(move |mut cx| {
-                                let callback = narrative_event_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let (event, content_type) = match event.event {
-                                    Event::Notify(what, content_type) => {
-                                        let v = match var_to_js_value(&mut cx, &what) {
-                                            Ok(v) => v,
-                                            Err(e) => {
-                                                return cx.throw_error(e.to_string());
-                                            }
-                                        };
-
-                                        let c : Handle<JsValue> = match content_type {
-                                            Some(c) => {
-                                                let c = cx.string(c.as_str());
-                                                c.upcast()
-                                            }
-                                            None => cx.undefined().upcast()
-                                        };
-
-                                        (v, c)
+                Ok(events) = events_recv(client_id.clone(), &mut events_sub) => {
+                    for event in events {
+                        match event {
+                            ClientEvent::SystemMessage(_author, msg) => {
+                                debug!("System message: {}", msg);
+                                let continuation = channel.send(move |mut cx| {
+                                    let callback = system_message_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let msg = cx.string(msg);
+                                    let msg: Handle<JsValue> = msg.upcast();
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![msg]) else {
+                                        return cx.throw_error("Unable to call system message callback");
+                                    };
+                                    Ok(system_message_callback)
+                                }).join();
+                                system_message_callback = match continuation {
+                                    Ok(continuation) => continuation,
+                                    Err(e) => {
+                                        info!("Unable to schedule continuation: {}", e);
+                                        break 'connection;
                                     }
-
-
-                                };
-
-                                let event: Handle<JsValue> = event.upcast();
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![event, content_type]) else {
-                                    return cx.throw_error("Unable to call narrative event callback");
                                 };
-                                Ok(narrative_event_callback)
-                            }).join();
-                            narrative_event_callback = match continuation {
-                                Ok(continuation) => continuation,
-                                Err(e) => {
-                                    info!("Unable to schedule continuation: {}", e);
-                                    break;
+                            }
+                            ClientEvent::Narrative(_author, event) => {
+                                debug!("Narrative event: {:?}", event);
+                                // Only notifications are passed on to node; it has no presentation API.
+                                if !matches!(event.event, Event::Notify(..)) {
+                                    continue;
                                 }
-                            };
-                        }
-                        ClientEvent::RequestInput(request_id) => {
-                            debug!("Requesting input for request ID: {}", request_id);
-                            // Server is requesting some input back through corelated with `request_id`
-                            let continuation = channel.send(move |mut cx| {
-                                let callback = request_input_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let request_id = cx.string(request_id.to_string());
-                                let request_id: Handle<JsValue> = request_id.upcast();
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![request_id]) else {
-                                    return cx.throw_error("Unable to call request input callback");
+                                let continuation = channel.send(move |mut cx| {
+                                    let callback = narrative_event_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let (event, content_type) = match event.event {
+                                        Event::Notify(what, content_type) => {
+                                            let v = match var_to_js_value(&mut cx, &what) {
+                                                Ok(v) => v,
+                                                Err(e) => {
+                                                    return cx.throw_error(e.to_string());
+                                                }
+                                            };
+
+                                            let c : Handle<JsValue> = match content_type {
+                                                Some(c) => {
+                                                    let c = cx.string(c.as_str());
+                                                    c.upcast()
+                                                }
+                                                None => cx.undefined().upcast()
+                                            };
+
+                                            (v, c)
+                                        }
+                                        Event::Present(_) | Event::Unpresent(_) => unreachable!(),
+                                    };
+
+                                    let event: Handle<JsValue> = event.upcast();
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![event, content_type]) else {
+                                        return cx.throw_error("Unable to call narrative event callback");
+                                    };
+                                    Ok(narrative_event_callback)
+                                }).join();
+                                narrative_event_callback = match continuation {
+                                    Ok(continuation) => continuation,
+                                    Err(e) => {
+                                        info!("Unable to schedule continuation: {}", e);
+                                        break 'connection;
+                                    }
                                 };
-                                Ok(request_input_callback)
-                            }).join();
-                            request_input_callback = match continuation {
-                                Ok(continuation) => continuation,
-                                Err(e) => {
-                                    info!("Unable to schedule continuation: {}", e);
-                                    break;
-                                }
-                            };
-                        }
-                        ClientEvent::Disconnect() => {
-                            debug!("Disconnecting");
-                            channel.send(move |mut cx| {
-                                let callback = disconnect_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![]) else {
-                                    return cx.throw_error("Unable to call disconnect callback");
+                            }
+                            ClientEvent::RequestInput(request_id) => {
+                                debug!("Requesting input for request ID: {}", request_id);
+                                // Server is requesting some input back through corelated with `request_id`
+                                let continuation = channel.send(move |mut cx| {
+                                    let callback = request_input_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let request_id = cx.string(request_id.to_string());
+                                    let request_id: Handle<JsValue> = request_id.upcast();
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![request_id]) else {
+                                        return cx.throw_error("Unable to call request input callback");
+                                    };
+                                    Ok(request_input_callback)
+                                }).join();
+                                request_input_callback = match continuation {
+                                    Ok(continuation) => continuation,
+                                    Err(e) => {
+                                        info!("Unable to schedule continuation: {}", e);
+                                        break 'connection;
+                                    }
                                 };
-                                Ok(disconnect_callback)
-                            });
-                            return;
-                        }
-                        ClientEvent::TaskError(_ti, te) => {
-                            debug!("Task error: {:?}", te);
-                            let continuation = channel.send(move |mut cx| {
-                                let callback = task_error_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let te = te.to_string();
-                                let te = cx.string(te);
-                                let te: Handle<JsValue> = te.upcast();
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![te]) else {
-                                    return cx.throw_error("Unable to call task error callback");
+                            }
+                            ClientEvent::Disconnect() => {
+                                debug!("Disconnecting");
+                                channel.send(move |mut cx| {
+                                    let callback = disconnect_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![]) else {
+                                        return cx.throw_error("Unable to call disconnect callback");
+                                    };
+                                    Ok(disconnect_callback)
+                                });
+                                return;
+                            }
+                            ClientEvent::TaskError(_ti, te) => {
+                                debug!("Task error: {:?}", te);
+                                let continuation = channel.send(move |mut cx| {
+                                    let callback = task_error_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let te = te.to_string();
+                                    let te = cx.string(te);
+                                    let te: Handle<JsValue> = te.upcast();
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![te]) else {
+                                        return cx.throw_error("Unable to call task error callback");
+                                    };
+                                    Ok(task_error_callback)
+                                }).join();
+                                task_error_callback = match continuation {
+                                    Ok(continuation) => continuation,
+                                    Err(e) => {
+                                        info!("Unable to schedule continuation: {}", e);
+                                        break 'connection;
+                                    }
                                 };
-                                Ok(task_error_callback)
-                            }).join();
-                            task_error_callback = match continuation {
-                                Ok(continuation) => continuation,
-                                Err(e) => {
-                                    info!("Unable to schedule continuation: {}", e);
-                                    break;
-                                }
-                            };
-                        }
-                        ClientEvent::TaskSuccess(ti, _result) => {
-                            debug!("Task success");
-                            let continuation = channel.send(move |mut cx| {
-                                let callback = task_success_callback.clone(&mut cx);
-                                let callback = callback.into_inner(&mut cx);
-                                let task_id = cx.number(ti as f64).upcast();
-                                let undefined = cx.undefined();
-                                let Ok(_) = callback.call(&mut cx, undefined, vec![task_id]) else {
-                                    return cx.throw_error("Unable to call task success callback");
+                            }
+                            ClientEvent::TaskSuccess(ti, _result) => {
+                                debug!("Task success");
+                                let continuation = channel.send(move |mut cx| {
+                                    let callback = task_success_callback.clone(&mut cx);
+                                    let callback = callback.into_inner(&mut cx);
+                                    let task_id = cx.number(ti as f64).upcast();
+                                    let undefined = cx.undefined();
+                                    let Ok(_) = callback.call(&mut cx, undefined, vec![task_id]) else {
+                                        return cx.throw_error("Unable to call task success callback");
+                                    };
+                                    Ok(task_success_callback)
+                                }).join();
+                                task_success_callback = match continuation {
+                                    Ok(continuation) => continuation,
+                                    Err(e) => {
+                                        info!("Unable to schedule continuation: {}", e);
+                                        break 'connection;
+                                    }
                                 };
-                                Ok(task_success_callback)
-                            }).join();
-                            task_success_callback = match continuation {
-                                Ok(continuation) => continuation,
-                                Err(e) => {
-                                    info!("Unable to schedule continuation: {}", e);
-                                    break;
-                                }
-                            };
+                            }
+                            ClientEvent::PlayerSwitched { new_player, new_auth_token } => {
+                                debug!("Switched to player {}", new_player);
+                                let mut connection = connection.lock().unwrap();
+                                connection.connection_oid = new_player;
+                                connection.auth_token = Some(new_auth_token);
+                            }
+                            ClientEvent::OutputLimits(limits) => {
+                                // Output goes straight to node's callbacks, which buffer it
+                                // themselves.
+                                debug!("Ignoring output limits {:?}", limits);
+                            }
                         }
                     }
                 }
@@ -128,7 +128,7 @@ pub fn make_listeners_client<'a, C: Context<'a>>(
                         }
                     };
                 }
-                Some(ListenersMessage::AddListener(obj, addr)) => {
+                Some(ListenersMessage::AddListener(obj, addr, _)) => {
                     let continuation = channel
                         .send(move |mut cx| {
                             let callback = add_listener_callback.clone(&mut cx);
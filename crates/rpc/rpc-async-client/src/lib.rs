@@ -15,21 +15,25 @@
 
 use crate::rpc_client::RpcSendClient;
 use rpc_common::{
-    DaemonToHostReply, HostBroadcastEvent, HostToDaemonMessage, HostToken, HostType, ReplyResult,
-    RpcError, HOST_BROADCAST_TOPIC, MOOR_HOST_TOKEN_FOOTER,
+    DaemonToHostReply, HostBroadcastEvent, HostToDaemonMessage, HostToken, HostType, Metric,
+    ReplyResult, RpcError, HOST_BROADCAST_TOPIC, MOOR_HOST_TOKEN_FOOTER,
 };
 use rusty_paseto::prelude::{Footer, Key, Paseto, PasetoAsymmetricPrivateKey, Payload, Public, V4};
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tmq::request;
 use tracing::{error, info, warn};
 
 use crate::pubsub_client::hosts_events_recv;
-pub use listeners::{ListenersClient, ListenersError, ListenersMessage};
+pub use listeners::{
+    ListenerOptions, ListenersClient, ListenersError, ListenersMessage, DEFAULT_CONNECT_TIMEOUT,
+    DEFAULT_OUT_OF_BAND_PREFIX, DEFAULT_OUT_OF_BAND_QUOTE_PREFIX, DEFAULT_TCP_KEEPALIVE,
+};
 
 mod listeners;
+pub mod output;
 pub mod pubsub_client;
 pub mod rpc_client;
 
@@ -65,6 +69,34 @@ pub async fn send_host_to_daemon_msg(
     }
 }
 
+/// Fetch the daemon's performance counters (scheduler totals, queue depths, connection counts).
+pub async fn request_performance_counters(
+    rpc_client: &mut RpcSendClient,
+    host_token: &HostToken,
+) -> Result<(SystemTime, Vec<Metric>), RpcError> {
+    match send_host_to_daemon_msg(
+        rpc_client,
+        host_token,
+        HostToDaemonMessage::RequestPerformanceCounters,
+    )
+    .await?
+    {
+        DaemonToHostReply::PerfCounters(time, metrics) => Ok((time, metrics)),
+        reply => Err(RpcError::UnexpectedReply(format!(
+            "Unexpected reply from daemon: {:?}",
+            reply
+        ))),
+    }
+}
+
+/// Resolve once `kill_switch` has been thrown, for hosts to select on alongside their loops, which
+/// only notice it between events.
+pub async fn until_killed(kill_switch: Arc<AtomicBool>) {
+    while !kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
 /// Start the host session with the daemon, and return the RPC client to use for further
 /// communication.
 pub async fn start_host_session(
@@ -109,6 +141,14 @@ pub async fn start_host_session(
                     reason
                 )));
             }
+            Ok(reply) => {
+                warn!(
+                    "Unexpected reply from daemon to host registration: {:?}",
+                    reply
+                );
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                continue;
+            }
             Err(e) => {
                 warn!("Error communicating with daemon: {} to send host token", e);
                 tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
@@ -161,6 +201,9 @@ pub async fn proces_hosts_events(
                         error!("Daemon has rejected this host: {}. Shutting down.", reason);
                         kill_switch.store(true, std::sync::atomic::Ordering::SeqCst);
                     }
+                    Ok(reply) => {
+                        warn!("Unexpected reply from daemon to ping: {:?}", reply);
+                    }
                     Err(e) => {
                         warn!(
                             "Error communicating with daemon: {} to respond to ping: {:?}",
@@ -173,7 +216,7 @@ pub async fn proces_hosts_events(
                 handler_object,
                 host_type,
                 port,
-                print_messages: _,
+                print_messages,
             } => {
                 if host_type == our_host_type {
                     let listen_addr = format!("{}:{}", listen_address, port);
@@ -189,7 +232,7 @@ pub async fn proces_hosts_events(
                             .parse::<SocketAddr>()
                             .unwrap_or_else(|_| panic!("Unable to parse address: {}", listen_addr));
                         if let Err(e) = listeners
-                            .add_listener(&handler_object, sockaddr_sockaddr)
+                            .add_listener(&handler_object, sockaddr_sockaddr, print_messages)
                             .await
                         {
                             error!("Error starting listener: {}", e);
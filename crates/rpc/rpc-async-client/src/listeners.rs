@@ -13,6 +13,7 @@
 
 use moor_values::Obj;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ListenersError {
@@ -24,6 +25,63 @@ pub enum ListenersError {
     GetListenersFailed,
 }
 
+/// LambdaMOO's default for `$server_options.connect_timeout`.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(300);
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+/// Input lines starting with this are out-of-band commands, per MCP 2.1.
+pub const DEFAULT_OUT_OF_BAND_PREFIX: &str = "#$#";
+/// Input lines starting with this are in-band, even if what follows looks out-of-band.
+pub const DEFAULT_OUT_OF_BAND_QUOTE_PREFIX: &str = "#$\"";
+
+/// How a host treats the connections its listeners accept.
+/// The timeouts are fallbacks, used when the core doesn't set `$server_options.connect_timeout` or
+/// `$server_options.idle_timeout` itself.
+#[derive(Debug, Clone)]
+pub struct ListenerOptions {
+    /// Interval after which to start sending TCP keepalive probes on an idle socket, so that dead
+    /// peers are noticed. `None` leaves keepalive off.
+    pub tcp_keepalive: Option<Duration>,
+    /// How long a connection may sit without logging in before it is dropped.
+    pub connect_timeout: Option<Duration>,
+    /// How long a logged-in connection may go without sending input before it is dropped.
+    pub idle_timeout: Option<Duration>,
+    /// Lines from the client starting with this go to `$do_out_of_band_command` instead of the
+    /// command parser, and in-band output starting with it is quoted.
+    pub out_of_band_prefix: String,
+    /// Lines from the client starting with this are in-band with the prefix removed, letting the
+    /// client send text which would otherwise look out-of-band.
+    pub out_of_band_quote_prefix: String,
+    /// Offer GMCP to clients via telnet option negotiation. Off by default, since clients which
+    /// don't speak telnet would see the negotiation as garbage.
+    pub gmcp: bool,
+    /// Ask clients for their terminal type (MTTS) and offer MXP, to learn what markup they can
+    /// display. Off by default, for the same reason.
+    pub negotiate_capabilities: bool,
+    /// Mark the end of prompts (see `notify()`'s `no_newline`) with telnet GA, or EOR for
+    /// clients which agree to it, so that they can be shown as prompts. Off by default, for the
+    /// same reason.
+    pub prompt_marks: bool,
+    /// Print `*** Connected ***` and the server's other messages to connections. Set per listener,
+    /// from the print-messages argument to `listen()`.
+    pub print_messages: bool,
+}
+
+impl Default for ListenerOptions {
+    fn default() -> Self {
+        Self {
+            tcp_keepalive: Some(DEFAULT_TCP_KEEPALIVE),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            idle_timeout: None,
+            out_of_band_prefix: DEFAULT_OUT_OF_BAND_PREFIX.to_string(),
+            out_of_band_quote_prefix: DEFAULT_OUT_OF_BAND_QUOTE_PREFIX.to_string(),
+            gmcp: false,
+            negotiate_capabilities: false,
+            prompt_marks: false,
+            print_messages: true,
+        }
+    }
+}
+
 /// A client for talking to a host-specific backend for managing the set of listeners.
 #[derive(Clone)]
 pub struct ListenersClient {
@@ -31,7 +89,8 @@ pub struct ListenersClient {
 }
 
 pub enum ListenersMessage {
-    AddListener(Obj, SocketAddr),
+    /// Listen at the address, for the handler object, printing server messages or not.
+    AddListener(Obj, SocketAddr, bool),
     RemoveListener(SocketAddr),
     GetListeners(tokio::sync::oneshot::Sender<Vec<(Obj, SocketAddr)>>),
 }
@@ -45,9 +104,14 @@ impl ListenersClient {
         &self,
         handler: &Obj,
         addr: SocketAddr,
+        print_messages: bool,
     ) -> Result<(), ListenersError> {
         self.listeners_channel
-            .send(ListenersMessage::AddListener(handler.clone(), addr))
+            .send(ListenersMessage::AddListener(
+                handler.clone(),
+                addr,
+                print_messages,
+            ))
             .await
             .map_err(|_| ListenersError::AddListenerFailed(handler.clone(), addr))?;
         Ok(())
@@ -0,0 +1,257 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Bounded output buffering for client connections.
+//!
+//! A host which wrote to its clients directly would stop reading events for a connection
+//! whenever its client stopped reading, and a slow client would hold up everything the host does
+//! for it. Instead output goes into a buffer which a separate task writes out to the client. The
+//! buffer holds at most `OutputLimits::max_lines` lines; beyond that the connection's
+//! `OutputOverflow` policy decides what gives. Lines thrown away are owned up to, as in
+//! LambdaMOO, with a notice sent once there's room again.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures_util::{Sink, SinkExt};
+use rpc_common::{OutputLimits, OutputOverflow};
+use thiserror::Error;
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// What the client is told when lines of its output were thrown away, in LambdaMOO's words.
+pub fn overflow_notice(lost: usize) -> String {
+    if lost == 1 {
+        ">> Network buffer overflow: 1 line of output to you has been lost <<".to_string()
+    } else {
+        format!(">> Network buffer overflow: {lost} lines of output to you have been lost <<")
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum OutputError {
+    #[error("Output to the client overflowed its buffer")]
+    Overflowed,
+    #[error("Unable to write output to the client: {0}")]
+    WriteFailed(String),
+}
+
+struct State<T> {
+    lines: VecDeque<T>,
+    limits: OutputLimits,
+    /// Lines thrown away since the last notice of it.
+    lost: usize,
+    closing: bool,
+    failed: Option<OutputError>,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Wakes the writer when there's something for it to do.
+    wake: Notify,
+}
+
+/// The sending side of a connection's output buffer. Sending (or feeding, or flushing) only waits
+/// for the line to be buffered, not written; once the writer has failed, or output overflowed
+/// with `OutputOverflow::Disconnect`, everything fails. Closing lets the writer finish with
+/// what's buffered, then close the connection.
+pub struct BufferedOutput<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Send + 'static> BufferedOutput<T> {
+    /// Start writing to `sink` in the background. `notice` makes the line telling the client how
+    /// many lines it lost.
+    pub fn spawn<S, N>(sink: S, limits: OutputLimits, notice: N) -> Self
+    where
+        S: Sink<T> + Unpin + Send + 'static,
+        S::Error: std::fmt::Display,
+        N: Fn(usize) -> T + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                lines: VecDeque::new(),
+                limits,
+                lost: 0,
+                closing: false,
+                failed: None,
+            }),
+            wake: Notify::new(),
+        });
+        tokio::spawn(write_out(sink, shared.clone(), notice));
+        Self { shared }
+    }
+
+    /// Apply the connection's newly set limits to the lines sent from now on.
+    pub fn set_limits(&self, limits: OutputLimits) {
+        self.shared.state.lock().unwrap().limits = limits;
+    }
+}
+
+impl<T> Sink<T> for BufferedOutput<T> {
+    type Error = OutputError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &self.shared.state.lock().unwrap().failed {
+            Some(e) => Poll::Ready(Err(e.clone())),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, line: T) -> Result<(), Self::Error> {
+        let mut state = self.shared.state.lock().unwrap();
+        if let Some(e) = &state.failed {
+            return Err(e.clone());
+        }
+        if state.lines.len() >= state.limits.max_lines {
+            match state.limits.overflow {
+                OutputOverflow::DropOldest => {
+                    state.lines.pop_front();
+                    state.lost += 1;
+                }
+                OutputOverflow::DropNewest => {
+                    state.lost += 1;
+                    return Ok(());
+                }
+                OutputOverflow::Disconnect => {
+                    state.failed = Some(OutputError::Overflowed);
+                    drop(state);
+                    self.shared.wake.notify_one();
+                    return Err(OutputError::Overflowed);
+                }
+            }
+        }
+        state.lines.push_back(line);
+        drop(state);
+        self.shared.wake.notify_one();
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_ready(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.shared.state.lock().unwrap().closing = true;
+        self.shared.wake.notify_one();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Drop for BufferedOutput<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closing = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+async fn write_out<S, T, N>(mut sink: S, shared: Arc<Shared<T>>, notice: N)
+where
+    S: Sink<T> + Unpin,
+    S::Error: std::fmt::Display,
+    N: Fn(usize) -> T,
+{
+    loop {
+        let (lines, closing) = {
+            let mut state = shared.state.lock().unwrap();
+            if state.failed.is_some() {
+                break;
+            }
+            let mut lines = Vec::with_capacity(state.lines.len() + 1);
+            if state.lost > 0 {
+                debug!(lost = state.lost, "Output to slow client lost");
+                lines.push(notice(state.lost));
+                state.lost = 0;
+            }
+            lines.extend(state.lines.drain(..));
+            (lines, state.closing)
+        };
+        if lines.is_empty() {
+            if closing {
+                break;
+            }
+            shared.wake.notified().await;
+            continue;
+        }
+        let mut written = Ok(());
+        for line in lines {
+            written = sink.feed(line).await;
+            if written.is_err() {
+                break;
+            }
+        }
+        if written.is_ok() {
+            written = sink.flush().await;
+        }
+        if let Err(e) = written {
+            shared.state.lock().unwrap().failed = Some(OutputError::WriteFailed(e.to_string()));
+            break;
+        }
+    }
+    let _ = sink.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures_util::StreamExt;
+
+    fn notice(lost: usize) -> String {
+        format!("lost {lost}")
+    }
+
+    /// A client which doesn't read anything until told to.
+    fn stalled_client() -> (mpsc::Sender<String>, mpsc::Receiver<String>) {
+        mpsc::channel(0)
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (sink, client) = stalled_client();
+        let limits = OutputLimits {
+            max_lines: 2,
+            overflow: OutputOverflow::DropOldest,
+        };
+        let mut output = BufferedOutput::spawn(sink, limits, notice);
+        // The writer takes the first line, and stalls writing it.
+        output.send("1".to_string()).await.unwrap();
+        tokio::task::yield_now().await;
+        for line in ["2", "3", "4"] {
+            output.send(line.to_string()).await.unwrap();
+        }
+        output.close().await.unwrap();
+        let received: Vec<_> = client.collect().await;
+        assert_eq!(received, vec!["1", "lost 1", "3", "4"]);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let (sink, _client) = stalled_client();
+        let limits = OutputLimits {
+            max_lines: 1,
+            overflow: OutputOverflow::Disconnect,
+        };
+        let mut output = BufferedOutput::spawn(sink, limits, notice);
+        output.send("1".to_string()).await.unwrap();
+        tokio::task::yield_now().await;
+        output.send("2".to_string()).await.unwrap();
+        assert!(matches!(
+            output.send("3".to_string()).await,
+            Err(OutputError::Overflowed)
+        ));
+        assert!(output.send("4".to_string()).await.is_err());
+    }
+}
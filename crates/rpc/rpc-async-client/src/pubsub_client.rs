@@ -19,24 +19,27 @@ use uuid::Uuid;
 
 use rpc_common::{ClientEvent, ClientsBroadcastEvent, HostBroadcastEvent, RpcError};
 
+/// Receive the next message on the narrative channel, and the events in it, in order. The daemon
+/// sends a client's narrative events in batches, `[client_id, event, event, ...]`; anything else
+/// comes on its own.
 pub async fn events_recv(
     client_id: Uuid,
     subscribe: &mut Subscribe,
-) -> Result<ClientEvent, RpcError> {
+) -> Result<Vec<ClientEvent>, RpcError> {
     let Some(Ok(mut inbound)) = subscribe.next().await else {
         return Err(RpcError::CouldNotReceive(
             "Unable to receive published event".to_string(),
         ));
     };
 
-    // bincode decode the message, and it should be ConnectionEvent
-    if inbound.len() != 2 {
+    // bincode decode the message, and it should be ConnectionEvents
+    if inbound.len() < 2 {
         return Err(RpcError::CouldNotDecode(format!(
             "Unexpected message length: {}",
             inbound.len()
         )));
     }
-    let (Some(received_client_id), Some(event)) = (inbound.pop_front(), inbound.pop_front()) else {
+    let Some(received_client_id) = inbound.pop_front() else {
         return Err(RpcError::CouldNotDecode(
             "Unexpected message format".to_string(),
         ));
@@ -52,12 +55,17 @@ pub async fn events_recv(
         return Err(RpcError::CouldNotDecode("Unexpected client ID".to_string()));
     }
 
-    let decode_result = bincode::decode_from_slice(event.as_ref(), bincode::config::standard());
-    let (msg, _msg_size): (ClientEvent, usize) = decode_result.map_err(|e| {
-        RpcError::CouldNotDecode(format!("Unable to decode published event: {}", e))
-    })?;
-
-    Ok(msg)
+    inbound
+        .iter()
+        .map(|event| {
+            let decode_result =
+                bincode::decode_from_slice(event.as_ref(), bincode::config::standard());
+            let (msg, _msg_size): (ClientEvent, usize) = decode_result.map_err(|e| {
+                RpcError::CouldNotDecode(format!("Unable to decode published event: {}", e))
+            })?;
+            Ok(msg)
+        })
+        .collect()
 }
 
 pub async fn broadcast_recv(subscribe: &mut Subscribe) -> Result<ClientsBroadcastEvent, RpcError> {
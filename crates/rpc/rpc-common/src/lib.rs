@@ -15,8 +15,8 @@ use bincode::{Decode, Encode};
 use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use moor_values::model::ObjectRef;
-use moor_values::tasks::{NarrativeEvent, SchedulerError, VerbProgramError};
-use moor_values::{Obj, Symbol, Var};
+use moor_values::tasks::{NarrativeEvent, Presentation, SchedulerError, VerbProgramError};
+use moor_values::{v_int, v_str, Obj, Symbol, Var, Variant};
 use rusty_paseto::prelude::Key;
 use std::net::SocketAddr;
 use std::path::Path;
@@ -72,7 +72,7 @@ pub enum MessageType {
     HostClientToDaemon(Vec<u8>),
 }
 
-#[derive(Copy, Debug, Eq, PartialEq, Clone, Decode, Encode)]
+#[derive(Copy, Debug, Eq, PartialEq, Clone, Decode, Encode, Hash)]
 pub enum HostType {
     TCP,
     WebSocket,
@@ -105,13 +105,16 @@ pub enum HostToDaemonMessage {
     DetachHost(),
     /// Respond to a host ping request.
     HostPong(SystemTime, HostType, Vec<(Obj, SocketAddr)>),
+    /// Request the daemon's performance counters.
+    RequestPerformanceCounters,
 }
 
 /// An RPC message sent from a host to the daemon on behalf of a client.
 #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
 pub enum HostClientToDaemonMessage {
-    /// Establish a new connection, requesting a client token and a connection object
-    ConnectionEstablish(String),
+    /// Establish a new connection from the given peer, on the listener on the given (local) port,
+    /// requesting a client token and a connection object
+    ConnectionEstablish(String, u16),
     /// Anonymously request a sysprop (e.g. $login.welcome_message)
     RequestSysProp(ClientToken, ObjectRef, Symbol),
     /// Login using the words (e.g. "create player bob" or "connect player bob") and return an
@@ -121,6 +124,9 @@ pub enum HostClientToDaemonMessage {
     /// and a client token -- or None if the auth token is not valid.
     /// If a ConnectType is specified, the user_connected verb will be called.
     Attach(AuthToken, Option<ConnectType>, Obj, String),
+    /// Exchange a still-valid auth token for the player this connection is attached as for a
+    /// new one, with a new expiry.
+    RefreshAuthToken(ClientToken, AuthToken),
     /// Send a command to be executed.
     Command(ClientToken, AuthToken, Obj, String),
     /// Return the (visible) verbs on the given object.
@@ -135,12 +141,27 @@ pub enum HostClientToDaemonMessage {
     Program(ClientToken, AuthToken, ObjectRef, Symbol, Vec<String>),
     /// Respond to a request for input.
     RequestedInput(ClientToken, AuthToken, u128, String),
-    /// Send an "out of band" command to be executed.
-    OutOfBand(ClientToken, AuthToken, Obj, String),
+    /// Send an "out of band" command to be executed. There is no auth token if the connection
+    /// hasn't logged in yet, as is usual for e.g. MCP negotiation.
+    OutOfBand(ClientToken, Option<AuthToken>, Obj, String),
+    /// A GMCP message (package name, and its data decoded from JSON) from the client, to be handed
+    /// to `do_gmcp` on the handler object. As with out of band, there may be no auth token yet.
+    Gmcp(ClientToken, Option<AuthToken>, Obj, String, Var),
+    /// Report (or with `None`, clear) an attribute of the connection, such as a client capability
+    /// the host negotiated, for `connection_attributes()`.
+    SetClientAttribute(ClientToken, Symbol, Option<Var>),
+    /// Return the presentations currently showing for the player, e.g. to restore them on
+    /// reconnect.
+    RequestCurrentPresentations(ClientToken, AuthToken),
+    /// The player closed the presentation with the given id.
+    DismissPresentation(ClientToken, AuthToken, String),
     /// Evaluate a MOO expression.
     Eval(ClientToken, AuthToken, String),
     /// Resolve an object reference into a Var
     Resolve(ClientToken, AuthToken, ObjectRef),
+    /// Return the generation the given object was last changed at, for checking whether a copy
+    /// of it is stale.
+    ObjectGeneration(ClientToken, AuthToken, ObjectRef),
     /// Respond to a client ping request.
     ClientPong(ClientToken, SystemTime, Obj, HostType, SocketAddr),
     /// We're done with this connection, buh-bye.
@@ -204,6 +225,79 @@ pub enum DaemonToHostReply {
     Ack,
     /// The daemon does not like this host for some reason. The host should die.
     Reject(String),
+    /// The daemon's performance counters, in response to RequestPerformanceCounters.
+    PerfCounters(SystemTime, Vec<Metric>),
+}
+
+#[derive(Copy, Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub enum MetricKind {
+    /// A cumulative total which only ever goes up (until restart).
+    Counter,
+    /// A sampled value which can go up or down.
+    Gauge,
+}
+
+/// A single named measurement exported by the daemon, modeled on the Prometheus data model.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct Metric {
+    pub name: String,
+    pub help: String,
+    pub kind: MetricKind,
+    /// (label, value) pairs distinguishing this sample from others of the same name.
+    pub labels: Vec<(String, String)>,
+    pub value: u64,
+}
+
+impl Metric {
+    pub fn new(name: &str, help: &str, kind: MetricKind, value: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            help: help.to_string(),
+            kind,
+            labels: vec![],
+            value,
+        }
+    }
+
+    pub fn with_label(mut self, label: &str, value: &str) -> Self {
+        self.labels.push((label.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Render metrics in the Prometheus text exposition format. Samples sharing a name must be
+/// adjacent, and the first of them supplies the HELP and TYPE lines.
+pub fn metrics_to_prometheus(metrics: &[Metric]) -> String {
+    let mut out = String::new();
+    let mut last_name: Option<&str> = None;
+    for metric in metrics {
+        if last_name != Some(metric.name.as_str()) {
+            let kind = match metric.kind {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+            };
+            out.push_str(&format!("# HELP {} {}\n", metric.name, metric.help));
+            out.push_str(&format!("# TYPE {} {}\n", metric.name, kind));
+            last_name = Some(metric.name.as_str());
+        }
+        out.push_str(&metric.name);
+        if !metric.labels.is_empty() {
+            let labels: Vec<_> = metric
+                .labels
+                .iter()
+                .map(|(label, value)| {
+                    let value = value
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace('\n', "\\n");
+                    format!("{label}=\"{value}\"")
+                })
+                .collect();
+            out.push_str(&format!("{{{}}}", labels.join(",")));
+        }
+        out.push_str(&format!(" {}\n", metric.value));
+    }
+    out
 }
 
 /// An RPC message sent from the daemon to a client on a specific host, in response to a
@@ -225,6 +319,14 @@ pub enum DaemonToClientReply {
     PropertyValue(PropInfo, Var),
     VerbValue(VerbInfo, Vec<String>),
     ResolveResult(Var),
+    ObjectGeneration(Obj, u64),
+    AttributeSet,
+    CurrentPresentations(Vec<Presentation>),
+    PresentationDismissed,
+    AuthTokenRefreshed(AuthToken),
+    /// The input was taken, and the task reading it (in `read_lines()`) wants more: the client
+    /// should send its next line to the same input request, rather than as a command.
+    InputMore(u128),
 }
 
 /// Errors at the message passing level.
@@ -250,6 +352,10 @@ pub enum RpcMessageError {
     EntityRetrievalError(String),
     #[error("Internal error: {0}")]
     InternalError(String),
+    /// The daemon, or the listener, has as many connections as it's allowed. Carries the message
+    /// to show the refused client.
+    #[error("Server full: {0}")]
+    ServerFull(String),
 }
 
 /// Events which occur over the pubsub channel, but destined for specific clients.
@@ -270,6 +376,96 @@ pub enum ClientEvent {
     TaskError(usize, SchedulerError),
     /// Task return common on success that the client can get.
     TaskSuccess(usize, Var),
+    /// The connection now belongs to a different player (e.g. via `switch_player`), and must use
+    /// the given token from now on; the one issued for the previous player is no longer accepted.
+    PlayerSwitched {
+        new_player: Obj,
+        new_auth_token: AuthToken,
+    },
+    /// The connection's `output-limit` or `output-overflow` option has been set, so the host is to
+    /// buffer its output accordingly from now on.
+    OutputLimits(OutputLimits),
+}
+
+/// What a host does with output for a connection whose client isn't reading it as fast as it's
+/// produced, once `OutputLimits::max_lines` are waiting.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Decode, Encode)]
+pub enum OutputOverflow {
+    /// Throw away the oldest waiting line to make room, as LambdaMOO does.
+    DropOldest,
+    /// Throw away the new line.
+    DropNewest,
+    /// Disconnect the client.
+    Disconnect,
+}
+
+impl OutputOverflow {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "drop-oldest" => Some(Self::DropOldest),
+            "drop-newest" => Some(Self::DropNewest),
+            "disconnect" => Some(Self::Disconnect),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DropOldest => "drop-oldest",
+            Self::DropNewest => "drop-newest",
+            Self::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// How much output a host holds for a connection whose client can't keep up with it, and what
+/// happens beyond that. Set per connection through the `output-limit` and `output-overflow`
+/// connection options.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Decode, Encode)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    pub overflow: OutputOverflow,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines: 1024,
+            overflow: OutputOverflow::DropOldest,
+        }
+    }
+}
+
+impl OutputLimits {
+    /// The connection options these are set through.
+    pub const OPTIONS: [&'static str; 2] = ["output-limit", "output-overflow"];
+
+    /// Set the connection option `name` to `value`, or say why it can't be.
+    pub fn set_option(&mut self, name: &str, value: &Var) -> Result<(), String> {
+        match (name, value.variant()) {
+            ("output-limit", Variant::Int(lines)) if *lines > 0 => {
+                self.max_lines = *lines as usize;
+            }
+            ("output-limit", _) => return Err("output-limit must be a positive integer".into()),
+            ("output-overflow", Variant::Str(policy)) => {
+                self.overflow = OutputOverflow::parse(policy.as_string()).ok_or_else(|| {
+                    "output-overflow must be one of drop-oldest, drop-newest or disconnect"
+                        .to_string()
+                })?;
+            }
+            ("output-overflow", _) => return Err("output-overflow must be a string".into()),
+            _ => return Err(format!("Unknown connection option {name}")),
+        }
+        Ok(())
+    }
+
+    /// The connection options' values, by name.
+    pub fn options(&self) -> Vec<(Symbol, Var)> {
+        vec![
+            (Symbol::mk("output-limit"), v_int(self.max_lines as i64)),
+            (Symbol::mk("output-overflow"), v_str(self.overflow.as_str())),
+        ]
+    }
 }
 
 /// Events which occur over the pubsub endpoint, but are for all the hosts.
@@ -332,6 +528,15 @@ pub fn parse_keypair(public_key: &str, private_key: &str) -> Result<(Key<64>, Ke
     Ok((priv_key, pub_key))
 }
 
+/// Load just a public key from the given PEM file, e.g. to verify tokens signed by another daemon.
+pub fn load_public_key(public_key: &Path) -> Result<Key<32>, KeyError> {
+    let pubkey_pem = std::fs::read_to_string(public_key).map_err(KeyError::ReadError)?;
+    let public_key =
+        VerifyingKey::from_public_key_pem(&pubkey_pem).map_err(|_| KeyError::KeyParseError)?;
+    Key::try_from(public_key.to_bytes())
+        .map_err(|_| KeyError::IncorrectKeyFormat("public".to_string()))
+}
+
 /// Load a keypair from the given public and private key (PEM) files.
 pub fn load_keypair(public_key: &Path, private_key: &Path) -> Result<(Key<64>, Key<32>), KeyError> {
     let (Some(pubkey_pem), Some(privkey_pem)) = (
@@ -346,3 +551,46 @@ pub fn load_keypair(public_key: &Path, private_key: &Path) -> Result<(Key<64>, K
 
     parse_keypair(&pubkey_pem, &privkey_pem)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{metrics_to_prometheus, Metric, MetricKind};
+
+    #[test]
+    fn test_prometheus_help_and_type_once_per_name() {
+        let requests = "requests_total";
+        let metrics = vec![
+            Metric::new(requests, "Requests.", MetricKind::Counter, 3).with_label("kind", "a"),
+            Metric::new(requests, "Requests.", MetricKind::Counter, 4).with_label("kind", "b"),
+            Metric::new("in_flight", "Requests in flight.", MetricKind::Gauge, 2),
+        ];
+        assert_eq!(
+            metrics_to_prometheus(&metrics),
+            "# HELP requests_total Requests.\n\
+             # TYPE requests_total counter\n\
+             requests_total{kind=\"a\"} 3\n\
+             requests_total{kind=\"b\"} 4\n\
+             # HELP in_flight Requests in flight.\n\
+             # TYPE in_flight gauge\n\
+             in_flight 2\n"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_label_escaping() {
+        let metric = Metric::new("things", "Things.", MetricKind::Gauge, 1)
+            .with_label("path", "C:\\moor")
+            .with_label("quote", "say \"hi\"")
+            .with_label("lines", "one\ntwo");
+        let rendered = metrics_to_prometheus(&[metric]);
+        assert_eq!(
+            rendered.lines().last(),
+            Some(r#"things{path="C:\\moor",quote="say \"hi\"",lines="one\ntwo"} 1"#)
+        );
+    }
+
+    #[test]
+    fn test_prometheus_empty() {
+        assert_eq!(metrics_to_prometheus(&[]), "");
+    }
+}
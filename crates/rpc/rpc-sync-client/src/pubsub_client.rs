@@ -18,8 +18,9 @@ use zmq::Socket;
 
 use rpc_common::{ClientEvent, ClientsBroadcastEvent, RpcError};
 
-/// Blocking receive on the narrative channel, returning a `ConnectionEvent`.
-pub fn events_recv(client_id: Uuid, subscribe: &Socket) -> Result<ClientEvent, RpcError> {
+/// Blocking receive on the narrative channel, returning the `ConnectionEvent`s in the next
+/// message, in order (the daemon batches narrative events).
+pub fn events_recv(client_id: Uuid, subscribe: &Socket) -> Result<Vec<ClientEvent>, RpcError> {
     let Ok(inbound) = subscribe.recv_multipart(0) else {
         return Err(RpcError::CouldNotReceive(
             "Unable to receive narrative message".to_string(),
@@ -27,14 +28,14 @@ pub fn events_recv(client_id: Uuid, subscribe: &Socket) -> Result<ClientEvent, R
     };
 
     // bincode decode the message, and it should be ConnectionEvent
-    if inbound.len() != 2 {
+    if inbound.len() < 2 {
         return Err(RpcError::CouldNotDecode(format!(
             "Unexpected message length: {}",
             inbound.len()
         )));
     }
 
-    let (received_client_id, event) = (&inbound[0], &inbound[1]);
+    let (received_client_id, events) = (&inbound[0], &inbound[1..]);
 
     let Ok(received_client_id) = Uuid::from_slice(received_client_id) else {
         return Err(RpcError::CouldNotDecode(
@@ -46,12 +47,17 @@ pub fn events_recv(client_id: Uuid, subscribe: &Socket) -> Result<ClientEvent, R
         return Err(RpcError::CouldNotDecode("Unexpected client ID".to_string()));
     }
 
-    let decode_result = bincode::decode_from_slice(event.as_ref(), bincode::config::standard());
-    let (msg, _msg_size): (ClientEvent, usize) = decode_result.map_err(|e| {
-        RpcError::CouldNotDecode(format!("Unable to decode narrative message: {}", e))
-    })?;
-
-    Ok(msg)
+    events
+        .iter()
+        .map(|event| {
+            let decode_result =
+                bincode::decode_from_slice(event.as_ref(), bincode::config::standard());
+            let (msg, _msg_size): (ClientEvent, usize) = decode_result.map_err(|e| {
+                RpcError::CouldNotDecode(format!("Unable to decode narrative message: {}", e))
+            })?;
+            Ok(msg)
+        })
+        .collect()
 }
 
 /// Blocking receive on the broadcast channel, returning a `BroadcastEvent`.
@@ -0,0 +1,275 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The configuration file: one table each for the daemon, the telnet host and the web host, whose
+//! keys are the command line options of the standalone binaries, without the leading dashes.
+//!
+//! ```toml
+//! [daemon]
+//! db = "moor.db"
+//! textdump = "JHCore-DEV-2.db"
+//! rich-notify = true
+//!
+//! [telnet]
+//! telnet-port = 8888
+//!
+//! [web]
+//! listen-address = "0.0.0.0:8080"
+//! ```
+//!
+//! Either host can be left out with `enabled = false` in its table.
+
+use std::path::Path;
+
+use clap::{ArgAction, Parser};
+use eyre::{bail, eyre, Report};
+use toml::{Table, Value};
+
+/// Options moor-serve sets itself, to connect the hosts to the daemon.
+const DAEMON_RESERVED: &[&str] = &["rpc-listen", "events-listen"];
+const HOST_RESERVED: &[&str] = &["rpc-address", "events-address", "public-key", "private-key"];
+
+#[derive(Debug)]
+pub(crate) struct ServeConfig {
+    daemon: Table,
+    telnet: Option<Table>,
+    web: Option<Table>,
+}
+
+impl ServeConfig {
+    pub(crate) fn load(path: &Path) -> Result<Self, Report> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("Unable to read config file {path:?}: {e}"))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, Report> {
+        // Hosts which aren't mentioned run with their defaults.
+        let mut config = ServeConfig {
+            daemon: Table::new(),
+            telnet: Some(Table::new()),
+            web: Some(Table::new()),
+        };
+        for (section, value) in text.parse::<Table>()? {
+            let Value::Table(mut options) = value else {
+                bail!("Expected [{section}] to be a table");
+            };
+            let enabled = match options.remove("enabled") {
+                None => true,
+                Some(Value::Boolean(enabled)) => enabled,
+                Some(_) => bail!("{section}.enabled must be true or false"),
+            };
+            match section.as_str() {
+                "daemon" if enabled => config.daemon = options,
+                "daemon" => bail!("The daemon can't be disabled"),
+                "telnet" => config.telnet = enabled.then_some(options),
+                "web" => config.web = enabled.then_some(options),
+                _ => bail!("Unknown section [{section}]; expected [daemon], [telnet] or [web]"),
+            }
+        }
+        Ok(config)
+    }
+
+    pub(crate) fn daemon_args<A: Parser>(&self, rpc: &str, events: &str) -> Result<A, Report> {
+        let mut argv = command_line::<A>("daemon", &self.daemon, DAEMON_RESERVED)?;
+        argv.extend([
+            format!("--rpc-listen={rpc}"),
+            format!("--events-listen={events}"),
+        ]);
+        A::try_parse_from(argv).map_err(|e| eyre!("In [daemon]: {e}"))
+    }
+
+    pub(crate) fn telnet_args<A: Parser>(
+        &self,
+        host: &HostConnection,
+    ) -> Result<Option<A>, Report> {
+        host_args("telnet", self.telnet.as_ref(), host)
+    }
+
+    pub(crate) fn web_args<A: Parser>(&self, host: &HostConnection) -> Result<Option<A>, Report> {
+        host_args("web", self.web.as_ref(), host)
+    }
+}
+
+/// Where the hosts find the daemon, and the keys they share with it.
+pub(crate) struct HostConnection {
+    pub(crate) rpc: String,
+    pub(crate) events: String,
+    pub(crate) public_key: String,
+    pub(crate) private_key: String,
+}
+
+fn host_args<A: Parser>(
+    section: &str,
+    options: Option<&Table>,
+    host: &HostConnection,
+) -> Result<Option<A>, Report> {
+    let Some(options) = options else {
+        return Ok(None);
+    };
+    let mut argv = command_line::<A>(section, options, HOST_RESERVED)?;
+    argv.extend([
+        format!("--rpc-address={}", host.rpc),
+        format!("--events-address={}", host.events),
+        format!("--public-key={}", host.public_key),
+        format!("--private-key={}", host.private_key),
+    ]);
+    let args = A::try_parse_from(argv).map_err(|e| eyre!("In [{section}]: {e}"))?;
+    Ok(Some(args))
+}
+
+/// The command line that gives `A` the options in a config table.
+fn command_line<A: Parser>(
+    section: &str,
+    options: &Table,
+    reserved: &[&str],
+) -> Result<Vec<String>, Report> {
+    let mut command = A::command();
+    command.build();
+    let mut positionals = vec![];
+    let mut flags = vec![];
+    for (key, value) in options {
+        if reserved.contains(&key.as_str()) {
+            bail!("{section}.{key} is set by moor-serve, and can't be configured");
+        }
+        let Some(arg) = command.get_arguments().find(|arg| {
+            arg.get_long() == Some(key.as_str())
+                || (arg.is_positional() && arg.get_id().as_str() == key)
+        }) else {
+            bail!("Unknown option {section}.{key}");
+        };
+        let values = match value {
+            Value::Array(values) => values
+                .iter()
+                .map(|v| scalar(section, key, v))
+                .collect::<Result<Vec<_>, _>>()?,
+            value => vec![scalar(section, key, value)?],
+        };
+        if arg.is_positional() {
+            positionals.extend(values);
+            continue;
+        }
+        let flag = format!("--{key}");
+        if matches!(arg.get_action(), ArgAction::SetTrue) {
+            match value {
+                Value::Boolean(true) => flags.push(flag),
+                Value::Boolean(false) => {}
+                _ => bail!("{section}.{key} must be true or false"),
+            }
+            continue;
+        }
+        if arg.get_num_args().is_some_and(|n| n.max_values() > 1) {
+            flags.push(flag);
+            flags.extend(values);
+        } else {
+            for value in values {
+                flags.push(flag.clone());
+                flags.push(value);
+            }
+        }
+    }
+    let mut argv = vec![format!("moor-{section}")];
+    argv.extend(positionals);
+    argv.extend(flags);
+    Ok(argv)
+}
+
+fn scalar(section: &str, key: &str, value: &Value) -> Result<String, Report> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => bail!("{section}.{key} must be a string, number or boolean, or an array of them"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HostConnection, ServeConfig};
+
+    fn host() -> HostConnection {
+        HostConnection {
+            rpc: "inproc://moor-rpc".to_string(),
+            events: "inproc://moor-events".to_string(),
+            public_key: "public.pem".to_string(),
+            private_key: "private.pem".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_daemon_options() {
+        let config = ServeConfig::parse(
+            r#"
+            [daemon]
+            db = "world.db"
+            textdump = "core.db"
+            rich-notify = true
+            object-id-range = [100, 200]
+            numeric-connection-names = true
+            "#,
+        )
+        .unwrap();
+        let args: moor_daemon::args::Args = config
+            .daemon_args("inproc://moor-rpc", "inproc://moor-events")
+            .unwrap();
+        assert_eq!(args.db_args.db.to_str(), Some("world.db"));
+        assert_eq!(args.db_args.object_id_range, Some(vec![100, 200]));
+        assert!(args.numeric_connection_names);
+        assert_eq!(args.rpc_listen, "inproc://moor-rpc");
+        assert_eq!(args.events_listen, "inproc://moor-events");
+    }
+
+    #[test]
+    fn test_host_options() {
+        let config = ServeConfig::parse(
+            r#"
+            [daemon]
+            db = "world.db"
+
+            [telnet]
+            telnet-port = 7777
+            gmcp = true
+            prompt-marks = false
+
+            [web]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        let telnet: moor_telnet_host::Args = config.telnet_args(&host()).unwrap().unwrap();
+        assert_eq!(telnet.telnet_port, 7777);
+        assert!(telnet.gmcp);
+        assert!(!telnet.prompt_marks);
+        assert_eq!(telnet.client_args.rpc_address, "inproc://moor-rpc");
+        assert_eq!(telnet.client_args.private_key.to_str(), Some("private.pem"));
+        let web: Option<moor_web_host::Args> = config.web_args(&host()).unwrap();
+        assert!(web.is_none());
+    }
+
+    #[test]
+    fn test_bad_options() {
+        let unknown = ServeConfig::parse("[daemon]\ndb = \"world.db\"\nno-such-option = 1\n")
+            .unwrap()
+            .daemon_args::<moor_daemon::args::Args>("rpc", "events");
+        assert!(unknown.is_err());
+
+        let reserved =
+            ServeConfig::parse("[daemon]\ndb = \"world.db\"\nrpc-listen = \"tcp://*:1\"\n")
+                .unwrap()
+                .daemon_args::<moor_daemon::args::Args>("rpc", "events");
+        assert!(reserved.is_err());
+
+        assert!(ServeConfig::parse("[telnet-host]\n").is_err());
+    }
+}
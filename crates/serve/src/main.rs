@@ -0,0 +1,179 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The daemon, telnet host and web host in one process, for small deployments where running and
+//! wiring up three binaries is more trouble than it's worth.
+//!
+//! The daemon and the hosts share one ZMQ context and talk over `inproc://` endpoints, so nothing
+//! but the telnet and web listeners is exposed, and there are no socket files to clean up.
+//!
+//! On SIGINT or SIGTERM, the hosts are stopped first, and then the daemon, so the world gets a
+//! clean shutdown with no new commands coming in. If the world shuts itself down, the hosts are
+//! stopped after it.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use clap_derive::Parser;
+use eyre::Report;
+use moor_daemon::run_daemon_with_context;
+use moor_telnet_host::run_telnet_host_with_context;
+use moor_web_host::run_web_host_with_context;
+use tracing::level_filters::LevelFilter;
+use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+
+use crate::config::{HostConnection, ServeConfig};
+
+mod config;
+
+/// The daemon's endpoints, in the ZMQ context it shares with the hosts.
+const RPC_ENDPOINT: &str = "inproc://moor-rpc";
+const EVENTS_ENDPOINT: &str = "inproc://moor-events";
+
+/// How long the hosts are given to stop before the daemon is stopped regardless.
+const HOST_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(
+        long,
+        value_name = "config",
+        help = "Path to the TOML configuration file, with [daemon], [telnet] and [web] tables",
+        default_value = "moor.toml"
+    )]
+    config: PathBuf,
+
+    #[arg(long, help = "Enable debug logging", default_value = "false")]
+    debug: bool,
+}
+
+fn main() -> Result<(), Report> {
+    color_eyre::install()?;
+    let args: Args = Args::parse();
+
+    // The level is behind a reload layer, so that the daemon's config reloads can change it.
+    let (level_filter, level_handle) = reload::Layer::new(if args.debug {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    });
+    let main_subscriber = tracing_subscriber::registry().with(level_filter).with(
+        tracing_subscriber::fmt::layer()
+            .compact()
+            .with_ansi(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_names(true),
+    );
+    tracing::subscriber::set_global_default(main_subscriber)
+        .expect("Unable to set configure logging");
+    let set_log_level = Box::new(move |level: LevelFilter| {
+        if let Err(e) = level_handle.reload(level) {
+            error!(?e, "Unable to change log level");
+        }
+    });
+
+    let config = ServeConfig::load(&args.config)?;
+
+    let daemon_args: moor_daemon::args::Args = config.daemon_args(RPC_ENDPOINT, EVENTS_ENDPOINT)?;
+    let host = HostConnection {
+        rpc: RPC_ENDPOINT.to_string(),
+        events: EVENTS_ENDPOINT.to_string(),
+        public_key: daemon_args.public_key.display().to_string(),
+        private_key: daemon_args.private_key.display().to_string(),
+    };
+    let telnet_args: Option<moor_telnet_host::Args> = config.telnet_args(&host)?;
+    let web_args: Option<moor_web_host::Args> = config.web_args(&host)?;
+
+    // Signals stop the hosts; the daemon is stopped once they're done.
+    let hosts_kill_switch = Arc::new(AtomicBool::new(false));
+    let daemon_kill_switch = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, hosts_kill_switch.clone())?;
+    signal_hook::flag::register(signal_hook::consts::SIGINT, hosts_kill_switch.clone())?;
+
+    // The IO threads are set before any sockets are made, as ZMQ ignores the setting after that.
+    let zmq_ctx = zmq::Context::new();
+    zmq_ctx.set_io_threads(daemon_args.num_io_threads)?;
+
+    let daemon_thread_kill_switch = daemon_kill_switch.clone();
+    let daemon_ctx = zmq_ctx.clone();
+    let daemon_thread = std::thread::Builder::new()
+        .name("moor-daemon".to_string())
+        .spawn(move || {
+            run_daemon_with_context(
+                daemon_ctx,
+                daemon_args,
+                daemon_thread_kill_switch,
+                set_log_level,
+            )
+        })?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let mut hosts = vec![];
+    if let Some(telnet_args) = telnet_args {
+        hosts.push((
+            "telnet",
+            runtime.spawn(run_telnet_host_with_context(
+                zmq_ctx.clone(),
+                telnet_args,
+                hosts_kill_switch.clone(),
+            )),
+        ));
+    }
+    if let Some(web_args) = web_args {
+        hosts.push((
+            "web",
+            runtime.spawn(run_web_host_with_context(
+                zmq_ctx.clone(),
+                web_args,
+                hosts_kill_switch.clone(),
+            )),
+        ));
+    }
+    info!("moor-serve started");
+
+    while !hosts_kill_switch.load(Ordering::Relaxed)
+        && !daemon_kill_switch.load(Ordering::Relaxed)
+        && !daemon_thread.is_finished()
+    {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    info!("Stopping hosts...");
+    hosts_kill_switch.store(true, Ordering::SeqCst);
+    runtime.block_on(async {
+        for (name, host) in hosts {
+            match tokio::time::timeout(HOST_SHUTDOWN_TIMEOUT, host).await {
+                Ok(Ok(Ok(()))) => info!(host = name, "Host stopped"),
+                Ok(Ok(Err(e))) => error!(host = name, error = ?e, "Host failed"),
+                Ok(Err(e)) => error!(host = name, error = ?e, "Host panicked"),
+                Err(_) => warn!(host = name, "Host didn't stop in time"),
+            }
+        }
+    });
+    runtime.shutdown_timeout(HOST_SHUTDOWN_TIMEOUT);
+
+    info!("Stopping daemon...");
+    daemon_kill_switch.store(true, Ordering::SeqCst);
+    let result = daemon_thread.join().expect("Daemon thread panicked");
+    info!("Done.");
+
+    result
+}
@@ -0,0 +1,87 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! What we've learned about a client's display through terminal type (MTTS) and MXP negotiation,
+//! reported to the daemon as connection attributes.
+
+/// MTTS bits, see https://tintin.mudhalla.net/protocols/mtts/
+const MTTS_ANSI: u32 = 1;
+const MTTS_256_COLORS: u32 = 8;
+const MTTS_TRUECOLOR: u32 = 256;
+
+/// Clients cycle through at most this many terminal types: client name, terminal, MTTS.
+const MAX_TERMINAL_TYPES: usize = 3;
+
+/// Each capability is `None` until the client tells us one way or the other.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ClientCapabilities {
+    pub(crate) ansi: Option<bool>,
+    pub(crate) xterm256: Option<bool>,
+    pub(crate) truecolor: Option<bool>,
+    pub(crate) mxp: Option<bool>,
+    terminal_types: Vec<String>,
+    reported: [Option<bool>; 4],
+}
+
+impl ClientCapabilities {
+    /// Take in a terminal type reported by the client, returning whether to ask for the next one.
+    pub(crate) fn terminal_type(&mut self, name: &str) -> bool {
+        // Clients repeat the last type once they've run out.
+        if self.terminal_types.last().is_some_and(|last| last == name) {
+            return false;
+        }
+        self.terminal_types.push(name.to_string());
+
+        let name = name.to_ascii_uppercase();
+        if let Some(bits) = name
+            .strip_prefix("MTTS ")
+            .and_then(|bits| bits.trim().parse::<u32>().ok())
+        {
+            self.ansi = Some(bits & MTTS_ANSI != 0);
+            self.xterm256 = Some(bits & MTTS_256_COLORS != 0);
+            self.truecolor = Some(bits & MTTS_TRUECOLOR != 0);
+            return false;
+        }
+        // Otherwise, go by what the terminal type name hints at.
+        if name.contains("TRUECOLOR") {
+            self.truecolor = Some(true);
+        }
+        if name.contains("256COLOR") || self.truecolor == Some(true) {
+            self.xterm256 = Some(true);
+        }
+        if name.contains("ANSI")
+            || name.contains("XTERM")
+            || name.contains("VT100")
+            || self.xterm256 == Some(true)
+        {
+            self.ansi = Some(true);
+        }
+        self.terminal_types.len() < MAX_TERMINAL_TYPES
+    }
+
+    /// The capabilities which are now known, and have changed since they were last reported.
+    pub(crate) fn unreported(&mut self) -> Vec<(&'static str, bool)> {
+        let current = [self.ansi, self.xterm256, self.truecolor, self.mxp];
+        let names = ["ansi", "xterm256", "truecolor", "mxp"];
+        let changed = names
+            .iter()
+            .zip(current.iter().zip(self.reported.iter()))
+            .filter_map(|(name, (current, reported))| match current {
+                Some(value) if current != reported => Some((*name, *value)),
+                _ => None,
+            })
+            .collect();
+        self.reported = current;
+        changed
+    }
+}
@@ -14,20 +14,25 @@
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use eyre::bail;
 use eyre::Context;
-use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::stream::SplitStream;
 use futures_util::SinkExt;
 use futures_util::StreamExt;
 use moor_compiler::to_literal;
 use moor_values::model::ObjectRef;
-use moor_values::tasks::{AbortLimitReason, CommandError, Event, SchedulerError, VerbProgramError};
+use moor_values::tasks::{
+    AbortLimitReason, CommandError, Event, SchedulerError, VerbProgramError, CONTENT_TYPE_GMCP,
+    CONTENT_TYPE_OUT_OF_BAND, CONTENT_TYPE_PROMPT,
+};
 use moor_values::util::parse_into_words;
-use moor_values::{Obj, Symbol, Variant};
+use moor_values::{v_bool, Obj, Symbol, Var, Variant};
+use rpc_async_client::output::BufferedOutput;
 use rpc_async_client::pubsub_client::{broadcast_recv, events_recv};
 use rpc_async_client::rpc_client::RpcSendClient;
+use rpc_async_client::ListenerOptions;
 use rpc_common::{
     AuthToken, ClientEvent, ClientToken, ClientsBroadcastEvent, ConnectType, HostType, ReplyResult,
     RpcMessageError, VerbProgramResponse,
@@ -37,16 +42,24 @@ use termimad::MadSkin;
 use tmq::subscribe::Subscribe;
 use tokio::net::TcpStream;
 use tokio::select;
-use tokio_util::codec::{Framed, LinesCodec};
+use tokio::time::{sleep_until, Instant};
+use tokio_util::codec::Framed;
 use tracing::{debug, error, info, trace, warn};
 use uuid::Uuid;
 
-/// Out of band messages are prefixed with this string, e.g. for MCP clients.
-const OUT_OF_BAND_PREFIX: &str = "#$#";
+use crate::capabilities::ClientCapabilities;
+use crate::gmcp::{encode_gmcp, parse_gmcp};
+use crate::telnet::{
+    TelnetCodec, TelnetEvent, TelnetFrame, DO, DONT, EOR, GA, GMCP, MXP, TELOPT_EOR, TTYPE,
+    TTYPE_IS, TTYPE_SEND, WILL, WONT,
+};
 
 // TODO: switch to djot
 const CONTENT_TYPE_MARKDOWN: &str = "text/markdown";
 
+/// How long before an idle disconnect the player is warned that it's coming.
+const IDLE_WARNING: Duration = Duration::from_secs(60);
+
 pub(crate) struct TelnetConnection {
     pub(crate) peer_addr: SocketAddr,
     /// The "handler" object, who is responsible for this connection, defaults to SYSTEM_OBJECT,
@@ -57,9 +70,16 @@ pub(crate) struct TelnetConnection {
     pub(crate) client_id: Uuid,
     /// Current PASETO token.
     pub(crate) client_token: ClientToken,
-    pub(crate) write: SplitSink<Framed<TcpStream, LinesCodec>, String>,
-    pub(crate) read: SplitStream<Framed<TcpStream, LinesCodec>>,
+    /// Output to the client, buffered up to the connection's output limits.
+    pub(crate) write: BufferedOutput<TelnetFrame>,
+    pub(crate) read: SplitStream<Framed<TcpStream, TelnetCodec>>,
     pub(crate) kill_switch: Arc<AtomicBool>,
+    pub(crate) options: ListenerOptions,
+    /// Whether the client has agreed to GMCP.
+    pub(crate) gmcp: bool,
+    /// Whether the client has agreed to prompts being marked with EOR rather than GA.
+    pub(crate) eor: bool,
+    pub(crate) capabilities: ClientCapabilities,
 }
 
 /// The input modes the telnet session can be in.
@@ -73,6 +93,13 @@ enum LineMode {
     SpoolingProgram(String, String),
 }
 
+/// A line of client input, sorted by whether it's for the MOO or for the out-of-band handler.
+#[derive(Debug, PartialEq)]
+enum InputLine {
+    InBand(String),
+    OutOfBand(String),
+}
+
 impl TelnetConnection {
     pub(crate) async fn run(
         &mut self,
@@ -80,6 +107,19 @@ impl TelnetConnection {
         broadcast_sub: &mut Subscribe,
         rpc_client: &mut RpcSendClient,
     ) -> Result<(), eyre::Error> {
+        if self.options.gmcp {
+            self.write.send(TelnetFrame::Negotiate(WILL, GMCP)).await?;
+        }
+        if self.options.prompt_marks {
+            self.write
+                .send(TelnetFrame::Negotiate(WILL, TELOPT_EOR))
+                .await?;
+        }
+        if self.options.negotiate_capabilities {
+            self.write.send(TelnetFrame::Negotiate(DO, TTYPE)).await?;
+            self.write.send(TelnetFrame::Negotiate(WILL, MXP)).await?;
+        }
+
         // Provoke welcome message, which is a login command with no arguments, and we
         // don't care about the reply at this point.
         rpc_client
@@ -95,8 +135,15 @@ impl TelnetConnection {
             .await
             .expect("Unable to send login request to RPC server");
 
+        let connect_timeout = self
+            .server_option_timeout(rpc_client, "connect_timeout", self.options.connect_timeout)
+            .await;
+        let idle_timeout = self
+            .server_option_timeout(rpc_client, "idle_timeout", self.options.idle_timeout)
+            .await;
+
         let Ok((auth_token, player, connect_type)) = self
-            .authorization_phase(events_sub, broadcast_sub, rpc_client)
+            .authorization_phase(events_sub, broadcast_sub, rpc_client, connect_timeout)
             .await
         else {
             bail!("Unable to authorize connection");
@@ -107,11 +154,17 @@ impl TelnetConnection {
             ConnectType::Reconnected => "*** Reconnected ***",
             ConnectType::Created => "*** Created ***",
         };
-        self.write.send(connect_message.to_string()).await?;
+        self.send_server_message(connect_message).await?;
 
         debug!(?player, client_id = ?self.client_id, "Entering command dispatch loop");
         if self
-            .command_loop(auth_token.clone(), events_sub, broadcast_sub, rpc_client)
+            .command_loop(
+                auth_token.clone(),
+                events_sub,
+                broadcast_sub,
+                rpc_client,
+                idle_timeout,
+            )
             .await
             .is_err()
         {
@@ -129,14 +182,105 @@ impl TelnetConnection {
         Ok(())
     }
 
-    async fn output(&mut self, Event::Notify(msg, content_type): Event) -> Result<(), eyre::Error> {
+    /// Look up a timeout (in seconds) in `$server_options`, falling back to the host's own setting
+    /// if the core doesn't define one. Zero or a negative number means no timeout.
+    async fn server_option_timeout(
+        &self,
+        rpc_client: &mut RpcSendClient,
+        option: &str,
+        fallback: Option<Duration>,
+    ) -> Option<Duration> {
+        let response = rpc_client
+            .make_client_rpc_call(
+                self.client_id,
+                HostClientToDaemonMessage::RequestSysProp(
+                    self.client_token.clone(),
+                    ObjectRef::SysObj(vec![Symbol::mk("server_options")]),
+                    Symbol::mk(option),
+                ),
+            )
+            .await;
+        let Ok(ReplyResult::ClientSuccess(DaemonToClientReply::SysPropValue(Some(value)))) =
+            response
+        else {
+            return fallback;
+        };
+        match value.variant() {
+            Variant::Int(secs) if *secs > 0 => Some(Duration::from_secs(*secs as u64)),
+            Variant::Int(_) => None,
+            _ => {
+                warn!(
+                    option,
+                    ?value,
+                    "Ignoring non-integer $server_options timeout"
+                );
+                fallback
+            }
+        }
+    }
+
+    /// Send one of the server's own messages (`*** Connected ***` and so on), unless the listener
+    /// this connection came in on was started with print-messages off.
+    async fn send_server_message(&mut self, message: &str) -> Result<(), eyre::Error> {
+        if self.options.print_messages {
+            self.write.send(message.to_string().into()).await?;
+        }
+        Ok(())
+    }
+
+    async fn output(&mut self, event: Event) -> Result<(), eyre::Error> {
+        let (msg, content_type) = match event {
+            Event::Notify(msg, content_type) => (msg, content_type),
+            // Telnet has nowhere to put presentations.
+            Event::Present(_) | Event::Unpresent(_) => {
+                trace!(?event, "Ignoring presentation event");
+                return Ok(());
+            }
+        };
+        if content_type
+            .as_ref()
+            .is_some_and(|ct| ct.as_str() == CONTENT_TYPE_GMCP)
+        {
+            return self.output_gmcp(&msg).await;
+        }
+
         // Strings output as text lines to the client, otherwise send the
         // literal form (for e.g. lists, objrefs, etc)
+        // Out of band output (e.g. MCP messages from notify_oob()) goes to the client verbatim.
+        // Lines are buffered, and flushed together once the whole event has been written.
+        let out_of_band = content_type
+            .as_ref()
+            .is_some_and(|ct| ct.as_str() == CONTENT_TYPE_OUT_OF_BAND);
+        let prompt = content_type
+            .as_ref()
+            .is_some_and(|ct| ct.as_str() == CONTENT_TYPE_PROMPT);
         match msg.variant() {
+            Variant::Str(msg_text) if out_of_band => {
+                self.write
+                    .feed(msg_text.as_string().clone().into())
+                    .await
+                    .with_context(|| "Unable to send message to client")?;
+            }
+            Variant::Str(msg_text) if prompt => {
+                let text = quote_output(&self.options, msg_text.as_string().clone());
+                self.write
+                    .feed(TelnetFrame::Prompt(text))
+                    .await
+                    .with_context(|| "Unable to send message to client")?;
+                // Without a mark, clients which wait for a line break before showing anything
+                // won't show the prompt until the next line of output.
+                if self.options.prompt_marks {
+                    let mark = if self.eor { EOR } else { GA };
+                    self.write
+                        .feed(TelnetFrame::Command(mark))
+                        .await
+                        .with_context(|| "Unable to send message to client")?;
+                }
+            }
             Variant::Str(msg_text) => {
                 let formatted = output_format(msg_text.as_string(), content_type);
                 self.write
-                    .send(formatted)
+                    .feed(quote_output(&self.options, formatted).into())
                     .await
                     .with_context(|| "Unable to send message to client")?;
             }
@@ -148,30 +292,185 @@ impl TelnetConnection {
                     };
                     let formatted = output_format(line.as_string(), content_type);
                     self.write
-                        .send(formatted)
+                        .feed(quote_output(&self.options, formatted).into())
                         .await
                         .with_context(|| "Unable to send message to client")?;
                 }
             }
             _ => {
                 self.write
-                    .send(to_literal(&msg))
+                    .feed(to_literal(&msg).into())
                     .await
                     .with_context(|| "Unable to send message to client")?;
             }
         }
+        self.write
+            .flush()
+            .await
+            .with_context(|| "Unable to send message to client")?;
+        Ok(())
+    }
+
+    /// Send a `notify_gmcp()` message, `{package, data}`, if the client has agreed to GMCP.
+    async fn output_gmcp(&mut self, msg: &Var) -> Result<(), eyre::Error> {
+        if !self.gmcp {
+            return Ok(());
+        }
+        let message = match msg.variant() {
+            Variant::List(l) => match l.iter().collect::<Vec<_>>().as_slice() {
+                [package, data] => match package.variant() {
+                    Variant::Str(package) => encode_gmcp(package.as_string(), data),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(message) = message else {
+            warn!(?msg, "Unable to encode GMCP message");
+            return Ok(());
+        };
+        self.write
+            .send(TelnetFrame::Subnegotiation(GMCP, message))
+            .await
+            .with_context(|| "Unable to send message to client")?;
+        Ok(())
+    }
+
+    /// Let the daemon know about any newly learned client capabilities, for
+    /// `connection_attributes()`.
+    async fn report_capabilities(
+        &mut self,
+        rpc_client: &mut RpcSendClient,
+    ) -> Result<(), eyre::Error> {
+        for (capability, supported) in self.capabilities.unreported() {
+            let response = rpc_client
+                .make_client_rpc_call(
+                    self.client_id,
+                    HostClientToDaemonMessage::SetClientAttribute(
+                        self.client_token.clone(),
+                        Symbol::mk(capability),
+                        Some(v_bool(supported)),
+                    ),
+                )
+                .await?;
+            if let ReplyResult::Failure(e) = response {
+                warn!(?e, capability, "Unable to report client capability");
+            }
+        }
         Ok(())
     }
 
+    /// Deal with any telnet negotiation or subnegotiation from the client, returning the line of
+    /// input if that's what was received instead.
+    async fn telnet_event(
+        &mut self,
+        event: TelnetEvent,
+        rpc_client: &mut RpcSendClient,
+        auth_token: Option<&AuthToken>,
+    ) -> Result<Option<String>, eyre::Error> {
+        match event {
+            TelnetEvent::Line(line) => return Ok(Some(line)),
+            TelnetEvent::Do(GMCP) if self.options.gmcp => {
+                debug!(client_id = ?self.client_id, "Client agreed to GMCP");
+                self.gmcp = true;
+            }
+            TelnetEvent::Dont(GMCP) => {
+                self.gmcp = false;
+            }
+            TelnetEvent::Do(TELOPT_EOR) if self.options.prompt_marks => {
+                self.eor = true;
+            }
+            TelnetEvent::Dont(TELOPT_EOR) => {
+                self.eor = false;
+            }
+            TelnetEvent::Will(TTYPE) if self.options.negotiate_capabilities => {
+                self.write
+                    .send(TelnetFrame::Subnegotiation(TTYPE, vec![TTYPE_SEND]))
+                    .await?;
+            }
+            TelnetEvent::Subnegotiation(TTYPE, reply) if self.options.negotiate_capabilities => {
+                if let Some((&TTYPE_IS, name)) = reply.split_first() {
+                    let name = String::from_utf8_lossy(name);
+                    debug!(client_id = ?self.client_id, %name, "Client terminal type");
+                    if self.capabilities.terminal_type(&name) {
+                        self.write
+                            .send(TelnetFrame::Subnegotiation(TTYPE, vec![TTYPE_SEND]))
+                            .await?;
+                    }
+                    self.report_capabilities(rpc_client).await?;
+                }
+            }
+            TelnetEvent::Do(MXP) if self.options.negotiate_capabilities => {
+                self.capabilities.mxp = Some(true);
+                // Tell the client to start parsing MXP.
+                self.write
+                    .send(TelnetFrame::Subnegotiation(MXP, vec![]))
+                    .await?;
+                self.report_capabilities(rpc_client).await?;
+            }
+            TelnetEvent::Dont(MXP) if self.options.negotiate_capabilities => {
+                self.capabilities.mxp = Some(false);
+                self.report_capabilities(rpc_client).await?;
+            }
+            // Refuse any other option we're asked to enable, or offered. Refusals aren't answered,
+            // so that we can't end up in a negotiation loop.
+            TelnetEvent::Do(option) => {
+                self.write
+                    .send(TelnetFrame::Negotiate(WONT, option))
+                    .await?;
+            }
+            TelnetEvent::Will(option) => {
+                self.write
+                    .send(TelnetFrame::Negotiate(DONT, option))
+                    .await?;
+            }
+            TelnetEvent::Wont(_) | TelnetEvent::Dont(_) => {}
+            TelnetEvent::Subnegotiation(GMCP, message) if self.gmcp => {
+                let Some((package, data)) = parse_gmcp(&message) else {
+                    warn!(client_id = ?self.client_id, "Invalid GMCP message from client");
+                    return Ok(None);
+                };
+                let response = rpc_client
+                    .make_client_rpc_call(
+                        self.client_id,
+                        HostClientToDaemonMessage::Gmcp(
+                            self.client_token.clone(),
+                            auth_token.cloned(),
+                            self.handler_object.clone(),
+                            package,
+                            data,
+                        ),
+                    )
+                    .await?;
+                if let ReplyResult::Failure(e) = response {
+                    warn!(?e, "GMCP message failed");
+                }
+            }
+            TelnetEvent::Subnegotiation(option, _) => {
+                trace!(option, "Ignoring telnet subnegotiation");
+            }
+        }
+        Ok(None)
+    }
+
     async fn authorization_phase(
         &mut self,
         narrative_sub: &mut Subscribe,
         broadcast_sub: &mut Subscribe,
         rpc_client: &mut RpcSendClient,
+        connect_timeout: Option<Duration>,
     ) -> Result<(AuthToken, Obj, ConnectType), eyre::Error> {
         debug!(client_id = ?self.client_id, "Entering auth loop");
+        let mut deadline = connect_timeout.map(|t| Instant::now() + t);
         loop {
             select! {
+                _ = sleep_until_deadline(deadline) => {
+                    info!(client_id = ?self.client_id, "Timed out waiting for login");
+                    self.send_server_message("*** Timed-out waiting for login. ***").await?;
+                    self.write.close().await?;
+                    bail!("Timed out waiting for login");
+                }
                 Ok(event) = broadcast_recv(broadcast_sub) => {
                     trace!(?event, "broadcast_event");
                     match event {
@@ -181,37 +480,64 @@ impl TelnetConnection {
                         }
                     }
                 }
-                Ok(event) = events_recv(self.client_id, narrative_sub) => {
-                    trace!(?event, "narrative_event");
-                    match event {
-                        ClientEvent::SystemMessage(_author, msg) => {
-                            self.write.send(msg).await.with_context(|| "Unable to send message to client")?;
-                        }
-                        ClientEvent::Narrative(_author, event) => {
-                            self.output(event.event()).await?;
-                        }
-                        ClientEvent::RequestInput(_request_id) => {
-                            bail!("RequestInput before login");
-                        }
-                        ClientEvent::Disconnect() => {
-                            self.write.close().await?;
-                            bail!("Disconnect before login");
-                        }
-                        ClientEvent::TaskError(_ti, te) => {
-                            self.handle_task_error(te).await?;
-                        }
-                        ClientEvent::TaskSuccess(_ti, result) => {
-                            trace!(?result, "TaskSuccess")
-                            // We don't need to do anything with successes.
+                Ok(events) = events_recv(self.client_id, narrative_sub) => {
+                    trace!(?events, "narrative_event");
+                    for event in events {
+                        match event {
+                            ClientEvent::SystemMessage(_author, msg) => {
+                                self.write.send(msg.into()).await.with_context(|| "Unable to send message to client")?;
+                            }
+                            ClientEvent::Narrative(_author, event) => {
+                                self.output(event.event()).await?;
+                            }
+                            ClientEvent::RequestInput(_request_id) => {
+                                bail!("RequestInput before login");
+                            }
+                            ClientEvent::Disconnect() => {
+                                self.write.close().await?;
+                                bail!("Disconnect before login");
+                            }
+                            ClientEvent::TaskError(_ti, te) => {
+                                self.handle_task_error(te).await?;
+                            }
+                            ClientEvent::TaskSuccess(_ti, result) => {
+                                trace!(?result, "TaskSuccess")
+                                // We don't need to do anything with successes.
+                            }
+                            ClientEvent::OutputLimits(limits) => {
+                                self.write.set_limits(limits);
+                            }
+                            ClientEvent::PlayerSwitched { new_player, new_auth_token } => {
+                                // Something (e.g. a guest login verb) has handed this connection a
+                                // player without going through the login command.
+                                info!(player = ?new_player, client_id = ?self.client_id, "Switched to player before login");
+                                self.connection_oid = new_player.clone();
+                                return Ok((new_auth_token, new_player, ConnectType::Connected))
+                            }
                         }
                     }
                 }
                 // Auto loop
-                line = self.read.next() => {
-                    let Some(line) = line else {
+                event = self.read.next() => {
+                    let Some(event) = event else {
                         bail!("Connection closed before login");
                     };
-                    let line = line.unwrap();
+                    let Some(line) = self.telnet_event(event?, rpc_client, None).await? else {
+                        continue;
+                    };
+                    deadline = connect_timeout.map(|t| Instant::now() + t);
+                    let line = match classify_input(&self.options, line) {
+                        InputLine::OutOfBand(line) => {
+                            // Out of band negotiation (e.g. MCP) happens before login, too.
+                            let response = rpc_client.make_client_rpc_call(self.client_id,
+                                HostClientToDaemonMessage::OutOfBand(self.client_token.clone(), None, self.handler_object.clone(), line)).await?;
+                            if let ReplyResult::Failure(e) = response {
+                                warn!(?e, "Out of band message failed");
+                            }
+                            continue;
+                        }
+                        InputLine::InBand(line) => line,
+                    };
                     let words = parse_into_words(&line);
                     let response = rpc_client.make_client_rpc_call(self.client_id,
                         HostClientToDaemonMessage::LoginCommand(self.client_token.clone(), self.handler_object.clone(), words, true)).await.expect("Unable to send login request to RPC server");
@@ -227,24 +553,67 @@ impl TelnetConnection {
 
     async fn command_loop(
         &mut self,
-        auth_token: AuthToken,
+        mut auth_token: AuthToken,
         events_sub: &mut Subscribe,
         broadcast_sub: &mut Subscribe,
         rpc_client: &mut RpcSendClient,
+        idle_timeout: Option<Duration>,
     ) -> Result<(), eyre::Error> {
         let mut line_mode = LineMode::Input;
         let mut program_input = vec![];
+        let mut last_input = Instant::now();
+        let mut warned_idle = false;
+        let idle_warning = idle_timeout
+            .map(|timeout| IDLE_WARNING.min(timeout / 2))
+            .unwrap_or_default();
         loop {
             if self.kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
                 return Ok(());
             }
+            // Warn shortly before the idle disconnect, then disconnect.
+            let idle_deadline = idle_timeout.map(|timeout| {
+                if warned_idle {
+                    last_input + timeout
+                } else {
+                    last_input + timeout - idle_warning
+                }
+            });
             select! {
-                line = self.read.next() => {
-                    let Some(line) = line else {
+                _ = sleep_until_deadline(idle_deadline) => {
+                    if !warned_idle {
+                        self.send_server_message(&format!("*** You have been idle too long, and will be disconnected in {} seconds. ***", idle_warning.as_secs())).await?;
+                        warned_idle = true;
+                        continue;
+                    }
+                    info!(client_id = ?self.client_id, "Disconnecting idle connection");
+                    self.send_server_message("*** Disconnected for inactivity. ***").await?;
+                    self.write.close().await?;
+                    return Ok(());
+                }
+                event = self.read.next() => {
+                    let Some(event) = event else {
                         info!("Connection closed");
                         return Ok(());
                     };
-                    let line = line.unwrap();
+                    let Some(line) = self.telnet_event(event?, rpc_client, Some(&auth_token)).await? else {
+                        continue;
+                    };
+                    last_input = Instant::now();
+                    warned_idle = false;
+
+                    // Out of band lines bypass any pending read() or program spooling, and are
+                    // really just fire and forget.
+                    let line = match classify_input(&self.options, line) {
+                        InputLine::OutOfBand(line) => {
+                            let response = rpc_client.make_client_rpc_call(self.client_id,
+                                HostClientToDaemonMessage::OutOfBand(self.client_token.clone(), Some(auth_token.clone()), self.handler_object.clone(), line)).await?;
+                            if let ReplyResult::Failure(e) = response {
+                                warn!(?e, "Out of band message failed");
+                            }
+                            continue;
+                        }
+                        InputLine::InBand(line) => line,
+                    };
 
                     let response = match line_mode.clone() {
                         LineMode::Input => {
@@ -255,12 +624,12 @@ impl TelnetConnection {
                                 let words = parse_into_words(&line);
                                 let usage_msg = "Usage: .program <target>:<verb>";
                                 if words.len() != 2 {
-                                    self.write.send(usage_msg.to_string()).await?;
+                                    self.write.send(usage_msg.to_string().into()).await?;
                                     continue
                                 }
                                 let verb_spec = words[1].split(':').collect::<Vec<_>>();
                                 if verb_spec.len() != 2 {
-                                    self.write.send(usage_msg.to_string()).await?;
+                                    self.write.send(usage_msg.to_string().into()).await?;
                                     continue
                                 }
                                 let target = verb_spec[0].to_string();
@@ -268,30 +637,24 @@ impl TelnetConnection {
 
                                 // verb must be a valid identifier
                                 if !verb.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                                    self.write.send("You must specify a verb; use the format object:verb.".to_string()).await?;
+                                    self.write.send("You must specify a verb; use the format object:verb.".to_string().into()).await?;
                                     continue
                                 }
 
                                 // target should be a valid object #number, $objref, ident, or
                                 //  a string inside quotes
                                 if !target.starts_with('$') && !target.starts_with('#') && !target.starts_with('"') && !target.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                                    self.write.send("You must specify a target; use the format object:verb.".to_string()).await?;
+                                    self.write.send("You must specify a target; use the format object:verb.".to_string().into()).await?;
                                     continue
                                 }
 
-                                self.write.send(format!("Now programming {}. Use \".\" to end.", words[1])).await?;
+                                self.write.send(format!("Now programming {}. Use \".\" to end.", words[1]).into()).await?;
 
                                 line_mode = LineMode::SpoolingProgram(target, verb);
                                 continue
                             }
 
-                            // If the line begins with the out of band prefix, then send it that way,
-                            // instead. And really just fire and forget.
-                            if line.starts_with(OUT_OF_BAND_PREFIX) {
-                                rpc_client.make_client_rpc_call(self.client_id, HostClientToDaemonMessage::OutOfBand(self.client_token.clone(), auth_token.clone(), self.handler_object.clone(), line)).await?
-                            } else {
-                                rpc_client.make_client_rpc_call(self.client_id, HostClientToDaemonMessage::Command(self.client_token.clone(), auth_token.clone(), self.handler_object.clone(), line)).await?
-                            }
+                            rpc_client.make_client_rpc_call(self.client_id, HostClientToDaemonMessage::Command(self.client_token.clone(), auth_token.clone(), self.handler_object.clone(), line)).await?
                         },
                         // Are we expecting to respond to prompt input? If so, send this through to that, and switch the mode back to input
                         LineMode::WaitingReply(ref input_reply_id) => {
@@ -322,6 +685,10 @@ impl TelnetConnection {
                         ReplyResult::ClientSuccess(DaemonToClientReply::InputThanks) => {
                             // Nothing to do
                         }
+                        ReplyResult::ClientSuccess(DaemonToClientReply::InputMore(input_request_id)) => {
+                            // The task is reading lines up to a terminator; keep sending them to it.
+                            line_mode = LineMode::WaitingReply(input_request_id);
+                        }
                         ReplyResult::HostSuccess(_) => {
                             error!("Unexpected host response to client message!");
                         }
@@ -335,13 +702,13 @@ impl TelnetConnection {
                         ReplyResult::ClientSuccess(DaemonToClientReply::ProgramResponse(resp)) => {
                             match resp {
                                 VerbProgramResponse::Success(o,verb) => {
-                                    self.write.send(format!("0 error(s).\nVerb {} programmed on object {}", verb, o)).await?;
+                                    self.write.send(format!("0 error(s).\nVerb {} programmed on object {}", verb, o).into()).await?;
                                 }
                                 VerbProgramResponse::Failure(VerbProgramError::CompilationError(e)) => {
-                                    self.write.send(format!("{} error(s).\n{}", e.len(), e.join("\n"))).await?;
+                                    self.write.send(format!("{} error(s).\n{}", e.len(), e.join("\n")).into()).await?;
                                 }
                                 VerbProgramResponse::Failure(VerbProgramError::NoVerbToProgram) => {
-                                    self.write.send("That object does not have that verb.".to_string()).await?;
+                                    self.write.send("That object does not have that verb.".to_string().into()).await?;
                                 }
                                 VerbProgramResponse::Failure(e) => {
                                     error!("Unhandled verb program error: {:?}", e);
@@ -364,28 +731,38 @@ impl TelnetConnection {
                         }
                     }
                 }
-                Ok(event) = events_recv(self.client_id, events_sub) => {
-                    match event {
-                        ClientEvent::SystemMessage(_author, msg) => {
-                            self.write.send(msg).await.with_context(|| "Unable to send message to client")?;
-                        }
-                        ClientEvent::Narrative(_author, event) => {
-                            self.output(event.event()).await?;
-                        }
-                        ClientEvent::RequestInput(request_id) => {
-                            // Server is requesting that the next line of input get sent through as a response to this request.
-                            line_mode = LineMode::WaitingReply(request_id);
-                        }
-                        ClientEvent::Disconnect() => {
-                            self.write.send("** Disconnected **".to_string()).await.expect("Unable to send disconnect message to client");
-                            self.write.close().await.expect("Unable to close connection");
-                            return Ok(())
-                        }
-                        ClientEvent::TaskError(_ti, te) => {
-                            self.handle_task_error(te).await?;
-                        }
-                        ClientEvent::TaskSuccess(_ti, _result) => {
-                            // We don't need to do anything with successes.
+                Ok(events) = events_recv(self.client_id, events_sub) => {
+                    for event in events {
+                        match event {
+                            ClientEvent::SystemMessage(_author, msg) => {
+                                self.write.send(msg.into()).await.with_context(|| "Unable to send message to client")?;
+                            }
+                            ClientEvent::Narrative(_author, event) => {
+                                self.output(event.event()).await?;
+                            }
+                            ClientEvent::RequestInput(request_id) => {
+                                // Server is requesting that the next line of input get sent through as a response to this request.
+                                line_mode = LineMode::WaitingReply(request_id);
+                            }
+                            ClientEvent::Disconnect() => {
+                                self.write.send("** Disconnected **".to_string().into()).await.expect("Unable to send disconnect message to client");
+                                self.write.close().await.expect("Unable to close connection");
+                                return Ok(())
+                            }
+                            ClientEvent::TaskError(_ti, te) => {
+                                self.handle_task_error(te).await?;
+                            }
+                            ClientEvent::TaskSuccess(_ti, _result) => {
+                                // We don't need to do anything with successes.
+                            }
+                            ClientEvent::OutputLimits(limits) => {
+                                self.write.set_limits(limits);
+                            }
+                            ClientEvent::PlayerSwitched { new_player, new_auth_token } => {
+                                info!(player = ?new_player, client_id = ?self.client_id, "Switched player");
+                                self.connection_oid = new_player;
+                                auth_token = new_auth_token;
+                            }
                         }
                     }
                 }
@@ -397,50 +774,65 @@ impl TelnetConnection {
         match task_error {
             SchedulerError::CommandExecutionError(CommandError::CouldNotParseCommand) => {
                 self.write
-                    .send("I couldn't understand that.".to_string())
+                    .send("I couldn't understand that.".to_string().into())
                     .await?;
             }
             SchedulerError::CommandExecutionError(CommandError::NoObjectMatch) => {
                 self.write
-                    .send("I don't see that here.".to_string())
+                    .send("I don't see that here.".to_string().into())
                     .await?;
             }
             SchedulerError::CommandExecutionError(CommandError::NoCommandMatch) => {
                 self.write
-                    .send("I couldn't understand that.".to_string())
+                    .send("I couldn't understand that.".to_string().into())
                     .await?;
             }
             SchedulerError::CommandExecutionError(CommandError::PermissionDenied) => {
-                self.write.send("You can't do that.".to_string()).await?;
+                self.write
+                    .send("You can't do that.".to_string().into())
+                    .await?;
             }
             SchedulerError::VerbProgramFailed(VerbProgramError::CompilationError(lines)) => {
                 for line in lines {
-                    self.write.send(line).await?;
+                    self.write.send(line.into()).await?;
                 }
-                self.write.send("Verb not programmed.".to_string()).await?;
+                self.write
+                    .send("Verb not programmed.".to_string().into())
+                    .await?;
             }
             SchedulerError::VerbProgramFailed(VerbProgramError::NoVerbToProgram) => {
                 self.write
-                    .send("That object does not have that verb definition.".to_string())
+                    .send(
+                        "That object does not have that verb definition."
+                            .to_string()
+                            .into(),
+                    )
                     .await?;
             }
             SchedulerError::TaskAbortedLimit(AbortLimitReason::Ticks(_)) => {
-                self.write.send("Task ran out of ticks".to_string()).await?;
+                self.write
+                    .send("Task ran out of ticks".to_string().into())
+                    .await?;
             }
             SchedulerError::TaskAbortedLimit(AbortLimitReason::Time(_)) => {
                 self.write
-                    .send("Task ran out of seconds".to_string())
+                    .send("Task ran out of seconds".to_string().into())
                     .await?;
             }
             SchedulerError::TaskAbortedError => {
-                self.write.send("Task aborted".to_string()).await?;
+                self.write.send("Task aborted".to_string().into()).await?;
             }
             SchedulerError::TaskAbortedException(e) => {
                 // This should not really be happening here... but?
-                self.write.send(format!("Task exception: {}", e)).await?;
+                self.write
+                    .send(format!("Task exception: {}", e).into())
+                    .await?;
             }
             SchedulerError::TaskAbortedCancelled => {
-                self.write.send("Task cancelled".to_string()).await?;
+                self.write.send("Task cancelled".to_string().into()).await?;
+            }
+            SchedulerError::ServerTooBusy => {
+                // The player has already been sent the core's "server too busy" message.
             }
             _ => {
                 warn!(?task_error, "Unhandled unexpected task error");
@@ -450,6 +842,36 @@ impl TelnetConnection {
     }
 }
 
+/// Split client input into in-band and out-of-band lines, per MCP 2.1: lines beginning with the
+/// out-of-band prefix are out-of-band, and lines beginning with the quote prefix are in-band with
+/// the quote prefix stripped.
+fn classify_input(options: &ListenerOptions, line: String) -> InputLine {
+    if let Some(unquoted) = line.strip_prefix(&options.out_of_band_quote_prefix) {
+        return InputLine::InBand(unquoted.to_string());
+    }
+    if line.starts_with(&options.out_of_band_prefix) {
+        return InputLine::OutOfBand(line);
+    }
+    InputLine::InBand(line)
+}
+
+/// Quote in-band output which would otherwise be mistaken by the client for out-of-band.
+fn quote_output(options: &ListenerOptions, line: String) -> String {
+    if line.starts_with(&options.out_of_band_prefix) {
+        format!("{}{}", options.out_of_band_quote_prefix, line)
+    } else {
+        line
+    }
+}
+
+/// Sleep until `deadline`, or forever if there isn't one.
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
 fn markdown_to_ansi(markdown: &str) -> String {
     let skin = MadSkin::default_dark();
     // TODO: permit different text stylings here. e.g. user themes for colours, styling, etc.
@@ -469,3 +891,72 @@ fn output_format(content: &str, content_type: Option<Symbol>) -> String {
         _ => content.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rpc_async_client::ListenerOptions;
+
+    use crate::connection::{classify_input, quote_output, InputLine};
+
+    #[test]
+    fn test_classify_input() {
+        let options = ListenerOptions::default();
+        assert_eq!(
+            classify_input(&options, "look".to_string()),
+            InputLine::InBand("look".to_string())
+        );
+        assert_eq!(
+            classify_input(&options, "#$#mcp version: 2.1".to_string()),
+            InputLine::OutOfBand("#$#mcp version: 2.1".to_string())
+        );
+        // Quoted lines are in-band, without the quote, even if they then look out-of-band.
+        assert_eq!(
+            classify_input(&options, "#$\"#$#not mcp".to_string()),
+            InputLine::InBand("#$#not mcp".to_string())
+        );
+        assert_eq!(
+            classify_input(&options, "say #$#".to_string()),
+            InputLine::InBand("say #$#".to_string())
+        );
+    }
+
+    #[test]
+    fn test_classify_input_other_prefixes() {
+        let options = ListenerOptions {
+            out_of_band_prefix: "@@".to_string(),
+            out_of_band_quote_prefix: "@!".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            classify_input(&options, "@@ping".to_string()),
+            InputLine::OutOfBand("@@ping".to_string())
+        );
+        assert_eq!(
+            classify_input(&options, "@!@@ping".to_string()),
+            InputLine::InBand("@@ping".to_string())
+        );
+        assert_eq!(
+            classify_input(&options, "#$#mcp".to_string()),
+            InputLine::InBand("#$#mcp".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quote_output() {
+        let options = ListenerOptions::default();
+        assert_eq!(
+            quote_output(&options, "Hello".to_string()),
+            "Hello".to_string()
+        );
+        assert_eq!(
+            quote_output(&options, "#$#mcp version: 2.1".to_string()),
+            "#$\"#$#mcp version: 2.1".to_string()
+        );
+        // Which is what classify_input() undoes, should the output be echoed back.
+        let quoted = quote_output(&options, "#$#edit".to_string());
+        assert_eq!(
+            classify_input(&options, quoted),
+            InputLine::InBand("#$#edit".to_string())
+        );
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! GMCP messages are `Package.Name <json>`. Unlike the web host's JSON encoding, which has to
+//! round-trip any MOO value, this maps JSON onto the closest plain MOO values (objects to maps with
+//! string keys, arrays to lists), since that's what both MUD clients and MOO code expect.
+
+use moor_values::{v_float, v_int, v_list, v_map, v_none, v_str, Var, Variant};
+use serde_json::{Map, Number, Value};
+
+/// Split a GMCP message into its package name and its data, which is absent (`v_none()`) if the
+/// message has none. Returns `None` if the data isn't valid JSON.
+pub(crate) fn parse_gmcp(message: &[u8]) -> Option<(String, Var)> {
+    let message = String::from_utf8_lossy(message);
+    let (package, data) = match message.split_once(|c: char| c.is_ascii_whitespace()) {
+        Some((package, data)) => (package, data.trim()),
+        None => (message.as_ref(), ""),
+    };
+    if data.is_empty() {
+        return Some((package.to_string(), v_none()));
+    }
+    let data: Value = serde_json::from_str(data).ok()?;
+    Some((package.to_string(), json_to_var(&data)))
+}
+
+/// Encode a GMCP message. Returns `None` if `data` has no JSON equivalent (e.g. a map with
+/// non-string keys).
+pub(crate) fn encode_gmcp(package: &str, data: &Var) -> Option<Vec<u8>> {
+    let mut message = package.as_bytes().to_vec();
+    if let Variant::None = data.variant() {
+        return Some(message);
+    }
+    message.push(b' ');
+    message.extend(serde_json::to_vec(&var_to_json(data)?).ok()?);
+    Some(message)
+}
+
+fn json_to_var(value: &Value) -> Var {
+    match value {
+        Value::Null => v_none(),
+        Value::Bool(b) => v_int(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => v_int(i),
+            None => v_float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => v_str(s),
+        Value::Array(a) => v_list(&a.iter().map(json_to_var).collect::<Vec<_>>()),
+        Value::Object(o) => v_map(
+            &o.iter()
+                .map(|(k, v)| (v_str(k), json_to_var(v)))
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+fn var_to_json(v: &Var) -> Option<Value> {
+    Some(match v.variant() {
+        Variant::None => Value::Null,
+        Variant::Str(s) => Value::String(s.as_string().clone()),
+        Variant::Obj(o) => Value::String(o.to_string()),
+        Variant::Int(i) => Value::Number(Number::from(*i)),
+        Variant::Float(f) => Value::Number(Number::from_f64(*f)?),
+        Variant::Err(e) => Value::String(e.name().to_string()),
+        Variant::List(l) => Value::Array(l.iter().map(|e| var_to_json(&e)).collect::<Option<_>>()?),
+        Variant::Map(m) => {
+            let mut object = Map::new();
+            for (k, v) in m.iter() {
+                let Variant::Str(k) = k.variant() else {
+                    return None;
+                };
+                object.insert(k.as_string().clone(), var_to_json(&v)?);
+            }
+            Value::Object(object)
+        }
+        Variant::Flyweight(_) => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use moor_values::{v_float, v_int, v_list, v_map, v_none, v_obj, v_str, Obj};
+
+    use crate::gmcp::{encode_gmcp, parse_gmcp};
+
+    #[test]
+    fn test_parse_gmcp() {
+        assert_eq!(
+            parse_gmcp(b"Core.Hello {\"client\": \"Mudlet\", \"version\": 4}"),
+            Some((
+                "Core.Hello".to_string(),
+                v_map(&[
+                    (v_str("client"), v_str("Mudlet")),
+                    (v_str("version"), v_int(4)),
+                ])
+            ))
+        );
+        assert_eq!(
+            parse_gmcp(b"Core.Supports.Set [\"Char 1\", \"Room 1\"]"),
+            Some((
+                "Core.Supports.Set".to_string(),
+                v_list(&[v_str("Char 1"), v_str("Room 1")])
+            ))
+        );
+        assert_eq!(
+            parse_gmcp(b"Char.Vitals {\"hp\": 1.5, \"dead\": false, \"x\": null}"),
+            Some((
+                "Char.Vitals".to_string(),
+                v_map(&[
+                    (v_str("dead"), v_int(0)),
+                    (v_str("hp"), v_float(1.5)),
+                    (v_str("x"), v_none()),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_gmcp_without_data() {
+        assert_eq!(
+            parse_gmcp(b"Core.Ping"),
+            Some(("Core.Ping".to_string(), v_none()))
+        );
+        assert_eq!(
+            parse_gmcp(b"Core.Ping  "),
+            Some(("Core.Ping".to_string(), v_none()))
+        );
+    }
+
+    #[test]
+    fn test_parse_gmcp_invalid_json() {
+        assert_eq!(parse_gmcp(b"Core.Hello {client: 1}"), None);
+    }
+
+    #[test]
+    fn test_encode_gmcp() {
+        assert_eq!(
+            encode_gmcp("Core.Ping", &v_none()),
+            Some(b"Core.Ping".to_vec())
+        );
+        assert_eq!(
+            encode_gmcp(
+                "Char.Status",
+                &v_map(&[
+                    (v_str("name"), v_str("Wizard")),
+                    (v_str("room"), v_obj(Obj::mk_id(2))),
+                    (v_str("exits"), v_list(&[v_str("north")])),
+                ])
+            ),
+            Some(
+                b"Char.Status {\"exits\":[\"north\"],\"name\":\"Wizard\",\"room\":\"#2\"}".to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_encode_gmcp_without_json_equivalent() {
+        assert_eq!(
+            encode_gmcp("Char.Status", &v_map(&[(v_int(1), v_str("x"))])),
+            None
+        );
+        assert_eq!(encode_gmcp("Char.Status", &v_float(f64::NAN)), None);
+    }
+
+    #[test]
+    fn test_gmcp_round_trip() {
+        let data = v_map(&[
+            (v_str("hp"), v_int(10)),
+            (v_str("tags"), v_list(&[v_str("a"), v_str("b")])),
+        ]);
+        let message = encode_gmcp("Char.Vitals", &data).unwrap();
+        assert_eq!(
+            parse_gmcp(&message),
+            Some(("Char.Vitals".to_string(), data))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+#![allow(clippy::too_many_arguments)]
+
+//! The telnet host, as a library, so that it can be run in the same process as the daemon.
+
+use crate::listen::Listeners;
+use clap_derive::Parser;
+use moor_values::SYSTEM_OBJECT;
+use rpc_async_client::{
+    make_host_token, proces_hosts_events, start_host_session, until_killed, ListenerOptions,
+    DEFAULT_OUT_OF_BAND_PREFIX, DEFAULT_OUT_OF_BAND_QUOTE_PREFIX,
+};
+use rpc_common::client_args::RpcClientArgs;
+use rpc_common::{load_keypair, HostType};
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tracing::info;
+
+mod capabilities;
+mod connection;
+mod gmcp;
+mod listen;
+mod telnet;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub client_args: RpcClientArgs,
+
+    #[arg(
+        long,
+        value_name = "telnet-address",
+        help = "Listen address for the default telnet connections listener",
+        default_value = "0.0.0.0"
+    )]
+    pub telnet_address: String,
+
+    #[arg(
+        long,
+        value_name = "telnet-port",
+        help = "Listen port for the default telnet connections listener",
+        default_value = "8888"
+    )]
+    pub telnet_port: u16,
+
+    #[arg(
+        long,
+        value_name = "tcp-keepalive",
+        help = "Seconds of socket inactivity before sending TCP keepalive probes (0 to disable)",
+        default_value = "60"
+    )]
+    pub tcp_keepalive: u64,
+
+    #[arg(
+        long,
+        value_name = "connect-timeout",
+        help = "Seconds an un-logged-in connection may idle before it is dropped, if $server_options.connect_timeout is not set (0 to disable)",
+        default_value = "300"
+    )]
+    pub connect_timeout: u64,
+
+    #[arg(
+        long,
+        value_name = "idle-timeout",
+        help = "Seconds a logged-in connection may idle before it is dropped, if $server_options.idle_timeout is not set (0 to disable)",
+        default_value = "0"
+    )]
+    pub idle_timeout: u64,
+
+    #[arg(
+        long,
+        value_name = "out-of-band-prefix",
+        help = "Input lines starting with this are sent to $do_out_of_band_command",
+        default_value = DEFAULT_OUT_OF_BAND_PREFIX
+    )]
+    pub out_of_band_prefix: String,
+
+    #[arg(
+        long,
+        value_name = "out-of-band-quote-prefix",
+        help = "Input lines starting with this are treated as in-band, with the prefix removed",
+        default_value = DEFAULT_OUT_OF_BAND_QUOTE_PREFIX
+    )]
+    pub out_of_band_quote_prefix: String,
+
+    #[arg(
+        long,
+        help = "Negotiate GMCP with clients, passing messages to do_gmcp on the listener's handler object",
+        default_value = "false"
+    )]
+    pub gmcp: bool,
+
+    #[arg(
+        long,
+        help = "Negotiate terminal type (MTTS) and MXP with clients, reporting what they support in connection_attributes()",
+        default_value = "false"
+    )]
+    pub negotiate_capabilities: bool,
+
+    #[arg(
+        long,
+        help = "Mark the end of prompts with telnet GA, or EOR for clients which negotiate it",
+        default_value = "false"
+    )]
+    pub prompt_marks: bool,
+
+    #[arg(long, help = "Enable debug logging", default_value = "false")]
+    pub debug: bool,
+}
+
+/// Command line durations in seconds, where zero means "never".
+fn secs(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Serve telnet connections for the daemon at `args.client_args.rpc_address` until
+/// `kill_switch` is thrown, or the daemon goes away.
+pub async fn run_telnet_host(args: Args, kill_switch: Arc<AtomicBool>) -> Result<(), eyre::Error> {
+    run_telnet_host_with_context(tmq::Context::new(), args, kill_switch).await
+}
+
+/// As `run_telnet_host`, but with its sockets in `zmq_ctx`, so that it can reach a daemon in the
+/// same process over `inproc://` endpoints.
+pub async fn run_telnet_host_with_context(
+    zmq_ctx: tmq::Context,
+    args: Args,
+    kill_switch: Arc<AtomicBool>,
+) -> Result<(), eyre::Error> {
+    // Parse the telnet address and port.
+    let listen_addr = format!("{}:{}", args.telnet_address, args.telnet_port);
+    let telnet_sockaddr = listen_addr.parse::<SocketAddr>().unwrap();
+
+    let (mut listeners_server, listeners_channel, listeners) = Listeners::new(
+        zmq_ctx.clone(),
+        args.client_args.rpc_address.clone(),
+        args.client_args.events_address.clone(),
+        kill_switch.clone(),
+        ListenerOptions {
+            tcp_keepalive: secs(args.tcp_keepalive),
+            connect_timeout: secs(args.connect_timeout),
+            idle_timeout: secs(args.idle_timeout),
+            out_of_band_prefix: args.out_of_band_prefix.clone(),
+            out_of_band_quote_prefix: args.out_of_band_quote_prefix.clone(),
+            gmcp: args.gmcp,
+            negotiate_capabilities: args.negotiate_capabilities,
+            prompt_marks: args.prompt_marks,
+            print_messages: true,
+        },
+    );
+    let listeners_thread = tokio::spawn(async move {
+        listeners_server.run(listeners_channel).await;
+    });
+
+    listeners
+        .add_listener(&SYSTEM_OBJECT, telnet_sockaddr, true)
+        .await
+        .expect("Unable to start default listener");
+
+    let (private_key, _public_key) =
+        load_keypair(&args.client_args.public_key, &args.client_args.private_key)
+            .expect("Unable to load keypair from public and private key files");
+    let host_token = make_host_token(&private_key, HostType::TCP);
+
+    let rpc_client = start_host_session(
+        &host_token,
+        zmq_ctx.clone(),
+        args.client_args.rpc_address.clone(),
+        kill_switch.clone(),
+        listeners.clone(),
+    )
+    .await
+    .expect("Unable to establish initial host session");
+
+    let host_listen_loop = proces_hosts_events(
+        rpc_client,
+        host_token,
+        zmq_ctx.clone(),
+        args.client_args.events_address.clone(),
+        args.telnet_address.clone(),
+        kill_switch.clone(),
+        listeners.clone(),
+        HostType::TCP,
+    );
+    select! {
+        _ = host_listen_loop => {
+            info!("Host events loop exited.");
+        },
+        _ = listeners_thread => {
+            info!("Listener set exited.");
+        }
+        _ = until_killed(kill_switch.clone()) => {
+            info!("Kill switch activated, stopping...");
+        }
+    }
+
+    Ok(())
+}
@@ -12,22 +12,28 @@
 //
 
 use crate::connection::TelnetConnection;
+use crate::telnet::{TelnetCodec, TelnetFrame};
 use eyre::bail;
 use futures_util::stream::SplitSink;
 use futures_util::StreamExt;
 use moor_values::Obj;
+use rpc_async_client::output::{overflow_notice, BufferedOutput};
 use rpc_async_client::rpc_client::RpcSendClient;
-use rpc_async_client::{ListenersClient, ListenersMessage};
+use rpc_async_client::{ListenerOptions, ListenersClient, ListenersMessage};
 use rpc_common::HostClientToDaemonMessage::ConnectionEstablish;
-use rpc_common::{DaemonToClientReply, ReplyResult, CLIENT_BROADCAST_TOPIC};
+use rpc_common::{
+    DaemonToClientReply, OutputLimits, ReplyResult, RpcMessageError, CLIENT_BROADCAST_TOPIC,
+};
+use socket2::{SockRef, TcpKeepalive};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tmq::{request, subscribe};
+use tokio::io::AsyncWriteExt;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::select;
-use tokio_util::codec::{Framed, LinesCodec};
+use tokio_util::codec::Framed;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
@@ -37,6 +43,7 @@ pub struct Listeners {
     rpc_address: String,
     events_address: String,
     kill_switch: Arc<AtomicBool>,
+    options: ListenerOptions,
 }
 
 impl Listeners {
@@ -45,6 +52,7 @@ impl Listeners {
         rpc_address: String,
         events_address: String,
         kill_switch: Arc<AtomicBool>,
+        options: ListenerOptions,
     ) -> (
         Self,
         tokio::sync::mpsc::Receiver<ListenersMessage>,
@@ -57,6 +65,7 @@ impl Listeners {
             rpc_address,
             events_address,
             kill_switch,
+            options,
         };
         let listeners_client = ListenersClient::new(tx);
         (listeners, rx, listeners_client)
@@ -77,7 +86,7 @@ impl Listeners {
             }
 
             match listeners_channel.recv().await {
-                Some(ListenersMessage::AddListener(handler, addr)) => {
+                Some(ListenersMessage::AddListener(handler, addr, print_messages)) => {
                     let listener = TcpListener::bind(addr)
                         .await
                         .expect("Unable to bind listener");
@@ -89,6 +98,10 @@ impl Listeners {
                     let rpc_address = self.rpc_address.clone();
                     let events_address = self.events_address.clone();
                     let kill_switch = self.kill_switch.clone();
+                    let options = ListenerOptions {
+                        print_messages,
+                        ..self.options.clone()
+                    };
 
                     // One task per listener.
                     tokio::spawn(async move {
@@ -119,6 +132,7 @@ impl Listeners {
                                                 listener_port,
                                                 stream,
                                                 addr,
+                                                options.clone(),
                                             ));
                                         }
                                         Err(e) => {
@@ -180,7 +194,16 @@ impl Listener {
         listener_port: u16,
         stream: TcpStream,
         peer_addr: SocketAddr,
+        options: ListenerOptions,
     ) -> Result<(), eyre::Report> {
+        if let Some(keepalive) = options.tcp_keepalive {
+            let keepalive = TcpKeepalive::new()
+                .with_time(keepalive)
+                .with_interval(keepalive);
+            if let Err(e) = SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+                warn!(?e, ?peer_addr, "Unable to set TCP keepalive on connection");
+            }
+        }
         let connection_kill_switch = kill_switch.clone();
         let rpc_address = rpc_address.clone();
         let events_address = events_address.clone();
@@ -203,7 +226,10 @@ impl Listener {
             let mut rpc_client = RpcSendClient::new(rpc_request_sock);
 
             let (client_token, connection_oid) = match rpc_client
-                .make_client_rpc_call(client_id, ConnectionEstablish(peer_addr.to_string()))
+                .make_client_rpc_call(
+                    client_id,
+                    ConnectionEstablish(peer_addr.to_string(), listener_port),
+                )
                 .await
             {
                 Ok(ReplyResult::ClientSuccess(DaemonToClientReply::NewConnection(
@@ -213,6 +239,15 @@ impl Listener {
                     info!("Connection established, connection ID: {}", objid);
                     (token, objid)
                 }
+                Ok(ReplyResult::Failure(RpcMessageError::ServerFull(msg))) => {
+                    // Tell them why, rather than just dropping them.
+                    info!(?peer_addr, "Refusing connection; server full");
+                    let mut stream = stream;
+                    let msg = msg.replace('\n', "\r\n");
+                    stream.write_all(format!("{msg}\r\n").as_bytes()).await?;
+                    stream.shutdown().await?;
+                    return Ok(());
+                }
                 Ok(ReplyResult::Failure(f)) => {
                     bail!("RPC failure in connection establishment: {}", f);
                 }
@@ -246,9 +281,14 @@ impl Listener {
             );
 
             // Re-ify the connection.
-            let framed_stream = Framed::new(stream, LinesCodec::new());
-            let (write, read): (SplitSink<Framed<TcpStream, LinesCodec>, String>, _) =
+            let framed_stream = Framed::new(stream, TelnetCodec::new());
+            let (write, read): (SplitSink<Framed<TcpStream, TelnetCodec>, TelnetFrame>, _) =
                 framed_stream.split();
+            // Written out in the background, so a client which isn't reading can't hold up its
+            // connection. Buffered to the default limits until the connection's options change.
+            let write = BufferedOutput::spawn(write, OutputLimits::default(), |lost| {
+                overflow_notice(lost).into()
+            });
             let mut tcp_connection = TelnetConnection {
                 handler_object,
                 peer_addr,
@@ -258,6 +298,10 @@ impl Listener {
                 write,
                 read,
                 kill_switch: connection_kill_switch,
+                options,
+                gmcp: false,
+                eor: false,
+                capabilities: Default::default(),
             };
 
             tcp_connection
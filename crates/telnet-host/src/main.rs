@@ -11,50 +11,14 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-#![allow(clippy::too_many_arguments)]
-
-use crate::listen::Listeners;
 use clap::Parser;
-use clap_derive::Parser;
-use moor_values::SYSTEM_OBJECT;
-use rpc_async_client::{make_host_token, proces_hosts_events, start_host_session};
-use rpc_common::client_args::RpcClientArgs;
-use rpc_common::{load_keypair, HostType};
-use std::net::SocketAddr;
+use moor_telnet_host::{run_telnet_host, Args};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::select;
 use tokio::signal::unix::{signal, SignalKind};
 use tracing::info;
 
-mod connection;
-mod listen;
-
-#[derive(Parser, Debug)]
-struct Args {
-    #[command(flatten)]
-    client_args: RpcClientArgs,
-
-    #[arg(
-        long,
-        value_name = "telnet-address",
-        help = "Listen address for the default telnet connections listener",
-        default_value = "0.0.0.0"
-    )]
-    telnet_address: String,
-
-    #[arg(
-        long,
-        value_name = "telnet-port",
-        help = "Listen port for the default telnet connections listener",
-        default_value = "8888"
-    )]
-    telnet_port: u16,
-
-    #[arg(long, help = "Enable debug logging", default_value = "false")]
-    debug: bool,
-}
-
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), eyre::Error> {
     color_eyre::install()?;
@@ -82,59 +46,10 @@ async fn main() -> Result<(), eyre::Error> {
 
     let kill_switch = Arc::new(AtomicBool::new(false));
 
-    // Parse the telnet address and port.
-    let listen_addr = format!("{}:{}", args.telnet_address, args.telnet_port);
-    let telnet_sockaddr = listen_addr.parse::<SocketAddr>().unwrap();
-
-    let zmq_ctx = tmq::Context::new();
-
-    let (mut listeners_server, listeners_channel, listeners) = Listeners::new(
-        zmq_ctx.clone(),
-        args.client_args.rpc_address.clone(),
-        args.client_args.events_address.clone(),
-        kill_switch.clone(),
-    );
-    let listeners_thread = tokio::spawn(async move {
-        listeners_server.run(listeners_channel).await;
-    });
-
-    listeners
-        .add_listener(&SYSTEM_OBJECT, telnet_sockaddr)
-        .await
-        .expect("Unable to start default listener");
-
-    let (private_key, _public_key) =
-        load_keypair(&args.client_args.public_key, &args.client_args.private_key)
-            .expect("Unable to load keypair from public and private key files");
-    let host_token = make_host_token(&private_key, HostType::TCP);
-
-    let rpc_client = start_host_session(
-        &host_token,
-        zmq_ctx.clone(),
-        args.client_args.rpc_address.clone(),
-        kill_switch.clone(),
-        listeners.clone(),
-    )
-    .await
-    .expect("Unable to establish initial host session");
-
-    let host_listen_loop = proces_hosts_events(
-        rpc_client,
-        host_token,
-        zmq_ctx.clone(),
-        args.client_args.events_address.clone(),
-        args.telnet_address.clone(),
-        kill_switch.clone(),
-        listeners.clone(),
-        HostType::TCP,
-    );
     select! {
-        _ = host_listen_loop => {
-            info!("Host events loop exited.");
+        result = run_telnet_host(args, kill_switch.clone()) => {
+            result?;
         },
-        _ = listeners_thread => {
-            info!("Listener set exited.");
-        }
         _ = hup_signal.recv() => {
             info!("HUP received, stopping...");
             kill_switch.store(true, std::sync::atomic::Ordering::SeqCst);
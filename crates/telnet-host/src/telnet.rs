@@ -0,0 +1,316 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! A line codec which also understands telnet (RFC 854) commands, option negotiation and
+//! subnegotiation, so that we can speak protocols like GMCP alongside plain lines of text.
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+pub(crate) const IAC: u8 = 255;
+pub(crate) const DONT: u8 = 254;
+pub(crate) const DO: u8 = 253;
+pub(crate) const WONT: u8 = 252;
+pub(crate) const WILL: u8 = 251;
+pub(crate) const SB: u8 = 250;
+pub(crate) const SE: u8 = 240;
+pub(crate) const GA: u8 = 249;
+/// The end-of-record command, as distinct from the option which enables it.
+pub(crate) const EOR: u8 = 239;
+
+/// Terminal type (RFC 1091), which MUD clients also use to report capabilities via MTTS.
+pub(crate) const TTYPE: u8 = 24;
+pub(crate) const TTYPE_IS: u8 = 0;
+pub(crate) const TTYPE_SEND: u8 = 1;
+/// End of record (RFC 885), which clients use to tell where a prompt ends, in place of GA.
+pub(crate) const TELOPT_EOR: u8 = 25;
+/// MUD eXtension Protocol.
+pub(crate) const MXP: u8 = 91;
+/// Generic MUD Communication Protocol, see https://www.gammon.com.au/gmcp
+pub(crate) const GMCP: u8 = 201;
+
+/// Something received from the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TelnetEvent {
+    Line(String),
+    Will(u8),
+    Wont(u8),
+    Do(u8),
+    Dont(u8),
+    Subnegotiation(u8, Vec<u8>),
+}
+
+/// Something to send to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TelnetFrame {
+    Line(String),
+    /// Text without a line break after it.
+    Prompt(String),
+    /// A command on its own, e.g. `GA`.
+    Command(u8),
+    /// One of `WILL`, `WONT`, `DO`, `DONT`, and the option it applies to.
+    Negotiate(u8, u8),
+    Subnegotiation(u8, Vec<u8>),
+}
+
+impl From<String> for TelnetFrame {
+    fn from(line: String) -> Self {
+        TelnetFrame::Line(line)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TelnetCodec {
+    /// The line being accumulated, with telnet commands already stripped out.
+    line: Vec<u8>,
+}
+
+impl TelnetCodec {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn take_line(&mut self) -> TelnetEvent {
+        let mut line = std::mem::take(&mut self.line);
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        TelnetEvent::Line(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+impl Decoder for TelnetCodec {
+    type Item = TelnetEvent;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // Commands are only consumed once they've been received in full, so a partial one at the
+        // end of the buffer is left for the next call.
+        loop {
+            let Some(&b) = src.first() else {
+                return Ok(None);
+            };
+            match b {
+                IAC => {
+                    let Some(&command) = src.get(1) else {
+                        return Ok(None);
+                    };
+                    match command {
+                        IAC => {
+                            src.advance(2);
+                            self.line.push(IAC);
+                        }
+                        WILL | WONT | DO | DONT => {
+                            let Some(&option) = src.get(2) else {
+                                return Ok(None);
+                            };
+                            src.advance(3);
+                            return Ok(Some(match command {
+                                WILL => TelnetEvent::Will(option),
+                                WONT => TelnetEvent::Wont(option),
+                                DO => TelnetEvent::Do(option),
+                                _ => TelnetEvent::Dont(option),
+                            }));
+                        }
+                        SB => {
+                            let Some(&option) = src.get(2) else {
+                                return Ok(None);
+                            };
+                            let mut data = vec![];
+                            let mut i = 3;
+                            loop {
+                                match (src.get(i), src.get(i + 1)) {
+                                    (None, _) | (Some(&IAC), None) => return Ok(None),
+                                    (Some(&IAC), Some(&SE)) => {
+                                        src.advance(i + 2);
+                                        return Ok(Some(TelnetEvent::Subnegotiation(option, data)));
+                                    }
+                                    (Some(&IAC), Some(&IAC)) => {
+                                        data.push(IAC);
+                                        i += 2;
+                                    }
+                                    (Some(&b), _) => {
+                                        data.push(b);
+                                        i += 1;
+                                    }
+                                }
+                            }
+                        }
+                        // NOP, GA, AYT and friends; nothing for us to do.
+                        _ => src.advance(2),
+                    }
+                }
+                b'\n' => {
+                    src.advance(1);
+                    return Ok(Some(self.take_line()));
+                }
+                // Telnet sends a bare carriage return as CR NUL.
+                0 => src.advance(1),
+                b => {
+                    src.advance(1);
+                    self.line.push(b);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(event) = self.decode(src)? {
+            return Ok(Some(event));
+        }
+        // Whatever's left is an unterminated line, or an incomplete command which we drop.
+        src.clear();
+        if self.line.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.take_line()))
+    }
+}
+
+impl Encoder<TelnetFrame> for TelnetCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, item: TelnetFrame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match item {
+            // UTF-8 never contains 0xFF, so there's no IAC to escape in a line.
+            TelnetFrame::Line(line) => {
+                dst.reserve(line.len() + 1);
+                dst.put(line.as_bytes());
+                dst.put_u8(b'\n');
+            }
+            TelnetFrame::Prompt(text) => {
+                dst.put(text.as_bytes());
+            }
+            TelnetFrame::Command(command) => {
+                dst.put_slice(&[IAC, command]);
+            }
+            TelnetFrame::Negotiate(command, option) => {
+                dst.put_slice(&[IAC, command, option]);
+            }
+            TelnetFrame::Subnegotiation(option, data) => {
+                dst.put_slice(&[IAC, SB, option]);
+                for b in data {
+                    if b == IAC {
+                        dst.put_u8(IAC);
+                    }
+                    dst.put_u8(b);
+                }
+                dst.put_slice(&[IAC, SE]);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use crate::telnet::{TelnetCodec, TelnetEvent, TelnetFrame, GMCP, IAC, SB, SE, WILL};
+
+    fn decode_all(codec: &mut TelnetCodec, src: &mut BytesMut) -> Vec<TelnetEvent> {
+        let mut events = vec![];
+        while let Some(event) = codec.decode(src).unwrap() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_decode_gmcp_subnegotiation() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(b"look\r\n");
+        src.extend_from_slice(&[IAC, SB, GMCP]);
+        src.extend_from_slice(b"Core.Hello {\"client\": \"x\"}");
+        src.extend_from_slice(&[IAC, SE]);
+        src.extend_from_slice(b"north\n");
+        assert_eq!(
+            decode_all(&mut codec, &mut src),
+            vec![
+                TelnetEvent::Line("look".to_string()),
+                TelnetEvent::Subnegotiation(GMCP, b"Core.Hello {\"client\": \"x\"}".to_vec()),
+                TelnetEvent::Line("north".to_string()),
+            ]
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_decode_split_subnegotiation() {
+        // A subnegotiation arriving in pieces is held back until it's complete, even when the
+        // piece ends part way through IAC SE.
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(&[IAC, SB, GMCP]);
+        src.extend_from_slice(b"Core.Ping");
+        src.extend_from_slice(&[IAC]);
+        assert_eq!(decode_all(&mut codec, &mut src), vec![]);
+        src.extend_from_slice(&[SE]);
+        assert_eq!(
+            decode_all(&mut codec, &mut src),
+            vec![TelnetEvent::Subnegotiation(GMCP, b"Core.Ping".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_decode_escaped_iac_in_subnegotiation() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(&[IAC, SB, GMCP, b'a', IAC, IAC, b'b', IAC, SE]);
+        assert_eq!(
+            decode_all(&mut codec, &mut src),
+            vec![TelnetEvent::Subnegotiation(GMCP, vec![b'a', IAC, b'b'])]
+        );
+    }
+
+    #[test]
+    fn test_decode_negotiation_within_line() {
+        let mut codec = TelnetCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(b"lo");
+        src.extend_from_slice(&[IAC, WILL, GMCP]);
+        src.extend_from_slice(b"ok\r\n");
+        assert_eq!(
+            decode_all(&mut codec, &mut src),
+            vec![
+                TelnetEvent::Will(GMCP),
+                TelnetEvent::Line("look".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_gmcp_subnegotiation() {
+        let mut codec = TelnetCodec::new();
+        let mut dst = BytesMut::new();
+        codec
+            .encode(
+                TelnetFrame::Subnegotiation(GMCP, vec![b'a', IAC, b'b']),
+                &mut dst,
+            )
+            .unwrap();
+        assert_eq!(
+            dst.as_ref(),
+            &[IAC, SB, GMCP, b'a', IAC, IAC, b'b', IAC, SE]
+        );
+
+        // And what's encoded decodes back to the same message.
+        let mut codec = TelnetCodec::new();
+        assert_eq!(
+            decode_all(&mut codec, &mut dst),
+            vec![TelnetEvent::Subnegotiation(GMCP, vec![b'a', IAC, b'b'])]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Differential testing of mooR against LambdaMOO. The commands in `.moot` scripts are sent to
+//! both servers over telnet (their expected results are ignored), and wherever the two answer
+//! differently it's reported, unless `known_differences.txt` says that's on purpose.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use eyre::{eyre, WrapErr};
+use moor_moot::{CommandKind, MootClient, NONPROGRAMMER, PROGRAMMER, WIZARD};
+use moor_values::Obj;
+use serde::Serialize;
+
+/// How many read timeouts (of a second each) to sit through waiting for a command to finish.
+const MAX_TIMEOUTS: usize = 10;
+
+/// A command from a `.moot` script, and who sends it.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub line_no: usize,
+    pub player: Obj,
+    pub kind: CommandKind,
+    pub command: String,
+}
+
+impl Step {
+    /// The line sent to the server.
+    fn line(&self) -> String {
+        match self.kind {
+            CommandKind::Eval => format!("; {}", self.command),
+            CommandKind::Command => self.command.clone(),
+        }
+    }
+}
+
+/// The commands in the `.moot` script at `path`, in order.
+pub fn read_steps(path: &Path) -> eyre::Result<Vec<Step>> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("{}", path.display()))?;
+    let mut player = WIZARD;
+    let mut steps: Vec<Step> = vec![];
+    let mut continuing = false;
+    for (line_no, line) in text.lines().enumerate() {
+        let line_no = line_no + 1;
+        if let Some(rest) = line.strip_prefix('>') {
+            if continuing {
+                steps.last_mut().unwrap().command.push_str(rest);
+            }
+            continue;
+        }
+        continuing = false;
+        if line.starts_with([';', '%']) {
+            steps.push(Step {
+                line_no,
+                player: player.clone(),
+                kind: line.chars().next().unwrap().into(),
+                command: line[1..].trim_start().to_string(),
+            });
+            continuing = true;
+        } else if let Some(name) = line.strip_prefix('@') {
+            player = match name {
+                "wizard" => WIZARD,
+                "programmer" => PROGRAMMER,
+                "nonprogrammer" => NONPROGRAMMER,
+                _ => return Err(eyre!("{}:{line_no}: unknown player {name}", path.display())),
+            };
+        }
+    }
+    Ok(steps)
+}
+
+/// Send `steps` to the server listening on `port`, returning the lines each one got back.
+pub fn record(port: u16, steps: &[Step]) -> eyre::Result<Vec<Vec<String>>> {
+    let mut clients: HashMap<Obj, MootClient> = HashMap::new();
+    let mut transcript = vec![];
+    for step in steps {
+        if !clients.contains_key(&step.player) {
+            clients.insert(step.player.clone(), connect(port, &step.player)?);
+        }
+        let client = clients.get_mut(&step.player).unwrap();
+        client.write_line(step.line())?;
+
+        // Everything up to the answer to this is the step's output.
+        let sync = format!("\"moot-conformance-sync:{}\"", step.line_no);
+        client.write_line(format!("; return {sync};"))?;
+        let mut output = vec![];
+        let mut timeouts = 0;
+        loop {
+            match client.read_line()? {
+                Some(line) if line == sync => break,
+                Some(line) => output.push(line),
+                None if timeouts < MAX_TIMEOUTS => timeouts += 1,
+                None => return Err(eyre!("line {}: no response", step.line_no)),
+            }
+        }
+        transcript.push(output);
+    }
+    Ok(transcript)
+}
+
+fn connect(port: u16, player: &Obj) -> eyre::Result<MootClient> {
+    let start = Instant::now();
+    loop {
+        match MootClient::new(port) {
+            Ok(mut client) => {
+                client.write_line(format!("connect {player}"))?;
+                return match client.read_line()?.as_deref() {
+                    Some("*** Connected ***") => Ok(client),
+                    other => Err(eyre!("could not connect as {player}: {other:?}")),
+                };
+            }
+            Err(e) if start.elapsed() > Duration::from_secs(5) => return Err(e),
+            Err(_) => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+/// Differences listed in a file of `<script>` (the whole script) or `<script>:<line>` entries, one
+/// per line, with `#` starting a comment saying why.
+#[derive(Debug, Default)]
+pub struct KnownDifferences {
+    scripts: HashSet<String>,
+    lines: HashSet<(String, usize)>,
+}
+
+impl KnownDifferences {
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let mut known = Self::default();
+        for (line_no, line) in text.lines().enumerate() {
+            let entry = line.split('#').next().unwrap().trim();
+            if entry.is_empty() {
+                continue;
+            }
+            match entry.rsplit_once(':') {
+                Some((script, line)) => {
+                    let line = line
+                        .parse()
+                        .map_err(|_| eyre!("line {}: bad line number {line}", line_no + 1))?;
+                    known.lines.insert((script.to_string(), line));
+                }
+                None => {
+                    known.scripts.insert(entry.to_string());
+                }
+            }
+        }
+        Ok(known)
+    }
+
+    pub fn contains(&self, script: &str, line_no: usize) -> bool {
+        self.scripts.contains(script) || self.lines.contains(&(script.to_string(), line_no))
+    }
+}
+
+/// A command the two servers answered differently.
+#[derive(Clone, Debug, Serialize)]
+pub struct Divergence {
+    pub line_no: usize,
+    pub command: String,
+    pub lambdamoo: Vec<String>,
+    pub moor: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScriptReport {
+    pub script: String,
+    pub steps: usize,
+    /// Differences not accounted for.
+    pub divergences: Vec<Divergence>,
+    /// Differences listed as known.
+    pub known: Vec<Divergence>,
+}
+
+impl ScriptReport {
+    pub fn compare(
+        script: &str,
+        steps: &[Step],
+        lambdamoo: Vec<Vec<String>>,
+        moor: Vec<Vec<String>>,
+        known: &KnownDifferences,
+    ) -> Self {
+        let mut report = ScriptReport {
+            script: script.to_string(),
+            steps: steps.len(),
+            divergences: vec![],
+            known: vec![],
+        };
+        for ((step, lambdamoo), moor) in steps.iter().zip(lambdamoo).zip(moor) {
+            if lambdamoo == moor {
+                continue;
+            }
+            let divergence = Divergence {
+                line_no: step.line_no,
+                command: step.line(),
+                lambdamoo,
+                moor,
+            };
+            if known.contains(script, step.line_no) {
+                report.known.push(divergence);
+            } else {
+                report.divergences.push(divergence);
+            }
+        }
+        report
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub scripts: Vec<ScriptReport>,
+}
+
+impl Report {
+    pub fn divergences(&self) -> usize {
+        self.scripts.iter().map(|s| s.divergences.len()).sum()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Unable to serialize report")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_differences() {
+        let known = KnownDifferences::parse(
+            "# mooR extensions\nuuid.moot\nmath.moot:12 # float formatting\n",
+        )
+        .unwrap();
+        assert!(known.contains("uuid.moot", 3));
+        assert!(known.contains("math.moot", 12));
+        assert!(!known.contains("math.moot", 13));
+    }
+
+    #[test]
+    fn test_compare() {
+        let step = |line_no| Step {
+            line_no,
+            player: WIZARD,
+            kind: CommandKind::Eval,
+            command: "return 1;".to_string(),
+        };
+        let steps = [step(1), step(2), step(3)];
+        let known = KnownDifferences::parse("a.moot:3").unwrap();
+        let report = ScriptReport::compare(
+            "a.moot",
+            &steps,
+            vec![vec!["1".into()], vec!["1".into()], vec!["1".into()]],
+            vec![vec!["1".into()], vec!["1.0".into()], vec!["2".into()]],
+            &known,
+        );
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.divergences[0].line_no, 2);
+        assert_eq!(report.known.len(), 1);
+    }
+}
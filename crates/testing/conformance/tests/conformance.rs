@@ -0,0 +1,139 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Run the kernel's `.moot` scripts against both LambdaMOO and mooR, each started fresh from
+//! `Test.db` for every script, and fail on any difference not in `known_differences.txt`.
+//! Configured using ENV vars
+//! * MOOT_MOO_PATH: path to the `moo` binary, defaults to `$HOME/MOO-1.8.1/moo`; the test is
+//!   skipped if it isn't there. Nothing in this tree builds LambdaMOO: build 1.8.1 from its
+//!   source release (`./configure && make`) and point this at the `moo` it produces, as for the
+//!   moot crate's own LambdaMOO test
+//! * MOOR_CONFORMANCE_REPORT: where to write the JSON report, defaults to
+//!   `conformance-report.json` in cargo's temporary directory for tests
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use moor_conformance::{read_steps, record, KnownDifferences, Report, ScriptReport};
+use moor_moot::{free_port, test_db_path, ManagedChild, MoorServer};
+
+fn moo_path() -> PathBuf {
+    env::var("MOOT_MOO_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            let mut path = PathBuf::from(env::var("HOME").unwrap());
+            path.push("MOO-1.8.1/moo");
+            path
+        })
+}
+
+fn report_path() -> PathBuf {
+    env::var("MOOR_CONFORMANCE_REPORT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conformance-report.json")
+        })
+}
+
+fn scripts_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../kernel/testsuite/moot")
+}
+
+fn start_moo(port: u16) -> ManagedChild {
+    ManagedChild::new(
+        "moo",
+        Command::new(moo_path())
+            .arg(test_db_path())
+            .arg("/dev/null")
+            .arg(port.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|_| panic!("Failed to start moo server {}", moo_path().display())),
+    )
+}
+
+fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_scripts(&path, scripts);
+        } else if path.extension().is_some_and(|e| e == "moot") {
+            scripts.push(path);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_conformance() {
+    if !moo_path().exists() {
+        eprintln!(
+            "Skipping: no LambdaMOO at {} (set MOOT_MOO_PATH)",
+            moo_path().display()
+        );
+        return;
+    }
+    let known = KnownDifferences::parse(
+        &fs::read_to_string(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("known_differences.txt"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    let mut scripts = vec![];
+    collect_scripts(&scripts_dir(), &mut scripts);
+    scripts.sort();
+
+    let mut report = Report::default();
+    for path in scripts {
+        let name = path
+            .strip_prefix(scripts_dir())
+            .unwrap()
+            .display()
+            .to_string();
+        let steps = read_steps(&path).unwrap();
+
+        let port = free_port();
+        let moo = start_moo(port);
+        let lambdamoo =
+            record(port, &steps).unwrap_or_else(|e| panic!("{name} (LambdaMOO): {e:?}"));
+        drop(moo);
+
+        let moor = MoorServer::start();
+        let moor_transcript =
+            record(moor.port(), &steps).unwrap_or_else(|e| panic!("{name} (mooR): {e:?}"));
+        drop(moor);
+
+        report.scripts.push(ScriptReport::compare(
+            &name,
+            &steps,
+            lambdamoo,
+            moor_transcript,
+            &known,
+        ));
+    }
+
+    let report_path = report_path();
+    fs::write(&report_path, report.to_json()).unwrap();
+    assert_eq!(
+        report.divergences(),
+        0,
+        "mooR and LambdaMOO differ; see {}",
+        report_path.display()
+    );
+}
@@ -0,0 +1,36 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! For any source that compiles, decompiling and unparsing the program must produce source which
+//! compiles again, and which unparses back to the same text (i.e. unparse is a fixpoint).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use moor_compiler::{compile, program_to_tree, unparse, CompileOptions};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(program) = compile(source, CompileOptions::default()) else {
+        return;
+    };
+    let tree = program_to_tree(&program).expect("Could not decompile program");
+    let unparsed = unparse(&tree).expect("Could not unparse decompiled program");
+    let recompiled = compile(&unparsed.join("\n"), CompileOptions::default())
+        .expect("Could not recompile unparsed program");
+    let retree = program_to_tree(&recompiled).expect("Could not decompile recompiled program");
+    let reunparsed = unparse(&retree).expect("Could not unparse recompiled program");
+    assert_eq!(unparsed, reunparsed);
+});
@@ -0,0 +1,29 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Feed arbitrary bytes to the program decoder, which is what the VM uses to turn a stored verb
+//! binary into an opcode stream. Corrupt binaries must be rejected, not crash the decoder, and
+//! anything that does decode must survive being decompiled.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use moor_compiler::{program_to_tree, Program};
+use moor_values::AsByteBuffer;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(program) = Program::from_bytes(data.to_vec().into()) else {
+        return;
+    };
+    let _ = program_to_tree(&program);
+});
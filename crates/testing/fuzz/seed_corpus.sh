@@ -0,0 +1,19 @@
+#!/bin/sh
+# Seed the `compile` and `decompile_roundtrip` corpora from the `;`-eval lines of the kernel's
+# moot test suite. Run from this directory before the first `cargo fuzz run`.
+set -e
+
+MOOT_DIR=../../kernel/testsuite/moot
+
+for target in compile decompile_roundtrip; do
+    mkdir -p "corpus/$target"
+done
+
+find "$MOOT_DIR" -name '*.moot' | while read -r moot; do
+    grep '^; ' "$moot" | sed 's/^; //' | while IFS= read -r line; do
+        name=$(printf '%s' "$line" | cksum | cut -d' ' -f1)
+        for target in compile decompile_roundtrip; do
+            printf '%s' "$line" > "corpus/$target/$name"
+        done
+    done
+done
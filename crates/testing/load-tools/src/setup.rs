@@ -40,7 +40,7 @@ pub async fn noop_listeners_loop() -> (ListenersClient, JoinHandle<()>) {
     let t = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             match msg {
-                ListenersMessage::AddListener(_, _) => {}
+                ListenersMessage::AddListener(_, _, _) => {}
                 ListenersMessage::RemoveListener(_) => {}
                 ListenersMessage::GetListeners(r) => {
                     let _ = r.send(vec![]);
@@ -125,7 +125,7 @@ pub async fn create_user_session(
     let client_id = uuid::Uuid::new_v4();
     let peer_addr = format!("{}.test", Uuid::new_v4());
     let (client_token, connection_oid) = match rpc_client
-        .make_client_rpc_call(client_id, ConnectionEstablish(peer_addr.to_string()))
+        .make_client_rpc_call(client_id, ConnectionEstablish(peer_addr.to_string(), 0))
         .await
     {
         Ok(ReplyResult::ClientSuccess(DaemonToClientReply::NewConnection(token, objid))) => {
@@ -340,20 +340,24 @@ pub async fn listen_responses(
             if ks.load(std::sync::atomic::Ordering::Relaxed) {
                 break;
             }
-            let msg = events_recv(client_id, &mut events_sub).await;
-            match msg {
-                Ok(ClientEvent::TaskSuccess(tid, v)) => {
-                    let mut tasks = event_listen_task_results.lock().await;
-                    tasks.insert(tid, Ok(v));
-                }
-                Ok(ClientEvent::TaskError(tid, e)) => {
-                    let mut tasks = event_listen_task_results.lock().await;
-                    tasks.insert(tid, Err(anyhow!("Task error: {:?}", e)));
-                }
-                Ok(_) => {}
+            let msgs = match events_recv(client_id, &mut events_sub).await {
+                Ok(msgs) => msgs,
                 Err(e) => {
                     panic!("Error in event recv: {}", e);
                 }
+            };
+            for msg in msgs {
+                match msg {
+                    ClientEvent::TaskSuccess(tid, v) => {
+                        let mut tasks = event_listen_task_results.lock().await;
+                        tasks.insert(tid, Ok(v));
+                    }
+                    ClientEvent::TaskError(tid, e) => {
+                        let mut tasks = event_listen_task_results.lock().await;
+                        tasks.insert(tid, Err(anyhow!("Task error: {:?}", e)));
+                    }
+                    _ => {}
+                }
             }
         }
         let seconds_since_start = start_time.elapsed().as_secs();
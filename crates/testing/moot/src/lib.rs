@@ -28,6 +28,9 @@ use moor_values::Obj;
 
 use pretty_assertions::assert_eq;
 
+mod moor_server;
+pub use moor_server::{free_port, MoorServer};
+
 #[allow(dead_code)]
 pub const WIZARD: Obj = Obj::mk_id(3);
 #[allow(dead_code)]
@@ -387,7 +390,7 @@ impl MootClient {
         result
     }
 
-    fn read_line(&self) -> eyre::Result<Option<String>> {
+    pub fn read_line(&self) -> eyre::Result<Option<String>> {
         let mut buf = String::new();
         match BufReader::new(&self.stream).read_line(&mut buf) {
             Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
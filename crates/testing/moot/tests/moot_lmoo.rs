@@ -15,7 +15,7 @@
 //! Configured using ENV vars
 //! * MOOT_MOO_PATH: path to the `moo` binary, defaults to `$HOME/MOO-1.8.1/moo`
 //! * MOOT_DB_PATH: path to the textdump file, defaults to the `Test.db` next to this file
-//! * MOOT_PORT: port the MOO server listens on, defaults to 7777
+//! * MOOT_PORT: port the MOO server listens on, defaults to a free one, so tests can run in parallel
 
 use std::{
     env,
@@ -24,7 +24,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use moor_moot::{execute_moot_test, test_db_path, ManagedChild, TelnetMootRunner};
+use moor_moot::{execute_moot_test, free_port, test_db_path, ManagedChild, TelnetMootRunner};
 
 fn moo_path() -> PathBuf {
     env::var("MOOT_MOO_PATH")
@@ -45,16 +45,16 @@ fn db_path() -> PathBuf {
 fn moo_port() -> u16 {
     env::var("MOOT_PORT")
         .map(|s| s.parse().unwrap())
-        .unwrap_or(7777)
+        .unwrap_or_else(|_| free_port())
 }
 
-fn start_moo() -> ManagedChild {
+fn start_moo(port: u16) -> ManagedChild {
     ManagedChild::new(
         "moo",
         Command::new(moo_path())
             .arg(db_path())
             .arg("/dev/null")
-            .arg(moo_port().to_string())
+            .arg(port.to_string())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
@@ -63,11 +63,12 @@ fn start_moo() -> ManagedChild {
 }
 
 fn test_moo(path: &Path) {
-    let moo = Arc::new(Mutex::new(start_moo()));
+    let port = moo_port();
+    let moo = Arc::new(Mutex::new(start_moo(port)));
     let moo_clone = moo.clone();
     let validate_state = move || moo_clone.lock().unwrap().assert_running();
 
-    execute_moot_test(TelnetMootRunner::new(moo_port()), path, validate_state);
+    execute_moot_test(TelnetMootRunner::new(port), path, validate_state);
 
     drop(moo);
 }
@@ -372,7 +372,12 @@ function handle_narrative_event(msg) {
     console.log("No event data in message: " + msg);
     return;
   }
-  if (event["message"]) {
+  // See doc/web-client-protocol.md for the message schemas.
+  if (event["kind"] === "player_switched") {
+    context.auth_token = event["auth_token"];
+  } else if (event["kind"] !== "narrative") {
+    console.log("Unhandled " + event["kind"] + " message: " + msg);
+  } else if (event["message"]) {
     handle_narrative_msg(event);
   } else if (event["system_message"]) {
     handle_system_message(event);
@@ -11,7 +11,7 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::host::web_host::{LoginType, WsHostError};
+use crate::host::web_host::{rpc_call, LoginType, WsHostError};
 use crate::host::WebHost;
 use axum::extract::{ConnectInfo, State};
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
@@ -20,6 +20,7 @@ use axum::Form;
 use rpc_async_client::rpc_client::RpcSendClient;
 use rpc_common::{
     AuthToken, ClientToken, DaemonToClientReply, HostClientToDaemonMessage, ReplyResult,
+    RpcMessageError,
 };
 use serde_derive::Deserialize;
 use std::net::SocketAddr;
@@ -68,6 +69,13 @@ async fn auth_handler(
                     .body("".to_string())
                     .unwrap();
             }
+            Err(WsHostError::RpcFailure(RpcMessageError::ServerFull(msg))) => {
+                warn!("Server full; refusing {}", player);
+                return Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(msg)
+                    .unwrap();
+            }
             Err(e) => {
                 error!("Unable to establish connection: {}", e);
                 return Response::builder()
@@ -134,6 +142,50 @@ async fn auth_handler(
         .unwrap()
 }
 
+/// Exchange the auth token in the `X-Moor-Auth-Token` header, which must still be valid, for a new
+/// one (with a new expiry, if the daemon expires them), returned in the same header.
+pub async fn refresh_auth_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(host): State<WebHost>,
+    header_map: HeaderMap,
+) -> Response {
+    let (auth_token, client_id, client_token, mut rpc_client) =
+        match auth_auth(host, addr, header_map).await {
+            Ok(connection_details) => connection_details,
+            Err(status) => return status.into_response(),
+        };
+
+    let response = match rpc_call(
+        client_id,
+        &mut rpc_client,
+        HostClientToDaemonMessage::RefreshAuthToken(client_token.clone(), auth_token),
+    )
+    .await
+    {
+        Ok(DaemonToClientReply::AuthTokenRefreshed(auth_token)) => Response::builder()
+            .status(StatusCode::OK)
+            .header("X-Moor-Auth-Token", auth_token.0)
+            .body("".to_string())
+            .unwrap(),
+        Ok(r) => {
+            error!("Unexpected response from RPC server: {:?}", r);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(status) => status.into_response(),
+    };
+
+    // We're done with this RPC connection, so we detach it.
+    let _ = rpc_client
+        .make_client_rpc_call(
+            client_id,
+            HostClientToDaemonMessage::Detach(client_token.clone()),
+        )
+        .await
+        .expect("Unable to send detach to RPC server");
+
+    response
+}
+
 pub async fn auth_auth(
     host: WebHost,
     addr: SocketAddr,
@@ -157,7 +209,11 @@ pub async fn auth_auth(
         .attach_authenticated(auth_token.clone(), None, addr)
         .await
         .map_err(|e| match e {
-            WsHostError::AuthenticationFailed => StatusCode::UNAUTHORIZED,
+            // Including tokens which have expired or been revoked.
+            WsHostError::AuthenticationFailed
+            | WsHostError::RpcFailure(RpcMessageError::PermissionDenied) => {
+                StatusCode::UNAUTHORIZED
+            }
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         })?;
 
@@ -0,0 +1,213 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Syntax highlighting of verb code for read-only browsing, so that an object explorer can show
+//! it without loading an editor. The token classes are those the Monaco tokenizer in `editor.js`
+//! uses, so code looks the same whether it's being browsed or edited.
+
+use moor_values::Error;
+
+const KEYWORDS: &[&str] = &[
+    "if", "elseif", "else", "endif", "for", "in", "endfor", "while", "endwhile", "fork", "endfork",
+    "try", "except", "finally", "endtry", "return", "break", "continue", "let", "const", "global",
+    "this",
+];
+
+/// Operators two characters long, which have to be matched before their first character is taken
+/// as an operator on its own.
+const OPERATORS: &[&str] = &["<=", ">=", "==", "!=", "&&", "||", "->", "=>", ".."];
+
+/// Split each line of `code` into `(class, text)` tokens. The texts of a line's tokens, joined,
+/// are the line; whitespace and anything unrecognized get the empty class.
+pub fn highlight(code: &[String]) -> Vec<Vec<(&'static str, String)>> {
+    code.iter().map(|line| highlight_line(line)).collect()
+}
+
+fn highlight_line(line: &str) -> Vec<(&'static str, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let class = if c.is_whitespace() {
+            i = scan(&chars, i, |c| c.is_whitespace());
+            ""
+        } else if c == '#'
+            && chars
+                .get(i + 1)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '-')
+        {
+            i = scan(&chars, i + 2, |c| c.is_ascii_digit());
+            "type.namespace"
+        } else if c == '$' && chars.get(i + 1).is_some_and(|c| is_ident_start(*c)) {
+            i = scan(&chars, i + 1, is_ident_continue);
+            "type.namespace"
+        } else if is_ident_start(c) {
+            i = scan(&chars, i, is_ident_continue);
+            let word: String = chars[start..i].iter().collect();
+            if KEYWORDS.contains(&word.to_lowercase().as_str()) {
+                "keyword"
+            } else if Error::parse_str(&word).is_some() {
+                "constant"
+            } else {
+                "identifier"
+            }
+        } else if c.is_ascii_digit() {
+            i = scan(&chars, i, |c| c.is_ascii_digit());
+            let mut float = false;
+            // A `.` only continues the number if a digit follows, as `1..2` is a range.
+            if chars.get(i) == Some(&'.') && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                i = scan(&chars, i + 1, |c| c.is_ascii_digit());
+                float = true;
+            }
+            if matches!(chars.get(i), Some('e' | 'E')) {
+                let digits = match chars.get(i + 1) {
+                    Some('+' | '-') => i + 2,
+                    _ => i + 1,
+                };
+                if chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                    i = scan(&chars, digits, |c| c.is_ascii_digit());
+                    float = true;
+                }
+            }
+            if float {
+                "number.float"
+            } else {
+                "number"
+            }
+        } else if c == '"' {
+            i += 1;
+            let mut terminated = false;
+            while i < chars.len() {
+                match chars[i] {
+                    '\\' => i += 2,
+                    '"' => {
+                        i += 1;
+                        terminated = true;
+                        break;
+                    }
+                    _ => i += 1,
+                }
+            }
+            i = i.min(chars.len());
+            if terminated {
+                "string"
+            } else {
+                "string.invalid"
+            }
+        } else if "()[]{}".contains(c) {
+            i += 1;
+            "delimiter.bracket"
+        } else if let Some(op) = OPERATORS
+            .iter()
+            .find(|op| chars[i..].starts_with(&op.chars().collect::<Vec<_>>()))
+        {
+            i += op.len();
+            "delimiter"
+        } else if "+-*/%^!~<>=?|&.,;:@`'$".contains(c) {
+            i += 1;
+            "delimiter"
+        } else {
+            i += 1;
+            ""
+        };
+        tokens.push((class, chars[start..i].iter().collect()));
+    }
+    tokens
+}
+
+fn scan(chars: &[char], from: usize, want: impl Fn(char) -> bool) -> usize {
+    let mut i = from.min(chars.len());
+    while i < chars.len() && want(chars[i]) {
+        i += 1;
+    }
+    i
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(line: &str) -> Vec<(&'static str, String)> {
+        highlight_line(line)
+            .into_iter()
+            .filter(|(class, _)| !class.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_statement() {
+        let expected: Vec<(&str, String)> = vec![
+            ("keyword", "if"),
+            ("delimiter.bracket", "("),
+            ("identifier", "x"),
+            ("delimiter", "=="),
+            ("type.namespace", "#-1"),
+            ("delimiter", "||"),
+            ("type.namespace", "$nothing"),
+            ("delimiter", "."),
+            ("identifier", "name"),
+            ("delimiter", "!="),
+            ("string", "\"a \\\"b\\\"\""),
+            ("delimiter.bracket", ")"),
+        ]
+        .into_iter()
+        .map(|(c, t)| (c, t.to_string()))
+        .collect();
+        assert_eq!(
+            classes("if (x == #-1 || $nothing.name != \"a \\\"b\\\"\")"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_highlight_numbers_and_errors() {
+        let expected: Vec<(&str, String)> = vec![
+            ("keyword", "return"),
+            ("delimiter.bracket", "{"),
+            ("number", "1"),
+            ("delimiter", ".."),
+            ("number", "2"),
+            ("delimiter", ","),
+            ("number.float", "1.5e3"),
+            ("delimiter", ","),
+            ("constant", "E_PERM"),
+            ("delimiter.bracket", "}"),
+            ("delimiter", ";"),
+        ]
+        .into_iter()
+        .map(|(c, t)| (c, t.to_string()))
+        .collect();
+        assert_eq!(classes("return {1..2, 1.5e3, E_PERM};"), expected);
+    }
+
+    #[test]
+    fn test_highlight_keeps_text() {
+        let code = vec!["  x = \"unterminated".to_string(), "".to_string()];
+        let lines = highlight(&code);
+        assert_eq!(lines.len(), 2);
+        let text: String = lines[0].iter().map(|(_, t)| t.as_str()).collect();
+        assert_eq!(text, code[0]);
+        assert_eq!(lines[0].last().unwrap().0, "string.invalid");
+        assert!(lines[1].is_empty());
+    }
+}
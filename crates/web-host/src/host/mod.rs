@@ -12,14 +12,19 @@
 //
 
 mod auth;
+mod highlight;
 mod props;
+pub mod protocol;
 mod verbs;
 pub mod web_host;
 mod ws_connection;
 
 pub use auth::connect_auth_handler;
 pub use auth::create_auth_handler;
-use moor_values::{v_err, v_float, v_int, v_list, v_map, v_none, v_objid, v_str, Var, Variant};
+pub use auth::refresh_auth_handler;
+use moor_values::{
+    v_bool, v_err, v_float, v_int, v_list, v_map, v_none, v_objid, v_str, Var, Variant,
+};
 pub use props::properties_handler;
 pub use props::property_retrieval_handler;
 use serde::Serialize;
@@ -30,8 +35,8 @@ pub use verbs::verb_retrieval_handler;
 pub use verbs::verbs_handler;
 pub use web_host::WebHost;
 pub use web_host::{
-    eval_handler, resolve_objref_handler, welcome_message_handler, ws_connect_attach_handler,
-    ws_create_attach_handler,
+    eval_handler, object_generation_handler, resolve_objref_handler, welcome_message_handler,
+    ws_connect_attach_handler, ws_create_attach_handler,
 };
 
 #[derive(serde_derive::Serialize, Deserialize)]
@@ -101,8 +106,6 @@ pub fn var_as_json(v: &Var) -> serde_json::Value {
     }
 }
 
-// Not used yet
-#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum JsonParseError {
     #[error("Unknown type")]
@@ -113,10 +116,10 @@ pub enum JsonParseError {
     InvalidRepresentation,
 }
 
-#[allow(dead_code)]
 pub fn json_as_var(j: &serde_json::Value) -> Result<Var, JsonParseError> {
     match j {
         serde_json::Value::Null => Ok(v_none()),
+        serde_json::Value::Bool(b) => Ok(v_bool(*b)),
         serde_json::Value::String(s) => Ok(v_str(s)),
         serde_json::Value::Number(n) => Ok(if n.is_i64() {
             v_int(n.as_i64().unwrap())
@@ -183,7 +186,6 @@ pub fn json_as_var(j: &serde_json::Value) -> Result<Var, JsonParseError> {
             }
             Ok(v_list(&v))
         }
-        _ => Err(JsonParseError::UnknownType),
     }
 }
 
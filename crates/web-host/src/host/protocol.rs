@@ -0,0 +1,302 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The JSON messages exchanged with web clients over the websocket. Every message is an object
+//! whose `kind` says which of the schemas below it follows; see `doc/web-client-protocol.md`.
+//! Changes which would break an existing client bump `PROTOCOL_VERSION`.
+
+use crate::host::serialize_var;
+use moor_values::tasks::Presentation;
+use moor_values::Var;
+use serde_json::{Map, Value};
+use std::time::SystemTime;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Messages from the host to the client.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Always sent first, so the client knows which version of the protocol it's talking to.
+    Hello {
+        protocol_version: u32,
+        player: Value,
+    },
+    Narrative(NarrativeOutput),
+    /// The next line (or `input` message with this id) answers a `read()` in a running task.
+    InputRequest {
+        request_id: String,
+    },
+    Error(ErrorOutput),
+    /// Show `content` in the place named by `target` (e.g. "window", "right-dock"), replacing any
+    /// presentation with the same id, until an `unpresent` withdraws it.
+    Present {
+        id: String,
+        content_type: String,
+        content: String,
+        target: String,
+        attributes: Map<String, Value>,
+    },
+    Unpresent {
+        id: String,
+    },
+    /// The value a task submitted by this client returned.
+    Result {
+        #[serde(serialize_with = "serialize_var")]
+        value: Var,
+    },
+    /// The connection now belongs to `player`; requests must use `auth_token` from now on.
+    PlayerSwitched {
+        player: Value,
+        auth_token: String,
+    },
+}
+
+/// A narrative event, either output from the world (`message`) or a notice from the system itself
+/// (`system_message`).
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NarrativeOutput {
+    pub author: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub server_time: SystemTime,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ErrorOutput {
+    pub message: String,
+    pub description: Option<Vec<String>>,
+    pub server_time: SystemTime,
+}
+
+/// Messages from the client to the host.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Command {
+        line: String,
+    },
+    /// Answers the `input_request` with the same id.
+    Input {
+        request_id: String,
+        line: String,
+    },
+    /// Capabilities of the client (e.g. `"html": true`) for `connection_attributes()`. A `null`
+    /// value clears the attribute.
+    Attributes {
+        attributes: Map<String, Value>,
+    },
+    /// The player closed the presentation with this id.
+    Dismiss {
+        id: String,
+    },
+}
+
+impl ServerMessage {
+    pub fn present(presentation: Presentation) -> Self {
+        ServerMessage::Present {
+            id: presentation.id,
+            content_type: presentation.content_type,
+            content: presentation.content,
+            target: presentation.target,
+            attributes: presentation
+                .attributes
+                .into_iter()
+                .map(|(name, value)| (name, Value::String(value)))
+                .collect(),
+        }
+    }
+}
+
+impl ClientMessage {
+    /// Anything which isn't a protocol message is taken to be a command line, as sent by clients
+    /// which predate the protocol.
+    pub fn parse(text: &str) -> ClientMessage {
+        if text.trim_start().starts_with('{') {
+            if let Ok(message) = serde_json::from_str(text) {
+                return message;
+            }
+        }
+        ClientMessage::Command {
+            line: text.to_string(),
+        }
+    }
+}
+
+/// These pin down the JSON of each message, since clients outside this repository depend on it.
+#[cfg(test)]
+mod tests {
+    use crate::host::protocol::{
+        ClientMessage, ErrorOutput, NarrativeOutput, ServerMessage, PROTOCOL_VERSION,
+    };
+    use moor_values::tasks::Presentation;
+    use moor_values::{v_int, v_list, v_str};
+    use serde_json::json;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn to_json(message: &ServerMessage) -> serde_json::Value {
+        serde_json::to_value(message).unwrap()
+    }
+
+    #[test]
+    fn test_hello() {
+        let message = ServerMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            player: json!({"oid": 2}),
+        };
+        assert_eq!(
+            to_json(&message),
+            json!({"kind": "hello", "protocol_version": 1, "player": {"oid": 2}})
+        );
+    }
+
+    #[test]
+    fn test_player_switched() {
+        let message = ServerMessage::PlayerSwitched {
+            player: json!({"oid": 5}),
+            auth_token: "v4.public.xyz".to_string(),
+        };
+        assert_eq!(
+            to_json(&message),
+            json!({"kind": "player_switched", "player": {"oid": 5}, "auth_token": "v4.public.xyz"})
+        );
+    }
+
+    #[test]
+    fn test_narrative() {
+        let message = ServerMessage::Narrative(NarrativeOutput {
+            author: json!({"oid": 3}),
+            system_message: None,
+            message: Some(json!("Hello!")),
+            content_type: Some("text/plain".to_string()),
+            server_time: UNIX_EPOCH + Duration::from_secs(1),
+        });
+        assert_eq!(
+            to_json(&message),
+            json!({
+                "kind": "narrative",
+                "author": {"oid": 3},
+                "message": "Hello!",
+                "content_type": "text/plain",
+                "server_time": {"secs_since_epoch": 1, "nanos_since_epoch": 0},
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_request_and_error() {
+        let message = ServerMessage::InputRequest {
+            request_id: "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+        };
+        assert_eq!(
+            to_json(&message),
+            json!({"kind": "input_request", "request_id": "67e55044-10b1-426f-9247-bb680e5fe0c8"})
+        );
+
+        let message = ServerMessage::Error(ErrorOutput {
+            message: "I don't understand that.".to_string(),
+            description: None,
+            server_time: UNIX_EPOCH,
+        });
+        assert_eq!(
+            to_json(&message),
+            json!({
+                "kind": "error",
+                "message": "I don't understand that.",
+                "description": null,
+                "server_time": {"secs_since_epoch": 0, "nanos_since_epoch": 0},
+            })
+        );
+    }
+
+    #[test]
+    fn test_result() {
+        let message = ServerMessage::Result {
+            value: v_list(&[v_int(1), v_str("two")]),
+        };
+        assert_eq!(
+            to_json(&message),
+            json!({"kind": "result", "value": [1, "two"]})
+        );
+    }
+
+    #[test]
+    fn test_presentations() {
+        let message = ServerMessage::present(Presentation {
+            id: "map".to_string(),
+            content_type: "text/html".to_string(),
+            content: "<b>You are here</b>".to_string(),
+            target: "right-dock".to_string(),
+            attributes: vec![("title".to_string(), "Map".to_string())],
+        });
+        assert_eq!(
+            to_json(&message),
+            json!({
+                "kind": "present",
+                "id": "map",
+                "content_type": "text/html",
+                "content": "<b>You are here</b>",
+                "target": "right-dock",
+                "attributes": {"title": "Map"},
+            })
+        );
+
+        let message = ServerMessage::Unpresent {
+            id: "map".to_string(),
+        };
+        assert_eq!(to_json(&message), json!({"kind": "unpresent", "id": "map"}));
+    }
+
+    #[test]
+    fn test_client_messages() {
+        assert_eq!(
+            ClientMessage::parse(r#"{"kind": "command", "line": "look"}"#),
+            ClientMessage::Command {
+                line: "look".to_string()
+            }
+        );
+        assert_eq!(
+            ClientMessage::parse(r#"{"kind": "input", "request_id": "x", "line": "yes"}"#),
+            ClientMessage::Input {
+                request_id: "x".to_string(),
+                line: "yes".to_string()
+            }
+        );
+        let ClientMessage::Attributes { attributes } =
+            ClientMessage::parse(r#"{"kind": "attributes", "attributes": {"html": true}}"#)
+        else {
+            panic!("expected attributes");
+        };
+        assert_eq!(attributes.get("html"), Some(&json!(true)));
+        assert_eq!(
+            ClientMessage::parse(r#"{"kind": "dismiss", "id": "map"}"#),
+            ClientMessage::Dismiss {
+                id: "map".to_string()
+            }
+        );
+
+        // Plain lines, and anything else which isn't a protocol message, are commands.
+        for line in ["look", "{ not json", r#"{"kind": "dance"}"#] {
+            assert_eq!(
+                ClientMessage::parse(line),
+                ClientMessage::Command {
+                    line: line.to_string()
+                }
+            );
+        }
+    }
+}
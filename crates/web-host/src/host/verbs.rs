@@ -11,7 +11,7 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::host::{auth, web_host, WebHost};
+use crate::host::{auth, highlight, web_host, WebHost};
 use axum::body::Bytes;
 use axum::extract::{ConnectInfo, Path, State};
 use axum::http::{HeaderMap, StatusCode};
@@ -155,6 +155,7 @@ pub async fn verb_retrieval_handler(
             "owner": owner.id().0,
             "names": names.iter().map(|s| s.to_string()).collect::<Vec<String>>(),
             "code": code,
+            "highlighted": highlight::highlight(&code),
             "r": r,
             "w": w,
             "x": x,
@@ -33,6 +33,7 @@ use rpc_common::{
     ConnectType, DaemonToClientReply, HostClientToDaemonMessage, ReplyResult,
     CLIENT_BROADCAST_TOPIC,
 };
+use serde_json::json;
 use std::net::SocketAddr;
 use tmq::{request, subscribe};
 use tracing::warn;
@@ -51,6 +52,8 @@ pub struct WebHost {
     rpc_addr: String,
     pubsub_addr: String,
     pub(crate) handler_object: Obj,
+    /// The local port of the listener this host serves, so the daemon can count its connections.
+    listen_port: u16,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -64,13 +67,19 @@ pub enum WsHostError {
 }
 
 impl WebHost {
-    pub fn new(rpc_addr: String, narrative_addr: String, handler_object: Obj) -> Self {
-        let tmq_context = tmq::Context::new();
+    pub fn new(
+        zmq_context: tmq::Context,
+        rpc_addr: String,
+        narrative_addr: String,
+        handler_object: Obj,
+        listen_port: u16,
+    ) -> Self {
         Self {
-            zmq_context: tmq_context,
+            zmq_context,
             rpc_addr,
             pubsub_addr: narrative_addr,
             handler_object,
+            listen_port,
         }
     }
 }
@@ -202,7 +211,10 @@ impl WebHost {
         let mut rpc_client = RpcSendClient::new(rcp_request_sock);
 
         let client_token = match rpc_client
-            .make_client_rpc_call(client_id, ConnectionEstablish(addr.to_string()))
+            .make_client_rpc_call(
+                client_id,
+                ConnectionEstablish(addr.to_string(), self.listen_port),
+            )
             .await
         {
             Ok(ReplyResult::ClientSuccess(DaemonToClientReply::NewConnection(
@@ -249,9 +261,7 @@ pub(crate) async fn rpc_call(
         Ok(rpc_response) => match rpc_response {
             ReplyResult::ClientSuccess(r) => Ok(r),
 
-            ReplyResult::Failure(RpcMessageError::PermissionDenied) => {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+            ReplyResult::Failure(RpcMessageError::PermissionDenied) => Err(StatusCode::FORBIDDEN),
             ReplyResult::Failure(f) => {
                 error!("RPC failure in welcome message retrieval: {:?}", f);
                 Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -274,6 +284,9 @@ pub async fn welcome_message_handler(
         match host.establish_client_connection(addr).await {
             Ok((client_id, rpc_client, client_token)) => (client_id, rpc_client, client_token),
             Err(WsHostError::AuthenticationFailed) => return StatusCode::FORBIDDEN.into_response(),
+            Err(WsHostError::RpcFailure(RpcMessageError::ServerFull(msg))) => {
+                return (StatusCode::SERVICE_UNAVAILABLE, msg).into_response();
+            }
             Err(e) => {
                 error!("Unable to establish connection: {}", e);
                 return StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -410,6 +423,59 @@ pub async fn resolve_objref_handler(
     response
 }
 
+/// The generation the object was last changed at, so that clients holding a copy of it (its
+/// verbs, properties, etc.) can check whether it's stale without fetching it all again.
+pub async fn object_generation_handler(
+    State(host): State<WebHost>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    header_map: HeaderMap,
+    Path(object): Path<String>,
+) -> Response {
+    let (auth_token, client_id, client_token, mut rpc_client) =
+        match auth::auth_auth(host.clone(), addr, header_map.clone()).await {
+            Ok(connection_details) => connection_details,
+            Err(status) => return status.into_response(),
+        };
+
+    let Some(objref) = ObjectRef::parse_curie(&object) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let response = match rpc_call(
+        client_id,
+        &mut rpc_client,
+        HostClientToDaemonMessage::ObjectGeneration(
+            client_token.clone(),
+            auth_token.clone(),
+            objref,
+        ),
+    )
+    .await
+    {
+        Ok(DaemonToClientReply::ObjectGeneration(obj, generation)) => Json(json!({
+            "object": obj.id().0,
+            "generation": generation,
+        }))
+        .into_response(),
+        Ok(r) => {
+            error!("Unexpected response from RPC server: {:?}", r);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(status) => status.into_response(),
+    };
+
+    // We're done with this RPC connection, so we detach it.
+    let _ = rpc_client
+        .make_client_rpc_call(
+            client_id,
+            HostClientToDaemonMessage::Detach(client_token.clone()),
+        )
+        .await
+        .expect("Unable to send detach to RPC server");
+
+    response
+}
+
 /// Attach a websocket connection to an existing player.
 async fn attach(
     ws: WebSocketUpgrade,
@@ -11,22 +11,24 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::host::{serialize_var, var_as_json};
+use crate::host::protocol::{
+    ClientMessage, ErrorOutput, NarrativeOutput, ServerMessage, PROTOCOL_VERSION,
+};
+use crate::host::{json_as_var, var_as_json};
 use axum::extract::ws::{Message, WebSocket};
-use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
 use moor_values::tasks::{AbortLimitReason, CommandError, Event, SchedulerError, VerbProgramError};
-use moor_values::{v_obj, Obj, Var};
+use moor_values::{v_obj, Obj, Symbol};
+use rpc_async_client::output::{overflow_notice, BufferedOutput};
 use rpc_async_client::pubsub_client::broadcast_recv;
 use rpc_async_client::pubsub_client::events_recv;
 use rpc_async_client::rpc_client::RpcSendClient;
 use rpc_common::ClientsBroadcastEvent;
 use rpc_common::{
     AuthToken, ClientToken, ConnectType, DaemonToClientReply, HostClientToDaemonMessage,
-    ReplyResult, RpcMessageError,
+    OutputLimits, ReplyResult, RpcMessageError,
 };
 use rpc_common::{ClientEvent, HostType};
-use serde_json::Value;
 use std::net::SocketAddr;
 use std::time::SystemTime;
 use tmq::subscribe::Subscribe;
@@ -46,33 +48,33 @@ pub struct WebSocketConnection {
     pub(crate) handler_object: Obj,
 }
 
-/// The JSON output of a narrative event.
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct NarrativeOutput {
-    author: Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    system_message: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    message: Option<Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    content_type: Option<String>,
-    server_time: SystemTime,
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct ErrorOutput {
-    message: String,
-    description: Option<Vec<String>>,
-    server_time: SystemTime,
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
-pub struct ValueResult(#[serde(serialize_with = "serialize_var")] Var);
-
 impl WebSocketConnection {
     pub async fn handle(&mut self, connect_type: ConnectType, stream: WebSocket) {
         info!("New connection from {}, {}", self.peer_addr, self.player);
-        let (mut ws_sender, mut ws_receiver) = stream.split();
+        let (ws_sender, mut ws_receiver) = stream.split();
+        // Written out in the background, so a client which isn't reading can't hold up its
+        // connection. Buffered to the default limits until the connection's options change.
+        let author = var_as_json(&v_obj(self.player.clone()));
+        let mut ws_sender =
+            BufferedOutput::spawn(ws_sender, OutputLimits::default(), move |lost| {
+                let notice = ServerMessage::Narrative(NarrativeOutput {
+                    author: author.clone(),
+                    system_message: Some(overflow_notice(lost)),
+                    message: None,
+                    content_type: Some("text/plain".to_string()),
+                    server_time: SystemTime::now(),
+                });
+                Message::Text(serde_json::to_string(&notice).unwrap().into())
+            });
+
+        Self::emit(
+            &mut ws_sender,
+            ServerMessage::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                player: var_as_json(&v_obj(self.player.clone())),
+            },
+        )
+        .await;
 
         let connect_message = match connect_type {
             ConnectType::Connected => "*** Connected ***",
@@ -91,6 +93,28 @@ impl WebSocketConnection {
         )
         .await;
 
+        // Bring the client up to date with whatever's already being presented to the player.
+        match self
+            .rpc_client
+            .make_client_rpc_call(
+                self.client_id,
+                HostClientToDaemonMessage::RequestCurrentPresentations(
+                    self.client_token.clone(),
+                    self.auth_token.clone(),
+                ),
+            )
+            .await
+        {
+            Ok(ReplyResult::ClientSuccess(DaemonToClientReply::CurrentPresentations(
+                presentations,
+            ))) => {
+                for presentation in presentations {
+                    Self::emit(&mut ws_sender, ServerMessage::present(presentation)).await;
+                }
+            }
+            other => warn!(?other, "Unable to retrieve current presentations"),
+        }
+
         debug!(client_id = ?self.client_id, "Entering command dispatch loop");
 
         let mut expecting_input = None;
@@ -114,49 +138,74 @@ impl WebSocketConnection {
                         }
                     }
                 }
-                Ok(event) = events_recv(self.client_id, &mut self.narrative_sub) => {
-                    trace!(?event, "narrative_event");
-                    match event {
-                        ClientEvent::SystemMessage(author, msg) => {
-                            Self::emit_narrative(&mut ws_sender, NarrativeOutput {
-                                author: var_as_json(&v_obj(author)),
-                                system_message: Some(msg),
-                                message: None,
-                                content_type: Some("text/plain".to_string()),
-                                server_time: SystemTime::now(),
-                            }).await;
-                        }
-                        ClientEvent::Narrative(_author, event) => {
-                            let msg = event.event();
-                            let Event::Notify(msg, content_type) = msg;
-                            let content_type = content_type.map(|s| s.to_string());
-                            Self::emit_narrative(&mut ws_sender, NarrativeOutput {
-                                author: var_as_json(event.author()),
-                                system_message: None,
-                                message: Some(var_as_json(&msg)),
-                                content_type,
-                                server_time: event.timestamp(),
-                            }).await;
-                        }
-                        ClientEvent::RequestInput(request_id) => {
-                            expecting_input = Some(request_id);
-                        }
-                        ClientEvent::Disconnect() => {
-                            Self::emit_narrative(&mut ws_sender, NarrativeOutput {
-                                author: var_as_json(&v_obj(self.player.clone())),
-                                system_message: Some("** Disconnected **".to_string()),
-                                message: None,
-                                content_type: Some("text/plain".to_string()),
-                                server_time: SystemTime::now(),
-                            }).await;
-                            ws_sender.close().await.expect("Unable to close connection");
-                            return ;
-                        }
-                        ClientEvent::TaskError(_ti, te) => {
-                            self.handle_task_error(&mut ws_sender, te).await.expect("Unable to handle task error");
-                        }
-                        ClientEvent::TaskSuccess(_ti, s) => {
-                            Self::emit_value(&mut ws_sender, ValueResult(s)).await;
+                Ok(events) = events_recv(self.client_id, &mut self.narrative_sub) => {
+                    trace!(?events, "narrative_event");
+                    for event in events {
+                        match event {
+                            ClientEvent::SystemMessage(author, msg) => {
+                                Self::emit_narrative(&mut ws_sender, NarrativeOutput {
+                                    author: var_as_json(&v_obj(author)),
+                                    system_message: Some(msg),
+                                    message: None,
+                                    content_type: Some("text/plain".to_string()),
+                                    server_time: SystemTime::now(),
+                                }).await;
+                            }
+                            ClientEvent::Narrative(_author, event) => {
+                                match event.event() {
+                                    Event::Notify(msg, content_type) => {
+                                        let content_type = content_type.map(|s| s.to_string());
+                                        Self::emit_narrative(&mut ws_sender, NarrativeOutput {
+                                            author: var_as_json(event.author()),
+                                            system_message: None,
+                                            message: Some(var_as_json(&msg)),
+                                            content_type,
+                                            server_time: event.timestamp(),
+                                        }).await;
+                                    }
+                                    Event::Present(presentation) => {
+                                        Self::emit(&mut ws_sender, ServerMessage::present(presentation)).await;
+                                    }
+                                    Event::Unpresent(id) => {
+                                        Self::emit(&mut ws_sender, ServerMessage::Unpresent { id }).await;
+                                    }
+                                }
+                            }
+                            ClientEvent::RequestInput(request_id) => {
+                                expecting_input = Some(request_id);
+                                Self::emit(&mut ws_sender, ServerMessage::InputRequest {
+                                    request_id: Uuid::from_u128(request_id).to_string(),
+                                }).await;
+                            }
+                            ClientEvent::Disconnect() => {
+                                Self::emit_narrative(&mut ws_sender, NarrativeOutput {
+                                    author: var_as_json(&v_obj(self.player.clone())),
+                                    system_message: Some("** Disconnected **".to_string()),
+                                    message: None,
+                                    content_type: Some("text/plain".to_string()),
+                                    server_time: SystemTime::now(),
+                                }).await;
+                                ws_sender.close().await.expect("Unable to close connection");
+                                return ;
+                            }
+                            ClientEvent::TaskError(_ti, te) => {
+                                self.handle_task_error(&mut ws_sender, te).await.expect("Unable to handle task error");
+                            }
+                            ClientEvent::TaskSuccess(_ti, s) => {
+                                Self::emit(&mut ws_sender, ServerMessage::Result { value: s }).await;
+                            }
+                            ClientEvent::OutputLimits(limits) => {
+                                ws_sender.set_limits(limits);
+                            }
+                            ClientEvent::PlayerSwitched { new_player, new_auth_token } => {
+                                info!(player = ?new_player, "Switched player");
+                                self.player = new_player;
+                                self.auth_token = new_auth_token;
+                                Self::emit(&mut ws_sender, ServerMessage::PlayerSwitched {
+                                    player: var_as_json(&v_obj(self.player.clone())),
+                                    auth_token: self.auth_token.0.clone(),
+                                }).await;
+                            }
                         }
                     }
                 }
@@ -168,45 +217,105 @@ impl WebSocketConnection {
         &mut self,
         line: Message,
         expecting_input: &mut Option<u128>,
-        ws_sender: &mut SplitSink<WebSocket, Message>,
+        ws_sender: &mut BufferedOutput<Message>,
     ) {
         let line = line.into_text().unwrap();
-        let cmd = line.trim().to_string();
 
-        let response = match expecting_input.take() {
-            Some(input_request_id) => self
-                .rpc_client
-                .make_client_rpc_call(
-                    self.client_id,
-                    HostClientToDaemonMessage::RequestedInput(
+        let request = match ClientMessage::parse(&line) {
+            // Clients which don't answer input requests explicitly answer with their next line.
+            ClientMessage::Command { line } => {
+                let cmd = line.trim().to_string();
+                match expecting_input.take() {
+                    Some(input_request_id) => HostClientToDaemonMessage::RequestedInput(
                         self.client_token.clone(),
                         self.auth_token.clone(),
                         input_request_id,
                         cmd,
                     ),
-                )
-                .await
-                .expect("Unable to send input to RPC server"),
-            None => self
-                .rpc_client
-                .make_client_rpc_call(
-                    self.client_id,
-                    HostClientToDaemonMessage::Command(
+                    None => HostClientToDaemonMessage::Command(
                         self.client_token.clone(),
                         self.auth_token.clone(),
                         self.handler_object.clone(),
                         cmd,
                     ),
+                }
+            }
+            ClientMessage::Input { request_id, line } => {
+                let Ok(request_id) = Uuid::parse_str(&request_id) else {
+                    warn!(?request_id, "Invalid input request id from client");
+                    return;
+                };
+                let request_id = request_id.as_u128();
+                if *expecting_input == Some(request_id) {
+                    *expecting_input = None;
+                }
+                HostClientToDaemonMessage::RequestedInput(
+                    self.client_token.clone(),
+                    self.auth_token.clone(),
+                    request_id,
+                    line,
                 )
-                .await
-                .expect("Unable to send command to RPC server"),
+            }
+            ClientMessage::Dismiss { id } => HostClientToDaemonMessage::DismissPresentation(
+                self.client_token.clone(),
+                self.auth_token.clone(),
+                id,
+            ),
+            ClientMessage::Attributes { attributes } => {
+                for (name, value) in attributes {
+                    let value = match value {
+                        serde_json::Value::Null => None,
+                        value => match json_as_var(&value) {
+                            Ok(value) => Some(value),
+                            Err(e) => {
+                                warn!(?name, ?e, "Invalid connection attribute from client");
+                                continue;
+                            }
+                        },
+                    };
+                    let response = self
+                        .rpc_client
+                        .make_client_rpc_call(
+                            self.client_id,
+                            HostClientToDaemonMessage::SetClientAttribute(
+                                self.client_token.clone(),
+                                Symbol::mk(&name),
+                                value,
+                            ),
+                        )
+                        .await
+                        .expect("Unable to send connection attribute to RPC server");
+                    if let ReplyResult::Failure(e) = response {
+                        warn!(?name, ?e, "Could not set connection attribute");
+                    }
+                }
+                return;
+            }
         };
 
+        let response = self
+            .rpc_client
+            .make_client_rpc_call(self.client_id, request)
+            .await
+            .expect("Unable to send command to RPC server");
+
         match response {
             ReplyResult::ClientSuccess(DaemonToClientReply::TaskSubmitted(_))
-            | ReplyResult::ClientSuccess(DaemonToClientReply::InputThanks) => {
+            | ReplyResult::ClientSuccess(DaemonToClientReply::InputThanks)
+            | ReplyResult::ClientSuccess(DaemonToClientReply::PresentationDismissed) => {
                 // Nothing to do
             }
+            ReplyResult::ClientSuccess(DaemonToClientReply::InputMore(request_id)) => {
+                // The task is reading lines up to a terminator; the next one goes to it too.
+                *expecting_input = Some(request_id);
+                Self::emit(
+                    ws_sender,
+                    ServerMessage::InputRequest {
+                        request_id: Uuid::from_u128(request_id).to_string(),
+                    },
+                )
+                .await;
+            }
             ReplyResult::Failure(RpcMessageError::TaskError(e)) => {
                 self.handle_task_error(ws_sender, e)
                     .await
@@ -226,7 +335,7 @@ impl WebSocketConnection {
 
     async fn handle_task_error(
         &mut self,
-        ws_sender: &mut SplitSink<WebSocket, Message>,
+        ws_sender: &mut BufferedOutput<Message>,
         task_error: SchedulerError,
     ) -> Result<(), eyre::Error> {
         match task_error {
@@ -351,6 +460,9 @@ impl WebSocketConnection {
                 )
                 .await
             }
+            SchedulerError::ServerTooBusy => {
+                // The player has already been sent the core's "server too busy" message.
+            }
             _ => {
                 warn!(?task_error, "Unhandled unexpected task error");
             }
@@ -358,27 +470,15 @@ impl WebSocketConnection {
         Ok(())
     }
 
-    async fn emit_narrative(ws_sender: &mut SplitSink<WebSocket, Message>, msg: NarrativeOutput) {
-        // Serialize to JSON.
-        let msg = serde_json::to_string(&msg).unwrap();
-        let msg = Message::Text(msg.into());
-        ws_sender
-            .send(msg)
-            .await
-            .expect("Unable to send message to client");
+    async fn emit_narrative(ws_sender: &mut BufferedOutput<Message>, msg: NarrativeOutput) {
+        Self::emit(ws_sender, ServerMessage::Narrative(msg)).await
     }
 
-    async fn emit_error(ws_sender: &mut SplitSink<WebSocket, Message>, msg: ErrorOutput) {
-        // Serialize to JSON.
-        let msg = serde_json::to_string(&msg).unwrap();
-        let msg = Message::Text(msg.into());
-        ws_sender
-            .send(msg)
-            .await
-            .expect("Unable to send message to client");
+    async fn emit_error(ws_sender: &mut BufferedOutput<Message>, msg: ErrorOutput) {
+        Self::emit(ws_sender, ServerMessage::Error(msg)).await
     }
 
-    async fn emit_value(ws_sender: &mut SplitSink<WebSocket, Message>, msg: ValueResult) {
+    async fn emit(ws_sender: &mut BufferedOutput<Message>, msg: ServerMessage) {
         // Serialize to JSON.
         let msg = serde_json::to_string(&msg).unwrap();
         let msg = Message::Text(msg.into());
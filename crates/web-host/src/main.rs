@@ -11,211 +11,13 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-mod client;
-mod host;
-
-use crate::client::{editor_handler, js_handler, root_handler};
-use crate::host::WebHost;
-use std::collections::HashMap;
-
-use axum::routing::{get, post};
-use axum::Router;
 use clap::Parser;
-use clap_derive::Parser;
-
-use moor_values::{Obj, SYSTEM_OBJECT};
-use rpc_async_client::{
-    make_host_token, proces_hosts_events, start_host_session, ListenersClient, ListenersMessage,
-};
-use rpc_common::client_args::RpcClientArgs;
-use rpc_common::{load_keypair, HostType};
-use std::net::SocketAddr;
+use moor_web_host::{run_web_host, Args};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use tokio::net::TcpListener;
 use tokio::select;
 use tokio::signal::unix::{signal, SignalKind};
-use tracing::{info, warn};
-
-#[derive(Parser, Debug)]
-struct Args {
-    #[command(flatten)]
-    client_args: RpcClientArgs,
-
-    #[arg(
-        long,
-        value_name = "listen-address",
-        help = "HTTP listen address",
-        default_value = "0.0.0.0:8080"
-    )]
-    listen_address: String,
-}
-
-struct Listeners {
-    listeners: HashMap<SocketAddr, Listener>,
-    zmq_ctx: tmq::Context,
-    rpc_address: String,
-    events_address: String,
-    kill_switch: Arc<AtomicBool>,
-}
-
-impl Listeners {
-    pub fn new(
-        zmq_ctx: tmq::Context,
-        rpc_address: String,
-        events_address: String,
-        kill_switch: Arc<AtomicBool>,
-    ) -> (
-        Self,
-        tokio::sync::mpsc::Receiver<ListenersMessage>,
-        ListenersClient,
-    ) {
-        let (tx, rx) = tokio::sync::mpsc::channel(100);
-        let listeners = Self {
-            listeners: HashMap::new(),
-            zmq_ctx,
-            rpc_address,
-            events_address,
-            kill_switch,
-        };
-        let listeners_client = ListenersClient::new(tx);
-        (listeners, rx, listeners_client)
-    }
-
-    pub async fn run(
-        &mut self,
-        mut listeners_channel: tokio::sync::mpsc::Receiver<ListenersMessage>,
-    ) {
-        self.zmq_ctx
-            .set_io_threads(8)
-            .expect("Unable to set ZMQ IO threads");
-
-        loop {
-            if self.kill_switch.load(std::sync::atomic::Ordering::Relaxed) {
-                info!("Host kill switch activated, stopping...");
-                return;
-            }
-
-            match listeners_channel.recv().await {
-                Some(ListenersMessage::AddListener(handler, addr)) => {
-                    let ws_host = WebHost::new(
-                        self.rpc_address.clone(),
-                        self.events_address.clone(),
-                        handler.clone(),
-                    );
-                    let main_router = match mk_routes(ws_host) {
-                        Ok(mr) => mr,
-                        Err(e) => {
-                            warn!(?e, "Unable to create main router");
-                            return;
-                        }
-                    };
-
-                    let listener = TcpListener::bind(addr)
-                        .await
-                        .expect("Unable to bind listener");
-                    let (terminate_send, terminate_receive) = tokio::sync::watch::channel(false);
-                    self.listeners
-                        .insert(addr, Listener::new(terminate_send, handler));
-
-                    // One task per listener.
-                    tokio::spawn(async move {
-                        let mut term_receive = terminate_receive.clone();
-                        select! {
-                            _ = term_receive.changed() => {
-                                info!("Listener terminated, stopping...");
-                            }
-                            _ = Listener::serve(listener, main_router) => {
-                                info!("Listener exited, restarting...");
-                            }
-                        }
-                    });
-                }
-                Some(ListenersMessage::RemoveListener(addr)) => {
-                    let listener = self.listeners.remove(&addr);
-                    info!(?addr, "Removing listener");
-                    if let Some(listener) = listener {
-                        listener
-                            .terminate
-                            .send(true)
-                            .expect("Unable to send terminate message");
-                    }
-                }
-                Some(ListenersMessage::GetListeners(tx)) => {
-                    let listeners = self
-                        .listeners
-                        .iter()
-                        .map(|(addr, listener)| (listener.handler_object.clone(), *addr))
-                        .collect();
-                    tx.send(listeners).expect("Unable to send listeners list");
-                }
-                None => {
-                    warn!("Listeners channel closed, stopping...");
-                    return;
-                }
-            }
-        }
-    }
-}
-pub struct Listener {
-    pub(crate) handler_object: Obj,
-    pub(crate) terminate: tokio::sync::watch::Sender<bool>,
-}
-
-impl Listener {
-    pub fn new(terminate: tokio::sync::watch::Sender<bool>, handler_object: Obj) -> Self {
-        Self {
-            handler_object,
-            terminate,
-        }
-    }
-
-    pub async fn serve(listener: TcpListener, main_router: Router) -> eyre::Result<()> {
-        let addr = listener.local_addr()?;
-        info!("Listening on {:?}", addr);
-        axum::serve(
-            listener,
-            main_router.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await?;
-        info!("Done listening on {:?}", addr);
-        Ok(())
-    }
-}
-fn mk_routes(web_host: WebHost) -> eyre::Result<Router> {
-    let webhost_router = Router::new()
-        .route(
-            "/ws/attach/connect/:token",
-            get(host::ws_connect_attach_handler),
-        )
-        .route(
-            "/ws/attach/create/:token",
-            get(host::ws_create_attach_handler),
-        )
-        .route("/", get(root_handler))
-        .route("/moor.js", get(js_handler))
-        .route("/rpc.js", get(client::rpc_handler))
-        .route("/editor.js", get(editor_handler))
-        .route("/moor.css", get(client::css_handler))
-        .route("/var.js", get(client::var_handler))
-        .route("/auth/connect", post(host::connect_auth_handler))
-        .route("/auth/create", post(host::create_auth_handler))
-        .route("/welcome", get(host::welcome_message_handler))
-        .route("/eval", post(host::eval_handler))
-        .route("/verbs", get(host::verbs_handler))
-        .route("/verbs/:object/:name", get(host::verb_retrieval_handler))
-        .route("/verbs/:object/:name", post(host::verb_program_handler))
-        .route("/properties", get(host::properties_handler))
-        // ?oid=1234 or ?sysobj=foo.bar.baz or ?match=foo
-        .route("/objects/:object", get(host::resolve_objref_handler))
-        .route(
-            "/properties/:object/:name",
-            get(host::property_retrieval_handler),
-        )
-        .with_state(web_host);
-
-    Ok(Router::new().nest("/", webhost_router))
-}
+use tracing::info;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), eyre::Error> {
@@ -240,55 +42,10 @@ async fn main() -> Result<(), eyre::Error> {
 
     let kill_switch = Arc::new(AtomicBool::new(false));
 
-    let (private_key, _public_key) =
-        load_keypair(&args.client_args.public_key, &args.client_args.private_key)
-            .expect("Unable to load keypair from public and private key files");
-    let host_token = make_host_token(&private_key, HostType::TCP);
-
-    let zmq_ctx = tmq::Context::new();
-
-    let (mut listeners_server, listeners_channel, listeners) = Listeners::new(
-        zmq_ctx.clone(),
-        args.client_args.rpc_address.clone(),
-        args.client_args.events_address.clone(),
-        kill_switch.clone(),
-    );
-    let listeners_thread = tokio::spawn(async move {
-        listeners_server.run(listeners_channel).await;
-    });
-
-    let rpc_client = start_host_session(
-        &host_token,
-        zmq_ctx.clone(),
-        args.client_args.rpc_address.clone(),
-        kill_switch.clone(),
-        listeners.clone(),
-    )
-    .await
-    .expect("Unable to establish initial host session");
-
-    listeners
-        .add_listener(&SYSTEM_OBJECT, args.listen_address.parse().unwrap())
-        .await
-        .expect("Unable to start default listener");
-
-    let host_listen_loop = proces_hosts_events(
-        rpc_client,
-        host_token,
-        zmq_ctx.clone(),
-        args.client_args.events_address.clone(),
-        args.listen_address.clone(),
-        kill_switch.clone(),
-        listeners.clone(),
-        HostType::TCP,
-    );
     select! {
-        _ = host_listen_loop => {
-            info!("Host events loop exited.");
+        result = run_web_host(args, kill_switch.clone()) => {
+            result?;
         },
-        _ = listeners_thread => {
-            info!("Listener set exited.");
-        }
         _ = hup_signal.recv() => {
             info!("HUP received, stopping...");
             kill_switch.store(true, std::sync::atomic::Ordering::SeqCst);